@@ -0,0 +1,77 @@
+//! Local Cedar evaluation of the bundled policy set, for development and
+//! tests. Production swaps in Amazon Verified Permissions behind the same
+//! `PolicyEngine` trait.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use cedar_policy::{
+    Authorizer, Context, Decision, Entities, Entity, EntityId, EntityTypeName, EntityUid, PolicySet,
+    Request,
+};
+
+use super::PolicyEngine;
+
+/// Policies shipped with the backend.
+const BUNDLED_POLICIES: &[&str] = &[
+    include_str!("../../policies/robot_commands.cedar"),
+    include_str!("../../policies/robots.cedar"),
+    include_str!("../../policies/tenants.cedar"),
+];
+
+pub struct LocalCedarEngine {
+    policies: PolicySet,
+    authorizer: Authorizer,
+}
+
+impl LocalCedarEngine {
+    pub fn new(policies: &str) -> anyhow::Result<Self> {
+        let policies = PolicySet::from_str(policies).map_err(|e| anyhow!("invalid Cedar policies: {}", e))?;
+        Ok(Self {
+            policies,
+            authorizer: Authorizer::new(),
+        })
+    }
+
+    /// The policies in `backend/policies`.
+    pub fn bundled() -> anyhow::Result<Self> {
+        Self::new(&BUNDLED_POLICIES.join("\n"))
+    }
+}
+
+#[async_trait]
+impl PolicyEngine for LocalCedarEngine {
+    async fn is_authorized(
+        &self,
+        user_id: &str,
+        roles: &[String],
+        action: &str,
+        resource: (&str, &str),
+    ) -> anyhow::Result<bool> {
+        let principal = uid("User", user_id)?;
+        let parents = roles
+            .iter()
+            .map(|role| uid("Role", role))
+            .collect::<anyhow::Result<HashSet<_>>>()?;
+        let mut entities = vec![Entity::new(principal.clone(), HashMap::new(), parents.clone())];
+        entities.extend(parents.into_iter().map(Entity::with_uid));
+        let entities = Entities::from_entities(entities).map_err(|e| anyhow!("{}", e))?;
+
+        let request = Request::new(
+            Some(principal),
+            Some(uid("Action", action)?),
+            Some(uid(resource.0, resource.1)?),
+            Context::empty(),
+        );
+        let response = self.authorizer.is_authorized(&request, &self.policies, &entities);
+        Ok(response.decision() == Decision::Allow)
+    }
+}
+
+fn uid(entity_type: &str, id: &str) -> anyhow::Result<EntityUid> {
+    let entity_type = EntityTypeName::from_str(entity_type).map_err(|e| anyhow!("{}", e))?;
+    let id = EntityId::from_str(id).map_err(|e| anyhow!("{}", e))?;
+    Ok(EntityUid::from_type_name_and_id(entity_type, id))
+}
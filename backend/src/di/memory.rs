@@ -0,0 +1,110 @@
+//! In-memory `StorageProvider` and `BlobStorage` for local development and tests.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+
+use super::{BlobStorage, StorageProvider, TTL_ATTRIBUTE};
+use crate::services::unix_now;
+
+/// Items keyed by (table, tenant_id, pk). Not persisted across restarts.
+/// Items past their `ttl` are hidden, and deleted on the next write.
+#[derive(Default)]
+pub struct InMemoryStorage {
+    items: RwLock<HashMap<(String, String, String), serde_json::Value>>,
+}
+
+impl InMemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl StorageProvider for InMemoryStorage {
+    #[tracing::instrument(skip(self))]
+    async fn get_item(&self, table: &str, key: &str, tenant_id: &str) -> anyhow::Result<Option<serde_json::Value>> {
+        let items = self.items.read().map_err(|_| anyhow::anyhow!("storage lock poisoned"))?;
+        Ok(items
+            .get(&(table.to_string(), tenant_id.to_string(), key.to_string()))
+            .filter(|item| !expired(item, unix_now()))
+            .cloned())
+    }
+
+    #[tracing::instrument(skip(self, item))]
+    async fn put_item(&self, table: &str, item: serde_json::Value) -> anyhow::Result<()> {
+        let tenant_id = item["tenant_id"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("item is missing tenant_id"))?
+            .to_string();
+        let key = item["pk"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("item is missing pk"))?
+            .to_string();
+
+        let mut items = self.items.write().map_err(|_| anyhow::anyhow!("storage lock poisoned"))?;
+        let now = unix_now();
+        items.retain(|_, item| !expired(item, now));
+        items.insert((table.to_string(), tenant_id, key), item);
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn query_by_tenant(&self, table: &str, tenant_id: &str) -> anyhow::Result<Vec<serde_json::Value>> {
+        let items = self.items.read().map_err(|_| anyhow::anyhow!("storage lock poisoned"))?;
+        Ok(items
+            .iter()
+            .filter(|((t, tenant, _), item)| {
+                t == table && tenant == tenant_id && !expired(item, unix_now())
+            })
+            .map(|(_, item)| item.clone())
+            .collect())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn delete_item(&self, table: &str, key: &str, tenant_id: &str) -> anyhow::Result<()> {
+        let mut items = self.items.write().map_err(|_| anyhow::anyhow!("storage lock poisoned"))?;
+        items.remove(&(table.to_string(), tenant_id.to_string(), key.to_string()));
+        Ok(())
+    }
+}
+
+fn expired(item: &serde_json::Value, now: u64) -> bool {
+    item[TTL_ATTRIBUTE].as_u64().is_some_and(|ttl| ttl <= now)
+}
+
+/// Blobs keyed by their full key. Not persisted across restarts.
+#[derive(Default)]
+pub struct InMemoryBlobStorage {
+    blobs: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl InMemoryBlobStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl BlobStorage for InMemoryBlobStorage {
+    #[tracing::instrument(skip(self, data), fields(len = data.len()))]
+    async fn put_blob(&self, key: &str, data: Vec<u8>) -> anyhow::Result<()> {
+        let mut blobs = self.blobs.write().map_err(|_| anyhow::anyhow!("blob lock poisoned"))?;
+        blobs.insert(key.to_string(), data);
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_blob(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let blobs = self.blobs.read().map_err(|_| anyhow::anyhow!("blob lock poisoned"))?;
+        Ok(blobs.get(key).cloned())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn delete_blob(&self, key: &str) -> anyhow::Result<()> {
+        let mut blobs = self.blobs.write().map_err(|_| anyhow::anyhow!("blob lock poisoned"))?;
+        blobs.remove(key);
+        Ok(())
+    }
+}
@@ -2,16 +2,66 @@
 
 use async_trait::async_trait;
 
+use crate::models::notification::Notification;
+
+pub mod cedar;
+pub mod memory;
+pub mod notifier;
+
+pub use cedar::LocalCedarEngine;
+pub use memory::{InMemoryBlobStorage, InMemoryStorage};
+
+/// Item attribute holding the Unix time (s) after which the storage may
+/// delete the item, like a DynamoDB TTL attribute. Expired items can still
+/// be returned until they are deleted, so readers check it too.
+pub const TTL_ATTRIBUTE: &str = "ttl";
+
 #[async_trait]
 pub trait StorageProvider: Send + Sync {
     async fn get_item(&self, table: &str, key: &str, tenant_id: &str) -> anyhow::Result<Option<serde_json::Value>>;
     async fn put_item(&self, table: &str, item: serde_json::Value) -> anyhow::Result<()>;
     async fn query_by_tenant(&self, table: &str, tenant_id: &str) -> anyhow::Result<Vec<serde_json::Value>>;
+    /// Remove an item; removing a missing one is not an error.
+    async fn delete_item(&self, table: &str, key: &str, tenant_id: &str) -> anyhow::Result<()>;
+}
+
+/// Large binary objects (dumps, recordings) kept outside the table.
+#[async_trait]
+pub trait BlobStorage: Send + Sync {
+    async fn put_blob(&self, key: &str, data: Vec<u8>) -> anyhow::Result<()>;
+    async fn get_blob(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+    /// Remove a blob; removing a missing one is not an error.
+    async fn delete_blob(&self, key: &str) -> anyhow::Result<()>;
 }
 
+/// Cedar authorization decisions.
+#[async_trait]
+pub trait PolicyEngine: Send + Sync {
+    /// Whether the user, as a member of `roles`, may take `action` (an
+    /// `Action::` id) on `resource` (entity type and id).
+    async fn is_authorized(
+        &self,
+        user_id: &str,
+        roles: &[String],
+        action: &str,
+        resource: (&str, &str),
+    ) -> anyhow::Result<bool>;
+}
+
+/// A hosted model. Implementations wrap `invoke` in a span
+/// (`#[tracing::instrument(skip_all)]`) so model latency shows in traces.
 #[async_trait]
 pub trait AiProvider: Send + Sync {
     async fn invoke(&self, prompt: &str) -> anyhow::Result<String>;
 }
 
-// TODO: Implement DynamoDbProvider, BedrockProvider
+/// A channel that delivers notifications to humans.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Short channel description for logs and delivery reports.
+    fn name(&self) -> String;
+    async fn send(&self, notification: &Notification) -> anyhow::Result<()>;
+}
+
+// TODO: Implement DynamoDbProvider, S3BlobStorage, BedrockProvider, VerifiedPermissionsEngine
+// (in-memory / local stand-ins locally)
@@ -0,0 +1,149 @@
+//! Notifier implementations — SNS, SMTP email, and Slack webhooks.
+
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::Notifier;
+use crate::models::notification::{Notification, Severity};
+
+/// SNS caps subjects at 100 characters.
+const SNS_MAX_SUBJECT: usize = 100;
+
+fn subject(n: &Notification) -> String {
+    let severity = match n.severity {
+        Severity::Info => "INFO",
+        Severity::Warning => "WARNING",
+        Severity::Critical => "CRITICAL",
+    };
+    match &n.robot_id {
+        Some(robot) => format!("[{}] {} ({})", severity, n.title, robot),
+        None => format!("[{}] {}", severity, n.title),
+    }
+}
+
+/// Publishes to an SNS topic.
+pub struct SnsNotifier {
+    client: aws_sdk_sns::Client,
+    topic_arn: String,
+}
+
+impl SnsNotifier {
+    pub fn new(client: aws_sdk_sns::Client, topic_arn: String) -> Self {
+        Self { client, topic_arn }
+    }
+}
+
+#[async_trait]
+impl Notifier for SnsNotifier {
+    fn name(&self) -> String {
+        format!("sns:{}", self.topic_arn)
+    }
+
+    #[tracing::instrument(skip_all, fields(channel = %self.name()))]
+    async fn send(&self, notification: &Notification) -> anyhow::Result<()> {
+        let subject: String = subject(notification).chars().take(SNS_MAX_SUBJECT).collect();
+        self.client
+            .publish()
+            .topic_arn(&self.topic_arn)
+            .subject(subject)
+            .message(&notification.body)
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+/// Platform SMTP relay settings, read from the environment.
+#[derive(Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub username: String,
+    pub password: String,
+    pub from: String,
+}
+
+impl SmtpSettings {
+    /// `SMTP_HOST`, `SMTP_USERNAME`, `SMTP_PASSWORD`, `SMTP_FROM`; `None` if unset.
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            host: std::env::var("SMTP_HOST").ok()?,
+            username: std::env::var("SMTP_USERNAME").ok()?,
+            password: std::env::var("SMTP_PASSWORD").ok()?,
+            from: std::env::var("SMTP_FROM").ok()?,
+        })
+    }
+}
+
+/// Sends email through an SMTP relay.
+pub struct SmtpNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl SmtpNotifier {
+    pub fn new(settings: &SmtpSettings, to: &[String]) -> anyhow::Result<Self> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?
+            .credentials(Credentials::new(settings.username.clone(), settings.password.clone()))
+            .build();
+        let to = to.iter().map(|addr| addr.parse()).collect::<Result<_, _>>()?;
+        Ok(Self {
+            transport,
+            from: settings.from.parse()?,
+            to,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for SmtpNotifier {
+    fn name(&self) -> String {
+        format!("email:{}", self.to.len())
+    }
+
+    #[tracing::instrument(skip_all, fields(channel = %self.name()))]
+    async fn send(&self, notification: &Notification) -> anyhow::Result<()> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(subject(notification));
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        let message = builder.body(notification.body.clone())?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Posts to a Slack incoming webhook.
+pub struct SlackNotifier {
+    http: reqwest::Client,
+    webhook_url: String,
+}
+
+impl SlackNotifier {
+    pub fn new(http: reqwest::Client, webhook_url: String) -> Self {
+        Self { http, webhook_url }
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> String {
+        "slack".to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(channel = %self.name()))]
+    async fn send(&self, notification: &Notification) -> anyhow::Result<()> {
+        let text = format!("*{}*\n{}", subject(notification), notification.body);
+        self.http
+            .post(&self.webhook_url)
+            .json(&serde_json::json!({ "text": text }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
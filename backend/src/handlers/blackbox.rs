@@ -0,0 +1,216 @@
+//! Black box endpoints — robots upload flight recorder dumps after a fall or
+//! a servo fault; operators list them per robot and download them for
+//! post-mortem analysis.
+
+use actix_web::{delete, error, get, post, web, HttpResponse};
+
+use super::commands::authorized;
+use crate::di::PolicyEngine;
+use crate::middleware::tenant::TenantContext;
+use crate::models::blackbox::BlackboxDump;
+use crate::models::usage::{QuotaState, UsageMetric};
+use crate::services::blackbox::{BlackboxService, MAX_DUMP_BYTES};
+use crate::services::usage::UsageService;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(upload_dump)
+        .service(list_dumps)
+        .service(get_dump)
+        .service(download_dump)
+        .service(delete_dump);
+}
+
+/// Upload a dump (the raw `.bin` file as an octet-stream body). The triage
+/// summary is extracted on upload; malformed dumps are rejected. The stored
+/// size counts toward the tenant's stored bytes.
+#[utoipa::path(
+    tag = "blackbox",
+    params(("robot_id" = String, Path, description = "Robot identifier")),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 201, description = "Dump stored", body = BlackboxDump),
+        (status = 400, description = "Not a valid dump"),
+        (status = 403, description = "Storage quota exceeded"),
+        (status = 413, description = "Dump too large")
+    )
+)]
+#[post("/api/robots/{robot_id}/blackbox")]
+#[tracing::instrument(skip_all)]
+pub async fn upload_dump(
+    tenant: web::ReqData<TenantContext>,
+    blackbox: web::Data<BlackboxService>,
+    usage: web::Data<UsageService>,
+    robot_id: web::Path<String>,
+    payload: web::Payload,
+) -> actix_web::Result<HttpResponse> {
+    let data = payload
+        .to_bytes_limited(MAX_DUMP_BYTES)
+        .await
+        .map_err(|_| error::ErrorPayloadTooLarge("dump too large"))??;
+
+    // Charge what is actually stored, the same size deletion gives back
+    let size = data.len() as u64;
+    let state = usage
+        .try_record(&tenant.tenant_id, UsageMetric::StorageBytes, size)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    if state == QuotaState::Exceeded {
+        tracing::warn!(tenant_id = %tenant.tenant_id, "Storage quota exceeded, rejecting dump");
+        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "quota_exceeded",
+            "metric": UsageMetric::StorageBytes,
+        })));
+    }
+
+    let dump = match blackbox.upload(&tenant.tenant_id, &robot_id, data.to_vec()).await {
+        Ok(dump) => dump,
+        Err(e) => {
+            if let Err(e) = usage.release_storage(&tenant.tenant_id, size).await {
+                tracing::error!(tenant_id = %tenant.tenant_id, "Failed to release storage usage: {}", e);
+            }
+            return Err(error::ErrorBadRequest(e));
+        }
+    };
+    tracing::info!(
+        tenant_id = %tenant.tenant_id,
+        robot_id = %dump.robot_id,
+        dump_id = %dump.dump_id,
+        trigger = ?dump.summary.trigger,
+        "Black box dump stored"
+    );
+    Ok(HttpResponse::Created().json(dump))
+}
+
+/// A robot's dumps with their triage summaries, newest first.
+#[utoipa::path(
+    tag = "blackbox",
+    params(("robot_id" = String, Path, description = "Robot identifier")),
+    responses((status = 200, description = "Dumps", body = [BlackboxDump]))
+)]
+#[get("/api/robots/{robot_id}/blackbox")]
+#[tracing::instrument(skip_all)]
+pub async fn list_dumps(
+    tenant: web::ReqData<TenantContext>,
+    blackbox: web::Data<BlackboxService>,
+    robot_id: web::Path<String>,
+) -> actix_web::Result<HttpResponse> {
+    let dumps = blackbox
+        .list(&tenant.tenant_id, &robot_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(dumps))
+}
+
+/// One dump's metadata and triage summary.
+#[utoipa::path(
+    tag = "blackbox",
+    params(
+        ("robot_id" = String, Path, description = "Robot identifier"),
+        ("dump_id" = String, Path, description = "Dump identifier")
+    ),
+    responses(
+        (status = 200, description = "Dump", body = BlackboxDump),
+        (status = 404, description = "Unknown dump")
+    )
+)]
+#[get("/api/robots/{robot_id}/blackbox/{dump_id}")]
+#[tracing::instrument(skip_all)]
+pub async fn get_dump(
+    tenant: web::ReqData<TenantContext>,
+    blackbox: web::Data<BlackboxService>,
+    path: web::Path<(String, String)>,
+) -> actix_web::Result<HttpResponse> {
+    let (robot_id, dump_id) = path.into_inner();
+    match blackbox
+        .get(&tenant.tenant_id, &robot_id, &dump_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+    {
+        Some(dump) => Ok(HttpResponse::Ok().json(dump)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+/// The raw dump, for `openduckrust blackbox inspect`.
+#[utoipa::path(
+    tag = "blackbox",
+    params(
+        ("robot_id" = String, Path, description = "Robot identifier"),
+        ("dump_id" = String, Path, description = "Dump identifier")
+    ),
+    responses(
+        (status = 200, description = "Dump file", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 404, description = "Unknown dump")
+    )
+)]
+#[get("/api/robots/{robot_id}/blackbox/{dump_id}/download")]
+#[tracing::instrument(skip_all)]
+pub async fn download_dump(
+    tenant: web::ReqData<TenantContext>,
+    blackbox: web::Data<BlackboxService>,
+    path: web::Path<(String, String)>,
+) -> actix_web::Result<HttpResponse> {
+    let (robot_id, dump_id) = path.into_inner();
+    match blackbox
+        .download(&tenant.tenant_id, &robot_id, &dump_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+    {
+        Some(data) => Ok(HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"blackbox-{}.bin\"", dump_id),
+            ))
+            .body(data)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+/// Delete a dump. Its size no longer counts toward the tenant's stored bytes.
+#[utoipa::path(
+    tag = "blackbox",
+    params(
+        ("robot_id" = String, Path, description = "Robot identifier"),
+        ("dump_id" = String, Path, description = "Dump identifier")
+    ),
+    responses(
+        (status = 200, description = "Dump deleted", body = BlackboxDump),
+        (status = 403, description = "Not allowed to delete dumps"),
+        (status = 404, description = "Unknown dump")
+    )
+)]
+#[delete("/api/robots/{robot_id}/blackbox/{dump_id}")]
+#[tracing::instrument(skip_all)]
+pub async fn delete_dump(
+    tenant: web::ReqData<TenantContext>,
+    blackbox: web::Data<BlackboxService>,
+    usage: web::Data<UsageService>,
+    policies: web::Data<dyn PolicyEngine>,
+    path: web::Path<(String, String)>,
+) -> actix_web::Result<HttpResponse> {
+    let (robot_id, dump_id) = path.into_inner();
+    if !authorized(&tenant, policies.get_ref(), "DeleteBlackboxDump", &robot_id).await? {
+        tracing::warn!(user_id = %tenant.user_id, robot_id = %robot_id, "Dump deletion refused");
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let Some(dump) = blackbox
+        .delete(&tenant.tenant_id, &robot_id, &dump_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if let Err(e) = usage.release_storage(&tenant.tenant_id, dump.size_bytes).await {
+        tracing::error!(tenant_id = %tenant.tenant_id, "Failed to release storage usage: {}", e);
+    }
+    tracing::info!(
+        tenant_id = %tenant.tenant_id,
+        robot_id = %dump.robot_id,
+        dump_id = %dump.dump_id,
+        deleted_by = %tenant.user_id,
+        "Black box dump deleted"
+    );
+    Ok(HttpResponse::Ok().json(dump))
+}
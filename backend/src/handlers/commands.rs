@@ -0,0 +1,187 @@
+//! Remote command endpoints — operators issue commands to robots, confirm
+//! dangerous ones, and robots fetch what is queued for them.
+//!
+//! Every request is checked against the Cedar policies: `IssueCommand` for
+//! routine commands, `IssueDangerousCommand` and `ConfirmDangerousCommand`
+//! for the two phases of a dangerous one, and `ReadCommands` for the robot
+//! fetching its queue. The runtime does not poll this queue yet, so the
+//! two-phase flow here guards the backend path only; commands sent to the
+//! runtime's WebSocket go through its own confirmation step.
+
+use actix_web::{error, get, post, web, HttpResponse};
+
+use crate::di::PolicyEngine;
+use crate::middleware::tenant::TenantContext;
+use crate::models::command::{IssueCommandRequest, RobotCommand};
+use crate::services::commands::{CommandService, Confirmation};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(issue_command)
+        .service(confirm_command)
+        .service(pending_commands);
+}
+
+/// Issue a command. Routine commands are queued at once (201); dangerous
+/// ones are held until confirmed (202).
+#[utoipa::path(
+    tag = "commands",
+    params(("robot_id" = String, Path, description = "Robot identifier")),
+    request_body = IssueCommandRequest,
+    responses(
+        (status = 201, description = "Command queued", body = RobotCommand),
+        (status = 202, description = "Dangerous command awaiting confirmation", body = RobotCommand),
+        (status = 400, description = "Invalid command"),
+        (status = 403, description = "Not allowed to issue this command")
+    )
+)]
+#[post("/api/robots/{robot_id}/commands")]
+#[tracing::instrument(skip_all)]
+pub async fn issue_command(
+    tenant: web::ReqData<TenantContext>,
+    commands: web::Data<CommandService>,
+    policies: web::Data<dyn PolicyEngine>,
+    robot_id: web::Path<String>,
+    body: web::Json<IssueCommandRequest>,
+) -> actix_web::Result<HttpResponse> {
+    let command = body.into_inner().command;
+    command.validate().map_err(error::ErrorBadRequest)?;
+    let dangerous = command.is_dangerous();
+    let action = if dangerous {
+        "IssueDangerousCommand"
+    } else {
+        "IssueCommand"
+    };
+    if !authorized(&tenant, policies.get_ref(), action, &robot_id).await? {
+        tracing::warn!(user_id = %tenant.user_id, robot_id = %robot_id, ?command, "Command refused");
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let cmd = commands
+        .issue(&tenant.tenant_id, &robot_id, &tenant.user_id, command)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    tracing::info!(
+        tenant_id = %tenant.tenant_id,
+        robot_id = %cmd.robot_id,
+        command_id = %cmd.command_id,
+        command = ?cmd.command,
+        status = ?cmd.status,
+        "Command issued"
+    );
+    if dangerous {
+        Ok(HttpResponse::Accepted().json(cmd))
+    } else {
+        Ok(HttpResponse::Created().json(cmd))
+    }
+}
+
+/// Confirm a dangerous command, releasing it to the robot.
+#[utoipa::path(
+    tag = "commands",
+    params(
+        ("robot_id" = String, Path, description = "Robot identifier"),
+        ("command_id" = String, Path, description = "Command identifier")
+    ),
+    responses(
+        (status = 200, description = "Command confirmed and queued", body = RobotCommand),
+        (status = 403, description = "Not allowed to confirm dangerous commands, or confirming one's own"),
+        (status = 404, description = "Unknown command"),
+        (status = 409, description = "Command is not awaiting confirmation"),
+        (status = 410, description = "Confirmation window has passed")
+    )
+)]
+#[post("/api/robots/{robot_id}/commands/{command_id}/confirm")]
+#[tracing::instrument(skip_all)]
+pub async fn confirm_command(
+    tenant: web::ReqData<TenantContext>,
+    commands: web::Data<CommandService>,
+    policies: web::Data<dyn PolicyEngine>,
+    path: web::Path<(String, String)>,
+) -> actix_web::Result<HttpResponse> {
+    let (robot_id, command_id) = path.into_inner();
+    if !authorized(&tenant, policies.get_ref(), "ConfirmDangerousCommand", &robot_id).await? {
+        tracing::warn!(user_id = %tenant.user_id, command_id = %command_id, "Confirmation refused");
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    match commands
+        .confirm(&tenant.tenant_id, &robot_id, &command_id, &tenant.user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+    {
+        Confirmation::Confirmed(cmd) => {
+            tracing::warn!(
+                tenant_id = %tenant.tenant_id,
+                robot_id = %cmd.robot_id,
+                command_id = %cmd.command_id,
+                command = ?cmd.command,
+                issued_by = %cmd.issued_by,
+                confirmed_by = %tenant.user_id,
+                "Dangerous command confirmed"
+            );
+            Ok(HttpResponse::Ok().json(cmd))
+        }
+        Confirmation::NotFound => Ok(HttpResponse::NotFound().finish()),
+        Confirmation::NotAwaiting => Ok(HttpResponse::Conflict().finish()),
+        Confirmation::SameUser => {
+            tracing::warn!(user_id = %tenant.user_id, command_id = %command_id, "Self-confirmation refused");
+            Ok(HttpResponse::Forbidden().finish())
+        }
+        Confirmation::Expired => Ok(HttpResponse::Gone().finish()),
+    }
+}
+
+/// Queued commands for the robot, oldest first. Fetching marks them
+/// delivered; dangerous commands not fetched in time are expired instead.
+/// Only the robot itself may fetch them: its token's subject must be the
+/// robot id, with `ReadCommands` on it.
+#[utoipa::path(
+    tag = "commands",
+    params(("robot_id" = String, Path, description = "Robot identifier")),
+    responses(
+        (status = 200, description = "Commands to execute", body = [RobotCommand]),
+        (status = 403, description = "Caller is not this robot")
+    )
+)]
+#[get("/api/robots/{robot_id}/commands/pending")]
+#[tracing::instrument(skip_all)]
+pub async fn pending_commands(
+    tenant: web::ReqData<TenantContext>,
+    commands: web::Data<CommandService>,
+    policies: web::Data<dyn PolicyEngine>,
+    robot_id: web::Path<String>,
+) -> actix_web::Result<HttpResponse> {
+    if tenant.user_id != *robot_id || !authorized(&tenant, policies.get_ref(), "ReadCommands", &robot_id).await? {
+        tracing::warn!(user_id = %tenant.user_id, robot_id = %robot_id, "Command fetch refused");
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let pending = commands
+        .take_pending(&tenant.tenant_id, &robot_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(pending))
+}
+
+/// Whether the caller may take `action` on the robot.
+pub(crate) async fn authorized(
+    tenant: &TenantContext,
+    policies: &dyn PolicyEngine,
+    action: &str,
+    robot_id: &str,
+) -> actix_web::Result<bool> {
+    authorized_on(tenant, policies, action, ("Robot", robot_id)).await
+}
+
+/// Whether the caller may take `action` on any resource (entity type and id).
+pub(crate) async fn authorized_on(
+    tenant: &TenantContext,
+    policies: &dyn PolicyEngine,
+    action: &str,
+    resource: (&str, &str),
+) -> actix_web::Result<bool> {
+    policies
+        .is_authorized(&tenant.user_id, &tenant.roles, action, resource)
+        .await
+        .map_err(error::ErrorInternalServerError)
+}
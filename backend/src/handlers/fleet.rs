@@ -0,0 +1,337 @@
+//! Fleet map endpoints — site metadata, fleet composition by site, and
+//! deleting and restoring robots (`DeleteRobot` and `RestoreRobot` in the
+//! Cedar policies).
+
+use actix_web::{delete, error, get, post, put, web, HttpResponse};
+
+use super::commands::authorized;
+use crate::di::PolicyEngine;
+use crate::middleware::tenant::TenantContext;
+use crate::models::fleet::{
+    AssignSiteRequest, FleetTopology, HeartbeatRequest, Robot, RobotHealth, Site, UpsertSiteRequest,
+};
+use crate::models::notification::{Notification, NotificationKind, Severity};
+use crate::services::fleet::{FleetService, Restore};
+use crate::services::notifications::NotificationService;
+use crate::services::policy_metrics::PolicyMetricsService;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_sites)
+        .service(upsert_site)
+        .service(assign_site)
+        .service(heartbeat)
+        .service(list_deleted_robots)
+        .service(delete_robot)
+        .service(restore_robot)
+        .service(topology);
+}
+
+/// List sites for the tenant.
+#[utoipa::path(
+    tag = "fleet",
+    responses((status = 200, description = "Sites", body = [Site]))
+)]
+#[get("/api/sites")]
+#[tracing::instrument(skip_all)]
+pub async fn list_sites(
+    tenant: web::ReqData<TenantContext>,
+    fleet: web::Data<FleetService>,
+) -> actix_web::Result<HttpResponse> {
+    let sites = fleet
+        .list_sites(&tenant.tenant_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(sites))
+}
+
+/// Create or update a site's metadata.
+#[utoipa::path(
+    tag = "fleet",
+    params(("site_id" = String, Path, description = "Site identifier")),
+    request_body = UpsertSiteRequest,
+    responses((status = 200, description = "Site saved", body = Site))
+)]
+#[put("/api/sites/{site_id}")]
+#[tracing::instrument(skip_all)]
+pub async fn upsert_site(
+    tenant: web::ReqData<TenantContext>,
+    fleet: web::Data<FleetService>,
+    site_id: web::Path<String>,
+    body: web::Json<UpsertSiteRequest>,
+) -> actix_web::Result<HttpResponse> {
+    let site = fleet
+        .upsert_site(&tenant.tenant_id, &site_id, body.into_inner())
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    tracing::info!(tenant_id = %tenant.tenant_id, site_id = %site.site_id, "Site saved");
+    Ok(HttpResponse::Ok().json(site))
+}
+
+/// Assign a robot to a site, or unassign it with `site_id: null`.
+#[utoipa::path(
+    tag = "fleet",
+    params(("robot_id" = String, Path, description = "Robot identifier")),
+    request_body = AssignSiteRequest,
+    responses(
+        (status = 200, description = "Robot updated", body = Robot),
+        (status = 404, description = "Unknown robot or site")
+    )
+)]
+#[put("/api/robots/{robot_id}/site")]
+#[tracing::instrument(skip_all)]
+pub async fn assign_site(
+    tenant: web::ReqData<TenantContext>,
+    fleet: web::Data<FleetService>,
+    robot_id: web::Path<String>,
+    body: web::Json<AssignSiteRequest>,
+) -> actix_web::Result<HttpResponse> {
+    match fleet
+        .assign_site(&tenant.tenant_id, &robot_id, body.into_inner().site_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+    {
+        Some(robot) => Ok(HttpResponse::Ok().json(robot)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+/// Robot heartbeat; registers the robot on first contact. A transition to
+/// `faulted` raises a critical alert on the tenant's notification channels,
+/// and policy counters are added to the fleet learning metrics.
+#[utoipa::path(
+    tag = "fleet",
+    params(("robot_id" = String, Path, description = "Robot identifier")),
+    request_body = HeartbeatRequest,
+    responses(
+        (status = 200, description = "Heartbeat recorded", body = Robot),
+        (status = 410, description = "Robot was deleted; restore it first")
+    )
+)]
+#[post("/api/robots/{robot_id}/heartbeat")]
+#[tracing::instrument(skip_all)]
+pub async fn heartbeat(
+    tenant: web::ReqData<TenantContext>,
+    fleet: web::Data<FleetService>,
+    notifications: web::Data<NotificationService>,
+    policy_metrics: web::Data<PolicyMetricsService>,
+    robot_id: web::Path<String>,
+    body: web::Json<HeartbeatRequest>,
+) -> actix_web::Result<HttpResponse> {
+    let previous = fleet
+        .get_robot(&tenant.tenant_id, &robot_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .and_then(|r| r.reported_health);
+
+    let heartbeat = body.into_inner();
+    let counters = heartbeat.metrics.clone();
+    let loop_margin = heartbeat.runtime.as_ref().map(|r| r.loop_margin);
+    let Some(robot) = fleet
+        .record_heartbeat(&tenant.tenant_id, &robot_id, heartbeat)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+    else {
+        return Ok(HttpResponse::Gone().finish());
+    };
+
+    if let Some(counters) = counters {
+        policy_metrics
+            .record(&tenant.tenant_id, &robot_id, &counters, loop_margin)
+            .await
+            .map_err(error::ErrorInternalServerError)?;
+    }
+
+    if robot.reported_health == Some(RobotHealth::Faulted) && previous != Some(RobotHealth::Faulted) {
+        let tenant_id = tenant.tenant_id.clone();
+        let notification = Notification {
+            kind: NotificationKind::Alert,
+            severity: Severity::Critical,
+            title: format!("{} reported a fault", robot.name),
+            body: format!(
+                "Robot {} reported a fault (fall or servo limit) and has stopped.",
+                robot.robot_id
+            ),
+            robot_id: Some(robot.robot_id.clone()),
+        };
+        // Deliver in the background so a slow channel never delays the robot
+        actix_web::rt::spawn(async move {
+            if let Err(e) = notifications.notify(&tenant_id, &notification).await {
+                tracing::error!(tenant_id = %tenant_id, "Failed to send fault alert: {}", e);
+            }
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(robot))
+}
+
+/// Deleted robots that can still be restored, soonest purged first.
+#[utoipa::path(
+    tag = "fleet",
+    responses((status = 200, description = "Deleted robots", body = [Robot]))
+)]
+#[get("/api/robots/deleted")]
+#[tracing::instrument(skip_all)]
+pub async fn list_deleted_robots(
+    tenant: web::ReqData<TenantContext>,
+    fleet: web::Data<FleetService>,
+) -> actix_web::Result<HttpResponse> {
+    let robots = fleet
+        .list_deleted_robots(&tenant.tenant_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(robots))
+}
+
+/// Delete a robot. It leaves the fleet at once, but it and its records are
+/// kept, restorable, until `purge_at`.
+#[utoipa::path(
+    tag = "fleet",
+    params(("robot_id" = String, Path, description = "Robot identifier")),
+    responses(
+        (status = 200, description = "Robot deleted", body = Robot),
+        (status = 403, description = "Not allowed to delete robots"),
+        (status = 404, description = "Unknown robot")
+    )
+)]
+#[delete("/api/robots/{robot_id}")]
+#[tracing::instrument(skip_all)]
+pub async fn delete_robot(
+    tenant: web::ReqData<TenantContext>,
+    fleet: web::Data<FleetService>,
+    policies: web::Data<dyn PolicyEngine>,
+    robot_id: web::Path<String>,
+) -> actix_web::Result<HttpResponse> {
+    if !authorized(&tenant, policies.get_ref(), "DeleteRobot", &robot_id).await? {
+        tracing::warn!(user_id = %tenant.user_id, robot_id = %robot_id, "Robot deletion refused");
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    match fleet
+        .delete_robot(&tenant.tenant_id, &robot_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+    {
+        Some(robot) => {
+            tracing::warn!(
+                tenant_id = %tenant.tenant_id,
+                robot_id = %robot.robot_id,
+                deleted_by = %tenant.user_id,
+                purge_at = ?robot.purge_at,
+                "Robot deleted"
+            );
+            Ok(HttpResponse::Ok().json(robot))
+        }
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+/// Restore a deleted robot with its records.
+#[utoipa::path(
+    tag = "fleet",
+    params(("robot_id" = String, Path, description = "Robot identifier")),
+    responses(
+        (status = 200, description = "Robot restored", body = Robot),
+        (status = 403, description = "Not allowed to restore robots"),
+        (status = 404, description = "Unknown or already purged robot"),
+        (status = 409, description = "Robot is not deleted")
+    )
+)]
+#[post("/api/robots/{robot_id}/restore")]
+#[tracing::instrument(skip_all)]
+pub async fn restore_robot(
+    tenant: web::ReqData<TenantContext>,
+    fleet: web::Data<FleetService>,
+    policies: web::Data<dyn PolicyEngine>,
+    robot_id: web::Path<String>,
+) -> actix_web::Result<HttpResponse> {
+    if !authorized(&tenant, policies.get_ref(), "RestoreRobot", &robot_id).await? {
+        tracing::warn!(user_id = %tenant.user_id, robot_id = %robot_id, "Robot restore refused");
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    match fleet
+        .restore_robot(&tenant.tenant_id, &robot_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+    {
+        Restore::Restored(robot) => {
+            tracing::info!(
+                tenant_id = %tenant.tenant_id,
+                robot_id = %robot.robot_id,
+                restored_by = %tenant.user_id,
+                "Robot restored"
+            );
+            Ok(HttpResponse::Ok().json(robot))
+        }
+        Restore::NotFound => Ok(HttpResponse::NotFound().finish()),
+        Restore::NotDeleted => Ok(HttpResponse::Conflict().finish()),
+    }
+}
+
+/// Fleet composition grouped by site, with aggregate health.
+#[utoipa::path(
+    tag = "fleet",
+    responses((status = 200, description = "Fleet topology", body = FleetTopology))
+)]
+#[get("/api/fleet/topology")]
+#[tracing::instrument(skip_all)]
+pub async fn topology(
+    tenant: web::ReqData<TenantContext>,
+    fleet: web::Data<FleetService>,
+) -> actix_web::Result<HttpResponse> {
+    let topology = fleet
+        .topology(&tenant.tenant_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(topology))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{App, HttpMessage};
+    use serde_json::json;
+
+    use super::*;
+    use crate::di::memory::InMemoryStorage;
+    use crate::di::StorageProvider;
+
+    const TENANT: &str = "tenant-1";
+    const ROBOT: &str = "duck-1";
+
+    #[actix_web::test]
+    async fn test_deleted_robot_heartbeat_gone() {
+        let storage: Arc<dyn StorageProvider> = Arc::new(InMemoryStorage::new());
+        let fleet = FleetService::new(storage.clone());
+        let first = serde_json::from_value(json!({ "health": "healthy" })).unwrap();
+        fleet.record_heartbeat(TENANT, ROBOT, first).await.unwrap();
+        fleet.delete_robot(TENANT, ROBOT).await.unwrap();
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(fleet))
+                .app_data(web::Data::new(NotificationService::new(storage.clone(), None, None, None)))
+                .app_data(web::Data::new(PolicyMetricsService::new(storage)))
+                .wrap_fn(|req, srv| {
+                    req.extensions_mut().insert(TenantContext {
+                        tenant_id: TENANT.to_string(),
+                        user_id: ROBOT.to_string(),
+                        roles: Vec::new(),
+                    });
+                    srv.call(req)
+                })
+                .service(heartbeat),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri(&format!("/api/robots/{}/heartbeat", ROBOT))
+            .set_json(json!({ "health": "healthy" }))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::GONE);
+    }
+}
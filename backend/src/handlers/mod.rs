@@ -1 +1,8 @@
 // openduckrust — handlers
+
+pub mod blackbox;
+pub mod commands;
+pub mod fleet;
+pub mod notifications;
+pub mod policy_metrics;
+pub mod usage;
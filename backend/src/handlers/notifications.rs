@@ -0,0 +1,112 @@
+//! Notification settings endpoints.
+//!
+//! Changing the channels, and sending test notifications on them, takes
+//! `SetNotificationConfig` on the caller's tenant (tenant admins, see
+//! `policies/tenants.cedar`).
+
+use actix_web::{error, get, post, put, web, HttpResponse};
+
+use super::commands::authorized_on;
+use crate::di::PolicyEngine;
+use crate::middleware::tenant::TenantContext;
+use crate::models::notification::{
+    DeliveryResult, Notification, NotificationConfig, NotificationKind, Severity,
+};
+use crate::services::notifications::NotificationService;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_config).service(set_config).service(send_test);
+}
+
+/// Notification channels of the caller's tenant.
+#[utoipa::path(
+    tag = "notifications",
+    responses((status = 200, description = "Notification config", body = NotificationConfig))
+)]
+#[get("/api/notifications/config")]
+#[tracing::instrument(skip_all)]
+pub async fn get_config(
+    tenant: web::ReqData<TenantContext>,
+    notifications: web::Data<NotificationService>,
+) -> actix_web::Result<HttpResponse> {
+    let config = notifications
+        .config(&tenant.tenant_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(config))
+}
+
+/// Replace the notification channels of the caller's tenant. Slack webhooks
+/// must be on `hooks.slack.com`, SNS topics under the deployment's prefix for
+/// the tenant, and email recipients valid addresses, at most
+/// `MAX_EMAIL_RECIPIENTS` of them.
+#[utoipa::path(
+    tag = "notifications",
+    request_body = NotificationConfig,
+    responses(
+        (status = 200, description = "Config saved", body = NotificationConfig),
+        (status = 400, description = "Invalid channel"),
+        (status = 403, description = "Not allowed to change notification channels")
+    )
+)]
+#[put("/api/notifications/config")]
+#[tracing::instrument(skip_all)]
+pub async fn set_config(
+    tenant: web::ReqData<TenantContext>,
+    notifications: web::Data<NotificationService>,
+    policies: web::Data<dyn PolicyEngine>,
+    body: web::Json<NotificationConfig>,
+) -> actix_web::Result<HttpResponse> {
+    if !may_configure(&tenant, policies.get_ref()).await? {
+        tracing::warn!(user_id = %tenant.user_id, "Notification config change refused");
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    notifications
+        .validate(&tenant.tenant_id, &body)
+        .map_err(error::ErrorBadRequest)?;
+
+    notifications
+        .set_config(&tenant.tenant_id, &body)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    tracing::info!(tenant_id = %tenant.tenant_id, channels = body.channels.len(), "Notification config saved");
+    Ok(HttpResponse::Ok().json(body.into_inner()))
+}
+
+/// Send a test notification on every configured channel.
+#[utoipa::path(
+    tag = "notifications",
+    responses(
+        (status = 200, description = "Per-channel delivery results", body = [DeliveryResult]),
+        (status = 403, description = "Not allowed to change notification channels")
+    )
+)]
+#[post("/api/notifications/test")]
+#[tracing::instrument(skip_all)]
+pub async fn send_test(
+    tenant: web::ReqData<TenantContext>,
+    notifications: web::Data<NotificationService>,
+    policies: web::Data<dyn PolicyEngine>,
+) -> actix_web::Result<HttpResponse> {
+    if !may_configure(&tenant, policies.get_ref()).await? {
+        tracing::warn!(user_id = %tenant.user_id, "Test notification refused");
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let notification = Notification {
+        kind: NotificationKind::Test,
+        severity: Severity::Info,
+        title: "Test notification".to_string(),
+        body: format!("Notification channels for tenant {} are working.", tenant.tenant_id),
+        robot_id: None,
+    };
+    let results = notifications
+        .notify(&tenant.tenant_id, &notification)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// Whether the caller may change (and test) their tenant's channels.
+async fn may_configure(tenant: &TenantContext, policies: &dyn PolicyEngine) -> actix_web::Result<bool> {
+    authorized_on(tenant, policies, "SetNotificationConfig", ("Tenant", tenant.tenant_id.as_str())).await
+}
@@ -0,0 +1,52 @@
+//! Fleet learning metrics endpoint — policy versions compared on real-world
+//! falls, loop margin and energy, for judging a policy release.
+
+use actix_web::{error, get, web, HttpResponse};
+use serde::Deserialize;
+
+use crate::middleware::tenant::TenantContext;
+use crate::models::policy_metrics::PolicyComparison;
+use crate::services::policy_metrics::PolicyMetricsService;
+
+/// Window compared when the request doesn't say.
+const DEFAULT_DAYS: u32 = 30;
+
+/// Longest window that can be asked for.
+const MAX_DAYS: u32 = 3650;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(compare_policies);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompareQuery {
+    days: Option<u32>,
+}
+
+/// Fleet-wide metrics per policy version over the last `days` days (30 by
+/// default), oldest version first.
+#[utoipa::path(
+    tag = "policies",
+    params(("days" = Option<u32>, Query, description = "Days to include, up to today")),
+    responses(
+        (status = 200, description = "Policy comparison", body = PolicyComparison),
+        (status = 400, description = "days out of range")
+    )
+)]
+#[get("/api/policies/metrics")]
+#[tracing::instrument(skip_all)]
+pub async fn compare_policies(
+    tenant: web::ReqData<TenantContext>,
+    metrics: web::Data<PolicyMetricsService>,
+    query: web::Query<CompareQuery>,
+) -> actix_web::Result<HttpResponse> {
+    let days = query.days.unwrap_or(DEFAULT_DAYS);
+    if !(1..=MAX_DAYS).contains(&days) {
+        return Err(error::ErrorBadRequest(format!("days must be 1 to {}", MAX_DAYS)));
+    }
+    let comparison = metrics
+        .compare(&tenant.tenant_id, days)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(comparison))
+}
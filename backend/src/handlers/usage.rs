@@ -0,0 +1,93 @@
+//! Usage and quota endpoints.
+
+use actix_web::{error, get, put, web, HttpResponse};
+
+use super::commands::authorized_on;
+use crate::di::PolicyEngine;
+use crate::middleware::tenant::TenantContext;
+use crate::models::usage::{QuotaLimits, UsageReport};
+use crate::services::usage::UsageService;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_usage).service(get_quotas).service(set_quotas);
+}
+
+/// Current-period usage against quotas for the caller's tenant.
+#[utoipa::path(
+    tag = "usage",
+    responses((status = 200, description = "Usage report", body = UsageReport))
+)]
+#[get("/api/usage")]
+#[tracing::instrument(skip_all)]
+pub async fn get_usage(
+    tenant: web::ReqData<TenantContext>,
+    usage: web::Data<UsageService>,
+) -> actix_web::Result<HttpResponse> {
+    let report = usage
+        .report(&tenant.tenant_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Quotas of a tenant. Callers may read their own tenant; reading another
+/// takes `ReadQuotas` on it (platform admins, see `policies/tenants.cedar`).
+#[utoipa::path(
+    tag = "usage",
+    params(("tenant_id" = String, Path, description = "Tenant identifier")),
+    responses(
+        (status = 200, description = "Quota limits", body = QuotaLimits),
+        (status = 403, description = "Not allowed to read this tenant")
+    )
+)]
+#[get("/api/tenants/{tenant_id}/quotas")]
+#[tracing::instrument(skip_all)]
+pub async fn get_quotas(
+    tenant: web::ReqData<TenantContext>,
+    usage: web::Data<UsageService>,
+    policies: web::Data<dyn PolicyEngine>,
+    tenant_id: web::Path<String>,
+) -> actix_web::Result<HttpResponse> {
+    if *tenant_id != tenant.tenant_id
+        && !authorized_on(&tenant, policies.get_ref(), "ReadQuotas", ("Tenant", tenant_id.as_str())).await?
+    {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let quotas = usage
+        .quotas(&tenant_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok().json(quotas))
+}
+
+/// Replace a tenant's quotas. Takes `SetQuotas` on the tenant (platform
+/// admins).
+#[utoipa::path(
+    tag = "usage",
+    params(("tenant_id" = String, Path, description = "Tenant identifier")),
+    request_body = QuotaLimits,
+    responses(
+        (status = 200, description = "Quotas saved", body = QuotaLimits),
+        (status = 403, description = "Not allowed to change this tenant's quotas")
+    )
+)]
+#[put("/api/tenants/{tenant_id}/quotas")]
+#[tracing::instrument(skip_all)]
+pub async fn set_quotas(
+    tenant: web::ReqData<TenantContext>,
+    usage: web::Data<UsageService>,
+    policies: web::Data<dyn PolicyEngine>,
+    tenant_id: web::Path<String>,
+    body: web::Json<QuotaLimits>,
+) -> actix_web::Result<HttpResponse> {
+    if !authorized_on(&tenant, policies.get_ref(), "SetQuotas", ("Tenant", tenant_id.as_str())).await? {
+        tracing::warn!(user_id = %tenant.user_id, tenant_id = %tenant_id, "Quota change refused");
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    usage
+        .set_quotas(&tenant_id, &body)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    tracing::info!(tenant_id = %tenant_id, user_id = %tenant.user_id, "Quotas updated");
+    Ok(HttpResponse::Ok().json(body.into_inner()))
+}
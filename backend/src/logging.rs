@@ -0,0 +1,218 @@
+//! Log outputs, configured from the environment.
+//!
+//! Same outputs and variables as the robot runtime and the CLI:
+//! `ODR_LOG_FORMAT` (`auto`, `pretty` or `json`) picks the console format,
+//! `ODR_LOG_DIR` adds daily-rotated JSON files (the last `ODR_LOG_KEEP`, 7
+//! by default), and `ODR_LOG_REMOTE=host:port` ships JSON lines over TCP to
+//! a log collector. `auto` is pretty on a terminal and JSON otherwise, so
+//! CloudWatch keeps getting JSON. `RUST_LOG` filters every output.
+//!
+//! Spans are also exported as OpenTelemetry traces when
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`)
+//! names an OTLP/HTTP collector, under `OTEL_SERVICE_NAME` (default
+//! `openduckrust-api`). Export runs on its own thread and always includes
+//! INFO spans, whatever `RUST_LOG` says.
+
+use std::io::{IsTerminal, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Minimum time between connection attempts to the log collector.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Connect/write timeout towards the log collector.
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Log outputs read from the environment.
+pub struct LogSettings {
+    pub pretty: bool,
+    pub dir: Option<PathBuf>,
+    pub keep: usize,
+    pub remote: Option<String>,
+    /// Service name of the exported traces, if an OTLP endpoint is set.
+    pub otlp_service: Option<String>,
+}
+
+impl LogSettings {
+    /// `ODR_LOG_FORMAT`, `ODR_LOG_DIR`, `ODR_LOG_KEEP`, `ODR_LOG_REMOTE`, and
+    /// the standard `OTEL_*` variables.
+    pub fn from_env() -> Self {
+        let otlp = ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"]
+            .iter()
+            .any(|var| std::env::var_os(var).is_some());
+        let pretty = match std::env::var("ODR_LOG_FORMAT").as_deref() {
+            Ok("pretty") => true,
+            Ok("json") => false,
+            _ => std::io::stderr().is_terminal(),
+        };
+        Self {
+            pretty,
+            dir: std::env::var_os("ODR_LOG_DIR").map(PathBuf::from),
+            keep: std::env::var("ODR_LOG_KEEP")
+                .ok()
+                .and_then(|keep| keep.parse().ok())
+                .unwrap_or(7),
+            remote: std::env::var("ODR_LOG_REMOTE").ok(),
+            otlp_service: otlp.then(|| {
+                std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "openduckrust-api".into())
+            }),
+        }
+    }
+}
+
+/// Keeps the background log writers running until dropped.
+#[must_use = "logs stop being written when the guard is dropped"]
+pub struct LogGuard {
+    _guards: Vec<WorkerGuard>,
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for LogGuard {
+    /// Export the spans still batched.
+    fn drop(&mut self) {
+        if let Some(provider) = self.tracer_provider.take() {
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// Install the log outputs.
+pub fn init(settings: &LogSettings) -> std::io::Result<LogGuard> {
+    let mut guards = Vec::new();
+
+    let file = match settings.dir {
+        Some(ref dir) => {
+            std::fs::create_dir_all(dir)?;
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix("openduckrust-api")
+                .filename_suffix("log")
+                .max_log_files(settings.keep.max(1))
+                .build(dir)
+                .map_err(std::io::Error::other)?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            guards.push(guard);
+            Some(fmt::layer().json().with_ansi(false).with_writer(writer))
+        }
+        None => None,
+    };
+
+    let remote = match settings.remote {
+        Some(ref addr) => {
+            let (writer, guard) = tracing_appender::non_blocking(RemoteWriter::new(addr));
+            guards.push(guard);
+            Some(fmt::layer().json().with_ansi(false).with_writer(writer))
+        }
+        None => None,
+    };
+
+    let console_json = (!settings.pretty).then(|| fmt::layer().json().with_writer(std::io::stderr));
+    let console_pretty = settings.pretty.then(|| fmt::layer().with_writer(std::io::stderr));
+
+    let tracer_provider = match settings.otlp_service {
+        Some(ref service) => Some(tracer_provider(service)?),
+        None => None,
+    };
+    let otel = tracer_provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer("openduckrust-api"))
+            .with_filter(LevelFilter::INFO)
+    });
+
+    // `RUST_LOG` filters the log outputs only, so traces keep their spans
+    let logs = Layer::and_then(console_pretty, console_json)
+        .and_then(file)
+        .and_then(remote)
+        .with_filter(EnvFilter::from_default_env());
+
+    tracing_subscriber::registry()
+        .with(logs)
+        .with(otel)
+        .try_init()
+        .map_err(std::io::Error::other)?;
+    if let Some(ref service) = settings.otlp_service {
+        tracing::info!("Exporting traces over OTLP as {}", service);
+    }
+    Ok(LogGuard {
+        _guards: guards,
+        tracer_provider,
+    })
+}
+
+/// OTLP/HTTP span exporter, its endpoint read from the `OTEL_*` variables,
+/// and W3C `traceparent` propagation so callers' traces continue here.
+fn tracer_provider(service: &str) -> std::io::Result<SdkTracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+        .map_err(std::io::Error::other)?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service.to_string()).build())
+        .build();
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(provider)
+}
+
+/// Line sink for a TCP log collector that reconnects on its own; lines
+/// written while it is unreachable are dropped.
+struct RemoteWriter {
+    addr: String,
+    stream: Option<TcpStream>,
+    last_attempt: Option<Instant>,
+}
+
+impl RemoteWriter {
+    fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.to_string(),
+            stream: None,
+            last_attempt: None,
+        }
+    }
+
+    fn connect(&self) -> std::io::Result<TcpStream> {
+        let addr = self.addr.to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "log collector not found")
+        })?;
+        let stream = TcpStream::connect_timeout(&addr, REMOTE_TIMEOUT)?;
+        stream.set_write_timeout(Some(REMOTE_TIMEOUT))?;
+        Ok(stream)
+    }
+}
+
+impl Write for RemoteWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let retry = self.last_attempt.is_none_or(|t| t.elapsed() >= RECONNECT_INTERVAL);
+        if self.stream.is_none() && retry {
+            self.last_attempt = Some(Instant::now());
+            self.stream = self.connect().ok();
+        }
+        if let Some(ref mut stream) = self.stream {
+            if stream.write_all(buf).is_err() {
+                self.stream = None;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(ref mut stream) = self.stream {
+            let _ = stream.flush();
+        }
+        Ok(())
+    }
+}
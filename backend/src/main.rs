@@ -1,38 +1,174 @@
-use actix_web::{web, App, HttpServer, middleware};
-use tracing_subscriber::EnvFilter;
+use std::sync::Arc;
+
+use actix_web::{middleware::from_fn, web, App, HttpServer};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 mod handlers;
+mod logging;
 mod models;
-mod middleware as app_middleware;
+mod middleware;
 mod services;
 mod di;
 
+use di::notifier::SmtpSettings;
+use di::{BlobStorage, InMemoryBlobStorage, InMemoryStorage, LocalCedarEngine, PolicyEngine, StorageProvider};
+use logging::LogSettings;
+use middleware::tenant::TenantSettings;
+use services::blackbox::BlackboxService;
+use services::commands::CommandService;
+use services::fleet::{FleetService, DEFAULT_RETENTION_SECS};
+use services::notifications::NotificationService;
+use services::policy_metrics::PolicyMetricsService;
+use services::usage::UsageService;
+
 #[derive(OpenApi)]
 #[openapi(
     info(title = "OpenDuckRust API", version = "0.1.0"),
-    paths(),
-    components(schemas())
+    paths(
+        handlers::fleet::list_sites,
+        handlers::fleet::upsert_site,
+        handlers::fleet::assign_site,
+        handlers::fleet::heartbeat,
+        handlers::fleet::list_deleted_robots,
+        handlers::fleet::delete_robot,
+        handlers::fleet::restore_robot,
+        handlers::fleet::topology,
+        handlers::blackbox::upload_dump,
+        handlers::blackbox::list_dumps,
+        handlers::blackbox::get_dump,
+        handlers::blackbox::download_dump,
+        handlers::blackbox::delete_dump,
+        handlers::commands::issue_command,
+        handlers::commands::confirm_command,
+        handlers::commands::pending_commands,
+        handlers::notifications::get_config,
+        handlers::notifications::set_config,
+        handlers::notifications::send_test,
+        handlers::usage::get_usage,
+        handlers::usage::get_quotas,
+        handlers::usage::set_quotas,
+        handlers::policy_metrics::compare_policies,
+    ),
+    components(schemas(
+        models::fleet::Site,
+        models::fleet::UpsertSiteRequest,
+        models::fleet::AssignSiteRequest,
+        models::fleet::RobotHealth,
+        models::fleet::Robot,
+        models::fleet::HeartbeatRequest,
+        models::fleet::SafetyState,
+        models::fleet::RuntimeHealth,
+        models::fleet::RobotSummary,
+        models::fleet::AggregateHealth,
+        models::fleet::SiteGroup,
+        models::fleet::FleetTopology,
+        models::blackbox::BlackboxTrigger,
+        models::blackbox::FallDirection,
+        models::blackbox::BlackboxSummary,
+        models::blackbox::BlackboxDump,
+        models::command::CommandKind,
+        models::command::CommandStatus,
+        models::command::IssueCommandRequest,
+        models::command::RobotCommand,
+        models::notification::Severity,
+        models::notification::NotificationKind,
+        models::notification::Notification,
+        models::notification::NotificationChannel,
+        models::notification::NotificationConfig,
+        models::notification::DeliveryResult,
+        models::usage::UsageMetric,
+        models::usage::TenantUsage,
+        models::usage::Quota,
+        models::usage::QuotaLimits,
+        models::usage::QuotaState,
+        models::usage::MetricUsage,
+        models::usage::UsageReport,
+        models::policy_metrics::PolicyCounters,
+        models::policy_metrics::PolicyMetrics,
+        models::policy_metrics::PolicyComparison,
+    ))
 )]
 pub struct ApiDoc;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .json()
-        .init();
+    let _log_guard = logging::init(&LogSettings::from_env())?;
 
     tracing::info!("Starting openduckrust API server");
 
+    // TODO: Swap for DynamoDbProvider when running against AWS
+    let storage: Arc<dyn StorageProvider> = Arc::new(InMemoryStorage::new());
+    let retention_secs = match std::env::var("ROBOT_RETENTION_DAYS") {
+        Ok(days) => days
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .and_then(|days| days.checked_mul(24 * 3600))
+            .ok_or_else(|| {
+                std::io::Error::other(format!("ROBOT_RETENTION_DAYS must be a number of days, got {:?}", days))
+            })?,
+        Err(_) => DEFAULT_RETENTION_SECS,
+    };
+    let fleet = web::Data::new(FleetService::new(storage.clone()).with_retention(retention_secs));
+    let usage = web::Data::new(UsageService::new(storage.clone()));
+    // TODO: Swap for S3BlobStorage when running against AWS
+    let blobs: Arc<dyn BlobStorage> = Arc::new(InMemoryBlobStorage::new());
+    let blackbox = web::Data::new(BlackboxService::new(storage.clone(), blobs));
+    let commands = web::Data::new(CommandService::new(storage.clone()));
+    let policy_metrics = web::Data::new(PolicyMetricsService::new(storage.clone()));
+    // TODO: Swap for VerifiedPermissionsEngine when running against AWS
+    let policy_engine: Arc<dyn PolicyEngine> =
+        Arc::new(LocalCedarEngine::bundled().map_err(std::io::Error::other)?);
+    let policies: web::Data<dyn PolicyEngine> = web::Data::from(policy_engine);
+    let tenant_settings = TenantSettings::from_env();
+    if tenant_settings.jwt_secret.is_none() && !tenant_settings.trust_headers {
+        tracing::warn!("Neither ODR_JWT_SECRET nor ODR_TRUST_TENANT_HEADERS set, every API request will be refused");
+    } else if tenant_settings.trust_headers {
+        tracing::warn!("Trusting X-Tenant-Id/X-User-Id/X-Roles headers, for development only");
+    }
+    let tenant_settings = web::Data::new(tenant_settings);
+
+    let aws_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    let smtp = SmtpSettings::from_env();
+    if smtp.is_none() {
+        tracing::warn!("SMTP_* not set, email notifications disabled");
+    }
+    let sns_topic_prefix = std::env::var("SNS_TOPIC_ARN_PREFIX").ok().filter(|s| !s.is_empty());
+    if sns_topic_prefix.is_none() {
+        tracing::warn!("SNS_TOPIC_ARN_PREFIX not set, SNS notifications disabled");
+    }
+    let notifications = web::Data::new(NotificationService::new(
+        storage.clone(),
+        Some(aws_sdk_sns::Client::new(&aws_config)),
+        sns_topic_prefix,
+        smtp,
+    ));
+
     HttpServer::new(move || {
         App::new()
+            .app_data(fleet.clone())
+            .app_data(usage.clone())
+            .app_data(blackbox.clone())
+            .app_data(commands.clone())
+            .app_data(policy_metrics.clone())
+            .app_data(policies.clone())
+            .app_data(notifications.clone())
+            .app_data(tenant_settings.clone())
+            // Outermost last: trace, then tenant, then quotas (which meter by tenant)
+            .wrap(from_fn(middleware::quota::enforce_quotas))
+            .wrap(from_fn(middleware::tenant::resolve_tenant))
+            .wrap(from_fn(middleware::trace::trace_requests))
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}")
                     .url("/api-docs/openapi.json", ApiDoc::openapi()),
             )
-            // TODO: Add tenant middleware, routes
+            .configure(handlers::fleet::configure)
+            .configure(handlers::blackbox::configure)
+            .configure(handlers::commands::configure)
+            .configure(handlers::notifications::configure)
+            .configure(handlers::usage::configure)
+            .configure(handlers::policy_metrics::configure)
     })
     .bind("0.0.0.0:8080")?
     .run()
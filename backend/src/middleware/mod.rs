@@ -1 +1,5 @@
 // openduckrust — middleware
+
+pub mod quota;
+pub mod tenant;
+pub mod trace;
@@ -0,0 +1,162 @@
+//! Quota enforcement middleware — meters telemetry and command requests per
+//! tenant and rejects them once a hard quota is exceeded.
+//!
+//! Past the soft limit, requests still succeed but carry an `X-Quota-Warning`
+//! header naming the metric. Past the hard limit, period-based metrics answer
+//! 429 (they reset next period) and stored bytes answer 403 (they only clear
+//! once data is deleted). The tenant context comes from the tenant
+//! middleware, which must wrap this one; requests without one (only those
+//! outside `/api/`) pass through.
+//!
+//! Byte metrics are sized from `Content-Length`, so metered uploads without
+//! one are answered 411. Period metrics are checked and recorded in one step
+//! before the handler runs, and given back if it fails. Stored bytes are only
+//! pre-checked here: the black box upload handler records the stored dump's
+//! actual size, the same figure deletion gives back.
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, CONTENT_LENGTH};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+
+use super::tenant::TenantContext;
+use crate::models::usage::{QuotaState, UsageMetric};
+use crate::services::usage::UsageService;
+
+pub const QUOTA_WARNING_HEADER: HeaderName = HeaderName::from_static("x-quota-warning");
+
+/// Which metric (and how much of it) a request consumes, if it is metered.
+/// The amount is `None` for a byte metric without a usable `Content-Length`.
+fn classify(req: &ServiceRequest) -> Option<(UsageMetric, Option<u64>)> {
+    if req.method() != Method::POST {
+        return None;
+    }
+
+    let path = req.path();
+    let bytes = || {
+        req.headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+    };
+    if path.ends_with("/heartbeat") || path.ends_with("/telemetry") {
+        Some((UsageMetric::TelemetryBytes, bytes()))
+    } else if path.ends_with("/blackbox") {
+        Some((UsageMetric::StorageBytes, bytes()))
+    } else if path.ends_with("/commands") {
+        Some((UsageMetric::Commands, Some(1)))
+    } else {
+        None
+    }
+}
+
+fn metric_name(metric: UsageMetric) -> &'static str {
+    match metric {
+        UsageMetric::TelemetryBytes => "telemetry_bytes",
+        UsageMetric::StorageBytes => "storage_bytes",
+        UsageMetric::AiTokens => "ai_tokens",
+        UsageMetric::Commands => "commands",
+    }
+}
+
+pub async fn enforce_quotas(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let tenant = req.extensions().get::<TenantContext>().cloned();
+    let usage = req.app_data::<web::Data<UsageService>>().cloned();
+
+    let (Some(tenant), Some(usage), Some((metric, amount))) = (tenant, usage, classify(&req)) else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let Some(amount) = amount else {
+        let response = HttpResponse::LengthRequired().json(serde_json::json!({
+            "error": "length_required",
+            "metric": metric,
+        }));
+        return Ok(req.into_response(response).map_into_right_body());
+    };
+
+    // Stored bytes are recorded by the upload handler, at the stored size
+    let recorded_here = metric != UsageMetric::StorageBytes;
+    let state = if recorded_here {
+        usage.try_record(&tenant.tenant_id, metric, amount).await
+    } else {
+        usage.check(&tenant.tenant_id, metric, amount).await
+    }
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if state == QuotaState::Exceeded {
+        let status = match metric {
+            UsageMetric::StorageBytes => StatusCode::FORBIDDEN,
+            _ => StatusCode::TOO_MANY_REQUESTS,
+        };
+        tracing::warn!(tenant_id = %tenant.tenant_id, metric = metric_name(metric), "Hard quota exceeded, rejecting");
+        let response = HttpResponse::build(status).json(serde_json::json!({
+            "error": "quota_exceeded",
+            "metric": metric,
+        }));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    let mut res = next.call(req).await?;
+
+    if recorded_here && !res.status().is_success() {
+        if let Err(e) = usage.release(&tenant.tenant_id, metric, amount).await {
+            tracing::error!(tenant_id = %tenant.tenant_id, "Failed to release usage: {}", e);
+        }
+    }
+
+    if state == QuotaState::Warning {
+        tracing::warn!(tenant_id = %tenant.tenant_id, metric = metric_name(metric), "Soft quota exceeded");
+        res.headers_mut()
+            .insert(QUOTA_WARNING_HEADER, HeaderValue::from_static(metric_name(metric)));
+    }
+
+    Ok(res.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn post(path: &str, length: Option<&str>) -> ServiceRequest {
+        let mut req = TestRequest::post().uri(path);
+        if let Some(length) = length {
+            req = req.insert_header((CONTENT_LENGTH, length));
+        }
+        req.to_srv_request()
+    }
+
+    #[test]
+    fn test_classify_metered_requests() {
+        let heartbeat = post("/api/robots/duck-1/heartbeat", Some("512"));
+        assert_eq!(classify(&heartbeat), Some((UsageMetric::TelemetryBytes, Some(512))));
+        let telemetry = post("/api/robots/duck-1/telemetry", Some("2048"));
+        assert_eq!(classify(&telemetry), Some((UsageMetric::TelemetryBytes, Some(2048))));
+        let blackbox = post("/api/robots/duck-1/blackbox", Some("4096"));
+        assert_eq!(classify(&blackbox), Some((UsageMetric::StorageBytes, Some(4096))));
+        // Commands count one each, whatever their size
+        let command = post("/api/robots/duck-1/commands", None);
+        assert_eq!(classify(&command), Some((UsageMetric::Commands, Some(1))));
+    }
+
+    #[test]
+    fn test_classify_byte_metric_without_length() {
+        let missing = post("/api/robots/duck-1/telemetry", None);
+        assert_eq!(classify(&missing), Some((UsageMetric::TelemetryBytes, None)));
+        let garbage = post("/api/robots/duck-1/blackbox", Some("lots"));
+        assert_eq!(classify(&garbage), Some((UsageMetric::StorageBytes, None)));
+    }
+
+    #[test]
+    fn test_classify_unmetered_requests() {
+        let read = TestRequest::get().uri("/api/robots/duck-1/telemetry").to_srv_request();
+        assert_eq!(classify(&read), None);
+        assert_eq!(classify(&post("/api/robots", Some("64"))), None);
+    }
+}
@@ -1,9 +1,28 @@
-//! Multi-tenancy middleware — extracts tenant_id from JWT and injects into request extensions.
+//! Multi-tenancy middleware — resolves the caller's tenant and injects it into request extensions.
 //! All DynamoDB queries are filtered by tenant_id via this global middleware.
+//!
+//! Every `/api/` request needs a `TenantContext`: handlers take it as `web::ReqData<TenantContext>` and the quota
+//! middleware meters by it. It comes from one of:
+//!
+//! - a bearer JWT signed with `ODR_JWT_SECRET` (HS256), carrying `tenant_id`, `sub` and `roles` claims;
+//! - with `ODR_TRUST_TENANT_HEADERS=1`, the `X-Tenant-Id`, `X-User-Id` and `X-Roles` (comma-separated) headers.
+//!   This trusts the caller completely and is meant for development only.
+//!
+//! Requests that match neither are answered 401. Cognito tokens (RS256, verified against the user pool's keys) are
+//! not supported yet.
 
-use actix_web::{dev::ServiceRequest, Error, HttpMessage};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, AUTHORIZATION};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 
+pub const TENANT_ID_HEADER: HeaderName = HeaderName::from_static("x-tenant-id");
+pub const USER_ID_HEADER: HeaderName = HeaderName::from_static("x-user-id");
+pub const ROLES_HEADER: HeaderName = HeaderName::from_static("x-roles");
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TenantContext {
     pub tenant_id: String,
@@ -11,9 +30,79 @@ pub struct TenantContext {
     pub roles: Vec<String>,
 }
 
-pub async fn extract_tenant(req: &ServiceRequest) -> Result<TenantContext, Error> {
-    // TODO: Decode JWT from Authorization header
-    // TODO: Extract tenant_id, user_id, roles from claims
-    // TODO: Verify with Amazon Verified Permissions (Cedar)
-    todo!("Implement JWT tenant extraction + Cedar RBAC check")
+/// Where tenant contexts may come from, read once at startup.
+#[derive(Debug, Clone)]
+pub struct TenantSettings {
+    pub jwt_secret: Option<String>,
+    pub trust_headers: bool,
+}
+
+impl TenantSettings {
+    pub fn from_env() -> Self {
+        Self {
+            jwt_secret: std::env::var("ODR_JWT_SECRET").ok().filter(|s| !s.is_empty()),
+            trust_headers: std::env::var("ODR_TRUST_TENANT_HEADERS").is_ok_and(|v| v == "1" || v == "true"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    tenant_id: String,
+    sub: String,
+    #[serde(default)]
+    roles: Vec<String>,
+}
+
+pub fn extract_tenant(req: &ServiceRequest, settings: &TenantSettings) -> Option<TenantContext> {
+    let header = |name: &HeaderName| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+
+    if let Some(token) = header(&AUTHORIZATION).and_then(|v| v.strip_prefix("Bearer ")) {
+        let secret = settings.jwt_secret.as_ref()?;
+        let key = DecodingKey::from_secret(secret.as_bytes());
+        let claims = decode::<Claims>(token, &key, &Validation::new(Algorithm::HS256))
+            .map_err(|e| tracing::warn!("Rejected bearer token: {}", e))
+            .ok()?
+            .claims;
+        return Some(TenantContext {
+            tenant_id: claims.tenant_id,
+            user_id: claims.sub,
+            roles: claims.roles,
+        });
+    }
+
+    if !settings.trust_headers {
+        return None;
+    }
+    Some(TenantContext {
+        tenant_id: header(&TENANT_ID_HEADER)?.to_string(),
+        user_id: header(&USER_ID_HEADER)?.to_string(),
+        roles: header(&ROLES_HEADER)
+            .map(|roles| roles.split(',').map(str::trim).filter(|r| !r.is_empty()).map(String::from).collect())
+            .unwrap_or_default(),
+    })
+}
+
+pub async fn resolve_tenant(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if !req.path().starts_with("/api/") {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let tenant = req.app_data::<web::Data<TenantSettings>>().and_then(|settings| extract_tenant(&req, settings));
+    let Some(tenant) = tenant else {
+        tracing::warn!(path = %req.path(), "No tenant context, rejecting");
+        return Ok(req.into_response(HttpResponse::Unauthorized().finish()).map_into_right_body());
+    };
+
+    req.extensions_mut().insert(tenant);
+    Ok(next.call(req).await?.map_into_left_body())
 }
@@ -0,0 +1,69 @@
+//! Request tracing middleware — one span per request, continuing the
+//! caller's trace.
+//!
+//! A W3C `traceparent` header (sent by the CLI) makes the request's span a
+//! child of the caller's, so an exported trace covers both sides. Robots
+//! send an `X-Correlation-Id` instead; it is recorded on the span and echoed
+//! back, and requests without one get a fresh id so every log line of a
+//! request can be found by it.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use opentelemetry::propagation::Extractor;
+use tracing::field::Empty;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+pub const CORRELATION_ID_HEADER: HeaderName = HeaderName::from_static("x-correlation-id");
+
+/// Longest correlation id taken from a caller; longer ones are replaced.
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+/// Reads propagation headers off an actix request.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+pub async fn trace_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let correlation_id = req
+        .headers()
+        .get(&CORRELATION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_CORRELATION_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        otel.kind = "server",
+        http.method = %req.method(),
+        http.route = req.match_pattern().unwrap_or_else(|| req.path().to_string()),
+        http.status_code = Empty,
+        correlation_id = %correlation_id,
+    );
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(req.headers()))
+    });
+    let _ = span.set_parent(parent);
+
+    let mut res = next.call(req).instrument(span.clone()).await?;
+    span.record("http.status_code", res.status().as_u16());
+    if let Ok(value) = HeaderValue::from_str(&correlation_id) {
+        res.headers_mut().insert(CORRELATION_ID_HEADER, value);
+    }
+    Ok(res)
+}
@@ -0,0 +1,62 @@
+//! Black box models — flight recorder dumps uploaded by robots after a fall
+//! or a servo fault, with the triage summary extracted on upload.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What made the robot write the dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlackboxTrigger {
+    Fall,
+    ServoFault,
+    Other,
+}
+
+/// Which way the robot was leaning when the dump was written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FallDirection {
+    Forward,
+    Backward,
+    Left,
+    Right,
+}
+
+/// Triage metadata extracted from a dump.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BlackboxSummary {
+    pub trigger: BlackboxTrigger,
+    /// Reason recorded by the robot, e.g. `fall (tilt 1.12 rad)`.
+    pub reason: String,
+    /// The fault reported by the servo safety logic, for `servo_fault` dumps.
+    pub servo_fault: Option<String>,
+    pub fall_direction: Option<FallDirection>,
+    /// Largest tilt from vertical in the dump (rad).
+    pub max_tilt: f64,
+    /// Joints that lagged far behind their targets in the last second before
+    /// the dump, most lagging first: the servos could not keep up.
+    pub saturated_joints: Vec<String>,
+    /// Ticks that overran the control period.
+    pub overruns: u32,
+    /// Ticks with a collision detected.
+    pub collisions: u32,
+    pub control_freq: u32,
+    pub ticks: u32,
+    /// Time covered by the dump (s).
+    pub duration: f64,
+}
+
+/// A stored dump.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BlackboxDump {
+    pub dump_id: String,
+    pub robot_id: String,
+    pub tenant_id: String,
+    /// Unix time (s) the robot wrote the dump.
+    pub recorded_at: f64,
+    /// Unix time (s) of the upload.
+    pub uploaded_at: u64,
+    pub size_bytes: u64,
+    pub summary: BlackboxSummary,
+}
@@ -0,0 +1,85 @@
+//! Remote command models — commands operators send to robots through the
+//! backend. Dangerous ones wait for an explicit confirmation before they are
+//! delivered.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What the robot is asked to do. Same wire format as the runtime's remote
+/// commands.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommandKind {
+    Pause,
+    Unpause,
+    /// Play a sound by file name, or a random one without a name.
+    PlaySound {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Stream a sound (wav, mp3, ogg or flac) from an http(s) URL.
+    PlayUrl { url: String },
+    /// Play a recorded emote by name.
+    PlayEmote { name: String },
+    Estop,
+    Release,
+    /// Switch every servo's torque off. The robot collapses if it was
+    /// standing or walking.
+    TorqueOff,
+}
+
+impl CommandKind {
+    /// Whether the command needs the dangerous-command permission and a
+    /// confirmation step.
+    pub fn is_dangerous(&self) -> bool {
+        matches!(self, CommandKind::TorqueOff)
+    }
+
+    /// Why the command can't be sent, if it can't.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            CommandKind::PlayUrl { url } if !(url.starts_with("http://") || url.starts_with("https://")) => {
+                Err("url must be an http or https URL".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Where a command is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandStatus {
+    /// Dangerous command issued, not yet confirmed.
+    AwaitingConfirmation,
+    /// Ready for the robot to fetch.
+    Queued,
+    Delivered,
+    /// Not confirmed or not fetched before `expires_at`.
+    Expired,
+}
+
+/// Request body for issuing a command.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct IssueCommandRequest {
+    pub command: CommandKind,
+}
+
+/// A command sent to a robot.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RobotCommand {
+    pub command_id: String,
+    pub robot_id: String,
+    pub tenant_id: String,
+    pub command: CommandKind,
+    pub status: CommandStatus,
+    pub issued_by: String,
+    /// Unix time (s) the command was issued.
+    pub issued_at: u64,
+    /// Unix time (s) after which a dangerous command can no longer be
+    /// confirmed, or, once confirmed, delivered. None for routine commands.
+    pub expires_at: Option<u64>,
+    pub confirmed_by: Option<String>,
+    pub confirmed_at: Option<u64>,
+    pub delivered_at: Option<u64>,
+}
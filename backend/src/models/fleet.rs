@@ -0,0 +1,158 @@
+//! Fleet topology models — robots, sites, and aggregate health.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::policy_metrics::PolicyCounters;
+
+/// A physical location (classroom, lab, exhibition hall) hosting robots.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Site {
+    pub site_id: String,
+    pub tenant_id: String,
+    pub name: String,
+    /// Free-form address or room description.
+    pub location: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// IANA timezone, e.g. `America/New_York`.
+    pub timezone: Option<String>,
+}
+
+/// Request body for creating or updating a site.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct UpsertSiteRequest {
+    pub name: String,
+    pub location: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub timezone: Option<String>,
+}
+
+/// Request body for assigning a robot to a site (`null` to unassign).
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct AssignSiteRequest {
+    pub site_id: Option<String>,
+}
+
+/// Health state reported by (or inferred for) a robot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RobotHealth {
+    Healthy,
+    Degraded,
+    Faulted,
+    /// No heartbeat within the offline window.
+    Offline,
+}
+
+/// A robot registered to a tenant.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Robot {
+    pub robot_id: String,
+    pub tenant_id: String,
+    pub name: String,
+    pub site_id: Option<String>,
+    /// Unix time (s) of the last heartbeat.
+    pub last_heartbeat: Option<u64>,
+    /// Health from the last heartbeat.
+    pub reported_health: Option<RobotHealth>,
+    /// Control-loop condition from the last heartbeat.
+    #[serde(default)]
+    pub runtime_health: Option<RuntimeHealth>,
+    /// Unix time (s) the robot was deleted; set while it can be restored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<u64>,
+    /// Unix time (s) a deleted robot and its records are purged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purge_at: Option<u64>,
+}
+
+/// What the robot's safety logic is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyState {
+    Running,
+    Paused,
+    SoftStopped,
+    Estopped,
+    Fallen,
+}
+
+/// Control-loop condition reported by the robot runtime.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuntimeHealth {
+    /// Smoothed share of the control period left after each tick (negative
+    /// when the loop overruns).
+    pub loop_margin: f64,
+    /// Slowest recent tick (ms).
+    pub worst_loop_ms: f64,
+    /// Ticks that overran the period since the runtime started.
+    pub overruns: u64,
+    /// Smoothed share of failed servo bus transactions.
+    pub bus_error_rate: f64,
+    /// Age of the newest IMU sample (ms).
+    pub imu_age_ms: Option<f64>,
+    pub safety: SafetyState,
+}
+
+/// Heartbeat sent periodically by each robot.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct HeartbeatRequest {
+    /// Display name; used when the robot registers on first heartbeat.
+    pub name: Option<String>,
+    pub health: RobotHealth,
+    /// Control-loop condition; absent from older runtimes.
+    #[serde(default)]
+    pub runtime: Option<RuntimeHealth>,
+    /// Policy performance counters; absent from older runtimes.
+    #[serde(default)]
+    pub metrics: Option<PolicyCounters>,
+}
+
+/// Robot entry in the fleet map, with health resolved against heartbeat age.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RobotSummary {
+    pub robot_id: String,
+    pub name: String,
+    pub health: RobotHealth,
+    pub last_heartbeat: Option<u64>,
+    pub runtime_health: Option<RuntimeHealth>,
+}
+
+/// Robot counts by health state.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct AggregateHealth {
+    pub total: usize,
+    pub healthy: usize,
+    pub degraded: usize,
+    pub faulted: usize,
+    pub offline: usize,
+}
+
+impl AggregateHealth {
+    pub fn add(&mut self, health: RobotHealth) {
+        self.total += 1;
+        match health {
+            RobotHealth::Healthy => self.healthy += 1,
+            RobotHealth::Degraded => self.degraded += 1,
+            RobotHealth::Faulted => self.faulted += 1,
+            RobotHealth::Offline => self.offline += 1,
+        }
+    }
+}
+
+/// Robots at one site (or unassigned robots when `site` is `None`).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SiteGroup {
+    pub site: Option<Site>,
+    pub health: AggregateHealth,
+    pub robots: Vec<RobotSummary>,
+}
+
+/// Fleet composition grouped by site, for the operations dashboard.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FleetTopology {
+    pub health: AggregateHealth,
+    pub sites: Vec<SiteGroup>,
+}
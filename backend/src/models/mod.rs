@@ -1 +1,8 @@
 // openduckrust — models
+
+pub mod blackbox;
+pub mod command;
+pub mod fleet;
+pub mod notification;
+pub mod policy_metrics;
+pub mod usage;
@@ -0,0 +1,76 @@
+//! Notification models — messages sent to humans and per-tenant channel config.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// What triggered a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// Raised by the alert engine (falls, faults, offline robots).
+    Alert,
+    /// A policy or firmware deployment failed.
+    DeploymentFailure,
+    /// Sent on request to verify channel configuration.
+    Test,
+}
+
+/// A message to deliver on every configured channel.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub severity: Severity,
+    pub title: String,
+    pub body: String,
+    pub robot_id: Option<String>,
+}
+
+/// A delivery channel configured by a tenant.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationChannel {
+    /// Publish to an SNS topic (fan-out to SMS, email, or other subscribers).
+    Sns { topic_arn: String },
+    /// Email via the platform SMTP relay.
+    Email { to: Vec<String> },
+    /// Post to a Slack incoming webhook.
+    Slack { webhook_url: String },
+}
+
+/// Per-tenant notification settings.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotificationConfig {
+    pub channels: Vec<NotificationChannel>,
+    /// Notifications below this severity are not sent.
+    #[serde(default = "default_min_severity")]
+    pub min_severity: Severity,
+}
+
+fn default_min_severity() -> Severity {
+    Severity::Warning
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            min_severity: default_min_severity(),
+        }
+    }
+}
+
+/// Delivery result for one channel.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeliveryResult {
+    pub channel: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
@@ -0,0 +1,66 @@
+//! Fleet learning metrics — real-world performance per policy version.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Counters a robot reports in its heartbeat, accumulated since the policy
+/// it names started running (they restart with the runtime or on a policy
+/// swap).
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PolicyCounters {
+    /// Policy version running (model name or release tag).
+    pub policy: String,
+    /// Seconds spent walking (unpaused).
+    pub active_secs: f64,
+    pub falls: u64,
+    /// Distance walked (m).
+    pub distance_m: f64,
+    /// Energy drawn from the battery (Wh).
+    pub energy_wh: f64,
+}
+
+/// One robot's totals for one policy version on one UTC day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyRollup {
+    pub tenant_id: String,
+    pub policy: String,
+    pub robot_id: String,
+    /// `YYYY-MM-DD` (UTC).
+    pub day: String,
+    pub active_secs: f64,
+    pub falls: u64,
+    pub distance_m: f64,
+    pub energy_wh: f64,
+    /// Sum and count of the heartbeats' loop margins, for the mean.
+    pub loop_margin_sum: f64,
+    pub loop_margin_samples: u64,
+}
+
+/// Fleet-wide performance of one policy version.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PolicyMetrics {
+    pub policy: String,
+    /// First and last day (`YYYY-MM-DD`, UTC) it ran in the window.
+    pub first_seen: String,
+    pub last_seen: String,
+    /// Robots that ran it.
+    pub robots: usize,
+    pub active_hours: f64,
+    pub falls: u64,
+    pub distance_m: f64,
+    pub energy_wh: f64,
+    /// `None` until the policy has walked.
+    pub falls_per_hour: Option<f64>,
+    /// Mean share of the control period left after each tick.
+    pub mean_loop_margin: Option<f64>,
+    /// Wh per meter walked.
+    pub energy_per_meter: Option<f64>,
+}
+
+/// Policy versions side by side, oldest first.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PolicyComparison {
+    /// First day (`YYYY-MM-DD`, UTC) included.
+    pub since: String,
+    pub policies: Vec<PolicyMetrics>,
+}
@@ -0,0 +1,127 @@
+//! Usage metering and quota models.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A metered resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageMetric {
+    /// Telemetry ingested (bytes per billing period).
+    TelemetryBytes,
+    /// Data currently stored (bytes, not reset per period).
+    StorageBytes,
+    /// AI-provider tokens consumed (per billing period).
+    AiTokens,
+    /// Robot commands issued (per billing period).
+    Commands,
+}
+
+impl UsageMetric {
+    pub const ALL: [UsageMetric; 4] = [
+        UsageMetric::TelemetryBytes,
+        UsageMetric::StorageBytes,
+        UsageMetric::AiTokens,
+        UsageMetric::Commands,
+    ];
+}
+
+/// Per-tenant counters for one billing period.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TenantUsage {
+    pub tenant_id: String,
+    /// Billing period, `YYYY-MM` (UTC).
+    pub period: String,
+    pub telemetry_bytes: u64,
+    pub storage_bytes: u64,
+    pub ai_tokens: u64,
+    pub commands: u64,
+}
+
+impl TenantUsage {
+    pub fn get(&self, metric: UsageMetric) -> u64 {
+        match metric {
+            UsageMetric::TelemetryBytes => self.telemetry_bytes,
+            UsageMetric::StorageBytes => self.storage_bytes,
+            UsageMetric::AiTokens => self.ai_tokens,
+            UsageMetric::Commands => self.commands,
+        }
+    }
+
+    pub fn get_mut(&mut self, metric: UsageMetric) -> &mut u64 {
+        match metric {
+            UsageMetric::TelemetryBytes => &mut self.telemetry_bytes,
+            UsageMetric::StorageBytes => &mut self.storage_bytes,
+            UsageMetric::AiTokens => &mut self.ai_tokens,
+            UsageMetric::Commands => &mut self.commands,
+        }
+    }
+}
+
+/// Soft (warn) and hard (reject) limits for one metric. `None` = unlimited.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+pub struct Quota {
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+/// Quotas for every metered resource of a tenant.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuotaLimits {
+    pub telemetry_bytes: Quota,
+    pub storage_bytes: Quota,
+    pub ai_tokens: Quota,
+    pub commands: Quota,
+}
+
+impl QuotaLimits {
+    pub fn get(&self, metric: UsageMetric) -> Quota {
+        match metric {
+            UsageMetric::TelemetryBytes => self.telemetry_bytes,
+            UsageMetric::StorageBytes => self.storage_bytes,
+            UsageMetric::AiTokens => self.ai_tokens,
+            UsageMetric::Commands => self.commands,
+        }
+    }
+}
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+impl Default for QuotaLimits {
+    fn default() -> Self {
+        Self {
+            telemetry_bytes: Quota { soft: Some(4 * GIB), hard: Some(5 * GIB) },
+            storage_bytes: Quota { soft: Some(8 * GIB), hard: Some(10 * GIB) },
+            ai_tokens: Quota { soft: Some(1_600_000), hard: Some(2_000_000) },
+            commands: Quota { soft: Some(80_000), hard: Some(100_000) },
+        }
+    }
+}
+
+/// Where a tenant stands against one quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaState {
+    Ok,
+    /// Past the soft limit: requests succeed with a warning.
+    Warning,
+    /// Past the hard limit: requests are rejected.
+    Exceeded,
+}
+
+/// Usage of one metric against its quota.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MetricUsage {
+    pub metric: UsageMetric,
+    pub used: u64,
+    pub quota: Quota,
+    pub state: QuotaState,
+}
+
+/// Response of the usage endpoint.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UsageReport {
+    pub tenant_id: String,
+    pub period: String,
+    pub metrics: Vec<MetricUsage>,
+}
@@ -0,0 +1,422 @@
+//! Black box service — stores flight recorder dumps uploaded by robots and
+//! extracts a triage summary from each.
+//!
+//! The dump itself goes to blob storage, untouched, for download; its
+//! metadata and summary live in the single table next to the robot. The
+//! binary format is the runtime's (`runtime/src/blackbox.rs`): a header with
+//! the joint names, then fixed-size tick records.
+
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use uuid::Uuid;
+
+use super::{unix_now, TABLE};
+use crate::di::{BlobStorage, StorageProvider};
+use crate::models::blackbox::{BlackboxDump, BlackboxSummary, BlackboxTrigger, FallDirection};
+
+const BLACKBOX_ENTITY: &str = "blackbox";
+
+/// Largest dump accepted (bytes).
+pub const MAX_DUMP_BYTES: usize = 16 * 1024 * 1024;
+
+const MAGIC: &[u8; 8] = b"ODRBBOX\0";
+const MAX_VERSION: u16 = 2;
+
+/// Seconds before the dump checked for joints lagging their targets.
+const SATURATION_WINDOW: f64 = 1.0;
+
+/// Mean gap between target and position (rad) above which a joint counts as
+/// saturated.
+const SATURATION_ERROR: f64 = 0.3;
+
+/// Bit set on ticks that overran the control period.
+const FLAG_OVERRUN: u8 = 1;
+/// Bit set on ticks with a collision.
+const FLAG_COLLISION: u8 = 1 << 1;
+
+pub struct BlackboxService {
+    storage: Arc<dyn StorageProvider>,
+    blobs: Arc<dyn BlobStorage>,
+}
+
+impl BlackboxService {
+    pub fn new(storage: Arc<dyn StorageProvider>, blobs: Arc<dyn BlobStorage>) -> Self {
+        Self { storage, blobs }
+    }
+
+    /// Store a dump and its summary. Fails on a malformed dump.
+    pub async fn upload(&self, tenant_id: &str, robot_id: &str, data: Vec<u8>) -> anyhow::Result<BlackboxDump> {
+        let (recorded_at, summary) = summarize(&data)?;
+        let dump = BlackboxDump {
+            dump_id: Uuid::new_v4().to_string(),
+            robot_id: robot_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            recorded_at,
+            uploaded_at: unix_now(),
+            size_bytes: data.len() as u64,
+            summary,
+        };
+        self.blobs
+            .put_blob(&blob_key(tenant_id, robot_id, &dump.dump_id), data)
+            .await?;
+
+        let mut item = serde_json::to_value(&dump)?;
+        item["pk"] = dump_key(robot_id, &dump.dump_id).into();
+        item["entity"] = BLACKBOX_ENTITY.into();
+        self.storage.put_item(TABLE, item).await?;
+        Ok(dump)
+    }
+
+    /// A robot's dumps, newest first.
+    pub async fn list(&self, tenant_id: &str, robot_id: &str) -> anyhow::Result<Vec<BlackboxDump>> {
+        let mut dumps: Vec<BlackboxDump> = self
+            .storage
+            .query_by_tenant(TABLE, tenant_id)
+            .await?
+            .into_iter()
+            .filter(|item| item["entity"] == BLACKBOX_ENTITY && item["robot_id"] == robot_id)
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?;
+        dumps.sort_by(|a, b| b.recorded_at.total_cmp(&a.recorded_at));
+        Ok(dumps)
+    }
+
+    pub async fn get(&self, tenant_id: &str, robot_id: &str, dump_id: &str) -> anyhow::Result<Option<BlackboxDump>> {
+        match self.storage.get_item(TABLE, &dump_key(robot_id, dump_id), tenant_id).await? {
+            Some(item) => Ok(Some(serde_json::from_value(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The raw dump, as uploaded.
+    pub async fn download(&self, tenant_id: &str, robot_id: &str, dump_id: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        self.blobs.get_blob(&blob_key(tenant_id, robot_id, dump_id)).await
+    }
+
+    /// Delete a dump and its file. Returns the deleted dump, whose
+    /// `size_bytes` the caller gives back to the tenant's storage quota.
+    pub async fn delete(&self, tenant_id: &str, robot_id: &str, dump_id: &str) -> anyhow::Result<Option<BlackboxDump>> {
+        let Some(dump) = self.get(tenant_id, robot_id, dump_id).await? else {
+            return Ok(None);
+        };
+        self.blobs.delete_blob(&blob_key(tenant_id, robot_id, dump_id)).await?;
+        self.storage
+            .delete_item(TABLE, &dump_key(robot_id, dump_id), tenant_id)
+            .await?;
+        Ok(Some(dump))
+    }
+}
+
+pub fn dump_key(robot_id: &str, dump_id: &str) -> String {
+    format!("ROBOT#{}#BLACKBOX#{}", robot_id, dump_id)
+}
+
+fn blob_key(tenant_id: &str, robot_id: &str, dump_id: &str) -> String {
+    format!("blackbox/{}/{}/{}.bin", tenant_id, robot_id, dump_id)
+}
+
+/// One decoded tick, reduced to what triage needs.
+struct Tick {
+    time: f64,
+    flags: u8,
+    tilt: f64,
+    gravity: [f64; 3],
+    positions: Vec<f64>,
+    targets: Vec<f64>,
+}
+
+impl Tick {
+    fn is_finite(&self) -> bool {
+        [self.time, self.tilt]
+            .iter()
+            .chain(&self.gravity)
+            .chain(&self.positions)
+            .chain(&self.targets)
+            .all(|v| v.is_finite())
+    }
+}
+
+/// Decode a dump into its recording time and triage summary. Frames with a
+/// non-finite value are skipped; a dump without any other frame is rejected.
+pub fn summarize(data: &[u8]) -> anyhow::Result<(f64, BlackboxSummary)> {
+    let mut r = Reader { data, at: 0 };
+    if r.take(MAGIC.len())? != MAGIC {
+        bail!("not a black box dump");
+    }
+    let version = r.u16()?;
+    if version == 0 || version > MAX_VERSION {
+        bail!("unsupported dump version {}", version);
+    }
+    let control_freq = r.u32()?;
+    let recorded_at = r.f64()?;
+    if !recorded_at.is_finite() {
+        bail!("recording time is not a number");
+    }
+    let reason = r.str()?;
+    let joints = r.u16()? as usize;
+    let joint_names = (0..joints).map(|_| r.str()).collect::<anyhow::Result<Vec<_>>>()?;
+    let count = r.u32()? as usize;
+
+    let mut ticks = Vec::with_capacity(count.min(data.len()));
+    for _ in 0..count {
+        r.take(8)?; // tick number
+        let time = r.f64()?;
+        r.take(16)?; // stage timings
+        r.take(1)?; // safety state
+        let flags = r.u8()?;
+        let tilt = r.f32()?;
+        let mut gravity = [0.0; 3];
+        if version >= 2 {
+            for g in gravity.iter_mut() {
+                *g = r.f32()?;
+            }
+        }
+        r.take(7 * 4)?; // commands
+        let positions = (0..joints).map(|_| r.f32()).collect::<anyhow::Result<Vec<_>>>()?;
+        let targets = (0..joints).map(|_| r.f32()).collect::<anyhow::Result<Vec<_>>>()?;
+        let tick = Tick { time, flags, tilt, gravity, positions, targets };
+        // A NaN would win every max and come back from storage as null
+        if tick.is_finite() {
+            ticks.push(tick);
+        }
+    }
+    if ticks.is_empty() {
+        bail!("dump has no readable frames");
+    }
+
+    let (trigger, servo_fault) = if reason.starts_with("fall") {
+        (BlackboxTrigger::Fall, None)
+    } else if let Some(fault) = reason.strip_prefix("servo fault") {
+        let fault = fault.trim().trim_start_matches('(').trim_end_matches(')');
+        (BlackboxTrigger::ServoFault, Some(fault.to_string()))
+    } else {
+        (BlackboxTrigger::Other, None)
+    };
+
+    let steepest = ticks.iter().max_by(|a, b| a.tilt.total_cmp(&b.tilt));
+    let fall_direction = match trigger {
+        BlackboxTrigger::Fall => steepest.and_then(|t| fall_direction(t.gravity)),
+        _ => None,
+    };
+    let duration = match (ticks.first(), ticks.last()) {
+        (Some(first), Some(last)) => last.time - first.time,
+        _ => 0.0,
+    };
+
+    let summary = BlackboxSummary {
+        trigger,
+        reason,
+        servo_fault,
+        fall_direction,
+        max_tilt: steepest.map_or(0.0, |t| t.tilt),
+        saturated_joints: saturated_joints(&ticks, &joint_names),
+        overruns: ticks.iter().filter(|t| t.flags & FLAG_OVERRUN != 0).count() as u32,
+        collisions: ticks.iter().filter(|t| t.flags & FLAG_COLLISION != 0).count() as u32,
+        control_freq,
+        ticks: ticks.len() as u32,
+        duration,
+    };
+    Ok((recorded_at, summary))
+}
+
+/// Direction of the larger horizontal gravity component in the body frame:
+/// +x when pitched forward, -y when rolled to the right. None without
+/// gravity data (dumps from older runtimes).
+fn fall_direction(gravity: [f64; 3]) -> Option<FallDirection> {
+    let [x, y, _] = gravity;
+    if x == 0.0 && y == 0.0 {
+        return None;
+    }
+    Some(if x.abs() >= y.abs() {
+        if x > 0.0 {
+            FallDirection::Forward
+        } else {
+            FallDirection::Backward
+        }
+    } else if y < 0.0 {
+        FallDirection::Right
+    } else {
+        FallDirection::Left
+    })
+}
+
+/// Joints whose mean gap to their target over the last `SATURATION_WINDOW`
+/// exceeds `SATURATION_ERROR`, largest gap first.
+fn saturated_joints(ticks: &[Tick], joint_names: &[String]) -> Vec<String> {
+    let Some(last) = ticks.last() else {
+        return Vec::new();
+    };
+    let window: Vec<&Tick> = ticks
+        .iter()
+        .filter(|t| last.time - t.time <= SATURATION_WINDOW)
+        .collect();
+
+    let mut lagging: Vec<(f64, &String)> = joint_names
+        .iter()
+        .enumerate()
+        .map(|(j, name)| {
+            let total: f64 = window.iter().map(|t| (t.targets[j] - t.positions[j]).abs()).sum();
+            (total / window.len() as f64, name)
+        })
+        .filter(|(error, _)| *error > SATURATION_ERROR)
+        .collect();
+    lagging.sort_by(|a, b| b.0.total_cmp(&a.0));
+    lagging.into_iter().map(|(_, name)| name.clone()).collect()
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let Some(slice) = self.data.get(self.at..self.at + n) else {
+            bail!("dump truncated at byte {}", self.at);
+        };
+        self.at += n;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into()?)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> anyhow::Result<f64> {
+        self.array().map(|b| f32::from_le_bytes(b) as f64)
+    }
+
+    fn f64(&mut self) -> anyhow::Result<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    fn str(&mut self) -> anyhow::Result<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).context("invalid UTF-8 in dump")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JOINTS: [&str; 2] = ["left_knee", "right_knee"];
+
+    /// One tick as the runtime writes it: time, tilt and, per joint,
+    /// (position, target).
+    struct Frame {
+        time: f64,
+        tilt: f32,
+        joints: [(f32, f32); 2],
+    }
+
+    fn frame(time: f64, error: f32) -> Frame {
+        Frame { time, tilt: 0.1, joints: [(0.0, error), (0.0, 0.0)] }
+    }
+
+    fn dump(reason: &str, frames: &[Frame]) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.extend(2u16.to_le_bytes());
+        out.extend(50u32.to_le_bytes());
+        out.extend(1_700_000_000.0f64.to_le_bytes());
+        let push_str = |out: &mut Vec<u8>, s: &str| {
+            out.extend((s.len() as u16).to_le_bytes());
+            out.extend(s.as_bytes());
+        };
+        push_str(&mut out, reason);
+        out.extend((JOINTS.len() as u16).to_le_bytes());
+        for name in JOINTS {
+            push_str(&mut out, name);
+        }
+        out.extend((frames.len() as u32).to_le_bytes());
+        for (i, f) in frames.iter().enumerate() {
+            out.extend((i as u64).to_le_bytes());
+            out.extend(f.time.to_le_bytes());
+            out.extend([0u8; 16]); // stage timings
+            out.push(0); // safety state
+            out.push(0); // flags
+            out.extend(f.tilt.to_le_bytes());
+            for g in [0.0f32, 0.0, -1.0] {
+                out.extend(g.to_le_bytes());
+            }
+            out.extend([0u8; 7 * 4]); // commands
+            for (position, _) in f.joints {
+                out.extend(position.to_le_bytes());
+            }
+            for (_, target) in f.joints {
+                out.extend(target.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_summarizes_valid_dump() {
+        let frames: Vec<Frame> = (0..5).map(|i| frame(i as f64 * 0.02, 0.0)).collect();
+        let (recorded_at, summary) = summarize(&dump("fall (tilt 1.20 rad)", &frames)).unwrap();
+        assert_eq!(recorded_at, 1_700_000_000.0);
+        assert_eq!(summary.trigger, BlackboxTrigger::Fall);
+        assert_eq!(summary.ticks, 5);
+        assert!((summary.duration - 0.08).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rejects_truncated_dump() {
+        let data = dump("fall", &[frame(0.0, 0.0), frame(0.02, 0.0)]);
+        for len in [0, 4, MAGIC.len() + 3, data.len() - 1] {
+            assert!(summarize(&data[..len]).is_err(), "accepted {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn test_rejects_empty_dump() {
+        assert!(summarize(&dump("fall", &[])).is_err());
+    }
+
+    #[test]
+    fn test_skips_non_finite_frames() {
+        let mut nan_tilt = frame(0.02, 0.0);
+        nan_tilt.tilt = f32::NAN;
+        let mut inf_target = frame(0.04, 0.0);
+        inf_target.joints[1].1 = f32::INFINITY;
+        let frames = [frame(0.0, 0.0), nan_tilt, inf_target, frame(0.06, 0.0)];
+
+        let (_, summary) = summarize(&dump("fall", &frames)).unwrap();
+        assert_eq!(summary.ticks, 2);
+        assert!(summary.max_tilt.is_finite());
+        assert!(summary.duration.is_finite());
+        // Survives a trip through storage
+        let json = serde_json::to_value(&summary).unwrap();
+        serde_json::from_value::<BlackboxSummary>(json).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_dump_without_finite_frames() {
+        let mut bad = frame(0.0, 0.0);
+        bad.time = f64::NAN;
+        assert!(summarize(&dump("fall", &[bad])).is_err());
+    }
+
+    #[test]
+    fn test_saturation_threshold() {
+        let lagging = |error: f32| -> Vec<String> {
+            let frames: Vec<Frame> = (0..10).map(|i| frame(i as f64 * 0.02, error)).collect();
+            summarize(&dump("servo fault (overload)", &frames)).unwrap().1.saturated_joints
+        };
+        assert!(lagging(SATURATION_ERROR as f32 - 0.01).is_empty());
+        assert_eq!(lagging(SATURATION_ERROR as f32 + 0.01), vec!["left_knee".to_string()]);
+    }
+}
@@ -0,0 +1,225 @@
+//! Command service — queues commands for robots and runs the two-phase
+//! flow for dangerous ones.
+//!
+//! A routine command is queued as soon as it is issued. A dangerous one is
+//! held as `awaiting_confirmation` until it is confirmed, and both the
+//! confirmation and the robot's fetch must happen within
+//! `CONFIRMATION_WINDOW_SECS` of the previous step; otherwise it expires, so
+//! a stale "torque off" never reaches a robot whose situation has changed.
+//! The confirmation must come from someone other than the issuer, so it is a
+//! second pair of eyes and not a second click. Permission checks are the
+//! handlers' job.
+//!
+//! The runtime does not fetch commands from here yet: its own confirmation
+//! step (`runtime/src/remote.rs`) only covers commands sent to it directly
+//! over the WebSocket.
+
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use super::{unix_now, TABLE};
+use crate::di::StorageProvider;
+use crate::models::command::{CommandKind, CommandStatus, RobotCommand};
+
+/// Time to confirm a dangerous command, then for the robot to fetch it (s).
+pub const CONFIRMATION_WINDOW_SECS: u64 = 60;
+
+const COMMAND_ENTITY: &str = "command";
+
+/// Result of a confirmation attempt.
+pub enum Confirmation {
+    Confirmed(Box<RobotCommand>),
+    NotFound,
+    /// The command is routine or was already confirmed.
+    NotAwaiting,
+    /// The confirming user is the one who issued it.
+    SameUser,
+    Expired,
+}
+
+pub struct CommandService {
+    storage: Arc<dyn StorageProvider>,
+}
+
+impl CommandService {
+    pub fn new(storage: Arc<dyn StorageProvider>) -> Self {
+        Self { storage }
+    }
+
+    /// Record a command: queued if routine, awaiting confirmation if
+    /// dangerous.
+    pub async fn issue(
+        &self,
+        tenant_id: &str,
+        robot_id: &str,
+        user_id: &str,
+        command: CommandKind,
+    ) -> anyhow::Result<RobotCommand> {
+        let now = unix_now();
+        let dangerous = command.is_dangerous();
+        let cmd = RobotCommand {
+            command_id: Uuid::new_v4().to_string(),
+            robot_id: robot_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            command,
+            status: if dangerous {
+                CommandStatus::AwaitingConfirmation
+            } else {
+                CommandStatus::Queued
+            },
+            issued_by: user_id.to_string(),
+            issued_at: now,
+            expires_at: dangerous.then_some(now + CONFIRMATION_WINDOW_SECS),
+            confirmed_by: None,
+            confirmed_at: None,
+            delivered_at: None,
+        };
+        self.put(&cmd).await?;
+        Ok(cmd)
+    }
+
+    /// Confirm a dangerous command, queuing it for the robot.
+    pub async fn confirm(
+        &self,
+        tenant_id: &str,
+        robot_id: &str,
+        command_id: &str,
+        user_id: &str,
+    ) -> anyhow::Result<Confirmation> {
+        let Some(mut cmd) = self.get(tenant_id, robot_id, command_id).await? else {
+            return Ok(Confirmation::NotFound);
+        };
+        if cmd.status != CommandStatus::AwaitingConfirmation {
+            return Ok(Confirmation::NotAwaiting);
+        }
+        if cmd.issued_by == user_id {
+            return Ok(Confirmation::SameUser);
+        }
+        let now = unix_now();
+        if cmd.expires_at.is_some_and(|t| now > t) {
+            cmd.status = CommandStatus::Expired;
+            self.put(&cmd).await?;
+            return Ok(Confirmation::Expired);
+        }
+        cmd.status = CommandStatus::Queued;
+        cmd.confirmed_by = Some(user_id.to_string());
+        cmd.confirmed_at = Some(now);
+        cmd.expires_at = Some(now + CONFIRMATION_WINDOW_SECS);
+        self.put(&cmd).await?;
+        Ok(Confirmation::Confirmed(Box::new(cmd)))
+    }
+
+    pub async fn get(&self, tenant_id: &str, robot_id: &str, command_id: &str) -> anyhow::Result<Option<RobotCommand>> {
+        match self.storage.get_item(TABLE, &command_key(robot_id, command_id), tenant_id).await? {
+            Some(item) => Ok(Some(serde_json::from_value(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Hand the robot its queued commands, oldest first, marking them
+    /// delivered. Commands past their deadline are expired instead.
+    pub async fn take_pending(&self, tenant_id: &str, robot_id: &str) -> anyhow::Result<Vec<RobotCommand>> {
+        let now = unix_now();
+        let mut open: Vec<RobotCommand> = self
+            .storage
+            .query_by_tenant(TABLE, tenant_id)
+            .await?
+            .into_iter()
+            .filter(|item| item["entity"] == COMMAND_ENTITY && item["robot_id"] == robot_id)
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<RobotCommand>, _>>()?
+            .into_iter()
+            .filter(|c| matches!(c.status, CommandStatus::Queued | CommandStatus::AwaitingConfirmation))
+            .collect();
+        open.sort_by_key(|c| c.issued_at);
+
+        let mut pending = Vec::new();
+        for mut cmd in open {
+            if cmd.expires_at.is_some_and(|t| now > t) {
+                tracing::info!(command_id = %cmd.command_id, status = ?cmd.status, "Command expired");
+                cmd.status = CommandStatus::Expired;
+                self.put(&cmd).await?;
+            } else if cmd.status == CommandStatus::Queued {
+                cmd.status = CommandStatus::Delivered;
+                cmd.delivered_at = Some(now);
+                self.put(&cmd).await?;
+                pending.push(cmd);
+            }
+        }
+        Ok(pending)
+    }
+
+    async fn put(&self, cmd: &RobotCommand) -> anyhow::Result<()> {
+        let mut item = serde_json::to_value(cmd)?;
+        item["pk"] = command_key(&cmd.robot_id, &cmd.command_id).into();
+        item["entity"] = COMMAND_ENTITY.into();
+        self.storage.put_item(TABLE, item).await
+    }
+}
+
+pub fn command_key(robot_id: &str, command_id: &str) -> String {
+    format!("ROBOT#{}#COMMAND#{}", robot_id, command_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::di::memory::InMemoryStorage;
+
+    const TENANT: &str = "tenant-1";
+    const ROBOT: &str = "duck-1";
+
+    async fn issue_torque_off(service: &CommandService) -> RobotCommand {
+        let cmd = service
+            .issue(TENANT, ROBOT, "alice", CommandKind::TorqueOff)
+            .await
+            .unwrap();
+        assert_eq!(cmd.status, CommandStatus::AwaitingConfirmation);
+        cmd
+    }
+
+    #[tokio::test]
+    async fn test_issuer_cannot_confirm() {
+        let service = CommandService::new(Arc::new(InMemoryStorage::new()));
+        let cmd = issue_torque_off(&service).await;
+
+        let result = service.confirm(TENANT, ROBOT, &cmd.command_id, "alice").await.unwrap();
+        assert!(matches!(result, Confirmation::SameUser));
+        assert!(service.take_pending(TENANT, ROBOT).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_other_user_confirms() {
+        let service = CommandService::new(Arc::new(InMemoryStorage::new()));
+        let cmd = issue_torque_off(&service).await;
+
+        let result = service.confirm(TENANT, ROBOT, &cmd.command_id, "bob").await.unwrap();
+        let Confirmation::Confirmed(confirmed) = result else {
+            panic!("confirmation by another user refused");
+        };
+        assert_eq!(confirmed.confirmed_by.as_deref(), Some("bob"));
+
+        let pending = service.take_pending(TENANT, ROBOT).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].status, CommandStatus::Delivered);
+        // Delivered once only
+        assert!(service.take_pending(TENANT, ROBOT).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_after_window_expires() {
+        let service = CommandService::new(Arc::new(InMemoryStorage::new()));
+        let mut cmd = issue_torque_off(&service).await;
+        // Issued more than a window ago
+        cmd.issued_at -= CONFIRMATION_WINDOW_SECS + 1;
+        cmd.expires_at = Some(cmd.issued_at + CONFIRMATION_WINDOW_SECS);
+        service.put(&cmd).await.unwrap();
+
+        let result = service.confirm(TENANT, ROBOT, &cmd.command_id, "bob").await.unwrap();
+        assert!(matches!(result, Confirmation::Expired));
+        let stored = service.get(TENANT, ROBOT, &cmd.command_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, CommandStatus::Expired);
+        assert!(service.take_pending(TENANT, ROBOT).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,392 @@
+//! Fleet service — site metadata, robot heartbeats, and topology queries.
+//!
+//! Entities share the single table and are told apart by an `entity` field;
+//! every read and write is scoped to the caller's tenant.
+//!
+//! Deleting a robot only tombstones it: it drops out of the fleet, but it
+//! and every record keyed under it (black box dumps, commands) are kept for
+//! the retention window and come back when it is restored. They are given a
+//! storage TTL at the end of the window, so the purge itself is left to the
+//! storage.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::{unix_now, TABLE};
+use crate::di::{StorageProvider, TTL_ATTRIBUTE};
+use crate::models::fleet::{
+    AggregateHealth, FleetTopology, HeartbeatRequest, Robot, RobotHealth, RobotSummary, Site,
+    SiteGroup, UpsertSiteRequest,
+};
+
+/// Robots without a heartbeat for this long are reported offline.
+pub const OFFLINE_AFTER_SECS: u64 = 120;
+
+/// How long deleted robots can be restored before they are purged.
+pub const DEFAULT_RETENTION_SECS: u64 = 30 * 24 * 3600;
+
+const SITE_ENTITY: &str = "site";
+const ROBOT_ENTITY: &str = "robot";
+
+/// Result of a restore attempt.
+pub enum Restore {
+    Restored(Box<Robot>),
+    /// Never existed, or already purged.
+    NotFound,
+    NotDeleted,
+}
+
+pub struct FleetService {
+    storage: Arc<dyn StorageProvider>,
+    retention_secs: u64,
+}
+
+impl FleetService {
+    pub fn new(storage: Arc<dyn StorageProvider>) -> Self {
+        Self {
+            storage,
+            retention_secs: DEFAULT_RETENTION_SECS,
+        }
+    }
+
+    /// Keep deleted robots restorable for `secs` instead of the default.
+    pub fn with_retention(mut self, secs: u64) -> Self {
+        self.retention_secs = secs;
+        self
+    }
+
+    pub async fn upsert_site(
+        &self,
+        tenant_id: &str,
+        site_id: &str,
+        req: UpsertSiteRequest,
+    ) -> anyhow::Result<Site> {
+        let site = Site {
+            site_id: site_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            name: req.name,
+            location: req.location,
+            latitude: req.latitude,
+            longitude: req.longitude,
+            timezone: req.timezone,
+        };
+        self.put(SITE_ENTITY, &site_key(site_id), &site).await?;
+        Ok(site)
+    }
+
+    pub async fn list_sites(&self, tenant_id: &str) -> anyhow::Result<Vec<Site>> {
+        self.list(tenant_id, SITE_ENTITY).await
+    }
+
+    /// A live robot; deleted robots are `None`.
+    pub async fn get_robot(&self, tenant_id: &str, robot_id: &str) -> anyhow::Result<Option<Robot>> {
+        let robot = self.get_robot_record(tenant_id, robot_id).await?;
+        Ok(robot.filter(|robot| robot.deleted_at.is_none()))
+    }
+
+    /// Tombstone a robot and its records until the retention window runs
+    /// out. Returns `None` if the robot is unknown; deleting again is a no-op.
+    pub async fn delete_robot(&self, tenant_id: &str, robot_id: &str) -> anyhow::Result<Option<Robot>> {
+        let Some(mut robot) = self.get_robot_record(tenant_id, robot_id).await? else {
+            return Ok(None);
+        };
+        if robot.deleted_at.is_some() {
+            return Ok(Some(robot));
+        }
+
+        let now = unix_now();
+        let purge_at = now.saturating_add(self.retention_secs);
+        self.set_records_ttl(tenant_id, robot_id, Some(purge_at)).await?;
+        robot.deleted_at = Some(now);
+        robot.purge_at = Some(purge_at);
+        self.put_with_ttl(ROBOT_ENTITY, &robot_key(robot_id), &robot, Some(purge_at))
+            .await?;
+        Ok(Some(robot))
+    }
+
+    /// Bring a deleted robot and its records back.
+    pub async fn restore_robot(&self, tenant_id: &str, robot_id: &str) -> anyhow::Result<Restore> {
+        let Some(mut robot) = self.get_robot_record(tenant_id, robot_id).await? else {
+            return Ok(Restore::NotFound);
+        };
+        if robot.deleted_at.is_none() {
+            return Ok(Restore::NotDeleted);
+        }
+
+        self.set_records_ttl(tenant_id, robot_id, None).await?;
+        robot.deleted_at = None;
+        robot.purge_at = None;
+        self.put(ROBOT_ENTITY, &robot_key(robot_id), &robot).await?;
+        Ok(Restore::Restored(Box::new(robot)))
+    }
+
+    /// Deleted robots that can still be restored, soonest purged first.
+    pub async fn list_deleted_robots(&self, tenant_id: &str) -> anyhow::Result<Vec<Robot>> {
+        let now = unix_now();
+        let mut robots: Vec<Robot> = self
+            .list::<Robot>(tenant_id, ROBOT_ENTITY)
+            .await?
+            .into_iter()
+            .filter(|robot| robot.deleted_at.is_some() && !purged(robot, now))
+            .collect();
+        robots.sort_by_key(|robot| robot.purge_at);
+        Ok(robots)
+    }
+
+    /// Assign a robot to a site. Returns `None` if the robot or site is unknown.
+    pub async fn assign_site(
+        &self,
+        tenant_id: &str,
+        robot_id: &str,
+        site_id: Option<String>,
+    ) -> anyhow::Result<Option<Robot>> {
+        let Some(mut robot) = self.get_robot(tenant_id, robot_id).await? else {
+            return Ok(None);
+        };
+        if let Some(ref id) = site_id {
+            if self.get::<Site>(tenant_id, &site_key(id)).await?.is_none() {
+                return Ok(None);
+            }
+        }
+
+        robot.site_id = site_id;
+        self.put(ROBOT_ENTITY, &robot_key(robot_id), &robot).await?;
+        Ok(Some(robot))
+    }
+
+    /// Record a heartbeat, registering the robot on first contact. Returns
+    /// `None` for a deleted robot, which stays deleted until restored.
+    pub async fn record_heartbeat(
+        &self,
+        tenant_id: &str,
+        robot_id: &str,
+        req: HeartbeatRequest,
+    ) -> anyhow::Result<Option<Robot>> {
+        let existing = self.get_robot_record(tenant_id, robot_id).await?;
+        if existing.as_ref().is_some_and(|robot| robot.deleted_at.is_some()) {
+            return Ok(None);
+        }
+        let mut robot = existing.unwrap_or_else(|| Robot {
+            robot_id: robot_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            name: robot_id.to_string(),
+            site_id: None,
+            last_heartbeat: None,
+            reported_health: None,
+            runtime_health: None,
+            deleted_at: None,
+            purge_at: None,
+        });
+
+        if let Some(name) = req.name {
+            robot.name = name;
+        }
+        robot.last_heartbeat = Some(unix_now());
+        robot.reported_health = Some(req.health);
+        robot.runtime_health = req.runtime;
+
+        self.put(ROBOT_ENTITY, &robot_key(robot_id), &robot).await?;
+        Ok(Some(robot))
+    }
+
+    /// Fleet composition grouped by site, with aggregate health per site.
+    pub async fn topology(&self, tenant_id: &str) -> anyhow::Result<FleetTopology> {
+        let sites = self.list_sites(tenant_id).await?;
+        let robots: Vec<Robot> = self.list(tenant_id, ROBOT_ENTITY).await?;
+        let now = unix_now();
+
+        let mut groups: BTreeMap<Option<String>, SiteGroup> = sites
+            .into_iter()
+            .map(|site| {
+                let group = SiteGroup {
+                    site: Some(site.clone()),
+                    health: AggregateHealth::default(),
+                    robots: Vec::new(),
+                };
+                (Some(site.site_id), group)
+            })
+            .collect();
+
+        let mut fleet_health = AggregateHealth::default();
+
+        for robot in robots.into_iter().filter(|robot| robot.deleted_at.is_none()) {
+            let health = effective_health(&robot, now);
+            fleet_health.add(health);
+
+            // Robots pointing at a deleted site fall back to "unassigned"
+            let key = robot.site_id.clone().filter(|id| groups.contains_key(&Some(id.clone())));
+            let group = groups.entry(key).or_insert_with(|| SiteGroup {
+                site: None,
+                health: AggregateHealth::default(),
+                robots: Vec::new(),
+            });
+            group.health.add(health);
+            group.robots.push(RobotSummary {
+                robot_id: robot.robot_id,
+                name: robot.name,
+                health,
+                last_heartbeat: robot.last_heartbeat,
+                runtime_health: robot.runtime_health,
+            });
+        }
+
+        Ok(FleetTopology {
+            health: fleet_health,
+            sites: groups.into_values().collect(),
+        })
+    }
+
+    /// A robot, deleted or not, unless it has been purged.
+    async fn get_robot_record(&self, tenant_id: &str, robot_id: &str) -> anyhow::Result<Option<Robot>> {
+        let robot: Option<Robot> = self.get(tenant_id, &robot_key(robot_id)).await?;
+        Ok(robot.filter(|robot| !purged(robot, unix_now())))
+    }
+
+    /// Set (or clear) the storage TTL of every record keyed under a robot.
+    async fn set_records_ttl(&self, tenant_id: &str, robot_id: &str, ttl: Option<u64>) -> anyhow::Result<()> {
+        let prefix = format!("{}#", robot_key(robot_id));
+        for mut item in self.storage.query_by_tenant(TABLE, tenant_id).await? {
+            if !item["pk"].as_str().is_some_and(|pk| pk.starts_with(&prefix)) {
+                continue;
+            }
+            match ttl {
+                Some(ttl) => item[TTL_ATTRIBUTE] = ttl.into(),
+                None => {
+                    if let Some(fields) = item.as_object_mut() {
+                        fields.remove(TTL_ATTRIBUTE);
+                    }
+                }
+            }
+            self.storage.put_item(TABLE, item).await?;
+        }
+        Ok(())
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        tenant_id: &str,
+        key: &str,
+    ) -> anyhow::Result<Option<T>> {
+        match self.storage.get_item(TABLE, key, tenant_id).await? {
+            Some(item) => Ok(Some(serde_json::from_value(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn put<T: serde::Serialize>(&self, entity: &str, key: &str, value: &T) -> anyhow::Result<()> {
+        self.put_with_ttl(entity, key, value, None).await
+    }
+
+    async fn put_with_ttl<T: serde::Serialize>(
+        &self,
+        entity: &str,
+        key: &str,
+        value: &T,
+        ttl: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let mut item = serde_json::to_value(value)?;
+        item["pk"] = key.into();
+        item["entity"] = entity.into();
+        if let Some(ttl) = ttl {
+            item[TTL_ATTRIBUTE] = ttl.into();
+        }
+        self.storage.put_item(TABLE, item).await
+    }
+
+    async fn list<T: serde::de::DeserializeOwned>(
+        &self,
+        tenant_id: &str,
+        entity: &str,
+    ) -> anyhow::Result<Vec<T>> {
+        self.storage
+            .query_by_tenant(TABLE, tenant_id)
+            .await?
+            .into_iter()
+            .filter(|item| item["entity"] == entity)
+            .map(|item| Ok(serde_json::from_value(item)?))
+            .collect()
+    }
+}
+
+/// Reported health, overridden by `Offline` when heartbeats have stopped.
+pub fn effective_health(robot: &Robot, now: u64) -> RobotHealth {
+    match (robot.last_heartbeat, robot.reported_health) {
+        (Some(at), Some(health)) if now.saturating_sub(at) <= OFFLINE_AFTER_SECS => health,
+        _ => RobotHealth::Offline,
+    }
+}
+
+/// Whether a deleted robot's retention window has run out (the storage may
+/// not have deleted it yet).
+fn purged(robot: &Robot, now: u64) -> bool {
+    robot.purge_at.is_some_and(|at| at <= now)
+}
+
+pub fn site_key(site_id: &str) -> String {
+    format!("SITE#{}", site_id)
+}
+
+pub fn robot_key(robot_id: &str) -> String {
+    format!("ROBOT#{}", robot_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::di::memory::InMemoryStorage;
+
+    const TENANT: &str = "tenant-1";
+    const ROBOT: &str = "duck-1";
+
+    fn heartbeat() -> HeartbeatRequest {
+        HeartbeatRequest {
+            name: None,
+            health: RobotHealth::Healthy,
+            runtime: None,
+            metrics: None,
+        }
+    }
+
+    async fn deleted_robot(fleet: &FleetService) -> Robot {
+        fleet.record_heartbeat(TENANT, ROBOT, heartbeat()).await.unwrap().unwrap();
+        fleet.delete_robot(TENANT, ROBOT).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_restore_before_purge() {
+        let fleet = FleetService::new(Arc::new(InMemoryStorage::new()));
+        let deleted = deleted_robot(&fleet).await;
+        assert!(deleted.purge_at.is_some_and(|at| at > unix_now()));
+        assert!(fleet.get_robot(TENANT, ROBOT).await.unwrap().is_none());
+        assert_eq!(fleet.list_deleted_robots(TENANT).await.unwrap().len(), 1);
+
+        let Restore::Restored(robot) = fleet.restore_robot(TENANT, ROBOT).await.unwrap() else {
+            panic!("robot not restored within its retention window");
+        };
+        assert_eq!(robot.deleted_at, None);
+        assert_eq!(robot.purge_at, None);
+        assert!(fleet.get_robot(TENANT, ROBOT).await.unwrap().is_some());
+        assert!(fleet.list_deleted_robots(TENANT).await.unwrap().is_empty());
+        assert!(matches!(fleet.restore_robot(TENANT, ROBOT).await.unwrap(), Restore::NotDeleted));
+    }
+
+    #[tokio::test]
+    async fn test_restore_after_purge() {
+        // No retention: the robot is purged as soon as it is deleted
+        let fleet = FleetService::new(Arc::new(InMemoryStorage::new())).with_retention(0);
+        deleted_robot(&fleet).await;
+
+        assert!(matches!(fleet.restore_robot(TENANT, ROBOT).await.unwrap(), Restore::NotFound));
+        assert!(fleet.list_deleted_robots(TENANT).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_deleted_robot_heartbeat_refused() {
+        let fleet = FleetService::new(Arc::new(InMemoryStorage::new()));
+        deleted_robot(&fleet).await;
+
+        assert!(fleet.record_heartbeat(TENANT, ROBOT, heartbeat()).await.unwrap().is_none());
+        assert!(fleet.get_robot(TENANT, ROBOT).await.unwrap().is_none());
+        assert!(fleet.topology(TENANT).await.unwrap().sites.is_empty());
+    }
+}
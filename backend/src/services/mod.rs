@@ -1 +1,21 @@
 // openduckrust — services
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod blackbox;
+pub mod commands;
+pub mod fleet;
+pub mod notifications;
+pub mod policy_metrics;
+pub mod usage;
+
+/// Single-table name for all platform entities.
+pub const TABLE: &str = "openduckrust";
+
+/// Seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,228 @@
+//! Notification service — routes alerts and deployment failures to each
+//! tenant's configured channels.
+//!
+//! Channels are chosen by tenants but used with the backend's network access
+//! and AWS role, so they are checked both when saved and before each
+//! delivery: Slack webhooks must be on `hooks.slack.com`, SNS topics must be
+//! named with the deployment's `SNS_TOPIC_ARN_PREFIX` followed by the tenant
+//! id, and email goes to at most `MAX_EMAIL_RECIPIENTS` valid addresses.
+//! Delivery results only say whether a channel failed; the transport error
+//! is logged, not returned.
+
+use std::sync::Arc;
+
+use super::TABLE;
+use crate::di::notifier::{SlackNotifier, SmtpNotifier, SmtpSettings, SnsNotifier};
+use crate::di::{Notifier, StorageProvider};
+use crate::models::notification::{
+    DeliveryResult, Notification, NotificationChannel, NotificationConfig, NotificationKind,
+};
+
+const CONFIG_KEY: &str = "NOTIFICATION_CONFIG";
+const CONFIG_ENTITY: &str = "notification_config";
+
+/// Only Slack incoming webhooks are posted to.
+const SLACK_WEBHOOK_PREFIX: &str = "https://hooks.slack.com/";
+
+/// Most recipients one email channel may list.
+pub const MAX_EMAIL_RECIPIENTS: usize = 10;
+
+/// What delivery results say about a failed send.
+const DELIVERY_FAILED: &str = "delivery failed";
+
+pub struct NotificationService {
+    storage: Arc<dyn StorageProvider>,
+    sns: Option<aws_sdk_sns::Client>,
+    /// ARN prefix of the deployment's notification topics (account, region
+    /// and name prefix); SNS channels are refused without one.
+    sns_topic_prefix: Option<String>,
+    smtp: Option<SmtpSettings>,
+    http: reqwest::Client,
+}
+
+impl NotificationService {
+    pub fn new(
+        storage: Arc<dyn StorageProvider>,
+        sns: Option<aws_sdk_sns::Client>,
+        sns_topic_prefix: Option<String>,
+        smtp: Option<SmtpSettings>,
+    ) -> Self {
+        Self {
+            storage,
+            sns,
+            sns_topic_prefix,
+            smtp,
+            // A webhook must not bounce the request on to another host
+            http: reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Why a tenant may not use this config, if it may not.
+    pub fn validate(&self, tenant_id: &str, config: &NotificationConfig) -> Result<(), String> {
+        config
+            .channels
+            .iter()
+            .try_for_each(|channel| validate_channel(channel, tenant_id, self.sns_topic_prefix.as_deref()))
+    }
+
+    pub async fn config(&self, tenant_id: &str) -> anyhow::Result<NotificationConfig> {
+        match self.storage.get_item(TABLE, CONFIG_KEY, tenant_id).await? {
+            Some(item) => Ok(serde_json::from_value(item)?),
+            None => Ok(NotificationConfig::default()),
+        }
+    }
+
+    pub async fn set_config(&self, tenant_id: &str, config: &NotificationConfig) -> anyhow::Result<()> {
+        let mut item = serde_json::to_value(config)?;
+        item["pk"] = CONFIG_KEY.into();
+        item["tenant_id"] = tenant_id.into();
+        item["entity"] = CONFIG_ENTITY.into();
+        self.storage.put_item(TABLE, item).await
+    }
+
+    /// Deliver a notification on every channel of the tenant. Failures on one
+    /// channel do not stop delivery on the others.
+    pub async fn notify(&self, tenant_id: &str, notification: &Notification) -> anyhow::Result<Vec<DeliveryResult>> {
+        let config = self.config(tenant_id).await?;
+
+        if notification.kind != NotificationKind::Test && notification.severity < config.min_severity {
+            return Ok(Vec::new());
+        }
+
+        let mut results = Vec::with_capacity(config.channels.len());
+        for channel in &config.channels {
+            // Refusals are the backend's own words; transport errors stay in the logs
+            let (name, error) = match self.notifier(tenant_id, channel) {
+                Ok(notifier) => match notifier.send(notification).await {
+                    Ok(()) => (notifier.name(), None),
+                    Err(e) => {
+                        tracing::error!(tenant_id = %tenant_id, channel = %notifier.name(), "Notification delivery failed: {}", e);
+                        (notifier.name(), Some(DELIVERY_FAILED.to_string()))
+                    }
+                },
+                Err(e) => {
+                    tracing::error!(tenant_id = %tenant_id, channel = channel_label(channel), "Notification channel refused: {}", e);
+                    (channel_label(channel).to_string(), Some(e.to_string()))
+                }
+            };
+            results.push(DeliveryResult {
+                channel: name,
+                ok: error.is_none(),
+                error,
+            });
+        }
+
+        Ok(results)
+    }
+
+    fn notifier(&self, tenant_id: &str, channel: &NotificationChannel) -> anyhow::Result<Box<dyn Notifier>> {
+        // Configs saved before these checks existed are held to them too
+        validate_channel(channel, tenant_id, self.sns_topic_prefix.as_deref()).map_err(anyhow::Error::msg)?;
+        Ok(match channel {
+            NotificationChannel::Sns { topic_arn } => {
+                let client = self
+                    .sns
+                    .clone()
+                    .ok_or_else(|| anyhow::anyhow!("SNS is not configured on this deployment"))?;
+                Box::new(SnsNotifier::new(client, topic_arn.clone()))
+            }
+            NotificationChannel::Email { to } => {
+                let smtp = self
+                    .smtp
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("SMTP is not configured on this deployment"))?;
+                Box::new(SmtpNotifier::new(smtp, to)?)
+            }
+            NotificationChannel::Slack { webhook_url } => {
+                Box::new(SlackNotifier::new(self.http.clone(), webhook_url.clone()))
+            }
+        })
+    }
+}
+
+/// Why a tenant may not deliver to this channel, if it may not.
+pub fn validate_channel(channel: &NotificationChannel, tenant_id: &str, sns_topic_prefix: Option<&str>) -> Result<(), String> {
+    match channel {
+        NotificationChannel::Slack { webhook_url } => {
+            if !webhook_url.starts_with(SLACK_WEBHOOK_PREFIX) {
+                return Err(format!("Slack webhooks must start with {}", SLACK_WEBHOOK_PREFIX));
+            }
+        }
+        NotificationChannel::Sns { topic_arn } => {
+            let Some(prefix) = sns_topic_prefix else {
+                return Err("SNS topics are not enabled on this deployment".to_string());
+            };
+            // `<prefix><tenant>` or `<prefix><tenant>-...`, so tenant "acme"
+            // cannot reach the topics of "acme2"
+            let tenant_topic = format!("{}{}", prefix, tenant_id);
+            let ours = topic_arn
+                .strip_prefix(&tenant_topic)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'));
+            if !ours {
+                return Err(format!("SNS topics must be {0} or start with {0}-", tenant_topic));
+            }
+        }
+        NotificationChannel::Email { to } => {
+            if to.is_empty() || to.len() > MAX_EMAIL_RECIPIENTS {
+                return Err(format!("email channels take 1 to {} recipients", MAX_EMAIL_RECIPIENTS));
+            }
+            if let Some(bad) = to.iter().find(|addr| addr.parse::<lettre::Address>().is_err()) {
+                return Err(format!("invalid email address {:?}", bad));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn channel_label(channel: &NotificationChannel) -> &'static str {
+    match channel {
+        NotificationChannel::Sns { .. } => "sns",
+        NotificationChannel::Email { .. } => "email",
+        NotificationChannel::Slack { .. } => "slack",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREFIX: &str = "arn:aws:sns:us-east-1:123456789012:odr-";
+
+    fn check(channel: NotificationChannel) -> Result<(), String> {
+        validate_channel(&channel, "acme", Some(PREFIX))
+    }
+
+    #[test]
+    fn test_slack_webhooks_only_on_slack() {
+        let slack = |url: &str| NotificationChannel::Slack { webhook_url: url.to_string() };
+        assert!(check(slack("https://hooks.slack.com/services/T0/B0/x")).is_ok());
+        assert!(check(slack("http://hooks.slack.com/services/T0/B0/x")).is_err());
+        assert!(check(slack("https://hooks.slack.com.evil.example/x")).is_err());
+        assert!(check(slack("http://169.254.169.254/latest/meta-data/")).is_err());
+    }
+
+    #[test]
+    fn test_sns_topics_scoped_to_tenant() {
+        let sns = |arn: &str| NotificationChannel::Sns { topic_arn: arn.to_string() };
+        assert!(check(sns(&format!("{}acme-alerts", PREFIX))).is_ok());
+        assert!(check(sns(&format!("{}other-alerts", PREFIX))).is_err());
+        assert!(check(sns(&format!("{}acme2-alerts", PREFIX))).is_err());
+        assert!(check(sns("arn:aws:sns:us-east-1:999999999999:odr-acme-alerts")).is_err());
+        // Disabled without a deployment prefix
+        let channel = sns(&format!("{}acme-alerts", PREFIX));
+        assert!(validate_channel(&channel, "acme", None).is_err());
+    }
+
+    #[test]
+    fn test_email_recipients_capped_and_valid() {
+        let email = |to: Vec<String>| NotificationChannel::Email { to };
+        assert!(check(email(vec!["ops@example.com".to_string()])).is_ok());
+        assert!(check(email(Vec::new())).is_err());
+        assert!(check(email(vec!["not an address".to_string()])).is_err());
+        let many = (0..=MAX_EMAIL_RECIPIENTS).map(|i| format!("ops{}@example.com", i)).collect();
+        assert!(check(email(many)).is_err());
+    }
+}
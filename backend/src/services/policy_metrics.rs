@@ -0,0 +1,169 @@
+//! Policy metrics service — long-term rollups of how each policy version
+//! performs across the fleet.
+//!
+//! Heartbeats carry counters accumulated since the policy started running.
+//! The last counters seen are kept per robot; each heartbeat adds the
+//! difference to that robot's rollup for the policy and UTC day, so no
+//! robot-side state is lost when heartbeats are dropped, and a restart or
+//! policy swap (counters going back to zero) starts from the new values.
+//! Rollups are kept after their robot is deleted, so release comparisons
+//! still cover retired robots.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use super::fleet::robot_key;
+use super::usage::utc_date;
+use super::{unix_now, TABLE};
+use crate::di::StorageProvider;
+use crate::models::policy_metrics::{PolicyComparison, PolicyCounters, PolicyMetrics, PolicyRollup};
+
+const COUNTERS_ENTITY: &str = "policy_counters";
+const ROLLUP_ENTITY: &str = "policy_rollup";
+
+pub struct PolicyMetricsService {
+    storage: Arc<dyn StorageProvider>,
+    // Serializes read-modify-write of rollups within this process.
+    write_lock: Mutex<()>,
+}
+
+impl PolicyMetricsService {
+    pub fn new(storage: Arc<dyn StorageProvider>) -> Self {
+        Self {
+            storage,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Add what a heartbeat's counters grew by since the robot's last one.
+    pub async fn record(
+        &self,
+        tenant_id: &str,
+        robot_id: &str,
+        counters: &PolicyCounters,
+        loop_margin: Option<f64>,
+    ) -> anyhow::Result<()> {
+        let _guard = self.write_lock.lock().await;
+
+        let counters_key = counters_key(robot_id);
+        let previous: Option<PolicyCounters> = self.get(tenant_id, &counters_key).await?;
+        let delta = counters_delta(previous.as_ref(), counters);
+
+        let day = utc_date(unix_now());
+        let key = rollup_key(&counters.policy, &day, robot_id);
+        let mut rollup: PolicyRollup = self.get(tenant_id, &key).await?.unwrap_or_else(|| PolicyRollup {
+            tenant_id: tenant_id.to_string(),
+            policy: counters.policy.clone(),
+            robot_id: robot_id.to_string(),
+            day,
+            ..PolicyRollup::default()
+        });
+        rollup.active_secs += delta.active_secs;
+        rollup.falls += delta.falls;
+        rollup.distance_m += delta.distance_m;
+        rollup.energy_wh += delta.energy_wh;
+        if let Some(margin) = loop_margin.filter(|m| m.is_finite()) {
+            rollup.loop_margin_sum += margin;
+            rollup.loop_margin_samples += 1;
+        }
+
+        self.put(tenant_id, ROLLUP_ENTITY, &key, &rollup).await?;
+        self.put(tenant_id, COUNTERS_ENTITY, &counters_key, counters).await
+    }
+
+    /// Every policy version that ran in the last `days` days, across the
+    /// tenant's fleet.
+    pub async fn compare(&self, tenant_id: &str, days: u32) -> anyhow::Result<PolicyComparison> {
+        let since = utc_date(unix_now().saturating_sub(u64::from(days.saturating_sub(1)) * 86_400));
+        let rollups: Vec<PolicyRollup> = self
+            .storage
+            .query_by_tenant(TABLE, tenant_id)
+            .await?
+            .into_iter()
+            .filter(|item| item["entity"] == ROLLUP_ENTITY)
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()?;
+
+        let mut by_policy: BTreeMap<&str, Vec<&PolicyRollup>> = BTreeMap::new();
+        for rollup in rollups.iter().filter(|r| r.day >= since) {
+            by_policy.entry(&rollup.policy).or_default().push(rollup);
+        }
+        let mut policies: Vec<PolicyMetrics> = by_policy.into_values().map(|r| summarize(&r)).collect();
+        policies.sort_by(|a, b| (&a.first_seen, &a.policy).cmp(&(&b.first_seen, &b.policy)));
+
+        Ok(PolicyComparison { since, policies })
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, tenant_id: &str, key: &str) -> anyhow::Result<Option<T>> {
+        match self.storage.get_item(TABLE, key, tenant_id).await? {
+            Some(item) => Ok(Some(serde_json::from_value(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn put<T: serde::Serialize>(&self, tenant_id: &str, entity: &str, key: &str, value: &T) -> anyhow::Result<()> {
+        let mut item = serde_json::to_value(value)?;
+        item["pk"] = key.into();
+        item["tenant_id"] = tenant_id.into();
+        item["entity"] = entity.into();
+        self.storage.put_item(TABLE, item).await
+    }
+}
+
+/// What the counters grew by. Counters for another policy, or lower than
+/// before, were restarted and count in full.
+fn counters_delta(previous: Option<&PolicyCounters>, current: &PolicyCounters) -> PolicyCounters {
+    let restarted = previous.is_none_or(|p| {
+        p.policy != current.policy
+            || current.active_secs < p.active_secs
+            || current.falls < p.falls
+            || current.distance_m < p.distance_m
+            || current.energy_wh < p.energy_wh
+    });
+    match previous {
+        Some(p) if !restarted => PolicyCounters {
+            policy: current.policy.clone(),
+            active_secs: current.active_secs - p.active_secs,
+            falls: current.falls - p.falls,
+            distance_m: current.distance_m - p.distance_m,
+            energy_wh: current.energy_wh - p.energy_wh,
+        },
+        _ => current.clone(),
+    }
+}
+
+/// Fleet totals and rates of one policy's rollups.
+fn summarize(rollups: &[&PolicyRollup]) -> PolicyMetrics {
+    let active_secs: f64 = rollups.iter().map(|r| r.active_secs).sum();
+    let falls: u64 = rollups.iter().map(|r| r.falls).sum();
+    let distance_m: f64 = rollups.iter().map(|r| r.distance_m).sum();
+    let energy_wh: f64 = rollups.iter().map(|r| r.energy_wh).sum();
+    let margin_sum: f64 = rollups.iter().map(|r| r.loop_margin_sum).sum();
+    let margin_samples: u64 = rollups.iter().map(|r| r.loop_margin_samples).sum();
+    let robots: BTreeSet<&str> = rollups.iter().map(|r| r.robot_id.as_str()).collect();
+    let active_hours = active_secs / 3600.0;
+
+    PolicyMetrics {
+        policy: rollups[0].policy.clone(),
+        first_seen: rollups.iter().map(|r| r.day.clone()).min().unwrap_or_default(),
+        last_seen: rollups.iter().map(|r| r.day.clone()).max().unwrap_or_default(),
+        robots: robots.len(),
+        active_hours,
+        falls,
+        distance_m,
+        energy_wh,
+        falls_per_hour: (active_hours > 0.0).then(|| falls as f64 / active_hours),
+        mean_loop_margin: (margin_samples > 0).then(|| margin_sum / margin_samples as f64),
+        energy_per_meter: (distance_m > 0.0).then(|| energy_wh / distance_m),
+    }
+}
+
+fn counters_key(robot_id: &str) -> String {
+    format!("{}#POLICY_COUNTERS", robot_key(robot_id))
+}
+
+fn rollup_key(policy: &str, day: &str, robot_id: &str) -> String {
+    format!("POLICY#{}#{}#{}", policy, day, robot_id)
+}
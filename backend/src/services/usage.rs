@@ -0,0 +1,253 @@
+//! Usage metering service — per-tenant counters and quota checks.
+//!
+//! Period counters (telemetry, AI tokens, commands) live in one item per
+//! tenant and billing month; stored bytes are a running gauge that carries
+//! across periods. Quota overrides are stored per tenant, falling back to
+//! `QuotaLimits::default()`.
+
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use super::{unix_now, TABLE};
+use crate::di::StorageProvider;
+use crate::models::usage::{MetricUsage, Quota, QuotaLimits, QuotaState, TenantUsage, UsageMetric, UsageReport};
+
+const USAGE_ENTITY: &str = "usage";
+const STORAGE_ENTITY: &str = "storage_usage";
+const QUOTA_ENTITY: &str = "quota";
+
+pub struct UsageService {
+    storage: Arc<dyn StorageProvider>,
+    // Serializes read-modify-write of counters within this process.
+    // TODO: Use DynamoDB atomic ADD updates once DynamoDbProvider lands
+    write_lock: Mutex<()>,
+}
+
+impl UsageService {
+    pub fn new(storage: Arc<dyn StorageProvider>) -> Self {
+        Self {
+            storage,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Usage of the current billing period.
+    pub async fn usage(&self, tenant_id: &str) -> anyhow::Result<TenantUsage> {
+        let period = billing_period(unix_now());
+        let mut usage: TenantUsage = self
+            .get(tenant_id, &usage_key(&period))
+            .await?
+            .unwrap_or_else(|| TenantUsage {
+                tenant_id: tenant_id.to_string(),
+                period,
+                ..TenantUsage::default()
+            });
+
+        usage.storage_bytes = self
+            .get::<TenantUsage>(tenant_id, STORAGE_KEY)
+            .await?
+            .map(|gauge| gauge.storage_bytes)
+            .unwrap_or(0);
+        Ok(usage)
+    }
+
+    /// Add `amount` to a metric for the current period (or to the storage
+    /// gauge) unless that would pass its hard quota. The check and the
+    /// increment happen under one lock, so concurrent requests cannot all slip
+    /// in under the limit. Returns the quota state with `amount` added;
+    /// nothing is recorded when it is `Exceeded`.
+    pub async fn try_record(&self, tenant_id: &str, metric: UsageMetric, amount: u64) -> anyhow::Result<QuotaState> {
+        let _guard = self.write_lock.lock().await;
+
+        let state = self.check(tenant_id, metric, amount).await?;
+        if state != QuotaState::Exceeded {
+            self.adjust_locked(tenant_id, metric, |value| value.saturating_add(amount)).await?;
+        }
+        Ok(state)
+    }
+
+    /// Give back usage recorded for a request that then failed.
+    pub async fn release(&self, tenant_id: &str, metric: UsageMetric, amount: u64) -> anyhow::Result<()> {
+        self.adjust(tenant_id, metric, |value| value.saturating_sub(amount)).await
+    }
+
+    /// Subtract freed bytes from the storage gauge.
+    pub async fn release_storage(&self, tenant_id: &str, bytes: u64) -> anyhow::Result<()> {
+        self.release(tenant_id, UsageMetric::StorageBytes, bytes).await
+    }
+
+    pub async fn quotas(&self, tenant_id: &str) -> anyhow::Result<QuotaLimits> {
+        Ok(self.get(tenant_id, QUOTA_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn set_quotas(&self, tenant_id: &str, quotas: &QuotaLimits) -> anyhow::Result<()> {
+        self.put(tenant_id, QUOTA_ENTITY, QUOTA_KEY, quotas).await
+    }
+
+    /// Quota state if `amount` more of `metric` were consumed now.
+    pub async fn check(&self, tenant_id: &str, metric: UsageMetric, amount: u64) -> anyhow::Result<QuotaState> {
+        let usage = self.usage(tenant_id).await?;
+        let quotas = self.quotas(tenant_id).await?;
+        Ok(quota_state(usage.get(metric).saturating_add(amount), quotas.get(metric)))
+    }
+
+    /// Usage of every metric against its quota.
+    pub async fn report(&self, tenant_id: &str) -> anyhow::Result<UsageReport> {
+        let usage = self.usage(tenant_id).await?;
+        let quotas = self.quotas(tenant_id).await?;
+
+        let metrics = UsageMetric::ALL
+            .iter()
+            .map(|&metric| {
+                let used = usage.get(metric);
+                let quota = quotas.get(metric);
+                MetricUsage {
+                    metric,
+                    used,
+                    quota,
+                    state: quota_state(used, quota),
+                }
+            })
+            .collect();
+
+        Ok(UsageReport {
+            tenant_id: tenant_id.to_string(),
+            period: usage.period,
+            metrics,
+        })
+    }
+
+    async fn adjust(&self, tenant_id: &str, metric: UsageMetric, f: impl FnOnce(u64) -> u64) -> anyhow::Result<()> {
+        let _guard = self.write_lock.lock().await;
+        self.adjust_locked(tenant_id, metric, f).await
+    }
+
+    /// `adjust` for callers already holding `write_lock`.
+    async fn adjust_locked(&self, tenant_id: &str, metric: UsageMetric, f: impl FnOnce(u64) -> u64) -> anyhow::Result<()> {
+        let (entity, key) = match metric {
+            UsageMetric::StorageBytes => (STORAGE_ENTITY, STORAGE_KEY.to_string()),
+            _ => (USAGE_ENTITY, usage_key(&billing_period(unix_now()))),
+        };
+
+        let mut usage: TenantUsage = self.get(tenant_id, &key).await?.unwrap_or_else(|| TenantUsage {
+            tenant_id: tenant_id.to_string(),
+            period: billing_period(unix_now()),
+            ..TenantUsage::default()
+        });
+        let value = usage.get_mut(metric);
+        *value = f(*value);
+
+        self.put(tenant_id, entity, &key, &usage).await
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, tenant_id: &str, key: &str) -> anyhow::Result<Option<T>> {
+        match self.storage.get_item(TABLE, key, tenant_id).await? {
+            Some(item) => Ok(Some(serde_json::from_value(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn put<T: serde::Serialize>(&self, tenant_id: &str, entity: &str, key: &str, value: &T) -> anyhow::Result<()> {
+        let mut item = serde_json::to_value(value)?;
+        item["pk"] = key.into();
+        item["tenant_id"] = tenant_id.into();
+        item["entity"] = entity.into();
+        self.storage.put_item(TABLE, item).await
+    }
+}
+
+const STORAGE_KEY: &str = "USAGE#STORAGE";
+const QUOTA_KEY: &str = "QUOTA";
+
+fn usage_key(period: &str) -> String {
+    format!("USAGE#{}", period)
+}
+
+/// Classify a usage level against its soft and hard limits.
+pub fn quota_state(used: u64, quota: Quota) -> QuotaState {
+    if quota.hard.is_some_and(|hard| used > hard) {
+        QuotaState::Exceeded
+    } else if quota.soft.is_some_and(|soft| used > soft) {
+        QuotaState::Warning
+    } else {
+        QuotaState::Ok
+    }
+}
+
+/// Calendar month (`YYYY-MM`, UTC) containing the given Unix time.
+pub fn billing_period(unix_secs: u64) -> String {
+    utc_date(unix_secs)[..7].to_string()
+}
+
+/// Calendar day (`YYYY-MM-DD`, UTC) containing the given Unix time.
+pub fn utc_date(unix_secs: u64) -> String {
+    // Civil-from-days conversion (H. Hinnant), valid for all post-epoch dates
+    let days = (unix_secs / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_state_limits_are_inclusive() {
+        let quota = Quota {
+            soft: Some(100),
+            hard: Some(200),
+        };
+        assert_eq!(quota_state(0, quota), QuotaState::Ok);
+        assert_eq!(quota_state(100, quota), QuotaState::Ok);
+        assert_eq!(quota_state(101, quota), QuotaState::Warning);
+        assert_eq!(quota_state(200, quota), QuotaState::Warning);
+        assert_eq!(quota_state(201, quota), QuotaState::Exceeded);
+    }
+
+    #[test]
+    fn test_quota_state_unlimited() {
+        assert_eq!(quota_state(u64::MAX, Quota::default()), QuotaState::Ok);
+
+        let hard_only = Quota {
+            soft: None,
+            hard: Some(10),
+        };
+        assert_eq!(quota_state(10, hard_only), QuotaState::Ok);
+        assert_eq!(quota_state(11, hard_only), QuotaState::Exceeded);
+
+        let soft_only = Quota {
+            soft: Some(10),
+            hard: None,
+        };
+        assert_eq!(quota_state(u64::MAX, soft_only), QuotaState::Warning);
+    }
+
+    #[test]
+    fn test_billing_period_rolls_over_at_month_end() {
+        assert_eq!(billing_period(0), "1970-01");
+        assert_eq!(billing_period(1_706_745_599), "2024-01");
+        assert_eq!(billing_period(1_706_745_600), "2024-02");
+        // December into January of the next year
+        assert_eq!(billing_period(1_704_067_199), "2023-12");
+        assert_eq!(billing_period(1_704_067_200), "2024-01");
+    }
+
+    #[test]
+    fn test_utc_date() {
+        assert_eq!(utc_date(0), "1970-01-01");
+        assert_eq!(utc_date(1_704_067_199), "2023-12-31");
+        assert_eq!(utc_date(1_704_067_200), "2024-01-01");
+        // Leap day, and the non-leap century year after it
+        assert_eq!(utc_date(1_709_208_000), "2024-02-29");
+        assert_eq!(utc_date(4_107_542_400), "2100-03-01");
+        assert_eq!(utc_date(4_107_542_400 - 1), "2100-02-28");
+    }
+}
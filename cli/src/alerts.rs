@@ -0,0 +1,212 @@
+//! `openduckrust alerts` — list, acknowledge, and silence fleet alerts.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::api::ApiClient;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    Open,
+    Acknowledged,
+    Silenced,
+    Resolved,
+}
+
+#[derive(clap::Subcommand)]
+pub enum AlertsCommand {
+    /// List alerts, newest first
+    List {
+        /// Only alerts for this robot
+        #[arg(long)]
+        robot: Option<String>,
+        /// Only alerts at or above this severity
+        #[arg(long, value_enum)]
+        severity: Option<Severity>,
+        /// Only alerts in this state (default: open)
+        #[arg(long, value_enum, default_value_t = AlertState::Open)]
+        state: AlertState,
+    },
+    /// Acknowledge an alert
+    Ack {
+        alert_id: String,
+        /// Note recorded with the acknowledgement
+        #[arg(long)]
+        note: Option<String>,
+    },
+    /// Silence matching alerts for a while (e.g. 30m, 2h, 1d)
+    Silence {
+        /// Duration of the silence
+        duration: String,
+        /// Only silence alerts for this robot
+        #[arg(long)]
+        robot: Option<String>,
+        /// Only silence alerts at or below this severity
+        #[arg(long, value_enum)]
+        severity: Option<Severity>,
+        /// Why the alerts are silenced
+        #[arg(long)]
+        reason: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Alert {
+    alert_id: String,
+    robot_id: Option<String>,
+    severity: Severity,
+    state: AlertState,
+    title: String,
+    /// Unix time (s).
+    created_at: u64,
+}
+
+#[derive(Serialize)]
+struct AckRequest {
+    note: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SilenceRequest {
+    robot_id: Option<String>,
+    severity: Option<Severity>,
+    duration_secs: u64,
+    reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Silence {
+    silence_id: String,
+    /// Unix time (s).
+    until: u64,
+}
+
+pub async fn run(api: &ApiClient, command: AlertsCommand) -> Result<()> {
+    match command {
+        AlertsCommand::List { robot, severity, state } => {
+            let mut query = vec![("state", enum_str(&state))];
+            if let Some(robot) = robot {
+                query.push(("robot_id", robot));
+            }
+            if let Some(severity) = severity {
+                query.push(("min_severity", enum_str(&severity)));
+            }
+
+            let alerts: Vec<Alert> = api.get("/api/alerts", &query).await?;
+            if alerts.is_empty() {
+                println!("No {} alerts", enum_str(&state));
+                return Ok(());
+            }
+
+            println!("{:<24} {:<9} {:<13} {:<16} {:>8}  TITLE", "ID", "SEVERITY", "STATE", "ROBOT", "AGE");
+            let now = unix_now();
+            for a in alerts {
+                println!(
+                    "{:<24} {:<9} {:<13} {:<16} {:>8}  {}",
+                    a.alert_id,
+                    enum_str(&a.severity),
+                    enum_str(&a.state),
+                    a.robot_id.as_deref().unwrap_or("-"),
+                    format_age(now.saturating_sub(a.created_at)),
+                    a.title
+                );
+            }
+        }
+        AlertsCommand::Ack { alert_id, note } => {
+            let alert: Alert = api
+                .post(&format!("/api/alerts/{}/ack", alert_id), &AckRequest { note })
+                .await?;
+            println!("Acknowledged {} ({})", alert.alert_id, alert.title);
+        }
+        AlertsCommand::Silence { duration, robot, severity, reason } => {
+            let duration_secs = parse_duration(&duration)?;
+            let silence: Silence = api
+                .post(
+                    "/api/alerts/silences",
+                    &SilenceRequest {
+                        robot_id: robot,
+                        severity,
+                        duration_secs,
+                        reason,
+                    },
+                )
+                .await?;
+            println!(
+                "Silence {} active for {}",
+                silence.silence_id,
+                format_age(silence.until.saturating_sub(unix_now()))
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Parse `90`, `90s`, `30m`, `2h`, or `1d` into seconds.
+fn parse_duration(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let value: u64 = match number.parse() {
+        Ok(v) if v > 0 => v,
+        _ => bail!("Invalid duration {:?} (expected e.g. 30m, 2h, 1d)", s),
+    };
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => bail!("Invalid duration unit {:?} (expected s, m, h, or d)", unit),
+    };
+    Ok(value * scale)
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86_400),
+    }
+}
+
+/// The serde (snake_case) name of a unit enum variant.
+fn enum_str<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), 90);
+        assert_eq!(parse_duration("30m").unwrap(), 1800);
+        assert_eq!(parse_duration("2h").unwrap(), 7200);
+        assert_eq!(parse_duration("1d").unwrap(), 86_400);
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Thin HTTP client for the openduckrust API.
+//!
+//! Every request of one CLI run carries the same W3C trace id in its
+//! `traceparent` header, so the backend's spans for the whole command form
+//! one trace. Errors quote the id to look it up.
+
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const DEFAULT_API_URL: &str = "http://localhost:8080";
+
+pub struct ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    trace_id: u128,
+}
+
+impl ApiClient {
+    /// Configure from `OPENDUCKRUST_API_URL` and `OPENDUCKRUST_TOKEN`.
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("OPENDUCKRUST_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: std::env::var("OPENDUCKRUST_TOKEN").ok(),
+            trace_id: rand::thread_rng().gen_range(1..=u128::MAX),
+        }
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T> {
+        let req = self.http.get(self.url(path)).query(query);
+        self.send(req).await
+    }
+
+    /// Like `get`, but `None` when the API answers 404.
+    pub async fn get_optional<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<Option<T>> {
+        let req = self.http.get(self.url(path)).query(query);
+        let res = self.execute(req).await?;
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        self.decode(res).await.map(Some)
+    }
+
+    pub async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        let req = self.http.post(self.url(path)).json(body);
+        self.send(req).await
+    }
+
+    pub async fn put<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        let req = self.http.put(self.url(path)).json(body);
+        self.send(req).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, req: reqwest::RequestBuilder) -> Result<T> {
+        let res = self.execute(req).await?;
+        self.decode(res).await
+    }
+
+    async fn execute(&self, mut req: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+        let span_id: u64 = rand::thread_rng().gen_range(1..=u64::MAX);
+        let traceparent = format!("00-{:032x}-{:016x}-01", self.trace_id, span_id);
+        req.header("traceparent", traceparent).send().await.context("Request to the API failed")
+    }
+
+    async fn decode<T: DeserializeOwned>(&self, res: reqwest::Response) -> Result<T> {
+        let status = res.status();
+        if !status.is_success() {
+            let body = res.text().await.unwrap_or_default();
+            bail!("API returned {}: {} (trace {:032x})", status, body.trim(), self.trace_id);
+        }
+        res.json().await.context("Failed to decode API response")
+    }
+}
@@ -0,0 +1,304 @@
+//! `openduckrust blackbox` — inspect the runtime's flight recorder dumps.
+//!
+//! `blackbox inspect dump.bin` prints the last ticks before the fall or
+//! fault: per-stage timings, the safety verdict, tilt and flags. `--csv`
+//! exports every tick, joint positions and targets included, for a
+//! spreadsheet or pandas; `--mcap` writes an MCAP file with one JSON message
+//! per tick that Foxglove Studio can plot.
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use openduckrust_runtime::blackbox::{
+    BlackBoxDump, TickRecord, FLAG_COLLISION, FLAG_OVERRUN, FLAG_THROTTLED,
+};
+use openduckrust_runtime::runtime_health::SafetyState;
+
+/// Names of the seven command channels.
+const COMMAND_NAMES: [&str; 7] =
+    ["lin_vel_x", "lin_vel_y", "ang_vel", "neck_pitch", "head_pitch", "head_yaw", "head_roll"];
+
+/// MCAP file magic, at both ends of the file.
+const MCAP_MAGIC: &[u8; 8] = b"\x89MCAP0\r\n";
+
+#[derive(clap::Subcommand)]
+pub enum BlackboxCommand {
+    /// Decode a dump and print the last ticks
+    Inspect(InspectArgs),
+}
+
+#[derive(clap::Args)]
+pub struct InspectArgs {
+    /// Dump file, e.g. ~/.openduckrust/blackbox/blackbox-<time>.bin
+    file: PathBuf,
+    /// Ticks to print, counting back from the dump
+    #[arg(short = 'n', long, default_value_t = 20)]
+    last: usize,
+    /// Export every tick to a CSV file
+    #[arg(long)]
+    csv: Option<PathBuf>,
+    /// Export every tick to an MCAP file
+    #[arg(long)]
+    mcap: Option<PathBuf>,
+}
+
+pub fn run(command: BlackboxCommand) -> Result<()> {
+    match command {
+        BlackboxCommand::Inspect(args) => inspect(args),
+    }
+}
+
+fn inspect(args: InspectArgs) -> Result<()> {
+    let dump = BlackBoxDump::load(&args.file)?;
+    let span = match (dump.ticks.first(), dump.ticks.last()) {
+        (Some(first), Some(last)) => last.time - first.time,
+        _ => 0.0,
+    };
+    println!("Reason:  {}", dump.reason);
+    println!("Dumped:  {:.3} (unix time)", dump.dumped_at);
+    println!(
+        "Ticks:   {} at {} Hz ({:.1} s), {} joints",
+        dump.ticks.len(),
+        dump.control_freq,
+        span,
+        dump.joint_names.len()
+    );
+    let overruns = dump.ticks.iter().filter(|t| t.flags & FLAG_OVERRUN != 0).count();
+    if let Some(worst) = dump.ticks.iter().max_by_key(|t| t.timings.total) {
+        println!(
+            "Slowest: tick {} took {:.2} ms; {} overruns",
+            worst.tick,
+            ms(worst.timings.total),
+            overruns
+        );
+    }
+
+    println!(
+        "\n{:>8}  {:>8}  {:>6}  {:>6}  {:>6}  {:>6}  {:>12}  {:>5}  flags",
+        "tick", "time", "read", "infer", "write", "total", "safety", "tilt"
+    );
+    let from = dump.ticks.len().saturating_sub(args.last);
+    for t in &dump.ticks[from..] {
+        println!(
+            "{:>8}  {:>8.3}  {:>6.2}  {:>6.2}  {:>6.2}  {:>6.2}  {:>12}  {:>5.2}  {}",
+            t.tick,
+            t.time,
+            ms(t.timings.read),
+            ms(t.timings.inference),
+            ms(t.timings.write),
+            ms(t.timings.total),
+            safety_name(t.safety),
+            t.tilt,
+            flag_names(t.flags).join(",")
+        );
+    }
+
+    if let Some(ref path) = args.csv {
+        write_csv(&dump, path)?;
+        println!("\nWrote {} ticks to {}", dump.ticks.len(), path.display());
+    }
+    if let Some(ref path) = args.mcap {
+        write_mcap(&dump, path)?;
+        println!("\nWrote {} ticks to {}", dump.ticks.len(), path.display());
+    }
+    Ok(())
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+fn safety_name(state: SafetyState) -> String {
+    serde_json::to_value(state)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn flag_names(flags: u8) -> Vec<&'static str> {
+    [(FLAG_OVERRUN, "overrun"), (FLAG_COLLISION, "collision"), (FLAG_THROTTLED, "throttled")]
+        .into_iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+fn write_csv(dump: &BlackBoxDump, path: &Path) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+
+    let columns = [
+        "tick", "time", "read_ms", "inference_ms", "write_ms", "total_ms", "safety", "flags", "tilt",
+        "gravity_x", "gravity_y", "gravity_z",
+    ];
+    let mut header: Vec<String> = columns.iter().map(|h| h.to_string()).collect();
+    header.extend(COMMAND_NAMES.iter().map(|c| format!("cmd_{c}")));
+    header.extend(dump.joint_names.iter().map(|j| format!("{j}_pos")));
+    header.extend(dump.joint_names.iter().map(|j| format!("{j}_target")));
+    writeln!(out, "{}", header.join(","))?;
+
+    for t in &dump.ticks {
+        let mut row = vec![
+            t.tick.to_string(),
+            format!("{:.4}", t.time),
+            format!("{:.3}", ms(t.timings.read)),
+            format!("{:.3}", ms(t.timings.inference)),
+            format!("{:.3}", ms(t.timings.write)),
+            format!("{:.3}", ms(t.timings.total)),
+            safety_name(t.safety),
+            flag_names(t.flags).join("|"),
+            format!("{:.4}", t.tilt),
+        ];
+        let values = t.gravity.iter().chain(&t.commands).chain(&t.positions).chain(&t.targets);
+        row.extend(values.map(|v| format!("{v:.4}")));
+        writeln!(out, "{}", row.join(","))?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// One tick as a JSON message.
+fn tick_message(dump: &BlackBoxDump, t: &TickRecord) -> serde_json::Value {
+    let joints = |values: &[f32]| -> serde_json::Map<String, serde_json::Value> {
+        dump.joint_names.iter().cloned().zip(values.iter().map(|&v| json!(v))).collect()
+    };
+    let commands: serde_json::Map<String, serde_json::Value> = COMMAND_NAMES
+        .iter()
+        .zip(t.commands)
+        .map(|(name, value)| (name.to_string(), json!(value)))
+        .collect();
+    json!({
+        "tick": t.tick,
+        "time": t.time,
+        "timings_ms": {
+            "read": ms(t.timings.read),
+            "inference": ms(t.timings.inference),
+            "write": ms(t.timings.write),
+            "total": ms(t.timings.total),
+        },
+        "safety": safety_name(t.safety),
+        "flags": flag_names(t.flags),
+        "tilt": t.tilt,
+        "gravity": t.gravity,
+        "commands": commands,
+        "positions": joints(&t.positions),
+        "targets": joints(&t.targets),
+    })
+}
+
+/// Write an unindexed MCAP file: one channel, one JSON message per tick,
+/// timestamped from the dump time back.
+fn write_mcap(dump: &BlackBoxDump, path: &Path) -> Result<()> {
+    let last_time = dump.ticks.last().map_or(0.0, |t| t.time);
+    let mut out = Vec::from(&MCAP_MAGIC[..]);
+
+    let mut header = Vec::new();
+    mcap_str(&mut header, "");
+    mcap_str(&mut header, "openduckrust");
+    mcap_record(&mut out, 0x01, &header);
+
+    let mut schema = Vec::new();
+    schema.extend_from_slice(&1u16.to_le_bytes());
+    mcap_str(&mut schema, "openduckrust.BlackboxTick");
+    mcap_str(&mut schema, "jsonschema");
+    mcap_bytes(&mut schema, br#"{"type":"object"}"#);
+    mcap_record(&mut out, 0x03, &schema);
+
+    let mut channel = Vec::new();
+    channel.extend_from_slice(&1u16.to_le_bytes()); // channel id
+    channel.extend_from_slice(&1u16.to_le_bytes()); // schema id
+    mcap_str(&mut channel, "/blackbox/tick");
+    mcap_str(&mut channel, "json");
+    channel.extend_from_slice(&0u32.to_le_bytes()); // no metadata
+    mcap_record(&mut out, 0x04, &channel);
+
+    for (sequence, t) in dump.ticks.iter().enumerate() {
+        let at = dump.dumped_at - (last_time - t.time);
+        let nanos = (at.max(0.0) * 1e9) as u64;
+        let mut message = Vec::new();
+        message.extend_from_slice(&1u16.to_le_bytes());
+        message.extend_from_slice(&(sequence as u32).to_le_bytes());
+        message.extend_from_slice(&nanos.to_le_bytes()); // log time
+        message.extend_from_slice(&nanos.to_le_bytes()); // publish time
+        message.extend_from_slice(&serde_json::to_vec(&tick_message(dump, t))?);
+        mcap_record(&mut out, 0x05, &message);
+    }
+
+    mcap_record(&mut out, 0x0F, &0u32.to_le_bytes()); // data end, no CRC
+    mcap_record(&mut out, 0x02, &[0u8; 20]); // footer, no summary
+    out.extend_from_slice(MCAP_MAGIC);
+
+    std::fs::write(path, out).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn mcap_record(out: &mut Vec<u8>, opcode: u8, content: &[u8]) {
+    out.push(opcode);
+    out.extend_from_slice(&(content.len() as u64).to_le_bytes());
+    out.extend_from_slice(content);
+}
+
+fn mcap_str(out: &mut Vec<u8>, s: &str) {
+    mcap_bytes(out, s.as_bytes());
+}
+
+fn mcap_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openduckrust_runtime::blackbox::StageTimings;
+
+    #[test]
+    fn test_csv_and_mcap_export() {
+        let tick = |n: u64| TickRecord {
+            tick: n,
+            time: n as f64 * 0.02,
+            timings: StageTimings::default(),
+            safety: SafetyState::Running,
+            flags: FLAG_OVERRUN,
+            tilt: 0.1,
+            gravity: [0.0, 0.0, -1.0],
+            commands: [0.0; 7],
+            positions: vec![0.5],
+            targets: vec![0.25],
+        };
+        let dump = BlackBoxDump {
+            control_freq: 50,
+            dumped_at: 1_700_000_000.0,
+            reason: "fall".to_string(),
+            joint_names: vec!["left_knee".to_string()],
+            ticks: vec![tick(0), tick(1)],
+        };
+        let dir = std::env::temp_dir();
+        let csv = dir.join(format!("odr-blackbox-{}.csv", std::process::id()));
+        write_csv(&dump, &csv).unwrap();
+        let text = std::fs::read_to_string(&csv).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("left_knee_pos,left_knee_target"));
+        assert!(lines[2].starts_with("1,0.0200,") && lines[2].contains(",running,overrun,"));
+
+        let mcap = dir.join(format!("odr-blackbox-{}.mcap", std::process::id()));
+        write_mcap(&dump, &mcap).unwrap();
+        let bytes = std::fs::read(&mcap).unwrap();
+        assert!(bytes.starts_with(MCAP_MAGIC) && bytes.ends_with(MCAP_MAGIC));
+        // Walk the records: header, schema, channel, two messages, data end, footer
+        let mut at = MCAP_MAGIC.len();
+        let mut opcodes = Vec::new();
+        while at < bytes.len() - MCAP_MAGIC.len() {
+            opcodes.push(bytes[at]);
+            let len = u64::from_le_bytes(bytes[at + 1..at + 9].try_into().unwrap()) as usize;
+            at += 9 + len;
+        }
+        assert_eq!(opcodes, vec![0x01, 0x03, 0x04, 0x05, 0x05, 0x0F, 0x02]);
+        std::fs::remove_file(csv).unwrap();
+        std::fs::remove_file(mcap).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+//! `openduckrust calibrate` — measure joint offsets by posing the robot.
+//!
+//! Torque is disabled so every joint can be moved by hand. For each joint in
+//! turn, the user holds it at its mechanical zero (the pose the policy calls
+//! 0 rad) and presses Enter; the raw servo reading there becomes the joint's
+//! offset. The new `joints_offsets` are shown next to the old ones and only
+//! written back to the duck config after confirmation.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::io::{BufRead, Write as _};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use openduckrust_runtime::config::DuckConfig;
+use openduckrust_runtime::motors::{MotorController, MotorInterface};
+
+/// Readings averaged per joint.
+const SAMPLES: usize = 10;
+
+/// Offsets larger than this (rad) usually mean a horn mounted a spline off.
+const SUSPICIOUS_OFFSET: f64 = 0.5;
+
+#[derive(clap::Args)]
+pub struct CalibrateArgs {
+    /// Duck config to update (default: ~/duck_config.json)
+    #[arg(long)]
+    duck_config_path: Option<PathBuf>,
+    /// Serial port of the servo bus
+    #[arg(long, default_value = "/dev/ttyACM0")]
+    serial_port: String,
+    /// Calibrate only these joints (default: all)
+    #[arg(long, value_delimiter = ',')]
+    joints: Vec<String>,
+}
+
+/// `path`, or `~/duck_config.json`.
+pub(crate) fn duck_config_path(path: Option<PathBuf>) -> Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path),
+        None => Ok(PathBuf::from(std::env::var("HOME").context("HOME is not set")?)
+            .join("duck_config.json")),
+    }
+}
+
+pub fn run(args: CalibrateArgs) -> Result<()> {
+    let config_path = duck_config_path(args.duck_config_path)?;
+    let config = DuckConfig::load(&config_path)?;
+
+    let joints: Vec<String> = if args.joints.is_empty() {
+        config.robot_model.joint_names()
+    } else {
+        for name in &args.joints {
+            if !config.robot_model.contains(name) {
+                bail!("Unknown joint {name:?}");
+            }
+        }
+        args.joints.clone()
+    };
+
+    // Read raw positions: without offsets, the reading at zero is the offset
+    let raw_config = DuckConfig {
+        joints_offset: HashMap::new(),
+        ..config.clone()
+    };
+    let mut hwi = MotorController::with_joints(&raw_config, &args.serial_port, &joints)?;
+    hwi.torque_kill_switch()
+        .context("Could not open the servo bus to disable torque")?
+        .disable_all()?;
+
+    println!("Torque disabled. For each joint, hold it at its zero pose and press Enter.");
+    println!("Type s to skip a joint (keep its offset), q to quit without saving.\n");
+
+    let names = hwi.joint_names().to_vec();
+    let mut offsets = config.joints_offset.clone();
+    let mut stdin = std::io::stdin().lock();
+    for (i, name) in names.iter().enumerate() {
+        print!(
+            "{name:>16} (offset {:+.3}): pose at zero, then Enter [s/q] ",
+            config.joint_offset(name)
+        );
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        stdin.read_line(&mut line)?;
+        match line.trim() {
+            "q" => {
+                println!("Aborted, {} unchanged", config_path.display());
+                return Ok(());
+            }
+            "s" => continue,
+            _ => {}
+        }
+
+        let offset = average_position(&mut hwi, i)?;
+        println!("{name:>16} reads {offset:+.3} rad");
+        if offset.abs() > SUSPICIOUS_OFFSET {
+            println!("{:>16} that's far from zero, check the horn is on the right spline", "");
+        }
+        offsets.insert(name.clone(), offset);
+    }
+
+    println!("\n{:>16}  {:>8}  {:>8}", "joint", "old", "new");
+    for name in &names {
+        println!(
+            "{name:>16}  {:+8.3}  {:+8.3}",
+            config.joint_offset(name),
+            offsets.get(name).copied().unwrap_or(0.0)
+        );
+    }
+    print!("\nWrite these offsets to {}? [y/N] ", config_path.display());
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    stdin.read_line(&mut answer)?;
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        println!("Not saved");
+        return Ok(());
+    }
+
+    DuckConfig::write_joints_offsets(&config_path, &offsets)?;
+    println!("Saved joints_offsets to {}", config_path.display());
+    Ok(())
+}
+
+/// Mean of `SAMPLES` position readings of joint `index` (rad).
+fn average_position(hwi: &mut MotorController, index: usize) -> Result<f64> {
+    let mut sum = 0.0;
+    let mut count = 0;
+    for _ in 0..SAMPLES {
+        if let Some(positions) = hwi.get_present_positions() {
+            sum += positions[index];
+            count += 1;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    if count == 0 {
+        bail!("Could not read joint positions from the servo bus");
+    }
+    Ok(sum / count as f64)
+}
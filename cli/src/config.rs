@@ -0,0 +1,295 @@
+//! `openduckrust config` — move duck configs between local files and the
+//! backend's per-robot config store.
+//!
+//! The backend keeps a numbered version of each robot's config. `pull`
+//! writes the stored config to a local file and `push` uploads a local file
+//! as the next version. Both show what would change and ask before writing;
+//! `push` also sends the version it was compared against, so a push racing
+//! someone else's is refused instead of silently overwriting it.
+//!
+//! `validate` checks a local file strictly (unknown keys, joint names, value
+//! ranges) and `init` writes a commented template to start from.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use openduckrust_runtime::config::DuckConfig;
+use openduckrust_runtime::config_check;
+
+use crate::api::ApiClient;
+
+/// Unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 2;
+
+#[derive(clap::Subcommand)]
+pub enum ConfigCommand {
+    /// Upload a local config as the robot's next config version
+    Push {
+        /// Duck config JSON file
+        file: PathBuf,
+        #[arg(long)]
+        robot: String,
+        /// Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Download the robot's config to a local file
+    Pull {
+        /// Where to write it
+        #[arg(default_value = "duck_config.json")]
+        file: PathBuf,
+        #[arg(long)]
+        robot: String,
+        /// An earlier version instead of the latest
+        #[arg(long)]
+        version: Option<u64>,
+        /// Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Check a local config for unknown keys, bad joint names and out-of-range values
+    Validate {
+        #[arg(default_value = "duck_config.json")]
+        file: PathBuf,
+    },
+    /// Write a commented config template with every setting at its default
+    Init {
+        #[arg(default_value = "duck_config.json")]
+        file: PathBuf,
+        /// Replace an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct RobotConfig {
+    version: u64,
+    config: serde_json::Value,
+    /// Unix time (s).
+    updated_at: u64,
+    updated_by: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PushRequest<'a> {
+    config: &'a serde_json::Value,
+    /// The version the push replaces; `None` for a robot's first config.
+    base_version: Option<u64>,
+}
+
+pub async fn run(api: &ApiClient, command: ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Push { file, robot, yes } => {
+            let local = read_config(&file)?;
+            let path = format!("/api/robots/{}/config", robot);
+            let remote: Option<RobotConfig> = api.get_optional(&path, &[]).await?;
+            let base_version = remote.as_ref().map(|r| r.version);
+            let current = remote.map(|r| r.config).unwrap_or(serde_json::Value::Null);
+
+            match base_version {
+                Some(version) if current == local => {
+                    println!("{} is already at version {}, nothing to push", robot, version);
+                    return Ok(());
+                }
+                Some(version) => println!("Changes to {} version {}:", robot, version),
+                None => println!("{} has no config yet, pushing:", robot),
+            }
+            print_diff(&current, &local);
+            if !yes && !confirm(&format!("Push {} to {}?", file.display(), robot))? {
+                println!("Not pushed");
+                return Ok(());
+            }
+
+            let request = PushRequest {
+                config: &local,
+                base_version,
+            };
+            let pushed: RobotConfig = api
+                .put(&path, &request)
+                .await
+                .context("Push failed (if the robot's config changed meanwhile, run push again)")?;
+            println!("Pushed {} as version {} of {}", file.display(), pushed.version, robot);
+        }
+        ConfigCommand::Pull { file, robot, version, yes } => {
+            let path = format!("/api/robots/{}/config", robot);
+            let query: Vec<_> = version.map(|v| ("version", v.to_string())).into_iter().collect();
+            let Some(remote) = api.get_optional::<RobotConfig>(&path, &query).await? else {
+                bail!("{} has no config version {}", robot, version.map_or("yet".into(), |v| v.to_string()));
+            };
+            println!(
+                "{} version {}, saved {} by {}",
+                robot,
+                remote.version,
+                format_time(remote.updated_at),
+                remote.updated_by.as_deref().unwrap_or("unknown")
+            );
+
+            if file.exists() {
+                let local = read_config(&file)?;
+                if local == remote.config {
+                    println!("{} is up to date", file.display());
+                    return Ok(());
+                }
+                println!("Changes to {}:", file.display());
+                print_diff(&local, &remote.config);
+                if !yes && !confirm(&format!("Overwrite {}?", file.display()))? {
+                    println!("Not pulled");
+                    return Ok(());
+                }
+            }
+
+            let contents = serde_json::to_string_pretty(&remote.config)? + "\n";
+            std::fs::write(&file, contents).with_context(|| format!("Failed to write {}", file.display()))?;
+            println!("Wrote version {} to {}", remote.version, file.display());
+        }
+        ConfigCommand::Validate { file } => {
+            let contents =
+                std::fs::read_to_string(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+            let issues = config_check::check(&contents);
+            if issues.is_empty() {
+                println!("{} is valid", file.display());
+                return Ok(());
+            }
+            for issue in &issues {
+                println!("{}: {}", file.display(), issue);
+            }
+            bail!("{} problem(s) in {}", issues.len(), file.display());
+        }
+        ConfigCommand::Init { file, force } => {
+            if file.exists() && !force {
+                bail!("{} already exists (--force replaces it)", file.display());
+            }
+            std::fs::write(&file, config_check::template())
+                .with_context(|| format!("Failed to write {}", file.display()))?;
+            println!("Wrote a config template to {}", file.display());
+        }
+    }
+    Ok(())
+}
+
+/// Read a config file, checking that the runtime would accept it.
+fn read_config(path: &Path) -> Result<serde_json::Value> {
+    let contents = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let value: serde_json::Value = serde_json::from_str(&config_check::strip_comments(&contents))
+        .with_context(|| format!("{} is not valid JSON", path.display()))?;
+    serde_json::from_value::<DuckConfig>(value.clone())
+        .with_context(|| format!("{} is not a valid duck config", path.display()))?;
+    Ok(value)
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case("y"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line diff of `old` to `new` (longest common subsequence).
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    // common[i][j]: common lines of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Print the changed lines of the two configs, pretty-printed, with some
+/// context.
+fn print_diff(old: &serde_json::Value, new: &serde_json::Value) {
+    let pretty = |value: &serde_json::Value| match value {
+        serde_json::Value::Null => String::new(),
+        value => serde_json::to_string_pretty(value).unwrap_or_default(),
+    };
+    let (old, new) = (pretty(old), pretty(new));
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let lines = diff_lines(&old, &new);
+
+    let changed: Vec<usize> = (0..lines.len()).filter(|&i| !matches!(lines[i], Line::Same(_))).collect();
+    let near_change = |i: usize| changed.iter().any(|&c| c.abs_diff(i) <= CONTEXT_LINES);
+    let mut skipped = false;
+    for (i, line) in lines.iter().enumerate() {
+        if !near_change(i) {
+            skipped = true;
+            continue;
+        }
+        if skipped {
+            println!("  ...");
+            skipped = false;
+        }
+        match line {
+            Line::Same(text) => println!("  {}", text),
+            Line::Removed(text) => println!("- {}", text),
+            Line::Added(text) => println!("+ {}", text),
+        }
+    }
+}
+
+fn format_time(unix: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    match now.saturating_sub(unix) {
+        s if s < 60 => format!("{}s ago", s),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86_400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let old = ["{", "  \"a\": 1,", "  \"b\": 2", "}"];
+        let new = ["{", "  \"a\": 1,", "  \"b\": 3,", "  \"c\": 4", "}"];
+        assert_eq!(
+            diff_lines(&old, &new),
+            [
+                Line::Same("{"),
+                Line::Same("  \"a\": 1,"),
+                Line::Removed("  \"b\": 2"),
+                Line::Added("  \"b\": 3,"),
+                Line::Added("  \"c\": 4"),
+                Line::Same("}"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! `openduckrust emotes` — manage the recorded emote library.
+//!
+//! Emotes are recorded on the robot with `--capture-emote NAME` and saved as
+//! JSON in the emote directory (`~/.openduckrust/emotes` by default). `list`
+//! shows that library, `play` asks a running runtime to play one over its
+//! WebSocket remote (`--remote-listen`), and `push` uploads one to the fleet
+//! backend's asset endpoints so other robots can fetch it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::TcpStream;
+use std::path::PathBuf;
+
+use openduckrust_runtime::config::EmotesConfig;
+use openduckrust_runtime::emotes::{Emote, EmoteLibrary};
+use openduckrust_runtime::remote::{RemoteCommand, DEFAULT_PORT};
+
+use crate::api::ApiClient;
+
+#[derive(clap::Subcommand)]
+pub enum EmotesCommand {
+    /// List the recorded emotes
+    List {
+        #[command(flatten)]
+        library: LibraryArgs,
+    },
+    /// Play an emote on a running robot
+    Play {
+        name: String,
+        /// Robot address, as HOST or HOST:PORT (default port 9871)
+        #[arg(long, default_value = "localhost")]
+        robot: String,
+    },
+    /// Upload an emote to the fleet backend
+    Push {
+        name: String,
+        /// Only make it available to these robots (default: the whole fleet)
+        #[arg(long = "robot")]
+        robots: Vec<String>,
+        #[command(flatten)]
+        library: LibraryArgs,
+    },
+}
+
+#[derive(clap::Args)]
+pub struct LibraryArgs {
+    /// Emote directory (default: ~/.openduckrust/emotes)
+    #[arg(long)]
+    dir: Option<PathBuf>,
+}
+
+impl LibraryArgs {
+    fn open(self) -> Result<EmoteLibrary> {
+        let dir = match self.dir {
+            Some(dir) => dir,
+            None => {
+                let default = EmotesConfig::default().dir;
+                let home = std::env::var("HOME").context("HOME is not set")?;
+                PathBuf::from(default.replacen('~', &home, 1))
+            }
+        };
+        Ok(EmoteLibrary::new(dir))
+    }
+}
+
+#[derive(Serialize)]
+struct AssetUpload<'a> {
+    kind: &'static str,
+    name: &'a str,
+    content: &'a Emote,
+    /// Empty for every robot.
+    robot_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    asset_id: String,
+}
+
+pub async fn run(api: &ApiClient, command: EmotesCommand) -> Result<()> {
+    match command {
+        EmotesCommand::List { library } => {
+            let library = library.open()?;
+            let names = library.names()?;
+            if names.is_empty() {
+                println!("No emotes recorded yet (capture one with --capture-emote NAME)");
+                return Ok(());
+            }
+
+            println!("{:<24} {:>9} {:>9}", "NAME", "KEYFRAMES", "DURATION");
+            for name in names {
+                match library.load(&name) {
+                    Ok(emote) => println!(
+                        "{:<24} {:>9} {:>8.1}s",
+                        name,
+                        emote.keyframes.len(),
+                        emote.duration()
+                    ),
+                    Err(e) => println!("{:<24} invalid: {:#}", name, e),
+                }
+            }
+        }
+        EmotesCommand::Play { name, robot } => {
+            tokio::task::spawn_blocking(move || play(name, &robot)).await??
+        }
+        EmotesCommand::Push { name, robots, library } => {
+            let emote = library.open()?.load(&name)?;
+            let asset: Asset = api
+                .post(
+                    "/api/assets",
+                    &AssetUpload {
+                        kind: "emote",
+                        name: &emote.name,
+                        content: &emote,
+                        robot_ids: robots.clone(),
+                    },
+                )
+                .await?;
+            let target = if robots.is_empty() {
+                "the whole fleet".to_string()
+            } else {
+                robots.join(", ")
+            };
+            println!("Pushed emote {} as asset {} for {}", name, asset.asset_id, target);
+        }
+    }
+    Ok(())
+}
+
+/// Send a `play_emote` command to the runtime's WebSocket remote.
+fn play(name: String, robot: &str) -> Result<()> {
+    let addr = if robot.contains(':') {
+        robot.to_string()
+    } else {
+        format!("{robot}:{DEFAULT_PORT}")
+    };
+    let stream = TcpStream::connect(&addr).with_context(|| {
+        format!("Failed to connect to {addr} (is the runtime started with --remote-listen?)")
+    })?;
+    let (mut socket, _) =
+        tungstenite::client(format!("ws://{addr}/"), stream).context("WebSocket handshake failed")?;
+
+    let command = serde_json::to_string(&RemoteCommand::PlayEmote { name: name.clone() })?;
+    socket
+        .send(tungstenite::Message::text(command))
+        .context("Failed to send the command")?;
+    let _ = socket.close(None);
+    let _ = socket.flush();
+    println!("Asked {addr} to play emote {name}");
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! `openduckrust gamepad` — forward a gamepad connected to this machine to a
+//! robot over UDP.
+//!
+//! The controller is read with the runtime's own input stack, so sticks, head
+//! mode, and buttons behave exactly as if it were paired to the Pi. Start the
+//! runtime with `--teleop-listen 0.0.0.0` to accept the packets.
+
+use anyhow::{Context, Result};
+use std::io::Write as _;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use openduckrust_runtime::controller::{CommandSource, StickLayout, XBoxController};
+use openduckrust_runtime::teleop::{TeleopPacket, DEFAULT_PORT};
+
+#[derive(clap::Args)]
+pub struct GamepadArgs {
+    /// Robot address, as HOST or HOST:PORT (default port 9870)
+    robot: String,
+    /// Packets sent per second
+    #[arg(long, default_value_t = 30)]
+    rate: u32,
+    /// Stick layout: `combined` also pitches the head with the right stick's
+    /// Y axis while driving
+    #[arg(long, value_enum, default_value_t = StickLayout::Toggle)]
+    stick_layout: StickLayout,
+}
+
+pub fn run(args: GamepadArgs) -> Result<()> {
+    let addr = if args.robot.contains(':') {
+        args.robot.clone()
+    } else {
+        format!("{}:{}", args.robot, DEFAULT_PORT)
+    };
+
+    let socket = UdpSocket::bind("0.0.0.0:0").context("Failed to open UDP socket")?;
+    socket
+        .connect(&addr)
+        .with_context(|| format!("Failed to resolve robot address {addr}"))?;
+
+    let mut controller = XBoxController::new(args.rate, args.stick_layout);
+    let period = Duration::from_secs_f64(1.0 / args.rate.max(1) as f64);
+    let mut seq: u64 = 0;
+    let mut send_errors: u64 = 0;
+
+    println!("Forwarding gamepad to udp://{addr} at {} Hz (Ctrl-C to stop)", args.rate);
+
+    loop {
+        let tick_start = Instant::now();
+
+        let packet = TeleopPacket::from_output(seq, controller.get_last_command());
+        let payload = serde_json::to_vec(&packet)?;
+        // A robot that is not listening yet shows up as send errors; keep trying
+        if socket.send(&payload).is_err() {
+            send_errors += 1;
+        }
+        seq += 1;
+
+        let c = packet.commands;
+        print!(
+            "\rvx {:+.2}  vy {:+.2}  yaw {:+.2}  head [{:+.2} {:+.2} {:+.2}]  sent {}  errors {}   ",
+            c[0], c[1], c[2], c[4], c[5], c[6], seq, send_errors
+        );
+        std::io::stdout().flush()?;
+
+        if let Some(remaining) = period.checked_sub(tick_start.elapsed()) {
+            std::thread::sleep(remaining);
+        }
+    }
+}
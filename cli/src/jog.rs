@@ -0,0 +1,178 @@
+//! `openduckrust jog` — move single joints of a robot on its stand.
+//!
+//! Connects to the runtime's WebSocket remote (`--remote-listen`), asks it
+//! for jog mode and confirms it (jog mode switches torque on), then takes
+//! commands from the terminal:
+//!
+//! ```text
+//! left_knee +0.05   nudge a joint (rad); the runtime caps each step
+//! show              target and present position of every joint
+//! quit              leave jog mode
+//! ```
+//!
+//! The duck must be paused, and should be on its stand. The runtime's
+//! `--remote-key` is read from `OPENDUCKRUST_REMOTE_KEY`.
+
+use anyhow::{bail, Context, Result};
+use std::io::{BufRead, ErrorKind, Write as _};
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tungstenite::{Message, WebSocket};
+
+use openduckrust_runtime::remote::{RemoteCommand, Telemetry, DEFAULT_PORT};
+
+/// How long to wait for the runtime to answer `jog_start`.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Socket read timeout, so terminal input is picked up promptly.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(clap::Args)]
+pub struct JogArgs {
+    /// Robot address, as HOST or HOST:PORT (default port 9871)
+    #[arg(long, default_value = "localhost")]
+    robot: String,
+}
+
+pub fn run(args: JogArgs) -> Result<()> {
+    let addr = if args.robot.contains(':') {
+        args.robot.clone()
+    } else {
+        format!("{}:{DEFAULT_PORT}", args.robot)
+    };
+    let stream = TcpStream::connect(&addr).with_context(|| {
+        format!("Failed to connect to {addr} (is the runtime started with --remote-listen?)")
+    })?;
+    let (mut socket, _) =
+        tungstenite::client(format!("ws://{addr}/"), stream).context("WebSocket handshake failed")?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    print!("Jog mode switches torque on. Is the duck paused and on its stand? [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        println!("Not jogging");
+        return Ok(());
+    }
+
+    send(&mut socket, &RemoteCommand::JogStart)?;
+    let mut joint_names: Vec<String> = Vec::new();
+    let deadline = Instant::now() + CONFIRM_TIMEOUT;
+    let token = loop {
+        if Instant::now() > deadline {
+            bail!("{addr} did not ask to confirm jog mode");
+        }
+        let Some(message) = read(&mut socket)? else {
+            continue;
+        };
+        match message["type"].as_str() {
+            Some("hello") => joint_names = serde_json::from_value(message["joint_names"].clone())?,
+            Some("confirm_required") => break message["token"].as_str().unwrap_or("").to_string(),
+            Some("confirm_rejected") => bail!("Jog mode refused: {}", message["reason"]),
+            _ => {}
+        }
+    };
+    let key = std::env::var("OPENDUCKRUST_REMOTE_KEY").ok();
+    send(&mut socket, &RemoteCommand::Confirm { token, key })?;
+
+    // The runtime refuses unless paused, without e-stop or servo fault
+    let deadline = Instant::now() + CONFIRM_TIMEOUT;
+    let mut latest: Option<Telemetry> = None;
+    while !latest.as_ref().is_some_and(|t| t.jogging) {
+        if Instant::now() > deadline {
+            bail!("{addr} did not enter jog mode (is the duck paused? see the runtime log)");
+        }
+        if let Some(message) = read(&mut socket)?.filter(|m| m["type"] == "telemetry") {
+            latest = serde_json::from_value(message).ok();
+        }
+    }
+    println!("Jogging. Type `JOINT DELTA`, `show` or `quit`.");
+
+    // Terminal lines arrive on a channel so the socket keeps being read
+    let (line_tx, line_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if line_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    loop {
+        if let Some(message) = read(&mut socket)? {
+            match message["type"].as_str() {
+                Some("telemetry") => latest = serde_json::from_value(message).ok(),
+                Some("event") => println!("event: {}", message["event"]),
+                _ => {}
+            }
+        }
+
+        let line = match line_rx.try_recv() {
+            Ok(line) => line,
+            Err(mpsc::TryRecvError::Empty) => continue,
+            Err(mpsc::TryRecvError::Disconnected) => "quit".to_string(),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["quit" | "q"] => break,
+            ["show"] => show(&joint_names, latest.as_ref()),
+            [joint, delta] => match delta.parse::<f64>() {
+                Ok(delta) => send(
+                    &mut socket,
+                    &RemoteCommand::Jog {
+                        joint: joint.to_string(),
+                        delta,
+                    },
+                )?,
+                Err(_) => println!("Not a number: {delta}"),
+            },
+            _ => println!("Type `JOINT DELTA` (e.g. `left_knee +0.05`), `show` or `quit`"),
+        }
+    }
+
+    send(&mut socket, &RemoteCommand::JogStop)?;
+    let _ = socket.close(None);
+    let _ = socket.flush();
+    println!("Jog mode off");
+    Ok(())
+}
+
+/// Print every joint's jog target and present position.
+fn show(joint_names: &[String], telemetry: Option<&Telemetry>) {
+    let Some(t) = telemetry.filter(|t| t.jogging) else {
+        println!("Not jogging (yet): is the duck paused?");
+        return;
+    };
+    println!("{:>16}  {:>8}  {:>8}", "joint", "target", "present");
+    for (i, name) in joint_names.iter().enumerate() {
+        let target = t.motor_targets.get(i).map_or("?".to_string(), |v| format!("{v:+.3}"));
+        let present = t.joint_positions.get(i).map_or("?".to_string(), |v| format!("{v:+.3}"));
+        println!("{name:>16}  {target:>8}  {present:>8}");
+    }
+}
+
+pub(crate) fn send(socket: &mut WebSocket<TcpStream>, command: &RemoteCommand) -> Result<()> {
+    socket
+        .send(Message::text(serde_json::to_string(command)?))
+        .context("Failed to send the command")
+}
+
+/// Next JSON message from the runtime, or `None` if nothing arrived in time.
+pub(crate) fn read(socket: &mut WebSocket<TcpStream>) -> Result<Option<serde_json::Value>> {
+    match socket.read() {
+        Ok(Message::Text(text)) => Ok(serde_json::from_str(&text).ok()),
+        Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => {
+            bail!("The runtime closed the connection")
+        }
+        Ok(_) => Ok(None),
+        Err(tungstenite::Error::Io(e))
+            if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
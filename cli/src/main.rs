@@ -1,10 +1,26 @@
 use clap::Parser;
+use openduckrust_runtime::logging::{self, LogArgs};
+
+mod alerts;
+mod api;
+mod blackbox;
+mod calibrate;
+mod config;
+mod emotes;
+mod gamepad;
+mod jog;
+mod motors;
+mod routine;
+mod simulate;
 
 #[derive(Parser)]
 #[command(name = "openduckrust", about = "openduckrust CLI")]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[command(flatten)]
+    log: LogArgs,
 }
 
 #[derive(clap::Subcommand)]
@@ -13,13 +29,73 @@ enum Commands {
     Health,
     /// Login to the platform
     Login { #[arg(short, long)] email: String },
+    /// Manage fleet alerts
+    Alerts {
+        #[command(subcommand)]
+        command: alerts::AlertsCommand,
+    },
+    /// Forward a locally connected gamepad to a robot
+    Gamepad(gamepad::GamepadArgs),
+    /// Run a policy on simulated servos with a live terminal view
+    Simulate(simulate::SimulateArgs),
+    /// Measure joint offsets by posing the robot and save them to the duck config
+    Calibrate(calibrate::CalibrateArgs),
+    /// Move single joints of a paused robot on its stand
+    Jog(jog::JogArgs),
+    /// Run walk-in-place or the step test on a robot and print the report
+    Routine(routine::RoutineArgs),
+    /// Servo bus diagnostics
+    Motors {
+        #[command(subcommand)]
+        command: motors::MotorsCommand,
+    },
+    /// List, play, and push recorded emotes
+    Emotes {
+        #[command(subcommand)]
+        command: emotes::EmotesCommand,
+    },
+    /// Push, pull, validate and create robot configs
+    Config {
+        #[command(subcommand)]
+        command: config::ConfigCommand,
+    },
+    /// Inspect flight recorder dumps written on falls and faults
+    Blackbox {
+        #[command(subcommand)]
+        command: blackbox::BlackboxCommand,
+    },
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    let _log_guard = logging::init(&cli.log, "openduckrust-cli", "warn")?;
+    let api = api::ApiClient::from_env();
     match cli.command {
         Commands::Health => { println!("TODO: ping API health endpoint"); }
         Commands::Login { email } => { println!("TODO: authenticate {email}"); }
+        Commands::Alerts { command } => alerts::run(&api, command).await?,
+        Commands::Gamepad(args) => {
+            tokio::task::spawn_blocking(move || gamepad::run(args)).await??
+        }
+        Commands::Simulate(args) => {
+            tokio::task::spawn_blocking(move || simulate::run(args)).await??
+        }
+        Commands::Calibrate(args) => {
+            tokio::task::spawn_blocking(move || calibrate::run(args)).await??
+        }
+        Commands::Jog(args) => tokio::task::spawn_blocking(move || jog::run(args)).await??,
+        Commands::Routine(args) => {
+            tokio::task::spawn_blocking(move || routine::run(args)).await??
+        }
+        Commands::Motors { command } => {
+            tokio::task::spawn_blocking(move || motors::run(command)).await??
+        }
+        Commands::Emotes { command } => emotes::run(&api, command).await?,
+        Commands::Config { command } => config::run(&api, command).await?,
+        Commands::Blackbox { command } => {
+            tokio::task::spawn_blocking(move || blackbox::run(command)).await??
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,225 @@
+//! `openduckrust motors` — servo bus diagnostics.
+//!
+//! `motors scan` pings every ID on the Feetech bus and lists the servos that
+//! answer, then checks them against the joint map: missing joints, IDs
+//! shared by two servos, and servos no joint uses.
+//!
+//! `motors dump` saves every servo's register table to a JSON file and
+//! `motors restore` writes the saved settings back, e.g. onto a replacement
+//! servo still at its factory ID (`--servo left_knee --onto 1`). `motors
+//! set` changes one register, such as the return delay or the baud rate.
+
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+
+use openduckrust_runtime::bus_scan::{model_name, BusScanner, ScanReport, COMMON_BAUD_RATES};
+use openduckrust_runtime::config::DuckConfig;
+use openduckrust_runtime::motors::MotorController;
+use openduckrust_runtime::servo_registers::{restore_plan, RegisterDump, RegisterWrite};
+
+use crate::calibrate::duck_config_path;
+
+#[derive(clap::Subcommand)]
+pub enum MotorsCommand {
+    /// Ping every servo ID and check the bus against the joint map
+    Scan(ScanArgs),
+    /// Save the register table of every servo to a JSON file
+    Dump {
+        /// Output file
+        output: PathBuf,
+        #[command(flatten)]
+        bus: BusArgs,
+    },
+    /// Write saved register settings back to the servos
+    Restore(RestoreArgs),
+    /// Write one register of one servo
+    Set {
+        /// Servo ID
+        #[arg(long)]
+        id: u8,
+        /// Register name, e.g. return_delay, baud_rate, min_angle_limit
+        register: String,
+        value: u16,
+        #[command(flatten)]
+        bus: BusArgs,
+    },
+}
+
+#[derive(clap::Args)]
+pub struct BusArgs {
+    /// Serial port of the servo bus
+    #[arg(long, default_value = "/dev/ttyACM0")]
+    serial_port: String,
+    /// Duck config giving the robot model (default: ~/duck_config.json)
+    #[arg(long)]
+    duck_config_path: Option<PathBuf>,
+}
+
+impl BusArgs {
+    fn open(&self) -> Result<MotorController> {
+        let config = DuckConfig::load(&duck_config_path(self.duck_config_path.clone())?)?;
+        MotorController::new(&config, &self.serial_port)
+    }
+}
+
+#[derive(clap::Args)]
+pub struct RestoreArgs {
+    /// Dump file written by `motors dump`
+    input: PathBuf,
+    /// Restore only this servo, by joint name or ID (default: all)
+    #[arg(long)]
+    servo: Option<String>,
+    /// Write it onto the servo currently at this ID, e.g. a new servo at
+    /// its factory ID 1
+    #[arg(long, requires = "servo")]
+    onto: Option<u8>,
+    /// Show the writes without making them
+    #[arg(long)]
+    dry_run: bool,
+    #[command(flatten)]
+    bus: BusArgs,
+}
+
+#[derive(clap::Args)]
+pub struct ScanArgs {
+    /// Serial port of the servo bus
+    #[arg(long, default_value = "/dev/ttyACM0")]
+    serial_port: String,
+    /// Baud rates to scan
+    #[arg(long, value_delimiter = ',', default_value = "1000000")]
+    baud: Vec<u32>,
+    /// Scan every baud rate the STS3215 supports (slow)
+    #[arg(long, conflicts_with = "baud")]
+    all_bauds: bool,
+    /// Expect only these joints, e.g. on a test rig (default: all)
+    #[arg(long, value_delimiter = ',')]
+    joints: Vec<String>,
+    /// Duck config giving the robot model (default: ~/duck_config.json)
+    #[arg(long)]
+    duck_config_path: Option<PathBuf>,
+}
+
+pub fn run(command: MotorsCommand) -> Result<()> {
+    match command {
+        MotorsCommand::Scan(args) => scan(args),
+        MotorsCommand::Dump { output, bus } => {
+            let dump = bus.open()?.dump_all_registers()?;
+            dump.save(&output)?;
+            println!("Saved the registers of {} servos to {}", dump.servos.len(), output.display());
+            Ok(())
+        }
+        MotorsCommand::Restore(args) => restore(args),
+        MotorsCommand::Set { id, register, value, bus } => {
+            bus.open()?.set_register(id, &register, value)?;
+            println!("Servo {id}: {register} = {value}");
+            Ok(())
+        }
+    }
+}
+
+fn restore(args: RestoreArgs) -> Result<()> {
+    let dump = RegisterDump::load(&args.input)?;
+    let targets: Vec<(u8, _)> = match args.servo {
+        Some(ref servo) => {
+            let saved = dump
+                .find(servo)
+                .with_context(|| format!("No servo {servo:?} in {}", args.input.display()))?;
+            vec![(args.onto.unwrap_or(saved.id), saved)]
+        }
+        None => dump.servos.iter().map(|saved| (saved.id, saved)).collect(),
+    };
+
+    let mut motors = args.bus.open()?;
+    for (id, saved) in targets {
+        let label = saved.joint.clone().unwrap_or_else(|| saved.id.to_string());
+        let writes = if args.dry_run {
+            let current = motors.dump_registers(id)?;
+            restore_plan(&current, saved)
+        } else {
+            motors.restore_registers(id, saved)?
+        };
+        if writes.is_empty() {
+            println!("Servo {id} ({label}): already matches");
+            continue;
+        }
+        let verb = if args.dry_run { "would write" } else { "wrote" };
+        println!("Servo {id} ({label}): {verb}");
+        for RegisterWrite { register, from, to } in writes {
+            let from = from.map_or("?".to_string(), |v| v.to_string());
+            println!("  {:<28} {:>6} -> {}", register.name, from, to);
+        }
+    }
+    Ok(())
+}
+
+fn scan(args: ScanArgs) -> Result<()> {
+    let config = DuckConfig::load(&duck_config_path(args.duck_config_path.clone())?)?;
+    let model = &config.robot_model;
+    for name in &args.joints {
+        if !model.contains(name) {
+            bail!("Unknown joint {name:?}");
+        }
+    }
+    let expected: Vec<(&str, u8)> = model
+        .joints
+        .iter()
+        .filter(|joint| args.joints.is_empty() || args.joints.contains(&joint.name))
+        .map(|joint| (joint.name.as_str(), joint.id))
+        .collect();
+
+    let bauds = if args.all_bauds {
+        COMMON_BAUD_RATES.to_vec()
+    } else {
+        args.baud
+    };
+    let mut servos = Vec::new();
+    for baud in bauds {
+        println!("Scanning {} at {} baud...", args.serial_port, baud);
+        servos.extend(BusScanner::open(&args.serial_port, baud)?.scan()?);
+    }
+    let report = ScanReport::new(servos, &expected);
+
+    println!(
+        "\n{:>4}  {:>16}  {:>9}  {:>8}  {:>8}  {:>9}",
+        "id", "joint", "baud", "model", "firmware", "position"
+    );
+    for servo in &report.servos {
+        let joint = expected
+            .iter()
+            .find(|(_, id)| *id == servo.id)
+            .map_or("-", |(name, _)| name);
+        let model = match servo.model {
+            Some(model) => model_name(model).map_or(model.to_string(), str::to_string),
+            None => "?".to_string(),
+        };
+        let firmware = servo.firmware.map_or("?".to_string(), |(major, minor)| format!("{major}.{minor}"));
+        let position = servo.position.map_or("?".to_string(), |p| format!("{p:+.3}"));
+        let flag = if servo.collided() { "  collision" } else { "" };
+        println!(
+            "{:>4}  {:>16}  {:>9}  {:>8}  {:>8}  {:>9}{}",
+            servo.id, joint, servo.baud, model, firmware, position, flag
+        );
+    }
+    println!();
+
+    for (name, id) in &report.missing {
+        println!("missing: {name} (ID {id}) did not answer");
+    }
+    for id in &report.duplicates {
+        println!("duplicate: ID {id} seems shared by more than one servo");
+    }
+    for id in &report.unexpected {
+        println!("unexpected: ID {id} answered but no joint uses it");
+    }
+
+    if !report.is_ok() {
+        bail!(
+            "Bus does not match the joint map ({} missing, {} duplicate, {} unexpected)",
+            report.missing.len(),
+            report.duplicates.len(),
+            report.unexpected.len()
+        );
+    }
+    println!("All {} joints found, no conflicts", expected.len());
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! `openduckrust routine` — run a calibration routine on a robot and print
+//! its report.
+//!
+//! Connects to the runtime's WebSocket remote (`--remote-listen`), starts
+//! `walk-in-place` or `step-test`, and waits for the `routine_finished`
+//! event with the per-leg touchdowns, stance, sway and warnings. The duck
+//! must be unpaused and standing. Leaving early keeps the routine running;
+//! `--stop` stops it.
+
+use anyhow::{bail, Context, Result};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use openduckrust_runtime::remote::{RemoteCommand, DEFAULT_PORT};
+use openduckrust_runtime::routines::{Routine, RoutineReport};
+
+use crate::jog::{read, send};
+
+/// Socket read timeout while waiting for the report.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(clap::Args)]
+pub struct RoutineArgs {
+    /// walk-in-place or step-test
+    #[arg(required_unless_present = "stop")]
+    routine: Option<String>,
+    /// Robot address, as HOST or HOST:PORT (default port 9871)
+    #[arg(long, default_value = "localhost")]
+    robot: String,
+    /// Stop the routine running on the robot
+    #[arg(long)]
+    stop: bool,
+}
+
+pub fn run(args: RoutineArgs) -> Result<()> {
+    let addr = if args.robot.contains(':') {
+        args.robot.clone()
+    } else {
+        format!("{}:{DEFAULT_PORT}", args.robot)
+    };
+    let stream = TcpStream::connect(&addr).with_context(|| {
+        format!("Failed to connect to {addr} (is the runtime started with --remote-listen?)")
+    })?;
+    let (mut socket, _) =
+        tungstenite::client(format!("ws://{addr}/"), stream).context("WebSocket handshake failed")?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    if args.stop {
+        send(&mut socket, &RemoteCommand::StopRoutine)?;
+        let _ = socket.close(None);
+        let _ = socket.flush();
+        println!("Asked {addr} to stop its routine");
+        return Ok(());
+    }
+
+    let routine: Routine = args.routine.as_deref().unwrap_or_default().parse()?;
+    send(&mut socket, &RemoteCommand::StartRoutine { routine })?;
+    println!("Running {} on {addr}, waiting for the report...", routine.name());
+
+    loop {
+        let Some(message) = read(&mut socket)? else {
+            continue;
+        };
+        // Routines don't run, and stop, while the duck is paused
+        if message["type"] == "telemetry" && message["paused"] == true {
+            bail!("{addr} is paused: unpause the duck to run {}", routine.name());
+        }
+        let event = &message["event"];
+        if message["type"] != "event" || event["type"] != "routine_finished" {
+            continue;
+        }
+        let report: RoutineReport = serde_json::from_value(event["report"].clone())
+            .context("Unreadable routine report")?;
+        if report.routine != routine {
+            continue;
+        }
+        print!("{report}");
+        let _ = socket.close(None);
+        let _ = socket.flush();
+        if !report.completed {
+            bail!("{} was stopped before the end (see the runtime log)", routine.name());
+        }
+        return Ok(());
+    }
+}
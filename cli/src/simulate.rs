@@ -0,0 +1,457 @@
+//! `openduckrust simulate` — run a policy against the simulated servos and
+//! watch it in the terminal.
+//!
+//! This is the runtime's control loop with the `sim` motor backend and mock
+//! sensors, so contributors without a robot can see what a policy does. Every
+//! few ticks the terminal is redrawn with a side-view stick figure of the duck
+//! and a rolling trace of each joint around its init position.
+
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use openduckrust_runtime::config::OrtConfig;
+use openduckrust_runtime::controller::{X_RANGE, YAW_RANGE, Y_RANGE};
+use openduckrust_runtime::imu::{ImuReader, MockImu};
+use openduckrust_runtime::inference::{self, InferenceBackend};
+use openduckrust_runtime::motors::{MotorInterface, SimMotorController};
+use openduckrust_runtime::observation::{ObservationBuilder, ObservationInputs, ObservationManifest};
+use openduckrust_runtime::orientation::ImuObservation;
+use openduckrust_runtime::peripherals::{FeetContactsReader, MockFeetContacts};
+use openduckrust_runtime::reference_motion::{self, PhaseTracker, PolyCoefficients};
+use openduckrust_runtime::robot_model::RobotModel;
+
+/// Terminal refresh rate; the control loop itself runs at `--control-freq`.
+const RENDER_HZ: u32 = 15;
+
+/// Stick figure canvas size in characters.
+const CANVAS_WIDTH: usize = 44;
+const CANVAS_HEIGHT: usize = 18;
+
+/// Canvas scale: columns per meter (rows are twice as tall as columns are wide).
+const COLS_PER_METER: f64 = 110.0;
+
+/// Samples kept per joint trace.
+const TRACE_WIDTH: usize = 60;
+
+/// Smallest range a trace is stretched over (rad), so a joint holding still
+/// does not turn sensor noise into a full-height wave.
+const TRACE_MIN_SPAN: f64 = 0.1;
+
+/// Approximate Open Duck Mini link lengths (m).
+const THIGH_LENGTH: f64 = 0.09;
+const SHIN_LENGTH: f64 = 0.09;
+const FOOT_LENGTH: f64 = 0.06;
+const NECK_LENGTH: f64 = 0.08;
+const HEAD_LENGTH: f64 = 0.06;
+
+#[derive(clap::Args)]
+pub struct SimulateArgs {
+    /// Path to the trained ONNX policy model
+    #[arg(long)]
+    model: PathBuf,
+    /// Observation manifest (default: `<model>.obs.json` if present)
+    #[arg(long)]
+    obs_manifest: Option<PathBuf>,
+    /// IMU values observed after the gyro when there is no manifest
+    #[arg(long, value_enum, default_value_t = ImuObservation::Accel)]
+    imu_obs: ImuObservation,
+    /// Inference engine running the policy
+    #[arg(long, value_enum, default_value_t = InferenceBackend::Ort)]
+    inference_backend: InferenceBackend,
+    /// Polynomial coefficients file giving the gait period (default: 25 steps)
+    #[arg(long)]
+    poly_coefficients: Option<PathBuf>,
+    /// Control loop frequency in Hz
+    #[arg(short = 'c', long, default_value_t = 50)]
+    control_freq: u32,
+    /// Action scale factor applied to policy output
+    #[arg(short = 'a', long, default_value_t = 0.25)]
+    action_scale: f64,
+    /// Proportional gain of the simulated leg servos
+    #[arg(short = 'p', long, default_value_t = 30)]
+    kp: u32,
+    /// Forward velocity command (m/s)
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    vx: f64,
+    /// Lateral velocity command (m/s)
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    vy: f64,
+    /// Yaw rate command (rad/s)
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    yaw: f64,
+    /// Stop after this many seconds (default: run until Ctrl-C)
+    #[arg(long)]
+    duration: Option<f64>,
+}
+
+pub fn run(args: SimulateArgs) -> Result<()> {
+    let ort = OrtConfig::default();
+    let mut policy = inference::load_policy(args.inference_backend, &ort, &args.model)
+        .with_context(|| format!("Failed to load policy {}", args.model.display()))?;
+
+    let manifest = match args.obs_manifest {
+        Some(ref path) => ObservationManifest::load(path)?,
+        None => {
+            let path = ObservationManifest::path_for_model(&args.model);
+            if path.exists() {
+                ObservationManifest::load(&path)?
+            } else {
+                ObservationManifest::default_layout(args.imu_obs)
+            }
+        }
+    };
+    // The stick figure is drawn for the 14-joint Mini v2
+    let model = RobotModel::default();
+    let num_dofs = model.num_dofs();
+    manifest
+        .validate(num_dofs, policy.input_dim())
+        .context("Observation layout does not match the model")?;
+    let mut obs_builder = ObservationBuilder::new(manifest, num_dofs);
+
+    let mut hwi = SimMotorController::for_model(&model);
+    let mut kps = vec![args.kp as f64; num_dofs];
+    // Same compliant head as on the robot
+    for name in ["neck_pitch", "head_pitch", "head_yaw", "head_roll"] {
+        if let Some(i) = model.index_of(name) {
+            kps[i] = 8.0;
+        }
+    }
+    hwi.set_kps(&kps)?;
+    hwi.turn_on()?;
+
+    let imu = MockImu::new();
+    let feet_contacts = MockFeetContacts;
+
+    let poly = args
+        .poly_coefficients
+        .as_deref()
+        .map(reference_motion::load_pickle)
+        .transpose()?
+        .flatten();
+    let nb_steps = poly.as_ref().map_or(25, PolyCoefficients::nb_steps_in_period);
+    let mut phase_tracker = PhaseTracker::new(nb_steps, 0.0);
+    phase_tracker.frequency_map = poly.as_ref().map(PolyCoefficients::frequency_map).unwrap_or_default();
+
+    let mut commands = [0.0f64; 7];
+    commands[0] = args.vx.clamp(X_RANGE[0], X_RANGE[1]);
+    commands[1] = args.vy.clamp(Y_RANGE[0], Y_RANGE[1]);
+    commands[2] = args.yaw.clamp(YAW_RANGE[0], YAW_RANGE[1]);
+
+    let init_pos = hwi.init_positions_array();
+    let joint_names = hwi.joint_names().to_vec();
+    let mut motor_targets = init_pos.clone();
+    let mut traces = JointTraces::new(num_dofs);
+
+    let control_period = Duration::from_secs_f64(1.0 / args.control_freq as f64);
+    let render_every = (args.control_freq / RENDER_HZ).max(1) as u64;
+    let start = Instant::now();
+    let mut tick: u64 = 0;
+    let mut inference_ms = 0.0;
+    let mut overruns: u64 = 0;
+
+    // Clear once; every frame then redraws from the top-left corner
+    print!("\x1b[2J");
+
+    loop {
+        let tick_start = Instant::now();
+        if args.duration.is_some_and(|d| start.elapsed().as_secs_f64() >= d) {
+            break;
+        }
+
+        let (Some(dof_pos), Some(dof_vel)) =
+            (hwi.get_present_positions(), hwi.get_present_velocities())
+        else {
+            continue;
+        };
+
+        let imu_data = imu.get_data();
+        let feet = feet_contacts.get();
+        let phase = phase_tracker.step(&commands);
+
+        let obs = obs_builder.build(&ObservationInputs {
+            imu: &imu_data,
+            commands: &commands,
+            dof_pos: &dof_pos,
+            init_pos: &init_pos,
+            dof_vel: &dof_vel,
+            motor_targets: &motor_targets,
+            feet_contacts: &feet,
+            phase: &phase,
+        });
+
+        let infer_start = Instant::now();
+        let action = policy.infer(&obs).context("Inference failed")?;
+        let took_ms = infer_start.elapsed().as_secs_f64() * 1000.0;
+        inference_ms = if tick == 0 { took_ms } else { 0.9 * inference_ms + 0.1 * took_ms };
+        obs_builder.push_action(&action);
+
+        motor_targets = init_pos
+            .iter()
+            .zip(&action)
+            .map(|(&init, &act)| init + act * args.action_scale)
+            .collect();
+        hwi.set_position_all_array(&motor_targets)?;
+
+        let relative: Vec<f64> = dof_pos.iter().zip(&init_pos).map(|(p, i)| p - i).collect();
+        traces.push(&relative);
+
+        if tick.is_multiple_of(render_every) {
+            let mut frame = String::from("\x1b[H");
+            let _ = writeln!(
+                frame,
+                "openduckrust simulate — {}  t={:.1}s  tick={}  inference={:.2}ms  overruns={}\x1b[K",
+                args.model.display(),
+                start.elapsed().as_secs_f64(),
+                tick,
+                inference_ms,
+                overruns
+            );
+            let _ = writeln!(
+                frame,
+                "command vx={:+.2} vy={:+.2} yaw={:+.2}  phase=[{:+.2}, {:+.2}]\x1b[K\n",
+                commands[0], commands[1], commands[2], phase[0], phase[1]
+            );
+            frame.push_str(&stick_figure(&joint_names, &dof_pos).render());
+            frame.push('\n');
+            frame.push_str(&traces.render(&joint_names));
+            frame.push_str("\x1b[J");
+
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(frame.as_bytes())?;
+            stdout.flush()?;
+        }
+        tick += 1;
+
+        let took = tick_start.elapsed();
+        if took > control_period {
+            overruns += 1;
+        } else {
+            std::thread::sleep(control_period - took);
+        }
+    }
+
+    hwi.turn_off()?;
+    println!(
+        "\nSimulated {:.1}s ({} ticks), average inference {:.2}ms, {} overruns",
+        start.elapsed().as_secs_f64(),
+        tick,
+        inference_ms,
+        overruns
+    );
+    Ok(())
+}
+
+// ── Stick figure ──
+
+/// Point in the sagittal plane (m): x forward, y up, hips at the origin.
+type Point = (f64, f64);
+
+/// Offset `length` along a direction measured from straight down, positive
+/// angles swinging backward.
+fn swing(from: Point, length: f64, angle: f64) -> Point {
+    (from.0 - length * angle.sin(), from.1 - length * angle.cos())
+}
+
+/// Knee, ankle, heel, and toe of one leg from its hip pitch, knee, and ankle
+/// angles (hip pitch already mirrored for the right side).
+fn leg_points(hip_pitch: f64, knee: f64, ankle: f64) -> [Point; 4] {
+    let knee_point = swing((0.0, 0.0), THIGH_LENGTH, hip_pitch);
+    let ankle_point = swing(knee_point, SHIN_LENGTH, hip_pitch + knee);
+
+    // The foot is flat when the three angles cancel out
+    let foot = hip_pitch + knee + ankle;
+    let (dx, dy) = (foot.cos(), foot.sin());
+    let heel = (ankle_point.0 - 0.3 * FOOT_LENGTH * dx, ankle_point.1 - 0.3 * FOOT_LENGTH * dy);
+    let toe = (ankle_point.0 + 0.7 * FOOT_LENGTH * dx, ankle_point.1 + 0.7 * FOOT_LENGTH * dy);
+    [knee_point, ankle_point, heel, toe]
+}
+
+/// Side view of the duck standing on the ground line.
+fn stick_figure(joint_names: &[String], positions: &[f64]) -> Canvas {
+    let joint = |name: &str| {
+        joint_names
+            .iter()
+            .position(|n| n == name)
+            .and_then(|i| positions.get(i).copied())
+            .unwrap_or(0.0)
+    };
+
+    // The hip pitch servos are mounted mirrored
+    let left = leg_points(joint("left_hip_pitch"), joint("left_knee"), joint("left_ankle"));
+    let right = leg_points(-joint("right_hip_pitch"), joint("right_knee"), joint("right_ankle"));
+
+    let tail = (-0.07, 0.02);
+    let chest = (0.05, 0.03);
+    let neck_top = swing(chest, -NECK_LENGTH, joint("neck_pitch"));
+    let head_angle = joint("neck_pitch") + joint("head_pitch");
+    let beak = (
+        neck_top.0 + HEAD_LENGTH * head_angle.cos(),
+        neck_top.1 - HEAD_LENGTH * head_angle.sin(),
+    );
+
+    // Stand the lowest foot point on the ground
+    let ground = left
+        .iter()
+        .chain(&right)
+        .map(|p| p.1)
+        .fold(f64::INFINITY, f64::min);
+
+    let mut canvas = Canvas::new(CANVAS_WIDTH, CANVAS_HEIGHT);
+    let origin = (CANVAS_WIDTH as f64 / 2.0, (CANVAS_HEIGHT - 2) as f64);
+    let to_cell = |p: Point| {
+        (
+            origin.0 + p.0 * COLS_PER_METER,
+            origin.1 - (p.1 - ground) * COLS_PER_METER / 2.0,
+        )
+    };
+
+    for x in 0..CANVAS_WIDTH {
+        canvas.set(x as f64, origin.1 + 1.0, '_');
+    }
+
+    // Right leg first so the left leg is drawn in front of it
+    for (leg, c) in [(&right, ':'), (&left, '#')] {
+        let [knee, ankle, heel, toe] = leg.map(to_cell);
+        canvas.line(to_cell((0.0, 0.0)), knee, c);
+        canvas.line(knee, ankle, c);
+        canvas.line(heel, toe, c);
+    }
+
+    canvas.line(to_cell(tail), to_cell(chest), 'o');
+    canvas.line(to_cell((0.0, 0.0)), to_cell((0.0, 0.025)), 'o');
+    canvas.line(to_cell(chest), to_cell(neck_top), '|');
+    canvas.line(to_cell(neck_top), to_cell(beak), '=');
+    canvas.set(to_cell(neck_top).0, to_cell(neck_top).1, '@');
+
+    canvas
+}
+
+/// Character grid with line drawing.
+struct Canvas {
+    width: usize,
+    height: usize,
+    cells: Vec<char>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![' '; width * height],
+        }
+    }
+
+    /// Set the cell containing (x, y); points off the canvas are ignored.
+    fn set(&mut self, x: f64, y: f64, c: char) {
+        let (x, y) = (x.round(), y.round());
+        if x >= 0.0 && y >= 0.0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.cells[y as usize * self.width + x as usize] = c;
+        }
+    }
+
+    fn line(&mut self, from: (f64, f64), to: (f64, f64), c: char) {
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0);
+        for i in 0..=steps as usize {
+            let t = i as f64 / steps;
+            self.set(from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t, c);
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::with_capacity((self.width + 4) * self.height);
+        for row in self.cells.chunks(self.width) {
+            out.extend(row);
+            out.push_str("\x1b[K\n");
+        }
+        out
+    }
+}
+
+// ── Joint traces ──
+
+/// Rolling history of every joint's offset from its init position.
+struct JointTraces {
+    history: Vec<VecDeque<f64>>,
+}
+
+impl JointTraces {
+    fn new(num_joints: usize) -> Self {
+        Self {
+            history: vec![VecDeque::with_capacity(TRACE_WIDTH); num_joints],
+        }
+    }
+
+    fn push(&mut self, values: &[f64]) {
+        for (trace, &v) in self.history.iter_mut().zip(values) {
+            if trace.len() == TRACE_WIDTH {
+                trace.pop_front();
+            }
+            trace.push_back(v);
+        }
+    }
+
+    fn render(&self, joint_names: &[String]) -> String {
+        let mut out = String::new();
+        for (trace, name) in self.history.iter().zip(joint_names) {
+            let current = trace.back().copied().unwrap_or(0.0);
+            let _ = writeln!(
+                out,
+                "{:<16} {:>+7.1}°  {}\x1b[K",
+                name,
+                current.to_degrees(),
+                sparkline(trace.iter().copied())
+            );
+        }
+        out
+    }
+}
+
+/// One row of block characters, scaled to the range of the values (at least
+/// `TRACE_MIN_SPAN` wide).
+fn sparkline(values: impl Iterator<Item = f64> + Clone) -> String {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let (min, max) = values
+        .clone()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    if !min.is_finite() {
+        return String::new();
+    }
+    let mid = (min + max) / 2.0;
+    let span = (max - min).max(TRACE_MIN_SPAN);
+    let low = mid - span / 2.0;
+
+    values
+        .map(|v| {
+            let level = ((v - low) / span * (LEVELS.len() - 1) as f64).round();
+            LEVELS[(level as usize).min(LEVELS.len() - 1)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_straight_leg_hangs_below_hip() {
+        let [knee, ankle, heel, toe] = leg_points(0.0, 0.0, 0.0);
+        assert!(knee.0.abs() < 1e-12 && (knee.1 + THIGH_LENGTH).abs() < 1e-12);
+        assert!((ankle.1 + THIGH_LENGTH + SHIN_LENGTH).abs() < 1e-12);
+        // Flat foot, toe pointing forward
+        assert!((heel.1 - toe.1).abs() < 1e-12);
+        assert!(toe.0 > heel.0);
+    }
+
+    #[test]
+    fn test_sparkline_scales_to_range() {
+        assert_eq!(sparkline([0.0, 0.5, 1.0].into_iter()), "▁▅█");
+        // Small wobbles stay near the middle instead of spanning full height
+        assert_eq!(sparkline([0.0, 0.0].into_iter()), "▅▅");
+    }
+}
@@ -37,7 +37,7 @@ if ! command -v aarch64-linux-gnu-gcc &>/dev/null; then
 fi
 
 cd "$PROJECT_DIR"
-cargo build --target "$TARGET" --release
+cargo build --target "$TARGET" --release --features gpu
 
 BINARY="target/${TARGET}/release/openduckrust-runtime"
 if [ -f "$BINARY" ]; then
@@ -13,7 +13,7 @@
 # On-device build (recommended for CUDA):
 #   1. SSH into the Jetson
 #   2. Install Rust: curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh
-#   3. cargo build --release
+#   3. cargo build --release --features gpu
 #   4. ORT_DYLIB_PATH=/usr/lib/libonnxruntime.so ./target/release/openduckrust-runtime ...
 # ──────────────────────────────────────────────────────────────────
 set -euo pipefail
@@ -35,7 +35,7 @@ echo ""
 echo "Cross-compilation produces a CPU-only binary. To enable CUDA:"
 echo "  1. SSH into the Jetson"
 echo "  2. Install Rust: curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh"
-echo "  3. cargo build --release"
+echo "  3. cargo build --release --features gpu"
 echo "  4. Set ORT_DYLIB_PATH to the CUDA-enabled libonnxruntime.so"
 echo ""
 
@@ -49,7 +49,7 @@ if ! command -v aarch64-linux-gnu-gcc &>/dev/null; then
 fi
 
 cd "$PROJECT_DIR"
-cargo build --target "$TARGET" --release
+cargo build --target "$TARGET" --release --features gpu
 
 BINARY="target/${TARGET}/release/openduckrust-runtime"
 if [ -f "$BINARY" ]; then
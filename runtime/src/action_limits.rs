@@ -0,0 +1,187 @@
+//! Safety envelope around the policy's motor targets.
+//!
+//! One bad inference output (a NaN, garbage observations, a model trained
+//! for another joint order) would otherwise send a joint across its whole
+//! range in a single tick. After inference, every target is held within
+//! `max_speed` times the control period of the policy's previous target and
+//! then, last, within `max_deviation` of the init pose, with per-joint
+//! overrides of both, so the limits mean the same at any control rate. A
+//! target that isn't a number keeps the previous one. Clamped targets are
+//! counted for telemetry.
+//!
+//! The previous targets are the envelope's own output, not what was sent:
+//! head offsets added afterwards would otherwise be fed back in and grow by
+//! a step every tick. Whatever else drives the joints (an emote) hands its
+//! targets over with `hold` so the policy eases back from them.
+
+use std::collections::HashMap;
+
+use crate::config::ActionLimitsConfig;
+
+/// Targets clamped by each limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Clamps {
+    /// Held back by the per-tick step limit (or not a number).
+    pub step: u32,
+    /// Held back by the deviation limit.
+    pub deviation: u32,
+}
+
+impl Clamps {
+    pub fn any(&self) -> bool {
+        self.step > 0 || self.deviation > 0
+    }
+}
+
+/// Per-joint limits on the policy's motor targets.
+pub struct ActionLimits {
+    init: Vec<f64>,
+    previous: Vec<f64>,
+    max_step: Vec<f64>,
+    max_deviation: Vec<f64>,
+    total: Clamps,
+}
+
+impl ActionLimits {
+    /// Limits for a loop ticking every `control_period` seconds.
+    pub fn new(
+        config: &ActionLimitsConfig,
+        joint_names: &[String],
+        init_pos: &[f64],
+        control_period: f64,
+    ) -> Self {
+        let per_joint = |limits: &HashMap<String, f64>, default: f64| -> Vec<f64> {
+            joint_names
+                .iter()
+                .map(|name| limits.get(name).copied().unwrap_or(default).abs())
+                .collect()
+        };
+        let max_speed = per_joint(&config.joint_max_speed, config.max_speed);
+        Self {
+            init: init_pos.to_vec(),
+            previous: init_pos.to_vec(),
+            max_step: max_speed.iter().map(|speed| speed * control_period).collect(),
+            max_deviation: per_joint(&config.joint_max_deviation, config.max_deviation),
+            total: Clamps::default(),
+        }
+    }
+
+    /// Clamp `targets` against the previous tick's. Returns what was
+    /// clamped this tick.
+    pub fn apply(&mut self, targets: &mut [f64]) -> Clamps {
+        let mut clamps = Clamps::default();
+        for (i, (target, previous)) in targets.iter_mut().zip(&mut self.previous).enumerate() {
+            if !target.is_finite() {
+                *target = *previous;
+                clamps.step += 1;
+                continue;
+            }
+            let (init, deviation) = (self.init[i], self.max_deviation[i]);
+            let (low, high) = (init - deviation, init + deviation);
+            let raw = *target;
+            let bounded = raw.clamp(low, high);
+            let step = self.max_step[i];
+            let stepped = bounded.clamp(*previous - step, *previous + step);
+            if stepped != bounded {
+                clamps.step += 1;
+            }
+            // Last, so a previous target outside the envelope can't keep one there
+            *target = stepped.clamp(low, high);
+            if raw != bounded || *target != stepped {
+                clamps.deviation += 1;
+            }
+            *previous = *target;
+        }
+        self.total.step += clamps.step;
+        self.total.deviation += clamps.deviation;
+        clamps
+    }
+
+    /// Take `targets`, sent by something other than the policy, as the
+    /// previous tick's.
+    pub fn hold(&mut self, targets: &[f64]) {
+        self.previous.copy_from_slice(targets);
+    }
+
+    /// Clamps since the start.
+    pub fn total(&self) -> Clamps {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_targets_held_within_step_and_deviation() {
+        let config = ActionLimitsConfig {
+            max_speed: 5.0,
+            max_deviation: 0.5,
+            joint_max_deviation: HashMap::from([("left_knee".to_string(), 1.0)]),
+            ..ActionLimitsConfig::default()
+        };
+        let joints: Vec<String> = vec!["left_hip_pitch".into(), "left_knee".into()];
+        // 0.1 rad per tick at 50 Hz
+        let mut limits = ActionLimits::new(&config, &joints, &[0.0, 1.0], 0.02);
+
+        // A slam is slowed to one step per tick
+        let mut targets = [0.05, 3.0];
+        let clamps = limits.apply(&mut targets);
+        assert_eq!(targets, [0.05, 1.1]);
+        assert_eq!(clamps, Clamps { step: 1, deviation: 1 });
+
+        // Never beyond the joint's deviation, and NaN keeps the last target
+        limits.hold(&[0.05, 1.95]);
+        let mut targets = [f64::NAN, 3.0];
+        limits.apply(&mut targets);
+        assert_eq!(targets, [0.05, 2.0]);
+        assert_eq!(limits.total(), Clamps { step: 2, deviation: 2 });
+
+        // Deviation wins over the step from a target held outside it
+        limits.hold(&[0.0, 2.5]);
+        let mut targets = [0.0, 2.5];
+        limits.apply(&mut targets);
+        assert_eq!(targets, [0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_step_scales_with_control_period() {
+        let config = ActionLimitsConfig {
+            joint_max_speed: HashMap::from([("left_knee".to_string(), 2.0)]),
+            ..ActionLimitsConfig::default()
+        };
+        let joints: Vec<String> = vec!["left_hip_pitch".into(), "left_knee".into()];
+
+        // The default 7.5 rad/s is 0.15 rad per tick at 50 Hz
+        let mut limits = ActionLimits::new(&config, &joints, &[0.0, 0.0], 0.02);
+        let mut targets = [1.0, 1.0];
+        limits.apply(&mut targets);
+        assert!((targets[0] - 0.15).abs() < 1e-12);
+        assert!((targets[1] - 0.04).abs() < 1e-12);
+
+        // and half of that at 100 Hz
+        let mut limits = ActionLimits::new(&config, &joints, &[0.0, 0.0], 0.01);
+        let mut targets = [1.0, 1.0];
+        limits.apply(&mut targets);
+        assert!((targets[0] - 0.075).abs() < 1e-12);
+        assert!((targets[1] - 0.02).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_offsets_after_limits_dont_accumulate() {
+        let config = ActionLimitsConfig::default();
+        let joints: Vec<String> = vec!["head_yaw".into()];
+        let mut limits = ActionLimits::new(&config, &joints, &[0.05], 0.02);
+
+        // A head offset above the step, held, added onto the policy's targets
+        let offset = 0.2;
+        for _ in 0..100 {
+            let mut targets = [0.05];
+            limits.apply(&mut targets);
+            let sent = targets[0] + offset;
+            assert!((sent - 0.25).abs() < 1e-12, "sent {}", sent);
+        }
+        assert_eq!(limits.total(), Clamps::default());
+    }
+}
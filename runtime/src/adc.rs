@@ -0,0 +1,84 @@
+//! ADC framing for force-sensitive resistor (FSR) feet.
+//!
+//! Two common hobby ADCs: the 10-bit MCP3008 on SPI, and the 16-bit
+//! ADS1115 on I2C, run in single-shot mode so each read converts the
+//! requested channel. Readings are fractions of the ADC's full scale and
+//! are mapped to a 0..1 contact value with each foot's calibration. Only
+//! the framing lives here; the buses are driven by the `peripherals` module.
+
+use crate::config::FsrConfig;
+use std::time::Duration;
+
+pub const MCP3008_CHANNELS: u8 = 8;
+pub const ADS1115_CHANNELS: u8 = 4;
+
+/// ADS1115 default I2C address (ADDR to ground).
+pub const ADS1115_ADDR: u16 = 0x48;
+pub const ADS1115_CONVERSION: u8 = 0x00;
+pub const ADS1115_CONFIG: u8 = 0x01;
+
+/// One conversion at 860 samples/s, with some margin.
+pub const ADS1115_CONVERSION_TIME: Duration = Duration::from_micros(1300);
+
+/// SPI frame selecting a single-ended MCP3008 channel.
+pub fn mcp3008_command(channel: u8) -> [u8; 3] {
+    [0x01, (0x08 | (channel & 0x07)) << 4, 0x00]
+}
+
+/// Reading clocked back during `mcp3008_command`.
+pub fn mcp3008_value(frame: &[u8; 3]) -> f64 {
+    let raw = (u16::from(frame[1] & 0x03) << 8) | u16::from(frame[2]);
+    f64::from(raw) / 1023.0
+}
+
+/// Config register starting a single-shot conversion of a single-ended
+/// ADS1115 channel, ±4.096 V range at 860 samples/s, comparator off.
+pub fn ads1115_single_shot(channel: u8) -> u16 {
+    const START: u16 = 1 << 15;
+    const SINGLE_ENDED: u16 = 0b100 << 12;
+    const FSR_4_096V: u16 = 0b001 << 9;
+    const SINGLE_SHOT: u16 = 1 << 8;
+    const SPS_860: u16 = 0b111 << 5;
+    const COMPARATOR_OFF: u16 = 0b11;
+    START
+        | SINGLE_ENDED
+        | (u16::from(channel & 0x03) << 12)
+        | FSR_4_096V
+        | SINGLE_SHOT
+        | SPS_860
+        | COMPARATOR_OFF
+}
+
+/// Conversion register contents (big-endian, signed).
+pub fn ads1115_value(bytes: [u8; 2]) -> f64 {
+    (f64::from(i16::from_be_bytes(bytes)) / 32767.0).max(0.0)
+}
+
+/// Contact value of a reading: 0 at the foot's `zero`, 1 from its
+/// `saturation` on.
+pub fn fsr_contact(reading: f64, fsr: &FsrConfig) -> f64 {
+    ((reading - fsr.zero) / (fsr.saturation - fsr.zero)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_and_contact_values() {
+        assert_eq!(mcp3008_command(1), [0x01, 0x90, 0x00]);
+        assert_eq!(mcp3008_value(&[0xFF, 0x03, 0xFF]), 1.0);
+        assert_eq!(ads1115_single_shot(0), 0xC3E3);
+        assert_eq!(ads1115_single_shot(3), 0xF3E3);
+        assert_eq!(ads1115_value([0x80, 0x00]), 0.0);
+
+        let fsr = FsrConfig {
+            channel: 0,
+            zero: 0.1,
+            saturation: 0.5,
+        };
+        assert_eq!(fsr_contact(0.05, &fsr), 0.0);
+        assert!((fsr_contact(0.3, &fsr) - 0.5).abs() < 1e-9);
+        assert_eq!(fsr_contact(0.9, &fsr), 1.0);
+    }
+}
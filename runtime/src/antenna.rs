@@ -0,0 +1,145 @@
+//! Antenna animations — short keyframed moves played over a resting pose.
+//!
+//! The antennas rest where the control loop puts them (centered, or swaying
+//! with the gait) and play an animation when something happens: a wiggle
+//! for sounds and emotes, a perk-up when the backend link comes back or the
+//! policy changes, a droop on low battery or a collision. Animations move
+//! from the resting pose through their keyframes and settle back, so they
+//! never jump. `Animator` is pure; the `peripherals` module drives the
+//! servos from it on a background thread.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::cloud::CloudStatus;
+use crate::events::Event;
+
+/// Time to settle back to the resting pose after the last keyframe.
+const SETTLE: f64 = 0.3;
+
+/// A preset antenna move. Positions are [left, right], -1..1 with +1
+/// raised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AntennaAnimation {
+    Wiggle,
+    PerkUp,
+    Droop,
+}
+
+impl AntennaAnimation {
+    /// (time from start in s, [left, right]) keyframes.
+    fn keyframes(self) -> &'static [(f64, [f64; 2])] {
+        match self {
+            AntennaAnimation::Wiggle => &[
+                (0.1, [0.6, -0.6]),
+                (0.2, [-0.6, 0.6]),
+                (0.3, [0.6, -0.6]),
+                (0.4, [-0.6, 0.6]),
+                (0.5, [0.6, -0.6]),
+            ],
+            AntennaAnimation::PerkUp => &[(0.15, [1.0, 1.0]), (0.9, [1.0, 1.0])],
+            AntennaAnimation::Droop => &[(0.5, [-0.8, -0.8]), (2.0, [-0.8, -0.8])],
+        }
+    }
+
+    pub fn duration(self) -> Duration {
+        let last = self.keyframes().last().map_or(0.0, |&(t, _)| t);
+        Duration::from_secs_f64(last + SETTLE)
+    }
+
+    /// The animation an event plays, if any.
+    pub fn for_event(event: &Event) -> Option<Self> {
+        match event {
+            Event::AntennaRequested { animation } => Some(*animation),
+            Event::SoundRequested { .. } | Event::EmoteRequested { .. } => {
+                Some(AntennaAnimation::Wiggle)
+            }
+            Event::PolicySwapped { .. } => Some(AntennaAnimation::PerkUp),
+            Event::CloudStatusChanged {
+                status: CloudStatus::Online,
+            } => Some(AntennaAnimation::PerkUp),
+            Event::LowBattery { .. } | Event::CollisionDetected { .. } => {
+                Some(AntennaAnimation::Droop)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Resting pose plus the animation being played over it.
+#[derive(Debug, Clone, Default)]
+pub struct Animator {
+    rest: [f64; 2],
+    playing: Option<(AntennaAnimation, Instant)>,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_rest(&mut self, rest: [f64; 2]) {
+        self.rest = rest.map(|p| p.clamp(-1.0, 1.0));
+    }
+
+    /// Start an animation, replacing the one playing.
+    pub fn play(&mut self, animation: AntennaAnimation, now: Instant) {
+        self.playing = Some((animation, now));
+    }
+
+    /// Back to center, dropping the animation.
+    pub fn stop(&mut self) {
+        self.rest = [0.0, 0.0];
+        self.playing = None;
+    }
+
+    /// [left, right] position at `now`.
+    pub fn pose(&mut self, now: Instant) -> [f64; 2] {
+        let Some((animation, start)) = self.playing else {
+            return self.rest;
+        };
+        let t = now.saturating_duration_since(start).as_secs_f64();
+        let keyframes = animation.keyframes();
+        let end = animation.duration().as_secs_f64();
+        if t >= end {
+            self.playing = None;
+            return self.rest;
+        }
+
+        // The resting pose is the first and last keyframe
+        let mut from = (0.0, self.rest);
+        for &to in keyframes.iter().chain([(end, self.rest)].iter()) {
+            if t < to.0 {
+                let share = (t - from.0) / (to.0 - from.0);
+                return [0, 1].map(|i| from.1[i] + share * (to.1[i] - from.1[i]));
+            }
+            from = to;
+        }
+        self.rest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_animation_leaves_and_returns_to_rest() {
+        let mut animator = Animator::new();
+        animator.set_rest([0.2, -0.2]);
+        let start = Instant::now();
+        let at = |s: f64| start + Duration::from_secs_f64(s);
+
+        animator.play(AntennaAnimation::PerkUp, start);
+        assert_eq!(animator.pose(start), [0.2, -0.2]);
+        assert_eq!(animator.pose(at(0.5)), [1.0, 1.0]);
+        let settling = animator.pose(at(1.05));
+        assert!(settling[0] < 1.0 && settling[0] > 0.2);
+        assert_eq!(animator.pose(at(1.3)), [0.2, -0.2]);
+        assert!(animator.playing.is_none());
+
+        let event = Event::LowBattery { voltage: 6.4 };
+        assert_eq!(AntennaAnimation::for_event(&event), Some(AntennaAnimation::Droop));
+    }
+}
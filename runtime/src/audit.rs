@@ -0,0 +1,302 @@
+//! Latency audit: finds which calls in the control path blow the 20 ms
+//! budget.
+//!
+//! With `--latency-audit-us N`, every potentially blocking call in the
+//! control path runs inside a `span`: serial reads and writes, the channels
+//! to the motor I/O thread, event bus publishes and log writes. A span that
+//! takes longer than N µs is recorded under its tag path, the tags of the
+//! spans open around it joined with `/` (`write/channel_send`,
+//! `read/serial_read`), so the same call reached from two places is told
+//! apart. The first overrun of each path is logged at the end of its tick
+//! and a table of all of them at exit.
+//!
+//! Allocations are audited too: the runtime installs `CountingAllocator`,
+//! which counts every allocation made inside a span and times them against
+//! the same budget. Slow ones are recorded as `<path>/alloc`.
+//!
+//! When the audit is off a span costs one atomic load.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Nesting depth whose tags are kept; deeper spans are still timed.
+const MAX_DEPTH: usize = 8;
+
+/// Budget per call (ns); 0 when the audit is off.
+static BUDGET_NS: AtomicU64 = AtomicU64::new(0);
+
+/// Allocations made inside spans, on every thread.
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Overruns per tag path, and paths whose first overrun isn't logged yet.
+static OVERRUNS: Mutex<Option<Overruns>> = Mutex::new(None);
+
+thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    static TAGS: [Cell<&'static str>; MAX_DEPTH] = const { [const { Cell::new("") }; MAX_DEPTH] };
+    /// Allocations over budget on this thread, and those already
+    /// attributed to a span.
+    static SLOW_ALLOCS: Cell<u64> = const { Cell::new(0) };
+    static CLAIMED_ALLOCS: Cell<u64> = const { Cell::new(0) };
+    /// Slowest allocation not attributed yet (ns).
+    static WORST_ALLOC_NS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Overruns of one tag path.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Overrun {
+    pub count: u64,
+    pub worst: Duration,
+    pub total: Duration,
+}
+
+#[derive(Debug, Default)]
+struct Overruns {
+    paths: HashMap<String, Overrun>,
+    unreported: Vec<String>,
+}
+
+/// Start auditing calls against `budget`.
+pub fn enable(budget: Duration) {
+    let ns = u64::try_from(budget.as_nanos()).unwrap_or(u64::MAX).max(1);
+    BUDGET_NS.store(ns, Ordering::Relaxed);
+    tracing::info!("Latency audit on: reporting calls over {}µs", budget.as_micros());
+}
+
+pub fn enabled() -> bool {
+    BUDGET_NS.load(Ordering::Relaxed) > 0
+}
+
+/// Time the rest of the scope under `tag`.
+pub fn span(tag: &'static str) -> Span {
+    if !enabled() {
+        return Span { started: None };
+    }
+    let depth = DEPTH.get();
+    if depth < MAX_DEPTH {
+        TAGS.with(|tags| tags[depth].set(tag));
+    }
+    DEPTH.set(depth + 1);
+    Span {
+        started: Some(SpanStart {
+            at: Instant::now(),
+            slow_allocs: SLOW_ALLOCS.get(),
+            claimed_allocs: CLAIMED_ALLOCS.get(),
+        }),
+    }
+}
+
+/// Guard of an open span; records it when dropped.
+#[must_use = "the span ends when the guard is dropped"]
+pub struct Span {
+    started: Option<SpanStart>,
+}
+
+struct SpanStart {
+    at: Instant,
+    slow_allocs: u64,
+    claimed_allocs: u64,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(ref start) = self.started else {
+            return;
+        };
+        let took = start.at.elapsed();
+        let budget = Duration::from_nanos(BUDGET_NS.load(Ordering::Relaxed));
+        // Slow allocations not already claimed by a span nested in this one
+        let slow = SLOW_ALLOCS.get() - start.slow_allocs;
+        let claimed = CLAIMED_ALLOCS.get() - start.claimed_allocs;
+        let unclaimed = slow - claimed;
+
+        let depth = DEPTH.get();
+        // Allocations made while recording are the audit's own
+        DEPTH.set(0);
+        if took > budget || unclaimed > 0 {
+            let path = TAGS.with(|tags| {
+                tags[..depth.min(MAX_DEPTH)].iter().map(Cell::get).collect::<Vec<_>>().join("/")
+            });
+            if took > budget {
+                record(path.clone(), took, 1);
+            }
+            if unclaimed > 0 {
+                let worst = Duration::from_nanos(WORST_ALLOC_NS.replace(0));
+                record(format!("{}/alloc", path), worst, unclaimed);
+                CLAIMED_ALLOCS.set(CLAIMED_ALLOCS.get() + unclaimed);
+            }
+        }
+        DEPTH.set(depth - 1);
+    }
+}
+
+fn record(path: String, took: Duration, count: u64) {
+    let Ok(mut overruns) = OVERRUNS.lock() else {
+        return;
+    };
+    let overruns = overruns.get_or_insert_with(Overruns::default);
+    let entry = overruns.paths.entry(path.clone()).or_default();
+    if entry.count == 0 {
+        overruns.unreported.push(path);
+    }
+    entry.count += count;
+    entry.worst = entry.worst.max(took);
+    entry.total += took * count as u32;
+}
+
+/// Log paths that overran for the first time. Call it outside any span,
+/// at the end of a tick, so the logging isn't billed to the control path.
+pub fn flush() {
+    if !enabled() {
+        return;
+    }
+    let first: Vec<(String, Overrun)> = match OVERRUNS.lock() {
+        Ok(mut overruns) => match overruns.as_mut() {
+            Some(o) => o.unreported.drain(..).map(|p| (p.clone(), o.paths[&p])).collect(),
+            None => return,
+        },
+        Err(_) => return,
+    };
+    for (path, overrun) in first {
+        // Not a warning: the limiter would hold back all but the first path
+        tracing::info!(
+            target: "latency_audit",
+            "{} took {}µs, over the audit budget",
+            path,
+            overrun.worst.as_micros()
+        );
+    }
+}
+
+/// Overruns so far per tag path, most frequent first.
+pub fn overruns() -> Vec<(String, Overrun)> {
+    let mut paths: Vec<(String, Overrun)> = match OVERRUNS.lock() {
+        Ok(overruns) => overruns
+            .as_ref()
+            .map(|o| o.paths.iter().map(|(p, o)| (p.clone(), *o)).collect())
+            .unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(&b.0)));
+    paths
+}
+
+/// Log the table of overruns, for the end of the run.
+pub fn report(ticks: u64) {
+    if !enabled() {
+        return;
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    tracing::info!(
+        "Latency audit: {} allocations in the control path ({:.1} per tick)",
+        allocations,
+        allocations as f64 / ticks.max(1) as f64
+    );
+    let paths = overruns();
+    if paths.is_empty() {
+        tracing::info!("Latency audit: no call went over budget");
+    }
+    for (path, overrun) in paths {
+        tracing::info!(
+            "Latency audit: {:<32} {:>6} over budget, worst {:>6}µs, mean {:>6}µs",
+            path,
+            overrun.count,
+            overrun.worst.as_micros(),
+            (overrun.total / overrun.count.max(1) as u32).as_micros()
+        );
+    }
+}
+
+/// System allocator that counts and times allocations made inside spans.
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn timed<T>(&self, allocate: impl FnOnce() -> T) -> T {
+        let budget = BUDGET_NS.load(Ordering::Relaxed);
+        // try_with: thread-locals may already be gone while a thread exits
+        if budget == 0 || DEPTH.try_with(Cell::get).unwrap_or(0) == 0 {
+            return allocate();
+        }
+        let start = Instant::now();
+        let result = allocate();
+        let took = start.elapsed().as_nanos() as u64;
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        if took > budget {
+            SLOW_ALLOCS.set(SLOW_ALLOCS.get() + 1);
+            WORST_ALLOC_NS.set(WORST_ALLOC_NS.get().max(took));
+        }
+        result
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.timed(|| unsafe { System.alloc(layout) })
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.timed(|| unsafe { System.alloc_zeroed(layout) })
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.timed(|| unsafe { System.realloc(ptr, layout, new_size) })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+/// Writer whose writes run in a span, for log outputs.
+pub struct Audited<W> {
+    tag: &'static str,
+    inner: W,
+}
+
+impl<W> Audited<W> {
+    pub fn new(tag: &'static str, inner: W) -> Self {
+        Self { tag, inner }
+    }
+}
+
+impl<W: Write> Write for Audited<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let _span = span(self.tag);
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let _span = span(self.tag);
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overruns_recorded_by_path() {
+        enable(Duration::from_millis(1));
+        {
+            let _outer = span("write");
+            let _fast = span("fast");
+            drop(_fast);
+            let _slow = span("slow");
+            std::thread::sleep(Duration::from_millis(3));
+        }
+
+        let paths = overruns();
+        let slow = paths.iter().find(|(p, _)| p == "write/slow").unwrap().1;
+        assert_eq!(slow.count, 1);
+        assert!(slow.worst >= Duration::from_millis(3));
+        assert!(paths.iter().any(|(p, _)| p == "write"));
+        assert!(!paths.iter().any(|(p, _)| p.ends_with("fast")));
+        assert_eq!(DEPTH.get(), 0);
+    }
+}
@@ -0,0 +1,162 @@
+//! Battery monitoring from the servo bus input voltage.
+//!
+//! Every STS3215 reports its input voltage, which is the battery voltage
+//! minus wiring losses. The health poll hands those readings here; the
+//! median across servos is smoothed and mapped to a remaining percentage
+//! with a Li-ion discharge curve.
+//!
+//! Low and critical levels are latched for the rest of the run: a battery
+//! that recovers a few tenths of a volt once the load comes off is still
+//! nearly empty. A brown-out otherwise shows up as unexplained serial
+//! errors, so the runtime pauses at the critical level and refuses to
+//! leave pause until the battery is swapped.
+
+use crate::config::BatteryConfig;
+
+/// Played (if present in the sound directory) when the battery runs low.
+pub const LOW_BATTERY_SOUND: &str = "low_battery.wav";
+
+/// Weight of each new reading in the smoothed voltage.
+const SMOOTHING: f64 = 0.3;
+
+/// Resting Li-ion cell voltage against remaining charge (%).
+const DISCHARGE_CURVE: [(f64, f64); 11] = [
+    (3.00, 0.0),
+    (3.30, 2.0),
+    (3.40, 5.0),
+    (3.50, 10.0),
+    (3.60, 20.0),
+    (3.70, 35.0),
+    (3.80, 50.0),
+    (3.90, 65.0),
+    (4.00, 80.0),
+    (4.10, 90.0),
+    (4.20, 100.0),
+];
+
+/// Battery level, worst last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BatteryLevel {
+    Ok,
+    Low,
+    Critical,
+}
+
+/// Tracks the battery from servo voltage readings.
+pub struct BatteryMonitor {
+    config: BatteryConfig,
+    voltage: Option<f64>,
+    level: BatteryLevel,
+}
+
+impl BatteryMonitor {
+    pub fn new(config: BatteryConfig) -> Self {
+        Self {
+            config,
+            voltage: None,
+            level: BatteryLevel::Ok,
+        }
+    }
+
+    /// Feed one set of servo input voltages. Returns the new level when it
+    /// drops.
+    pub fn update(&mut self, servo_voltages: &[f64]) -> Option<BatteryLevel> {
+        let mut readings: Vec<f64> = servo_voltages.iter().copied().filter(|v| *v > 0.0).collect();
+        if readings.is_empty() {
+            return None;
+        }
+        readings.sort_by(f64::total_cmp);
+        let median = readings[readings.len() / 2];
+
+        let voltage = match self.voltage {
+            Some(v) => v + SMOOTHING * (median - v),
+            None => median,
+        };
+        self.voltage = Some(voltage);
+
+        let percent = self.percent().unwrap_or(100.0);
+        let level = if percent <= self.config.critical_percent {
+            BatteryLevel::Critical
+        } else if percent <= self.config.low_percent {
+            BatteryLevel::Low
+        } else {
+            BatteryLevel::Ok
+        };
+        tracing::debug!("Battery {:.2}V ({:.0}%)", voltage, percent);
+
+        if level > self.level {
+            self.level = level;
+            tracing::warn!("Battery {:?}: {:.2}V ({:.0}%)", level, voltage, percent);
+            return Some(level);
+        }
+        None
+    }
+
+    /// Smoothed pack voltage, once a reading has arrived.
+    pub fn voltage(&self) -> Option<f64> {
+        self.voltage
+    }
+
+    /// Estimated remaining charge (0-100).
+    pub fn percent(&self) -> Option<f64> {
+        let cells = self.config.cells.max(1) as f64;
+        self.voltage.map(|v| cell_percent(v / cells))
+    }
+
+    pub fn level(&self) -> BatteryLevel {
+        self.level
+    }
+}
+
+/// Interpolate the discharge curve for one cell.
+fn cell_percent(cell_voltage: f64) -> f64 {
+    let (first, last) = (DISCHARGE_CURVE[0], DISCHARGE_CURVE[DISCHARGE_CURVE.len() - 1]);
+    if cell_voltage <= first.0 {
+        return first.1;
+    }
+    if cell_voltage >= last.0 {
+        return last.1;
+    }
+    DISCHARGE_CURVE
+        .windows(2)
+        .find(|w| cell_voltage <= w[1].0)
+        .map(|w| {
+            let ((v0, p0), (v1, p1)) = (w[0], w[1]);
+            p0 + (cell_voltage - v0) / (v1 - v0) * (p1 - p0)
+        })
+        .unwrap_or(last.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discharge_curve() {
+        assert_eq!(cell_percent(4.3), 100.0);
+        assert_eq!(cell_percent(2.5), 0.0);
+        assert!((cell_percent(3.75) - 42.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_levels_latch_on_the_way_down() {
+        let mut battery = BatteryMonitor::new(BatteryConfig::default());
+
+        // A dead servo reading 0 V and one outlier don't move the median
+        assert_eq!(battery.update(&[8.0, 0.0, 8.0, 5.0, 8.0]), None);
+        assert_eq!(battery.voltage(), Some(8.0));
+        assert_eq!(battery.level(), BatteryLevel::Ok);
+
+        let mut dropped = Vec::new();
+        for _ in 0..30 {
+            dropped.extend(battery.update(&[6.6; 3]));
+        }
+        assert_eq!(dropped, vec![BatteryLevel::Low, BatteryLevel::Critical]);
+
+        // Voltage rebounds once the load comes off; the level stays
+        for _ in 0..30 {
+            assert_eq!(battery.update(&[7.3; 3]), None);
+        }
+        assert_eq!(battery.level(), BatteryLevel::Critical);
+    }
+}
@@ -0,0 +1,232 @@
+//! Gait degradation as the battery sags.
+//!
+//! A sagging pack leaves the servos less torque headroom, and a gait that
+//! was steady on a full battery starts to rock. `SagTracker` records, for
+//! each 0.1 V/cell band the pack passes through, how fast the duck was
+//! commanded to walk and how much its body rocked (roll and pitch rate)
+//! while it did, and logs the band once the voltage leaves it.
+//!
+//! With `adapt` on, it also derives a scale (`min_scale`..1) for the action
+//! scale and the commanded velocity range. The scale shrinks linearly from
+//! `start_cell_voltage` to `end_cell_voltage`, and further when the body
+//! rocks more than `instability_ratio` times as much as it did above
+//! `start_cell_voltage`. Like the battery levels, it only ever goes down:
+//! the voltage recovers when the load comes off, and the duck shouldn't
+//! speed back up into the wobble.
+
+use crate::config::BatterySagConfig;
+use crate::controller::{X_RANGE, YAW_RANGE, Y_RANGE};
+
+/// Width of the voltage bands the stability stats are kept for (V/cell).
+const BAND: f64 = 0.1;
+
+/// Commanded speed, as a share of the range, above which a tick counts as
+/// walking.
+const WALKING_SHARE: f64 = 0.3;
+
+/// Time constant of the smoothed body rate (s). The full-battery baseline
+/// also needs this much walking before it counts.
+const SMOOTHING_TIME: f64 = 5.0;
+
+/// The scale is logged each time it drops by this much.
+const LOG_STEP: f64 = 0.05;
+
+/// Walking stats for one voltage band.
+#[derive(Debug, Clone, Copy, Default)]
+struct BandStats {
+    ticks: u64,
+    speed: f64,
+    rate: f64,
+}
+
+/// Tracks gait stability against battery voltage and the resulting scale.
+pub struct SagTracker {
+    config: BatterySagConfig,
+    cells: f64,
+    cell_voltage: Option<f64>,
+    band: Option<i64>,
+    stats: BandStats,
+    rate: Option<f64>,
+    baseline: Option<f64>,
+    baseline_time: f64,
+    scale: f64,
+    logged_scale: f64,
+}
+
+impl SagTracker {
+    pub fn new(config: BatterySagConfig, cells: u32) -> Self {
+        if config.adapt {
+            tracing::info!(
+                "Battery sag: scaling the gait down from {:.2} to {:.2} V/cell (min {:.0}%)",
+                config.start_cell_voltage,
+                config.end_cell_voltage,
+                config.min_scale * 100.0
+            );
+        }
+        Self {
+            config,
+            cells: cells.max(1) as f64,
+            cell_voltage: None,
+            band: None,
+            stats: BandStats::default(),
+            rate: None,
+            baseline: None,
+            baseline_time: 0.0,
+            scale: 1.0,
+            logged_scale: 1.0,
+        }
+    }
+
+    /// Feed one control tick: the velocity commands actually sent to the
+    /// policy and the body angular rate. Ticks that aren't walking are
+    /// ignored.
+    pub fn record(&mut self, commands: &[f64], gyro: [f64; 3], dt: f64) {
+        let speed = commands
+            .iter()
+            .zip([X_RANGE, Y_RANGE, YAW_RANGE])
+            .map(|(c, [min, max])| c.abs() / min.abs().max(max.abs()))
+            .fold(0.0, f64::max);
+        if speed < WALKING_SHARE {
+            return;
+        }
+
+        let body_rate = gyro[0].hypot(gyro[1]);
+        let rate = match self.rate {
+            Some(r) => r + (dt / SMOOTHING_TIME).min(1.0) * (body_rate - r),
+            None => body_rate,
+        };
+        self.rate = Some(rate);
+
+        self.stats.ticks += 1;
+        self.stats.speed += speed;
+        self.stats.rate += body_rate;
+
+        let full = self.cell_voltage.is_some_and(|v| v >= self.config.start_cell_voltage);
+        if full && self.scale >= 1.0 {
+            self.baseline_time += dt;
+            if self.baseline_time >= SMOOTHING_TIME {
+                self.baseline = Some(rate);
+            }
+        }
+    }
+
+    /// Feed the smoothed pack voltage.
+    pub fn update(&mut self, voltage: f64) {
+        let cell = voltage / self.cells;
+        self.cell_voltage = Some(cell);
+
+        // Bands only move down, so a reading flickering at a boundary
+        // doesn't split one band's stats
+        let band = (cell / BAND).floor() as i64;
+        if self.band.is_none_or(|b| band < b) {
+            if let Some(previous) = self.band {
+                self.log_band(previous);
+            }
+            self.band = Some(band);
+            self.stats = BandStats::default();
+        }
+
+        if !self.config.adapt {
+            return;
+        }
+        let c = &self.config;
+        let min_scale = c.min_scale.clamp(0.0, 1.0);
+        let sag = (c.start_cell_voltage - cell) / (c.start_cell_voltage - c.end_cell_voltage);
+        let voltage_scale = 1.0 - sag.clamp(0.0, 1.0) * (1.0 - min_scale);
+        let stability_scale = match (self.baseline, self.rate) {
+            (Some(base), Some(rate)) if base > 0.0 && rate > base * c.instability_ratio => {
+                base * c.instability_ratio / rate
+            }
+            _ => 1.0,
+        };
+        let scale = voltage_scale.min(stability_scale).max(min_scale);
+        if scale >= self.scale {
+            return;
+        }
+        self.scale = scale;
+
+        let floored = scale <= min_scale && self.logged_scale > min_scale;
+        if self.logged_scale - scale >= LOG_STEP || floored {
+            tracing::warn!(
+                "Battery at {:.2} V/cell (body rate {:.2} rad/s), gait scaled to {:.0}%",
+                cell,
+                self.rate.unwrap_or(0.0),
+                scale * 100.0
+            );
+            self.logged_scale = scale;
+        }
+    }
+
+    /// Factor (`min_scale`..1) applied to the action scale and velocity
+    /// range; 1 unless `adapt` is on.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Clamp velocity commands to the scaled share of `ranges`.
+    pub fn limit_velocity(&self, commands: &mut [f64], ranges: &[[f64; 2]]) {
+        for (c, range) in commands.iter_mut().zip(ranges) {
+            *c = c.clamp(range[0] * self.scale, range[1] * self.scale);
+        }
+    }
+
+    fn log_band(&self, band: i64) {
+        let s = &self.stats;
+        if s.ticks == 0 {
+            return;
+        }
+        let low = band as f64 * BAND;
+        tracing::info!(
+            "Battery {:.1}-{:.1} V/cell: walked {} ticks at {:.0}% speed, body rate {:.2} rad/s",
+            low,
+            low + BAND,
+            s.ticks,
+            s.speed / s.ticks as f64 * 100.0,
+            s.rate / s.ticks as f64
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_follows_voltage_and_wobble_down_only() {
+        let config = BatterySagConfig {
+            adapt: true,
+            ..BatterySagConfig::default()
+        };
+        let mut sag = SagTracker::new(config, 2);
+        let walk = [X_RANGE[1], 0.0, 0.0];
+
+        // Full battery: steady walking sets the baseline
+        sag.update(8.0);
+        for _ in 0..300 {
+            sag.record(&walk, [0.2, 0.0, 0.0], 0.02);
+        }
+        assert_eq!(sag.scale(), 1.0);
+        assert!(sag.baseline.is_some_and(|b| (b - 0.2).abs() < 1e-9));
+
+        // Halfway through the sag range
+        let (start, end) = (sag.config.start_cell_voltage, sag.config.end_cell_voltage);
+        sag.update(start + end);
+        let halfway = 1.0 - 0.5 * (1.0 - sag.config.min_scale);
+        assert!((sag.scale() - halfway).abs() < 1e-9);
+
+        // Rocking three times as much scales down further, to the floor
+        for _ in 0..1000 {
+            sag.record(&walk, [0.6, 0.0, 0.0], 0.02);
+        }
+        sag.update(start + end);
+        assert_eq!(sag.scale(), sag.config.min_scale);
+
+        // A recovering voltage doesn't undo it
+        sag.update(8.4);
+        assert_eq!(sag.scale(), sag.config.min_scale);
+
+        let mut commands = [X_RANGE[1], 0.0, YAW_RANGE[0]];
+        sag.limit_velocity(&mut commands, &[X_RANGE, Y_RANGE, YAW_RANGE]);
+        assert!((commands[0] - X_RANGE[1] * sag.config.min_scale).abs() < 1e-9);
+    }
+}
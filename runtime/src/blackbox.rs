@@ -0,0 +1,411 @@
+//! Flight recorder: the last seconds of control ticks, dumped on a fall or
+//! a servo fault.
+//!
+//! Every completed tick is pushed into a ring buffer holding its stage
+//! timings, the safety verdict, the commands, and the present and target
+//! joint positions. When the duck falls or faults, the buffer is written to
+//! `<dir>/blackbox-<unix time>.bin` on a background thread so the control
+//! loop never waits on the SD card; only the newest `keep` dumps are kept.
+//! `openduckrust blackbox inspect` decodes them.
+//!
+//! The dump format is little-endian binary:
+//!
+//! ```text
+//! header:  magic "ODRBBOX\0", version u16, control_freq u32,
+//!          dumped_at f64 (unix seconds), reason str, joint count u16,
+//!          joint names str..., tick count u32
+//! tick:    tick u64, time f64, read/inference/write/total µs u32 x4,
+//!          safety u8, flags u8, tilt f32, gravity f32 x3, commands f32 x7,
+//!          positions f32 x joints, targets f32 x joints
+//! str:     u16 length, UTF-8 bytes
+//! ```
+
+use anyhow::{bail, Context, Result};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::runtime_health::SafetyState;
+
+const MAGIC: &[u8; 8] = b"ODRBBOX\0";
+const VERSION: u16 = 2;
+
+/// The tick ran past the control period.
+pub const FLAG_OVERRUN: u8 = 1;
+/// A collision was detected on this tick.
+pub const FLAG_COLLISION: u8 = 1 << 1;
+/// Thermal throttling scaled the commands down.
+pub const FLAG_THROTTLED: u8 = 1 << 2;
+
+/// Time spent in each stage of one tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StageTimings {
+    /// IMU and servo reads.
+    pub read: Duration,
+    pub inference: Duration,
+    /// Goal position write.
+    pub write: Duration,
+    /// The whole tick.
+    pub total: Duration,
+}
+
+/// One recorded control tick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickRecord {
+    pub tick: u64,
+    /// Seconds since the control loop started.
+    pub time: f64,
+    pub timings: StageTimings,
+    pub safety: SafetyState,
+    /// `FLAG_*` bits.
+    pub flags: u8,
+    /// Tilt from vertical (rad).
+    pub tilt: f32,
+    /// Gravity in the body frame; +x when pitched forward, -y when rolled
+    /// to the right.
+    pub gravity: [f32; 3],
+    pub commands: [f32; 7],
+    pub positions: Vec<f32>,
+    pub targets: Vec<f32>,
+}
+
+/// A decoded dump.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlackBoxDump {
+    pub control_freq: u32,
+    /// Unix time the dump was written.
+    pub dumped_at: f64,
+    /// What triggered it, e.g. "fall (tilt 1.12 rad)".
+    pub reason: String,
+    pub joint_names: Vec<String>,
+    /// Oldest first.
+    pub ticks: Vec<TickRecord>,
+}
+
+/// Ring buffer of the most recent ticks.
+pub struct BlackBox {
+    ticks: VecDeque<TickRecord>,
+    capacity: usize,
+    control_freq: u32,
+    joint_names: Vec<String>,
+    dir: PathBuf,
+    keep: usize,
+}
+
+impl BlackBox {
+    /// Keep `seconds` of ticks at `control_freq`, dumping into `dir`.
+    pub fn new(
+        seconds: f64,
+        control_freq: u32,
+        joint_names: Vec<String>,
+        dir: PathBuf,
+        keep: usize,
+    ) -> Self {
+        let capacity = ((seconds * control_freq as f64).ceil() as usize).max(1);
+        Self {
+            ticks: VecDeque::with_capacity(capacity),
+            capacity,
+            control_freq,
+            joint_names,
+            dir,
+            keep: keep.max(1),
+        }
+    }
+
+    pub fn record(&mut self, record: TickRecord) {
+        if self.ticks.len() == self.capacity {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back(record);
+    }
+
+    /// The buffer as it is now.
+    pub fn snapshot(&self, reason: &str, dumped_at: f64) -> BlackBoxDump {
+        BlackBoxDump {
+            control_freq: self.control_freq,
+            dumped_at,
+            reason: reason.to_string(),
+            joint_names: self.joint_names.clone(),
+            ticks: self.ticks.iter().cloned().collect(),
+        }
+    }
+
+    /// Write the buffer out in the background.
+    pub fn dump(&self, reason: &str, dumped_at: f64) {
+        if self.ticks.is_empty() {
+            return;
+        }
+        let dump = self.snapshot(reason, dumped_at);
+        let dir = self.dir.clone();
+        let keep = self.keep;
+        std::thread::spawn(move || match write_dump(&dump, &dir, keep) {
+            Ok(path) => tracing::warn!("Black box dumped to {} ({})", path.display(), dump.reason),
+            Err(e) => tracing::error!("Black box dump failed: {:#}", e),
+        });
+    }
+}
+
+fn write_dump(dump: &BlackBoxDump, dir: &Path, keep: usize) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!("blackbox-{:.0}.bin", dump.dumped_at * 1000.0));
+    std::fs::write(&path, dump.encode())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    // Names sort by time: drop the oldest beyond `keep`
+    let mut dumps: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            name.starts_with("blackbox-") && name.ends_with(".bin")
+        })
+        .collect();
+    dumps.sort();
+    for old in dumps.iter().take(dumps.len().saturating_sub(keep)) {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(path)
+}
+
+fn safety_code(state: SafetyState) -> u8 {
+    match state {
+        SafetyState::Running => 0,
+        SafetyState::Paused => 1,
+        SafetyState::SoftStopped => 2,
+        SafetyState::Estopped => 3,
+        SafetyState::Fallen => 4,
+    }
+}
+
+fn safety_from_code(code: u8) -> Result<SafetyState> {
+    Ok(match code {
+        0 => SafetyState::Running,
+        1 => SafetyState::Paused,
+        2 => SafetyState::SoftStopped,
+        3 => SafetyState::Estopped,
+        4 => SafetyState::Fallen,
+        _ => bail!("Unknown safety state {}", code),
+    })
+}
+
+impl BlackBoxDump {
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::decode(&bytes).with_context(|| format!("Invalid black box dump {}", path.display()))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.control_freq.to_le_bytes());
+        out.extend_from_slice(&self.dumped_at.to_le_bytes());
+        put_str(&mut out, &self.reason);
+        out.extend_from_slice(&(self.joint_names.len() as u16).to_le_bytes());
+        for name in &self.joint_names {
+            put_str(&mut out, name);
+        }
+        out.extend_from_slice(&(self.ticks.len() as u32).to_le_bytes());
+
+        let micros = |d: Duration| (d.as_micros().min(u32::MAX as u128) as u32).to_le_bytes();
+        for t in &self.ticks {
+            out.extend_from_slice(&t.tick.to_le_bytes());
+            out.extend_from_slice(&t.time.to_le_bytes());
+            let timings = [t.timings.read, t.timings.inference, t.timings.write, t.timings.total];
+            for d in timings {
+                out.extend_from_slice(&micros(d));
+            }
+            out.push(safety_code(t.safety));
+            out.push(t.flags);
+            out.extend_from_slice(&t.tilt.to_le_bytes());
+            // Exactly one value per joint, so ticks keep a fixed size
+            let joints = self.joint_names.len();
+            let padded = |values: &[f32]| {
+                (0..joints).map(|i| values.get(i).copied().unwrap_or(0.0)).collect::<Vec<_>>()
+            };
+            let values = t.gravity.iter().chain(&t.commands);
+            for value in values.chain(&padded(&t.positions)).chain(&padded(&t.targets)) {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut r = Reader { bytes, at: 0 };
+        if r.take(MAGIC.len())? != MAGIC {
+            bail!("Not a black box dump");
+        }
+        let version = r.u16()?;
+        if version == 0 || version > VERSION {
+            bail!("Unsupported dump version {}", version);
+        }
+        let control_freq = r.u32()?;
+        let dumped_at = r.f64()?;
+        let reason = r.str()?;
+        let joints = r.u16()? as usize;
+        let joint_names = (0..joints).map(|_| r.str()).collect::<Result<Vec<_>>>()?;
+        let count = r.u32()? as usize;
+
+        let mut ticks = Vec::with_capacity(count.min(bytes.len()));
+        for _ in 0..count {
+            let tick = r.u64()?;
+            let time = r.f64()?;
+            let mut micros = || r.u32().map(|us| Duration::from_micros(us as u64));
+            let timings = StageTimings {
+                read: micros()?,
+                inference: micros()?,
+                write: micros()?,
+                total: micros()?,
+            };
+            let safety = safety_from_code(r.u8()?)?;
+            let flags = r.u8()?;
+            let tilt = r.f32()?;
+            // Version 1 dumps had no gravity
+            let mut gravity = [0.0; 3];
+            if version >= 2 {
+                for g in gravity.iter_mut() {
+                    *g = r.f32()?;
+                }
+            }
+            let mut commands = [0.0; 7];
+            for c in commands.iter_mut() {
+                *c = r.f32()?;
+            }
+            let positions = (0..joints).map(|_| r.f32()).collect::<Result<Vec<_>>>()?;
+            let targets = (0..joints).map(|_| r.f32()).collect::<Result<Vec<_>>>()?;
+            ticks.push(TickRecord {
+                tick,
+                time,
+                timings,
+                safety,
+                flags,
+                tilt,
+                gravity,
+                commands,
+                positions,
+                targets,
+            });
+        }
+        Ok(Self {
+            control_freq,
+            dumped_at,
+            reason,
+            joint_names,
+            ticks,
+        })
+    }
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    let bytes = &s.as_bytes()[..s.len().min(u16::MAX as usize)];
+    out.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let Some(slice) = self.bytes.get(self.at..self.at + n) else {
+            bail!("Dump truncated at byte {}", self.at);
+        };
+        self.at += n;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("slice of length N"))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        self.array().map(f32::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    fn str(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).context("Invalid UTF-8 in dump")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(n: u64) -> TickRecord {
+        TickRecord {
+            tick: n,
+            time: n as f64 * 0.02,
+            timings: StageTimings {
+                read: Duration::from_micros(1200),
+                inference: Duration::from_micros(3400),
+                write: Duration::from_micros(800),
+                total: Duration::from_micros(6000 + n),
+            },
+            safety: if n == 4 { SafetyState::Fallen } else { SafetyState::Running },
+            flags: if n == 4 { FLAG_OVERRUN | FLAG_COLLISION } else { 0 },
+            tilt: n as f32 * 0.1,
+            gravity: [0.1, 0.0, -0.99],
+            commands: [0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            positions: vec![n as f32, -0.5],
+            targets: vec![0.25, 0.5],
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer_round_trip() {
+        let dir = std::env::temp_dir().join(format!("odr-blackbox-{}", std::process::id()));
+        let joints = vec!["left_hip_yaw".to_string(), "neck_pitch".to_string()];
+        let mut blackbox = BlackBox::new(0.06, 50, joints.clone(), dir.clone(), 2);
+        for n in 0..5 {
+            blackbox.record(tick(n));
+        }
+
+        // Only the last three ticks (0.06 s at 50 Hz) are kept
+        let dump = blackbox.snapshot("fall (tilt 1.10 rad)", 1_700_000_000.5);
+        assert_eq!(dump.ticks.iter().map(|t| t.tick).collect::<Vec<_>>(), vec![2, 3, 4]);
+
+        let decoded = BlackBoxDump::decode(&dump.encode()).unwrap();
+        assert_eq!(decoded, dump);
+        assert_eq!(decoded.joint_names, joints);
+        assert_eq!(decoded.ticks[2].safety, SafetyState::Fallen);
+        assert!(BlackBoxDump::decode(&dump.encode()[..40]).is_err());
+        assert!(BlackBoxDump::decode(b"not a dump at all").is_err());
+
+        // Old dumps are pruned
+        for at in [1.0, 2.0, 3.0] {
+            write_dump(&BlackBoxDump { dumped_at: at, ..dump.clone() }, &dir, 2).unwrap();
+        }
+        let mut left: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["blackbox-2000.bin", "blackbox-3000.bin"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+//! BNO055 UART protocol.
+//!
+//! With PS1 pulled high the BNO055 talks UART (115200 baud, 8N1) instead of
+//! I2C, which sidesteps the Pi's I2C clock-stretching bug. Every register
+//! access is a command: `AA 00 reg len data...` writes, `AA 01 reg len`
+//! reads. A write is answered `EE status`, a read `BB len data...`, or
+//! `EE status` when it failed. The chip drops commands that arrive while it
+//! is busy and answers them with a bus over-run status; those are worth
+//! sending again. Only the framing lives here; the serial port is driven by
+//! the `imu` module.
+
+/// First byte of every command.
+pub const START: u8 = 0xAA;
+const WRITE: u8 = 0x00;
+const READ: u8 = 0x01;
+
+/// First byte of a successful read response.
+pub const READ_RESPONSE: u8 = 0xBB;
+/// First byte of a status response.
+pub const STATUS_RESPONSE: u8 = 0xEE;
+
+pub const WRITE_SUCCESS: u8 = 0x01;
+const BUS_OVER_RUN: u8 = 0x08;
+const RECEIVE_CHARACTER_TIMEOUT: u8 = 0x0B;
+
+/// Most bytes one command may read or write.
+pub const MAX_LEN: usize = 128;
+
+/// A response decoded from the start of the received bytes.
+#[derive(Debug, PartialEq)]
+pub enum Response<'a> {
+    /// More bytes are needed.
+    Incomplete,
+    /// Register contents from a read.
+    Data(&'a [u8]),
+    Status(u8),
+    /// The first byte starts no response (line noise): skip it.
+    Invalid,
+}
+
+/// Command writing `data` from `register` on.
+pub fn write_command(register: u8, data: &[u8]) -> Vec<u8> {
+    let len = data.len().min(MAX_LEN);
+    let mut command = vec![START, WRITE, register, len as u8];
+    command.extend_from_slice(&data[..len]);
+    command
+}
+
+/// Command reading `len` bytes from `register` on.
+pub fn read_command(register: u8, len: usize) -> [u8; 4] {
+    [START, READ, register, len.min(MAX_LEN) as u8]
+}
+
+pub fn parse_response(bytes: &[u8]) -> Response<'_> {
+    match bytes {
+        [] | [READ_RESPONSE] | [STATUS_RESPONSE] => Response::Incomplete,
+        [STATUS_RESPONSE, status, ..] => Response::Status(*status),
+        [READ_RESPONSE, len, data @ ..] => match data.get(..*len as usize) {
+            Some(data) => Response::Data(data),
+            None => Response::Incomplete,
+        },
+        _ => Response::Invalid,
+    }
+}
+
+/// Whether a command that failed with `status` is worth sending again.
+pub fn is_transient(status: u8) -> bool {
+    matches!(status, BUS_OVER_RUN | RECEIVE_CHARACTER_TIMEOUT)
+}
+
+pub fn status_message(status: u8) -> &'static str {
+    match status {
+        WRITE_SUCCESS => "write success",
+        0x03 => "read fail",
+        0x04 => "write fail",
+        0x05 => "invalid register address",
+        0x06 => "register is read-only",
+        0x07 => "wrong start byte",
+        BUS_OVER_RUN => "bus over-run",
+        0x09 => "too long",
+        0x0A => "too short",
+        RECEIVE_CHARACTER_TIMEOUT => "receive character timeout",
+        _ => "unknown status",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_commands_and_parses_responses() {
+        assert_eq!(write_command(0x3D, &[0x0C]), [START, WRITE, 0x3D, 1, 0x0C]);
+        assert_eq!(read_command(0x14, 6), [START, READ, 0x14, 6]);
+
+        let read = [READ_RESPONSE, 2, 0x34, 0x12, 0xFF];
+        assert_eq!(parse_response(&read), Response::Data(&[0x34, 0x12]));
+        assert_eq!(parse_response(&read[..3]), Response::Incomplete);
+        assert_eq!(parse_response(&[STATUS_RESPONSE]), Response::Incomplete);
+        assert_eq!(parse_response(&[STATUS_RESPONSE, WRITE_SUCCESS]), Response::Status(1));
+        assert_eq!(parse_response(&[0x00, READ_RESPONSE]), Response::Invalid);
+
+        assert!(is_transient(BUS_OVER_RUN));
+        assert!(!is_transient(0x05));
+    }
+}
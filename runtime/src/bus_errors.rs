@@ -0,0 +1,124 @@
+//! Servo bus error-rate accounting and escalation.
+//!
+//! Every bus transaction (one read including its retries, or one write) is
+//! recorded as a success or failure in an exponentially weighted error rate.
+//! A few lost packets are normal and handled by retries; a rising rate means
+//! a loose cable, a brown-out or a confused USB adapter. Past
+//! `reopen_error_rate` the serial port is reopened once; if the rate keeps
+//! climbing past `stop_error_rate`, the robot is soft-stopped. Both
+//! thresholds re-arm once the rate has fallen back below half the reopen
+//! threshold.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::SerialConfig;
+
+/// Counters surfaced in telemetry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BusStats {
+    /// Smoothed share of failed transactions (0-1).
+    pub error_rate: f64,
+    pub transactions: u64,
+    /// Retried reads.
+    pub retries: u64,
+    /// Transactions that failed even after retries.
+    pub failures: u64,
+    pub reopens: u64,
+}
+
+/// Action required by the error rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escalation {
+    /// Close and reopen the serial port.
+    Reopen,
+    /// Stop driving the servos.
+    SoftStop,
+}
+
+/// Error-rate estimate and escalation state of one bus.
+pub struct BusErrorTracker {
+    config: SerialConfig,
+    stats: BusStats,
+    reopened: bool,
+    stopped: bool,
+}
+
+impl BusErrorTracker {
+    pub fn new(config: SerialConfig) -> Self {
+        Self {
+            config,
+            stats: BusStats::default(),
+            reopened: false,
+            stopped: false,
+        }
+    }
+
+    /// Record one transaction; returns the escalation it triggers, if any.
+    pub fn record(&mut self, ok: bool) -> Option<Escalation> {
+        let alpha = 1.0 / self.config.error_window.max(1) as f64;
+        let sample = if ok { 0.0 } else { 1.0 };
+        self.stats.error_rate += alpha * (sample - self.stats.error_rate);
+        self.stats.transactions += 1;
+        if !ok {
+            self.stats.failures += 1;
+        }
+
+        let rate = self.stats.error_rate;
+        if rate < self.config.reopen_error_rate / 2.0 {
+            self.reopened = false;
+            self.stopped = false;
+        }
+        if !self.stopped && self.reopened && rate >= self.config.stop_error_rate {
+            self.stopped = true;
+            return Some(Escalation::SoftStop);
+        }
+        if !self.reopened && rate >= self.config.reopen_error_rate {
+            self.reopened = true;
+            self.stats.reopens += 1;
+            return Some(Escalation::Reopen);
+        }
+        None
+    }
+
+    /// Count a read retry.
+    pub fn retried(&mut self) {
+        self.stats.retries += 1;
+    }
+
+    pub fn stats(&self) -> BusStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escalates_then_rearms() {
+        let mut tracker = BusErrorTracker::new(SerialConfig::default());
+        for _ in 0..100 {
+            assert_eq!(tracker.record(true), None);
+        }
+        // Occasional failures stay below the thresholds
+        for i in 0..200 {
+            assert_eq!(tracker.record(i % 20 != 0), None);
+        }
+
+        let escalations: Vec<Escalation> = (0..100).filter_map(|_| tracker.record(false)).collect();
+        assert_eq!(escalations, vec![Escalation::Reopen, Escalation::SoftStop]);
+        assert_eq!(tracker.stats().reopens, 1);
+        assert!(tracker.stats().error_rate > 0.5);
+
+        // Once the bus recovers, the next burst of errors escalates again
+        for _ in 0..300 {
+            tracker.record(true);
+        }
+        assert!(tracker.stats().error_rate < 0.1);
+        assert_eq!(
+            (0..100).find_map(|_| tracker.record(false)),
+            Some(Escalation::Reopen)
+        );
+        assert_eq!(tracker.stats().reopens, 2);
+    }
+}
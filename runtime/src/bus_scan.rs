@@ -0,0 +1,289 @@
+//! Servo bus scan: find every servo on the Feetech bus.
+//!
+//! Pings each ID from 0 to 253, at one or more baud rates, and reads the
+//! model number, firmware version and present position of every servo that
+//! answers. The result is checked against the robot model's joint map:
+//! joints whose servo did not answer are reported missing, answering IDs
+//! that no joint uses are reported as unexpected, and IDs that look shared
+//! by two servos are reported as duplicates.
+//!
+//! Two servos with the same ID both answer the ping, so their status packets
+//! collide on the wire. A duplicate shows up as several replies to one ping,
+//! a reply with a bad checksum, or the same ID answering at two baud rates.
+
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use crate::motors::{compute_checksum, parse_status_packet, raw_to_rad, read_packet, ParsedStatus, HEADER};
+
+const INST_PING: u8 = 0x01;
+
+// Register addresses for STS3215
+const ADDR_FIRMWARE_VERSION: u8 = 0; // major(1), minor(1)
+const ADDR_MODEL_NUMBER: u8 = 3;
+const ADDR_PRESENT_POSITION: u8 = 56;
+
+/// Highest servo ID; 254 is the broadcast ID.
+pub const MAX_ID: u8 = 253;
+
+/// Baud rates the STS3215 can be configured for, fastest first.
+pub const COMMON_BAUD_RATES: &[u32] = &[
+    1_000_000, 500_000, 250_000, 128_000, 115_200, 76_800, 57_600, 38_400,
+];
+
+/// Model number the STS3215 reports.
+const MODEL_STS3215: u16 = 777;
+
+/// Human-readable name of a Feetech model number, if known.
+pub fn model_name(model: u16) -> Option<&'static str> {
+    match model {
+        MODEL_STS3215 => Some("STS3215"),
+        _ => None,
+    }
+}
+
+/// A servo that answered a ping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedServo {
+    pub id: u8,
+    /// Baud rate it answered at.
+    pub baud: u32,
+    pub model: Option<u16>,
+    /// Firmware version (major, minor).
+    pub firmware: Option<(u8, u8)>,
+    /// Present position (rad, without joint offset).
+    pub position: Option<f64>,
+    /// Status packets received for the ping.
+    pub replies: usize,
+    /// Whether any of them was corrupted.
+    pub corrupted: bool,
+}
+
+impl ScannedServo {
+    /// Whether the ping replies look like two servos answering at once.
+    pub fn collided(&self) -> bool {
+        self.replies > 1 || self.corrupted
+    }
+}
+
+/// Replies collected during one reply window.
+#[derive(Debug, Default)]
+struct Replies {
+    /// (id, params) of every packet with a good checksum.
+    packets: Vec<(u8, Vec<u8>)>,
+    /// Packets with a bad checksum, plus stray bytes.
+    corrupted: bool,
+}
+
+/// Serial connection used to scan the bus at one baud rate.
+pub struct BusScanner {
+    port: Box<dyn serialport::SerialPort>,
+    baud: u32,
+    window: Duration,
+}
+
+impl BusScanner {
+    pub fn open(serial_port: &str, baud: u32) -> Result<Self> {
+        let port = serialport::new(serial_port, baud)
+            .timeout(Duration::from_millis(1))
+            .open()
+            .with_context(|| format!("Failed to open serial port {} at {} baud", serial_port, baud))?;
+        // Time to send a short read reply (10 bits per byte) plus the
+        // servo's return delay
+        let window = Duration::from_micros(1500) + Duration::from_secs_f64(16.0 * 10.0 / baud as f64);
+        Ok(Self { port, baud, window })
+    }
+
+    /// Ping every ID and read the details of those that answer.
+    pub fn scan(&mut self) -> Result<Vec<ScannedServo>> {
+        let mut servos = Vec::new();
+        for id in 0..=MAX_ID {
+            let replies = self.transact(&ping_packet(id))?;
+            let count = replies.packets.iter().filter(|(i, _)| *i == id).count();
+            if count == 0 && !replies.corrupted {
+                continue;
+            }
+
+            let model = self.read(id, ADDR_MODEL_NUMBER, 2)?.map(|d| u16::from_le_bytes([d[0], d[1]]));
+            let firmware = self.read(id, ADDR_FIRMWARE_VERSION, 2)?.map(|d| (d[0], d[1]));
+            let position = self
+                .read(id, ADDR_PRESENT_POSITION, 2)?
+                .map(|d| raw_to_rad(i16::from_le_bytes([d[0], d[1]])));
+            servos.push(ScannedServo {
+                id,
+                baud: self.baud,
+                model,
+                firmware,
+                position,
+                replies: count.max(1),
+                corrupted: replies.corrupted,
+            });
+        }
+        Ok(servos)
+    }
+
+    /// Read `len` bytes at `addr` from `id`. None unless exactly one good
+    /// reply of the right length came back.
+    fn read(&mut self, id: u8, addr: u8, len: u8) -> Result<Option<Vec<u8>>> {
+        let replies = self.transact(&read_packet(id, addr, len))?;
+        let mut matching = replies.packets.into_iter().filter(|(i, _)| *i == id);
+        Ok(match (matching.next(), matching.next()) {
+            (Some((_, params)), None) if params.len() == len as usize => Some(params),
+            _ => None,
+        })
+    }
+
+    /// Send `packet` and collect every status packet until the reply window
+    /// closes. Waits the whole window so a second servo's reply is seen too.
+    fn transact(&mut self, packet: &[u8]) -> Result<Replies> {
+        let _ = self.port.clear(serialport::ClearBuffer::Input);
+        self.port.write_all(packet).context("Serial write failed")?;
+        self.port.flush().context("Serial flush failed")?;
+
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 64];
+        let deadline = Instant::now() + self.window;
+        while Instant::now() < deadline {
+            match self.port.read(&mut chunk) {
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e).context("Serial read failed"),
+            }
+        }
+        Ok(parse_replies(&buf))
+    }
+}
+
+fn ping_packet(id: u8) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(6);
+    packet.extend_from_slice(&HEADER);
+    packet.push(id);
+    packet.push(2); // instruction + checksum
+    packet.push(INST_PING);
+    packet.push(compute_checksum(&packet[2..]));
+    packet
+}
+
+/// Split everything received in a reply window into status packets.
+fn parse_replies(mut buf: &[u8]) -> Replies {
+    let mut replies = Replies::default();
+    loop {
+        match parse_status_packet(buf) {
+            ParsedStatus::Incomplete => {
+                replies.corrupted |= !buf.is_empty();
+                return replies;
+            }
+            ParsedStatus::Skip(n) => {
+                replies.corrupted = true;
+                buf = &buf[n..];
+            }
+            ParsedStatus::Packet { id, params, len, .. } => {
+                replies.packets.push((id, params));
+                buf = &buf[len..];
+            }
+        }
+    }
+}
+
+/// Scan results checked against the expected joint map.
+#[derive(Debug)]
+pub struct ScanReport {
+    /// Every servo found, by baud rate then ID.
+    pub servos: Vec<ScannedServo>,
+    /// Joints (name, ID) whose servo did not answer.
+    pub missing: Vec<(String, u8)>,
+    /// IDs that seem to be shared by more than one servo.
+    pub duplicates: Vec<u8>,
+    /// Answering IDs that no expected joint uses.
+    pub unexpected: Vec<u8>,
+}
+
+impl ScanReport {
+    pub fn new(servos: Vec<ScannedServo>, expected: &[(&str, u8)]) -> Self {
+        let mut ids: Vec<u8> = servos.iter().map(|s| s.id).collect();
+        ids.sort_unstable();
+
+        let mut duplicates: Vec<u8> = ids
+            .windows(2)
+            .filter(|w| w[0] == w[1])
+            .map(|w| w[0])
+            .chain(servos.iter().filter(|s| s.collided()).map(|s| s.id))
+            .collect();
+        duplicates.sort_unstable();
+        duplicates.dedup();
+        ids.dedup();
+
+        let missing = expected
+            .iter()
+            .filter(|(_, id)| !ids.contains(id))
+            .map(|&(name, id)| (name.to_string(), id))
+            .collect();
+        let unexpected = ids
+            .into_iter()
+            .filter(|id| !expected.iter().any(|(_, e)| e == id))
+            .collect();
+
+        Self {
+            servos,
+            missing,
+            duplicates,
+            unexpected,
+        }
+    }
+
+    /// Whether the bus matches the joint map exactly.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.duplicates.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servo(id: u8, baud: u32) -> ScannedServo {
+        ScannedServo {
+            id,
+            baud,
+            model: Some(MODEL_STS3215),
+            firmware: Some((3, 10)),
+            position: Some(0.0),
+            replies: 1,
+            corrupted: false,
+        }
+    }
+
+    #[test]
+    fn test_collided_replies_are_flagged() {
+        let mut reply = vec![0x00];
+        reply.extend_from_slice(&[0xFF, 0xFF, 21, 2, 0, !(21u8 + 2)]);
+        reply.extend_from_slice(&[0xFF, 0xFF, 21, 2, 0, !(21u8 + 2)]);
+        let replies = parse_replies(&reply);
+        assert_eq!(replies.packets.len(), 2);
+        assert!(replies.corrupted);
+        assert!(!parse_replies(&reply[1..7]).corrupted);
+    }
+
+    #[test]
+    fn test_report_against_joint_map() {
+        let expected = [("left_hip_yaw", 20), ("left_hip_roll", 21), ("left_knee", 23)];
+        let mut collided = servo(23, 1_000_000);
+        collided.replies = 2;
+        let servos = vec![
+            servo(1, 1_000_000),
+            servo(20, 1_000_000),
+            collided,
+            servo(20, 115_200),
+        ];
+
+        let report = ScanReport::new(servos, &expected);
+        assert_eq!(report.missing, vec![("left_hip_roll".to_string(), 21)]);
+        assert_eq!(report.duplicates, vec![20, 23]);
+        assert_eq!(report.unexpected, vec![1]);
+        assert!(!report.is_ok());
+
+        let report = ScanReport::new(vec![servo(20, 1_000_000)], &expected[..1]);
+        assert!(report.is_ok());
+    }
+}
@@ -0,0 +1,359 @@
+//! Camera capture and MJPEG streaming.
+//!
+//! Frames come from a capture process writing an MJPEG stream to its
+//! stdout: `rpicam-vid` (`libcamera-vid` on older images) for the Pi camera,
+//! or `ffmpeg` for a V4L2 webcam that outputs MJPEG. A background thread
+//! splits the stream into JPEG frames and keeps the newest in a
+//! `FrameFeed`, which vision features read and the remote server streams
+//! to browsers on its own port:
+//!
+//! ```text
+//! http://<duck>:9871/camera.mjpg   multipart MJPEG stream
+//! http://<duck>:9871/camera.jpg    the latest frame
+//! ```
+
+use anyhow::{bail, Context, Result};
+use std::io::{ErrorKind, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::{CameraConfig, CameraSource};
+
+/// Bytes a frame may grow to before the stream is assumed corrupt.
+const MAX_FRAME_BYTES: usize = 4 << 20;
+
+const SOI: [u8; 2] = [0xFF, 0xD8];
+const EOI: [u8; 2] = [0xFF, 0xD9];
+
+const BOUNDARY: &str = "frame";
+
+/// Splits an MJPEG byte stream into JPEG frames (start to end of image).
+#[derive(Debug, Default)]
+pub struct JpegSplitter {
+    buffer: Vec<u8>,
+}
+
+impl JpegSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add bytes; returns the frames they complete.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+        loop {
+            let Some(start) = find(&self.buffer, &SOI, 0) else {
+                // Keep a trailing 0xFF, which may start the next frame
+                let keep = usize::from(self.buffer.last() == Some(&0xFF));
+                self.buffer.drain(..self.buffer.len() - keep);
+                break;
+            };
+            let Some(end) = find(&self.buffer, &EOI, start + 2) else {
+                self.buffer.drain(..start);
+                if self.buffer.len() > MAX_FRAME_BYTES {
+                    tracing::warn!("Camera frame over {} bytes, skipping", MAX_FRAME_BYTES);
+                    self.buffer.clear();
+                }
+                break;
+            };
+            frames.push(self.buffer[start..end + 2].to_vec());
+            self.buffer.drain(..end + 2);
+        }
+        frames
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+/// What an HTTP request to the remote server asks of the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraRequest {
+    Stream,
+    Snapshot,
+}
+
+impl CameraRequest {
+    /// Parse an HTTP request head; `None` for WebSocket upgrades and other
+    /// paths.
+    pub fn parse(head: &str) -> Option<Self> {
+        let mut lines = head.lines();
+        let mut request_line = lines.next()?.split_whitespace();
+        if request_line.next()? != "GET" {
+            return None;
+        }
+        let upgrade = lines.any(|line| {
+            let line = line.to_ascii_lowercase();
+            line.starts_with("upgrade:") && line.contains("websocket")
+        });
+        if upgrade {
+            return None;
+        }
+        let path = request_line.next()?.split('?').next()?;
+        match path {
+            "/camera.mjpg" => Some(CameraRequest::Stream),
+            "/camera.jpg" => Some(CameraRequest::Snapshot),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Latest {
+    /// Frames captured so far; 0 before the first.
+    seq: u64,
+    frame: Option<Arc<Vec<u8>>>,
+    closed: bool,
+}
+
+/// The newest camera frame, shared between threads.
+#[derive(Clone, Default)]
+pub struct FrameFeed {
+    shared: Arc<(Mutex<Latest>, Condvar)>,
+}
+
+impl FrameFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&self, frame: Vec<u8>) {
+        let (latest, ready) = &*self.shared;
+        if let Ok(mut latest) = latest.lock() {
+            latest.seq += 1;
+            latest.frame = Some(Arc::new(frame));
+        }
+        ready.notify_all();
+    }
+
+    /// The newest JPEG frame, if any.
+    pub fn latest(&self) -> Option<Arc<Vec<u8>>> {
+        self.shared.0.lock().ok()?.frame.clone()
+    }
+
+    /// Wait up to `timeout` for a frame newer than `seq`. Returns it with
+    /// its sequence number.
+    pub fn wait_newer(&self, seq: u64, timeout: Duration) -> Option<(u64, Arc<Vec<u8>>)> {
+        let (latest, ready) = &*self.shared;
+        let latest = latest.lock().ok()?;
+        let (latest, _) = ready
+            .wait_timeout_while(latest, timeout, |l| l.seq <= seq && !l.closed)
+            .ok()?;
+        match &latest.frame {
+            Some(frame) if latest.seq > seq => Some((latest.seq, Arc::clone(frame))),
+            _ => None,
+        }
+    }
+
+    /// Whether capture has stopped for good.
+    pub fn is_closed(&self) -> bool {
+        self.shared.0.lock().map_or(true, |l| l.closed)
+    }
+
+    fn close(&self) {
+        if let Ok(mut latest) = self.shared.0.lock() {
+            latest.closed = true;
+        }
+        self.shared.1.notify_all();
+    }
+}
+
+/// Capture process plus the thread reading its frames.
+pub struct Camera {
+    feed: FrameFeed,
+    child: Child,
+}
+
+impl Camera {
+    pub fn start(config: &CameraConfig) -> Result<Self> {
+        let mut child = spawn_capture(config)?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .context("Camera capture has no stdout")?;
+        let feed = FrameFeed::new();
+        let worker_feed = feed.clone();
+
+        thread::spawn(move || {
+            let mut splitter = JpegSplitter::new();
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                match stdout.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        for frame in splitter.push(&buf[..n]) {
+                            worker_feed.publish(frame);
+                        }
+                    }
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        tracing::warn!("Camera read failed: {}", e);
+                        break;
+                    }
+                }
+            }
+            worker_feed.close();
+            tracing::info!("Camera capture ended");
+        });
+
+        tracing::info!(
+            "Camera capturing {}x{} at {} fps ({:?})",
+            config.width,
+            config.height,
+            config.framerate,
+            config.source
+        );
+        Ok(Self { feed, child })
+    }
+
+    pub fn feed(&self) -> FrameFeed {
+        self.feed.clone()
+    }
+}
+
+impl Drop for Camera {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn spawn_capture(config: &CameraConfig) -> Result<Child> {
+    let split = |args: String| args.split_whitespace().map(String::from).collect::<Vec<_>>();
+    let commands: Vec<(&str, Vec<String>)> = match config.source {
+        CameraSource::Libcamera => {
+            let args = split(format!(
+                "-t 0 -n --codec mjpeg --width {} --height {} --framerate {} -q {} -o -",
+                config.width, config.height, config.framerate, config.quality
+            ));
+            // Renamed from libcamera-vid in Raspberry Pi OS Bookworm
+            vec![("rpicam-vid", args.clone()), ("libcamera-vid", args)]
+        }
+        CameraSource::V4l2 => {
+            let mut args = split(format!(
+                "-hide_banner -loglevel error -f v4l2 -input_format mjpeg \
+                 -video_size {}x{} -framerate {} -i",
+                config.width, config.height, config.framerate
+            ));
+            args.push(config.device.clone());
+            args.extend(split("-c:v copy -f mjpeg -".to_string()));
+            vec![("ffmpeg", args)]
+        }
+    };
+
+    for (program, args) in &commands {
+        let spawned = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(child) => return Ok(child),
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to start {}", program)),
+        }
+    }
+    let programs: Vec<_> = commands.iter().map(|(program, _)| *program).collect();
+    bail!("No camera capture program found (tried {})", programs.join(", "))
+}
+
+/// Answer an HTTP camera request on `stream` until the client leaves or
+/// `stopped` returns true.
+pub fn serve(
+    mut stream: impl Write,
+    request: CameraRequest,
+    feed: &FrameFeed,
+    stopped: impl Fn() -> bool,
+) -> Result<()> {
+    match request {
+        CameraRequest::Snapshot => {
+            let Some(frame) = feed.latest() else {
+                let body = "No camera frame yet\n";
+                write!(
+                    stream,
+                    "HTTP/1.1 503 Service Unavailable\r\nContent-Type: text/plain\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )?;
+                return Ok(());
+            };
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\
+                 Cache-Control: no-cache\r\nConnection: close\r\n\r\n",
+                frame.len()
+            )?;
+            stream.write_all(&frame)?;
+        }
+        CameraRequest::Stream => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\n\
+                 Content-Type: multipart/x-mixed-replace; boundary={}\r\n\
+                 Cache-Control: no-cache\r\nConnection: close\r\n\r\n",
+                BOUNDARY
+            )?;
+            let mut seq = 0;
+            while !stopped() && !feed.is_closed() {
+                let Some((next, frame)) = feed.wait_newer(seq, Duration::from_millis(500)) else {
+                    continue;
+                };
+                seq = next;
+                write!(
+                    stream,
+                    "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+                    BOUNDARY,
+                    frame.len()
+                )?;
+                stream.write_all(&frame)?;
+                stream.write_all(b"\r\n")?;
+            }
+        }
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_frames_across_reads() {
+        let mut splitter = JpegSplitter::new();
+        let first = [0xFF, 0xD8, 1, 2, 0xFF, 0xD9];
+        let second = [0xFF, 0xD8, 3, 0xFF, 0xD9];
+
+        // Garbage, a whole frame, then the next one split mid-marker
+        let mut stream = vec![9, 9];
+        stream.extend_from_slice(&first);
+        stream.extend_from_slice(&second);
+        let (a, b) = stream.split_at(9);
+        assert_eq!(splitter.push(a), vec![first.to_vec()]);
+        assert!(splitter.push(&b[..2]).is_empty());
+        assert_eq!(splitter.push(&b[2..]), vec![second.to_vec()]);
+
+        let head = "GET /camera.mjpg HTTP/1.1\r\nHost: duck\r\n\r\n";
+        assert_eq!(CameraRequest::parse(head), Some(CameraRequest::Stream));
+        let upgrade = "GET /camera.jpg HTTP/1.1\r\nUpgrade: websocket\r\n\r\n";
+        assert_eq!(CameraRequest::parse(upgrade), None);
+        assert_eq!(CameraRequest::parse("GET / HTTP/1.1\r\n\r\n"), None);
+
+        let feed = FrameFeed::new();
+        feed.publish(first.to_vec());
+        let mut response = Vec::new();
+        serve(&mut response, CameraRequest::Snapshot, &feed, || false).unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 200 OK\r\nContent-Type: image/jpeg"));
+        assert!(response.ends_with(&first));
+    }
+}
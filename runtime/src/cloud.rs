@@ -0,0 +1,540 @@
+//! Backend connectivity — one link to the fleet API for every feature.
+//!
+//! Heartbeats, uploads and any other backend calls go through `CloudLink`,
+//! which sends them from a background thread so the control loop never waits
+//! on the network. Failures are handled in one place:
+//!
+//! - transient failures (no network, timeouts, 5xx, 429) back off
+//!   exponentially with jitter, from 1 s up to a minute;
+//! - `Delivery::Durable` requests are queued to an outbox directory on disk
+//!   first and removed only once the backend has accepted them, so they
+//!   survive both outages and restarts;
+//! - `Delivery::Latest` requests (heartbeats) keep only the newest body per
+//!   path, since a stale heartbeat is worth nothing;
+//! - the link's `CloudStatus` is the single "are we online" indicator shown in
+//!   telemetry and on the eyes.
+//!
+//! Requests the backend rejects outright (other 4xx) are logged and dropped.
+//! Each request carries an `X-Correlation-Id` made on the robot, kept across
+//! retries and quoted in these logs, to find it in the backend's traces.
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, select, Receiver, Sender};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::CloudConfig;
+use crate::runtime_health::RuntimeHealth;
+
+/// First retry delay after a failure, doubled per failure up to `MAX_BACKOFF`.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Per-request timeout.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests waiting to reach the worker thread before new ones are dropped.
+const QUEUE_CAPACITY: usize = 64;
+
+/// Outbox entries kept on disk; the oldest are discarded beyond this.
+const MAX_OUTBOX: usize = 10_000;
+
+/// Whether the robot can currently reach the backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum CloudStatus {
+    /// No backend configured.
+    #[default]
+    Disabled,
+    /// Configured, nothing sent yet.
+    Connecting,
+    Online,
+    Offline,
+}
+
+impl CloudStatus {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => CloudStatus::Connecting,
+            2 => CloudStatus::Online,
+            3 => CloudStatus::Offline,
+            _ => CloudStatus::Disabled,
+        }
+    }
+}
+
+/// How a request is kept until the backend accepts it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Only the newest body per path is kept, in memory.
+    Latest,
+    /// Queued on disk and sent in order, surviving restarts.
+    Durable,
+}
+
+/// Health the robot reports in its heartbeat (the backend's `RobotHealth`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportedHealth {
+    Healthy,
+    Degraded,
+    Faulted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Outgoing {
+    path: String,
+    body: Value,
+    #[serde(default)]
+    correlation_id: String,
+}
+
+/// Handle to the backend connectivity thread.
+pub struct CloudLink {
+    robot_id: String,
+    robot_name: Option<String>,
+    heartbeat_interval: Duration,
+    last_heartbeat: Option<Instant>,
+    sender: Sender<(Outgoing, Delivery)>,
+    status: Arc<AtomicU8>,
+    stop_tx: Sender<()>,
+}
+
+impl CloudLink {
+    /// Start the link if the config names a backend and a robot id; `None`
+    /// leaves the robot offline-only. The API token comes from
+    /// `OPENDUCKRUST_TOKEN`, as for the CLI.
+    pub fn from_config(config: &CloudConfig, outbox_dir: &Path) -> Result<Option<Self>> {
+        let (Some(api_url), Some(robot_id)) = (&config.api_url, &config.robot_id) else {
+            return Ok(None);
+        };
+        let token = std::env::var("OPENDUCKRUST_TOKEN").ok();
+        let mut link = Self::start(api_url, token, outbox_dir)?;
+        link.robot_id = robot_id.clone();
+        link.robot_name = config.robot_name.clone();
+        link.heartbeat_interval = Duration::from_secs_f64(config.heartbeat_interval.max(1.0));
+        Ok(Some(link))
+    }
+
+    fn start(api_url: &str, token: Option<String>, outbox_dir: &Path) -> Result<Self> {
+        let outbox = Outbox::open(outbox_dir)?;
+        if !outbox.is_empty() {
+            tracing::info!("Cloud outbox holds {} queued request(s)", outbox.len());
+        }
+
+        let (tx, rx) = bounded::<(Outgoing, Delivery)>(QUEUE_CAPACITY);
+        let (stop_tx, stop_rx) = bounded::<()>(1);
+        let status = Arc::new(AtomicU8::new(CloudStatus::Connecting as u8));
+
+        let client = ApiClient::new(api_url, token);
+        let worker_status = Arc::clone(&status);
+        thread::spawn(move || {
+            cloud_worker(client, outbox, rx, stop_rx, worker_status);
+        });
+
+        tracing::info!("Cloud link to {}", api_url);
+
+        Ok(Self {
+            robot_id: String::new(),
+            robot_name: None,
+            heartbeat_interval: Duration::MAX,
+            last_heartbeat: None,
+            sender: tx,
+            status,
+            stop_tx,
+        })
+    }
+
+    /// Queue a POST to `path` (non-blocking).
+    pub fn post(&self, path: &str, body: impl Serialize, delivery: Delivery) {
+        let body = match serde_json::to_value(body) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("Cloud: cannot serialize request to {}: {}", path, e);
+                return;
+            }
+        };
+        let outgoing = Outgoing {
+            path: path.to_string(),
+            body,
+            correlation_id: format!("{}-{:016x}", self.robot_id, rand::thread_rng().gen::<u64>()),
+        };
+        if self.sender.try_send((outgoing, delivery)).is_err() {
+            tracing::warn!("Cloud queue full, dropping request to {}", path);
+        }
+    }
+
+    /// Send a heartbeat, with the control loop's condition, if one is due.
+    pub fn heartbeat(&mut self, health: ReportedHealth, runtime: &RuntimeHealth, now: Instant) {
+        let due = self
+            .last_heartbeat
+            .is_none_or(|t| now.saturating_duration_since(t) >= self.heartbeat_interval);
+        if !due {
+            return;
+        }
+        self.last_heartbeat = Some(now);
+
+        #[derive(Serialize)]
+        struct Heartbeat<'a> {
+            name: Option<&'a str>,
+            health: ReportedHealth,
+            runtime: &'a RuntimeHealth,
+        }
+        let path = format!("/api/robots/{}/heartbeat", self.robot_id);
+        let body = Heartbeat {
+            name: self.robot_name.as_deref(),
+            health,
+            runtime,
+        };
+        self.post(&path, body, Delivery::Latest);
+    }
+
+    pub fn status(&self) -> CloudStatus {
+        CloudStatus::from_u8(self.status.load(Ordering::Relaxed))
+    }
+
+    /// Signal the background thread to stop.
+    pub fn stop(&self) {
+        let _ = self.stop_tx.try_send(());
+    }
+}
+
+impl Drop for CloudLink {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Exponential backoff with jitter.
+#[derive(Debug)]
+struct Backoff {
+    failures: u32,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Self { failures: 0 }
+    }
+
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+
+    /// Delay before the next attempt after another failure: doubling from
+    /// `MIN_BACKOFF`, capped at `MAX_BACKOFF`, less up to 20% jitter so a
+    /// fleet coming back online does not retry in lockstep.
+    fn next_delay(&mut self) -> Duration {
+        let exp = MIN_BACKOFF.saturating_mul(1 << self.failures.min(16));
+        self.failures = self.failures.saturating_add(1);
+        let jitter = rand::thread_rng().gen_range(0.8..=1.0);
+        exp.min(MAX_BACKOFF).mul_f64(jitter)
+    }
+}
+
+/// Durable requests, one JSON file each, named by sequence number.
+struct Outbox {
+    dir: PathBuf,
+    entries: VecDeque<(u64, Outgoing)>,
+    next_seq: u64,
+}
+
+impl Outbox {
+    fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cloud outbox {}", dir.display()))?;
+
+        let mut entries = BTreeMap::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(seq) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<u64>().ok())
+                .filter(|_| path.extension().is_some_and(|e| e == "json"))
+            else {
+                continue;
+            };
+            match fs::read(&path).map(|bytes| serde_json::from_slice::<Outgoing>(&bytes)) {
+                Ok(Ok(outgoing)) => {
+                    entries.insert(seq, outgoing);
+                }
+                _ => {
+                    tracing::warn!("Cloud outbox: discarding unreadable {}", path.display());
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+
+        let next_seq = entries.keys().next_back().map_or(0, |s| s + 1);
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            entries: entries.into_iter().collect(),
+            next_seq,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn file(&self, seq: u64) -> PathBuf {
+        self.dir.join(format!("{:020}.json", seq))
+    }
+
+    fn push(&mut self, outgoing: Outgoing) -> Result<()> {
+        if self.entries.len() >= MAX_OUTBOX {
+            tracing::warn!("Cloud outbox full, discarding the oldest request");
+            self.pop();
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        fs::write(self.file(seq), serde_json::to_vec(&outgoing)?)?;
+        self.entries.push_back((seq, outgoing));
+        Ok(())
+    }
+
+    fn front(&self) -> Option<&Outgoing> {
+        self.entries.front().map(|(_, o)| o)
+    }
+
+    fn pop(&mut self) {
+        if let Some((seq, _)) = self.entries.pop_front() {
+            let _ = fs::remove_file(self.file(seq));
+        }
+    }
+}
+
+/// Outcome of one request.
+enum SendError {
+    /// Worth retrying later.
+    Transient(String),
+    /// The backend refused the request itself.
+    Rejected(String),
+}
+
+struct ApiClient {
+    agent: ureq::Agent,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl ApiClient {
+    fn new(base_url: &str, token: Option<String>) -> Self {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into();
+        Self {
+            agent,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    fn post(&self, outgoing: &Outgoing) -> Result<(), SendError> {
+        let mut request = self.agent.post(format!("{}{}", self.base_url, outgoing.path));
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        if !outgoing.correlation_id.is_empty() {
+            request = request.header("X-Correlation-Id", &outgoing.correlation_id);
+        }
+        match request.send_json(&outgoing.body) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::StatusCode(code)) if code == 429 || code >= 500 => {
+                Err(SendError::Transient(format!("HTTP {}", code)))
+            }
+            Err(ureq::Error::StatusCode(code)) => Err(SendError::Rejected(format!("HTTP {}", code))),
+            Err(e) => Err(SendError::Transient(e.to_string())),
+        }
+    }
+}
+
+/// Background worker that delivers queued requests, backing off on failure.
+fn cloud_worker(
+    client: ApiClient,
+    mut outbox: Outbox,
+    rx: Receiver<(Outgoing, Delivery)>,
+    stop_rx: Receiver<()>,
+    status: Arc<AtomicU8>,
+) {
+    let mut latest: BTreeMap<String, Outgoing> = BTreeMap::new();
+    let mut backoff = Backoff::new();
+    let mut next_attempt = Instant::now();
+
+    let set_status = |new: CloudStatus| {
+        let old = CloudStatus::from_u8(status.swap(new as u8, Ordering::Relaxed));
+        if old != new {
+            tracing::info!("Cloud status: {:?}", new);
+        }
+    };
+
+    loop {
+        let pending = !latest.is_empty() || !outbox.is_empty();
+        let wait = if pending {
+            next_attempt.saturating_duration_since(Instant::now())
+        } else {
+            MAX_BACKOFF
+        };
+
+        let mut incoming = Vec::new();
+        select! {
+            recv(stop_rx) -> _ => break,
+            recv(rx) -> msg => match msg {
+                Ok(item) => incoming.push(item),
+                Err(_) => break,
+            },
+            default(wait) => {}
+        }
+        incoming.extend(rx.try_iter());
+
+        for (outgoing, delivery) in incoming {
+            match delivery {
+                Delivery::Latest => {
+                    latest.insert(outgoing.path.clone(), outgoing);
+                }
+                Delivery::Durable => {
+                    if let Err(e) = outbox.push(outgoing) {
+                        tracing::error!("Cloud outbox write failed: {}", e);
+                    }
+                }
+            }
+        }
+
+        if Instant::now() < next_attempt {
+            continue;
+        }
+
+        // Latest-only requests first (heartbeats), then the outbox in order
+        let result = (|| {
+            while let Some((path, outgoing)) = latest.pop_first() {
+                if let Err(e) = deliver(&client, &outgoing) {
+                    latest.entry(path).or_insert(outgoing);
+                    return Err(e);
+                }
+            }
+            while let Some(outgoing) = outbox.front() {
+                deliver(&client, outgoing)?;
+                outbox.pop();
+            }
+            Ok(())
+        })();
+
+        match result {
+            Ok(()) => {
+                backoff.reset();
+                set_status(CloudStatus::Online);
+            }
+            Err(reason) => {
+                let delay = backoff.next_delay();
+                tracing::debug!("Cloud unreachable ({}), retrying in {:.1}s", reason, delay.as_secs_f64());
+                next_attempt = Instant::now() + delay;
+                set_status(CloudStatus::Offline);
+            }
+        }
+    }
+
+    tracing::info!("Cloud worker thread exiting");
+}
+
+/// Send one request. Rejected requests count as delivered (they would never
+/// succeed); only transient failures are returned.
+fn deliver(client: &ApiClient, outgoing: &Outgoing) -> Result<(), String> {
+    match client.post(outgoing) {
+        Ok(()) => Ok(()),
+        Err(SendError::Rejected(reason)) => {
+            tracing::warn!(
+                "Cloud: backend rejected {} ({}, correlation id {}), dropping",
+                outgoing.path,
+                reason,
+                outgoing.correlation_id
+            );
+            Ok(())
+        }
+        Err(SendError::Transient(reason)) => Err(reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("odr-cloud-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let mut backoff = Backoff::new();
+        let delays: Vec<_> = (0..10).map(|_| backoff.next_delay()).collect();
+        assert!(delays[0] <= MIN_BACKOFF && delays[0] >= MIN_BACKOFF.mul_f64(0.8));
+        assert!(delays[3] >= Duration::from_secs_f64(8.0 * 0.8));
+        assert!(delays.iter().all(|&d| d <= MAX_BACKOFF));
+        backoff.reset();
+        assert!(backoff.next_delay() <= MIN_BACKOFF);
+    }
+
+    #[test]
+    fn test_outbox_survives_reopen_in_order() {
+        let dir = temp_dir("outbox");
+        let _ = fs::remove_dir_all(&dir);
+        let mut outbox = Outbox::open(&dir).unwrap();
+        for i in 0..3 {
+            let outgoing = Outgoing {
+                path: format!("/api/upload/{}", i),
+                body: json!({ "i": i }),
+                correlation_id: format!("duck-{}", i),
+            };
+            outbox.push(outgoing).unwrap();
+        }
+        outbox.pop();
+
+        let mut reopened = Outbox::open(&dir).unwrap();
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened.front().unwrap().path, "/api/upload/1");
+        assert_eq!(reopened.front().unwrap().correlation_id, "duck-1");
+        reopened.push(Outgoing {
+            path: "/api/upload/3".into(),
+            body: Value::Null,
+            correlation_id: String::new(),
+        })
+        .unwrap();
+        assert_eq!(reopened.entries.back().unwrap().0, 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unreachable_backend_goes_offline_and_keeps_durable() {
+        let dir = temp_dir("offline");
+        let _ = fs::remove_dir_all(&dir);
+        // Nothing listens on port 9 (discard) of localhost in the test sandbox
+        let link = CloudLink::start("http://127.0.0.1:9", None, &dir).unwrap();
+        assert_eq!(link.status(), CloudStatus::Connecting);
+
+        link.post("/api/upload", json!({ "x": 1 }), Delivery::Durable);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while link.status() != CloudStatus::Offline && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(link.status(), CloudStatus::Offline);
+        assert_eq!(Outbox::open(&dir).unwrap().len(), 1);
+
+        drop(link);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
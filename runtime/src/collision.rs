@@ -0,0 +1,204 @@
+//! Collision and jam detection from the servos' present load.
+//!
+//! Every `check_every` ticks the control loop reads the load of each servo.
+//! A joint has collided when its load passes its limit (`load_limit`, or its
+//! entry in `joint_load_limits`), or jumps by more than `load_spike` above
+//! its recent average — a leg catching a table edge, a finger in the knee.
+//! The offending joint's target is then pulled back toward where the joint
+//! actually is, which cuts the torque it pushes with, held there for
+//! `hold_duration` and eased back to the policy's target over as long. The
+//! control loop publishes a `CollisionDetected` event for each detection.
+
+use std::time::{Duration, Instant};
+
+use crate::config::CollisionConfig;
+
+/// Smoothing of the per-joint load average, in samples.
+const BASELINE_WINDOW: f64 = 25.0;
+
+/// Why a joint counts as collided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionKind {
+    /// Load above the joint's limit.
+    OverLimit,
+    /// Sudden jump above the joint's recent load.
+    Spike,
+}
+
+/// A detected collision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collision {
+    pub joint: usize,
+    /// Load that triggered it (fraction of max torque, signed).
+    pub load: f64,
+    pub kind: CollisionKind,
+}
+
+/// A joint target being held back after a collision.
+struct BackOff {
+    position: f64,
+    since: Instant,
+}
+
+/// Load monitoring and target back-off for every joint.
+pub struct CollisionDetector {
+    config: CollisionConfig,
+    limits: Vec<f64>,
+    baseline: Vec<Option<f64>>,
+    back_offs: Vec<Option<BackOff>>,
+    ticks: u64,
+}
+
+impl CollisionDetector {
+    pub fn new(config: CollisionConfig, joint_names: &[String]) -> Self {
+        let limits: Vec<f64> = joint_names
+            .iter()
+            .map(|name| config.joint_load_limits.get(name).copied().unwrap_or(config.load_limit))
+            .collect();
+        tracing::info!(
+            "Collision detection every {} ticks, load limit {:.0}%, spike {:.0}%",
+            config.check_every.max(1),
+            config.load_limit * 100.0,
+            config.load_spike * 100.0
+        );
+        Self {
+            config,
+            limits,
+            baseline: vec![None; joint_names.len()],
+            back_offs: joint_names.iter().map(|_| None).collect(),
+            ticks: 0,
+        }
+    }
+
+    /// Whether the loads should be read this tick. Call once per tick.
+    pub fn due(&mut self) -> bool {
+        let due = self.ticks.is_multiple_of(u64::from(self.config.check_every.max(1)));
+        self.ticks += 1;
+        due
+    }
+
+    /// Check fresh loads; joints that collided get their target backed off
+    /// from `targets` toward `present`.
+    pub fn update(
+        &mut self,
+        loads: &[f64],
+        present: &[f64],
+        targets: &[f64],
+        now: Instant,
+    ) -> Vec<Collision> {
+        let mut collisions = Vec::new();
+        for (joint, &load) in loads.iter().enumerate() {
+            if joint >= self.limits.len() || self.backing_off(joint, now) {
+                continue;
+            }
+            let magnitude = load.abs();
+            let kind = if magnitude > self.limits[joint] {
+                Some(CollisionKind::OverLimit)
+            } else if self.baseline[joint].is_some_and(|b| magnitude - b > self.config.load_spike) {
+                Some(CollisionKind::Spike)
+            } else {
+                None
+            };
+
+            match kind {
+                Some(kind) => {
+                    let (Some(&at), Some(&target)) = (present.get(joint), targets.get(joint)) else {
+                        continue;
+                    };
+                    let back_off = self.config.back_off.clamp(0.0, 1.0);
+                    self.back_offs[joint] = Some(BackOff {
+                        position: target + (at - target) * back_off,
+                        since: now,
+                    });
+                    collisions.push(Collision { joint, load, kind });
+                }
+                // The average only follows normal loads, so a jam stays a
+                // spike until it clears
+                None => {
+                    self.baseline[joint] = Some(match self.baseline[joint] {
+                        Some(b) => b + (magnitude - b) / BASELINE_WINDOW,
+                        None => magnitude,
+                    });
+                }
+            }
+        }
+        collisions
+    }
+
+    /// Replace the targets of backed-off joints. Call every tick.
+    pub fn apply(&mut self, targets: &mut [f64], now: Instant) {
+        let hold = Duration::from_secs_f64(self.config.hold_duration.max(0.0));
+        for (target, back_off) in targets.iter_mut().zip(&mut self.back_offs) {
+            let Some(b) = back_off else {
+                continue;
+            };
+            let elapsed = now.saturating_duration_since(b.since);
+            if elapsed < hold {
+                *target = b.position;
+            } else if elapsed < hold * 2 {
+                let s = (elapsed - hold).as_secs_f64() / hold.as_secs_f64();
+                *target = b.position + (*target - b.position) * s;
+            } else {
+                *back_off = None;
+            }
+        }
+    }
+
+    fn backing_off(&self, joint: usize, now: Instant) -> bool {
+        let hold = Duration::from_secs_f64(self.config.hold_duration.max(0.0));
+        self.back_offs[joint]
+            .as_ref()
+            .is_some_and(|b| now.saturating_duration_since(b.since) < hold * 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_spike_backs_off_the_joint() {
+        let config = CollisionConfig {
+            enabled: true,
+            joint_load_limits: HashMap::from([("left_knee".to_string(), 0.5)]),
+            back_off: 0.5,
+            hold_duration: 1.0,
+            ..CollisionConfig::default()
+        };
+        let joints: Vec<String> = vec!["left_hip_pitch".into(), "left_knee".into()];
+        let mut detector = CollisionDetector::new(config, &joints);
+        assert!(detector.due());
+        assert!(!detector.due());
+
+        let start = Instant::now();
+        let present = [0.0, 0.0];
+        let targets = [1.0, 1.0];
+        for _ in 0..10 {
+            assert!(detector.update(&[0.2, 0.1], &present, &targets, start).is_empty());
+        }
+
+        // A jump on the hip, an over-limit load on the knee
+        let collisions = detector.update(&[-0.7, 0.55], &present, &targets, start);
+        assert_eq!(
+            collisions,
+            vec![
+                Collision { joint: 0, load: -0.7, kind: CollisionKind::Spike },
+                Collision { joint: 1, load: 0.55, kind: CollisionKind::OverLimit },
+            ]
+        );
+        // Not reported again while backing off
+        assert!(detector.update(&[0.9, 0.9], &present, &targets, start).is_empty());
+
+        let at = |secs: f64| start + Duration::from_secs_f64(secs);
+        let mut held = targets;
+        detector.apply(&mut held, at(0.5));
+        assert_eq!(held, [0.5, 0.5]);
+        let mut easing = targets;
+        detector.apply(&mut easing, at(1.5));
+        assert_eq!(easing, [0.75, 0.75]);
+        let mut released = targets;
+        detector.apply(&mut released, at(2.5));
+        assert_eq!(released, targets);
+    }
+}
@@ -0,0 +1,114 @@
+//! Motor commands written faster than the policy runs.
+//!
+//! The policy was trained at the control frequency and keeps running at
+//! it, but the servos track smoother targets when they get them more often.
+//! With `--command-freq` set to a multiple of `--control-freq`, each policy
+//! tick is split into that many command steps: the first is written as
+//! usual, the rest at even intervals until the next tick, each moving the
+//! targets a step further from the previous command to the policy's new
+//! targets. The last step writes the new targets exactly, so the servos see
+//! the same poses as before, only filled in, one tick later at most.
+
+use anyhow::{bail, Result};
+use std::time::{Duration, Instant};
+
+/// A policy tick this many periods after the previous one starts over from
+/// its own targets instead of interpolating from old ones.
+const MAX_GAP_PERIODS: u32 = 2;
+
+/// Splits each policy tick's targets into evenly spaced command steps.
+#[derive(Debug, Clone)]
+pub struct CommandInterpolator {
+    steps: u32,
+    control_period: Duration,
+    from: Vec<f64>,
+    to: Vec<f64>,
+    started_at: Option<Instant>,
+}
+
+impl CommandInterpolator {
+    /// Steps for writing at `command_freq` (default: `control_freq`, one
+    /// step per tick).
+    pub fn new(control_freq: u32, command_freq: Option<u32>) -> Result<Self> {
+        if control_freq == 0 {
+            bail!("Control frequency must be above 0 Hz");
+        }
+        let command_freq = command_freq.unwrap_or(control_freq);
+        if command_freq < control_freq || !command_freq.is_multiple_of(control_freq) {
+            bail!(
+                "Command frequency {} Hz must be a multiple of the control frequency {} Hz",
+                command_freq,
+                control_freq
+            );
+        }
+        Ok(Self {
+            steps: command_freq / control_freq,
+            control_period: Duration::from_secs_f64(1.0 / control_freq as f64),
+            from: Vec::new(),
+            to: Vec::new(),
+            started_at: None,
+        })
+    }
+
+    /// Command steps per policy tick.
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Time between command steps.
+    pub fn command_period(&self) -> Duration {
+        self.control_period / self.steps
+    }
+
+    /// Begin a policy tick heading for `targets`, from the last command of
+    /// the previous tick if it was the one just before.
+    pub fn start(&mut self, targets: &[f64], now: Instant) {
+        let recent = self
+            .started_at
+            .is_some_and(|at| now.duration_since(at) <= self.control_period * MAX_GAP_PERIODS);
+        if recent && self.to.len() == targets.len() {
+            std::mem::swap(&mut self.from, &mut self.to);
+        } else {
+            self.from.clear();
+            self.from.extend_from_slice(targets);
+        }
+        self.to.clear();
+        self.to.extend_from_slice(targets);
+        self.started_at = Some(now);
+    }
+
+    /// Targets of command step `step` (1 to `steps`) of the current tick.
+    pub fn command(&self, step: u32) -> Vec<f64> {
+        let s = step.min(self.steps) as f64 / self.steps as f64;
+        self.from.iter().zip(&self.to).map(|(a, b)| a + (b - a) * s).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_fill_in_between_policy_ticks() {
+        assert!(CommandInterpolator::new(50, Some(120)).is_err());
+        assert!(CommandInterpolator::new(50, Some(25)).is_err());
+        assert!(CommandInterpolator::new(0, None).is_err());
+        assert!(CommandInterpolator::new(0, Some(50)).is_err());
+        let mut commands = CommandInterpolator::new(50, Some(200)).unwrap();
+        assert_eq!(commands.steps(), 4);
+        assert_eq!(commands.command_period(), Duration::from_millis(5));
+
+        // The first tick has nothing to come from
+        let start = Instant::now();
+        commands.start(&[0.0, 1.0], start);
+        assert_eq!(commands.command(1), [0.0, 1.0]);
+
+        commands.start(&[0.4, 0.6], start + Duration::from_millis(20));
+        assert_eq!(commands.command(1), [0.1, 0.9]);
+        assert_eq!(commands.command(4), [0.4, 0.6]);
+
+        // After a gap (paused, resting), targets are written as they are
+        commands.start(&[1.0, 1.0], start + Duration::from_secs(1));
+        assert_eq!(commands.command(1), [1.0, 1.0]);
+    }
+}
@@ -1,43 +1,1994 @@
 //! Duck configuration loader — reads duck_config.json for per-robot tuning.
 
-use anyhow::{Context, Result};
-use serde::Deserialize;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
+use crate::battery::LOW_BATTERY_SOUND;
+use crate::config_check;
+use crate::eye_expression::EyeExpression;
+use crate::overrides::Overrides;
+use crate::robot_model::RobotModel;
+use crate::servo_errors::ServoErrorReaction;
+
 /// Top-level duck configuration, loaded from JSON.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DuckConfig {
     #[serde(default)]
-    pub start_paused: bool,
+    pub start_paused: bool,
+
+    #[serde(default)]
+    pub imu_upside_down: bool,
+
+    /// Which IMU chip is fitted.
+    #[serde(default)]
+    pub imu_type: ImuType,
+
+    /// How the IMU chip is wired to the Pi.
+    #[serde(default)]
+    pub imu_bus: ImuBusConfig,
+
+    /// BNO055 calibration offsets saved by `--imu-calibrate` and restored at
+    /// startup (`~` expands to the home directory).
+    #[serde(default = "default_imu_calibration_path")]
+    pub imu_calibration_path: String,
+
+    /// IMU samples older than this (ms) soft-stop the duck.
+    #[serde(default = "default_imu_stale_ms")]
+    pub imu_stale_ms: f64,
+
+    #[serde(default)]
+    pub imu_mounting: ImuMountingConfig,
+
+    /// Foot contact sensors: GPIO switches or FSRs on an ADC.
+    #[serde(default)]
+    pub feet_contacts: FeetContactsConfig,
+
+    #[serde(default)]
+    pub phase_frequency_factor_offset: f64,
+
+    /// Phase frequency by commanded forward velocity.
+    #[serde(default)]
+    pub phase_frequency: PhaseFrequencyConfig,
+
+    /// What the gait phase does while the velocity commands are zero.
+    #[serde(default)]
+    pub phase_idle: PhaseIdleConfig,
+
+    #[serde(default)]
+    pub expression_features: ExpressionFeatures,
+
+    /// How the eyes are built: single LEDs or WS2812 rings.
+    #[serde(default)]
+    pub eye_leds: EyeLedsConfig,
+
+    /// Capture settings, used when `expression_features.camera` is on.
+    #[serde(default)]
+    pub camera: CameraConfig,
+
+    /// Head tracking on camera frames (needs the camera).
+    #[serde(default)]
+    pub head_tracking: HeadTrackingConfig,
+
+    /// Clap and wake word detection, used when
+    /// `expression_features.microphone` is on.
+    #[serde(default)]
+    pub microphone: MicrophoneConfig,
+
+    /// Text-to-speech, used when `expression_features.speaker` is on.
+    #[serde(default)]
+    pub speech: SpeechConfig,
+
+    /// Sounds played on runtime events, when `expression_features.speaker`
+    /// is on.
+    #[serde(default)]
+    pub sound_events: SoundEventsConfig,
+
+    /// Concurrency limit and ducking of overlapping sounds.
+    #[serde(default)]
+    pub sound_mixing: SoundMixingConfig,
+
+    /// Speaker volume (0.0-1.0).
+    #[serde(default = "default_volume")]
+    pub volume: f64,
+
+    #[serde(default = "default_joints_offsets", rename = "joints_offsets")]
+    pub joints_offset: HashMap<String, f64>,
+
+    /// Joints of the hardware variant: a built-in model name or a full
+    /// definition (see `robot_model`).
+    #[serde(default)]
+    pub robot_model: RobotModel,
+
+    /// Per-joint `[min, max]` overrides of the model's mechanical limits (radians).
+    #[serde(default)]
+    pub joint_limits: HashMap<String, [f64; 2]>,
+
+    /// Joints physically present on a partial test rig (e.g. a single leg on
+    /// a bench stand). The other joints are simulated. Empty means the full
+    /// robot.
+    #[serde(default)]
+    pub rig_joints: Vec<String>,
+
+    /// Servo family on the bus (`feetech` or `dynamixel`).
+    #[serde(default)]
+    pub servo_type: ServoType,
+
+    #[serde(default)]
+    pub gains: GainsConfig,
+
+    #[serde(default)]
+    pub torque_ramp: TorqueRampConfig,
+
+    #[serde(default)]
+    pub serial: SerialConfig,
+
+    #[serde(default)]
+    pub duty_cycle: DutyCycleConfig,
+
+    #[serde(default)]
+    pub servo_health: ServoHealthConfig,
+
+    #[serde(default)]
+    pub servo_errors: ServoErrorsConfig,
+
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+
+    #[serde(default)]
+    pub cloud: CloudConfig,
+
+    #[serde(default)]
+    pub gait: GaitConfig,
+
+    #[serde(default)]
+    pub battery: BatteryConfig,
+
+    #[serde(default)]
+    pub battery_sag: BatterySagConfig,
+
+    #[serde(default)]
+    pub thermal: ThermalConfig,
+
+    #[serde(default)]
+    pub emotes: EmotesConfig,
+
+    #[serde(default)]
+    pub collision: CollisionConfig,
+
+    /// Limits on the policy's motor targets, against bad inference outputs.
+    #[serde(default)]
+    pub action_limits: ActionLimitsConfig,
+
+    /// ONNX Runtime session settings for the policies and the planner.
+    #[serde(default)]
+    pub ort: OrtConfig,
+
+    /// Real-time scheduling of the control thread.
+    #[serde(default)]
+    pub realtime: RealtimeConfig,
+
+    #[serde(default)]
+    pub blackbox: BlackboxConfig,
+
+    #[serde(default)]
+    pub moments: MomentsConfig,
+
+    #[serde(default)]
+    pub routines: RoutinesConfig,
+
+    #[serde(default)]
+    pub sensor_latency: SensorLatencyConfig,
+
+    #[serde(default)]
+    pub gyro_bias: GyroBiasConfig,
+
+    #[serde(default)]
+    pub jog: JogConfig,
+}
+
+/// Which IMU chip is fitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImuType {
+    /// Bosch BNO055 (register interface over I2C).
+    #[default]
+    Bno055,
+    /// CEVA BNO085 (SH-2 over I2C); its game rotation vector is used as the
+    /// orientation directly.
+    Bno085,
+    /// InvenSense MPU-6050 (6-axis, no fusion on chip).
+    Mpu6050,
+}
+
+/// Bus the IMU chip is wired over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImuTransport {
+    #[default]
+    I2c,
+    /// BNO055 only (PS1 high), using its UART register protocol.
+    Uart,
+    /// MPU-6000 only, the SPI version of the MPU-6050 (`imu_type: mpu6050`).
+    Spi,
+}
+
+/// IMU wiring: the transport and its port settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImuBusConfig {
+    #[serde(default)]
+    pub transport: ImuTransport,
+
+    /// Serial device for `uart`.
+    #[serde(default = "default_imu_uart_path")]
+    pub uart_path: String,
+
+    #[serde(default = "default_imu_uart_baud")]
+    pub uart_baud: u32,
+
+    /// SPI bus and chip select for `spi` (`/dev/spidev<bus>.<chip_select>`).
+    #[serde(default)]
+    pub spi_bus: u8,
+
+    #[serde(default)]
+    pub spi_chip_select: u8,
+
+    #[serde(default = "default_imu_spi_clock_hz")]
+    pub spi_clock_hz: u32,
+}
+
+fn default_imu_uart_path() -> String {
+    "/dev/serial0".to_string()
+}
+
+fn default_imu_uart_baud() -> u32 {
+    115_200
+}
+
+fn default_imu_spi_clock_hz() -> u32 {
+    1_000_000
+}
+
+impl Default for ImuBusConfig {
+    fn default() -> Self {
+        Self {
+            transport: ImuTransport::default(),
+            uart_path: default_imu_uart_path(),
+            uart_baud: default_imu_uart_baud(),
+            spi_bus: 0,
+            spi_chip_select: 0,
+            spi_clock_hz: default_imu_spi_clock_hz(),
+        }
+    }
+}
+
+/// Angles (degrees) the IMU reads when the body is level, from a board
+/// mounted at a slant. They are rotated out of every reading.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+pub struct ImuMountingConfig {
+    #[serde(default)]
+    pub roll_bias: f64,
+
+    #[serde(default)]
+    pub pitch_bias: f64,
+
+    #[serde(default)]
+    pub yaw_bias: f64,
+}
+
+impl ImuMountingConfig {
+    pub fn is_level(&self) -> bool {
+        self.roll_bias == 0.0 && self.pitch_bias == 0.0 && self.yaw_bias == 0.0
+    }
+}
+
+/// Where the foot contact signal comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeetContactsSource {
+    /// Switches on GPIO 22 (left) and 27 (right): 0 or 1.
+    #[default]
+    Gpio,
+    /// Force-sensitive resistors on an MCP3008 (SPI): 0..1.
+    Mcp3008,
+    /// Force-sensitive resistors on an ADS1115 (I2C): 0..1.
+    Ads1115,
+}
+
+/// One foot's force-sensitive resistor. Readings are fractions of the ADC's
+/// full scale.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsrConfig {
+    pub channel: u8,
+
+    /// Reading with the foot in the air.
+    #[serde(default = "default_fsr_zero")]
+    pub zero: f64,
+
+    /// Reading from which the foot is in full contact.
+    #[serde(default = "default_fsr_saturation")]
+    pub saturation: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeetContactsConfig {
+    #[serde(default)]
+    pub source: FeetContactsSource,
+
+    #[serde(default = "default_left_fsr")]
+    pub left: FsrConfig,
+
+    #[serde(default = "default_right_fsr")]
+    pub right: FsrConfig,
+
+    /// MCP3008 SPI bus and chip select (`/dev/spidev<bus>.<chip_select>`).
+    #[serde(default)]
+    pub spi_bus: u8,
+
+    #[serde(default)]
+    pub spi_chip_select: u8,
+
+    /// ADS1115 I2C address.
+    #[serde(default = "default_ads1115_address")]
+    pub i2c_address: u16,
+
+    /// Rate the feet are sampled at, on their own thread.
+    #[serde(default = "default_feet_sample_hz")]
+    pub sample_hz: f64,
+
+    /// Low-pass cutoff applied to the readings (Hz); 0 disables it.
+    #[serde(default)]
+    pub low_pass_hz: f64,
+
+    /// Time a reading must stay past a threshold before the foot changes
+    /// state (ms).
+    #[serde(default = "default_feet_debounce_ms")]
+    pub debounce_ms: f64,
+
+    /// A lifted foot touches down above this reading...
+    #[serde(default = "default_feet_on_threshold")]
+    pub on_threshold: f64,
+
+    /// ...and a foot on the ground lifts off below this one.
+    #[serde(default = "default_feet_off_threshold")]
+    pub off_threshold: f64,
+}
+
+fn default_fsr_zero() -> f64 {
+    0.05
+}
+
+fn default_fsr_saturation() -> f64 {
+    0.6
+}
+
+fn default_left_fsr() -> FsrConfig {
+    FsrConfig {
+        channel: 0,
+        zero: default_fsr_zero(),
+        saturation: default_fsr_saturation(),
+    }
+}
+
+fn default_right_fsr() -> FsrConfig {
+    FsrConfig {
+        channel: 1,
+        ..default_left_fsr()
+    }
+}
+
+fn default_ads1115_address() -> u16 {
+    crate::adc::ADS1115_ADDR
+}
+
+fn default_feet_sample_hz() -> f64 {
+    200.0
+}
+
+fn default_feet_debounce_ms() -> f64 {
+    10.0
+}
+
+fn default_feet_on_threshold() -> f64 {
+    0.6
+}
+
+fn default_feet_off_threshold() -> f64 {
+    0.4
+}
+
+impl Default for FeetContactsConfig {
+    fn default() -> Self {
+        Self {
+            source: FeetContactsSource::default(),
+            left: default_left_fsr(),
+            right: default_right_fsr(),
+            spi_bus: 0,
+            spi_chip_select: 0,
+            i2c_address: default_ads1115_address(),
+            sample_hz: default_feet_sample_hz(),
+            low_pass_hz: 0.0,
+            debounce_ms: default_feet_debounce_ms(),
+            on_threshold: default_feet_on_threshold(),
+            off_threshold: default_feet_off_threshold(),
+        }
+    }
+}
+
+/// Which servos drive the joints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServoType {
+    /// Feetech STS3215 (Feetech/SCS protocol).
+    #[default]
+    Feetech,
+    /// Dynamixel XL330 or XL430 (Protocol 2.0).
+    Dynamixel,
+}
+
+/// Gait phase frequency by commanded forward velocity, matching the gait the
+/// policy was trained on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhaseFrequencyConfig {
+    /// `[vx (m/s), frequency factor]` points, interpolated linearly and held
+    /// past the ends. Empty takes them from the periods in the polynomial
+    /// coefficients file, if they vary with the command.
+    #[serde(default)]
+    pub table: Vec<[f64; 2]>,
+}
+
+/// How the gait phase behaves while standing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhaseIdleMode {
+    /// Keep advancing: the duck marches in place.
+    #[default]
+    March,
+    /// Stop at once.
+    Freeze,
+    /// Slow down to a stop over `decay_time`.
+    Decay,
+}
+
+/// The training setup's "standing" mode: the phase stops while the
+/// velocity commands are zero and restarts at a touchdown.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PhaseIdleConfig {
+    #[serde(default)]
+    pub mode: PhaseIdleMode,
+
+    /// Commands (vx, vy, yaw) all below this count as zero.
+    #[serde(default = "default_phase_idle_deadband")]
+    pub deadband: f64,
+
+    /// Seconds to slow down to a stop in `decay` mode.
+    #[serde(default = "default_phase_idle_decay_time")]
+    pub decay_time: f64,
+
+    /// Where in the period (0-1) the phase restarts when walking resumes.
+    #[serde(default)]
+    pub resume_phase: f64,
+}
+
+fn default_phase_idle_deadband() -> f64 {
+    0.01
+}
+
+fn default_phase_idle_decay_time() -> f64 {
+    0.3
+}
+
+impl Default for PhaseIdleConfig {
+    fn default() -> Self {
+        Self {
+            mode: PhaseIdleMode::default(),
+            deadband: default_phase_idle_deadband(),
+            decay_time: default_phase_idle_decay_time(),
+            resume_phase: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ExpressionFeatures {
+    #[serde(default)]
+    pub eyes: bool,
+    #[serde(default)]
+    pub projector: bool,
+    #[serde(default)]
+    pub antennas: bool,
+    #[serde(default)]
+    pub speaker: bool,
+    #[serde(default)]
+    pub microphone: bool,
+    #[serde(default)]
+    pub camera: bool,
+    /// Expressions following the gait.
+    #[serde(default)]
+    pub gait_reactions: GaitReactionsConfig,
+}
+
+/// Eyes, projector and antennas reacting to walking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GaitReactionsConfig {
+    /// Eyes brighten with walking speed.
+    #[serde(default)]
+    pub eyes: bool,
+    /// Projector pulses on each step.
+    #[serde(default)]
+    pub projector: bool,
+    /// Antennas sway from side to side with the steps.
+    #[serde(default)]
+    pub antennas: bool,
+    /// Eye brightness when standing still (0-1).
+    #[serde(default = "default_idle_brightness")]
+    pub idle_brightness: f64,
+    /// Length of the projector pulse (ms).
+    #[serde(default = "default_pulse_ms")]
+    pub pulse_ms: u64,
+    /// Antenna swing at full speed (-1..1 scale).
+    #[serde(default = "default_antenna_sway")]
+    pub antenna_sway: f64,
+}
+
+fn default_idle_brightness() -> f64 {
+    0.4
+}
+
+fn default_pulse_ms() -> u64 {
+    120
+}
+
+fn default_antenna_sway() -> f64 {
+    0.4
+}
+
+impl Default for GaitReactionsConfig {
+    fn default() -> Self {
+        Self {
+            eyes: false,
+            projector: false,
+            antennas: false,
+            idle_brightness: default_idle_brightness(),
+            pulse_ms: default_pulse_ms(),
+            antenna_sway: default_antenna_sway(),
+        }
+    }
+}
+
+/// The LEDs behind the eyes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EyeLedsKind {
+    /// One LED per eye on GPIO 24 (left) and 23 (right).
+    #[default]
+    Gpio,
+    /// A chain of WS2812 (NeoPixel) LEDs on SPI MOSI: the left ring, the
+    /// right ring, then the status pixels.
+    Neopixel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EyeLedsConfig {
+    #[serde(default)]
+    pub kind: EyeLedsKind,
+
+    #[serde(default = "default_pixels_per_eye")]
+    pub pixels_per_eye: usize,
+
+    /// Pixels after the eyes showing the duck's status; 0 for none.
+    #[serde(default)]
+    pub status_pixels: usize,
+
+    /// SPI bus and chip select (`/dev/spidev<bus>.<chip_select>`).
+    #[serde(default)]
+    pub spi_bus: u8,
+
+    #[serde(default)]
+    pub spi_chip_select: u8,
+
+    /// Eye color (RGB).
+    #[serde(default = "default_eye_color")]
+    pub color: [u8; 3],
+
+    /// Cap on pixel brightness (0-1); full-white rings draw a lot of current.
+    #[serde(default = "default_neopixel_brightness")]
+    pub max_brightness: f64,
+}
+
+fn default_pixels_per_eye() -> usize {
+    12
+}
+
+fn default_eye_color() -> [u8; 3] {
+    [255, 255, 255]
+}
+
+fn default_neopixel_brightness() -> f64 {
+    0.3
+}
+
+impl Default for EyeLedsConfig {
+    fn default() -> Self {
+        Self {
+            kind: EyeLedsKind::default(),
+            pixels_per_eye: default_pixels_per_eye(),
+            status_pixels: 0,
+            spi_bus: 0,
+            spi_chip_select: 0,
+            color: default_eye_color(),
+            max_brightness: default_neopixel_brightness(),
+        }
+    }
+}
+
+/// Where camera frames come from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraSource {
+    /// The Pi camera through `rpicam-vid`.
+    #[default]
+    Libcamera,
+    /// A V4L2 camera with MJPEG output (most USB webcams) through `ffmpeg`.
+    V4l2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraConfig {
+    #[serde(default)]
+    pub source: CameraSource,
+
+    /// V4L2 device, for the `v4l2` source.
+    #[serde(default = "default_camera_device")]
+    pub device: String,
+
+    #[serde(default = "default_camera_width")]
+    pub width: u32,
+
+    #[serde(default = "default_camera_height")]
+    pub height: u32,
+
+    /// Frames per second.
+    #[serde(default = "default_camera_framerate")]
+    pub framerate: u32,
+
+    /// JPEG quality (1-100), for the `libcamera` source.
+    #[serde(default = "default_camera_quality")]
+    pub quality: u32,
+}
+
+fn default_camera_device() -> String {
+    "/dev/video0".to_string()
+}
+
+fn default_camera_width() -> u32 {
+    640
+}
+
+fn default_camera_height() -> u32 {
+    480
+}
+
+fn default_camera_framerate() -> u32 {
+    15
+}
+
+fn default_camera_quality() -> u32 {
+    80
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            source: CameraSource::default(),
+            device: default_camera_device(),
+            width: default_camera_width(),
+            height: default_camera_height(),
+            framerate: default_camera_framerate(),
+            quality: default_camera_quality(),
+        }
+    }
+}
+
+/// Looking at people: an ONNX detector run on camera frames (see
+/// `head_tracking`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeadTrackingConfig {
+    /// SSD-style detector (e.g. UltraFace); unset disables tracking.
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Class to track in the detector's scores (1 is a face for UltraFace).
+    #[serde(default = "default_tracking_class")]
+    pub class: usize,
+
+    #[serde(default = "default_tracking_score_threshold")]
+    pub score_threshold: f64,
+
+    /// Detector input size (pixels).
+    #[serde(default = "default_tracking_input_width")]
+    pub input_width: u32,
+
+    #[serde(default = "default_tracking_input_height")]
+    pub input_height: u32,
+
+    /// Input normalization: `(pixel - mean) / std`.
+    #[serde(default = "default_tracking_mean")]
+    pub mean: f64,
+
+    #[serde(default = "default_tracking_std")]
+    pub std: f64,
+
+    /// Detections per second.
+    #[serde(default = "default_tracking_rate_hz")]
+    pub rate_hz: f64,
+
+    /// Camera field of view (degrees); the defaults are the Pi camera v2's.
+    #[serde(default = "default_tracking_horizontal_fov_deg")]
+    pub horizontal_fov_deg: f64,
+
+    #[serde(default = "default_tracking_vertical_fov_deg")]
+    pub vertical_fov_deg: f64,
+
+    /// Share of the target's angle off center corrected per detection.
+    #[serde(default = "default_tracking_gain")]
+    pub gain: f64,
+
+    /// Fastest head motion (rad/s).
+    #[serde(default = "default_tracking_max_speed")]
+    pub max_speed: f64,
+
+    /// Seconds without a detection before the head recenters.
+    #[serde(default = "default_tracking_lost_secs")]
+    pub lost_secs: f64,
+
+    /// For heads where positive pitch looks up rather than down.
+    #[serde(default)]
+    pub invert_pitch: bool,
+}
+
+fn default_tracking_class() -> usize {
+    1
+}
+
+fn default_tracking_score_threshold() -> f64 {
+    0.7
+}
+
+fn default_tracking_input_width() -> u32 {
+    320
+}
+
+fn default_tracking_input_height() -> u32 {
+    240
+}
+
+fn default_tracking_mean() -> f64 {
+    127.0
+}
+
+fn default_tracking_std() -> f64 {
+    128.0
+}
+
+fn default_tracking_rate_hz() -> f64 {
+    5.0
+}
+
+fn default_tracking_horizontal_fov_deg() -> f64 {
+    62.2
+}
+
+fn default_tracking_vertical_fov_deg() -> f64 {
+    48.8
+}
+
+fn default_tracking_gain() -> f64 {
+    0.5
+}
+
+fn default_tracking_max_speed() -> f64 {
+    1.5
+}
+
+fn default_tracking_lost_secs() -> f64 {
+    2.0
+}
+
+impl Default for HeadTrackingConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            class: default_tracking_class(),
+            score_threshold: default_tracking_score_threshold(),
+            input_width: default_tracking_input_width(),
+            input_height: default_tracking_input_height(),
+            mean: default_tracking_mean(),
+            std: default_tracking_std(),
+            rate_hz: default_tracking_rate_hz(),
+            horizontal_fov_deg: default_tracking_horizontal_fov_deg(),
+            vertical_fov_deg: default_tracking_vertical_fov_deg(),
+            gain: default_tracking_gain(),
+            max_speed: default_tracking_max_speed(),
+            lost_secs: default_tracking_lost_secs(),
+            invert_pitch: false,
+        }
+    }
+}
+
+/// What the duck does when it hears claps or its wake word.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum MicAction {
+    #[default]
+    None,
+    /// Play a sound by name, or a random one.
+    Sound {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    EyeExpression { expression: EyeExpression },
+    /// Unpause, with the same checks as the gamepad.
+    Unpause,
+}
+
+/// Listening through a microphone, used when `expression_features.microphone`
+/// is on (see `microphone`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MicrophoneConfig {
+    /// Input device name; unset uses the default.
+    #[serde(default)]
+    pub device: Option<String>,
+
+    /// Loudness (RMS, 0-1 of full scale) a clap must reach.
+    #[serde(default = "default_clap_threshold")]
+    pub clap_threshold: f64,
+
+    /// How many times louder than the background a clap must be.
+    #[serde(default = "default_clap_ratio")]
+    pub clap_ratio: f64,
+
+    /// Claps closer together than this (s) count as one double clap.
+    #[serde(default = "default_double_clap_secs")]
+    pub double_clap_secs: f64,
+
+    #[serde(default = "default_on_clap")]
+    pub on_clap: MicAction,
+
+    #[serde(default = "default_on_double_clap")]
+    pub on_double_clap: MicAction,
+
+    #[serde(default)]
+    pub wake_word: WakeWordConfig,
+
+    #[serde(default = "default_on_wake_word")]
+    pub on_wake_word: MicAction,
+}
+
+fn default_clap_threshold() -> f64 {
+    0.25
+}
+
+fn default_clap_ratio() -> f64 {
+    8.0
+}
+
+fn default_double_clap_secs() -> f64 {
+    0.6
+}
+
+fn default_on_clap() -> MicAction {
+    MicAction::EyeExpression {
+        expression: EyeExpression::Alert,
+    }
+}
+
+fn default_on_double_clap() -> MicAction {
+    MicAction::Sound { name: None }
+}
+
+fn default_on_wake_word() -> MicAction {
+    MicAction::EyeExpression {
+        expression: EyeExpression::HeartEyes,
+    }
+}
+
+impl Default for MicrophoneConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            clap_threshold: default_clap_threshold(),
+            clap_ratio: default_clap_ratio(),
+            double_clap_secs: default_double_clap_secs(),
+            on_clap: default_on_clap(),
+            on_double_clap: default_on_double_clap(),
+            wake_word: WakeWordConfig::default(),
+            on_wake_word: default_on_wake_word(),
+        }
+    }
+}
+
+/// ONNX keyword spotter taking raw audio.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WakeWordConfig {
+    /// Model taking `[1, samples]` of mono audio (-1..1) and returning
+    /// `[1, classes]` probabilities; unset disables the wake word.
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Class of the wake word.
+    #[serde(default = "default_wake_word_class")]
+    pub class: usize,
+
+    #[serde(default = "default_wake_word_threshold")]
+    pub threshold: f64,
+
+    /// Audio rate the model expects (Hz).
+    #[serde(default = "default_wake_word_sample_rate")]
+    pub sample_rate: u32,
+
+    /// Audio the model sees at once (s).
+    #[serde(default = "default_wake_word_window_secs")]
+    pub window_secs: f64,
+
+    /// Seconds between runs.
+    #[serde(default = "default_wake_word_every_secs")]
+    pub every_secs: f64,
+}
+
+fn default_wake_word_class() -> usize {
+    1
+}
+
+fn default_wake_word_threshold() -> f64 {
+    0.8
+}
+
+fn default_wake_word_sample_rate() -> u32 {
+    16_000
+}
+
+fn default_wake_word_window_secs() -> f64 {
+    1.0
+}
+
+fn default_wake_word_every_secs() -> f64 {
+    0.25
+}
+
+impl Default for WakeWordConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            class: default_wake_word_class(),
+            threshold: default_wake_word_threshold(),
+            sample_rate: default_wake_word_sample_rate(),
+            window_secs: default_wake_word_window_secs(),
+            every_secs: default_wake_word_every_secs(),
+        }
+    }
+}
+
+/// Sound played for a runtime event: one file, or a random pick from a
+/// pool of files (an empty pool picks from every sound).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SoundChoice {
+    File(String),
+    Pool(Vec<String>),
+}
+
+/// Sounds played when things happen; `null` keeps an event silent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoundEventsConfig {
+    /// Once the control loop starts.
+    #[serde(default)]
+    pub startup: Option<SoundChoice>,
+
+    #[serde(default)]
+    pub pause: Option<SoundChoice>,
+
+    #[serde(default)]
+    pub unpause: Option<SoundChoice>,
+
+    #[serde(default)]
+    pub fall_detected: Option<SoundChoice>,
+
+    #[serde(default = "default_low_battery_sound")]
+    pub low_battery: Option<SoundChoice>,
+
+    /// The gamepad's B button, and any other request for a random sound.
+    #[serde(default = "default_button_b_sound")]
+    pub button_b: Option<SoundChoice>,
+}
+
+fn default_low_battery_sound() -> Option<SoundChoice> {
+    Some(SoundChoice::File(LOW_BATTERY_SOUND.to_string()))
+}
+
+fn default_button_b_sound() -> Option<SoundChoice> {
+    Some(SoundChoice::Pool(Vec::new()))
+}
+
+impl Default for SoundEventsConfig {
+    fn default() -> Self {
+        Self {
+            startup: None,
+            pause: None,
+            unpause: None,
+            fall_detected: None,
+            low_battery: default_low_battery_sound(),
+            button_b: default_button_b_sound(),
+        }
+    }
+}
+
+/// How sounds share the speaker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoundMixingConfig {
+    /// Sounds playing at once; past it, the oldest of the lowest priority
+    /// gives way.
+    #[serde(default = "default_max_concurrent_sounds")]
+    pub max_concurrent: usize,
+
+    /// Volume factor of sounds while one of higher priority plays.
+    #[serde(default = "default_duck_volume")]
+    pub duck_volume: f64,
+}
+
+fn default_max_concurrent_sounds() -> usize {
+    4
+}
+
+fn default_duck_volume() -> f64 {
+    0.3
+}
+
+impl Default for SoundMixingConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_max_concurrent_sounds(),
+            duck_volume: default_duck_volume(),
+        }
+    }
+}
+
+/// Speech through the speaker, synthesized with a piper voice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechConfig {
+    /// Piper voice model (`.onnx`), with its `.onnx.json` next to it; unset
+    /// disables speech.
+    #[serde(default)]
+    pub voice: Option<String>,
+
+    /// Speaker of a multi-speaker voice.
+    #[serde(default)]
+    pub speaker: i64,
+
+    /// Speaking rate (1 = the voice's own).
+    #[serde(default = "default_speech_speed")]
+    pub speed: f64,
+
+    /// espeak-ng executable turning text into phonemes.
+    #[serde(default = "default_espeak")]
+    pub espeak: String,
+
+    /// Say pauses, resumes and low battery out loud.
+    #[serde(default = "default_announce")]
+    pub announce: bool,
+}
+
+fn default_speech_speed() -> f64 {
+    1.0
+}
+
+fn default_espeak() -> String {
+    "espeak-ng".to_string()
+}
+
+fn default_announce() -> bool {
+    true
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        Self {
+            voice: None,
+            speaker: 0,
+            speed: default_speech_speed(),
+            espeak: default_espeak(),
+            announce: default_announce(),
+        }
+    }
+}
+
+/// Servo PID gains: defaults for every joint plus per-joint overrides.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GainsConfig {
+    #[serde(default = "default_kp")]
+    pub kp: f64,
+
+    #[serde(default)]
+    pub kd: f64,
+
+    #[serde(default)]
+    pub ki: f64,
+
+    /// Per-joint overrides by joint name; unset gains fall back to the defaults.
+    #[serde(default = "default_joint_gains")]
+    pub joints: HashMap<String, JointGains>,
+}
+
+/// Gains overriding the defaults for one joint.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JointGains {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kp: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kd: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ki: Option<f64>,
+}
+
+/// Resolved per-joint gains, in joint order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gains {
+    pub kp: Vec<f64>,
+    pub kd: Vec<f64>,
+    pub ki: Vec<f64>,
+}
+
+fn default_kp() -> f64 {
+    30.0
+}
+
+/// Lower head KPs for compliance.
+fn default_joint_gains() -> HashMap<String, JointGains> {
+    ["neck_pitch", "head_pitch", "head_yaw", "head_roll"]
+        .into_iter()
+        .map(|name| {
+            let gains = JointGains {
+                kp: Some(8.0),
+                ..JointGains::default()
+            };
+            (name.to_string(), gains)
+        })
+        .collect()
+}
+
+impl Default for GainsConfig {
+    fn default() -> Self {
+        Self {
+            kp: default_kp(),
+            kd: 0.0,
+            ki: 0.0,
+            joints: default_joint_gains(),
+        }
+    }
+}
+
+impl GainsConfig {
+    /// Gains for `joint_names`, in order. Fails on overrides naming a joint
+    /// that doesn't exist, which is almost always a typo.
+    pub fn resolve(&self, joint_names: &[String]) -> Result<Gains> {
+        let mut unknown: Vec<&str> = self
+            .joints
+            .keys()
+            .filter(|name| !joint_names.contains(name))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            bail!("Gains configured for unknown joints: {}", unknown.join(", "));
+        }
+
+        let joint = |name: &String| self.joints.get(name).cloned().unwrap_or_default();
+        Ok(Gains {
+            kp: joint_names.iter().map(|n| joint(n).kp.unwrap_or(self.kp)).collect(),
+            kd: joint_names.iter().map(|n| joint(n).kd.unwrap_or(self.kd)).collect(),
+            ki: joint_names.iter().map(|n| joint(n).ki.unwrap_or(self.ki)).collect(),
+        })
+    }
+}
+
+/// Temperature-aware rest cycling for long demos.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DutyCycleConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Rest when the average servo temperature reaches this (°C).
+    #[serde(default = "default_rest_temperature")]
+    pub rest_temperature: f64,
+
+    /// Resume once the average has cooled below this (°C).
+    #[serde(default = "default_resume_temperature")]
+    pub resume_temperature: f64,
+
+    /// Minimum time spent resting (s).
+    #[serde(default = "default_rest_duration")]
+    pub rest_duration: f64,
+
+    /// KP applied to all joints while resting.
+    #[serde(default = "default_rest_kp")]
+    pub rest_kp: f64,
+
+    /// Offsets from the init pose that make up the resting crouch (radians).
+    #[serde(default = "default_rest_pose_offsets")]
+    pub rest_pose_offsets: HashMap<String, f64>,
+}
+
+fn default_rest_temperature() -> f64 {
+    55.0
+}
+
+fn default_resume_temperature() -> f64 {
+    45.0
+}
+
+fn default_rest_duration() -> f64 {
+    120.0
+}
+
+fn default_rest_kp() -> f64 {
+    8.0
+}
+
+fn default_rest_pose_offsets() -> HashMap<String, f64> {
+    [
+        ("left_hip_pitch", -0.2),
+        ("left_knee", 0.4),
+        ("left_ankle", -0.2),
+        ("right_hip_pitch", 0.2),
+        ("right_knee", 0.4),
+        ("right_ankle", -0.2),
+        ("neck_pitch", 0.3),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .collect()
+}
+
+impl Default for DutyCycleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rest_temperature: default_rest_temperature(),
+            resume_temperature: default_resume_temperature(),
+            rest_duration: default_rest_duration(),
+            rest_kp: default_rest_kp(),
+            rest_pose_offsets: default_rest_pose_offsets(),
+        }
+    }
+}
+
+/// Control-loop watchdog that disables torque if the loop stops ticking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    #[serde(default = "default_watchdog_enabled")]
+    pub enabled: bool,
+
+    /// Stall threshold, in control periods without a tick.
+    #[serde(default = "default_watchdog_timeout_periods")]
+    pub timeout_periods: f64,
+}
+
+fn default_watchdog_enabled() -> bool {
+    true
+}
+
+fn default_watchdog_timeout_periods() -> f64 {
+    10.0
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_watchdog_enabled(),
+            timeout_periods: default_watchdog_timeout_periods(),
+        }
+    }
+}
+
+/// Fleet backend connection. Off unless both `api_url` and `robot_id` are set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudConfig {
+    /// Backend API base URL, e.g. `https://api.example.com`.
+    #[serde(default)]
+    pub api_url: Option<String>,
+
+    #[serde(default)]
+    pub robot_id: Option<String>,
+
+    /// Display name registered with the first heartbeat.
+    #[serde(default)]
+    pub robot_name: Option<String>,
+
+    /// Seconds between heartbeats.
+    #[serde(default = "default_heartbeat_interval")]
+    pub heartbeat_interval: f64,
+
+    /// Directory queuing requests made while offline.
+    #[serde(default = "default_outbox_dir")]
+    pub outbox_dir: String,
+}
+
+fn default_heartbeat_interval() -> f64 {
+    30.0
+}
+
+fn default_outbox_dir() -> String {
+    "~/.openduckrust/outbox".to_string()
+}
+
+impl Default for CloudConfig {
+    fn default() -> Self {
+        Self {
+            api_url: None,
+            robot_id: None,
+            robot_name: None,
+            heartbeat_interval: default_heartbeat_interval(),
+            outbox_dir: default_outbox_dir(),
+        }
+    }
+}
+
+/// Gait symmetry analysis while walking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GaitConfig {
+    #[serde(default = "default_gait_enabled")]
+    pub enabled: bool,
+
+    /// Seconds of walking per analysis window.
+    #[serde(default = "default_gait_report_interval")]
+    pub report_interval: f64,
+
+    /// Symmetry index above which a left/right pair counts as uneven.
+    #[serde(default = "default_gait_warn_index")]
+    pub warn_index: f64,
+}
+
+fn default_gait_enabled() -> bool {
+    true
+}
+
+fn default_gait_report_interval() -> f64 {
+    60.0
+}
+
+fn default_gait_warn_index() -> f64 {
+    0.3
+}
+
+impl Default for GaitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_gait_enabled(),
+            report_interval: default_gait_report_interval(),
+            warn_index: default_gait_warn_index(),
+        }
+    }
+}
+
+/// Battery pack monitored through the servo input voltage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatteryConfig {
+    /// Li-ion cells in series.
+    #[serde(default = "default_battery_cells")]
+    pub cells: u32,
+
+    /// Remaining charge (%) that triggers the low-battery warning.
+    #[serde(default = "default_battery_low_percent")]
+    pub low_percent: f64,
+
+    /// Remaining charge (%) below which the duck pauses and stays paused.
+    #[serde(default = "default_battery_critical_percent")]
+    pub critical_percent: f64,
+}
+
+fn default_battery_cells() -> u32 {
+    2
+}
+
+fn default_battery_low_percent() -> f64 {
+    20.0
+}
+
+fn default_battery_critical_percent() -> f64 {
+    5.0
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            cells: default_battery_cells(),
+            low_percent: default_battery_low_percent(),
+            critical_percent: default_battery_critical_percent(),
+        }
+    }
+}
+
+/// KP ramp applied whenever torque is enabled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorqueRampConfig {
+    /// KP the ramp starts from.
+    #[serde(default = "default_ramp_start_kp")]
+    pub start_kp: f64,
+
+    /// Time to reach the configured gains (s).
+    #[serde(default = "default_ramp_duration")]
+    pub duration: f64,
+}
+
+fn default_ramp_start_kp() -> f64 {
+    2.0
+}
+
+fn default_ramp_duration() -> f64 {
+    1.0
+}
+
+impl Default for TorqueRampConfig {
+    fn default() -> Self {
+        Self {
+            start_kp: default_ramp_start_kp(),
+            duration: default_ramp_duration(),
+        }
+    }
+}
+
+/// Servo bus retries and error-rate escalation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerialConfig {
+    /// Extra attempts for servos that didn't answer a read.
+    #[serde(default = "default_serial_retries")]
+    pub retries: u32,
+
+    /// How long one read waits for status packets (ms).
+    #[serde(default = "default_status_timeout_ms")]
+    pub status_timeout_ms: f64,
+
+    /// Transactions averaged by the error-rate estimate (EWMA window).
+    #[serde(default = "default_error_window")]
+    pub error_window: u32,
+
+    /// Reopen the serial port above this error rate (0-1).
+    #[serde(default = "default_reopen_error_rate")]
+    pub reopen_error_rate: f64,
+
+    /// Soft-stop above this error rate (0-1), if reopening didn't help.
+    #[serde(default = "default_stop_error_rate")]
+    pub stop_error_rate: f64,
+
+    /// Run servo reads and writes on their own thread, overlapping them with
+    /// inference. Observations then lag one tick behind.
+    #[serde(default)]
+    pub async_io: bool,
+}
+
+fn default_serial_retries() -> u32 {
+    2
+}
+
+/// At 1 Mbaud a full 14-servo reply takes ~1.2 ms plus each servo's return
+/// delay.
+fn default_status_timeout_ms() -> f64 {
+    5.0
+}
+
+fn default_error_window() -> u32 {
+    50
+}
+
+fn default_reopen_error_rate() -> f64 {
+    0.2
+}
+
+fn default_stop_error_rate() -> f64 {
+    0.5
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self {
+            retries: default_serial_retries(),
+            status_timeout_ms: default_status_timeout_ms(),
+            error_window: default_error_window(),
+            reopen_error_rate: default_reopen_error_rate(),
+            stop_error_rate: default_stop_error_rate(),
+            async_io: false,
+        }
+    }
+}
+
+/// Gait degradation as the battery sags (see `battery_sag`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatterySagConfig {
+    /// Track and log gait stability against battery voltage.
+    #[serde(default = "default_battery_sag_enabled")]
+    pub enabled: bool,
+
+    /// Scale the action scale and velocity range down as the battery sags.
+    #[serde(default)]
+    pub adapt: bool,
+
+    /// Cell voltage below which the scale starts to shrink.
+    #[serde(default = "default_sag_start_cell_voltage")]
+    pub start_cell_voltage: f64,
+
+    /// Cell voltage at which the scale reaches `min_scale`.
+    #[serde(default = "default_sag_end_cell_voltage")]
+    pub end_cell_voltage: f64,
+
+    /// Smallest scale applied.
+    #[serde(default = "default_sag_min_scale")]
+    pub min_scale: f64,
+
+    /// Scale down further once the body rocks this many times as much as it
+    /// did on a full battery.
+    #[serde(default = "default_sag_instability_ratio")]
+    pub instability_ratio: f64,
+}
+
+fn default_battery_sag_enabled() -> bool {
+    true
+}
+
+fn default_sag_start_cell_voltage() -> f64 {
+    3.7
+}
+
+fn default_sag_end_cell_voltage() -> f64 {
+    3.4
+}
+
+fn default_sag_min_scale() -> f64 {
+    0.6
+}
+
+fn default_sag_instability_ratio() -> f64 {
+    1.5
+}
+
+impl Default for BatterySagConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_battery_sag_enabled(),
+            adapt: false,
+            start_cell_voltage: default_sag_start_cell_voltage(),
+            end_cell_voltage: default_sag_end_cell_voltage(),
+            min_scale: default_sag_min_scale(),
+            instability_ratio: default_sag_instability_ratio(),
+        }
+    }
+}
+
+/// Gait throttling by the hottest servo's temperature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThermalConfig {
+    #[serde(default = "default_thermal_enabled")]
+    pub enabled: bool,
+
+    /// Start throttling the gait above this temperature (°C).
+    #[serde(default = "default_warm_temperature")]
+    pub warm_temperature: f64,
+
+    /// Pause at this temperature (°C).
+    #[serde(default = "default_hot_temperature")]
+    pub hot_temperature: f64,
+
+    /// Resume once every servo has cooled below this (°C).
+    #[serde(default = "default_thermal_resume_temperature")]
+    pub resume_temperature: f64,
+
+    /// Velocity range and phase frequency scale just below the hot threshold.
+    #[serde(default = "default_thermal_min_scale")]
+    pub min_scale: f64,
+}
+
+fn default_thermal_enabled() -> bool {
+    true
+}
+
+fn default_warm_temperature() -> f64 {
+    55.0
+}
+
+fn default_hot_temperature() -> f64 {
+    65.0
+}
+
+fn default_thermal_resume_temperature() -> f64 {
+    55.0
+}
+
+fn default_thermal_min_scale() -> f64 {
+    0.5
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_thermal_enabled(),
+            warm_temperature: default_warm_temperature(),
+            hot_temperature: default_hot_temperature(),
+            resume_temperature: default_thermal_resume_temperature(),
+            min_scale: default_thermal_min_scale(),
+        }
+    }
+}
+
+/// Reactions to the error bits in servo status packets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServoErrorsConfig {
+    #[serde(default = "default_reduce_gains")]
+    pub overload: ServoErrorReaction,
+
+    #[serde(default = "default_torque_off")]
+    pub overheat: ServoErrorReaction,
+
+    #[serde(default = "default_log")]
+    pub input_voltage: ServoErrorReaction,
+
+    #[serde(default = "default_log")]
+    pub checksum: ServoErrorReaction,
+
+    /// Angle limit, overcurrent and instruction errors.
+    #[serde(default = "default_log")]
+    pub other: ServoErrorReaction,
+
+    /// KP factor applied by `reduce_gains`.
+    #[serde(default = "default_gain_reduction")]
+    pub gain_reduction: f64,
+}
+
+fn default_reduce_gains() -> ServoErrorReaction {
+    ServoErrorReaction::ReduceGains
+}
+
+fn default_torque_off() -> ServoErrorReaction {
+    ServoErrorReaction::TorqueOff
+}
+
+fn default_log() -> ServoErrorReaction {
+    ServoErrorReaction::Log
+}
+
+fn default_gain_reduction() -> f64 {
+    0.5
+}
+
+impl Default for ServoErrorsConfig {
+    fn default() -> Self {
+        Self {
+            overload: default_reduce_gains(),
+            overheat: default_torque_off(),
+            input_voltage: default_log(),
+            checksum: default_log(),
+            other: default_log(),
+            gain_reduction: default_gain_reduction(),
+        }
+    }
+}
+
+/// Recorded emotes: where they are stored and how captures are timed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmotesConfig {
+    /// Directory holding one `<name>.json` per emote.
+    #[serde(default = "default_emotes_dir")]
+    pub dir: String,
+
+    /// Seconds to move into each captured keyframe from the previous one.
+    #[serde(default = "default_keyframe_duration")]
+    pub keyframe_duration: f64,
+
+    /// Seconds to return to the pose the emote started from.
+    #[serde(default = "default_emote_return_duration")]
+    pub return_duration: f64,
+}
+
+fn default_emotes_dir() -> String {
+    "~/.openduckrust/emotes".to_string()
+}
+
+fn default_keyframe_duration() -> f64 {
+    0.8
+}
+
+fn default_emote_return_duration() -> f64 {
+    1.0
+}
+
+impl Default for EmotesConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_emotes_dir(),
+            keyframe_duration: default_keyframe_duration(),
+            return_duration: default_emote_return_duration(),
+        }
+    }
+}
+
+/// Collision and jam detection from the servos' present load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollisionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Read the loads every this many control ticks.
+    #[serde(default = "default_collision_check_every")]
+    pub check_every: u32,
 
+    /// Load limit (fraction of max torque) for joints without their own.
+    #[serde(default = "default_load_limit")]
+    pub load_limit: f64,
+
+    /// Per-joint load limits (fraction of max torque).
     #[serde(default)]
-    pub imu_upside_down: bool,
+    pub joint_load_limits: HashMap<String, f64>,
+
+    /// A load this far above the joint's recent average is a collision.
+    #[serde(default = "default_load_spike")]
+    pub load_spike: f64,
+
+    /// Share of the gap between target and present position given up when
+    /// a joint collides (0 keeps the target, 1 holds the present position).
+    #[serde(default = "default_collision_back_off")]
+    pub back_off: f64,
+
+    /// Seconds the backed-off target is held, then eased back over as long.
+    #[serde(default = "default_collision_hold")]
+    pub hold_duration: f64,
+}
+
+fn default_collision_check_every() -> u32 {
+    2
+}
+
+fn default_load_limit() -> f64 {
+    0.8
+}
 
+fn default_load_spike() -> f64 {
+    0.4
+}
+
+fn default_collision_back_off() -> f64 {
+    0.7
+}
+
+fn default_collision_hold() -> f64 {
+    0.5
+}
+
+impl Default for CollisionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            check_every: default_collision_check_every(),
+            load_limit: default_load_limit(),
+            joint_load_limits: HashMap::new(),
+            load_spike: default_load_spike(),
+            back_off: default_collision_back_off(),
+            hold_duration: default_collision_hold(),
+        }
+    }
+}
+
+/// Safety envelope around the policy's motor targets: how fast a target may
+/// move and how far it may stray from the standing pose.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionLimitsConfig {
+    #[serde(default = "default_action_limits_enabled")]
+    pub enabled: bool,
+
+    /// Fastest change of a target (rad/s), for joints without their own.
+    /// Each tick's step is this times the control period.
+    #[serde(default = "default_action_max_speed")]
+    pub max_speed: f64,
+
+    /// Per-joint limits on the speed of a target (rad/s).
     #[serde(default)]
-    pub phase_frequency_factor_offset: f64,
+    pub joint_max_speed: HashMap<String, f64>,
+
+    /// Largest distance of a target from the init pose (rad), for joints
+    /// without their own.
+    #[serde(default = "default_action_max_deviation")]
+    pub max_deviation: f64,
 
+    /// Per-joint limits on the distance from the init pose (rad).
     #[serde(default)]
-    pub expression_features: ExpressionFeatures,
+    pub joint_max_deviation: HashMap<String, f64>,
+}
 
-    #[serde(default = "default_joints_offsets", rename = "joints_offsets")]
-    pub joints_offset: HashMap<String, f64>,
+fn default_action_limits_enabled() -> bool {
+    true
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
-pub struct ExpressionFeatures {
-    #[serde(default)]
-    pub eyes: bool,
+fn default_action_max_speed() -> f64 {
+    // 0.15 rad per tick at 50 Hz, faster than the servos turn unloaded
+    7.5
+}
+
+fn default_action_max_deviation() -> f64 {
+    1.2
+}
+
+impl Default for ActionLimitsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_action_limits_enabled(),
+            max_speed: default_action_max_speed(),
+            joint_max_speed: HashMap::new(),
+            max_deviation: default_action_max_deviation(),
+            joint_max_deviation: HashMap::new(),
+        }
+    }
+}
+
+/// Graph optimizations ONNX Runtime applies when loading a model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptimizationLevel {
+    Disable,
+    /// Constant folding and redundant node removal.
+    Basic,
+    /// Basic plus operator fusions.
+    Extended,
+    /// Everything, including layout changes for the CPU.
+    #[default]
+    All,
+}
+
+/// Hardware ONNX Runtime runs the policies on.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionProvider {
+    /// ONNX Runtime's own CPU kernels.
+    #[default]
+    Cpu,
+    /// XNNPACK's ARM NEON kernels, usually faster on a Pi.
+    Xnnpack,
+    /// NVIDIA GPU, on Jetson-class boards.
+    Cuda,
+}
+
+/// ONNX Runtime session settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrtConfig {
+    /// Threads running each operator; 0 lets ONNX Runtime start one per
+    /// core. A small policy runs fastest on one, and leaves the other cores
+    /// to the rest of the runtime.
+    #[serde(default = "default_ort_intra_threads")]
+    pub intra_threads: usize,
+
     #[serde(default)]
-    pub projector: bool,
+    pub optimization_level: OptimizationLevel,
+
     #[serde(default)]
-    pub antennas: bool,
+    pub execution_provider: ExecutionProvider,
+}
+
+fn default_ort_intra_threads() -> usize {
+    1
+}
+
+impl Default for OrtConfig {
+    fn default() -> Self {
+        Self {
+            intra_threads: default_ort_intra_threads(),
+            optimization_level: OptimizationLevel::default(),
+            execution_provider: ExecutionProvider::default(),
+        }
+    }
+}
+
+/// Real-time settings for the control thread, each applied if the system
+/// allows it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RealtimeConfig {
+    /// SCHED_FIFO priority (1-99). Unset keeps the normal scheduler.
     #[serde(default)]
-    pub speaker: bool,
+    pub fifo_priority: Option<i32>,
+
+    /// CPU core to pin the control thread to, ideally one kept free of
+    /// other work with the `isolcpus` kernel parameter.
     #[serde(default)]
-    pub microphone: bool,
+    pub cpu: Option<usize>,
+
+    /// Lock the runtime's memory (mlockall), so page faults never stall the
+    /// loop.
     #[serde(default)]
-    pub camera: bool,
+    pub lock_memory: bool,
+}
+
+/// Servo health monitoring thresholds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServoHealthConfig {
+    /// Seconds between health polls.
+    #[serde(default = "default_health_poll_interval")]
+    pub poll_interval: f64,
+
+    /// Warn above this servo temperature (°C).
+    #[serde(default = "default_warn_temperature")]
+    pub warn_temperature: f64,
+
+    /// Soft-stop above this servo temperature (°C).
+    #[serde(default = "default_max_temperature")]
+    pub max_temperature: f64,
+
+    /// Warn below this input voltage (V).
+    #[serde(default = "default_warn_voltage")]
+    pub warn_voltage: f64,
+
+    /// Soft-stop below this input voltage (V).
+    #[serde(default = "default_min_voltage")]
+    pub min_voltage: f64,
+
+    /// Soft-stop above this input voltage (V).
+    #[serde(default = "default_max_voltage")]
+    pub max_voltage: f64,
+
+    /// Warn when |load| exceeds this fraction of maximum torque.
+    #[serde(default = "default_warn_load")]
+    pub warn_load: f64,
+}
+
+fn default_health_poll_interval() -> f64 {
+    1.0
+}
+
+fn default_warn_temperature() -> f64 {
+    60.0
+}
+
+fn default_max_temperature() -> f64 {
+    70.0
+}
+
+fn default_warn_voltage() -> f64 {
+    6.8
+}
+
+fn default_min_voltage() -> f64 {
+    6.2
+}
+
+fn default_max_voltage() -> f64 {
+    8.8
+}
+
+fn default_warn_load() -> f64 {
+    0.9
+}
+
+impl Default for ServoHealthConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: default_health_poll_interval(),
+            warn_temperature: default_warn_temperature(),
+            max_temperature: default_max_temperature(),
+            warn_voltage: default_warn_voltage(),
+            min_voltage: default_min_voltage(),
+            max_voltage: default_max_voltage(),
+            warn_load: default_warn_load(),
+        }
+    }
+}
+
+fn default_imu_calibration_path() -> String {
+    "~/.openduckrust/bno055_calibration.bin".to_string()
+}
+
+fn default_imu_stale_ms() -> f64 {
+    100.0
+}
+
+fn default_volume() -> f64 {
+    1.0
 }
 
 fn default_joints_offsets() -> HashMap<String, f64> {
@@ -63,6 +2014,13 @@ fn default_joints_offsets() -> HashMap<String, f64> {
 }
 
 impl DuckConfig {
+    /// Limits by joint name: the robot model's, with `joint_limits` on top.
+    pub fn resolved_joint_limits(&self) -> HashMap<String, [f64; 2]> {
+        let mut limits = self.robot_model.joint_limits();
+        limits.extend(self.joint_limits.iter().map(|(k, v)| (k.clone(), *v)));
+        limits
+    }
+
     /// Load configuration from a JSON file. Falls back to defaults if the file is missing.
     pub fn load(path: &Path) -> Result<Self> {
         if !path.exists() {
@@ -76,26 +2034,409 @@ impl DuckConfig {
         let contents =
             std::fs::read_to_string(path).context("Failed to read duck config file")?;
 
-        let config: DuckConfig =
-            serde_json::from_str(&contents).context("Failed to parse duck config JSON")?;
+        let config: DuckConfig = serde_json::from_value(Self::parse_file(path, &contents)?)
+            .context("Failed to parse duck config JSON")?;
 
         Ok(config)
     }
 
+    /// Parse the text of the config file at `path`, which may hold `//`
+    /// comments. Problems the config still loads with (unknown keys, values
+    /// out of range) are logged; one that doesn't load fails with them all.
+    fn parse_file(path: &Path, contents: &str) -> Result<serde_json::Value> {
+        let doc = serde_json::from_str::<serde_json::Value>(&config_check::strip_comments(contents))
+            .ok()
+            .filter(|doc| Self::deserialize(doc).is_ok());
+        let issues = config_check::check(contents);
+        let Some(doc) = doc else {
+            let report: Vec<String> = issues.iter().map(ToString::to_string).collect();
+            bail!("Invalid duck config {}:\n  {}", path.display(), report.join("\n  "));
+        };
+        for issue in &issues {
+            tracing::warn!("{}: {}", path.display(), issue);
+        }
+        Ok(doc)
+    }
+
+    /// Load configuration with persisted runtime overrides layered on top.
+    pub fn load_with_overrides(path: &Path, overrides: &Overrides) -> Result<Self> {
+        if overrides.is_empty() {
+            return Self::load(path);
+        }
+
+        let mut doc = if path.exists() {
+            let contents =
+                std::fs::read_to_string(path).context("Failed to read duck config file")?;
+            Self::parse_file(path, &contents)?
+        } else {
+            serde_json::to_value(Self::default())?
+        };
+        overrides.apply(&mut doc);
+
+        serde_json::from_value(doc).with_context(|| {
+            format!(
+                "Failed to apply overrides from {}",
+                overrides.path().display()
+            )
+        })
+    }
+
+    /// Replace `joints_offsets` in the config file at `path`, keeping every
+    /// other key as written (comments are lost). The file is rewritten
+    /// atomically.
+    pub fn write_joints_offsets(path: &Path, offsets: &HashMap<String, f64>) -> Result<()> {
+        let mut doc = if path.exists() {
+            let contents =
+                std::fs::read_to_string(path).context("Failed to read duck config file")?;
+            serde_json::from_str(&config_check::strip_comments(&contents))
+                .context("Failed to parse duck config JSON")?
+        } else {
+            serde_json::Value::Object(serde_json::Map::new())
+        };
+        let Some(map) = doc.as_object_mut() else {
+            bail!("Duck config {} must be a JSON object", path.display());
+        };
+        // Sorted by joint name so the file diffs cleanly
+        let offsets: std::collections::BTreeMap<_, _> = offsets.iter().collect();
+        map.insert("joints_offsets".to_string(), serde_json::to_value(offsets)?);
+
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&doc)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(())
+    }
+
     /// Get joint offset by name, defaulting to 0.0.
     pub fn joint_offset(&self, name: &str) -> f64 {
         self.joints_offset.get(name).copied().unwrap_or(0.0)
     }
 }
 
+/// Flight recorder dumped on falls and servo faults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlackboxConfig {
+    #[serde(default = "default_blackbox_enabled")]
+    pub enabled: bool,
+
+    /// Seconds of control ticks kept in memory.
+    #[serde(default = "default_blackbox_seconds")]
+    pub seconds: f64,
+
+    /// Where dumps are written (`~` expands to the home directory).
+    #[serde(default = "default_blackbox_dir")]
+    pub dir: String,
+
+    /// Dumps kept; older ones are deleted.
+    #[serde(default = "default_blackbox_keep")]
+    pub keep: usize,
+}
+
+fn default_blackbox_enabled() -> bool {
+    true
+}
+
+fn default_blackbox_seconds() -> f64 {
+    10.0
+}
+
+fn default_blackbox_dir() -> String {
+    "~/.openduckrust/blackbox".to_string()
+}
+
+fn default_blackbox_keep() -> usize {
+    20
+}
+
+impl Default for BlackboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_blackbox_enabled(),
+            seconds: default_blackbox_seconds(),
+            dir: default_blackbox_dir(),
+            keep: default_blackbox_keep(),
+        }
+    }
+}
+
+/// Telemetry clips saved around a moment on request (gamepad LB + RB).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MomentsConfig {
+    #[serde(default = "default_moments_enabled")]
+    pub enabled: bool,
+
+    /// Seconds kept from before the request.
+    #[serde(default = "default_moment_secs")]
+    pub before_secs: f64,
+
+    /// Seconds recorded after it.
+    #[serde(default = "default_moment_secs")]
+    pub after_secs: f64,
+
+    /// Where clips are written (`~` expands to the home directory).
+    #[serde(default = "default_moments_dir")]
+    pub dir: String,
+
+    /// Clips kept; older ones are deleted.
+    #[serde(default = "default_moments_keep")]
+    pub keep: usize,
+}
+
+fn default_moments_enabled() -> bool {
+    true
+}
+
+fn default_moment_secs() -> f64 {
+    10.0
+}
+
+fn default_moments_dir() -> String {
+    "~/.openduckrust/moments".to_string()
+}
+
+fn default_moments_keep() -> usize {
+    50
+}
+
+impl Default for MomentsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_moments_enabled(),
+            before_secs: default_moment_secs(),
+            after_secs: default_moment_secs(),
+            dir: default_moments_dir(),
+            keep: default_moments_keep(),
+        }
+    }
+}
+
+/// Walk-in-place and step-test calibration routines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutinesConfig {
+    #[serde(default = "default_walk_in_place_secs")]
+    pub walk_in_place_secs: f64,
+
+    /// Weight shifts in the step test, alternating left and right.
+    #[serde(default = "default_step_test_shifts")]
+    pub step_test_shifts: u32,
+
+    /// Seconds per weight shift.
+    #[serde(default = "default_shift_secs")]
+    pub shift_secs: f64,
+
+    /// Peak sideways command (m/s) of a shift.
+    #[serde(default = "default_shift_speed")]
+    pub shift_speed: f64,
+}
+
+fn default_walk_in_place_secs() -> f64 {
+    20.0
+}
+
+fn default_step_test_shifts() -> u32 {
+    6
+}
+
+fn default_shift_secs() -> f64 {
+    2.0
+}
+
+fn default_shift_speed() -> f64 {
+    0.05
+}
+
+impl Default for RoutinesConfig {
+    fn default() -> Self {
+        Self {
+            walk_in_place_secs: default_walk_in_place_secs(),
+            step_test_shifts: default_step_test_shifts(),
+            shift_secs: default_shift_secs(),
+            shift_speed: default_shift_speed(),
+        }
+    }
+}
+
+/// Artificial delays on the policy's sensor inputs, for sim-to-real studies.
+/// Zero (the default) feeds the policy fresh readings.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SensorLatencyConfig {
+    /// Control ticks the IMU reading is held back.
+    #[serde(default)]
+    pub imu_ticks: usize,
+
+    /// Control ticks the joint positions and velocities are held back.
+    #[serde(default)]
+    pub joint_ticks: usize,
+}
+
+/// Gyro bias estimated while paused and subtracted from every reading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GyroBiasConfig {
+    #[serde(default = "default_gyro_bias_enabled")]
+    pub enabled: bool,
+
+    /// Time constant (s) of the running estimate once it has settled.
+    #[serde(default = "default_gyro_bias_time_constant")]
+    pub time_constant: f64,
+
+    /// Largest rate (rad/s) beyond the current estimate counted as still.
+    #[serde(default = "default_gyro_bias_still_rate")]
+    pub still_rate: f64,
+
+    /// Largest gap (m/s^2) between the acceleration and 1 g counted as still.
+    #[serde(default = "default_gyro_bias_still_accel")]
+    pub still_accel: f64,
+}
+
+fn default_gyro_bias_enabled() -> bool {
+    true
+}
+
+fn default_gyro_bias_time_constant() -> f64 {
+    30.0
+}
+
+fn default_gyro_bias_still_rate() -> f64 {
+    0.05
+}
+
+fn default_gyro_bias_still_accel() -> f64 {
+    0.5
+}
+
+impl Default for GyroBiasConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_gyro_bias_enabled(),
+            time_constant: default_gyro_bias_time_constant(),
+            still_rate: default_gyro_bias_still_rate(),
+            still_accel: default_gyro_bias_still_accel(),
+        }
+    }
+}
+
+/// Manual joint jogging over the remote, with the robot on a stand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JogConfig {
+    /// Largest move (rad) a single jog command makes.
+    #[serde(default = "default_jog_max_step")]
+    pub max_step: f64,
+
+    /// Fastest a jogged joint travels (rad/s).
+    #[serde(default = "default_jog_max_speed")]
+    pub max_speed: f64,
+}
+
+fn default_jog_max_step() -> f64 {
+    0.1
+}
+
+fn default_jog_max_speed() -> f64 {
+    0.5
+}
+
+impl Default for JogConfig {
+    fn default() -> Self {
+        Self {
+            max_step: default_jog_max_step(),
+            max_speed: default_jog_max_speed(),
+        }
+    }
+}
+
 impl Default for DuckConfig {
     fn default() -> Self {
         Self {
             start_paused: false,
             imu_upside_down: false,
+            imu_type: ImuType::default(),
+            imu_bus: ImuBusConfig::default(),
+            imu_calibration_path: default_imu_calibration_path(),
+            imu_stale_ms: default_imu_stale_ms(),
+            imu_mounting: ImuMountingConfig::default(),
+            feet_contacts: FeetContactsConfig::default(),
             phase_frequency_factor_offset: 0.0,
+            phase_frequency: PhaseFrequencyConfig::default(),
+            phase_idle: PhaseIdleConfig::default(),
             expression_features: ExpressionFeatures::default(),
+            eye_leds: EyeLedsConfig::default(),
+            camera: CameraConfig::default(),
+            head_tracking: HeadTrackingConfig::default(),
+            microphone: MicrophoneConfig::default(),
+            speech: SpeechConfig::default(),
+            sound_events: SoundEventsConfig::default(),
+            sound_mixing: SoundMixingConfig::default(),
+            volume: default_volume(),
             joints_offset: default_joints_offsets(),
+            robot_model: RobotModel::default(),
+            joint_limits: HashMap::new(),
+            rig_joints: Vec::new(),
+            servo_type: ServoType::default(),
+            gains: GainsConfig::default(),
+            torque_ramp: TorqueRampConfig::default(),
+            serial: SerialConfig::default(),
+            duty_cycle: DutyCycleConfig::default(),
+            servo_health: ServoHealthConfig::default(),
+            servo_errors: ServoErrorsConfig::default(),
+            watchdog: WatchdogConfig::default(),
+            cloud: CloudConfig::default(),
+            gait: GaitConfig::default(),
+            battery: BatteryConfig::default(),
+            battery_sag: BatterySagConfig::default(),
+            thermal: ThermalConfig::default(),
+            emotes: EmotesConfig::default(),
+            collision: CollisionConfig::default(),
+            action_limits: ActionLimitsConfig::default(),
+            ort: OrtConfig::default(),
+            realtime: RealtimeConfig::default(),
+            blackbox: BlackboxConfig::default(),
+            moments: MomentsConfig::default(),
+            routines: RoutinesConfig::default(),
+            sensor_latency: SensorLatencyConfig::default(),
+            gyro_bias: GyroBiasConfig::default(),
+            jog: JogConfig::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gains_resolve_per_joint() {
+        let names = RobotModel::default().joint_names();
+        let mut config: GainsConfig =
+            serde_json::from_str(r#"{"kd": 2, "joints": {"left_knee": {"kp": 40, "ki": 1}}}"#)
+                .unwrap();
+        let gains = config.resolve(&names).unwrap();
+        assert_eq!((gains.kp[3], gains.kd[3], gains.ki[3]), (40.0, 2.0, 1.0));
+        assert_eq!((gains.kp[7], gains.kd[7], gains.ki[7]), (30.0, 2.0, 0.0));
+
+        let defaults = GainsConfig::default().resolve(&names).unwrap();
+        assert_eq!(defaults.kp[3], 30.0);
+        assert_eq!(defaults.kp[5..9], [8.0; 4]);
+
+        config.joints.insert("left_kne".to_string(), JointGains::default());
+        assert!(config.resolve(&names).is_err());
+    }
+
+    #[test]
+    fn test_write_joints_offsets_keeps_other_keys() {
+        let dir = std::env::temp_dir().join(format!("odr-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("duck_config.json");
+        std::fs::write(&path, r#"{"start_paused": true, "joints_offsets": {"left_knee": 0.3}}"#)
+            .unwrap();
+
+        let offsets = [("left_knee".to_string(), -0.05), ("head_yaw".to_string(), 0.1)].into();
+        DuckConfig::write_joints_offsets(&path, &offsets).unwrap();
+
+        let config = DuckConfig::load(&path).unwrap();
+        assert!(config.start_paused);
+        assert_eq!(config.joint_offset("left_knee"), -0.05);
+        assert_eq!(config.joint_offset("head_yaw"), 0.1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
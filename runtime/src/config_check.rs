@@ -0,0 +1,447 @@
+//! Strict checks of a duck config file, reported with line numbers.
+//!
+//! Loading a config with serde alone is forgiving in the wrong places: a
+//! misspelled key is ignored and its setting silently falls back to the
+//! default, and a wrong type stops at the first error. `check` reports
+//! every problem it can find in one go:
+//!
+//! - JSON syntax errors;
+//! - type errors, one per top-level section;
+//! - unknown keys, with the closest known key as a suggestion;
+//! - joint names in `joints_offsets`, `joint_limits`, `gains.joints`,
+//!   `action_limits` and `rig_joints` that aren't joints of the robot model;
+//! - gains outside what the servos accept, offsets too large to be a horn
+//!   misalignment and inverted joint limits.
+//!
+//! Config files may contain `//` comments, which `template` uses to explain
+//! each section.
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::config::{DuckConfig, ServoType};
+
+/// Largest joint offset (rad) accepted as a calibration: beyond 45° the
+/// horn is mounted on the wrong spline or the value is in degrees.
+const MAX_OFFSET: f64 = std::f64::consts::FRAC_PI_4;
+
+/// Top-level sections with a one-line description, in file order.
+const SECTIONS: &[(&str, &str)] = &[
+    ("start_paused", "Start in the paused state"),
+    ("imu_upside_down", "The IMU board is mounted upside down"),
+    ("imu_type", "IMU chip: bno055, bno085 or mpu6050"),
+    ("imu_bus", "How the IMU is wired: i2c, uart or spi, and its port"),
+    ("imu_calibration_path", "BNO055 calibration saved by --imu-calibrate"),
+    ("imu_stale_ms", "IMU samples older than this (ms) soft-stop the duck"),
+    ("imu_mounting", "Angles (degrees) the IMU reads when the body is level"),
+    ("feet_contacts", "Foot contact sensors: GPIO switches or FSRs on an ADC"),
+    ("phase_frequency_factor_offset", "Gait frequency offset (the D-pad adjusts it)"),
+    ("phase_frequency", "Gait frequency factor by commanded forward velocity"),
+    ("phase_idle", "Gait phase while standing: march, freeze or decay"),
+    ("expression_features", "Expression hardware that is fitted"),
+    ("eye_leds", "How the eyes are built: single LEDs or WS2812 rings"),
+    ("camera", "Capture settings, used when expression_features.camera is on"),
+    ("head_tracking", "Looking at people: an ONNX detector run on camera frames"),
+    ("microphone", "Clap and wake word detection"),
+    ("speech", "Text-to-speech with a piper voice"),
+    ("sound_events", "Sounds played on runtime events"),
+    ("sound_mixing", "Concurrency limit and ducking of overlapping sounds"),
+    ("volume", "Speaker volume (0.0-1.0)"),
+    ("joints_offsets", "Per-joint calibration offsets (rad), written by `calibrate`"),
+    ("robot_model", "Joints of the hardware variant: mini_v2, mini_v2_16dof or a full model"),
+    ("joint_limits", "Per-joint [min, max] overrides of the model's limits (rad)"),
+    ("rig_joints", "Joints present on a partial test rig; empty means the full robot"),
+    ("servo_type", "Servo family on the bus: feetech or dynamixel"),
+    ("gains", "Servo PID gains: defaults for every joint plus per-joint overrides"),
+    ("torque_ramp", "KP ramp applied whenever torque is enabled"),
+    ("serial", "Servo bus retries and error-rate escalation"),
+    ("duty_cycle", "Temperature-aware rest cycling for long demos"),
+    ("servo_health", "Servo health monitoring thresholds"),
+    ("servo_errors", "Reactions to the error bits in servo status packets"),
+    ("watchdog", "Disables torque if the control loop stops ticking"),
+    ("cloud", "Fleet backend connection, off unless api_url and robot_id are set"),
+    ("gait", "Gait symmetry analysis while walking"),
+    ("battery", "Battery pack monitored through the servo input voltage"),
+    ("battery_sag", "Gait degradation as the battery sags"),
+    ("thermal", "Gait throttling by the hottest servo's temperature"),
+    ("emotes", "Recorded emotes: where they are stored and how captures are timed"),
+    ("collision", "Collision and jam detection from the servos' present load"),
+    ("action_limits", "Speed and deviation limits on the policy's motor targets"),
+    ("ort", "ONNX Runtime threads, graph optimizations and execution provider"),
+    ("realtime", "SCHED_FIFO priority, CPU pinning and memory locking for the control loop"),
+    ("blackbox", "Flight recorder dumped on falls and servo faults"),
+    ("moments", "Telemetry clips saved around a moment on request"),
+    ("routines", "Walk-in-place and step-test calibration routines"),
+    ("sensor_latency", "Artificial delays on the policy's sensor inputs"),
+    ("gyro_bias", "Gyro bias estimated while paused"),
+    ("jog", "Manual joint jogging over the remote, with the robot on a stand"),
+];
+
+/// One problem found in a config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    /// 1-based line, when the problem can be placed.
+    pub line: Option<usize>,
+    /// Dotted key path (`gains.joints.left_knee.kp`); empty for the file.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Every problem in the config `text`, in file order.
+pub fn check(text: &str) -> Vec<Issue> {
+    let text = strip_comments(text);
+    let doc: Value = match serde_json::from_str(&text) {
+        Ok(doc) => doc,
+        Err(e) => {
+            let message = e.to_string();
+            let message = message.split(" at line ").next().unwrap_or_default();
+            return vec![issue(Some(e.line()), "", message)];
+        }
+    };
+    let Value::Object(sections) = &doc else {
+        return vec![issue(Some(1), "", "the config must be a JSON object")];
+    };
+    let lines = key_lines(&text);
+    let line = |path: &str| lines.get(path).copied();
+
+    // Each section on its own, so one bad section doesn't hide the others
+    let mut issues: Vec<Issue> = sections
+        .iter()
+        .filter_map(|(key, value)| {
+            let alone = Value::Object(Map::from_iter([(key.clone(), value.clone())]));
+            let e = serde_json::from_value::<DuckConfig>(alone).err()?;
+            Some(issue(line(key), key, &e.to_string()))
+        })
+        .collect();
+    if issues.is_empty() {
+        match serde_json::from_value::<DuckConfig>(doc.clone()) {
+            Ok(config) => {
+                let known = serde_json::to_value(&config).unwrap_or(Value::Null);
+                unknown_keys("", &doc, &known, &line, &mut issues);
+                check_values(&config, &line, &mut issues);
+            }
+            Err(e) => issues.push(issue(Some(1), "", &e.to_string())),
+        }
+    }
+    issues.sort_by_key(|issue| issue.line.unwrap_or(0));
+    issues
+}
+
+fn issue(line: Option<usize>, path: &str, message: &str) -> Issue {
+    Issue {
+        line,
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Report keys of `doc` the config doesn't have. `known` is the config as
+/// loaded, serialized back: it holds every key the loader read.
+fn unknown_keys(
+    path: &str,
+    doc: &Value,
+    known: &Value,
+    line: &impl Fn(&str) -> Option<usize>,
+    issues: &mut Vec<Issue>,
+) {
+    let (Value::Object(doc), Value::Object(known)) = (doc, known) else {
+        return;
+    };
+    for (key, value) in doc {
+        let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+        match known.get(key) {
+            Some(known) => unknown_keys(&child, value, known, line, issues),
+            None => {
+                let message = match closest(key, known.keys()) {
+                    Some(near) => format!("unknown key (did you mean \"{}\"?)", near),
+                    None => "unknown key".to_string(),
+                };
+                issues.push(issue(line(&child), &child, &message));
+            }
+        }
+    }
+}
+
+/// Joint names and value ranges.
+fn check_values(
+    config: &DuckConfig,
+    line: &impl Fn(&str) -> Option<usize>,
+    issues: &mut Vec<Issue>,
+) {
+    let joints = config.robot_model.joint_names();
+    let mut unknown_joint = |path: String, name: &str| {
+        if !joints.iter().any(|joint| joint == name) {
+            let message = match closest(name, joints.iter()) {
+                Some(near) => format!("not a joint of the model (did you mean \"{}\"?)", near),
+                None => "not a joint of the model".to_string(),
+            };
+            issues.push(issue(line(&path), &path, &message));
+        }
+    };
+    for name in config.joints_offset.keys() {
+        unknown_joint(format!("joints_offsets.{}", name), name);
+    }
+    for name in config.joint_limits.keys() {
+        unknown_joint(format!("joint_limits.{}", name), name);
+    }
+    for name in config.gains.joints.keys() {
+        unknown_joint(format!("gains.joints.{}", name), name);
+    }
+    for name in config.action_limits.joint_max_speed.keys() {
+        unknown_joint(format!("action_limits.joint_max_speed.{}", name), name);
+    }
+    for name in config.action_limits.joint_max_deviation.keys() {
+        unknown_joint(format!("action_limits.joint_max_deviation.{}", name), name);
+    }
+    for (i, name) in config.rig_joints.iter().enumerate() {
+        unknown_joint(format!("rig_joints[{}]", i), name);
+    }
+
+    // The gain registers: one byte on Feetech, 0-16383 on Dynamixel
+    let max_gain = match config.servo_type {
+        ServoType::Feetech => 254.0,
+        ServoType::Dynamixel => 16383.0,
+    };
+    let mut gains = vec![
+        ("gains.kp".to_string(), config.gains.kp),
+        ("gains.kd".to_string(), config.gains.kd),
+        ("gains.ki".to_string(), config.gains.ki),
+    ];
+    for (name, joint) in &config.gains.joints {
+        for (gain, value) in [("kp", joint.kp), ("kd", joint.kd), ("ki", joint.ki)] {
+            if let Some(value) = value {
+                gains.push((format!("gains.joints.{}.{}", name, gain), value));
+            }
+        }
+    }
+    for (path, value) in gains {
+        if !(0.0..=max_gain).contains(&value) {
+            let message = format!(
+                "gain {} is outside 0-{} for {:?} servos",
+                value, max_gain, config.servo_type
+            );
+            issues.push(issue(line(&path), &path, &message));
+        }
+    }
+
+    for (name, &offset) in &config.joints_offset {
+        if offset.abs() > MAX_OFFSET {
+            let path = format!("joints_offsets.{}", name);
+            let message = format!("offset {} rad is beyond ±{:.3} rad", offset, MAX_OFFSET);
+            issues.push(issue(line(&path), &path, &message));
+        }
+    }
+    for (name, &[min, max]) in &config.joint_limits {
+        if min >= max {
+            let path = format!("joint_limits.{}", name);
+            issues.push(issue(line(&path), &path, "the minimum must be below the maximum"));
+        }
+    }
+}
+
+/// The candidate nearest to `key`, if it is close enough to be a typo: two
+/// edits, or a third of the key for longer ones.
+fn closest<'a>(key: &str, candidates: impl Iterator<Item = &'a String>) -> Option<&'a str> {
+    let typo = (key.chars().count() / 3).max(2);
+    candidates
+        .map(|candidate| (edit_distance(key, candidate), candidate))
+        .filter(|&(distance, _)| distance <= typo)
+        .min()
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// Levenshtein distance.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Blank out `//` comments outside strings, keeping lines and columns.
+pub fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let (mut in_string, mut escaped, mut in_comment) = (false, false, false);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_comment {
+            in_comment = c != '\n';
+            out.push(if c == '\n' { '\n' } else { ' ' });
+            continue;
+        }
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == '/' && chars.peek() == Some(&'/') {
+            in_comment = true;
+            out.push(' ');
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Line of every key (dotted path) and array element (`path[i]`) in a JSON
+/// text. Expects valid JSON.
+fn key_lines(text: &str) -> HashMap<String, usize> {
+    enum Frame {
+        Object { path: String, key: Option<String> },
+        Array { path: String, index: usize },
+    }
+    // Path of the value starting at the current position
+    let value_path = |stack: &[Frame]| match stack.last() {
+        Some(Frame::Object { path, key: Some(key) }) if path.is_empty() => key.clone(),
+        Some(Frame::Object { path, key: Some(key) }) => format!("{}.{}", path, key),
+        Some(Frame::Array { path, index }) => format!("{}[{}]", path, index),
+        _ => String::new(),
+    };
+
+    let mut lines = HashMap::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut expect_key = false;
+    let mut line = 1;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '"' => {
+                let mut string = String::new();
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    match c {
+                        _ if escaped => {
+                            escaped = false;
+                            string.push(c);
+                        }
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => string.push(c),
+                    }
+                }
+                if expect_key {
+                    if let Some(Frame::Object { key, .. }) = stack.last_mut() {
+                        *key = Some(string);
+                    }
+                    lines.insert(value_path(&stack), line);
+                    expect_key = false;
+                } else if let Some(Frame::Array { .. }) = stack.last() {
+                    lines.insert(value_path(&stack), line);
+                }
+            }
+            '{' | '[' => {
+                let path = value_path(&stack);
+                lines.entry(path.clone()).or_insert(line);
+                stack.push(if c == '{' {
+                    Frame::Object { path, key: None }
+                } else {
+                    Frame::Array { path, index: 0 }
+                });
+                expect_key = c == '{';
+            }
+            '}' | ']' => {
+                stack.pop();
+                expect_key = false;
+            }
+            ',' => match stack.last_mut() {
+                Some(Frame::Object { .. }) => expect_key = true,
+                Some(Frame::Array { index, .. }) => *index += 1,
+                None => {}
+            },
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// A config with every setting at its default and a comment above each
+/// section.
+pub fn template() -> String {
+    let defaults = serde_json::to_value(DuckConfig::default()).unwrap_or(Value::Null);
+    let mut out = String::from(
+        "// Open Duck duck config. Every setting below is at its default;\n\
+         // delete the ones you don't change. Check edits with\n\
+         // `openduckrust config validate`.\n{\n",
+    );
+    for (i, (key, description)) in SECTIONS.iter().enumerate() {
+        let value = defaults.get(key).cloned().unwrap_or(Value::Null);
+        let value = serde_json::to_string_pretty(&value).unwrap_or_default();
+        let separator = if i + 1 < SECTIONS.len() { "," } else { "" };
+        out.push_str(&format!("  // {}\n", description));
+        out.push_str(&format!("  \"{}\": {}{}\n", key, value.replace('\n', "\n  "), separator));
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_reports_every_problem_with_lines() {
+        let text = r#"{
+            // Calibrated on the bench
+            "joints_offsets": {"left_knee": 0.02, "left_kne": 0.01, "right_ankle": 2.0},
+            "gains": {
+                "kp": 300,
+                "joints": {"head_yaw": {"kp": 8}}
+            },
+            "imu_stale": 40,
+            "volume": "loud"
+        }"#;
+        let issues = check(text);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(9));
+        assert_eq!(issues[0].path, "volume");
+
+        let issues = check(&text.replace("\"loud\"", "0.5"));
+        let found: Vec<(Option<usize>, &str)> =
+            issues.iter().map(|i| (i.line, i.path.as_str())).collect();
+        assert_eq!(found.len(), 4);
+        assert!(found.contains(&(Some(3), "joints_offsets.left_kne")));
+        assert!(found.contains(&(Some(3), "joints_offsets.right_ankle")));
+        assert!(found.contains(&(Some(5), "gains.kp")));
+        assert!(found.contains(&(Some(8), "imu_stale")));
+        assert!(issues.iter().any(|i| i.message.contains("did you mean \"imu_stale_ms\"")));
+
+        assert_eq!(check("{\n  \"volume\": 0.5,\n}")[0].line, Some(3));
+    }
+
+    #[test]
+    fn test_template_covers_every_section_and_checks_clean() {
+        let defaults = serde_json::to_value(DuckConfig::default()).unwrap();
+        let sections: Vec<&str> = SECTIONS.iter().map(|(key, _)| *key).collect();
+        for key in defaults.as_object().unwrap().keys() {
+            assert!(sections.contains(&key.as_str()), "{} has no description", key);
+        }
+        assert_eq!(check(&template()), []);
+    }
+}
@@ -0,0 +1,263 @@
+//! Hot reload of `duck_config.json` while the runtime is running.
+//!
+//! `ConfigWatcher` watches the config's directory with inotify (editors
+//! often save by writing a new file and renaming it over the old one, which
+//! a watch on the file itself would lose) and reports a change once the
+//! file has been quiet for a moment. The new config is then compared with
+//! the running one key by key:
+//!
+//! - joint offsets, PID gains, the phase frequency offset, and the eyes,
+//!   projector, antennas, speaker and gait reaction expression settings are
+//!   applied live;
+//! - everything else (servo type, serial port, IMU, model, microphone,
+//!   camera, ...) needs hardware to be reopened and is rejected: the running
+//!   value is kept until the next restart.
+//!
+//! Each reload logs what it applied and what it rejected. A live key that
+//! can't be applied (gains naming an unknown joint, a failed servo write) is
+//! rejected too, and its running value put back with `revert`, so the
+//! runtime keeps walking and the next save tries again.
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{unbounded, Receiver};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::Value;
+use std::ffi::OsString;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::DuckConfig;
+
+/// Quiet time after the last write before the file is read.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Config keys (dotted paths) that are safe to change while running.
+pub const LIVE_KEYS: &[&str] = &[
+    "joints_offsets",
+    "gains",
+    "phase_frequency_factor_offset",
+    "expression_features.eyes",
+    "expression_features.projector",
+    "expression_features.antennas",
+    "expression_features.speaker",
+    "expression_features.gait_reactions",
+];
+
+/// Watches the config file for changes.
+pub struct ConfigWatcher {
+    // Watching stops when it is dropped
+    _watcher: RecommendedWatcher,
+    events: Receiver<()>,
+    last_write: Option<Instant>,
+}
+
+impl ConfigWatcher {
+    pub fn start(path: &Path) -> Result<Self> {
+        let name: OsString = path.file_name().context("Config path has no file name")?.into();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let (tx, events) = unbounded();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else { return };
+            let written = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+            if written && event.paths.iter().any(|p| p.file_name() == Some(name.as_os_str())) {
+                let _ = tx.send(());
+            }
+        })
+        .context("Failed to create config watcher")?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", dir.display()))?;
+        tracing::info!("Watching {} for changes", path.display());
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            last_write: None,
+        })
+    }
+
+    /// Whether the file changed and has since been quiet. Never blocks.
+    pub fn changed(&mut self) -> bool {
+        if self.events.try_iter().count() > 0 {
+            self.last_write = Some(Instant::now());
+        }
+        match self.last_write {
+            Some(at) if at.elapsed() >= DEBOUNCE => {
+                self.last_write = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Outcome of comparing a reloaded config with the running one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reload {
+    /// Changed keys that were applied.
+    pub applied: Vec<String>,
+    /// Changed keys that need a restart.
+    pub rejected: Vec<String>,
+}
+
+impl Reload {
+    /// Whether `key`, or a key under it, was applied.
+    pub fn applied(&self, key: &str) -> bool {
+        self.applied.iter().any(|k| k == key || is_under(k, key))
+    }
+
+    /// Move `key` from applied to rejected, when applying it failed.
+    pub fn reject(&mut self, key: &str) {
+        self.applied.retain(|k| k != key);
+        self.rejected.push(key.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.rejected.is_empty()
+    }
+
+    pub fn log(&self) {
+        if self.is_empty() {
+            tracing::info!("Config reloaded: no changes");
+            return;
+        }
+        if !self.applied.is_empty() {
+            tracing::info!("Config reload applied: {}", self.applied.join(", "));
+        }
+        if !self.rejected.is_empty() {
+            tracing::warn!(
+                "Config reload rejected (restart to apply): {}",
+                self.rejected.join(", ")
+            );
+        }
+    }
+}
+
+/// Compare `new` with the `running` config and copy the live keys over.
+/// Rejected changes leave the running values as they were.
+pub fn apply(running: &mut DuckConfig, new: &DuckConfig) -> Result<Reload> {
+    let old = serde_json::to_value(&*running)?;
+    let mut reload = Reload::default();
+    compare("", &old, &serde_json::to_value(new)?, &mut reload);
+
+    if reload.applied("joints_offsets") {
+        running.joints_offset = new.joints_offset.clone();
+    }
+    if reload.applied("gains") {
+        running.gains = new.gains.clone();
+    }
+    if reload.applied("phase_frequency_factor_offset") {
+        running.phase_frequency_factor_offset = new.phase_frequency_factor_offset;
+    }
+    let (features, new_features) = (&mut running.expression_features, &new.expression_features);
+    features.eyes = new_features.eyes;
+    features.projector = new_features.projector;
+    features.antennas = new_features.antennas;
+    features.speaker = new_features.speaker;
+    features.gait_reactions = new_features.gait_reactions.clone();
+    Ok(reload)
+}
+
+/// Put a live key that could not be applied back to its `running` value and
+/// reject it.
+pub fn revert(config: &mut DuckConfig, running: &DuckConfig, reload: &mut Reload, key: &str) {
+    match key {
+        "joints_offsets" => config.joints_offset = running.joints_offset.clone(),
+        "gains" => config.gains = running.gains.clone(),
+        "phase_frequency_factor_offset" => {
+            config.phase_frequency_factor_offset = running.phase_frequency_factor_offset
+        }
+        _ => {}
+    }
+    reload.reject(key);
+}
+
+/// Sort the differences between `old` and `new` under `path` into live and
+/// rejected keys, going into objects that hold live keys.
+fn compare(path: &str, old: &Value, new: &Value, reload: &mut Reload) {
+    if old == new {
+        return;
+    }
+    if LIVE_KEYS.contains(&path) {
+        reload.applied.push(path.to_string());
+        return;
+    }
+    let holds_live = path.is_empty() || LIVE_KEYS.iter().any(|key| is_under(key, path));
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) if holds_live => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                let missing = Value::Null;
+                let (old, new) = (old.get(key), new.get(key));
+                compare(&child, old.unwrap_or(&missing), new.unwrap_or(&missing), reload);
+            }
+        }
+        _ => reload.rejected.push(path.to_string()),
+    }
+}
+
+/// Whether dotted `key` is strictly inside `parent`.
+fn is_under(key: &str, parent: &str) -> bool {
+    key.strip_prefix(parent).is_some_and(|rest| rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_keys_applied_others_rejected() {
+        let mut running = DuckConfig::default();
+        let mut new = running.clone();
+        new.joints_offset.insert("left_knee".to_string(), 0.05);
+        new.phase_frequency_factor_offset = 0.1;
+        new.expression_features.eyes = true;
+        new.expression_features.camera = true;
+        new.imu_upside_down = !running.imu_upside_down;
+
+        let reload = apply(&mut running, &new).unwrap();
+        assert_eq!(
+            reload.applied,
+            ["expression_features.eyes", "joints_offsets", "phase_frequency_factor_offset"]
+        );
+        assert_eq!(reload.rejected, ["expression_features.camera", "imu_upside_down"]);
+        assert!(reload.applied("expression_features"));
+        assert_eq!(running.joints_offset, new.joints_offset);
+        assert!(running.expression_features.eyes);
+        assert!(!running.expression_features.camera);
+        assert_ne!(running.imu_upside_down, new.imu_upside_down);
+
+        let mut reload = reload;
+        reload.reject("joints_offsets");
+        assert!(!reload.applied("joints_offsets"));
+        assert_eq!(reload.rejected.last().map(String::as_str), Some("joints_offsets"));
+
+        assert!(apply(&mut running.clone(), &running).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unresolvable_gains_reverted() {
+        let joint_names = ["left_knee".to_string(), "right_knee".to_string()];
+        let mut config = DuckConfig::default();
+        config.gains.joints.clear();
+        let running = config.clone();
+        let mut new = config.clone();
+        new.gains.kp = 12.0;
+        new.gains.joints.insert("left_kne".to_string(), Default::default());
+
+        let mut reload = apply(&mut config, &new).unwrap();
+        assert!(reload.applied("gains"));
+        assert!(config.gains.resolve(&joint_names).is_err());
+
+        revert(&mut config, &running, &mut reload, "gains");
+        assert!(!reload.applied("gains"));
+        assert_eq!(reload.rejected, ["gains"]);
+        assert_eq!(config.gains.kp, running.gains.kp);
+        assert!(config.gains.resolve(&joint_names).is_ok());
+    }
+}
@@ -0,0 +1,223 @@
+//! Foot contact filtering and touchdown/liftoff events.
+//!
+//! Switches bounce at heel strike and FSRs ring, so a raw reading flickers
+//! for a few milliseconds every step, which confuses phase-locked policies.
+//! `FeetSampler` reads the feet on its own thread, faster than the control
+//! loop (like the IMU), optionally low-passes the readings, and lets a foot
+//! change state only once its reading has stayed past the threshold (with
+//! hysteresis) for the debounce time. Each change is a touchdown or liftoff
+//! event, stamped with when the reading first crossed, for gait analysis.
+
+use anyhow::Result;
+use crossbeam_channel::{bounded, Receiver};
+use std::f64::consts::TAU;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::FeetContactsConfig;
+use crate::peripherals::FeetContactsReader;
+
+/// Events kept for the control loop to collect; newer ones are dropped.
+const EVENT_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactEdge {
+    Touchdown,
+    Liftoff,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactEvent {
+    /// 0 for the left foot, 1 for the right.
+    pub foot: usize,
+    pub edge: ContactEdge,
+    pub at: Instant,
+}
+
+/// Debounce and low-pass for one foot.
+#[derive(Debug, Clone)]
+pub struct ContactFilter {
+    low_pass_hz: f64,
+    debounce: Duration,
+    on_threshold: f64,
+    off_threshold: f64,
+    /// Switches report the debounced state rather than the level.
+    binary: bool,
+    level: Option<(f64, Instant)>,
+    down: bool,
+    crossed_at: Option<Instant>,
+    /// When the reading behind the last edge first crossed the threshold.
+    edge_at: Option<Instant>,
+}
+
+impl ContactFilter {
+    pub fn new(config: &FeetContactsConfig, binary: bool) -> Self {
+        Self {
+            low_pass_hz: config.low_pass_hz,
+            debounce: Duration::from_secs_f64(config.debounce_ms.max(0.0) / 1000.0),
+            on_threshold: config.on_threshold,
+            off_threshold: config.off_threshold,
+            binary,
+            level: None,
+            down: false,
+            crossed_at: None,
+            edge_at: None,
+        }
+    }
+
+    /// Take a raw reading; returns the edge if the foot changed state.
+    pub fn update(&mut self, raw: f64, now: Instant) -> Option<ContactEdge> {
+        let level = match self.level {
+            Some((level, last)) if self.low_pass_hz > 0.0 => {
+                let dt = now.saturating_duration_since(last).as_secs_f64();
+                let alpha = 1.0 - (-dt * TAU * self.low_pass_hz).exp();
+                level + alpha * (raw - level)
+            }
+            _ => raw,
+        };
+        self.level = Some((level, now));
+
+        let crossed = if self.down {
+            level < self.off_threshold
+        } else {
+            level > self.on_threshold
+        };
+        if !crossed {
+            self.crossed_at = None;
+            return None;
+        }
+        let since = *self.crossed_at.get_or_insert(now);
+        if now.saturating_duration_since(since) < self.debounce {
+            return None;
+        }
+        self.down = !self.down;
+        self.crossed_at = None;
+        self.edge_at = Some(since);
+        Some(if self.down {
+            ContactEdge::Touchdown
+        } else {
+            ContactEdge::Liftoff
+        })
+    }
+
+    /// Contact value for the policy: 0 or 1 for switches, the filtered
+    /// level (0..1) otherwise.
+    pub fn value(&self) -> f64 {
+        if self.binary {
+            if self.down {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            self.level.map_or(0.0, |(level, _)| level)
+        }
+    }
+}
+
+/// Feet read and filtered on a background thread.
+pub struct FeetSampler {
+    values: Arc<[AtomicU64; 2]>,
+    events: Receiver<ContactEvent>,
+    stop_flag: Arc<AtomicBool>,
+    _thread: thread::JoinHandle<()>,
+}
+
+impl FeetSampler {
+    /// Start sampling `read`, which returns the raw [left, right] readings.
+    /// `binary` says they are switches (0 or 1).
+    pub fn start(
+        config: &FeetContactsConfig,
+        binary: bool,
+        mut read: impl FnMut() -> Result<[f64; 2]> + Send + 'static,
+    ) -> Self {
+        let values = Arc::new([AtomicU64::new(0), AtomicU64::new(0)]);
+        let worker_values = Arc::clone(&values);
+        let (events_tx, events_rx) = bounded(EVENT_CAPACITY);
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop_flag);
+        let mut filters = [0, 1].map(|_| ContactFilter::new(config, binary));
+        let period = Duration::from_secs_f64(1.0 / config.sample_hz.max(1.0));
+
+        let handle = thread::spawn(move || {
+            let mut failing = false;
+            while !flag.load(Ordering::Relaxed) {
+                match read() {
+                    Ok(raw) => {
+                        let now = Instant::now();
+                        for (foot, filter) in filters.iter_mut().enumerate() {
+                            if let Some(edge) = filter.update(raw[foot], now) {
+                                let at = filter.edge_at.unwrap_or(now);
+                                let _ = events_tx.try_send(ContactEvent { foot, edge, at });
+                            }
+                            worker_values[foot].store(filter.value().to_bits(), Ordering::Relaxed);
+                        }
+                        failing = false;
+                    }
+                    Err(e) => {
+                        if !failing {
+                            tracing::warn!("Foot contact read failed, keeping last value: {e:#}");
+                        }
+                        failing = true;
+                    }
+                }
+                thread::sleep(period);
+            }
+        });
+
+        Self {
+            values,
+            events: events_rx,
+            stop_flag,
+            _thread: handle,
+        }
+    }
+}
+
+impl FeetContactsReader for FeetSampler {
+    fn get(&self) -> [f64; 2] {
+        [0, 1].map(|i| f64::from_bits(self.values[i].load(Ordering::Relaxed)))
+    }
+
+    fn events(&self) -> Vec<ContactEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for FeetSampler {
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bouncing_switch_gives_one_touchdown() {
+        let config = FeetContactsConfig::default();
+        let mut filter = ContactFilter::new(&config, true);
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Heel strike bouncing for 6 ms, then settled
+        let readings = [(0, 0.0), (5, 1.0), (7, 0.0), (9, 1.0), (11, 0.0), (13, 1.0)];
+        for (ms, raw) in readings {
+            assert_eq!(filter.update(raw, at(ms)), None);
+        }
+        assert_eq!(filter.update(1.0, at(18)), None);
+        assert_eq!(filter.update(1.0, at(23)), Some(ContactEdge::Touchdown));
+        assert_eq!(filter.edge_at, Some(at(13)));
+        assert_eq!(filter.value(), 1.0);
+
+        // A single dropout doesn't lift the foot
+        assert_eq!(filter.update(0.0, at(28)), None);
+        assert_eq!(filter.update(1.0, at(33)), None);
+        assert_eq!(filter.update(0.0, at(38)), None);
+        assert_eq!(filter.update(0.0, at(48)), Some(ContactEdge::Liftoff));
+        assert_eq!(filter.value(), 0.0);
+    }
+}
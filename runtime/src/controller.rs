@@ -2,20 +2,45 @@
 //!
 //! Replaces `xbox_controller.py`. Uses the `gilrs` crate for cross-platform
 //! gamepad support, running input polling in a background thread.
+//!
+//! Input goes through three stages, each usable on its own:
+//!
+//! 1. `RawGamepad` holds the stick, trigger and button state as the driver
+//!    last reported it.
+//! 2. `Buttons::update` debounces the pressed flags into edges.
+//! 3. `CommandShaper` turns normalized stick deflections into commands in
+//!    the velocity and head ranges, and clamps commands from sources that
+//!    already speak physical units.
+//!
+//! The keyboard, the forwarded gamepad and the WebSocket clients all go
+//! through the same shaping, so every source is scaled and bounded alike.
 
 use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
 use std::thread;
 use std::time::{Duration, Instant};
 
 /// Velocity command ranges (matching the Python runtime).
-const X_RANGE: [f64; 2] = [-0.15, 0.15];
-const Y_RANGE: [f64; 2] = [-0.2, 0.2];
-const YAW_RANGE: [f64; 2] = [-1.0, 1.0];
+pub const X_RANGE: [f64; 2] = [-0.15, 0.15];
+pub const Y_RANGE: [f64; 2] = [-0.2, 0.2];
+pub const YAW_RANGE: [f64; 2] = [-1.0, 1.0];
 
-const HEAD_PITCH_RANGE: [f64; 2] = [-0.78, 0.3];
-const HEAD_YAW_RANGE: [f64; 2] = [-0.5, 0.5];
+/// Head pose ranges (rad); a full stick deflection reaches the ends.
+pub(crate) const HEAD_PITCH_RANGE: [f64; 2] = [-0.3, 0.78];
+pub(crate) const HEAD_YAW_RANGE: [f64; 2] = [-0.5, 0.5];
 const HEAD_ROLL_RANGE: [f64; 2] = [-0.5, 0.5];
 
+/// What the sticks do outside head-control mode (Y toggles head control in
+/// both layouts).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StickLayout {
+    /// Sticks drive; head poses need head-control mode.
+    #[default]
+    Toggle,
+    /// Sticks drive, and right stick Y also pitches the head.
+    Combined,
+}
+
 /// Button state with debounce/trigger detection.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ButtonState {
@@ -35,7 +60,7 @@ impl ButtonState {
         }
     }
 
-    fn update(&mut self, value: bool, now: f64) {
+    pub(crate) fn update(&mut self, value: bool, now: f64) {
         if self.is_pressed && !value {
             self.released = true;
         }
@@ -63,12 +88,15 @@ pub struct Buttons {
     pub y: ButtonState,
     pub lb: ButtonState,
     pub rb: ButtonState,
+    pub start: ButtonState,
     pub dpad_up: ButtonState,
     pub dpad_down: ButtonState,
+    pub dpad_left: ButtonState,
+    pub dpad_right: ButtonState,
 }
 
 impl Buttons {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             a: ButtonState::new(),
             b: ButtonState::new(),
@@ -76,12 +104,65 @@ impl Buttons {
             y: ButtonState::new(),
             lb: ButtonState::new(),
             rb: ButtonState::new(),
+            start: ButtonState::new(),
             dpad_up: ButtonState::new(),
             dpad_down: ButtonState::new(),
+            dpad_left: ButtonState::new(),
+            dpad_right: ButtonState::new(),
+        }
+    }
+
+    /// Debounce one sample of raw pressed flags.
+    pub(crate) fn update(&mut self, pressed: &PressedButtons, now: f64) {
+        self.a.update(pressed.a, now);
+        self.b.update(pressed.b, now);
+        self.x.update(pressed.x, now);
+        self.y.update(pressed.y, now);
+        self.lb.update(pressed.lb, now);
+        self.rb.update(pressed.rb, now);
+        self.start.update(pressed.start, now);
+        self.dpad_up.update(pressed.dpad_up, now);
+        self.dpad_down.update(pressed.dpad_down, now);
+        self.dpad_left.update(pressed.dpad_left, now);
+        self.dpad_right.update(pressed.dpad_right, now);
+    }
+
+    /// The raw pressed flags, without edges.
+    pub fn pressed(&self) -> PressedButtons {
+        PressedButtons {
+            a: self.a.is_pressed,
+            b: self.b.is_pressed,
+            x: self.x.is_pressed,
+            y: self.y.is_pressed,
+            lb: self.lb.is_pressed,
+            rb: self.rb.is_pressed,
+            start: self.start.is_pressed,
+            dpad_up: self.dpad_up.is_pressed,
+            dpad_down: self.dpad_down.is_pressed,
+            dpad_left: self.dpad_left.is_pressed,
+            dpad_right: self.dpad_right.is_pressed,
         }
     }
 }
 
+/// Raw pressed state of every button.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PressedButtons {
+    pub a: bool,
+    pub b: bool,
+    pub x: bool,
+    pub y: bool,
+    pub lb: bool,
+    pub rb: bool,
+    pub start: bool,
+    pub dpad_up: bool,
+    pub dpad_down: bool,
+    #[serde(default)]
+    pub dpad_left: bool,
+    #[serde(default)]
+    pub dpad_right: bool,
+}
+
 /// Command output from the controller.
 #[derive(Debug, Clone)]
 pub struct ControllerOutput {
@@ -90,6 +171,8 @@ pub struct ControllerOutput {
     pub buttons: Buttons,
     pub left_trigger: f64,
     pub right_trigger: f64,
+    /// Emergency stop: hold the robot stopped, whatever other sources say.
+    pub estop: bool,
 }
 
 impl Default for ControllerOutput {
@@ -99,10 +182,19 @@ impl Default for ControllerOutput {
             buttons: Buttons::new(),
             left_trigger: 0.0,
             right_trigger: 0.0,
+            estop: false,
         }
     }
 }
 
+/// Source of driving commands and button presses: a gamepad (local or
+/// forwarded over the network), the keyboard, a remote client or a script.
+/// `input::CommandMux` merges several of them.
+pub trait CommandSource: Send {
+    /// Get the latest controller state (non-blocking).
+    fn get_last_command(&mut self) -> &ControllerOutput;
+}
+
 /// Xbox controller input handler running in a background thread.
 pub struct XBoxController {
     receiver: Receiver<ControllerOutput>,
@@ -112,14 +204,14 @@ pub struct XBoxController {
 
 impl XBoxController {
     /// Initialize the gamepad and start the background polling thread.
-    pub fn new(command_freq: u32) -> Self {
+    pub fn new(command_freq: u32, layout: StickLayout) -> Self {
         let (data_tx, data_rx) = bounded::<ControllerOutput>(1);
         let (stop_tx, stop_rx) = bounded::<()>(1);
 
         let period = Duration::from_secs_f64(1.0 / command_freq as f64);
 
         thread::spawn(move || {
-            controller_worker(data_tx, stop_rx, period);
+            controller_worker(data_tx, stop_rx, period, layout);
         });
 
         Self {
@@ -129,18 +221,19 @@ impl XBoxController {
         }
     }
 
-    /// Get the latest controller state (non-blocking).
-    pub fn get_last_command(&mut self) -> &ControllerOutput {
+    /// Signal the background thread to stop.
+    pub fn stop(&self) {
+        let _ = self.stop_tx.try_send(());
+    }
+}
+
+impl CommandSource for XBoxController {
+    fn get_last_command(&mut self) -> &ControllerOutput {
         if let Ok(output) = self.receiver.try_recv() {
             self.last_output = output;
         }
         &self.last_output
     }
-
-    /// Signal the background thread to stop.
-    pub fn stop(&self) {
-        let _ = self.stop_tx.try_send(());
-    }
 }
 
 impl Drop for XBoxController {
@@ -149,13 +242,185 @@ impl Drop for XBoxController {
     }
 }
 
+/// Gamepad state as the driver last reported it, before debouncing and
+/// shaping. Sticks are `[left_x, left_y, right_x, right_y]` in -1..1.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RawGamepad {
+    pub sticks: [f64; 4],
+    pub left_trigger: f64,
+    pub right_trigger: f64,
+    pub pressed: PressedButtons,
+}
+
+impl RawGamepad {
+    /// Triggers below this read as released.
+    const TRIGGER_DEADZONE: f64 = 0.1;
+
+    /// Fold one gilrs event into the state.
+    fn apply(&mut self, event: gilrs::EventType) {
+        use gilrs::{Axis, EventType};
+
+        match event {
+            EventType::AxisChanged(axis, value, _) => {
+                let v = value as f64;
+                let trigger = |v: f64| {
+                    let t = ((v + 1.0) / 2.0).max(0.0);
+                    if t < Self::TRIGGER_DEADZONE {
+                        0.0
+                    } else {
+                        t
+                    }
+                };
+                match axis {
+                    Axis::LeftStickX => self.sticks[0] = -v,
+                    Axis::LeftStickY => self.sticks[1] = -v,
+                    Axis::RightStickX => self.sticks[2] = -v,
+                    Axis::RightStickY => self.sticks[3] = -v,
+                    Axis::LeftZ => self.left_trigger = trigger(v),
+                    Axis::RightZ => self.right_trigger = trigger(v),
+                    Axis::DPadX => {
+                        self.pressed.dpad_left = v < -0.5;
+                        self.pressed.dpad_right = v > 0.5;
+                    }
+                    Axis::DPadY => {
+                        self.pressed.dpad_up = v > 0.5;
+                        self.pressed.dpad_down = v < -0.5;
+                    }
+                    _ => {}
+                }
+            }
+            EventType::ButtonPressed(button, _) => self.set_button(button, true),
+            EventType::ButtonReleased(button, _) => self.set_button(button, false),
+            _ => {}
+        }
+    }
+
+    fn set_button(&mut self, button: gilrs::Button, value: bool) {
+        use gilrs::Button;
+
+        let p = &mut self.pressed;
+        let flag = match button {
+            Button::South => &mut p.a,
+            Button::East => &mut p.b,
+            Button::West => &mut p.x,
+            Button::North => &mut p.y,
+            Button::LeftTrigger => &mut p.lb,
+            Button::RightTrigger => &mut p.rb,
+            Button::Start => &mut p.start,
+            Button::DPadUp => &mut p.dpad_up,
+            Button::DPadDown => &mut p.dpad_down,
+            Button::DPadLeft => &mut p.dpad_left,
+            Button::DPadRight => &mut p.dpad_right,
+            _ => return,
+        };
+        *flag = value;
+    }
+}
+
+/// Turns normalized input into commands. Stateful only for head-control
+/// mode, which Y toggles on gamepads.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommandShaper {
+    layout: StickLayout,
+    head_control_mode: bool,
+}
+
+impl CommandShaper {
+    pub fn new(layout: StickLayout) -> Self {
+        Self {
+            layout,
+            head_control_mode: false,
+        }
+    }
+
+    pub fn head_control_mode(&self) -> bool {
+        self.head_control_mode
+    }
+
+    pub fn toggle_head_control(&mut self) {
+        self.head_control_mode = !self.head_control_mode;
+    }
+
+    /// Map stick axes `[left_x, left_y, right_x, right_y]` (-1..1) to
+    /// commands for the current mode and layout.
+    pub fn sticks(&self, sticks: [f64; 4]) -> [f64; 7] {
+        let [left_x, left_y, right_x, right_y] = sticks;
+        let mut commands = [0.0f64; 7];
+
+        if !self.head_control_mode {
+            // Walking mode: left stick = velocity, right stick X = yaw
+            commands[..3].copy_from_slice(&Self::drive(left_y, left_x, right_x));
+
+            // Combined layout: right stick Y looks up and down while driving
+            if self.layout == StickLayout::Combined {
+                commands[4] = scale(right_y, HEAD_PITCH_RANGE);
+            }
+        } else {
+            commands[4..].copy_from_slice(&Self::head(left_y, left_x, right_x));
+        }
+
+        commands
+    }
+
+    /// `[lin_vel_x, lin_vel_y, ang_vel]` for normalized forward, sideways
+    /// and turn inputs (-1..1).
+    pub fn drive(forward: f64, sideways: f64, turn: f64) -> [f64; 3] {
+        [
+            scale(forward, X_RANGE),
+            scale(sideways, Y_RANGE),
+            scale(turn, YAW_RANGE),
+        ]
+    }
+
+    /// `[head_pitch, head_yaw, head_roll]` for normalized inputs (-1..1).
+    pub fn head(pitch: f64, yaw: f64, roll: f64) -> [f64; 3] {
+        [
+            scale(pitch, HEAD_PITCH_RANGE),
+            scale(yaw, HEAD_YAW_RANGE),
+            scale(roll, HEAD_ROLL_RANGE),
+        ]
+    }
+
+    /// Clamp commands that are already in physical units (m/s, rad/s, rad)
+    /// to the ranges a gamepad can reach. The neck pitch has no stick and is
+    /// left to the joint limits.
+    pub fn limit(commands: &mut [f64; 7]) {
+        let ranges = [
+            (0, X_RANGE),
+            (1, Y_RANGE),
+            (2, YAW_RANGE),
+            (4, HEAD_PITCH_RANGE),
+            (5, HEAD_YAW_RANGE),
+            (6, HEAD_ROLL_RANGE),
+        ];
+        for (i, [min, max]) in ranges {
+            // NaN from a malformed client would pass `clamp` unchanged
+            commands[i] = if commands[i].is_nan() {
+                0.0
+            } else {
+                commands[i].clamp(min, max)
+            };
+        }
+    }
+}
+
+/// Map -1..1 onto `[min, max]`: -1 reaches `min` and 1 reaches `max`.
+fn scale(value: f64, range: [f64; 2]) -> f64 {
+    if value >= 0.0 {
+        value * range[1].abs()
+    } else {
+        value * range[0].abs()
+    }
+}
+
 /// Background worker that polls the gamepad at the command frequency.
 fn controller_worker(
     data_tx: Sender<ControllerOutput>,
     stop_rx: Receiver<()>,
     period: Duration,
+    layout: StickLayout,
 ) {
-    use gilrs::{Axis, Button, EventType, Gilrs};
+    use gilrs::Gilrs;
 
     let mut gilrs = match Gilrs::new() {
         Ok(g) => g,
@@ -167,25 +432,9 @@ fn controller_worker(
 
     tracing::info!("Gamepad input thread started");
 
-    // Track raw axis values
-    let mut left_x: f64 = 0.0;
-    let mut left_y: f64 = 0.0;
-    let mut right_x: f64 = 0.0;
-    let mut _right_y: f64 = 0.0;
-    let mut left_trigger: f64 = 0.0;
-    let mut right_trigger: f64 = 0.0;
-
-    let mut a_pressed = false;
-    let mut b_pressed = false;
-    let mut x_pressed = false;
-    let mut y_pressed = false;
-    let mut lb_pressed = false;
-    let mut rb_pressed = false;
-    let mut dpad_up = false;
-    let mut dpad_down = false;
-
+    let mut raw = RawGamepad::default();
     let mut buttons = Buttons::new();
-    let mut head_control_mode = false;
+    let mut shaper = CommandShaper::new(layout);
 
     let start_time = Instant::now();
 
@@ -198,140 +447,24 @@ fn controller_worker(
 
         // Process all pending events
         while let Some(event) = gilrs.next_event() {
-            match event.event {
-                EventType::AxisChanged(axis, value, _) => {
-                    let v = value as f64;
-                    match axis {
-                        Axis::LeftStickX => left_x = -v,
-                        Axis::LeftStickY => left_y = -v,
-                        Axis::RightStickX => right_x = -v,
-                        Axis::RightStickY => _right_y = -v,
-                        Axis::LeftZ => {
-                            left_trigger = ((v + 1.0) / 2.0).max(0.0);
-                            if left_trigger < 0.1 {
-                                left_trigger = 0.0;
-                            }
-                        }
-                        Axis::RightZ => {
-                            right_trigger = ((v + 1.0) / 2.0).max(0.0);
-                            if right_trigger < 0.1 {
-                                right_trigger = 0.0;
-                            }
-                        }
-                        Axis::DPadX => {
-                            // Not used in the Python version
-                        }
-                        Axis::DPadY => {
-                            dpad_up = v > 0.5;
-                            dpad_down = v < -0.5;
-                        }
-                        _ => {}
-                    }
-                }
-                EventType::ButtonPressed(button, _) => match button {
-                    Button::South => a_pressed = true,
-                    Button::East => b_pressed = true,
-                    Button::West => x_pressed = true,
-                    Button::North => {
-                        y_pressed = true;
-                        head_control_mode = !head_control_mode;
-                    }
-                    Button::LeftTrigger => lb_pressed = true,
-                    Button::RightTrigger => rb_pressed = true,
-                    Button::DPadUp => dpad_up = true,
-                    Button::DPadDown => dpad_down = true,
-                    _ => {}
-                },
-                EventType::ButtonReleased(button, _) => match button {
-                    Button::South => a_pressed = false,
-                    Button::East => b_pressed = false,
-                    Button::West => x_pressed = false,
-                    Button::North => y_pressed = false,
-                    Button::LeftTrigger => lb_pressed = false,
-                    Button::RightTrigger => rb_pressed = false,
-                    Button::DPadUp => dpad_up = false,
-                    Button::DPadDown => dpad_down = false,
-                    _ => {}
-                },
-                _ => {}
-            }
-        }
-
-        // Compute commands
-        let mut commands = [0.0f64; 7];
-
-        if !head_control_mode {
-            // Walking mode: left stick = velocity, right stick X = yaw
-            let mut lin_vel_x = left_y;
-            let mut lin_vel_y = left_x;
-            let mut ang_vel = right_x;
-
-            if lin_vel_x >= 0.0 {
-                lin_vel_x *= X_RANGE[1].abs();
-            } else {
-                lin_vel_x *= X_RANGE[0].abs();
+            // LB + Y starts a calibration routine instead
+            let y_pressed =
+                matches!(event.event, gilrs::EventType::ButtonPressed(gilrs::Button::North, _));
+            if y_pressed && !raw.pressed.lb {
+                shaper.toggle_head_control();
             }
-
-            if lin_vel_y >= 0.0 {
-                lin_vel_y *= Y_RANGE[1].abs();
-            } else {
-                lin_vel_y *= Y_RANGE[0].abs();
-            }
-
-            if ang_vel >= 0.0 {
-                ang_vel *= YAW_RANGE[1].abs();
-            } else {
-                ang_vel *= YAW_RANGE[0].abs();
-            }
-
-            commands[0] = lin_vel_x;
-            commands[1] = lin_vel_y;
-            commands[2] = ang_vel;
-        } else {
-            // Head control mode
-            let mut head_yaw = left_x;
-            let mut head_pitch = left_y;
-            let mut head_roll = right_x;
-
-            if head_yaw >= 0.0 {
-                head_yaw *= HEAD_YAW_RANGE[0].abs();
-            } else {
-                head_yaw *= HEAD_YAW_RANGE[1].abs();
-            }
-
-            if head_pitch >= 0.0 {
-                head_pitch *= HEAD_PITCH_RANGE[0].abs();
-            } else {
-                head_pitch *= HEAD_PITCH_RANGE[1].abs();
-            }
-
-            if head_roll >= 0.0 {
-                head_roll *= HEAD_ROLL_RANGE[0].abs();
-            } else {
-                head_roll *= HEAD_ROLL_RANGE[1].abs();
-            }
-
-            commands[4] = head_pitch;
-            commands[5] = head_yaw;
-            commands[6] = head_roll;
+            raw.apply(event.event);
         }
 
-        // Update button states
-        let now = start_time.elapsed().as_secs_f64();
-        buttons.a.update(a_pressed, now);
-        buttons.b.update(b_pressed, now);
-        buttons.x.update(x_pressed, now);
-        buttons.y.update(y_pressed, now);
-        buttons.lb.update(lb_pressed, now);
-        buttons.rb.update(rb_pressed, now);
-        buttons.dpad_up.update(dpad_up, now);
-        buttons.dpad_down.update(dpad_down, now);
+        buttons.update(&raw.pressed, start_time.elapsed().as_secs_f64());
+        let commands = shaper.sticks(raw.sticks);
 
         let output = ControllerOutput {
             commands,
             buttons: buttons.clone(),
-            left_trigger,
-            right_trigger,
+            left_trigger: raw.left_trigger,
+            right_trigger: raw.right_trigger,
+            estop: false,
         };
 
         // Non-blocking send
@@ -352,3 +485,44 @@ fn controller_worker(
 
     tracing::info!("Controller worker thread exiting");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combined_layout_pitches_head_while_driving() {
+        let sticks = [0.0, 1.0, -0.5, 1.0];
+
+        let mut toggle = CommandShaper::new(StickLayout::Toggle);
+        let walk = toggle.sticks(sticks);
+        assert_eq!(walk[0], X_RANGE[1]);
+        assert_eq!(walk[2], -0.5);
+        assert_eq!(walk[4], 0.0);
+
+        let mut combined = CommandShaper::new(StickLayout::Combined);
+        let drive = combined.sticks(sticks);
+        assert_eq!(drive[..3], walk[..3]);
+        assert_eq!(drive[4], HEAD_PITCH_RANGE[1]);
+
+        // Head-control mode is the same in both layouts
+        toggle.toggle_head_control();
+        combined.toggle_head_control();
+        assert_eq!(combined.sticks(sticks), toggle.sticks(sticks));
+    }
+
+    #[test]
+    fn test_limit_bounds_physical_commands() {
+        let mut commands = [1.0, -1.0, f64::NAN, 0.4, 0.1, -2.0, 0.0];
+        CommandShaper::limit(&mut commands);
+        assert_eq!(commands, [X_RANGE[1], Y_RANGE[0], 0.0, 0.4, 0.1, HEAD_YAW_RANGE[0], 0.0]);
+
+        // Shaped full deflections are already in range
+        let mut shaped = [0.0; 7];
+        shaped[..3].copy_from_slice(&CommandShaper::drive(-1.0, 1.0, 1.0));
+        shaped[4..].copy_from_slice(&CommandShaper::head(1.0, -1.0, 1.0));
+        let before = shaped;
+        CommandShaper::limit(&mut shaped);
+        assert_eq!(shaped, before);
+    }
+}
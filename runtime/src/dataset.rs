@@ -0,0 +1,260 @@
+//! Training-data collection — records observations, actions, and operator
+//! labels for fine-tuning and imitation learning.
+//!
+//! Records are written as JSON Lines (one object per line, tagged by `type`)
+//! from a background thread, so a slow SD card never stalls the control loop.
+//! A session file starts with a `meta` record, followed by one `frame` record
+//! per control tick and a `label` record whenever the operator tags a moment:
+//!
+//! ```text
+//! {"type":"meta","control_freq":50,"action_scale":0.25,"joint_names":[...],...}
+//! {"type":"frame","tick":0,"time":0.0,"obs":[...],"action":[...],...}
+//! {"type":"label","tick":812,"time":16.24,"label":"fall"}
+//! ```
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Records buffered between the control loop and the writer thread.
+const QUEUE_CAPACITY: usize = 512;
+
+/// Flush the file every this many records, bounding data lost on power cut.
+const FLUSH_INTERVAL: usize = 50;
+
+/// Operator annotation attached to a moment in the session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Label {
+    Good,
+    Bad,
+    Fall,
+}
+
+/// Session metadata written as the first record.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionMeta {
+    pub control_freq: u32,
+    pub action_scale: f64,
+    pub joint_names: Vec<String>,
+    pub init_positions: Vec<f64>,
+    pub model: String,
+    /// Wall-clock start time (seconds since the Unix epoch).
+    pub started_at: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record {
+    Meta(SessionMeta),
+    Frame {
+        tick: u64,
+        time: f64,
+        obs: Vec<f64>,
+        action: Vec<f64>,
+        motor_targets: Vec<f64>,
+        commands: [f64; 7],
+    },
+    Label {
+        tick: u64,
+        time: f64,
+        label: Label,
+    },
+}
+
+/// Dataset recorder with a background writer thread.
+pub struct DatasetRecorder {
+    sender: Option<Sender<Record>>,
+    dropped: Arc<AtomicU64>,
+    path: PathBuf,
+    writer: Option<thread::JoinHandle<()>>,
+}
+
+impl DatasetRecorder {
+    /// Create a new session file in `directory` and start the writer thread.
+    pub fn new(directory: &Path, meta: SessionMeta) -> Result<Self> {
+        std::fs::create_dir_all(directory).with_context(|| {
+            format!("Failed to create dataset directory {}", directory.display())
+        })?;
+
+        let path = directory.join(format!("session-{}.jsonl", meta.started_at as u64));
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create dataset file {}", path.display()))?;
+
+        let (tx, rx) = bounded::<Record>(QUEUE_CAPACITY);
+        tx.send(Record::Meta(meta))
+            .context("Dataset writer queue closed")?;
+
+        let writer = thread::spawn(move || {
+            dataset_worker(BufWriter::new(file), rx);
+        });
+
+        tracing::info!("Recording training data to {}", path.display());
+
+        Ok(Self {
+            sender: Some(tx),
+            dropped: Arc::new(AtomicU64::new(0)),
+            path,
+            writer: Some(writer),
+        })
+    }
+
+    /// Record one control tick (non-blocking; drops the frame if the writer is behind).
+    pub fn record_frame(
+        &self,
+        tick: u64,
+        time: f64,
+        obs: &[f64],
+        action: &[f64],
+        motor_targets: &[f64],
+        commands: &[f64; 7],
+    ) {
+        self.send(Record::Frame {
+            tick,
+            time,
+            obs: obs.to_vec(),
+            action: action.to_vec(),
+            motor_targets: motor_targets.to_vec(),
+            commands: *commands,
+        });
+    }
+
+    /// Tag the current tick with an operator label.
+    pub fn record_label(&self, tick: u64, time: f64, label: Label) {
+        tracing::info!("Dataset label at tick {}: {:?}", tick, label);
+        self.send(Record::Label { tick, time, label });
+    }
+
+    /// Number of records dropped because the writer could not keep up.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Path of the session file being written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn send(&self, record: Record) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        match sender.try_send(record) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+impl Drop for DatasetRecorder {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer drain the queue and exit
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        tracing::info!(
+            "Dataset session closed: {} ({} records dropped)",
+            self.path.display(),
+            self.dropped()
+        );
+    }
+}
+
+/// Background worker that serializes records to the session file.
+fn dataset_worker(mut out: BufWriter<File>, rx: Receiver<Record>) {
+    let mut since_flush = 0;
+
+    for record in rx {
+        let result = serde_json::to_writer(&mut out, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|_| out.write_all(b"\n"));
+
+        if let Err(e) = result {
+            tracing::error!("Dataset write failed, stopping recording: {}", e);
+            return;
+        }
+
+        since_flush += 1;
+        if since_flush >= FLUSH_INTERVAL {
+            let _ = out.flush();
+            since_flush = 0;
+        }
+    }
+
+    let _ = out.flush();
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_time() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_episode_round_trip() {
+        let dir = std::env::temp_dir().join(format!("odr-dataset-{}", std::process::id()));
+        let meta = SessionMeta {
+            control_freq: 50,
+            action_scale: 0.25,
+            joint_names: vec!["left_knee".into(), "right_knee".into()],
+            init_positions: vec![0.5, -0.5],
+            model: "walk.onnx".into(),
+            started_at: 1_700_000_000.5,
+        };
+        let recorder = DatasetRecorder::new(&dir, meta).unwrap();
+        let path = recorder.path().to_path_buf();
+        assert!(path.ends_with("session-1700000000.jsonl"));
+        for tick in 0..3 {
+            let time = tick as f64 * 0.02;
+            let commands = [0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+            recorder.record_frame(tick, time, &[1.0, 2.0], &[0.1, -0.1], &[0.6, -0.6], &commands);
+        }
+        recorder.record_label(2, 0.04, Label::Fall);
+        assert_eq!(recorder.dropped(), 0);
+        // Dropping drains the queue and closes the file
+        drop(recorder);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let records: Vec<Value> =
+            contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(records.len(), 5);
+
+        assert_eq!(records[0]["type"], "meta");
+        assert_eq!(records[0]["control_freq"], 50);
+        assert_eq!(records[0]["joint_names"], json!(["left_knee", "right_knee"]));
+        assert_eq!(records[0]["init_positions"], json!([0.5, -0.5]));
+        assert_eq!(records[0]["model"], "walk.onnx");
+
+        for (tick, frame) in records[1..4].iter().enumerate() {
+            assert_eq!(frame["type"], "frame");
+            assert_eq!(frame["tick"], tick);
+            assert_eq!(frame["time"], tick as f64 * 0.02);
+            assert_eq!(frame["obs"], json!([1.0, 2.0]));
+            assert_eq!(frame["action"], json!([0.1, -0.1]));
+            assert_eq!(frame["motor_targets"], json!([0.6, -0.6]));
+            assert_eq!(frame["commands"], json!([0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]));
+        }
+
+        let label = json!({ "type": "label", "tick": 2, "time": 0.04, "label": "fall" });
+        assert_eq!(records[4], label);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
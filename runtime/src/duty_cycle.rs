@@ -0,0 +1,184 @@
+//! Temperature-aware duty cycling for long demos.
+//!
+//! Sustained walking heats the leg servos faster than they can shed it. When
+//! the average servo temperature crosses a threshold, the duck crouches into a
+//! low-load resting pose with reduced gains, waits at least a fixed time, and
+//! resumes once it has cooled down — so it survives a full exhibition day.
+//! The crouch is blended in from the walking targets, and on resuming the
+//! policy's targets are blended in from the crouch.
+
+use std::time::{Duration, Instant};
+
+use crate::config::DutyCycleConfig;
+
+/// Time taken to blend into or out of the resting pose.
+const POSE_BLEND_DURATION: Duration = Duration::from_secs(1);
+
+/// State change requested by the duty cycler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DutyEvent {
+    /// Start resting: crouch and lower the gains.
+    StartRest,
+    /// Cooled down: restore gains and blend control back to the policy.
+    Resume,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Active { resumed: Option<Instant> },
+    Resting { since: Instant },
+}
+
+/// Duty-cycle state machine driven by servo temperatures.
+pub struct DutyCycler {
+    config: DutyCycleConfig,
+    state: State,
+}
+
+impl DutyCycler {
+    pub fn new(config: DutyCycleConfig) -> Self {
+        tracing::info!(
+            "Duty cycling enabled: rest at {:.0}°C, resume below {:.0}°C, rest >= {:.0}s",
+            config.rest_temperature,
+            config.resume_temperature,
+            config.rest_duration
+        );
+        Self {
+            config,
+            state: State::Active { resumed: None },
+        }
+    }
+
+    /// Feed fresh temperature readings; returns a state change if one is due.
+    pub fn update(&mut self, temperatures: &[f64], now: Instant) -> Option<DutyEvent> {
+        if temperatures.is_empty() {
+            return None;
+        }
+
+        let average = temperatures.iter().sum::<f64>() / temperatures.len() as f64;
+
+        match self.state {
+            State::Active { .. } if average >= self.config.rest_temperature => {
+                tracing::warn!(
+                    "Average servo temperature {:.1}°C >= {:.1}°C, resting",
+                    average,
+                    self.config.rest_temperature
+                );
+                self.state = State::Resting { since: now };
+                Some(DutyEvent::StartRest)
+            }
+            State::Resting { since }
+                if now.duration_since(since).as_secs_f64() >= self.config.rest_duration
+                    && average < self.config.resume_temperature =>
+            {
+                tracing::info!(
+                    "Average servo temperature {:.1}°C, resuming after {:.0}s rest",
+                    average,
+                    now.duration_since(since).as_secs_f64()
+                );
+                self.state = State::Active { resumed: Some(now) };
+                Some(DutyEvent::Resume)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_resting(&self) -> bool {
+        matches!(self.state, State::Resting { .. })
+    }
+
+    /// Blend factor (0..1) from the pre-rest targets to the resting pose.
+    pub fn rest_blend(&self, now: Instant) -> f64 {
+        match self.state {
+            State::Resting { since } => {
+                (now.duration_since(since).as_secs_f64() / POSE_BLEND_DURATION.as_secs_f64())
+                    .min(1.0)
+            }
+            State::Active { .. } => 0.0,
+        }
+    }
+
+    /// Blend factor (0..1) from the resting pose to the policy's targets
+    /// just after resuming, None once the policy has full control.
+    pub fn resume_blend(&self, now: Instant) -> Option<f64> {
+        match self.state {
+            State::Active { resumed: Some(at) } => {
+                let blend =
+                    now.duration_since(at).as_secs_f64() / POSE_BLEND_DURATION.as_secs_f64();
+                (blend < 1.0).then_some(blend)
+            }
+            _ => None,
+        }
+    }
+
+    /// KP applied to all joints while resting.
+    pub fn rest_kp(&self) -> f64 {
+        self.config.rest_kp
+    }
+
+    /// The resting crouch: the init pose plus the configured per-joint offsets.
+    pub fn rest_pose(&self, init_pos: &[f64], joint_names: &[String]) -> Vec<f64> {
+        init_pos
+            .iter()
+            .zip(joint_names)
+            .map(|(&init, name)| {
+                init + self
+                    .config
+                    .rest_pose_offsets
+                    .get(name)
+                    .copied()
+                    .unwrap_or(0.0)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycler() -> DutyCycler {
+        DutyCycler::new(DutyCycleConfig {
+            enabled: true,
+            rest_duration: 10.0,
+            ..DutyCycleConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_rests_when_hot_and_resumes_when_cool() {
+        let mut duty = cycler();
+        let t0 = Instant::now();
+
+        assert_eq!(duty.update(&[40.0, 50.0], t0), None);
+        assert_eq!(duty.update(&[55.0, 57.0], t0), Some(DutyEvent::StartRest));
+        assert!(duty.is_resting());
+
+        // Cool but the minimum rest time has not elapsed
+        assert_eq!(duty.update(&[30.0], t0 + Duration::from_secs(5)), None);
+
+        // Rest time elapsed but still warm
+        assert_eq!(duty.update(&[50.0], t0 + Duration::from_secs(11)), None);
+
+        assert_eq!(
+            duty.update(&[40.0], t0 + Duration::from_secs(12)),
+            Some(DutyEvent::Resume)
+        );
+        assert!(!duty.is_resting());
+
+        // The policy's targets are blended back in
+        let resumed = t0 + Duration::from_secs(12);
+        assert_eq!(duty.resume_blend(resumed), Some(0.0));
+        assert_eq!(duty.resume_blend(resumed + Duration::from_millis(500)), Some(0.5));
+        assert_eq!(duty.resume_blend(resumed + POSE_BLEND_DURATION), None);
+    }
+
+    #[test]
+    fn test_rest_pose_applies_offsets() {
+        let duty = cycler();
+        let names = vec!["left_knee".to_string(), "head_yaw".to_string()];
+        let pose = duty.rest_pose(&[1.0, 0.1], &names);
+        assert!((pose[0] - 1.4).abs() < 1e-12);
+        assert_eq!(pose[1], 0.1);
+    }
+}
@@ -0,0 +1,769 @@
+//! Dynamixel X-series servo control (XL330, XL430) over Protocol 2.0.
+//!
+//! The alternative to the Feetech STS3215 bus for Open Duck builds using
+//! Dynamixels, selected with `"servo_type": "dynamixel"` in the duck config.
+//! `DynamixelController` implements the same `MotorInterface` as the Feetech
+//! `MotorController`; what differs is the wire protocol and the control
+//! table:
+//!
+//! - packets start with `FF FF FD 00`, carry 16-bit lengths and register
+//!   addresses, and end with a CRC16 instead of a one-byte checksum
+//! - any `FF FF FD` inside a packet body is byte-stuffed to `FF FF FD FD`
+//! - goal/present positions and velocities are 4 bytes wide
+//! - the status error byte holds an error number plus an alert bit; the
+//!   hardware error itself (overload, overheat, ...) sits in its own register
+//!
+//! Servos must be set to 1 Mbps and position control mode, with the IDs of
+//! the robot model's joint map. Gains are written as-is to the Position
+//! P/I/D Gain registers, which use a different scale from the Feetech ones.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::audit;
+use crate::bus_errors::{BusErrorTracker, BusStats, Escalation};
+use crate::config::{DuckConfig, TorqueRampConfig};
+use crate::joint_limits::{JointLimits, LimitCheck};
+use crate::motors::{
+    collect_status, cubic_ease, open_port, ramp_positions, ramp_steps, ramp_torque_on, rad_to_raw,
+    seek_header, KillPort, MotorInterface, ParsedStatus, ServoHealth, ServoReadError,
+    TorqueKillSwitch, GAIN_FADE_DURATION, RAMP_DURATION, RAMP_PERIOD,
+};
+use crate::servo_errors::{ServoError, ServoErrorReport};
+
+// Protocol 2.0 constants
+const HEADER: [u8; 4] = [0xFF, 0xFF, 0xFD, 0x00];
+const INST_READ: u8 = 0x02;
+const INST_WRITE: u8 = 0x03;
+const INST_SYNC_READ: u8 = 0x82;
+const INST_SYNC_WRITE: u8 = 0x83;
+const INST_STATUS: u8 = 0x55;
+const BROADCAST_ID: u8 = 0xFE;
+
+/// Status error byte: set when the hardware error register is non-zero.
+const ALERT: u8 = 0x80;
+
+/// Longest status packet accepted; longer lengths are line noise.
+const MAX_PACKET_LENGTH: usize = 512;
+
+// Register addresses for the X-series control table
+const ADDR_TORQUE_ENABLE: u16 = 64;
+const ADDR_HARDWARE_ERROR: u16 = 70;
+const ADDR_D_GAIN: u16 = 80;
+const ADDR_I_GAIN: u16 = 82;
+const ADDR_P_GAIN: u16 = 84;
+const ADDR_GOAL_POSITION: u16 = 116;
+const ADDR_PRESENT_LOAD: u16 = 126; // load/current(2) ... voltage(2) at 144, temperature(1) at 146
+const ADDR_PRESENT_VELOCITY: u16 = 128;
+const ADDR_PRESENT_POSITION: u16 = 132;
+
+/// Bytes from `ADDR_PRESENT_LOAD` through the present temperature.
+const HEALTH_LEN: u16 = 21;
+
+/// Hardware error register bits.
+const HW_INPUT_VOLTAGE: u8 = 0x01;
+const HW_OVERHEATING: u8 = 0x04;
+const HW_ELECTRICAL_SHOCK: u8 = 0x10;
+const HW_OVERLOAD: u8 = 0x20;
+
+/// Hardware interface for Dynamixel X-series bus servos.
+pub struct DynamixelController {
+    port: Box<dyn serialport::SerialPort>,
+    port_path: String,
+    joint_ids: Vec<u8>,
+    joint_names: Vec<String>,
+    offsets: HashMap<String, f64>,
+    limits: JointLimits,
+    init_pos: HashMap<String, f64>,
+    kps: Vec<f64>,
+    torque_ramp: TorqueRampConfig,
+    retries: u32,
+    /// How long one read waits for all status packets.
+    status_timeout: Duration,
+    errors: BusErrorTracker,
+    bus_fault: Option<String>,
+    /// Port handed to the torque kill switch, refreshed on reopen.
+    kill_port: KillPort,
+    /// Error bytes seen since the last `take_servo_errors`.
+    servo_errors: Vec<ServoErrorReport>,
+    /// Hardware error register of each joint, from the last health poll.
+    hardware_errors: Vec<u8>,
+    /// Torque was cut: goal writes are dropped until `turn_on`.
+    torque_cut: bool,
+}
+
+impl DynamixelController {
+    /// Drive the named joints (in robot joint order).
+    pub fn with_joints(config: &DuckConfig, serial_port: &str, names: &[String]) -> Result<Self> {
+        let (joint_names, joint_ids) = config.robot_model.select(names)?;
+
+        let port = open_port(serial_port)?;
+
+        Ok(Self {
+            port,
+            port_path: serial_port.to_string(),
+            limits: JointLimits::new(&joint_names, &config.resolved_joint_limits()),
+            kps: vec![0.0; joint_ids.len()],
+            hardware_errors: vec![0; joint_ids.len()],
+            joint_ids,
+            joint_names,
+            offsets: config.joints_offset.clone(),
+            init_pos: config.robot_model.init_positions(),
+            torque_ramp: config.torque_ramp.clone(),
+            retries: config.serial.retries,
+            status_timeout: Duration::from_secs_f64(config.serial.status_timeout_ms / 1000.0),
+            errors: BusErrorTracker::new(config.serial.clone()),
+            bus_fault: None,
+            kill_port: KillPort::default(),
+            servo_errors: Vec::new(),
+            torque_cut: false,
+        })
+    }
+
+    fn offset(&self, joint: usize) -> f64 {
+        self.offsets.get(&self.joint_names[joint]).copied().unwrap_or(0.0)
+    }
+
+    /// Write one 2-byte gain register on every joint.
+    fn write_gains(&mut self, addr: u16, gains: &[f64]) -> Result<()> {
+        let ids = self.joint_ids.clone();
+        for (&id, &gain) in ids.iter().zip(gains) {
+            let value = gain.clamp(0.0, u16::MAX as f64) as u16;
+            self.write_register(id, addr, &value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Switch torque on at every joint.
+    fn write_torque_on(&mut self) -> Result<()> {
+        let ids = self.joint_ids.clone();
+        for &id in &ids {
+            self.write_register(id, ADDR_TORQUE_ENABLE, &[1])?;
+        }
+        Ok(())
+    }
+
+    /// Enable torque at a low KP and ramp KP up to the configured gains.
+    fn enable_torque(&mut self) -> Result<()> {
+        let (kps, ramp) = (self.kps.clone(), self.torque_ramp.clone());
+        ramp_torque_on(
+            self,
+            &kps,
+            &ramp,
+            |bus, kps| bus.write_gains(ADDR_P_GAIN, kps),
+            Self::write_torque_on,
+        )?;
+        tracing::info!("Dynamixel: torque enabled, KP ramped up");
+        Ok(())
+    }
+
+    /// Sync-read a 4-byte register from all joints.
+    fn sync_read_i32(&mut self, addr: u16) -> Result<Vec<i32>> {
+        let ids = self.joint_ids.clone();
+        let results = self.sync_read(&ids, addr, 4)?;
+
+        let mut values = Vec::with_capacity(ids.len());
+        let mut failures = Vec::new();
+        for (&id, result) in ids.iter().zip(results) {
+            match result {
+                Ok(data) => values.push(i32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+                Err(e) => failures.push(format!("{}: {}", id, e)),
+            }
+        }
+
+        if !failures.is_empty() {
+            bail!("Servo read failed ({})", failures.join(", "));
+        }
+        Ok(values)
+    }
+}
+
+impl MotorInterface for DynamixelController {
+    fn init_positions_array(&self) -> Vec<f64> {
+        self.joint_names
+            .iter()
+            .map(|name| self.init_pos.get(name).copied().unwrap_or(0.0))
+            .collect()
+    }
+
+    fn joint_names(&self) -> &[String] {
+        &self.joint_names
+    }
+
+    fn set_kps(&mut self, kps: &[f64]) -> Result<()> {
+        self.kps = kps.to_vec();
+        self.write_gains(ADDR_P_GAIN, kps)
+    }
+
+    fn set_kds(&mut self, kds: &[f64]) -> Result<()> {
+        self.write_gains(ADDR_D_GAIN, kds)
+    }
+
+    fn set_kis(&mut self, kis: &[f64]) -> Result<()> {
+        self.write_gains(ADDR_I_GAIN, kis)
+    }
+
+    /// Same startup as the Feetech bus: hold the present pose, ramp KP up,
+    /// then follow a smooth trajectory to the init pose.
+    fn turn_on(&mut self) -> Result<()> {
+        self.torque_cut = false;
+        let Some(present) = self.get_present_positions() else {
+            tracing::warn!("Dynamixel: could not read positions, falling back to low-KP startup");
+            self.set_position_all_array(&self.init_positions_array())?;
+            return self.enable_torque();
+        };
+
+        self.set_position_all_array(&present)?;
+        self.enable_torque()?;
+        let init = self.init_positions_array();
+        ramp_positions(&present, &init, RAMP_DURATION, |t| self.set_position_all_array(t))?;
+        tracing::info!("Dynamixel: init position reached");
+        Ok(())
+    }
+
+    fn turn_off(&mut self) -> Result<()> {
+        if let Some(present) = self.get_present_positions() {
+            let init = self.init_positions_array();
+            ramp_positions(&present, &init, RAMP_DURATION, |t| self.set_position_all_array(t))?;
+
+            let full_kps = self.kps.clone();
+            let steps = ramp_steps(GAIN_FADE_DURATION);
+            for k in 1..=steps {
+                let scale = 1.0 - cubic_ease(k as f64 / steps as f64);
+                let kps: Vec<f64> = full_kps.iter().map(|kp| kp * scale).collect();
+                self.write_gains(ADDR_P_GAIN, &kps)?;
+                std::thread::sleep(RAMP_PERIOD);
+            }
+        } else {
+            tracing::warn!("Dynamixel: could not read positions, disabling torque without ramp");
+        }
+
+        let ids = self.joint_ids.clone();
+        for &id in &ids {
+            self.write_register(id, ADDR_TORQUE_ENABLE, &[0])?;
+        }
+        tracing::info!("Dynamixel: torque disabled");
+        Ok(())
+    }
+
+    fn disable_torque_now(&mut self) -> Result<()> {
+        let written = self.send(&write_packet(BROADCAST_ID, ADDR_TORQUE_ENABLE, &[0]));
+        self.record_transaction(written.is_ok());
+        written?;
+        self.torque_cut = true;
+        tracing::warn!("Dynamixel: torque cut");
+        Ok(())
+    }
+
+    fn torque_enabled(&mut self) -> Result<bool> {
+        let ids = self.joint_ids.clone();
+        for &id in &ids {
+            if self.read_register(id, ADDR_TORQUE_ENABLE, 1)?[0] == 0 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn set_position_all(&mut self, positions: &HashMap<String, f64>) -> Result<()> {
+        let ordered: Vec<Option<f64>> = self
+            .joint_names
+            .iter()
+            .map(|name| positions.get(name).copied())
+            .collect();
+        self.write_goals(&ordered)
+    }
+
+    fn set_position_all_array(&mut self, positions: &[f64]) -> Result<()> {
+        let ordered: Vec<Option<f64>> = positions.iter().copied().map(Some).collect();
+        self.write_goals(&ordered)
+    }
+
+    /// Read present positions of all joints (radians), minus offsets.
+    fn get_present_positions(&mut self) -> Option<Vec<f64>> {
+        match self.sync_read_i32(ADDR_PRESENT_POSITION) {
+            Ok(raw) => Some(
+                raw.iter()
+                    .enumerate()
+                    .map(|(i, &r)| position_to_rad(r) - self.offset(i))
+                    .collect(),
+            ),
+            Err(e) => {
+                tracing::warn!("Failed to read positions: {}", e);
+                None
+            }
+        }
+    }
+
+    fn get_present_velocities(&mut self) -> Option<Vec<f64>> {
+        match self.sync_read_i32(ADDR_PRESENT_VELOCITY) {
+            Ok(raw) => Some(raw.into_iter().map(velocity_to_rad_per_sec).collect()),
+            Err(e) => {
+                tracing::warn!("Failed to read velocities: {}", e);
+                None
+            }
+        }
+    }
+
+    fn get_present_loads(&mut self) -> Option<Vec<f64>> {
+        let ids = self.joint_ids.clone();
+        let results = match self.sync_read(&ids, ADDR_PRESENT_LOAD, 2) {
+            Ok(results) => results,
+            Err(e) => {
+                tracing::warn!("Failed to read loads: {}", e);
+                return None;
+            }
+        };
+        results
+            .into_iter()
+            .zip(&ids)
+            .map(|(result, &id)| match result {
+                Ok(data) => Some(decode_load(&data)),
+                Err(e) => {
+                    tracing::warn!("Failed to read load of servo {}: {}", id, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn get_servo_health(&mut self) -> Option<Vec<ServoHealth>> {
+        let ids = self.joint_ids.clone();
+        let read = self.sync_read(&ids, ADDR_HARDWARE_ERROR, 1).and_then(|hardware| {
+            Ok((hardware, self.sync_read(&ids, ADDR_PRESENT_LOAD, HEALTH_LEN)?))
+        });
+        let (hardware, health) = match read {
+            Ok(results) => results,
+            Err(e) => {
+                tracing::warn!("Failed to read servo health: {}", e);
+                return None;
+            }
+        };
+
+        let mut servos = Vec::with_capacity(ids.len());
+        for (joint, ((&id, hardware), health)) in ids.iter().zip(hardware).zip(health).enumerate() {
+            match (hardware, health) {
+                (Ok(hardware), Ok(data)) => {
+                    self.hardware_errors[joint] = hardware[0];
+                    servos.push(decode_servo_health(id, &data, hardware[0]));
+                }
+                (Err(e), _) | (_, Err(e)) => {
+                    tracing::warn!("Failed to read health of servo {}: {}", id, e);
+                    return None;
+                }
+            }
+        }
+        Some(servos)
+    }
+
+    fn joint_limits(&self) -> Option<&JointLimits> {
+        Some(&self.limits)
+    }
+
+    fn torque_kill_switch(&self) -> Option<TorqueKillSwitch> {
+        self.kill_port.refresh(&*self.port).then(|| {
+            TorqueKillSwitch::new(
+                self.kill_port.clone(),
+                write_packet(BROADCAST_ID, ADDR_TORQUE_ENABLE, &[0]),
+            )
+        })
+    }
+
+    fn bus_stats(&self) -> Option<BusStats> {
+        Some(self.errors.stats())
+    }
+
+    fn take_bus_fault(&mut self) -> Option<String> {
+        self.bus_fault.take()
+    }
+
+    fn take_servo_errors(&mut self) -> Vec<ServoErrorReport> {
+        std::mem::take(&mut self.servo_errors)
+    }
+
+    fn disable_joint_torque(&mut self, joint: usize) -> Result<()> {
+        let id = self.joint_ids[joint];
+        self.write_register(id, ADDR_TORQUE_ENABLE, &[0])
+    }
+
+    fn set_joint_offsets(&mut self, offsets: &HashMap<String, f64>) -> Result<()> {
+        self.offsets = offsets.clone();
+        Ok(())
+    }
+}
+
+impl DynamixelController {
+    // ── Low-level protocol ──
+
+    /// Sync-write goal positions, skipping joints without a target or whose
+    /// target the joint limits reject.
+    fn write_goals(&mut self, positions: &[Option<f64>]) -> Result<()> {
+        if self.torque_cut {
+            return Ok(());
+        }
+        let mut entries = Vec::with_capacity(self.joint_ids.len());
+        for (i, pos) in positions.iter().enumerate().take(self.joint_ids.len()) {
+            let Some(pos) = *pos else { continue };
+            if let LimitCheck::Write(pos) = self.limits.check(i, pos) {
+                let raw = rad_to_raw(pos + self.offset(i)) as i32;
+                entries.push((self.joint_ids[i], raw.to_le_bytes()));
+            }
+        }
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let mut params = Vec::with_capacity(4 + entries.len() * 5);
+        params.extend_from_slice(&ADDR_GOAL_POSITION.to_le_bytes());
+        params.extend_from_slice(&4u16.to_le_bytes());
+        for (id, data) in &entries {
+            params.push(*id);
+            params.extend_from_slice(data);
+        }
+        let packet = instruction_packet(BROADCAST_ID, INST_SYNC_WRITE, &params);
+        let written = self.send(&packet);
+        self.record_transaction(written.is_ok());
+        written
+    }
+
+    fn send(&mut self, packet: &[u8]) -> Result<()> {
+        let _span = audit::span("serial_write");
+        self.port.write_all(packet).context("Serial write failed")?;
+        self.port.flush().context("Serial flush failed")
+    }
+
+    /// Write a register and wait for the servo's status reply.
+    fn write_register(&mut self, id: u8, addr: u16, data: &[u8]) -> Result<()> {
+        let _ = self.port.clear(serialport::ClearBuffer::Input);
+        self.send(&write_packet(id, addr, data))?;
+        let result = self.read_status(&[id], 0).pop().expect("one result per requested id");
+        self.record_transaction(result.is_ok());
+        result
+            .map(|_| ())
+            .with_context(|| format!("Failed to write register {} of servo {}", addr, id))
+    }
+
+    fn read_register(&mut self, id: u8, addr: u16, len: u16) -> Result<Vec<u8>> {
+        let _ = self.port.clear(serialport::ClearBuffer::Input);
+        let mut params = addr.to_le_bytes().to_vec();
+        params.extend_from_slice(&len.to_le_bytes());
+        self.send(&instruction_packet(id, INST_READ, &params))?;
+        let result = self.read_status(&[id], len).pop().expect("one result per requested id");
+        self.record_transaction(result.is_ok());
+        result.with_context(|| format!("Failed to read register {} of servo {}", addr, id))
+    }
+
+    /// Read `len` bytes at `addr` from every servo in `ids`, retrying the
+    /// servos that didn't answer up to `retries` times.
+    fn sync_read(
+        &mut self,
+        ids: &[u8],
+        addr: u16,
+        len: u16,
+    ) -> Result<Vec<Result<Vec<u8>, ServoReadError>>> {
+        let mut results = match self.sync_read_once(ids, addr, len) {
+            Ok(results) => results,
+            Err(e) => {
+                self.record_transaction(false);
+                return Err(e);
+            }
+        };
+        for _ in 0..self.retries {
+            let failed: Vec<usize> = (0..ids.len()).filter(|&i| results[i].is_err()).collect();
+            if failed.is_empty() {
+                break;
+            }
+            self.errors.retried();
+            let failed_ids: Vec<u8> = failed.iter().map(|&i| ids[i]).collect();
+            let Ok(retried) = self.sync_read_once(&failed_ids, addr, len) else {
+                break;
+            };
+            for (i, result) in failed.into_iter().zip(retried) {
+                results[i] = result;
+            }
+        }
+        self.record_transaction(results.iter().all(Result::is_ok));
+        Ok(results)
+    }
+
+    fn sync_read_once(
+        &mut self,
+        ids: &[u8],
+        addr: u16,
+        len: u16,
+    ) -> Result<Vec<Result<Vec<u8>, ServoReadError>>> {
+        let mut params = Vec::with_capacity(4 + ids.len());
+        params.extend_from_slice(&addr.to_le_bytes());
+        params.extend_from_slice(&len.to_le_bytes());
+        params.extend_from_slice(ids);
+
+        // Discard stale bytes so they can't be mistaken for this reply
+        let _ = self.port.clear(serialport::ClearBuffer::Input);
+        self.send(&instruction_packet(BROADCAST_ID, INST_SYNC_READ, &params))?;
+        Ok(self.read_status(ids, len))
+    }
+
+    /// Collect status packets from `ids`, reporting the errors they carry
+    /// along with each servo's last known hardware error register.
+    fn read_status(&mut self, ids: &[u8], len: u16) -> Vec<Result<Vec<u8>, ServoReadError>> {
+        let (joint_ids, servo_errors) = (&self.joint_ids, &mut self.servo_errors);
+        let hardware_errors = &self.hardware_errors;
+        let timeout = self.status_timeout;
+        collect_status(
+            &mut *self.port,
+            ids,
+            len.into(),
+            timeout,
+            parse_status_packet,
+            |id, error| {
+                if let Some(joint) = joint_ids.iter().position(|&i| i == id) {
+                    let flags = status_error_flags(error, hardware_errors[joint]);
+                    if flags != 0 {
+                        servo_errors.push(ServoErrorReport { joint, id, flags });
+                    }
+                }
+            },
+        )
+    }
+
+    /// Feed the error rate and act on its escalation.
+    fn record_transaction(&mut self, ok: bool) {
+        match self.errors.record(ok) {
+            None => {}
+            Some(Escalation::Reopen) => {
+                tracing::warn!(
+                    "Servo bus error rate at {:.0}%, reopening {}",
+                    self.errors.stats().error_rate * 100.0,
+                    self.port_path
+                );
+                match open_port(&self.port_path) {
+                    Ok(port) => {
+                        self.port = port;
+                        self.kill_port.refresh(&*self.port);
+                    }
+                    Err(e) => tracing::error!("{:#}", e),
+                }
+            }
+            Some(Escalation::SoftStop) => {
+                self.bus_fault = Some(format!(
+                    "servo bus error rate at {:.0}% after reopening the port",
+                    self.errors.stats().error_rate * 100.0
+                ));
+            }
+        }
+    }
+}
+
+// ── Packets ──
+
+/// CRC-16 used by Protocol 2.0 (polynomial 0x8005, initial value 0).
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Insert an extra 0xFD after every `FF FF FD` so the body can't be
+/// mistaken for a header.
+fn stuff(body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len());
+    for &byte in body {
+        out.push(byte);
+        if out.ends_with(&HEADER[..3]) {
+            out.push(0xFD);
+        }
+    }
+    out
+}
+
+/// Undo `stuff`.
+fn unstuff(body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len());
+    let mut bytes = body.iter().copied().peekable();
+    while let Some(byte) = bytes.next() {
+        out.push(byte);
+        if out.ends_with(&HEADER[..3]) && bytes.peek() == Some(&0xFD) {
+            bytes.next();
+        }
+    }
+    out
+}
+
+/// Build an instruction packet `[FF FF FD 00, id, len(2), inst, params..., crc(2)]`.
+fn instruction_packet(id: u8, instruction: u8, params: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(1 + params.len());
+    body.push(instruction);
+    body.extend_from_slice(params);
+    let body = stuff(&body);
+
+    let length = (body.len() + 2) as u16; // body + crc
+    let mut packet = Vec::with_capacity(7 + body.len() + 2);
+    packet.extend_from_slice(&HEADER);
+    packet.push(id);
+    packet.extend_from_slice(&length.to_le_bytes());
+    packet.extend_from_slice(&body);
+    let crc = crc16(&packet);
+    packet.extend_from_slice(&crc.to_le_bytes());
+    packet
+}
+
+/// Build a WRITE instruction packet.
+fn write_packet(id: u8, addr: u16, data: &[u8]) -> Vec<u8> {
+    let mut params = addr.to_le_bytes().to_vec();
+    params.extend_from_slice(data);
+    instruction_packet(id, INST_WRITE, &params)
+}
+
+/// Parse a status packet `[FF FF FD 00, id, len(2), 0x55, err, params..., crc(2)]`
+/// from the start of `buf`, resynchronizing on the header if needed.
+fn parse_status_packet(buf: &[u8]) -> ParsedStatus {
+    if let Some(skip) = seek_header(buf, &HEADER) {
+        return skip;
+    }
+
+    if buf.len() < 7 {
+        return ParsedStatus::Incomplete;
+    }
+    let id = buf[4];
+    let length = u16::from_le_bytes([buf[5], buf[6]]) as usize; // inst + error + params + crc
+    if !(4..=MAX_PACKET_LENGTH).contains(&length) {
+        return ParsedStatus::Skip(HEADER.len());
+    }
+
+    let total = 7 + length;
+    if buf.len() < total {
+        return ParsedStatus::Incomplete;
+    }
+    // A failed CRC means the length can't be trusted either: resync on the next header
+    let crc = u16::from_le_bytes([buf[total - 2], buf[total - 1]]);
+    if crc16(&buf[..total - 2]) != crc {
+        return ParsedStatus::Skip(1);
+    }
+    if buf[7] != INST_STATUS {
+        // Not a reply, e.g. an instruction echoed by the adapter
+        return ParsedStatus::Skip(total);
+    }
+
+    ParsedStatus::Packet {
+        id,
+        error: buf[8],
+        params: unstuff(&buf[9..total - 2]),
+        len: total,
+    }
+}
+
+/// Translate a status error byte, plus the servo's last known hardware error
+/// register, into the status bits `ServoError` decodes.
+fn status_error_flags(error: u8, hardware: u8) -> u8 {
+    let mut errors = Vec::new();
+    match error & !ALERT {
+        0 => {}
+        // CRC error
+        3 => errors.push(ServoError::Checksum),
+        // Data range and data limit errors, e.g. a goal past the limits
+        4 | 6 => errors.push(ServoError::AngleLimit),
+        // Result fail, instruction, data length and access errors
+        _ => errors.push(ServoError::Instruction),
+    }
+    if error & ALERT != 0 {
+        errors.extend(hardware_errors(hardware));
+    }
+    errors.into_iter().fold(0, |flags, e| flags | e.bit())
+}
+
+/// Errors set in the hardware error register.
+fn hardware_errors(hardware: u8) -> Vec<ServoError> {
+    [
+        (HW_INPUT_VOLTAGE, ServoError::InputVoltage),
+        (HW_OVERHEATING, ServoError::Overheat),
+        (HW_ELECTRICAL_SHOCK, ServoError::Overcurrent),
+        (HW_OVERLOAD, ServoError::Overload),
+    ]
+    .into_iter()
+    .filter(|(bit, _)| hardware & bit != 0)
+    .map(|(_, error)| error)
+    .collect()
+}
+
+/// Convert a raw position (0-4095 per turn, center at 2048) to radians.
+fn position_to_rad(raw: i32) -> f64 {
+    ((raw as f64 - 2048.0) / 4096.0 * 360.0).to_radians()
+}
+
+/// Convert a raw velocity (0.229 RPM per step) to rad/s.
+fn velocity_to_rad_per_sec(raw: i32) -> f64 {
+    raw as f64 * 0.229 * std::f64::consts::PI / 30.0
+}
+
+/// Decode the present load register.
+fn decode_load(data: &[u8]) -> f64 {
+    // 0.1% of max torque on the XL430; the XL330 reports current (mA) here,
+    // which reads as a rough load fraction on the same scale
+    (i16::from_le_bytes([data[0], data[1]]) as f64 / 1000.0).clamp(-1.0, 1.0)
+}
+
+/// Decode the present load..temperature register block (addresses 126-146).
+fn decode_servo_health(id: u8, data: &[u8], hardware: u8) -> ServoHealth {
+    ServoHealth {
+        id,
+        temperature: data[20] as f64,
+        voltage: u16::from_le_bytes([data[18], data[19]]) as f64 / 10.0,
+        load: decode_load(data),
+        error_flags: hardware_errors(hardware).into_iter().fold(0, |flags, e| flags | e.bit()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc16_and_packets() {
+        assert_eq!(crc16(b"123456789"), 0xFEE8);
+        // Read present position (4 bytes) of ID 1, from the Protocol 2.0 manual
+        assert_eq!(
+            instruction_packet(1, INST_READ, &[0x84, 0x00, 0x04, 0x00]),
+            vec![0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x07, 0x00, 0x02, 0x84, 0x00, 0x04, 0x00, 0x1D, 0x15]
+        );
+
+        // A header-like sequence in the body is stuffed, and unstuffed on the way back
+        let body = [0x10, 0xFF, 0xFF, 0xFD, 0x20];
+        assert_eq!(stuff(&body), vec![0x10, 0xFF, 0xFF, 0xFD, 0xFD, 0x20]);
+        assert_eq!(unstuff(&stuff(&body)), body.to_vec());
+    }
+
+    #[test]
+    fn test_parse_status_packet() {
+        // Noise, then ID 1 answering with position 1024 and the alert bit set
+        let mut reply = vec![0x00, 0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x08, 0x00, INST_STATUS, ALERT];
+        reply.extend_from_slice(&1024i32.to_le_bytes());
+        let crc = crc16(&reply[1..]);
+        reply.extend_from_slice(&crc.to_le_bytes());
+
+        assert_eq!(parse_status_packet(&reply), ParsedStatus::Skip(1));
+        assert_eq!(parse_status_packet(&reply[1..reply.len() - 1]), ParsedStatus::Incomplete);
+        let ParsedStatus::Packet { id, error, params, len } = parse_status_packet(&reply[1..])
+        else {
+            panic!("expected a packet");
+        };
+        assert_eq!((id, error, len), (1, ALERT, reply.len() - 1));
+        let position = i32::from_le_bytes(params[..4].try_into().unwrap());
+        assert_eq!(position_to_rad(position), -std::f64::consts::FRAC_PI_2);
+
+        assert_eq!(
+            status_error_flags(ALERT, HW_OVERLOAD | HW_OVERHEATING),
+            ServoError::Overheat.bit() | ServoError::Overload.bit()
+        );
+        assert_eq!(status_error_flags(3, 0), ServoError::Checksum.bit());
+    }
+}
@@ -0,0 +1,301 @@
+//! Recorded emotes: poses captured by hand and played back as keyframes.
+//!
+//! In capture mode (`--capture-emote NAME`) the servos stay limp and the duck
+//! is posed by hand. A (Space on the keyboard) stores the current joint
+//! positions as the next keyframe, B (M) drops the last one, and Start (Tab)
+//! saves the emote and exits. Each emote is a JSON file in `emotes.dir`:
+//!
+//! ```json
+//! {
+//!     "name": "wave",
+//!     "joint_names": ["left_hip_yaw", "..."],
+//!     "keyframes": [
+//!         { "duration": 0.8, "positions": [0.0, "..."] }
+//!     ]
+//! }
+//! ```
+//!
+//! `duration` is the time to move into the keyframe from the previous one,
+//! so timings can be tuned by editing the file. Emotes are requested with an
+//! `EmoteRequested` event: from the gamepad's D-pad left/right, an `emote`
+//! mission step, or the remote `play_emote` command. `EmotePlayer` eases from
+//! the current targets through each keyframe and back again, overriding the
+//! policy's targets while it plays.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::motors::cubic_interpolate;
+
+/// One captured pose.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Seconds to move here from the previous pose.
+    pub duration: f64,
+    /// Joint positions (rad), in the order of the emote's `joint_names`.
+    pub positions: Vec<f64>,
+}
+
+/// A named sequence of keyframes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Emote {
+    pub name: String,
+    pub joint_names: Vec<String>,
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Emote {
+    /// Seconds from the first keyframe's start to the last keyframe.
+    pub fn duration(&self) -> f64 {
+        self.keyframes.iter().map(|k| k.duration).sum()
+    }
+
+    /// Keyframe poses in the order of `joint_names`. Joints the emote does
+    /// not cover keep their `hold` position.
+    fn poses_for(&self, joint_names: &[String], hold: &[f64]) -> Vec<Vec<f64>> {
+        let columns: Vec<Option<usize>> = joint_names
+            .iter()
+            .map(|name| self.joint_names.iter().position(|n| n == name))
+            .collect();
+        self.keyframes
+            .iter()
+            .map(|keyframe| {
+                columns
+                    .iter()
+                    .zip(hold)
+                    .map(|(column, &held)| {
+                        column
+                            .and_then(|c| keyframe.positions.get(c))
+                            .copied()
+                            .unwrap_or(held)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Directory of saved emotes.
+pub struct EmoteLibrary {
+    dir: PathBuf,
+}
+
+impl EmoteLibrary {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            bail!("Invalid emote name {:?} (use letters, digits, '_' and '-')", name);
+        }
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+
+    /// Names of the saved emotes, sorted.
+    pub fn names(&self) -> Result<Vec<String>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    names.push(stem.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn load(&self, name: &str) -> Result<Emote> {
+        let path = self.path(name)?;
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read emote {}", path.display()))?;
+        let emote: Emote = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid emote {}", path.display()))?;
+        for keyframe in &emote.keyframes {
+            if keyframe.positions.len() != emote.joint_names.len() {
+                bail!(
+                    "Emote {} has a keyframe with {} positions for {} joints",
+                    name,
+                    keyframe.positions.len(),
+                    emote.joint_names.len()
+                );
+            }
+        }
+        Ok(emote)
+    }
+
+    /// Write the emote atomically; returns its path.
+    pub fn save(&self, emote: &Emote) -> Result<PathBuf> {
+        let path = self.path(&emote.name)?;
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+
+        let tmp = path.with_extension("json.tmp");
+        let mut file = fs::File::create(&tmp)
+            .with_context(|| format!("Failed to create {}", tmp.display()))?;
+        file.write_all(&serde_json::to_vec_pretty(emote)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path).with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Keyframes being captured by hand.
+pub struct EmoteCapture {
+    name: String,
+    joint_names: Vec<String>,
+    keyframe_duration: f64,
+    keyframes: Vec<Keyframe>,
+}
+
+impl EmoteCapture {
+    pub fn new(name: &str, joint_names: Vec<String>, keyframe_duration: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            joint_names,
+            keyframe_duration,
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Store the current pose as the next keyframe.
+    pub fn capture(&mut self, positions: Vec<f64>) {
+        self.keyframes.push(Keyframe {
+            duration: self.keyframe_duration,
+            positions,
+        });
+    }
+
+    /// Drop the last keyframe; false if there was none.
+    pub fn undo(&mut self) -> bool {
+        self.keyframes.pop().is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn finish(self) -> Result<Emote> {
+        if self.keyframes.is_empty() {
+            bail!("No keyframes captured for emote {}", self.name);
+        }
+        Ok(Emote {
+            name: self.name,
+            joint_names: self.joint_names,
+            keyframes: self.keyframes,
+        })
+    }
+}
+
+/// Plays one emote: from the starting targets through each keyframe and back.
+pub struct EmotePlayer {
+    name: String,
+    from: Vec<f64>,
+    /// (seconds, pose) of each segment, the return to `from` last.
+    segments: Vec<(f64, Vec<f64>)>,
+    start: Instant,
+}
+
+impl EmotePlayer {
+    pub fn new(
+        emote: &Emote,
+        joint_names: &[String],
+        from: &[f64],
+        return_duration: f64,
+        now: Instant,
+    ) -> Self {
+        let mut segments: Vec<(f64, Vec<f64>)> = emote
+            .keyframes
+            .iter()
+            .map(|k| k.duration)
+            .zip(emote.poses_for(joint_names, from))
+            .collect();
+        segments.push((return_duration, from.to_vec()));
+        Self {
+            name: emote.name.clone(),
+            from: from.to_vec(),
+            segments,
+            start: now,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Motor targets at `now`; None once the emote is over.
+    pub fn targets(&self, now: Instant) -> Option<Vec<f64>> {
+        let mut t = now.saturating_duration_since(self.start).as_secs_f64();
+        let mut previous = &self.from;
+        for (duration, pose) in &self.segments {
+            if t < *duration {
+                return Some(cubic_interpolate(previous, pose, t / duration));
+            }
+            t -= duration;
+            previous = pose;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_capture_save_and_play() {
+        let dir = std::env::temp_dir().join(format!("odr-emotes-{}", std::process::id()));
+        let library = EmoteLibrary::new(dir.clone());
+        assert!(library.names().unwrap().is_empty());
+
+        let joints: Vec<String> = vec!["neck_pitch".into(), "head_pitch".into()];
+        let mut capture = EmoteCapture::new("nod", joints, 1.0);
+        capture.capture(vec![0.5, 0.0]);
+        capture.capture(vec![9.0, 9.0]);
+        assert!(capture.undo());
+        capture.capture(vec![0.0, -0.5]);
+        library.save(&capture.finish().unwrap()).unwrap();
+        assert_eq!(library.names().unwrap(), vec!["nod"]);
+        let escaping = Emote {
+            name: "../x".into(),
+            ..library.load("nod").unwrap()
+        };
+        assert!(library.save(&escaping).is_err());
+
+        // Played on a duck whose joints are ordered differently, with one
+        // joint the emote does not cover
+        let emote = library.load("nod").unwrap();
+        let joints: Vec<String> = vec!["head_pitch".into(), "head_yaw".into(), "neck_pitch".into()];
+        let from = [0.1, 0.2, 0.3];
+        let start = Instant::now();
+        let player = EmotePlayer::new(&emote, &joints, &from, 0.5, start);
+        let at = |secs: f64| player.targets(start + Duration::from_secs_f64(secs));
+
+        assert_eq!(at(0.0).unwrap(), from.to_vec());
+        assert_eq!(at(1.0).unwrap(), vec![0.0, 0.2, 0.5]);
+        assert_eq!(at(2.0).unwrap(), vec![-0.5, 0.2, 0.0]);
+        assert_eq!(at(2.5), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
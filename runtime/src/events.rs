@@ -0,0 +1,137 @@
+//! Runtime event bus.
+//!
+//! Things that happen to the duck (a fall, a pause, a policy swap, a servo
+//! fault) are published once as typed `Event`s, and every interested part of
+//! the runtime — safety, sounds, expression peripherals, the telemetry
+//! server — subscribes with its own channel instead of being called directly
+//! from the control loop.
+//!
+//! Publishing never blocks: a subscriber whose queue is full misses the event,
+//! and subscribers that have been dropped are pruned on the next publish.
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+use crate::antenna::AntennaAnimation;
+use crate::audit;
+use crate::cloud::CloudStatus;
+use crate::eye_expression::EyeExpression;
+use crate::routines::RoutineReport;
+
+/// Events each subscriber can have queued before new ones are dropped.
+const SUBSCRIBER_QUEUE: usize = 64;
+
+/// Something that happened to the duck.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The control loop is starting.
+    Started,
+    /// The duck tipped past the fall threshold (tilt from vertical, rad).
+    FallDetected { tilt: f64 },
+    PausedToggled { paused: bool },
+    PolicySwapped { from: String, to: String },
+    /// Torque must come off: a servo health limit, a watchdog trip or a
+    /// stale IMU.
+    ServoFault { reason: String },
+    LowBattery { voltage: f64 },
+    /// A joint's load jumped or passed its limit; its target was backed off.
+    CollisionDetected { joint: String, load: f64 },
+    CloudStatusChanged { status: CloudStatus },
+    /// Play a sound by file name, or a random one without a name.
+    SoundRequested { name: Option<String> },
+    /// Stream a sound from an HTTP URL.
+    SoundUrlRequested { url: String },
+    /// Say something through the speaker.
+    SpeechRequested { text: String },
+    /// Set the speaker's master volume (0-1).
+    VolumeRequested { volume: f64 },
+    StopSoundsRequested,
+    BlinkRequested,
+    /// Play a recorded emote by name.
+    EmoteRequested { name: String },
+    /// The walking speed moved to a new level (0 = stopped, 1 = full speed).
+    GaitSpeedChanged { speed: f64 },
+    /// A foot started its step (the gait phase crossed a half cycle).
+    StepTaken { foot: Foot },
+    /// Play an antenna animation (a gamepad trigger).
+    AntennaRequested { animation: AntennaAnimation },
+    /// Queue an eye expression.
+    EyeExpressionRequested { expression: EyeExpression },
+    /// Save a clip of the telemetry around now, optionally named.
+    MomentRequested { name: Option<String> },
+    /// A calibration routine ended, with what the legs did.
+    RoutineFinished { report: RoutineReport },
+    /// The microphone heard a clap, or several in a row.
+    ClapsHeard { claps: u32 },
+    /// The wake word model fired (score 0-1).
+    WakeWordHeard { score: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Foot {
+    Left,
+    Right,
+}
+
+/// Fan-out channel for `Event`s. Clones share the same subscribers.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<Event>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receive every event published from now on.
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (tx, rx) = bounded(SUBSCRIBER_QUEUE);
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+
+    /// Deliver `event` to every subscriber without blocking.
+    pub fn publish(&self, event: Event) {
+        let _span = audit::span("bus_publish");
+        tracing::debug!("Event: {:?}", event);
+        let Ok(mut subscribers) = self.subscribers.lock() else {
+            return;
+        };
+        subscribers.retain(|tx| match tx.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!("Event subscriber lagging, dropped {:?}", event);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_subscriber_gets_events() {
+        let bus = EventBus::new();
+        let a = bus.subscribe();
+        let b = bus.subscribe();
+
+        bus.publish(Event::PausedToggled { paused: true });
+        assert_eq!(a.try_recv(), Ok(Event::PausedToggled { paused: true }));
+        assert_eq!(b.try_recv(), Ok(Event::PausedToggled { paused: true }));
+
+        // A dropped subscriber is pruned; the others keep receiving
+        drop(a);
+        bus.publish(Event::LowBattery { voltage: 6.4 });
+        assert_eq!(bus.subscribers.lock().unwrap().len(), 1);
+        assert_eq!(b.try_recv(), Ok(Event::LowBattery { voltage: 6.4 }));
+    }
+}
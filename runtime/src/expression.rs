@@ -0,0 +1,195 @@
+//! Expressions that follow the gait.
+//!
+//! `GaitEvents` watches the walking commands and the gait phase and
+//! publishes two low-rate events on the bus: `GaitSpeedChanged` when the
+//! walking speed moves to a new level, and `StepTaken` each half gait cycle
+//! while walking. `GaitExpression` turns those events into expression
+//! outputs: eye brightness rising with speed, a projector pulse on every
+//! step, and an antenna sway towards the stepping foot. Each reaction is
+//! enabled separately in `expression_features.gait_reactions`.
+
+use std::time::{Duration, Instant};
+
+use crate::config::GaitReactionsConfig;
+use crate::controller::{X_RANGE, YAW_RANGE, Y_RANGE};
+use crate::events::{Event, Foot};
+
+/// Speed change that is worth a new `GaitSpeedChanged` event.
+const SPEED_STEP: f64 = 0.1;
+
+/// Commands below this count as standing still.
+const DEADBAND: f64 = 1e-3;
+
+/// Walking speed as a share of the command ranges (0-1): the largest of the
+/// forward, lateral and turning components.
+pub fn walking_speed(commands: &[f64; 7]) -> f64 {
+    let share = |value: f64, range: [f64; 2]| {
+        let limit = if value >= 0.0 { range[1] } else { range[0] };
+        (value / limit).abs()
+    };
+    let speed = share(commands[0], X_RANGE)
+        .max(share(commands[1], Y_RANGE))
+        .max(share(commands[2], YAW_RANGE));
+    if speed < DEADBAND {
+        0.0
+    } else {
+        speed.min(1.0)
+    }
+}
+
+/// Derives gait events from the commands and phase, one tick at a time.
+#[derive(Debug, Default)]
+pub struct GaitEvents {
+    speed: f64,
+    last_sin: Option<f64>,
+}
+
+impl GaitEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one tick's commands and `[cos, sin]` gait phase.
+    pub fn update(&mut self, commands: &[f64; 7], phase: [f64; 2]) -> Vec<Event> {
+        let mut events = Vec::new();
+        let speed = walking_speed(commands);
+        let walking = speed > 0.0;
+
+        if walking != (self.speed > 0.0) || (speed - self.speed).abs() >= SPEED_STEP {
+            self.speed = speed;
+            events.push(Event::GaitSpeedChanged { speed });
+        }
+
+        let sin = phase[1];
+        if let Some(last) = self.last_sin.filter(|_| walking) {
+            if last < 0.0 && sin >= 0.0 {
+                events.push(Event::StepTaken { foot: Foot::Left });
+            } else if last > 0.0 && sin <= 0.0 {
+                events.push(Event::StepTaken { foot: Foot::Right });
+            }
+        }
+        self.last_sin = Some(sin);
+        events
+    }
+}
+
+/// Expression outputs driven by gait events.
+pub struct GaitExpression {
+    config: GaitReactionsConfig,
+    speed: f64,
+    pulse_until: Option<Instant>,
+    sway: f64,
+}
+
+impl GaitExpression {
+    pub fn new(config: GaitReactionsConfig) -> Self {
+        Self {
+            config,
+            speed: 0.0,
+            pulse_until: None,
+            sway: 0.0,
+        }
+    }
+
+    /// Whether any reaction is enabled.
+    pub fn enabled(&self) -> bool {
+        self.config.eyes || self.config.projector || self.config.antennas
+    }
+
+    pub fn handle(&mut self, event: &Event, now: Instant) {
+        match *event {
+            Event::GaitSpeedChanged { speed } => {
+                self.speed = speed;
+                if speed == 0.0 {
+                    self.sway = 0.0;
+                }
+            }
+            Event::StepTaken { foot } => {
+                self.pulse_until = Some(now + Duration::from_millis(self.config.pulse_ms));
+                let side = match foot {
+                    Foot::Left => 1.0,
+                    Foot::Right => -1.0,
+                };
+                self.sway = side * self.config.antenna_sway * self.speed;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn walking(&self) -> bool {
+        self.speed > 0.0
+    }
+
+    /// Eye brightness (0-1), if the eyes follow the gait.
+    pub fn eye_brightness(&self) -> Option<f64> {
+        let idle = self.config.idle_brightness.clamp(0.0, 1.0);
+        self.config.eyes.then_some(idle + (1.0 - idle) * self.speed)
+    }
+
+    /// Whether the projector should be lit, while walking and pulsing.
+    pub fn projector_pulse(&self, now: Instant) -> Option<bool> {
+        (self.config.projector && self.walking())
+            .then(|| self.pulse_until.is_some_and(|until| now < until))
+    }
+
+    /// Antenna position (-1..1, positive towards the left), while walking.
+    pub fn antenna_sway(&self) -> Option<f64> {
+        (self.config.antennas && self.walking()).then_some(self.sway)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(angle: f64) -> [f64; 2] {
+        [angle.cos(), angle.sin()]
+    }
+
+    #[test]
+    fn test_steps_and_speed_levels() {
+        let mut gait = GaitEvents::new();
+        let mut commands = [0.0; 7];
+        assert!(gait.update(&commands, phase(-0.1)).is_empty());
+        // Standing: the phase still runs but no steps are reported
+        assert!(gait.update(&commands, phase(0.1)).is_empty());
+
+        commands[0] = X_RANGE[1] / 2.0;
+        assert_eq!(gait.update(&commands, phase(3.0)), vec![Event::GaitSpeedChanged { speed: 0.5 }]);
+        assert_eq!(
+            gait.update(&commands, phase(3.3)),
+            vec![Event::StepTaken { foot: Foot::Right }]
+        );
+        // Small speed changes are not reported
+        commands[0] = X_RANGE[1] * 0.55;
+        assert!(gait.update(&commands, phase(6.0)).is_empty());
+        assert_eq!(
+            gait.update(&commands, phase(6.4)),
+            vec![Event::StepTaken { foot: Foot::Left }]
+        );
+    }
+
+    #[test]
+    fn test_reactions_follow_events() {
+        let config = GaitReactionsConfig {
+            eyes: true,
+            projector: true,
+            antennas: true,
+            ..GaitReactionsConfig::default()
+        };
+        let mut expression = GaitExpression::new(config);
+        let now = Instant::now();
+        assert_eq!(expression.eye_brightness(), Some(0.4));
+        assert_eq!(expression.projector_pulse(now), None);
+
+        expression.handle(&Event::GaitSpeedChanged { speed: 1.0 }, now);
+        expression.handle(&Event::StepTaken { foot: Foot::Right }, now);
+        assert_eq!(expression.eye_brightness(), Some(1.0));
+        assert_eq!(expression.projector_pulse(now), Some(true));
+        assert_eq!(expression.projector_pulse(now + Duration::from_secs(1)), Some(false));
+        assert_eq!(expression.antenna_sway(), Some(-0.4));
+
+        expression.handle(&Event::GaitSpeedChanged { speed: 0.0 }, now);
+        assert_eq!(expression.antenna_sway(), None);
+    }
+}
@@ -0,0 +1,253 @@
+//! Eye expressions — named brightness patterns for the two LED eyes.
+//!
+//! An expression is either a moment (`blink`, `wink`) that plays once, or a
+//! mood (`idle`, `sleepy`, `alert`, `heart_eyes`) that holds until the next
+//! one. Requested expressions are queued and played in order, so a wink
+//! asked for during a blink follows it instead of cutting it off. While
+//! `idle` the eyes blink at random, twice in a row while the backend is
+//! unreachable and three times when the battery is low. `EyeEngine` is pure;
+//! the `peripherals` module drives the LEDs from it on a background thread.
+
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Expressions waiting to play; requests beyond this are dropped.
+const QUEUE_LIMIT: usize = 8;
+
+/// Random wait between idle blinks (ms).
+const BLINK_INTERVAL_MS: std::ops::Range<u64> = 1000..4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EyeExpression {
+    /// Open, blinking now and then.
+    #[default]
+    Idle,
+    Blink,
+    /// Left eye closed for a moment.
+    Wink,
+    /// Dim, slowly breathing.
+    Sleepy,
+    /// Wide open at full brightness, no blinking.
+    Alert,
+    /// A heartbeat pulse.
+    HeartEyes,
+}
+
+impl EyeExpression {
+    pub const ALL: [EyeExpression; 6] = [
+        EyeExpression::Idle,
+        EyeExpression::Blink,
+        EyeExpression::Wink,
+        EyeExpression::Sleepy,
+        EyeExpression::Alert,
+        EyeExpression::HeartEyes,
+    ];
+
+    /// Moods the gamepad cycles through.
+    pub const MOODS: [EyeExpression; 4] = [
+        EyeExpression::Idle,
+        EyeExpression::Alert,
+        EyeExpression::Sleepy,
+        EyeExpression::HeartEyes,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EyeExpression::Idle => "idle",
+            EyeExpression::Blink => "blink",
+            EyeExpression::Wink => "wink",
+            EyeExpression::Sleepy => "sleepy",
+            EyeExpression::Alert => "alert",
+            EyeExpression::HeartEyes => "heart_eyes",
+        }
+    }
+
+    /// Whether it plays once rather than holding.
+    pub fn is_moment(self) -> bool {
+        matches!(self, EyeExpression::Blink | EyeExpression::Wink)
+    }
+
+    /// The mood after this one, for cycling.
+    pub fn next_mood(self) -> Self {
+        let i = Self::MOODS.iter().position(|&m| m == self).unwrap_or(0);
+        Self::MOODS[(i + 1) % Self::MOODS.len()]
+    }
+
+    /// [left, right] level (0-1) `t` seconds in; `None` once a moment is
+    /// over.
+    fn level(self, t: f64) -> Option<[f64; 2]> {
+        let both = |level| Some([level, level]);
+        match self {
+            EyeExpression::Idle | EyeExpression::Alert => both(1.0),
+            EyeExpression::Blink if t < 0.1 => both(0.0),
+            EyeExpression::Wink if t < 0.3 => Some([0.0, 1.0]),
+            EyeExpression::Blink | EyeExpression::Wink if t < 0.4 => both(1.0),
+            EyeExpression::Blink | EyeExpression::Wink => None,
+            EyeExpression::Sleepy => both(0.25 + 0.1 * (TAU * t / 4.0).sin()),
+            EyeExpression::HeartEyes => match t % 1.0 {
+                beat if beat < 0.1 || (0.2..0.3).contains(&beat) => both(1.0),
+                _ => both(0.3),
+            },
+        }
+    }
+}
+
+impl FromStr for EyeExpression {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Self::ALL.into_iter().find(|e| e.name() == name).ok_or_else(|| {
+            let names: Vec<_> = Self::ALL.iter().map(|e| e.name()).collect();
+            anyhow!("Unknown eye expression {:?} (expected one of {})", name, names.join(", "))
+        })
+    }
+}
+
+/// Queued expressions, the current mood, and idle blinking.
+#[derive(Debug)]
+pub struct EyeEngine {
+    mood: EyeExpression,
+    mood_since: Instant,
+    moment: Option<(EyeExpression, Instant)>,
+    queue: VecDeque<EyeExpression>,
+    next_blink: Instant,
+    offline: bool,
+    low_battery: bool,
+    brightness: f64,
+}
+
+impl EyeEngine {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            mood: EyeExpression::Idle,
+            mood_since: now,
+            moment: None,
+            queue: VecDeque::new(),
+            next_blink: now + blink_interval(),
+            offline: false,
+            low_battery: false,
+            brightness: 1.0,
+        }
+    }
+
+    /// Queue an expression. Returns false if the queue is full.
+    pub fn request(&mut self, expression: EyeExpression) -> bool {
+        if self.queue.len() >= QUEUE_LIMIT {
+            return false;
+        }
+        self.queue.push_back(expression);
+        true
+    }
+
+    pub fn mood(&self) -> EyeExpression {
+        self.mood
+    }
+
+    /// The moment playing, or else the mood, as of the last `level` call.
+    pub fn showing(&self) -> EyeExpression {
+        self.moment.map_or(self.mood, |(moment, _)| moment)
+    }
+
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn low_battery(&self) -> bool {
+        self.low_battery
+    }
+
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    pub fn set_low_battery(&mut self, low: bool) {
+        self.low_battery = low;
+    }
+
+    /// Scale for every mood but `alert` (0-1).
+    pub fn set_brightness(&mut self, brightness: f64) {
+        self.brightness = brightness.clamp(0.0, 1.0);
+    }
+
+    /// [left, right] brightness (0-1) at `now`.
+    pub fn level(&mut self, now: Instant) -> [f64; 2] {
+        loop {
+            if let Some((moment, since)) = self.moment {
+                let t = now.saturating_duration_since(since).as_secs_f64();
+                match moment.level(t) {
+                    Some(level) => return self.scaled(level),
+                    None => self.moment = None,
+                }
+            }
+            if let Some(next) = self.queue.pop_front() {
+                if next.is_moment() {
+                    self.moment = Some((next, now));
+                } else if next != self.mood {
+                    self.mood = next;
+                    self.mood_since = now;
+                }
+                continue;
+            }
+            if self.mood == EyeExpression::Idle && now >= self.next_blink {
+                let blinks = if self.low_battery {
+                    3
+                } else if self.offline {
+                    2
+                } else {
+                    1
+                };
+                self.queue.extend([EyeExpression::Blink].repeat(blinks));
+                self.next_blink = now + blink_interval();
+                continue;
+            }
+            let t = now.saturating_duration_since(self.mood_since).as_secs_f64();
+            return self.scaled(self.mood.level(t).unwrap_or([1.0, 1.0]));
+        }
+    }
+
+    fn scaled(&self, level: [f64; 2]) -> [f64; 2] {
+        let scale = if self.mood == EyeExpression::Alert {
+            1.0
+        } else {
+            self.brightness
+        };
+        level.map(|l| l * scale)
+    }
+}
+
+fn blink_interval() -> Duration {
+    Duration::from_millis(rand::thread_rng().gen_range(BLINK_INTERVAL_MS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queued_expressions_play_in_order() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut eyes = EyeEngine::new(start);
+
+        assert!(eyes.request(EyeExpression::Wink));
+        assert!(eyes.request("alert".parse().unwrap()));
+        assert_eq!(eyes.level(at(0)), [0.0, 1.0]);
+        // Still winking; alert waits its turn
+        assert_eq!(eyes.level(at(200)), [0.0, 1.0]);
+        assert_eq!(eyes.mood(), EyeExpression::Idle);
+        eyes.set_brightness(0.5);
+        assert_eq!(eyes.level(at(500)), [1.0, 1.0]);
+        assert_eq!(eyes.mood(), EyeExpression::Alert);
+
+        // Alert never blinks
+        assert_eq!(eyes.level(at(10_000)), [1.0, 1.0]);
+        assert!("frown".parse::<EyeExpression>().is_err());
+        assert_eq!(EyeExpression::HeartEyes.next_mood(), EyeExpression::Idle);
+    }
+}
@@ -0,0 +1,326 @@
+//! Simulated gamepad: stick and button input played from a script, for
+//! testing pause handling, mode toggles, menus and failsafes without a
+//! controller.
+//!
+//! A script is a JSON (or YAML) timeline. Each input starts `at` seconds
+//! in and is held for `hold` seconds (a 0.1 s tap by default):
+//!
+//! ```json
+//! {
+//!     "name": "pause-and-walk",
+//!     "loop": false,
+//!     "inputs": [
+//!         { "at": 1.0, "buttons": ["a"] },
+//!         { "at": 2.0, "buttons": ["lb", "y"], "hold": 0.5 },
+//!         { "at": 3.0, "sticks": { "left_y": 1.0 }, "hold": 2.0 },
+//!         { "at": 3.5, "right_trigger": 1.0 },
+//!         { "at": 6.0, "estop": true, "hold": 1.0 }
+//!     ]
+//! }
+//! ```
+//!
+//! Inputs held at the same time combine: buttons and the e-stop are pressed
+//! if any holds them, triggers take the largest pull, and the input that
+//! starts last sets the sticks. The result goes through the same debouncing
+//! and stick shaping as a real gamepad (Y toggles head control), so the
+//! runtime can't tell the difference. Run it with `--fake-gamepad`.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::controller::{
+    Buttons, CommandShaper, CommandSource, ControllerOutput, PressedButtons, StickLayout,
+};
+
+fn default_hold() -> f64 {
+    0.1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Button {
+    A,
+    B,
+    X,
+    Y,
+    Lb,
+    Rb,
+    Start,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+}
+
+impl Button {
+    fn press(self, pressed: &mut PressedButtons) {
+        let flag = match self {
+            Button::A => &mut pressed.a,
+            Button::B => &mut pressed.b,
+            Button::X => &mut pressed.x,
+            Button::Y => &mut pressed.y,
+            Button::Lb => &mut pressed.lb,
+            Button::Rb => &mut pressed.rb,
+            Button::Start => &mut pressed.start,
+            Button::DpadUp => &mut pressed.dpad_up,
+            Button::DpadDown => &mut pressed.dpad_down,
+            Button::DpadLeft => &mut pressed.dpad_left,
+            Button::DpadRight => &mut pressed.dpad_right,
+        };
+        *flag = true;
+    }
+}
+
+/// Stick deflections (-1..1).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sticks {
+    #[serde(default)]
+    pub left_x: f64,
+    #[serde(default)]
+    pub left_y: f64,
+    #[serde(default)]
+    pub right_x: f64,
+    #[serde(default)]
+    pub right_y: f64,
+}
+
+/// One timed input.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptedInput {
+    /// Start (s from the script start).
+    pub at: f64,
+    /// How long it is held (s).
+    #[serde(default = "default_hold")]
+    pub hold: f64,
+    #[serde(default)]
+    pub buttons: Vec<Button>,
+    #[serde(default)]
+    pub sticks: Option<Sticks>,
+    #[serde(default)]
+    pub left_trigger: f64,
+    #[serde(default)]
+    pub right_trigger: f64,
+    #[serde(default)]
+    pub estop: bool,
+}
+
+impl ScriptedInput {
+    fn holds(&self, t: f64) -> bool {
+        t >= self.at && t < self.at + self.hold
+    }
+}
+
+/// A named input timeline.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct InputScript {
+    #[serde(default)]
+    pub name: String,
+    /// Start over once the last input is released.
+    #[serde(default, rename = "loop")]
+    pub repeat: bool,
+    pub inputs: Vec<ScriptedInput>,
+}
+
+impl InputScript {
+    /// Load a script from JSON, or YAML for `.yaml` / `.yml` files.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read input script {}", path.display()))?;
+        let yaml = path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+        let script: Self = if yaml {
+            serde_yaml::from_str(&text)
+                .with_context(|| format!("Failed to parse input script {}", path.display()))?
+        } else {
+            serde_json::from_str(&text)
+                .with_context(|| format!("Failed to parse input script {}", path.display()))?
+        };
+        script.validate()?;
+        Ok(script)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.inputs.is_empty() {
+            bail!("Input script has no inputs");
+        }
+        for (i, input) in self.inputs.iter().enumerate() {
+            let timed = [input.at, input.hold].iter().all(|v| v.is_finite() && *v >= 0.0);
+            if !timed || input.hold == 0.0 {
+                bail!("Input {} has an invalid time or hold: {:?}", i + 1, input);
+            }
+        }
+        Ok(())
+    }
+
+    /// When the last input is released (s).
+    pub fn length(&self) -> f64 {
+        self.inputs
+            .iter()
+            .map(|input| input.at + input.hold)
+            .fold(0.0, f64::max)
+    }
+
+    /// Raw gamepad state `t` seconds in: pressed buttons, sticks, triggers
+    /// and the e-stop.
+    fn state(&self, t: f64) -> (PressedButtons, [f64; 4], [f64; 2], bool) {
+        let mut pressed = PressedButtons::default();
+        let mut sticks = None;
+        let mut triggers = [0.0f64; 2];
+        let mut estop = false;
+        for input in self.inputs.iter().filter(|input| input.holds(t)) {
+            for button in &input.buttons {
+                button.press(&mut pressed);
+            }
+            if let Some(s) = input.sticks {
+                let latest = sticks.is_none_or(|(at, _)| input.at >= at);
+                if latest {
+                    sticks = Some((input.at, [s.left_x, s.left_y, s.right_x, s.right_y]));
+                }
+            }
+            triggers[0] = triggers[0].max(input.left_trigger);
+            triggers[1] = triggers[1].max(input.right_trigger);
+            estop |= input.estop;
+        }
+        let sticks = sticks.map_or([0.0; 4], |(_, s)| s.map(|v| v.clamp(-1.0, 1.0)));
+        (pressed, sticks, triggers.map(|v| v.clamp(0.0, 1.0)), estop)
+    }
+}
+
+/// Plays an input script as a command source.
+pub struct FakeGamepad {
+    script: InputScript,
+    started: Option<Instant>,
+    buttons: Buttons,
+    shaper: CommandShaper,
+    y_was_pressed: bool,
+    finished: bool,
+    output: ControllerOutput,
+}
+
+impl FakeGamepad {
+    pub fn new(script: InputScript, layout: StickLayout) -> Self {
+        tracing::info!(
+            "Fake gamepad '{}': {} inputs over {:.1}s{}",
+            script.name,
+            script.inputs.len(),
+            script.length(),
+            if script.repeat { ", looping" } else { "" }
+        );
+        Self {
+            script,
+            started: None,
+            buttons: Buttons::new(),
+            shaper: CommandShaper::new(layout),
+            y_was_pressed: false,
+            finished: false,
+            output: ControllerOutput::default(),
+        }
+    }
+
+    /// Whether a non-looping script has played out.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The gamepad `t` seconds into the script. Times must not go back.
+    pub fn update(&mut self, t: f64) -> &ControllerOutput {
+        let length = self.script.length();
+        let at = if self.script.repeat && length > 0.0 { t % length } else { t };
+        if !self.script.repeat && t >= length && !self.finished {
+            self.finished = true;
+            tracing::info!("Fake gamepad '{}' finished", self.script.name);
+        }
+
+        let (pressed, sticks, [left_trigger, right_trigger], estop) = self.script.state(at);
+        // As on a real gamepad, pressing Y switches the sticks to the head
+        // (LB + Y starts a calibration routine instead)
+        if pressed.y && !self.y_was_pressed && !pressed.lb {
+            self.shaper.toggle_head_control();
+        }
+        self.y_was_pressed = pressed.y;
+        self.buttons.update(&pressed, t);
+
+        self.output = ControllerOutput {
+            commands: self.shaper.sticks(sticks),
+            buttons: self.buttons.clone(),
+            left_trigger,
+            right_trigger,
+            estop,
+        };
+        &self.output
+    }
+}
+
+impl CommandSource for FakeGamepad {
+    fn get_last_command(&mut self) -> &ControllerOutput {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        self.update(started.elapsed().as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::X_RANGE;
+
+    #[test]
+    fn test_timeline_presses_and_drives() {
+        let script: InputScript = serde_json::from_str(
+            r#"{"inputs": [
+                {"at": 0.5, "buttons": ["a"]},
+                {"at": 1.0, "sticks": {"left_y": 1.0}, "hold": 1.0},
+                {"at": 1.5, "sticks": {"left_y": 0.5}, "hold": 0.2},
+                {"at": 2.5, "estop": true, "hold": 0.5}
+            ]}"#,
+        )
+        .unwrap();
+        script.validate().unwrap();
+        assert_eq!(script.length(), 3.0);
+        let mut pad = FakeGamepad::new(script, StickLayout::Toggle);
+
+        // A tap triggers once, on its first tick
+        assert!(!pad.update(0.45).buttons.a.triggered);
+        assert!(pad.update(0.5).buttons.a.triggered);
+        assert!(!pad.update(0.55).buttons.a.triggered);
+
+        assert_eq!(pad.update(1.2).commands[0], X_RANGE[1]);
+        assert_eq!(pad.update(1.6).commands[0], X_RANGE[1] / 2.0);
+        assert_eq!(pad.update(1.8).commands[0], X_RANGE[1]);
+
+        let output = pad.update(2.6);
+        assert!(output.estop);
+        assert_eq!(output.commands, [0.0; 7]);
+        assert!(!pad.finished());
+        assert!(!pad.update(3.0).estop);
+        assert!(pad.finished());
+
+        let bad: InputScript =
+            serde_json::from_str(r#"{"inputs": [{"at": 1.0, "hold": 0.0}]}"#).unwrap();
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_lb_y_keeps_steering_mode() {
+        let script: InputScript = serde_json::from_str(
+            r#"{"inputs": [
+                {"at": 0.5, "buttons": ["lb", "y"]},
+                {"at": 1.0, "sticks": {"left_y": 1.0}, "hold": 0.5},
+                {"at": 2.0, "buttons": ["y"]},
+                {"at": 2.5, "sticks": {"left_y": 1.0}, "hold": 0.5}
+            ]}"#,
+        )
+        .unwrap();
+        let mut pad = FakeGamepad::new(script, StickLayout::Toggle);
+        assert!(pad.update(0.5).buttons.y.triggered);
+        // Still driving after LB + Y
+        assert_eq!(pad.update(1.2).commands[0], X_RANGE[1]);
+        // Y alone switches the sticks to the head
+        pad.update(2.0);
+        assert_eq!(pad.update(2.7).commands[0], 0.0);
+    }
+}
@@ -0,0 +1,304 @@
+//! Follow mode — track another duck (or a person carrying a beacon) at a set
+//! distance.
+//!
+//! A `TargetSensor` reports range and bearing to the leader; `FollowController`
+//! turns that into walking velocity commands. The default sensor is a UWB
+//! ranging module on a serial port that prints one `distance_m,bearing_deg`
+//! line per measurement (e.g. a DWM3000 tag with angle-of-arrival firmware).
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use std::io::{BufRead, BufReader};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::controller::{X_RANGE, YAW_RANGE};
+
+/// Wait after a failed read (e.g. the beacon unplugged) before trying again.
+const READ_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Range and bearing to the followed target.
+#[derive(Debug, Clone, Copy)]
+pub struct TargetObservation {
+    /// Distance to the target in meters.
+    pub distance: f64,
+    /// Bearing to the target in radians (positive = target to the left).
+    pub bearing: f64,
+    /// When the measurement was taken.
+    pub timestamp: Instant,
+}
+
+/// Trait for target sensors (UWB beacon, camera detector, ...).
+pub trait TargetSensor: Send {
+    /// Latest measurement, if any has been received.
+    fn latest(&mut self) -> Option<TargetObservation>;
+}
+
+/// Tuning for the follow behavior.
+#[derive(Debug, Clone, Copy)]
+pub struct FollowParams {
+    /// Distance to hold behind the target (m).
+    pub follow_distance: f64,
+    /// Stop walking forward when closer than this (m), e.g. the leader stopped.
+    pub stop_distance: f64,
+    /// Forward velocity per meter of distance error.
+    pub kp_distance: f64,
+    /// Yaw rate per radian of bearing error.
+    pub kp_bearing: f64,
+    /// Measurements older than this are treated as a lost target.
+    pub timeout: Duration,
+}
+
+impl Default for FollowParams {
+    fn default() -> Self {
+        Self {
+            follow_distance: 0.6,
+            stop_distance: 0.3,
+            kp_distance: 0.3,
+            kp_bearing: 1.5,
+            timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Proportional follow controller producing [lin_vel_x, lin_vel_y, ang_vel].
+pub struct FollowController {
+    params: FollowParams,
+    target_lost: bool,
+}
+
+impl FollowController {
+    pub fn new(params: FollowParams) -> Self {
+        Self {
+            params,
+            target_lost: true,
+        }
+    }
+
+    /// Compute velocity commands from the latest observation at time `now`.
+    ///
+    /// Returns zero velocity when the target is missing or stale.
+    pub fn update(&mut self, observation: Option<TargetObservation>, now: Instant) -> [f64; 3] {
+        let fresh = observation
+            .filter(|o| now.saturating_duration_since(o.timestamp) <= self.params.timeout);
+
+        let Some(obs) = fresh else {
+            if !self.target_lost {
+                tracing::warn!("Follow: target lost, stopping");
+                self.target_lost = true;
+            }
+            return [0.0; 3];
+        };
+
+        if self.target_lost {
+            tracing::info!(
+                "Follow: target acquired at {:.2}m, {:.0}°",
+                obs.distance,
+                obs.bearing.to_degrees()
+            );
+            self.target_lost = false;
+        }
+
+        let ang_vel = (self.params.kp_bearing * obs.bearing).clamp(YAW_RANGE[0], YAW_RANGE[1]);
+
+        // Never walk forward inside the stop distance; back off gently instead
+        let distance_error = obs.distance - self.params.follow_distance;
+        let mut lin_vel_x = (self.params.kp_distance * distance_error).clamp(X_RANGE[0], X_RANGE[1]);
+        if obs.distance < self.params.stop_distance {
+            lin_vel_x = lin_vel_x.min(0.0);
+        }
+
+        // Turn in place first when the target is well off to the side
+        if obs.bearing.abs() > std::f64::consts::FRAC_PI_4 {
+            lin_vel_x = 0.0;
+        }
+
+        [lin_vel_x, 0.0, ang_vel]
+    }
+}
+
+// ── UWB beacon over serial ──
+
+/// UWB ranging module streaming `distance_m,bearing_deg` lines over serial.
+pub struct UwbBeacon {
+    receiver: Receiver<TargetObservation>,
+    stop_tx: Sender<()>,
+    last: Option<TargetObservation>,
+}
+
+impl UwbBeacon {
+    /// Open the serial port and start the background reader thread.
+    pub fn new(serial_port: &str, baud_rate: u32) -> Result<Self> {
+        let port = serialport::new(serial_port, baud_rate)
+            .timeout(Duration::from_millis(100))
+            .open()
+            .with_context(|| format!("Failed to open UWB beacon port {}", serial_port))?;
+
+        let (data_tx, data_rx) = bounded::<TargetObservation>(1);
+        let (stop_tx, stop_rx) = bounded::<()>(1);
+        let drain_rx = data_rx.clone();
+
+        thread::spawn(move || {
+            uwb_worker(BufReader::new(port), data_tx, drain_rx, stop_rx);
+        });
+
+        tracing::info!("UWB beacon initialized on {}", serial_port);
+
+        Ok(Self {
+            receiver: data_rx,
+            stop_tx,
+            last: None,
+        })
+    }
+
+    /// Signal the background thread to stop.
+    pub fn stop(&self) {
+        let _ = self.stop_tx.try_send(());
+    }
+}
+
+impl TargetSensor for UwbBeacon {
+    fn latest(&mut self) -> Option<TargetObservation> {
+        if let Ok(obs) = self.receiver.try_recv() {
+            self.last = Some(obs);
+        }
+        self.last
+    }
+}
+
+impl Drop for UwbBeacon {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Background worker that parses beacon lines as they arrive.
+fn uwb_worker(
+    mut reader: BufReader<Box<dyn serialport::SerialPort>>,
+    data_tx: Sender<TargetObservation>,
+    drain_rx: Receiver<TargetObservation>,
+    stop_rx: Receiver<()>,
+) {
+    let mut line = String::new();
+
+    loop {
+        if stop_rx.try_recv().is_ok() {
+            break;
+        }
+
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => {
+                thread::sleep(READ_RETRY_DELAY);
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+            Err(e) => {
+                tracing::trace!("UWB read error: {}", e);
+                thread::sleep(READ_RETRY_DELAY);
+                continue;
+            }
+        }
+
+        let Some((distance, bearing)) = parse_beacon_line(&line) else {
+            tracing::trace!("UWB: ignoring malformed line {:?}", line.trim());
+            continue;
+        };
+
+        let obs = TargetObservation {
+            distance,
+            bearing,
+            timestamp: Instant::now(),
+        };
+
+        match data_tx.try_send(obs) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                let _ = drain_rx.try_recv();
+                let _ = data_tx.try_send(obs);
+            }
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+
+    tracing::info!("UWB worker thread exiting");
+}
+
+/// Parse a `distance_m,bearing_deg` line into (meters, radians).
+fn parse_beacon_line(line: &str) -> Option<(f64, f64)> {
+    let mut fields = line.trim().split(',');
+    let distance: f64 = fields.next()?.trim().parse().ok()?;
+    let bearing_deg: f64 = fields.next()?.trim().parse().ok()?;
+    if fields.next().is_some() || !distance.is_finite() || distance < 0.0 {
+        return None;
+    }
+    if !bearing_deg.is_finite() {
+        return None;
+    }
+    Some((distance, bearing_deg.to_radians()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn obs(distance: f64, bearing_deg: f64, timestamp: Instant) -> Option<TargetObservation> {
+        Some(TargetObservation {
+            distance,
+            bearing: bearing_deg.to_radians(),
+            timestamp,
+        })
+    }
+
+    #[test]
+    fn test_follow_walks_toward_far_target() {
+        let mut follow = FollowController::new(FollowParams::default());
+        let now = Instant::now();
+        let cmd = follow.update(obs(2.0, 0.0, now), now);
+        assert_eq!(cmd[0], X_RANGE[1]);
+        assert_eq!(cmd[2], 0.0);
+    }
+
+    #[test]
+    fn test_follow_never_advances_inside_stop_distance() {
+        let params = FollowParams {
+            follow_distance: 0.1,
+            ..FollowParams::default()
+        };
+        let mut follow = FollowController::new(params);
+        let now = Instant::now();
+        let cmd = follow.update(obs(0.2, 0.0, now), now);
+        assert!(cmd[0] <= 0.0);
+    }
+
+    #[test]
+    fn test_follow_stops_on_stale_target() {
+        let mut follow = FollowController::new(FollowParams::default());
+        let then = Instant::now();
+        let now = then + Duration::from_secs(2);
+        assert_eq!(follow.update(obs(2.0, 10.0, then), now), [0.0; 3]);
+        assert_eq!(follow.update(None, now), [0.0; 3]);
+    }
+
+    #[test]
+    fn test_follow_turns_in_place_for_large_bearing() {
+        let mut follow = FollowController::new(FollowParams::default());
+        let now = Instant::now();
+        let cmd = follow.update(obs(2.0, 90.0, now), now);
+        assert_eq!(cmd[0], 0.0);
+        assert!(cmd[2] > 0.0);
+    }
+
+    #[test]
+    fn test_parse_beacon_line() {
+        let (d, b) = parse_beacon_line("1.25, -30.0\r\n").unwrap();
+        assert_eq!(d, 1.25);
+        assert!((b + 30f64.to_radians()).abs() < 1e-12);
+        assert!(parse_beacon_line("garbage").is_none());
+        assert!(parse_beacon_line("1.0,2.0,3.0").is_none());
+        assert!(parse_beacon_line("-1.0,0.0").is_none());
+        assert!(parse_beacon_line("1.0,NaN").is_none());
+        assert!(parse_beacon_line("1.0,inf").is_none());
+    }
+}
@@ -0,0 +1,350 @@
+//! Online gait symmetry analysis and per-leg wear report.
+//!
+//! While the duck walks, compares its legs on three measures: stance
+//! duration (foot on the ground), stride time (touchdown to touchdown), and
+//! per-joint tracking error (present position against the previous target).
+//! Each left/right pair becomes a symmetry index,
+//! `SI = |L - R| / ((L + R) / 2)`, which is 0 for a perfectly even gait.
+//!
+//! One lopsided window means little — a turn, a bump in the floor. A joint
+//! that tracks worse than its mirror window after window usually points at
+//! a mechanical problem (a worn gear, a loose horn, a binding bearing), and
+//! the report names it as a suspect.
+
+use serde::Serialize;
+
+use crate::config::GaitConfig;
+
+/// Windows in a row a joint must be asymmetric before it is reported.
+const PERSISTENT_WINDOWS: u32 = 3;
+
+/// Strides per leg needed before a window is judged.
+const MIN_STRIDES: u32 = 5;
+
+/// Stance or stride phases longer than this are standing, not walking (s).
+const MAX_PHASE: f64 = 2.0;
+
+/// Contact reading above which a foot counts as down.
+const CONTACT_THRESHOLD: f64 = 0.5;
+
+/// Running mean.
+#[derive(Debug, Clone, Copy, Default)]
+struct Mean {
+    sum: f64,
+    count: u32,
+}
+
+impl Mean {
+    fn push(&mut self, value: f64) {
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn get(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+}
+
+/// Contact timing for one foot.
+#[derive(Debug, Clone, Default)]
+struct Leg {
+    down_since: Option<f64>,
+    last_touchdown: Option<f64>,
+    stance: Mean,
+    stride: Mean,
+}
+
+impl Leg {
+    fn update(&mut self, time: f64, down: bool) {
+        match (down, self.down_since) {
+            (true, None) => {
+                if let Some(last) = self.last_touchdown {
+                    let stride = time - last;
+                    if stride < MAX_PHASE {
+                        self.stride.push(stride);
+                    }
+                }
+                self.last_touchdown = Some(time);
+                self.down_since = Some(time);
+            }
+            (false, Some(since)) => {
+                let stance = time - since;
+                if stance < MAX_PHASE {
+                    self.stance.push(stance);
+                }
+                self.down_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Forget the contact in progress (walking stopped).
+    fn interrupt(&mut self) {
+        self.down_since = None;
+        self.last_touchdown = None;
+    }
+}
+
+/// Tracking error of one left/right joint pair.
+#[derive(Debug, Clone, Serialize)]
+pub struct JointSymmetry {
+    pub left: String,
+    pub right: String,
+    /// RMS tracking error (rad).
+    pub left_rms: f64,
+    pub right_rms: f64,
+    pub symmetry_index: f64,
+    /// Consecutive asymmetric windows.
+    pub streak: u32,
+}
+
+/// One analysis window.
+#[derive(Debug, Clone, Serialize)]
+pub struct GaitReport {
+    pub strides: [u32; 2],
+    /// Mean stance duration [left, right] (s).
+    pub stance: [f64; 2],
+    /// Mean stride time [left, right] (s).
+    pub stride: [f64; 2],
+    pub stance_index: f64,
+    pub stride_index: f64,
+    pub joints: Vec<JointSymmetry>,
+    /// Joints that have tracked worse than their mirror for
+    /// `PERSISTENT_WINDOWS` windows in a row, worst first.
+    pub suspects: Vec<String>,
+}
+
+/// A mirrored joint pair and its accumulated squared tracking error.
+struct Pair {
+    left: usize,
+    right: usize,
+    error: [Mean; 2],
+    streak: u32,
+}
+
+/// Accumulates gait measurements and emits a `GaitReport` per window.
+pub struct GaitAnalyzer {
+    config: GaitConfig,
+    joint_names: Vec<String>,
+    legs: [Leg; 2],
+    pairs: Vec<Pair>,
+    window_start: Option<f64>,
+    last: Option<GaitReport>,
+}
+
+impl GaitAnalyzer {
+    /// Pairs every `left_*` joint with its `right_*` mirror.
+    pub fn new(config: GaitConfig, joint_names: &[String]) -> Self {
+        let pairs = joint_names
+            .iter()
+            .enumerate()
+            .filter_map(|(left, name)| {
+                let mirror = format!("right_{}", name.strip_prefix("left_")?);
+                let right = joint_names.iter().position(|n| *n == mirror)?;
+                Some(Pair {
+                    left,
+                    right,
+                    error: [Mean::default(); 2],
+                    streak: 0,
+                })
+            })
+            .collect();
+
+        Self {
+            config,
+            joint_names: joint_names.to_vec(),
+            legs: [Leg::default(), Leg::default()],
+            pairs,
+            window_start: None,
+            last: None,
+        }
+    }
+
+    /// Feed one control tick. `targets` are the motor targets the present
+    /// positions were chasing. Ticks that aren't walking are skipped.
+    pub fn update(
+        &mut self,
+        time: f64,
+        walking: bool,
+        feet: [f64; 2],
+        positions: &[f64],
+        targets: &[f64],
+    ) -> Option<GaitReport> {
+        if !walking {
+            self.legs.iter_mut().for_each(Leg::interrupt);
+            return None;
+        }
+
+        for (leg, contact) in self.legs.iter_mut().zip(feet) {
+            leg.update(time, contact > CONTACT_THRESHOLD);
+        }
+        for pair in &mut self.pairs {
+            for (side, joint) in [pair.left, pair.right].into_iter().enumerate() {
+                if let (Some(pos), Some(target)) = (positions.get(joint), targets.get(joint)) {
+                    pair.error[side].push((pos - target).powi(2));
+                }
+            }
+        }
+
+        let start = *self.window_start.get_or_insert(time);
+        if time - start < self.config.report_interval {
+            return None;
+        }
+        let strides = [self.legs[0].stride.count, self.legs[1].stride.count];
+        if strides.iter().any(|&s| s < MIN_STRIDES) {
+            return None;
+        }
+
+        self.window_start = Some(time);
+        let report = self.close_window(strides);
+        self.last = Some(report.clone());
+        Some(report)
+    }
+
+    /// The most recent report.
+    pub fn last_report(&self) -> Option<&GaitReport> {
+        self.last.as_ref()
+    }
+
+    fn close_window(&mut self, strides: [u32; 2]) -> GaitReport {
+        let mean = |m: Mean| m.get().unwrap_or(0.0);
+        let stance = [mean(self.legs[0].stance), mean(self.legs[1].stance)];
+        let stride = [mean(self.legs[0].stride), mean(self.legs[1].stride)];
+        for leg in &mut self.legs {
+            leg.stance = Mean::default();
+            leg.stride = Mean::default();
+        }
+
+        let mut joints = Vec::with_capacity(self.pairs.len());
+        let mut suspects = Vec::new();
+        for pair in &mut self.pairs {
+            let rms = pair.error.map(|e| e.get().unwrap_or(0.0).sqrt());
+            pair.error = [Mean::default(); 2];
+
+            let index = symmetry_index(rms[0], rms[1]);
+            if index > self.config.warn_index {
+                pair.streak += 1;
+            } else {
+                pair.streak = 0;
+            }
+            if pair.streak >= PERSISTENT_WINDOWS {
+                let worse = if rms[0] > rms[1] { pair.left } else { pair.right };
+                suspects.push((index, self.joint_names[worse].clone()));
+            }
+
+            joints.push(JointSymmetry {
+                left: self.joint_names[pair.left].clone(),
+                right: self.joint_names[pair.right].clone(),
+                left_rms: rms[0],
+                right_rms: rms[1],
+                symmetry_index: index,
+                streak: pair.streak,
+            });
+        }
+        suspects.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        GaitReport {
+            strides,
+            stance,
+            stride,
+            stance_index: symmetry_index(stance[0], stance[1]),
+            stride_index: symmetry_index(stride[0], stride[1]),
+            joints,
+            suspects: suspects.into_iter().map(|(_, name)| name).collect(),
+        }
+    }
+}
+
+/// `|a - b|` relative to their mean; 0 when both are 0.
+fn symmetry_index(a: f64, b: f64) -> f64 {
+    let mean = (a + b) / 2.0;
+    if mean <= f64::EPSILON {
+        0.0
+    } else {
+        (a - b).abs() / mean
+    }
+}
+
+/// Log a report: a one-line summary, and a warning naming suspect joints.
+pub fn log_report(report: &GaitReport, warn_index: f64) {
+    tracing::info!(
+        "Gait symmetry: stance SI {:.2} ({:.3}s / {:.3}s), stride SI {:.2}, {} / {} strides",
+        report.stance_index,
+        report.stance[0],
+        report.stance[1],
+        report.stride_index,
+        report.strides[0],
+        report.strides[1],
+    );
+    if report.stance_index > warn_index {
+        let shorter = if report.stance[0] < report.stance[1] { "left" } else { "right" };
+        tracing::warn!("Uneven stance: the {} leg spends less time loaded", shorter);
+    }
+    if !report.suspects.is_empty() {
+        tracing::warn!(
+            "Persistent tracking asymmetry, check for wear: {}",
+            report.suspects.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["left_knee", "neck_pitch", "right_knee"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    /// Walk for `seconds` at 50 Hz with alternating 0.4 s steps, the left
+    /// knee lagging its target by `left_error`.
+    fn walk(gait: &mut GaitAnalyzer, t0: f64, seconds: f64, left_error: f64) -> Option<GaitReport> {
+        let mut report = None;
+        let ticks = (seconds * 50.0) as usize;
+        for i in 0..ticks {
+            let t = t0 + i as f64 / 50.0;
+            let left_down = ((t / 0.4) as u64).is_multiple_of(2);
+            let feet = [left_down as u8 as f64, !left_down as u8 as f64];
+            let positions = [0.5 + left_error, 0.0, 0.5 + 0.01];
+            report = gait.update(t, true, feet, &positions, &[0.5, 0.0, 0.5]).or(report);
+        }
+        report
+    }
+
+    #[test]
+    fn test_even_gait_is_symmetric() {
+        let config = GaitConfig {
+            report_interval: 5.0,
+            ..GaitConfig::default()
+        };
+        let mut gait = GaitAnalyzer::new(config, &names());
+        let report = walk(&mut gait, 0.0, 6.0, 0.01).unwrap();
+
+        assert_eq!(report.joints.len(), 1);
+        assert!(report.stance_index < 0.05, "{:?}", report);
+        assert!(report.stride_index < 0.05, "{:?}", report);
+        assert!(report.joints[0].symmetry_index < 1e-9);
+        assert!(report.suspects.is_empty());
+    }
+
+    #[test]
+    fn test_persistent_asymmetry_names_the_worse_joint() {
+        let config = GaitConfig {
+            report_interval: 5.0,
+            ..GaitConfig::default()
+        };
+        let mut gait = GaitAnalyzer::new(config, &names());
+
+        let mut t = 0.0;
+        for window in 1..=PERSISTENT_WINDOWS {
+            let report = walk(&mut gait, t, 5.04, 0.05).unwrap();
+            assert_eq!(report.joints[0].streak, window);
+            t += 5.04;
+        }
+        let report = gait.last_report().unwrap();
+        assert_eq!(report.suspects, vec!["left_knee".to_string()]);
+    }
+}
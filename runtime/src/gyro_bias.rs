@@ -0,0 +1,116 @@
+//! Gyro bias estimation while paused.
+//!
+//! A small constant gyro offset reads as a slow turn, and the policy steers
+//! against it: the duck veers when told to walk straight. While the robot is
+//! paused and still, the gyro should read zero, so its readings there are the
+//! bias. They are averaged into an estimate that follows slow drift
+//! (temperature) and subtracted from every reading the control loop uses.
+
+use std::time::Instant;
+
+use crate::config::GyroBiasConfig;
+use crate::imu::ImuData;
+
+/// Standard gravity (m/s^2).
+const GRAVITY: f64 = 9.81;
+
+/// Still samples averaged before the estimate is trusted and logged.
+const SETTLE_SAMPLES: u32 = 20;
+
+pub struct GyroBias {
+    config: GyroBiasConfig,
+    bias: [f64; 3],
+    /// Still samples accumulated, saturating.
+    samples: u32,
+    last_update: Option<Instant>,
+}
+
+impl GyroBias {
+    pub fn new(config: GyroBiasConfig) -> Self {
+        Self {
+            config,
+            bias: [0.0; 3],
+            samples: 0,
+            last_update: None,
+        }
+    }
+
+    /// Current estimate (rad/s).
+    pub fn bias(&self) -> [f64; 3] {
+        self.bias
+    }
+
+    /// Fold in a reading taken while paused. Ignored unless the robot looks
+    /// still: gyro close to the current estimate and accel close to 1 g.
+    pub fn update(&mut self, data: &ImuData, now: Instant) {
+        let dt = self
+            .last_update
+            .map_or(0.0, |t| now.saturating_duration_since(t).as_secs_f64());
+        self.last_update = Some(now);
+
+        let rate = norm(std::array::from_fn(|i| data.gyro[i] - self.bias[i]));
+        let accel = norm(data.accel);
+        // Before the first estimate the bias itself may exceed the threshold
+        let turning = self.samples >= SETTLE_SAMPLES && rate > self.config.still_rate;
+        if turning || (accel - GRAVITY).abs() > self.config.still_accel {
+            return;
+        }
+
+        // Plain mean at first, then an exponential average with the time constant
+        self.samples = self.samples.saturating_add(1);
+        let alpha = (1.0 / self.samples as f64).max(dt / self.config.time_constant).min(1.0);
+        for (b, g) in self.bias.iter_mut().zip(data.gyro) {
+            *b += alpha * (g - *b);
+        }
+        if self.samples == SETTLE_SAMPLES {
+            tracing::info!(
+                "Gyro bias estimated: [{:.4}, {:.4}, {:.4}] rad/s",
+                self.bias[0],
+                self.bias[1],
+                self.bias[2]
+            );
+        }
+    }
+
+    /// Subtract the estimate from `data.gyro`.
+    pub fn correct(&self, data: &mut ImuData) {
+        for (g, b) in data.gyro.iter_mut().zip(self.bias) {
+            *g -= b;
+        }
+    }
+}
+
+fn norm(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_estimates_and_removes_bias() {
+        let mut estimator = GyroBias::new(GyroBiasConfig::default());
+        let still = ImuData {
+            gyro: [0.01, -0.02, 0.005],
+            accel: [0.0, 0.0, 9.81],
+            ..ImuData::default()
+        };
+        let t0 = Instant::now();
+        for i in 0..50 {
+            estimator.update(&still, t0 + Duration::from_millis(100 * i));
+        }
+        let mut data = still;
+        estimator.correct(&mut data);
+        assert!(data.gyro.iter().all(|g| g.abs() < 1e-9), "{:?}", data.gyro);
+
+        // Being picked up (turning) leaves the estimate alone
+        let moving = ImuData {
+            gyro: [0.5, 0.0, 0.0],
+            ..still
+        };
+        estimator.update(&moving, t0 + Duration::from_secs(6));
+        assert!((estimator.bias()[0] - 0.01).abs() < 1e-9);
+    }
+}
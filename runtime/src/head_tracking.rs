@@ -0,0 +1,438 @@
+//! Head tracking: the duck looks at the person (or object) in front of it.
+//!
+//! A background thread takes the newest camera frame a few times a second,
+//! decodes it to RGB at the detector's input size (through an `ffmpeg`
+//! process, as the capture already depends on it), and runs an SSD-style
+//! ONNX detector such as UltraFace. The best detection of the configured
+//! class moves the aim point by `gain` of its angle off the image center;
+//! as the camera sits in the head, this closes the loop on the target.
+//!
+//! The aim reaches the control loop as head pitch and yaw commands from
+//! `TrackingInput`, a scripted-priority `CommandSource`, so any person or
+//! remote driving takes over. The head moves at most `max_speed` toward the
+//! aim and goes back to center once the target has been lost for
+//! `lost_secs`. LB + B on the gamepad toggles tracking.
+//!
+//! The detector takes a `[1, 3, H, W]` RGB image normalized as
+//! `(pixel - mean) / std` and returns `scores [1, N, classes]` and
+//! `boxes [1, N, 4]` (corners, 0-1 of the image).
+
+use anyhow::{bail, Context, Result};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError};
+use ndarray::Array4;
+use ort::session::Session;
+use ort::value::Tensor;
+use std::io::{ErrorKind, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::camera::FrameFeed;
+use crate::config::HeadTrackingConfig;
+use crate::controller::{CommandSource, ControllerOutput, HEAD_PITCH_RANGE, HEAD_YAW_RANGE};
+
+/// Longest wait for a decoded frame before the frame is skipped.
+const DECODE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Head commands this close to center count as centered.
+const CENTERED: f64 = 1e-3;
+
+/// One detection, in image coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    /// Box center, -1 (left, top) to 1 (right, bottom).
+    pub center: [f64; 2],
+    pub score: f64,
+}
+
+/// The best-scoring box of `class` above `threshold`, from SSD-style
+/// outputs: `scores` is N x `classes`, `boxes` N x 4 corners (0-1).
+pub fn best_detection(
+    scores: &[f32],
+    boxes: &[f32],
+    classes: usize,
+    class: usize,
+    threshold: f64,
+) -> Option<Detection> {
+    if classes == 0 || class >= classes {
+        return None;
+    }
+    scores
+        .chunks_exact(classes)
+        .zip(boxes.chunks_exact(4))
+        .map(|(score, corners)| (score[class] as f64, corners))
+        .filter(|(score, _)| *score >= threshold)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(score, c)| Detection {
+            center: [
+                (c[0] + c[2]) as f64 - 1.0,
+                (c[1] + c[3]) as f64 - 1.0,
+            ],
+            score,
+        })
+}
+
+/// Turns detections into a head aim (pitch, yaw in rad).
+#[derive(Debug, Clone)]
+pub struct HeadAimer {
+    half_fov: [f64; 2],
+    gain: f64,
+    pitch_sign: f64,
+    aim: [f64; 2],
+}
+
+impl HeadAimer {
+    pub fn new(config: &HeadTrackingConfig) -> Self {
+        Self {
+            half_fov: [
+                config.horizontal_fov_deg.to_radians() / 2.0,
+                config.vertical_fov_deg.to_radians() / 2.0,
+            ],
+            gain: config.gain.clamp(0.0, 1.0),
+            pitch_sign: if config.invert_pitch { -1.0 } else { 1.0 },
+            aim: [0.0; 2],
+        }
+    }
+
+    /// Move the aim toward a detection seen from the current aim. Returns
+    /// `[pitch, yaw]`.
+    pub fn update(&mut self, detection: &Detection) -> [f64; 2] {
+        // Right of center turns the head right (negative yaw); below center
+        // pitches it down (positive pitch)
+        let yaw = -detection.center[0] * self.half_fov[0];
+        let pitch = self.pitch_sign * detection.center[1] * self.half_fov[1];
+        self.aim = [
+            (self.aim[0] + self.gain * pitch).clamp(HEAD_PITCH_RANGE[0], HEAD_PITCH_RANGE[1]),
+            (self.aim[1] + self.gain * yaw).clamp(HEAD_YAW_RANGE[0], HEAD_YAW_RANGE[1]),
+        ];
+        self.aim
+    }
+
+    pub fn reset(&mut self) {
+        self.aim = [0.0; 2];
+    }
+}
+
+/// Where the detector thread wants the head.
+#[derive(Debug, Clone, Copy, Default)]
+struct Aim {
+    /// `[pitch, yaw]` and when the target was last seen.
+    target: Option<([f64; 2], Instant)>,
+}
+
+/// Detector thread plus the state it shares with `TrackingInput`.
+pub struct HeadTracker {
+    enabled: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+    aim: Arc<Mutex<Aim>>,
+    max_speed: f64,
+    lost_after: Duration,
+}
+
+impl HeadTracker {
+    /// Load the detector and start following `feed`. Tracking starts off.
+    pub fn start(config: &HeadTrackingConfig, model: &Path, feed: FrameFeed) -> Result<Self> {
+        let mut detector = Detector::load(model, config)?;
+        let mut decoder = JpegDecoder::start(config.input_width, config.input_height)?;
+        let enabled = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let aim = Arc::new(Mutex::new(Aim::default()));
+
+        let interval = Duration::from_secs_f64(1.0 / config.rate_hz.max(0.1));
+        let mut aimer = HeadAimer::new(config);
+        let (worker_enabled, worker_stopped) = (Arc::clone(&enabled), Arc::clone(&stopped));
+        let worker_aim = Arc::clone(&aim);
+        thread::spawn(move || {
+            let mut seq = 0;
+            while !worker_stopped.load(Ordering::Acquire) && !feed.is_closed() {
+                if !worker_enabled.load(Ordering::Relaxed) {
+                    aimer.reset();
+                    thread::sleep(interval);
+                    continue;
+                }
+                let started = Instant::now();
+                let Some((next, jpeg)) = feed.wait_newer(seq, interval) else {
+                    continue;
+                };
+                seq = next;
+                match decoder.decode(&jpeg).and_then(|rgb| detector.detect(&rgb)) {
+                    Ok(Some(detection)) => {
+                        let target = aimer.update(&detection);
+                        if let Ok(mut aim) = worker_aim.lock() {
+                            aim.target = Some((target, Instant::now()));
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!("Head tracking stopped: {:#}", e);
+                        break;
+                    }
+                }
+                thread::sleep(interval.saturating_sub(started.elapsed()));
+            }
+            worker_enabled.store(false, Ordering::Relaxed);
+        });
+
+        tracing::info!("Head tracking ready ({}), LB + B toggles it", model.display());
+        Ok(Self {
+            enabled,
+            stopped,
+            aim,
+            max_speed: config.max_speed,
+            lost_after: Duration::from_secs_f64(config.lost_secs.max(0.0)),
+        })
+    }
+
+    /// Turn tracking on or off; returns whether it is now on.
+    pub fn toggle(&self) -> bool {
+        let enabled = !self.enabled.fetch_xor(true, Ordering::Relaxed);
+        if let Ok(mut aim) = self.aim.lock() {
+            aim.target = None;
+        }
+        enabled
+    }
+
+    /// Command source moving the head toward the aim.
+    pub fn input(&self) -> TrackingInput {
+        TrackingInput {
+            enabled: Arc::clone(&self.enabled),
+            aim: Arc::clone(&self.aim),
+            max_speed: self.max_speed,
+            lost_after: self.lost_after,
+            head: [0.0; 2],
+            last: None,
+            output: ControllerOutput::default(),
+        }
+    }
+}
+
+impl Drop for HeadTracker {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+    }
+}
+
+/// Head tracking as a `CommandSource`: head pitch and yaw only, and nothing
+/// once the head is back at center.
+pub struct TrackingInput {
+    enabled: Arc<AtomicBool>,
+    aim: Arc<Mutex<Aim>>,
+    max_speed: f64,
+    lost_after: Duration,
+    /// `[pitch, yaw]` commanded last.
+    head: [f64; 2],
+    last: Option<Instant>,
+    output: ControllerOutput,
+}
+
+impl TrackingInput {
+    /// Step the head toward the aim (center when lost or off).
+    fn step(&mut self, now: Instant) -> [f64; 2] {
+        let target = self
+            .aim
+            .lock()
+            .ok()
+            .and_then(|aim| aim.target)
+            .filter(|(_, seen)| now.saturating_duration_since(*seen) <= self.lost_after)
+            .filter(|_| self.enabled.load(Ordering::Relaxed))
+            .map_or([0.0; 2], |(target, _)| target);
+
+        let dt = self.last.map_or(0.0, |last| now.saturating_duration_since(last).as_secs_f64());
+        self.last = Some(now);
+        let max_step = self.max_speed * dt;
+        for (head, target) in self.head.iter_mut().zip(target) {
+            *head += (target - *head).clamp(-max_step, max_step);
+        }
+        self.head
+    }
+}
+
+impl CommandSource for TrackingInput {
+    fn get_last_command(&mut self) -> &ControllerOutput {
+        let [pitch, yaw] = self.step(Instant::now());
+        let centered = pitch.abs() < CENTERED && yaw.abs() < CENTERED;
+        self.output.commands = [0.0; 7];
+        if !centered {
+            self.output.commands[4] = pitch;
+            self.output.commands[5] = yaw;
+        }
+        &self.output
+    }
+}
+
+/// The ONNX detector.
+struct Detector {
+    session: Session,
+    input_name: String,
+    width: usize,
+    height: usize,
+    mean: f32,
+    std: f32,
+    class: usize,
+    threshold: f64,
+}
+
+impl Detector {
+    fn load(model: &Path, config: &HeadTrackingConfig) -> Result<Self> {
+        let session = Session::builder()
+            .context("Failed to create ONNX session builder")?
+            .commit_from_file(model)
+            .with_context(|| format!("Failed to load detector {}", model.display()))?;
+        if session.outputs().len() < 2 {
+            bail!("Detector {} must output scores and boxes", model.display());
+        }
+        let input_name = session.inputs()[0].name().to_string();
+        Ok(Self {
+            session,
+            input_name,
+            width: config.input_width as usize,
+            height: config.input_height as usize,
+            mean: config.mean as f32,
+            std: config.std as f32,
+            class: config.class,
+            threshold: config.score_threshold,
+        })
+    }
+
+    /// Detect in an RGB24 image of the input size.
+    fn detect(&mut self, rgb: &[u8]) -> Result<Option<Detection>> {
+        let (width, height) = (self.width, self.height);
+        let (mean, std) = (self.mean, self.std.max(f32::EPSILON));
+        let input = Array4::from_shape_fn((1, 3, height, width), |(_, c, y, x)| {
+            (rgb[(y * width + x) * 3 + c] as f32 - mean) / std
+        });
+        let input = Tensor::from_array(input).context("Failed to create detector input")?;
+        let outputs = self
+            .session
+            .run(ort::inputs![&self.input_name => input])
+            .context("Detector inference failed")?;
+
+        let (shape, scores) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .context("Failed to extract detector scores")?;
+        let classes = shape.last().and_then(|&c| usize::try_from(c).ok()).unwrap_or(0);
+        let (_, boxes) = outputs[1]
+            .try_extract_tensor::<f32>()
+            .context("Failed to extract detector boxes")?;
+        Ok(best_detection(scores, boxes, classes, self.class, self.threshold))
+    }
+}
+
+/// JPEG to raw RGB24 at a fixed size, through a long-running `ffmpeg`.
+struct JpegDecoder {
+    child: Child,
+    stdin: ChildStdin,
+    frames: Receiver<Vec<u8>>,
+}
+
+impl JpegDecoder {
+    fn start(width: u32, height: u32) -> Result<Self> {
+        let args = format!(
+            "-hide_banner -loglevel error -fflags nobuffer -probesize 32 -f mjpeg -i - \
+             -vf scale={}:{} -pix_fmt rgb24 -f rawvideo -",
+            width, height
+        );
+        let mut child = Command::new("ffmpeg")
+            .args(args.split_whitespace())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to start ffmpeg to decode camera frames")?;
+        let stdin = child.stdin.take().context("ffmpeg has no stdin")?;
+        let mut stdout = child.stdout.take().context("ffmpeg has no stdout")?;
+
+        // Read on a thread of its own: ffmpeg may hold a frame back until
+        // the next one arrives
+        let frame_bytes = width as usize * height as usize * 3;
+        let (tx, frames) = bounded(2);
+        thread::spawn(move || loop {
+            let mut frame = vec![0u8; frame_bytes];
+            match stdout.read_exact(&mut frame) {
+                Ok(()) => {
+                    if tx.send(frame).is_err() {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            frames,
+        })
+    }
+
+    /// Decode a JPEG; may return the previous frame's pixels while ffmpeg
+    /// is a frame behind.
+    fn decode(&mut self, jpeg: &[u8]) -> Result<Vec<u8>> {
+        self.stdin
+            .write_all(jpeg)
+            .and_then(|_| self.stdin.flush())
+            .context("Frame decoder exited")?;
+        let mut frame = match self.frames.recv_timeout(DECODE_TIMEOUT) {
+            Ok(frame) => frame,
+            Err(RecvTimeoutError::Timeout) => bail!("Frame decoder is not answering"),
+            Err(RecvTimeoutError::Disconnected) => bail!("Frame decoder exited"),
+        };
+        // Keep only the newest when it has caught up
+        while let Ok(newer) = self.frames.try_recv() {
+            frame = newer;
+        }
+        Ok(frame)
+    }
+}
+
+impl Drop for JpegDecoder {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection_turns_head_toward_target() {
+        // Two candidates for class 1; the second scores higher and sits in
+        // the right half, above center
+        let scores = [0.2, 0.8, 0.1, 0.9];
+        let boxes = [0.1, 0.1, 0.3, 0.3, 0.6, 0.2, 1.0, 0.4];
+        let detection = best_detection(&scores, &boxes, 2, 1, 0.7).unwrap();
+        assert!((detection.center[0] - 0.6).abs() < 1e-6);
+        assert!((detection.center[1] + 0.4).abs() < 1e-6);
+        assert!(best_detection(&scores, &boxes, 2, 1, 0.95).is_none());
+
+        let config = HeadTrackingConfig::default();
+        let mut aimer = HeadAimer::new(&config);
+        let [pitch, yaw] = aimer.update(&detection);
+        assert!(yaw < 0.0, "target on the right turns the head right");
+        assert!(pitch < 0.0, "target above center raises the head");
+
+        // The head eases toward the aim, then back to center once lost
+        let aim = Arc::new(Mutex::new(Aim::default()));
+        let start = Instant::now();
+        aim.lock().unwrap().target = Some(([pitch, yaw], start));
+        let mut input = TrackingInput {
+            enabled: Arc::new(AtomicBool::new(true)),
+            aim,
+            max_speed: 1.0,
+            lost_after: Duration::from_secs(1),
+            head: [0.0; 2],
+            last: Some(start),
+            output: ControllerOutput::default(),
+        };
+        let step = input.step(start + Duration::from_millis(10));
+        assert!((step[1] + 0.01).abs() < 1e-9);
+        assert_eq!(input.step(start + Duration::from_millis(500)), [pitch, yaw]);
+        assert_eq!(input.step(start + Duration::from_secs(2)), [0.0; 2]);
+    }
+}
@@ -0,0 +1,128 @@
+//! Servo health monitoring — temperature, voltage, load, and error flags.
+//!
+//! Health registers change slowly, so they are polled at a low rate on a
+//! secondary schedule inside the control loop. Readings past the warning
+//! thresholds are logged; readings past the hard limits request a soft-stop.
+
+use std::time::{Duration, Instant};
+
+use crate::config::ServoHealthConfig;
+use crate::motors::ServoHealth;
+
+/// Overall verdict for one health poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthStatus {
+    Ok,
+    Warning,
+    /// A hard limit was exceeded: the robot should soft-stop.
+    Fault,
+}
+
+/// Checks servo health readings against configured thresholds.
+pub struct HealthMonitor {
+    config: ServoHealthConfig,
+    poll_interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+impl HealthMonitor {
+    pub fn new(config: ServoHealthConfig) -> Self {
+        let poll_interval = Duration::from_secs_f64(config.poll_interval.max(0.1));
+        Self {
+            config,
+            poll_interval,
+            last_poll: None,
+        }
+    }
+
+    /// Whether health should be read on this tick.
+    pub fn needs_poll(&self, now: Instant) -> bool {
+        self.last_poll
+            .is_none_or(|last| now.duration_since(last) >= self.poll_interval)
+    }
+
+    /// Mark a poll as done, whether or not the read succeeded.
+    pub fn mark_polled(&mut self, now: Instant) {
+        self.last_poll = Some(now);
+    }
+
+    /// Check one set of readings, logging every servo out of range.
+    pub fn check(&self, health: &[ServoHealth], joint_names: &[String]) -> HealthStatus {
+        let mut status = HealthStatus::Ok;
+
+        for (h, name) in health.iter().zip(joint_names) {
+            let c = &self.config;
+
+            if h.temperature >= c.max_temperature {
+                tracing::error!(
+                    "Servo {} ({}) overheating: {:.0}°C >= {:.0}°C",
+                    h.id, name, h.temperature, c.max_temperature
+                );
+                status = status.max(HealthStatus::Fault);
+            } else if h.temperature >= c.warn_temperature {
+                tracing::warn!("Servo {} ({}) hot: {:.0}°C", h.id, name, h.temperature);
+                status = status.max(HealthStatus::Warning);
+            }
+
+            if h.voltage <= c.min_voltage || h.voltage >= c.max_voltage {
+                tracing::error!(
+                    "Servo {} ({}) input voltage {:.1}V outside {:.1}-{:.1}V",
+                    h.id, name, h.voltage, c.min_voltage, c.max_voltage
+                );
+                status = status.max(HealthStatus::Fault);
+            } else if h.voltage <= c.warn_voltage {
+                tracing::warn!("Servo {} ({}) low voltage: {:.1}V", h.id, name, h.voltage);
+                status = status.max(HealthStatus::Warning);
+            }
+
+            if h.load.abs() >= c.warn_load {
+                tracing::warn!(
+                    "Servo {} ({}) heavily loaded: {:.0}%",
+                    h.id,
+                    name,
+                    h.load * 100.0
+                );
+                status = status.max(HealthStatus::Warning);
+            }
+
+            if h.error_flags != 0 {
+                tracing::warn!(
+                    "Servo {} ({}) reports error flags {:#04x}",
+                    h.id,
+                    name,
+                    h.error_flags
+                );
+                status = status.max(HealthStatus::Warning);
+            }
+        }
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn servo(temperature: f64, voltage: f64) -> ServoHealth {
+        ServoHealth {
+            id: 1,
+            temperature,
+            voltage,
+            load: 0.1,
+            error_flags: 0,
+        }
+    }
+
+    #[test]
+    fn test_health_thresholds() {
+        let monitor = HealthMonitor::new(ServoHealthConfig::default());
+        let names = vec!["left_knee".to_string()];
+
+        assert_eq!(monitor.check(&[servo(40.0, 7.4)], &names), HealthStatus::Ok);
+        assert_eq!(monitor.check(&[servo(62.0, 7.4)], &names), HealthStatus::Warning);
+        assert_eq!(monitor.check(&[servo(40.0, 6.5)], &names), HealthStatus::Warning);
+        assert_eq!(monitor.check(&[servo(75.0, 7.4)], &names), HealthStatus::Fault);
+        assert_eq!(monitor.check(&[servo(40.0, 6.0)], &names), HealthStatus::Fault);
+    }
+}
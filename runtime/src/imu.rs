@@ -1,37 +1,167 @@
-//! BNO055 IMU sensor reading over I2C.
+//! IMU sensor reading over I2C, UART or SPI.
 //!
 //! Replaces `raw_imu.py`. Reads gyroscope and accelerometer data in a
 //! background thread at the control frequency, providing jitter-free data
-//! to the main control loop.
+//! to the main control loop. The chip is chosen by `imu_type` in
+//! `duck_config.json`: the BNO055 and MPU-6050 are fused here with a
+//! Madgwick filter, the BNO085's own game rotation vector is used as is.
+//! `imu_bus` picks the wiring: I2C for every chip, UART for a BNO055
+//! (sidestepping the Pi's I2C clock-stretching bug) and SPI for an
+//! MPU-6000, the SPI version of the MPU-6050.
+//!
+//! Every sample carries the time it was read. If the chip stops answering
+//! (a wedged I2C bus), `get_data` keeps returning the last sample, so the
+//! control loop checks its age; meanwhile the sampling thread reopens the
+//! chip every `REINIT_INTERVAL` until reads succeed again.
 
 // Hardware-specific imports are inside the cfg-gated hw module.
 
-/// IMU data packet: gyroscope and accelerometer readings.
-#[derive(Debug, Clone, Copy, Default)]
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+
+use crate::config::{ImuBusConfig, ImuType};
+use crate::orientation::{self, Quaternion};
+
+/// IMU data packet: raw gyroscope and accelerometer readings, plus the
+/// orientation estimated from them.
+#[derive(Debug, Clone, Copy)]
 pub struct ImuData {
     /// Gyroscope readings [x, y, z] in rad/s.
     pub gyro: [f64; 3],
     /// Accelerometer readings [x, y, z] in m/s^2.
     pub accel: [f64; 3],
+    /// Orientation quaternion [w, x, y, z] (body to world).
+    pub orientation: Quaternion,
+    /// Roll, pitch, yaw in radians.
+    pub euler: [f64; 3],
+    /// Unit gravity vector in the body frame ([0, 0, -1] when level).
+    pub projected_gravity: [f64; 3],
+    /// When the sample was read from the chip; `None` for data that didn't
+    /// come from one.
+    pub timestamp: Option<Instant>,
+}
+
+impl Default for ImuData {
+    fn default() -> Self {
+        Self {
+            gyro: [0.0; 3],
+            accel: [0.0; 3],
+            orientation: orientation::IDENTITY,
+            euler: [0.0; 3],
+            projected_gravity: [0.0, 0.0, -1.0],
+            timestamp: None,
+        }
+    }
+}
+
+impl ImuData {
+    /// Angle between the body's up axis and vertical, in radians.
+    pub fn tilt(&self) -> f64 {
+        (-self.projected_gravity[2]).clamp(-1.0, 1.0).acos()
+    }
+
+    /// Time since the sample was read, if it came from a chip.
+    pub fn age(&self, now: Instant) -> Option<Duration> {
+        self.timestamp.map(|t| now.saturating_duration_since(t))
+    }
 }
 
 /// Trait for IMU implementations (supports dependency injection for testing).
 pub trait ImuReader: Send {
     fn get_data(&self) -> ImuData;
     fn stop(&self);
+
+    /// Time since the newest sample was read, for readers that track it.
+    fn age(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Size of the BNO055 calibration offsets (accel, mag and gyro offsets,
+/// accel and mag radius).
+pub const CALIBRATION_LEN: usize = 22;
+
+/// BNO055 calibration levels, 0 (uncalibrated) to 3 (fully calibrated).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CalibrationStatus {
+    pub system: u8,
+    pub gyro: u8,
+    pub accel: u8,
+    pub mag: u8,
+}
+
+impl CalibrationStatus {
+    /// Decode the CALIB_STAT register.
+    pub fn from_register(value: u8) -> Self {
+        Self {
+            system: (value >> 6) & 0x03,
+            gyro: (value >> 4) & 0x03,
+            accel: (value >> 2) & 0x03,
+            mag: value & 0x03,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        [self.system, self.gyro, self.accel, self.mag].iter().all(|&level| level == 3)
+    }
+}
+
+/// Saved calibration offsets, or None if none were saved yet.
+pub fn load_calibration(path: &Path) -> Result<Option<[u8; CALIBRATION_LEN]>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    match data.try_into() {
+        Ok(offsets) => Ok(Some(offsets)),
+        Err(data) => bail!(
+            "{}: expected {} bytes of calibration, found {}",
+            path.display(),
+            CALIBRATION_LEN,
+            data.len()
+        ),
+    }
+}
+
+pub fn save_calibration(path: &Path, offsets: &[u8; CALIBRATION_LEN]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, offsets).with_context(|| format!("Failed to write {}", path.display()))
 }
 
 // ── Hardware implementation (Linux only — requires rppal / I2C) ──
 
 #[cfg(target_os = "linux")]
 mod hw {
-    use super::{ImuData, ImuReader};
-    use anyhow::{Context, Result};
+    use super::{
+        load_calibration, mount_quaternion, mount_vector, CalibrationStatus, ImuData, ImuReader,
+        CALIBRATION_LEN,
+    };
+    use crate::bno055_uart::{self, Response};
+    use crate::config::{ImuBusConfig, ImuTransport, ImuType};
+    use crate::orientation::{self, Madgwick, Quaternion};
+    use crate::peripherals::open_spi;
+    use crate::sh2::{self, Sh2Reading};
+    use anyhow::{bail, Context, Result};
     use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
     use rppal::i2c::I2c;
+    use rppal::spi::{Mode, Spi};
+    use rppal::uart::{Parity, Queue, Uart};
+    use std::path::Path;
     use std::thread;
     use std::time::{Duration, Instant};
 
+    /// Reads failing for this long make the sampling thread reopen the chip.
+    const REINIT_AFTER: Duration = Duration::from_millis(200);
+
+    /// Time between reopen attempts while the chip stays unreachable.
+    const REINIT_INTERVAL: Duration = Duration::from_secs(1);
+
     // BNO055 I2C address
     const BNO055_ADDR: u16 = 0x28;
 
@@ -41,62 +171,281 @@ mod hw {
     const BNO055_ACCEL_DATA: u8 = 0x08; // 6 bytes: X, Y, Z (each 2 bytes LE)
     const BNO055_AXIS_MAP_CONFIG: u8 = 0x41;
     const BNO055_AXIS_MAP_SIGN: u8 = 0x42;
+    const BNO055_CALIB_STAT: u8 = 0x35;
+    const BNO055_CALIB_OFFSETS: u8 = 0x55; // 22 bytes, written in config mode only
 
     // Operating modes
     const NDOF_MODE: u8 = 0x0C;
     const CONFIG_MODE: u8 = 0x00;
 
-    /// BNO055 IMU reader running in a background thread.
+    // MPU-6050 I2C address (AD0 low) and registers
+    const MPU6050_ADDR: u16 = 0x68;
+    const MPU6050_PWR_MGMT_1: u8 = 0x6B;
+    const MPU6050_USER_CTRL: u8 = 0x6A;
+    const MPU6050_I2C_IF_DIS: u8 = 0x10; // MPU-6000: SPI only from here on
+    const MPU6050_ACCEL_XOUT_H: u8 = 0x3B; // 14 bytes: accel, temperature, gyro (BE)
+
+    // MPU-6050 scales at the power-on ranges (±2 g, ±250 °/s)
+    const MPU6050_ACCEL_LSB_PER_G: f64 = 16384.0;
+    const MPU6050_GYRO_LSB_PER_DPS: f64 = 131.0;
+    const GRAVITY: f64 = 9.81;
+
+    /// Largest SHTP packet read; longer ones (only the startup
+    /// advertisement) are truncated, which is fine as they're ignored.
+    const SHTP_MAX_PACKET: usize = 512;
+
+    /// How long the BNO055 gets to answer a UART command.
+    const UART_TIMEOUT: Duration = Duration::from_millis(50);
+
+    /// Times a UART command is sent while the BNO055 reports itself busy.
+    const UART_ATTEMPTS: u32 = 3;
+
+    /// Register access to an IMU chip, whatever bus it hangs off.
+    trait Registers: Send {
+        fn write(&mut self, register: u8, data: &[u8]) -> Result<()>;
+        fn read(&mut self, register: u8, buf: &mut [u8]) -> Result<()>;
+    }
+
+    /// Open the transport `bus` configures to a BNO055 or MPU-6050.
+    fn open_registers(bus: &ImuBusConfig, imu_type: ImuType) -> Result<Box<dyn Registers>> {
+        Ok(match (bus.transport, imu_type) {
+            (ImuTransport::I2c, ImuType::Bno055) => Box::new(I2cRegisters::open(BNO055_ADDR)?),
+            (ImuTransport::I2c, ImuType::Mpu6050) => Box::new(I2cRegisters::open(MPU6050_ADDR)?),
+            (ImuTransport::Uart, ImuType::Bno055) => {
+                Box::new(Bno055Uart::open(&bus.uart_path, bus.uart_baud)?)
+            }
+            (ImuTransport::Spi, ImuType::Mpu6050) => Box::new(SpiRegisters::open(bus)?),
+            (transport, imu_type) => bail!("A {imu_type:?} IMU can't be wired over {transport:?}"),
+        })
+    }
+
+    struct I2cRegisters {
+        i2c: I2c,
+    }
+
+    impl I2cRegisters {
+        fn open(address: u16) -> Result<Self> {
+            let mut i2c = I2c::new().context("Failed to open I2C bus")?;
+            i2c.set_slave_address(address)
+                .context("Failed to set I2C slave address")?;
+            Ok(Self { i2c })
+        }
+    }
+
+    impl Registers for I2cRegisters {
+        fn write(&mut self, register: u8, data: &[u8]) -> Result<()> {
+            self.i2c.block_write(register, data).context("I2C write failed")
+        }
+
+        fn read(&mut self, register: u8, buf: &mut [u8]) -> Result<()> {
+            self.i2c.block_read(register, buf).context("I2C block read failed")
+        }
+    }
+
+    /// What the BNO055 answered a UART command with.
+    enum Reply {
+        Data(Vec<u8>),
+        Status(u8),
+    }
+
+    /// A BNO055 with PS1 high, on a serial port.
+    struct Bno055Uart {
+        uart: Uart,
+    }
+
+    impl Bno055Uart {
+        fn open(path: &str, baud: u32) -> Result<Self> {
+            let mut uart = Uart::with_path(path, baud, Parity::None, 8, 1)
+                .with_context(|| format!("Failed to open {path}"))?;
+            uart.set_read_mode(0, UART_TIMEOUT)?;
+            uart.set_write_mode(true)?;
+            Ok(Self { uart })
+        }
+
+        /// Send `command`, again while the chip answers that it was busy.
+        fn transact(&mut self, command: &[u8]) -> Result<Reply> {
+            let mut attempts = 0;
+            loop {
+                attempts += 1;
+                match self.exchange(command)? {
+                    Reply::Status(status)
+                        if bno055_uart::is_transient(status) && attempts < UART_ATTEMPTS => {}
+                    reply => return Ok(reply),
+                }
+            }
+        }
+
+        fn exchange(&mut self, command: &[u8]) -> Result<Reply> {
+            // Drop what's left of an answer that came too late
+            self.uart.flush(Queue::Input)?;
+            self.uart.write(command).context("UART write failed")?;
+
+            let deadline = Instant::now() + UART_TIMEOUT;
+            let mut received = Vec::new();
+            let mut chunk = [0u8; 64];
+            loop {
+                match bno055_uart::parse_response(&received) {
+                    Response::Data(data) => return Ok(Reply::Data(data.to_vec())),
+                    Response::Status(status) => return Ok(Reply::Status(status)),
+                    Response::Invalid => {
+                        received.remove(0);
+                        continue;
+                    }
+                    Response::Incomplete => {}
+                }
+                if Instant::now() >= deadline {
+                    bail!("No answer from the BNO055 over UART");
+                }
+                let len = self.uart.read(&mut chunk).context("UART read failed")?;
+                received.extend_from_slice(&chunk[..len]);
+            }
+        }
+    }
+
+    impl Registers for Bno055Uart {
+        fn write(&mut self, register: u8, data: &[u8]) -> Result<()> {
+            match self.transact(&bno055_uart::write_command(register, data))? {
+                Reply::Status(bno055_uart::WRITE_SUCCESS) => Ok(()),
+                Reply::Status(status) => bail!(
+                    "BNO055 write to {register:#04x} failed: {}",
+                    bno055_uart::status_message(status)
+                ),
+                Reply::Data(_) => bail!("BNO055 answered a write with data"),
+            }
+        }
+
+        fn read(&mut self, register: u8, buf: &mut [u8]) -> Result<()> {
+            match self.transact(&bno055_uart::read_command(register, buf.len()))? {
+                Reply::Data(data) if data.len() == buf.len() => {
+                    buf.copy_from_slice(&data);
+                    Ok(())
+                }
+                Reply::Data(data) => bail!("BNO055 sent {} of {} bytes", data.len(), buf.len()),
+                Reply::Status(status) => bail!(
+                    "BNO055 read of {register:#04x} failed: {}",
+                    bno055_uart::status_message(status)
+                ),
+            }
+        }
+    }
+
+    /// An MPU-6000 on `/dev/spidev<bus>.<chip_select>`.
+    struct SpiRegisters {
+        spi: Spi,
+    }
+
+    impl SpiRegisters {
+        fn open(bus: &ImuBusConfig) -> Result<Self> {
+            let spi = open_spi(bus.spi_bus, bus.spi_chip_select, bus.spi_clock_hz, Mode::Mode3)?;
+            Ok(Self { spi })
+        }
+    }
+
+    impl Registers for SpiRegisters {
+        fn write(&mut self, register: u8, data: &[u8]) -> Result<()> {
+            let mut frame = vec![register & 0x7F];
+            frame.extend_from_slice(data);
+            self.spi.write(&frame).context("SPI write failed")?;
+            Ok(())
+        }
+
+        fn read(&mut self, register: u8, buf: &mut [u8]) -> Result<()> {
+            // The first byte clocks out the register, the rest clock in data
+            let mut frame = vec![0u8; buf.len() + 1];
+            frame[0] = register | 0x80;
+            let mut received = vec![0u8; frame.len()];
+            self.spi.transfer(&mut received, &frame).context("SPI read failed")?;
+            buf.copy_from_slice(&received[1..]);
+            Ok(())
+        }
+    }
+
+    /// One reading from an IMU chip, in the body frame.
+    struct Sample {
+        gyro: [f64; 3],
+        accel: [f64; 3],
+        /// Orientation fused on the chip, if it provides one.
+        orientation: Option<Quaternion>,
+    }
+
+    /// An IMU chip polled by the sampling thread.
+    trait ImuDevice: Send {
+        fn read(&mut self) -> Result<Sample>;
+    }
+
+    /// IMU reader running in a background thread.
     pub struct Imu {
         receiver: Receiver<ImuData>,
         stop_tx: Sender<()>,
         last_data: std::cell::Cell<ImuData>,
+        last_update: std::cell::Cell<Option<Instant>>,
     }
 
     impl Imu {
-        /// Initialize the BNO055 and start the background sampling thread.
-        pub fn new(sampling_freq: u32, upside_down: bool) -> Result<Self> {
+        /// Initialize the chip and start the background sampling thread. A
+        /// BNO055 gets the offsets saved at `calibration_path`, if any.
+        pub fn new(
+            imu_type: ImuType,
+            bus: &ImuBusConfig,
+            sampling_freq: u32,
+            upside_down: bool,
+            calibration_path: &Path,
+        ) -> Result<Self> {
+            if imu_type == ImuType::Bno085 && bus.transport != ImuTransport::I2c {
+                bail!("A Bno085 IMU can't be wired over {:?}", bus.transport);
+            }
             let (data_tx, data_rx) = bounded::<ImuData>(1);
             let (stop_tx, stop_rx) = bounded::<()>(1);
 
-            let mut i2c = I2c::new().context("Failed to open I2C bus")?;
-            i2c.set_slave_address(BNO055_ADDR)
-                .context("Failed to set I2C slave address")?;
-
-            // Enter config mode for axis remap
-            i2c.smbus_write_byte(BNO055_OPR_MODE, CONFIG_MODE)?;
-            thread::sleep(Duration::from_millis(25));
-
-            // Remap axes for the duck's orientation
-            i2c.smbus_write_byte(BNO055_AXIS_MAP_CONFIG, 0x21)?;
-
-            // Set axis signs based on mounting orientation
-            if upside_down {
-                i2c.smbus_write_byte(BNO055_AXIS_MAP_SIGN, 0x07)?;
-            } else {
-                i2c.smbus_write_byte(BNO055_AXIS_MAP_SIGN, 0x04)?;
+            let offsets = match imu_type {
+                ImuType::Bno055 => load_calibration(calibration_path)?,
+                _ => None,
+            };
+            if imu_type == ImuType::Bno055 && offsets.is_none() {
+                tracing::warn!(
+                    "No BNO055 calibration at {}, run --imu-calibrate",
+                    calibration_path.display()
+                );
             }
-
-            // Enter NDOF mode
-            i2c.smbus_write_byte(BNO055_OPR_MODE, NDOF_MODE)?;
-            thread::sleep(Duration::from_millis(25));
+            let transport = bus.transport;
+            let bus = bus.clone();
+            let open = move || -> Result<Box<dyn ImuDevice>> {
+                Ok(match imu_type {
+                    ImuType::Bno055 => Box::new(Bno055::new(
+                        open_registers(&bus, imu_type)?,
+                        upside_down,
+                        offsets.as_ref(),
+                    )?),
+                    ImuType::Bno085 => Box::new(Bno085::new(sampling_freq, upside_down)?),
+                    ImuType::Mpu6050 => Box::new(Mpu6050::new(
+                        open_registers(&bus, imu_type)?,
+                        bus.transport == ImuTransport::Spi,
+                        upside_down,
+                    )?),
+                })
+            };
+            let device = open()?;
 
             tracing::info!(
-                "BNO055 IMU initialized at {} Hz (upside_down={})",
+                "{:?} IMU over {:?} initialized at {} Hz (upside_down={})",
+                imu_type,
+                transport,
                 sampling_freq,
                 upside_down
             );
 
             // Spawn background reader thread
             let period = Duration::from_secs_f64(1.0 / sampling_freq as f64);
+            let drain_rx = data_rx.clone();
             thread::spawn(move || {
-                imu_worker(i2c, data_tx, stop_rx, period);
+                imu_worker(device, open, data_tx, drain_rx, stop_rx, period);
             });
 
             Ok(Self {
                 receiver: data_rx,
                 stop_tx,
                 last_data: std::cell::Cell::new(ImuData::default()),
+                last_update: std::cell::Cell::new(None),
             })
         }
 
@@ -104,6 +453,7 @@ mod hw {
         pub fn get_data(&self) -> ImuData {
             if let Ok(data) = self.receiver.try_recv() {
                 self.last_data.set(data);
+                self.last_update.set(Some(Instant::now()));
             }
             self.last_data.get()
         }
@@ -122,6 +472,10 @@ mod hw {
         fn stop(&self) {
             self.stop()
         }
+
+        fn age(&self) -> Option<Duration> {
+            self.last_update.get().map(|t| t.elapsed())
+        }
     }
 
     impl Drop for Imu {
@@ -130,13 +484,21 @@ mod hw {
         }
     }
 
-    /// Background worker that reads IMU data at a fixed frequency.
+    /// Background worker that reads IMU data at a fixed frequency, and
+    /// reopens the chip with `open` while reads keep failing.
     fn imu_worker(
-        mut i2c: I2c,
+        mut device: Box<dyn ImuDevice>,
+        mut open: impl FnMut() -> Result<Box<dyn ImuDevice>>,
         data_tx: Sender<ImuData>,
+        drain_rx: Receiver<ImuData>,
         stop_rx: Receiver<()>,
         period: Duration,
     ) {
+        let mut filter = Madgwick::default();
+        let mut last_sample: Option<Instant> = None;
+        let mut failing_since: Option<Instant> = None;
+        let mut last_reopen: Option<Instant> = None;
+
         loop {
             let start = Instant::now();
 
@@ -144,28 +506,66 @@ mod hw {
                 break;
             }
 
-            let gyro = match read_vector(&mut i2c, BNO055_GYRO_DATA) {
-                Ok(raw) => [raw[0] / 900.0, raw[1] / 900.0, raw[2] / 900.0],
-                Err(e) => {
-                    tracing::trace!("IMU gyro read error: {}", e);
-                    continue;
+            let sample = match device.read() {
+                Ok(sample) => {
+                    if failing_since.take().is_some() && last_reopen.take().is_some() {
+                        tracing::info!("IMU reads recovered");
+                    }
+                    sample
                 }
-            };
-
-            let accel = match read_vector(&mut i2c, BNO055_ACCEL_DATA) {
-                Ok(raw) => [raw[0] / 100.0, raw[1] / 100.0, raw[2] / 100.0],
                 Err(e) => {
-                    tracing::trace!("IMU accel read error: {}", e);
+                    tracing::trace!("IMU read error: {}", e);
+                    let since = *failing_since.get_or_insert(start);
+                    let due =
+                        last_reopen.is_none_or(|t| start.duration_since(t) >= REINIT_INTERVAL);
+                    if start.duration_since(since) >= REINIT_AFTER && due {
+                        if last_reopen.is_none() {
+                            tracing::warn!("IMU reads failing ({:#}), reopening the chip", e);
+                        }
+                        last_reopen = Some(start);
+                        match open() {
+                            Ok(reopened) => {
+                                device = reopened;
+                                filter = Madgwick::default();
+                                last_sample = None;
+                            }
+                            Err(e) => tracing::debug!("IMU reopen failed: {:#}", e),
+                        }
+                    }
+                    // Don't spin on a dead bus
+                    let elapsed = start.elapsed();
+                    if elapsed < period {
+                        spin_sleep::sleep(period - elapsed);
+                    }
                     continue;
                 }
             };
 
-            let data = ImuData { gyro, accel };
+            let mut data = ImuData {
+                gyro: sample.gyro,
+                accel: sample.accel,
+                timestamp: Some(start),
+                ..ImuData::default()
+            };
+            match sample.orientation {
+                Some(q) => {
+                    data.orientation = q;
+                    data.euler = orientation::euler(q);
+                    data.projected_gravity = orientation::projected_gravity(q);
+                }
+                None => {
+                    let dt = last_sample.map_or(0.0, |t| start.duration_since(t).as_secs_f64());
+                    filter.update(sample.gyro, sample.accel, dt);
+                    filter.annotate(&mut data);
+                }
+            }
+            last_sample = Some(start);
 
             match data_tx.try_send(data) {
                 Ok(()) => {}
                 Err(TrySendError::Full(_)) => {
-                    let _ = data_tx.try_recv();
+                    // Drop the stale sample so the consumer always sees the freshest one
+                    let _ = drain_rx.try_recv();
                     let _ = data_tx.try_send(data);
                 }
                 Err(TrySendError::Disconnected(_)) => break,
@@ -180,22 +580,327 @@ mod hw {
         tracing::info!("IMU worker thread exiting");
     }
 
-    /// Read a 3-axis vector (6 bytes, little-endian i16) from the BNO055.
-    fn read_vector(i2c: &mut I2c, register: u8) -> Result<[f64; 3]> {
-        let mut buf = [0u8; 6];
-        i2c.block_read(register, &mut buf)
-            .context("I2C block read failed")?;
+    /// Bosch BNO055, axes remapped on the chip.
+    struct Bno055 {
+        regs: Box<dyn Registers>,
+    }
+
+    impl Bno055 {
+        fn new(
+            mut regs: Box<dyn Registers>,
+            upside_down: bool,
+            offsets: Option<&[u8; CALIBRATION_LEN]>,
+        ) -> Result<Self> {
+            // Enter config mode for axis remap
+            regs.write(BNO055_OPR_MODE, &[CONFIG_MODE])?;
+            thread::sleep(Duration::from_millis(25));
+
+            // Remap axes for the duck's orientation
+            regs.write(BNO055_AXIS_MAP_CONFIG, &[0x21])?;
+
+            // Set axis signs based on mounting orientation
+            if upside_down {
+                regs.write(BNO055_AXIS_MAP_SIGN, &[0x07])?;
+            } else {
+                regs.write(BNO055_AXIS_MAP_SIGN, &[0x04])?;
+            }
+
+            if let Some(offsets) = offsets {
+                regs.write(BNO055_CALIB_OFFSETS, offsets)
+                    .context("Failed to restore BNO055 calibration")?;
+                tracing::info!("BNO055 calibration restored");
+            }
+
+            // Enter NDOF mode
+            regs.write(BNO055_OPR_MODE, &[NDOF_MODE])?;
+            thread::sleep(Duration::from_millis(25));
+
+            Ok(Self { regs })
+        }
+
+        fn calibration_status(&mut self) -> Result<CalibrationStatus> {
+            let mut value = [0u8];
+            self.regs.read(BNO055_CALIB_STAT, &mut value)?;
+            Ok(CalibrationStatus::from_register(value[0]))
+        }
+
+        /// Read the calibration offsets. The chip only exposes them in config
+        /// mode, so fusion stops for about 50 ms.
+        fn calibration_offsets(&mut self) -> Result<[u8; CALIBRATION_LEN]> {
+            self.regs.write(BNO055_OPR_MODE, &[CONFIG_MODE])?;
+            thread::sleep(Duration::from_millis(25));
+            let mut offsets = [0u8; CALIBRATION_LEN];
+            let read = self.regs.read(BNO055_CALIB_OFFSETS, &mut offsets);
+            self.regs.write(BNO055_OPR_MODE, &[NDOF_MODE])?;
+            thread::sleep(Duration::from_millis(25));
+            read.context("Failed to read BNO055 calibration")?;
+            Ok(offsets)
+        }
+
+        /// Read a 3-axis vector (6 bytes, little-endian i16).
+        fn read_vector(&mut self, register: u8) -> Result<[f64; 3]> {
+            let mut buf = [0u8; 6];
+            self.regs.read(register, &mut buf)?;
+
+            let x = i16::from_le_bytes([buf[0], buf[1]]) as f64;
+            let y = i16::from_le_bytes([buf[2], buf[3]]) as f64;
+            let z = i16::from_le_bytes([buf[4], buf[5]]) as f64;
+
+            Ok([x, y, z])
+        }
+    }
+
+    impl ImuDevice for Bno055 {
+        fn read(&mut self) -> Result<Sample> {
+            let gyro = self.read_vector(BNO055_GYRO_DATA).context("gyro")?.map(|v| v / 900.0);
+            let accel = self.read_vector(BNO055_ACCEL_DATA).context("accel")?.map(|v| v / 100.0);
+            Ok(Sample {
+                gyro,
+                accel,
+                orientation: None,
+            })
+        }
+    }
+
+    /// A BNO055 opened directly, without the sampling thread, to watch its
+    /// calibration and save the offsets.
+    pub struct Bno055Calibration {
+        chip: Bno055,
+    }
+
+    impl Bno055Calibration {
+        pub fn open(upside_down: bool, bus: &ImuBusConfig) -> Result<Self> {
+            Ok(Self {
+                chip: Bno055::new(open_registers(bus, ImuType::Bno055)?, upside_down, None)?,
+            })
+        }
+
+        pub fn status(&mut self) -> Result<CalibrationStatus> {
+            self.chip.calibration_status()
+        }
+
+        pub fn offsets(&mut self) -> Result<[u8; CALIBRATION_LEN]> {
+            self.chip.calibration_offsets()
+        }
+    }
+
+    /// CEVA BNO085 over SH-2, axes remapped in software.
+    struct Bno085 {
+        i2c: I2c,
+        upside_down: bool,
+        /// Next sequence number per SHTP channel.
+        sequence: [u8; 6],
+        reading: Sh2Reading,
+    }
+
+    impl Bno085 {
+        fn new(sampling_freq: u32, upside_down: bool) -> Result<Self> {
+            let mut i2c = I2c::new().context("Failed to open I2C bus")?;
+            i2c.set_slave_address(sh2::I2C_ADDR)
+                .context("Failed to set I2C slave address")?;
+            let mut imu = Self {
+                i2c,
+                upside_down,
+                sequence: [0; 6],
+                reading: Sh2Reading::default(),
+            };
+
+            imu.send(sh2::CHANNEL_EXECUTABLE, &[sh2::EXECUTABLE_RESET])?;
+            thread::sleep(Duration::from_millis(300));
+            // Flush the advertisement and reset notices
+            while imu.receive()?.is_some() {}
+
+            let interval_us = 1_000_000 / sampling_freq.max(1);
+            for report in [
+                sh2::REPORT_GAME_ROTATION_VECTOR,
+                sh2::REPORT_GYROSCOPE,
+                sh2::REPORT_ACCELEROMETER,
+            ] {
+                imu.send(sh2::CHANNEL_CONTROL, &sh2::set_feature(report, interval_us))
+                    .context("Failed to enable BNO085 report")?;
+            }
+            Ok(imu)
+        }
+
+        fn send(&mut self, channel: u8, payload: &[u8]) -> Result<()> {
+            let sequence = &mut self.sequence[channel as usize];
+            let packet = sh2::packet(channel, *sequence, payload);
+            *sequence = sequence.wrapping_add(1);
+            self.i2c.write(&packet).context("SHTP write failed")?;
+            Ok(())
+        }
+
+        /// Read one packet: its channel and payload, or None when the hub
+        /// has nothing queued.
+        fn receive(&mut self) -> Result<Option<(u8, Vec<u8>)>> {
+            let mut header = [0u8; sh2::HEADER_LEN];
+            self.i2c.read(&mut header).context("SHTP read failed")?;
+            let (len, channel) = sh2::parse_header(&header);
+            if len <= sh2::HEADER_LEN {
+                return Ok(None);
+            }
+            // Each read restarts at the packet header
+            let mut packet = vec![0u8; len.min(SHTP_MAX_PACKET)];
+            self.i2c.read(&mut packet).context("SHTP read failed")?;
+            Ok(Some((channel, packet.split_off(sh2::HEADER_LEN))))
+        }
+    }
+
+    impl ImuDevice for Bno085 {
+        fn read(&mut self) -> Result<Sample> {
+            while let Some((channel, payload)) = self.receive()? {
+                if channel == sh2::CHANNEL_REPORTS {
+                    sh2::parse_reports(&payload, &mut self.reading);
+                }
+            }
+            let Sh2Reading {
+                accel: Some(accel),
+                gyro: Some(gyro),
+                orientation: Some(q),
+            } = self.reading
+            else {
+                bail!("waiting for the first BNO085 reports");
+            };
+            Ok(Sample {
+                gyro: mount_vector(gyro, self.upside_down),
+                accel: mount_vector(accel, self.upside_down),
+                orientation: Some(mount_quaternion(q, self.upside_down)),
+            })
+        }
+    }
+
+    /// InvenSense MPU-6050, axes remapped in software.
+    struct Mpu6050 {
+        regs: Box<dyn Registers>,
+        upside_down: bool,
+    }
+
+    impl Mpu6050 {
+        fn new(mut regs: Box<dyn Registers>, spi: bool, upside_down: bool) -> Result<Self> {
+            // Wake from sleep
+            regs.write(MPU6050_PWR_MGMT_1, &[0x00])?;
+            thread::sleep(Duration::from_millis(100));
+            if spi {
+                regs.write(MPU6050_USER_CTRL, &[MPU6050_I2C_IF_DIS])?;
+            }
+            Ok(Self { regs, upside_down })
+        }
+    }
+
+    impl ImuDevice for Mpu6050 {
+        fn read(&mut self) -> Result<Sample> {
+            let mut buf = [0u8; 14];
+            self.regs.read(MPU6050_ACCEL_XOUT_H, &mut buf)?;
+            let word = |i: usize| i16::from_be_bytes([buf[2 * i], buf[2 * i + 1]]) as f64;
+
+            let accel = [word(0), word(1), word(2)].map(|v| v / MPU6050_ACCEL_LSB_PER_G * GRAVITY);
+            let gyro = [word(4), word(5), word(6)]
+                .map(|v| (v / MPU6050_GYRO_LSB_PER_DPS).to_radians());
+            Ok(Sample {
+                gyro: mount_vector(gyro, self.upside_down),
+                accel: mount_vector(accel, self.upside_down),
+                orientation: None,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Reads fail until the device is reopened.
+        struct Wedged;
+
+        impl ImuDevice for Wedged {
+            fn read(&mut self) -> Result<Sample> {
+                bail!("bus wedged")
+            }
+        }
+
+        struct Level;
+
+        impl ImuDevice for Level {
+            fn read(&mut self) -> Result<Sample> {
+                Ok(Sample {
+                    gyro: [0.0; 3],
+                    accel: [0.0, 0.0, GRAVITY],
+                    orientation: Some(orientation::IDENTITY),
+                })
+            }
+        }
+
+        #[test]
+        fn test_worker_reopens_a_wedged_chip() {
+            let (data_tx, data_rx) = bounded::<ImuData>(1);
+            let (stop_tx, stop_rx) = bounded::<()>(1);
+            let drain_rx = data_rx.clone();
+            let opened = Instant::now();
+            let open = || -> Result<Box<dyn ImuDevice>> { Ok(Box::new(Level)) };
+            let worker = thread::spawn(move || {
+                let period = Duration::from_millis(5);
+                imu_worker(Box::new(Wedged), open, data_tx, drain_rx, stop_rx, period);
+            });
 
-        let x = i16::from_le_bytes([buf[0], buf[1]]) as f64;
-        let y = i16::from_le_bytes([buf[2], buf[3]]) as f64;
-        let z = i16::from_le_bytes([buf[4], buf[5]]) as f64;
+            let data = data_rx.recv_timeout(Duration::from_secs(2)).unwrap();
+            assert!(data.timestamp.is_some_and(|t| t.duration_since(opened) >= REINIT_AFTER));
+            assert!(data.age(Instant::now()).unwrap() < Duration::from_secs(1));
 
-        Ok([x, y, z])
+            stop_tx.send(()).unwrap();
+            worker.join().unwrap();
+        }
     }
 }
 
 #[cfg(target_os = "linux")]
-pub use hw::Imu;
+pub use hw::{Bno055Calibration, Imu};
+
+/// Open the configured IMU chip.
+#[cfg(target_os = "linux")]
+pub fn open(
+    imu_type: ImuType,
+    bus: &ImuBusConfig,
+    sampling_freq: u32,
+    upside_down: bool,
+    calibration_path: &Path,
+) -> Result<Box<dyn ImuReader>> {
+    Ok(Box::new(Imu::new(imu_type, bus, sampling_freq, upside_down, calibration_path)?))
+}
+
+/// Open the configured IMU chip. There is no I2C off Linux: a mock.
+#[cfg(not(target_os = "linux"))]
+pub fn open(
+    _imu_type: ImuType,
+    _bus: &ImuBusConfig,
+    _sampling_freq: u32,
+    _upside_down: bool,
+    _calibration_path: &Path,
+) -> Result<Box<dyn ImuReader>> {
+    Ok(Box::new(MockImu::new()))
+}
+
+/// Map a sensor-frame vector to the body frame for chips remapped in
+/// software, matching the BNO055's on-chip axis remap: X and Y swapped with X
+/// negated, or all three negated when mounted upside down.
+fn mount_vector(v: [f64; 3], upside_down: bool) -> [f64; 3] {
+    if upside_down {
+        [-v[1], -v[0], -v[2]]
+    } else {
+        [-v[1], v[0], v[2]]
+    }
+}
+
+/// The body-frame orientation for a sensor-frame one, consistent with
+/// `mount_vector`.
+fn mount_quaternion(q: Quaternion, upside_down: bool) -> Quaternion {
+    // Inverse of the mounting rotation: 180° about (1, -1, 0) upside down,
+    // 90° about z otherwise
+    let inverse_mount = if upside_down {
+        [0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0]
+    } else {
+        [FRAC_1_SQRT_2, 0.0, 0.0, -FRAC_1_SQRT_2]
+    };
+    orientation::multiply(q, inverse_mount)
+}
 
 /// Mock IMU for testing without hardware.
 pub struct MockImu {
@@ -208,11 +913,18 @@ impl MockImu {
             data: ImuData {
                 gyro: [0.0; 3],
                 accel: [0.0, 0.0, 9.81],
+                ..ImuData::default()
             },
         }
     }
 }
 
+impl Default for MockImu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ImuReader for MockImu {
     fn get_data(&self) -> ImuData {
         self.data
@@ -220,3 +932,39 @@ impl ImuReader for MockImu {
 
     fn stop(&self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibration_status_and_file() {
+        let status = CalibrationStatus::from_register(0b11_11_01_10);
+        assert_eq!((status.system, status.gyro, status.accel, status.mag), (3, 3, 1, 2));
+        assert!(!status.is_complete());
+        assert!(CalibrationStatus::from_register(0xFF).is_complete());
+
+        let dir = std::env::temp_dir().join(format!("odr-imu-cal-{}", std::process::id()));
+        let path = dir.join("bno055.bin");
+        assert_eq!(load_calibration(&path).unwrap(), None);
+        let offsets: [u8; CALIBRATION_LEN] = std::array::from_fn(|i| i as u8);
+        save_calibration(&path, &offsets).unwrap();
+        assert_eq!(load_calibration(&path).unwrap(), Some(offsets));
+        fs::write(&path, [1, 2, 3]).unwrap();
+        assert!(load_calibration(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mounting_keeps_gravity_consistent() {
+        // Sensor pitched and rolled a little
+        let q = orientation::multiply([0.98, 0.0, 0.199, 0.0], [0.995, 0.0998, 0.0, 0.0]);
+        for upside_down in [false, true] {
+            let from_vector = mount_vector(orientation::projected_gravity(q), upside_down);
+            let from_quaternion = orientation::projected_gravity(mount_quaternion(q, upside_down));
+            for (a, b) in from_vector.iter().zip(from_quaternion) {
+                assert!((a - b).abs() < 1e-9, "{:?} vs {:?}", from_vector, from_quaternion);
+            }
+        }
+    }
+}
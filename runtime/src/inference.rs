@@ -1,88 +1,442 @@
-//! ONNX policy inference — loads a trained neural network and runs forward passes.
+//! Policy inference — loads a trained neural network and runs forward passes.
 //!
-//! Replaces `onnx_infer.py`. Uses the `ort` crate (ONNX Runtime bindings for Rust).
+//! Replaces `onnx_infer.py`. Two backends share the `PolicyInference` trait:
+//! ONNX Runtime through the `ort` crate (default), and the pure-Rust `tract`
+//! engine (`--features tract`), which needs no libonnxruntime on the robot.
+//!
+//! Recurrent (LSTM/GRU) models are supported by both: every model input
+//! after the observation is hidden state, fed back from the model output at
+//! the same position (`obs, h_in, c_in -> actions, h_out, c_out`). The state
+//! starts at zero and is zeroed again by `reset_state`.
+//!
+//! ONNX Runtime sessions take their thread count, graph optimization level
+//! and execution provider from the `ort` config section. Quantized exports
+//! load like any other model: int8 ones keep float32 inputs and outputs,
+//! and float16 ones get their observation, state and outputs converted.
 
-use anyhow::{Context, Result};
-use ndarray::Array2;
+use anyhow::{anyhow, bail, Context, Result};
+use half::f16;
+#[cfg(any(feature = "xnnpack", feature = "gpu"))]
+use ort::ep;
+use ort::session::builder::{GraphOptimizationLevel, SessionBuilder};
 use ort::session::Session;
-use ort::value::Tensor;
+use ort::value::{DynValue, Tensor};
+use std::num::NonZeroUsize;
 use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::{ExecutionProvider, OptimizationLevel, OrtConfig};
+use crate::normalization::NormalizedPolicy;
+
+/// A loaded policy: observation vector in, action vector out.
+pub trait PolicyInference: Send {
+    /// Run a forward pass.
+    ///
+    /// The observation is a 1-D array. The output is a 1-D action vector
+    /// (typically 14 DOF for Open Duck Mini).
+    fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>>;
+
+    /// Observation length the model expects, if its input shape is static.
+    fn input_dim(&self) -> Option<usize>;
+
+    /// Zero the hidden state of a recurrent model, e.g. when the policy
+    /// takes over control again. Models without state ignore it.
+    fn reset_state(&mut self) {}
+
+    /// Benchmark inference latency (useful for verifying real-time performance).
+    fn benchmark(&mut self, obs_dim: usize, iterations: usize) -> Result<Duration> {
+        let dummy_obs: Vec<f64> = vec![0.0; obs_dim];
+        let start = Instant::now();
+
+        for _ in 0..iterations {
+            self.infer(&dummy_obs)?;
+        }
+
+        let elapsed = start.elapsed();
+        let avg = elapsed / iterations.max(1) as u32;
+        tracing::info!(
+            "Inference benchmark: {} iterations, avg {:.2}ms ({:.0} Hz)",
+            iterations,
+            avg.as_secs_f64() * 1000.0,
+            1.0 / avg.as_secs_f64()
+        );
+
+        // Don't carry state from the dummy observations into real runs
+        self.reset_state();
+        Ok(avg)
+    }
+}
+
+/// Which inference engine runs the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InferenceBackend {
+    /// ONNX Runtime (needs the libonnxruntime shared library).
+    Ort,
+    /// tract, pure Rust (requires building with `--features tract`).
+    Tract,
+}
+
+/// Load a policy with the selected backend. `ort` only applies to ONNX
+/// Runtime.
+pub fn load_policy(
+    backend: InferenceBackend,
+    ort: &OrtConfig,
+    model_path: &Path,
+) -> Result<Box<dyn PolicyInference>> {
+    let policy: Box<dyn PolicyInference> = match backend {
+        InferenceBackend::Ort => Box::new(OrtPolicy::load(model_path, ort)?),
+        #[cfg(feature = "tract")]
+        InferenceBackend::Tract => Box::new(tract_backend::TractPolicy::load(model_path)?),
+        #[cfg(not(feature = "tract"))]
+        InferenceBackend::Tract => {
+            anyhow::bail!("This runtime was built without tract support (rebuild with --features tract)")
+        }
+    };
+    NormalizedPolicy::for_model(policy, model_path)
+}
+
+/// ONNX Runtime session builder with the configured threads, graph
+/// optimizations and execution provider. An execution provider this build
+/// or the onnxruntime library lacks is an error, not a silent fallback to the
+/// CPU.
+pub fn session_builder(config: &OrtConfig) -> Result<SessionBuilder> {
+    let level = match config.optimization_level {
+        OptimizationLevel::Disable => GraphOptimizationLevel::Disable,
+        OptimizationLevel::Basic => GraphOptimizationLevel::Level1,
+        OptimizationLevel::Extended => GraphOptimizationLevel::Level2,
+        OptimizationLevel::All => GraphOptimizationLevel::All,
+    };
+    let builder = Session::builder()
+        .context("Failed to create ONNX session builder")?
+        .with_optimization_level(level)
+        .map_err(builder_error("set the graph optimization level"))?;
+    let threads = NonZeroUsize::new(config.intra_threads);
+    let (builder, threads) = match config.execution_provider {
+        ExecutionProvider::Cpu => (builder, threads),
+        #[cfg(feature = "xnnpack")]
+        ExecutionProvider::Xnnpack => {
+            // XNNPACK runs its own thread pool, the session's would compete
+            let mut xnnpack = ep::XNNPACK::default();
+            if let Some(threads) = threads {
+                xnnpack = xnnpack.with_intra_op_num_threads(threads);
+            }
+            let builder = builder
+                .with_execution_providers([xnnpack.build().error_on_failure()])
+                .map_err(builder_error("enable the XNNPACK execution provider"))?;
+            (builder, NonZeroUsize::new(1))
+        }
+        #[cfg(not(feature = "xnnpack"))]
+        ExecutionProvider::Xnnpack => {
+            anyhow::bail!("This runtime was built without XNNPACK support (rebuild with --features xnnpack)")
+        }
+        #[cfg(feature = "gpu")]
+        ExecutionProvider::Cuda => {
+            let builder = builder
+                .with_execution_providers([ep::CUDA::default().build().error_on_failure()])
+                .map_err(builder_error("enable the CUDA execution provider"))?;
+            (builder, threads)
+        }
+        #[cfg(not(feature = "gpu"))]
+        ExecutionProvider::Cuda => {
+            anyhow::bail!("This runtime was built without CUDA support (rebuild with --features gpu)")
+        }
+    };
+    match threads {
+        Some(threads) => builder
+            .with_intra_threads(threads.get())
+            .map_err(builder_error("set the thread count")),
+        None => Ok(builder),
+    }
+}
+
+/// Hidden state of a recurrent model, carried between runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct RecurrentState {
+    names: Vec<String>,
+    shapes: Vec<Vec<usize>>,
+    values: Vec<Vec<f32>>,
+}
+
+impl RecurrentState {
+    /// State for the model inputs after the observation, given by name and
+    /// shape. A dynamic dimension (negative) is the batch, of 1.
+    fn new(inputs: Vec<(String, Vec<i64>)>, num_outputs: usize) -> Result<Self> {
+        if inputs.len() >= num_outputs {
+            bail!(
+                "Model has {} state inputs but only {} outputs to feed them",
+                inputs.len(),
+                num_outputs.saturating_sub(1)
+            );
+        }
+        let mut state = Self::default();
+        for (name, shape) in inputs {
+            let shape: Vec<usize> =
+                shape.iter().map(|&d| usize::try_from(d).unwrap_or(1)).collect();
+            state.values.push(vec![0.0; shape.iter().product()]);
+            state.names.push(name);
+            state.shapes.push(shape);
+        }
+        Ok(state)
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    fn reset(&mut self) {
+        self.values.iter_mut().for_each(|v| v.fill(0.0));
+    }
+
+    /// Keep state `k` from a model output.
+    fn update(&mut self, k: usize, shape: &[usize], values: impl Iterator<Item = f32>) {
+        if self.shapes[k] != shape {
+            self.shapes[k] = shape.to_vec();
+        }
+        self.values[k].clear();
+        self.values[k].extend(values);
+    }
 
-/// ONNX policy wrapper for running the trained walking policy.
-pub struct PolicyInference {
+    fn describe(&self) -> String {
+        let states: Vec<String> = self
+            .names
+            .iter()
+            .zip(&self.shapes)
+            .map(|(name, shape)| format!("{} {:?}", name, shape))
+            .collect();
+        states.join(", ")
+    }
+}
+
+/// Maps a session builder error, which can carry the builder and so can't
+/// be held by `anyhow` directly.
+fn builder_error<E: std::fmt::Display>(what: &'static str) -> impl FnOnce(E) -> anyhow::Error {
+    move |e| anyhow!("Failed to {}: {}", what, e)
+}
+
+/// A float32 or float16 input tensor of `shape` holding `values`.
+fn float_tensor(shape: Vec<usize>, values: Vec<f32>, half: bool) -> Result<DynValue> {
+    let tensor = if half {
+        let values: Vec<f16> = values.into_iter().map(f16::from_f32).collect();
+        Tensor::from_array((shape, values))?.into_dyn()
+    } else {
+        Tensor::from_array((shape, values))?.into_dyn()
+    };
+    Ok(tensor)
+}
+
+/// Shape and values of a float32 or float16 output tensor.
+fn float_output(value: &DynValue, half: bool) -> Result<(Vec<usize>, Vec<f32>)> {
+    let dims = |shape: &[i64]| shape.iter().map(|&d| d as usize).collect();
+    if half {
+        let (shape, values) = value.try_extract_tensor::<f16>()?;
+        Ok((dims(shape), values.iter().map(|x| x.to_f32()).collect()))
+    } else {
+        let (shape, values) = value.try_extract_tensor::<f32>()?;
+        Ok((dims(shape), values.to_vec()))
+    }
+}
+
+/// ONNX Runtime policy.
+pub struct OrtPolicy {
     session: Session,
     input_name: String,
+    /// The model takes float16 inputs (a half-precision export).
+    half: bool,
+    state: RecurrentState,
 }
 
-impl PolicyInference {
+impl OrtPolicy {
     /// Load an ONNX model from disk.
-    pub fn load(model_path: &Path) -> Result<Self> {
-        let session = Session::builder()
-            .context("Failed to create ONNX session builder")?
+    pub fn load(model_path: &Path, config: &OrtConfig) -> Result<Self> {
+        let session = session_builder(config)?
             .commit_from_file(model_path)
             .context("Failed to load ONNX model")?;
 
         let input_name = session.inputs()[0].name().to_string();
+        // Compared with the element type of a float16 tensor of our own
+        let float16 = Tensor::from_array(([1usize], vec![f16::ZERO]))?.dtype().tensor_type();
+        let half = session.inputs()[0].dtype().tensor_type() == float16;
+        let state_inputs = session.inputs()[1..]
+            .iter()
+            .map(|input| {
+                let shape = input.dtype().tensor_shape().map(|s| s.to_vec());
+                (input.name().to_string(), shape.unwrap_or_default())
+            })
+            .collect();
+        let state = RecurrentState::new(state_inputs, session.outputs().len())?;
 
         tracing::info!(
-            "Loaded ONNX policy from {} (input: {})",
+            "Loaded ONNX policy from {} (input: {}{}, {:?} execution provider, {} threads)",
             model_path.display(),
-            input_name
+            input_name,
+            if half { ", float16" } else { "" },
+            config.execution_provider,
+            config.intra_threads
         );
+        if !state.is_empty() {
+            tracing::info!("Recurrent policy, hidden state: {}", state.describe());
+        }
 
         Ok(Self {
             session,
             input_name,
+            half,
+            state,
         })
     }
+}
 
-    /// Run a forward pass: observation vector in, action vector out.
-    ///
-    /// The observation is a 1-D float32 array. The output is a 1-D action vector
-    /// (typically 14 DOF for Open Duck Mini).
-    pub fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>> {
-        // Convert to f32 and reshape to [1, obs_dim]
+impl PolicyInference for OrtPolicy {
+    fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>> {
         let obs_f32: Vec<f32> = observation.iter().map(|&x| x as f32).collect();
-        let obs_len = obs_f32.len();
-        let input = Array2::from_shape_vec((1, obs_len), obs_f32)
-            .context("Failed to create observation array")?;
-
-        let input_tensor =
-            Tensor::from_array(input).context("Failed to create input tensor")?;
+        let input_tensor = float_tensor(vec![1, obs_f32.len()], obs_f32, self.half)
+            .context("Failed to create input tensor")?;
 
-        let outputs = self
-            .session
-            .run(ort::inputs![&self.input_name => input_tensor])
-            .context("ONNX inference failed")?;
+        let mut inputs = vec![(self.input_name.clone(), input_tensor)];
+        for k in 0..self.state.len() {
+            let (shape, values) = (self.state.shapes[k].clone(), self.state.values[k].clone());
+            let tensor = float_tensor(shape, values, self.half)
+                .context("Failed to create state tensor")?;
+            inputs.push((self.state.names[k].clone(), tensor));
+        }
+        let outputs = self.session.run(inputs).context("ONNX inference failed")?;
 
         // Extract the first output tensor data
-        let (_, output_data) = outputs[0]
-            .try_extract_tensor::<f32>()
-            .context("Failed to extract output tensor")?;
+        let (_, output_data) =
+            float_output(&outputs[0], self.half).context("Failed to extract output tensor")?;
 
         let action: Vec<f64> = output_data.iter().map(|&x| x as f64).collect();
 
+        for k in 0..self.state.len() {
+            let (shape, values) =
+                float_output(&outputs[k + 1], self.half).context("Failed to extract state output")?;
+            self.state.update(k, &shape, values.into_iter());
+        }
+
         Ok(action)
     }
 
-    /// Benchmark inference latency (useful for verifying real-time performance).
-    pub fn benchmark(&mut self, obs_dim: usize, iterations: usize) -> Result<std::time::Duration> {
-        let dummy_obs: Vec<f64> = vec![0.0; obs_dim];
-        let start = std::time::Instant::now();
+    fn reset_state(&mut self) {
+        self.state.reset();
+    }
 
-        for _ in 0..iterations {
-            self.infer(&dummy_obs)?;
+    fn input_dim(&self) -> Option<usize> {
+        let shape = self.session.inputs()[0].dtype().tensor_shape()?;
+        shape
+            .last()
+            .and_then(|&d| usize::try_from(d).ok())
+    }
+}
+
+#[cfg(feature = "tract")]
+mod tract_backend {
+    use super::{PolicyInference, RecurrentState};
+    use anyhow::{Context, Result};
+    use std::path::Path;
+    use tract_onnx::prelude::*;
+
+    /// tract policy, optimized for a [1, obs_dim] input at load time.
+    pub struct TractPolicy {
+        plan: TypedRunnableModel<TypedModel>,
+        input_dim: Option<usize>,
+        state: RecurrentState,
+    }
+
+    impl TractPolicy {
+        pub fn load(model_path: &Path) -> Result<Self> {
+            let model = tract_onnx::onnx()
+                .model_for_path(model_path)
+                .context("Failed to load ONNX model with tract")?
+                .into_optimized()
+                .context("Failed to optimize model")?;
+
+            let input_dim = model
+                .input_fact(0)?
+                .shape
+                .iter()
+                .last()
+                .and_then(|d| d.as_i64())
+                .and_then(|d| usize::try_from(d).ok());
+            let mut state_inputs = Vec::new();
+            for (i, outlet) in model.inputs.iter().enumerate().skip(1) {
+                let shape = model.input_fact(i)?.shape.iter().map(|d| d.as_i64().unwrap_or(-1));
+                state_inputs.push((model.node(outlet.node).name.clone(), shape.collect()));
+            }
+            let state = RecurrentState::new(state_inputs, model.outputs.len())?;
+
+            let plan = model.into_runnable().context("Failed to build tract plan")?;
+
+            tracing::info!("Loaded tract policy from {}", model_path.display());
+            if !state.is_empty() {
+                tracing::info!("Recurrent policy, hidden state: {}", state.describe());
+            }
+
+            Ok(Self {
+                plan,
+                input_dim,
+                state,
+            })
         }
+    }
 
-        let elapsed = start.elapsed();
-        let avg = elapsed / iterations as u32;
-        tracing::info!(
-            "Inference benchmark: {} iterations, avg {:.2}ms ({:.0} Hz)",
-            iterations,
-            avg.as_secs_f64() * 1000.0,
-            1.0 / avg.as_secs_f64()
-        );
+    impl PolicyInference for TractPolicy {
+        fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>> {
+            let obs_f32: Vec<f32> = observation.iter().map(|&x| x as f32).collect();
+            let input = Tensor::from_shape(&[1, obs_f32.len()], &obs_f32)?;
+            let mut inputs: TVec<TValue> = tvec!(input.into());
+            for k in 0..self.state.len() {
+                let state = Tensor::from_shape(&self.state.shapes[k], &self.state.values[k])?;
+                inputs.push(state.into());
+            }
+            let outputs = self.plan.run(inputs).context("tract inference failed")?;
 
-        Ok(avg)
+            let output = outputs[0]
+                .to_array_view::<f32>()
+                .context("Failed to extract output tensor")?;
+            for k in 0..self.state.len() {
+                let state = outputs[k + 1]
+                    .to_array_view::<f32>()
+                    .context("Failed to extract state output")?;
+                self.state.update(k, state.shape(), state.iter().copied());
+            }
+            Ok(output.iter().map(|&x| x as f64).collect())
+        }
+
+        fn input_dim(&self) -> Option<usize> {
+            self.input_dim
+        }
+
+        fn reset_state(&mut self) {
+            self.state.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recurrent_state_fed_back_and_reset() {
+        let inputs = vec![
+            ("h_in".to_string(), vec![1, -1, 4]),
+            ("c_in".to_string(), vec![1, -1, 4]),
+        ];
+        let mut state = RecurrentState::new(inputs.clone(), 3).unwrap();
+        assert_eq!(state.describe(), "h_in [1, 1, 4], c_in [1, 1, 4]");
+        assert_eq!(state.values[1], [0.0; 4]);
+
+        state.update(0, &[1, 1, 4], [0.5; 4].into_iter());
+        assert_eq!(state.values[0], [0.5; 4]);
+        state.reset();
+        assert_eq!(state.values[0], [0.0; 4]);
+
+        // Each state input needs an output to come from
+        assert!(RecurrentState::new(inputs, 2).is_err());
+        assert!(RecurrentState::new(Vec::new(), 1).unwrap().is_empty());
     }
 }
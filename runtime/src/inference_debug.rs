@@ -0,0 +1,218 @@
+//! Inference debugging: dump a policy's exact inputs and outputs to `.npy`.
+//!
+//! When the robot behaves differently than in simulation, the first question
+//! is whether the policy sees what it saw in training. `InferenceRecorder`
+//! wraps a policy and records the float32 observation tensor fed to the
+//! model and the raw output for the first N inferences, then writes
+//!
+//! ```text
+//! <dir>/<policy>.obs.npy      float32, shape (N, obs_dim)
+//! <dir>/<policy>.action.npy   float32, shape (N, action_dim)
+//! ```
+//!
+//! which load directly with `numpy.load` for diffing against training
+//! rollouts. Files are written off the control thread once N rows are in,
+//! or with whatever was recorded if the runtime stops first. Rows are
+//! consecutive inferences of that policy: control ticks for the walking
+//! policy, planner steps for the planner. For a policy with normalization
+//! statistics, the rows are the observation before normalization and the
+//! action after denormalization.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::inference::PolicyInference;
+
+/// Recorded rows for one tensor.
+#[derive(Debug, Default)]
+struct Rows {
+    data: Vec<f32>,
+    dim: Option<usize>,
+}
+
+impl Rows {
+    /// Whether a row of `len` values matches the earlier rows.
+    fn fits(&self, len: usize) -> bool {
+        self.dim.is_none_or(|dim| dim == len)
+    }
+
+    fn push(&mut self, row: &[f64]) {
+        self.dim = Some(row.len());
+        self.data.extend(row.iter().map(|&x| x as f32));
+    }
+
+    fn shape(&self) -> [usize; 2] {
+        let dim = self.dim.unwrap_or(0);
+        [self.data.len().checked_div(dim).unwrap_or(0), dim]
+    }
+}
+
+/// Policy wrapper that records its first `ticks` inferences.
+pub struct InferenceRecorder {
+    inner: Box<dyn PolicyInference>,
+    obs_path: PathBuf,
+    action_path: PathBuf,
+    ticks: usize,
+    rows: usize,
+    obs: Rows,
+    actions: Rows,
+    done: bool,
+}
+
+impl InferenceRecorder {
+    /// Record `ticks` inferences of `inner` into `dir` as `<name>.*.npy`.
+    pub fn new(inner: Box<dyn PolicyInference>, name: &str, dir: &Path, ticks: usize) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create inference debug dir {}", dir.display()))?;
+        tracing::info!(
+            "Recording {} inferences of {:?} to {}",
+            ticks,
+            name,
+            dir.display()
+        );
+        Ok(Self {
+            inner,
+            obs_path: dir.join(format!("{}.obs.npy", name)),
+            action_path: dir.join(format!("{}.action.npy", name)),
+            ticks,
+            rows: 0,
+            obs: Rows::default(),
+            actions: Rows::default(),
+            done: ticks == 0,
+        })
+    }
+
+    /// Take the recorded rows for writing.
+    fn finish(&mut self) -> Vec<(PathBuf, Rows)> {
+        self.done = true;
+        vec![
+            (self.obs_path.clone(), std::mem::take(&mut self.obs)),
+            (self.action_path.clone(), std::mem::take(&mut self.actions)),
+        ]
+    }
+}
+
+impl PolicyInference for InferenceRecorder {
+    fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>> {
+        let action = self.inner.infer(observation)?;
+        if self.done {
+            return Ok(action);
+        }
+
+        if !self.obs.fits(observation.len()) || !self.actions.fits(action.len()) {
+            tracing::warn!("Tensor shape changed after {} rows, inference debug stopped", self.rows);
+            write_all(self.finish());
+            return Ok(action);
+        }
+        self.obs.push(observation);
+        self.actions.push(&action);
+        self.rows += 1;
+        if self.rows == self.ticks {
+            let files = self.finish();
+            thread::spawn(move || write_all(files));
+        }
+        Ok(action)
+    }
+
+    fn input_dim(&self) -> Option<usize> {
+        self.inner.input_dim()
+    }
+
+    fn reset_state(&mut self) {
+        self.inner.reset_state();
+    }
+
+    /// Benchmark runs on dummy observations and isn't recorded.
+    fn benchmark(&mut self, obs_dim: usize, iterations: usize) -> Result<Duration> {
+        self.inner.benchmark(obs_dim, iterations)
+    }
+}
+
+impl Drop for InferenceRecorder {
+    fn drop(&mut self) {
+        if !self.done && self.rows > 0 {
+            write_all(self.finish());
+        }
+    }
+}
+
+fn write_all(files: Vec<(PathBuf, Rows)>) {
+    for (path, rows) in files {
+        let shape = rows.shape();
+        match write_npy(&path, shape, &rows.data) {
+            Ok(()) => tracing::info!("Wrote {} {:?}", path.display(), shape),
+            Err(e) => tracing::error!("Failed to write {}: {:#}", path.display(), e),
+        }
+    }
+}
+
+/// Write a 2-D little-endian float32 array in NumPy `.npy` format (v1.0).
+fn write_npy(path: &Path, shape: [usize; 2], data: &[f32]) -> Result<()> {
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        shape[0], shape[1]
+    );
+    // Magic (6) + version (2) + header length (2) + header, padded to 64 bytes
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    for value in data {
+        out.write_all(&value.to_le_bytes())?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Doubles the first observation value.
+    struct Doubler;
+
+    impl PolicyInference for Doubler {
+        fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>> {
+            Ok(vec![observation[0] * 2.0])
+        }
+
+        fn input_dim(&self) -> Option<usize> {
+            Some(2)
+        }
+    }
+
+    #[test]
+    fn test_partial_recording_written_on_drop() {
+        let dir = std::env::temp_dir().join(format!("odr-infer-debug-{}", std::process::id()));
+        let mut recorder = InferenceRecorder::new(Box::new(Doubler), "walk", &dir, 10).unwrap();
+        recorder.infer(&[0.5, 1.0]).unwrap();
+        recorder.infer(&[0.1, 2.0]).unwrap();
+        drop(recorder);
+
+        let bytes = std::fs::read(dir.join("walk.obs.npy")).unwrap();
+        assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+        assert!(header.contains("'shape': (2, 2)"), "{}", header);
+
+        let values: Vec<f32> = bytes[10 + header_len..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(values, vec![0.5, 1.0, 0.1, 2.0]);
+
+        let actions = std::fs::read(dir.join("walk.action.npy")).unwrap();
+        assert_eq!(actions.len(), 10 + header_len + 2 * 4);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,332 @@
+//! Policy inference on its own thread, decoupled from the control loop.
+//!
+//! With `--async-inference` a policy whose forward pass doesn't fit in the
+//! control period no longer holds the loop back. `ThreadedPolicy` moves the
+//! model to a worker thread and implements `PolicyInference` on top of it:
+//!
+//! - each tick queues its observation and returns at once; the worker
+//!   always runs the newest one and drops any it had no time for,
+//! - the tick's action is interpolated between the last two outputs, by
+//!   how far the time since the newest one has come in the gap between
+//!   them, so the targets move smoothly at the control rate however slowly
+//!   the policy runs,
+//! - the first tick, and any tick after the outputs went stale (the loop
+//!   stopped calling the policy for a while), waits for a fresh output.
+//!
+//! Outputs lag roughly one inference behind their observations. Resets and
+//! benchmarks run on the worker between inferences and block until done.
+
+use anyhow::{anyhow, Result};
+use crossbeam_channel::{bounded, select, Receiver, Sender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::audit;
+use crate::inference::PolicyInference;
+
+/// Outputs older than this are not interpolated from.
+const MAX_OUTPUT_AGE: Duration = Duration::from_millis(250);
+
+/// How long to wait for a fresh output before giving up.
+const OUTPUT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Blocking calls that can be queued for the worker.
+const CALL_QUEUE: usize = 4;
+
+type Call = Box<dyn FnOnce(&mut dyn PolicyInference) + Send>;
+
+/// An observation numbered in the order it was queued.
+type Observation = (u64, Vec<f64>);
+
+/// The worker's result for one observation.
+struct Output {
+    seq: u64,
+    action: Result<Vec<f64>>,
+    at: Instant,
+}
+
+/// An action output and when it came out.
+#[derive(Debug)]
+struct Held {
+    action: Vec<f64>,
+    at: Instant,
+}
+
+/// A policy running on a worker thread.
+pub struct ThreadedPolicy {
+    input_dim: Option<usize>,
+    observations_tx: Sender<Observation>,
+    observations_drain: Receiver<Observation>,
+    calls_tx: Sender<Call>,
+    outputs: Receiver<Output>,
+    next_seq: u64,
+    /// Outputs for observations before this one are discarded.
+    first_seq: u64,
+    previous: Option<Held>,
+    latest: Option<Held>,
+    stop_tx: Sender<()>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ThreadedPolicy {
+    /// Move `policy` to its own worker thread.
+    pub fn start(policy: Box<dyn PolicyInference>) -> Self {
+        let input_dim = policy.input_dim();
+        let (observations_tx, observations_rx) = bounded::<Observation>(1);
+        let (calls_tx, calls_rx) = bounded::<Call>(CALL_QUEUE);
+        let (output_tx, output_rx) = bounded::<Output>(1);
+        let observations_drain = observations_rx.clone();
+        let (stop_tx, stop_rx) = bounded::<()>(1);
+        let drain_rx = output_rx.clone();
+        let worker = thread::spawn(move || {
+            inference_worker(policy, observations_rx, calls_rx, output_tx, drain_rx, stop_rx);
+        });
+
+        tracing::info!("Policy inference running on its own thread");
+        Self {
+            input_dim,
+            observations_tx,
+            observations_drain,
+            calls_tx,
+            outputs: output_rx,
+            next_seq: 0,
+            first_seq: 0,
+            previous: None,
+            latest: None,
+            stop_tx,
+            worker: Some(worker),
+        }
+    }
+
+    /// Run `f` on the worker and wait for its result.
+    fn call<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut dyn PolicyInference) -> T + Send + 'static,
+    ) -> Result<T> {
+        let _span = audit::span("channel_call");
+        let (reply_tx, reply_rx) = bounded(1);
+        self.calls_tx
+            .send(Box::new(move |policy: &mut dyn PolicyInference| {
+                let _ = reply_tx.send(f(policy));
+            }))
+            .map_err(|_| anyhow!("Inference thread stopped"))?;
+        reply_rx.recv().map_err(|_| anyhow!("Inference thread stopped"))
+    }
+
+    /// Queue an observation, replacing any the worker has not picked up yet.
+    /// Returns its number.
+    fn queue(&mut self, observation: &[f64]) -> Result<u64> {
+        let _span = audit::span("channel_send");
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        match self.observations_tx.try_send((seq, observation.to_vec())) {
+            Ok(()) => {}
+            Err(TrySendError::Full(observation)) => {
+                let _ = self.observations_drain.try_recv();
+                let _ = self.observations_tx.try_send(observation);
+            }
+            Err(TrySendError::Disconnected(_)) => return Err(anyhow!("Inference thread stopped")),
+        }
+        Ok(seq)
+    }
+
+    /// Keep `output` as the newest action, unless it is for an observation
+    /// from before the last restart.
+    fn take(&mut self, output: Output) -> Result<()> {
+        if output.seq < self.first_seq {
+            return Ok(());
+        }
+        let action = output.action?;
+        self.previous = self.latest.take();
+        self.latest = Some(Held {
+            action,
+            at: output.at,
+        });
+        Ok(())
+    }
+
+    /// Forget the held outputs: the next tick waits for an output of an
+    /// observation from `seq` on.
+    fn restart(&mut self, seq: u64) {
+        self.first_seq = seq;
+        self.previous = None;
+        self.latest = None;
+    }
+}
+
+impl Drop for ThreadedPolicy {
+    fn drop(&mut self) {
+        let _ = self.stop_tx.try_send(());
+        // Wait for the policy to be dropped, so an inference recorder in it
+        // writes its files before the runtime exits
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl PolicyInference for ThreadedPolicy {
+    fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>> {
+        let seq = self.queue(observation)?;
+        {
+            let _span = audit::span("channel_recv");
+            while let Ok(output) = self.outputs.try_recv() {
+                self.take(output)?;
+            }
+        }
+        if let Some(ref latest) = self.latest {
+            let age = latest.at.elapsed();
+            if age > MAX_OUTPUT_AGE {
+                tracing::warn!(
+                    "Policy output is {:.0}ms old, waiting for a fresh one",
+                    age.as_secs_f64() * 1000.0
+                );
+                self.restart(seq);
+            }
+        }
+
+        let deadline = Instant::now() + OUTPUT_TIMEOUT;
+        while self.latest.is_none() {
+            let output = self.outputs.recv_deadline(deadline).map_err(|_| {
+                anyhow!("No policy output within {:.1}s", OUTPUT_TIMEOUT.as_secs_f64())
+            })?;
+            self.take(output)?;
+        }
+
+        let latest = self.latest.as_ref().expect("waited for an output");
+        Ok(match self.previous {
+            Some(ref previous) => interpolate(previous, latest, Instant::now()),
+            None => latest.action.clone(),
+        })
+    }
+
+    fn input_dim(&self) -> Option<usize> {
+        self.input_dim
+    }
+
+    fn reset_state(&mut self) {
+        // An observation still waiting was built before the reset
+        let _ = self.observations_drain.try_recv();
+        if let Err(e) = self.call(|policy| policy.reset_state()) {
+            tracing::warn!("Failed to reset policy state: {:#}", e);
+        }
+        // Outputs still in flight were computed from the old state
+        self.restart(self.next_seq);
+    }
+
+    fn benchmark(&mut self, obs_dim: usize, iterations: usize) -> Result<Duration> {
+        self.call(move |policy| policy.benchmark(obs_dim, iterations))?
+    }
+}
+
+/// Action between `previous` and `latest`: `previous` when `latest` has just
+/// come out, reaching `latest` once as long has passed again as between the
+/// two, and held there after.
+fn interpolate(previous: &Held, latest: &Held, now: Instant) -> Vec<f64> {
+    let gap = latest.at.saturating_duration_since(previous.at).as_secs_f64();
+    if gap <= 0.0 || previous.action.len() != latest.action.len() {
+        return latest.action.clone();
+    }
+    let s = (now.saturating_duration_since(latest.at).as_secs_f64() / gap).min(1.0);
+    previous
+        .action
+        .iter()
+        .zip(&latest.action)
+        .map(|(a, b)| a + (b - a) * s)
+        .collect()
+}
+
+/// Worker: run the newest observation, run calls in between.
+fn inference_worker(
+    mut policy: Box<dyn PolicyInference>,
+    observations_rx: Receiver<Observation>,
+    calls_rx: Receiver<Call>,
+    output_tx: Sender<Output>,
+    drain_rx: Receiver<Output>,
+    stop_rx: Receiver<()>,
+) {
+    loop {
+        let output = select! {
+            recv(stop_rx) -> _ => break,
+            recv(calls_rx) -> call => {
+                match call {
+                    Ok(call) => call(policy.as_mut()),
+                    Err(_) => break,
+                }
+                continue;
+            }
+            recv(observations_rx) -> observation => {
+                let Ok((seq, observation)) = observation else { break };
+                Output {
+                    seq,
+                    action: policy.infer(&observation),
+                    at: Instant::now(),
+                }
+            }
+        };
+
+        match output_tx.try_send(output) {
+            Ok(()) => {}
+            Err(TrySendError::Full(output)) => {
+                let _ = drain_rx.try_recv();
+                let _ = output_tx.try_send(output);
+            }
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+
+    tracing::info!("Inference thread exiting");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Echoes the observation.
+    struct Echo;
+
+    impl PolicyInference for Echo {
+        fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>> {
+            Ok(observation.to_vec())
+        }
+
+        fn input_dim(&self) -> Option<usize> {
+            Some(1)
+        }
+    }
+
+    #[test]
+    fn test_interpolates_between_last_two_outputs() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let previous = Held {
+            action: vec![0.0, 1.0],
+            at: at(0),
+        };
+        let latest = Held {
+            action: vec![1.0, 3.0],
+            at: at(40),
+        };
+        assert_eq!(interpolate(&previous, &latest, at(40)), [0.0, 1.0]);
+        assert_eq!(interpolate(&previous, &latest, at(50)), [0.25, 1.5]);
+        assert_eq!(interpolate(&previous, &latest, at(200)), [1.0, 3.0]);
+    }
+
+    #[test]
+    fn test_threaded_policy_follows_observations() {
+        let mut policy = ThreadedPolicy::start(Box::new(Echo));
+        assert_eq!(policy.input_dim(), Some(1));
+        // The first tick waits for the worker
+        assert_eq!(policy.infer(&[1.0]).unwrap(), [1.0]);
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while policy.infer(&[3.0]).unwrap() != [3.0] {
+            assert!(Instant::now() < deadline, "action never reached the observation");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // After a reset, nothing from before it is used
+        policy.reset_state();
+        assert_eq!(policy.infer(&[-2.0]).unwrap(), [-2.0]);
+    }
+}
@@ -0,0 +1,195 @@
+//! Command multiplexing across input sources.
+//!
+//! The control loop reads one `ControllerOutput` per tick. `CommandMux`
+//! builds it from every attached `CommandSource` (gamepad, keyboard, remote
+//! client, script):
+//!
+//! - An e-stop from any source wins: commands are zeroed and buttons ignored
+//!   until every source releases it.
+//! - Otherwise the highest-priority source with a non-zero command drives;
+//!   equal priorities go to the source added first. With nobody driving,
+//!   the commands are zero.
+//! - Button presses from every source count, so a remote pause still works
+//!   while the gamepad drives.
+
+use crate::controller::{ButtonState, Buttons, CommandSource, ControllerOutput};
+
+/// Commands below this magnitude count as an idle stick.
+const DEADBAND: f64 = 1e-3;
+
+/// Which source drives when several have non-zero commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Missions and other scripted input.
+    Scripted,
+    /// Browser teleop.
+    Remote,
+    /// A person next to the robot: gamepad or keyboard.
+    Local,
+}
+
+struct Entry {
+    name: String,
+    priority: Priority,
+    source: Box<dyn CommandSource>,
+}
+
+/// Merges several command sources into one.
+pub struct CommandMux {
+    sources: Vec<Entry>,
+    output: ControllerOutput,
+    driver: Option<usize>,
+    estop_by: Option<usize>,
+}
+
+impl CommandMux {
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+            output: ControllerOutput::default(),
+            driver: None,
+            estop_by: None,
+        }
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, priority: Priority, source: Box<dyn CommandSource>) {
+        let name = name.into();
+        tracing::info!("Command source: {} ({:?})", name, priority);
+        self.sources.push(Entry {
+            name,
+            priority,
+            source,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// The source whose commands were used on the last read.
+    pub fn driver(&self) -> Option<&str> {
+        self.driver.map(|i| self.sources[i].name.as_str())
+    }
+
+    /// The source holding the e-stop on the last read.
+    pub fn estop_source(&self) -> Option<&str> {
+        self.estop_by.map(|i| self.sources[i].name.as_str())
+    }
+}
+
+impl Default for CommandMux {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandSource for CommandMux {
+    fn get_last_command(&mut self) -> &ControllerOutput {
+        let mut merged = ControllerOutput::default();
+        let mut driver: Option<(usize, Priority)> = None;
+        let mut estop_by = None;
+
+        for (i, entry) in self.sources.iter_mut().enumerate() {
+            let output = entry.source.get_last_command();
+
+            if output.estop && estop_by.is_none() {
+                estop_by = Some(i);
+            }
+
+            merge_buttons(&mut merged.buttons, &output.buttons);
+            merged.left_trigger = merged.left_trigger.max(output.left_trigger);
+            merged.right_trigger = merged.right_trigger.max(output.right_trigger);
+
+            let active = output.commands.iter().any(|c| c.abs() > DEADBAND);
+            let outranks = driver.is_none_or(|(_, priority)| entry.priority > priority);
+            if active && outranks {
+                merged.commands = output.commands;
+                driver = Some((i, entry.priority));
+            }
+        }
+
+        if estop_by.is_some() {
+            merged = ControllerOutput {
+                estop: true,
+                ..ControllerOutput::default()
+            };
+            driver = None;
+        }
+
+        self.output = merged;
+        self.driver = driver.map(|(i, _)| i);
+        self.estop_by = estop_by;
+        &self.output
+    }
+}
+
+/// Count a button as pressed (or triggered) if it is on any source.
+fn merge_buttons(into: &mut Buttons, from: &Buttons) {
+    let pairs: [(&mut ButtonState, &ButtonState); 11] = [
+        (&mut into.a, &from.a),
+        (&mut into.b, &from.b),
+        (&mut into.x, &from.x),
+        (&mut into.y, &from.y),
+        (&mut into.lb, &from.lb),
+        (&mut into.rb, &from.rb),
+        (&mut into.start, &from.start),
+        (&mut into.dpad_up, &from.dpad_up),
+        (&mut into.dpad_down, &from.dpad_down),
+        (&mut into.dpad_left, &from.dpad_left),
+        (&mut into.dpad_right, &from.dpad_right),
+    ];
+    for (into, from) in pairs {
+        into.is_pressed |= from.is_pressed;
+        into.triggered |= from.triggered;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A source that returns whatever the test sets.
+    struct Fixed(ControllerOutput);
+
+    impl CommandSource for Fixed {
+        fn get_last_command(&mut self) -> &ControllerOutput {
+            &self.0
+        }
+    }
+
+    fn fixed(vx: f64, estop: bool) -> Box<Fixed> {
+        let mut output = ControllerOutput::default();
+        output.commands[0] = vx;
+        output.estop = estop;
+        Box::new(Fixed(output))
+    }
+
+    #[test]
+    fn test_priority_and_idle_sources() {
+        let mut mux = CommandMux::new();
+        mux.add("mission", Priority::Scripted, fixed(0.05, false));
+        mux.add("gamepad", Priority::Local, fixed(0.0, false));
+        let mut remote = fixed(0.1, false);
+        remote.0.buttons.a.triggered = true;
+        mux.add("remote", Priority::Remote, remote);
+
+        // The idle gamepad yields; the remote outranks the mission
+        let output = mux.get_last_command();
+        assert_eq!(output.commands[0], 0.1);
+        assert!(output.buttons.a.triggered);
+        assert_eq!(mux.driver(), Some("remote"));
+    }
+
+    #[test]
+    fn test_estop_wins_over_priority() {
+        let mut mux = CommandMux::new();
+        mux.add("gamepad", Priority::Local, fixed(0.15, false));
+        mux.add("remote", Priority::Remote, fixed(0.0, true));
+
+        let output = mux.get_last_command();
+        assert!(output.estop);
+        assert_eq!(output.commands, [0.0; 7]);
+        assert_eq!(mux.driver(), None);
+        assert_eq!(mux.estop_source(), Some("remote"));
+    }
+}
@@ -0,0 +1,107 @@
+//! Manual joint jogging, for assembling and checking linkages on a stand.
+//!
+//! While the duck is paused, a remote client can switch jog mode on
+//! (`jog_start`, confirmed like `torque_off` since it switches torque on) and
+//! then nudge one joint at a time with `jog` commands. Each nudge is capped at
+//! `max_step`, the goal is kept inside the joint's limits, and the joint
+//! travels to it no faster than `max_speed`. The present positions go out
+//! in the telemetry so the client sees where the joint actually went.
+//! Unpausing, `jog_stop` or a soft stop ends jog mode.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::config::JogConfig;
+
+pub struct Jogger {
+    config: JogConfig,
+    joint_names: Vec<String>,
+    limits: Vec<Option<[f64; 2]>>,
+    /// Where each joint was jogged to.
+    goals: Vec<f64>,
+    /// Positions written this tick, on their way to the goals.
+    targets: Vec<f64>,
+    last_step: Option<Instant>,
+}
+
+impl Jogger {
+    /// Start holding `positions` (usually the present ones).
+    pub fn new(
+        config: JogConfig,
+        joint_names: &[String],
+        limits: &HashMap<String, [f64; 2]>,
+        positions: &[f64],
+    ) -> Self {
+        Self {
+            config,
+            joint_names: joint_names.to_vec(),
+            limits: joint_names.iter().map(|n| limits.get(n).copied()).collect(),
+            goals: positions.to_vec(),
+            targets: positions.to_vec(),
+            last_step: None,
+        }
+    }
+
+    /// Move the goal of `joint` by `delta` (rad), capped at `max_step` and
+    /// kept inside the joint's limits. Returns the new goal.
+    pub fn jog(&mut self, joint: &str, delta: f64) -> Result<f64> {
+        let i = self
+            .joint_names
+            .iter()
+            .position(|n| n == joint)
+            .with_context(|| format!("Unknown joint {:?}", joint))?;
+        let step = self.config.max_step.abs();
+        let mut goal = self.goals[i] + delta.clamp(-step, step);
+        if let Some([min, max]) = self.limits[i] {
+            goal = goal.clamp(min, max);
+        }
+        self.goals[i] = goal;
+        Ok(goal)
+    }
+
+    /// Move the targets toward the goals at up to `max_speed` and return
+    /// them, to be written this tick.
+    pub fn step(&mut self, now: Instant) -> &[f64] {
+        let dt = self
+            .last_step
+            .map_or(0.0, |t| now.saturating_duration_since(t).as_secs_f64());
+        self.last_step = Some(now);
+        let max_move = self.config.max_speed.abs() * dt;
+        for (target, &goal) in self.targets.iter_mut().zip(&self.goals) {
+            *target += (goal - *target).clamp(-max_move, max_move);
+        }
+        &self.targets
+    }
+
+    pub fn targets(&self) -> &[f64] {
+        &self.targets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_jog_is_capped_limited_and_rate_limited() {
+        let names = vec!["left_knee".to_string(), "head_yaw".to_string()];
+        let limits = HashMap::from([("left_knee".to_string(), [0.0, 1.5])]);
+        let mut jog = Jogger::new(JogConfig::default(), &names, &limits, &[1.4, 0.0]);
+
+        // One step is capped at max_step, the goal at the joint limit
+        assert!((jog.jog("head_yaw", 1.0).unwrap() - 0.1).abs() < 1e-9);
+        assert_eq!(jog.jog("left_knee", 0.1).unwrap(), 1.5);
+        assert!(jog.jog("tail", 0.1).is_err());
+
+        // The target follows at max_speed (0.5 rad/s)
+        let t0 = Instant::now();
+        assert_eq!(jog.step(t0), [1.4, 0.0]);
+        let targets = jog.step(t0 + Duration::from_millis(100)).to_vec();
+        assert!((targets[0] - 1.45).abs() < 1e-9);
+        assert!((targets[1] - 0.05).abs() < 1e-9);
+        jog.step(t0 + Duration::from_secs(1));
+        assert_eq!(jog.targets(), [1.5, 0.1]);
+    }
+}
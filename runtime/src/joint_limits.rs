@@ -0,0 +1,148 @@
+//! Per-joint position limits enforced before goal positions reach the servos.
+//!
+//! A bad policy output or a large gamepad head offset must never drive a joint
+//! into its mechanical stop. Finite targets outside the range are clamped;
+//! non-finite targets (NaN/inf from a diverging policy) are rejected and the
+//! joint keeps its previous goal. Both are counted for telemetry.
+
+use std::collections::HashMap;
+
+/// Mechanical limits of the Open Duck Mini v2 (radians), from the robot model.
+pub fn default_joint_limits() -> HashMap<String, [f64; 2]> {
+    [
+        ("left_hip_yaw", [-0.524, 0.524]),
+        ("left_hip_roll", [-0.436, 0.436]),
+        ("left_hip_pitch", [-1.222, 0.524]),
+        ("left_knee", [-1.571, 1.571]),
+        ("left_ankle", [-1.571, 1.571]),
+        ("neck_pitch", [-0.349, 1.134]),
+        ("head_pitch", [-0.785, 0.785]),
+        ("head_yaw", [-2.793, 2.793]),
+        ("head_roll", [-0.524, 0.524]),
+        ("right_hip_yaw", [-0.524, 0.524]),
+        ("right_hip_roll", [-0.436, 0.436]),
+        ("right_hip_pitch", [-0.524, 1.222]),
+        ("right_knee", [-1.571, 1.571]),
+        ("right_ankle", [-1.571, 1.571]),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .collect()
+}
+
+/// Outcome of checking one target against its limits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitCheck {
+    /// Within limits (or clamped into them): write this value.
+    Write(f64),
+    /// Non-finite target: do not write this joint.
+    Reject,
+}
+
+/// Joint limits in joint order, with clamp/reject counters.
+#[derive(Debug, Clone)]
+pub struct JointLimits {
+    min: Vec<f64>,
+    max: Vec<f64>,
+    clamped: Vec<u64>,
+    rejected: Vec<u64>,
+}
+
+impl JointLimits {
+    /// Build limits for `joint_names` from the defaults, with `overrides`
+    /// (typically from `DuckConfig`) taking precedence. Joints with no entry
+    /// are unlimited.
+    pub fn new(joint_names: &[String], overrides: &HashMap<String, [f64; 2]>) -> Self {
+        let defaults = default_joint_limits();
+        let (min, max) = joint_names
+            .iter()
+            .map(|name| {
+                let [lo, hi] = overrides
+                    .get(name)
+                    .or_else(|| defaults.get(name))
+                    .copied()
+                    .unwrap_or([f64::NEG_INFINITY, f64::INFINITY]);
+                if lo > hi {
+                    tracing::warn!("Joint limits for {} are inverted, swapping", name);
+                    (hi, lo)
+                } else {
+                    (lo, hi)
+                }
+            })
+            .unzip();
+
+        Self {
+            min,
+            max,
+            clamped: vec![0; joint_names.len()],
+            rejected: vec![0; joint_names.len()],
+        }
+    }
+
+    /// Check the target for joint `index`, counting clamp and reject events.
+    pub fn check(&mut self, index: usize, target: f64) -> LimitCheck {
+        if !target.is_finite() {
+            self.rejected[index] += 1;
+            return LimitCheck::Reject;
+        }
+
+        let clamped = target.clamp(self.min[index], self.max[index]);
+        if clamped != target {
+            self.clamped[index] += 1;
+        }
+        LimitCheck::Write(clamped)
+    }
+
+    /// Total clamp events since startup.
+    pub fn clamp_count(&self) -> u64 {
+        self.clamped.iter().sum()
+    }
+
+    /// Total rejected (non-finite) targets since startup.
+    pub fn reject_count(&self) -> u64 {
+        self.rejected.iter().sum()
+    }
+
+    /// Clamp events per joint, in joint order.
+    pub fn clamp_counts(&self) -> &[u64] {
+        &self.clamped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        vec!["left_knee".to_string(), "head_yaw".to_string(), "antenna".to_string()]
+    }
+
+    #[test]
+    fn test_clamps_and_counts() {
+        let mut limits = JointLimits::new(&names(), &HashMap::new());
+
+        assert_eq!(limits.check(0, 1.0), LimitCheck::Write(1.0));
+        assert_eq!(limits.check(0, 2.0), LimitCheck::Write(1.571));
+        assert_eq!(limits.check(1, -3.0), LimitCheck::Write(-2.793));
+        assert_eq!(limits.check(2, 100.0), LimitCheck::Write(100.0));
+
+        assert_eq!(limits.clamp_count(), 2);
+        assert_eq!(limits.clamp_counts(), &[1, 1, 0]);
+    }
+
+    #[test]
+    fn test_rejects_non_finite() {
+        let mut limits = JointLimits::new(&names(), &HashMap::new());
+        assert_eq!(limits.check(0, f64::NAN), LimitCheck::Reject);
+        assert_eq!(limits.check(1, f64::INFINITY), LimitCheck::Reject);
+        assert_eq!(limits.reject_count(), 2);
+        assert_eq!(limits.clamp_count(), 0);
+    }
+
+    #[test]
+    fn test_overrides_take_precedence() {
+        let overrides = HashMap::from([("head_yaw".to_string(), [-0.5, 0.5])]);
+        let mut limits = JointLimits::new(&names(), &overrides);
+        assert_eq!(limits.check(1, 1.0), LimitCheck::Write(0.5));
+    }
+}
@@ -0,0 +1,332 @@
+//! Keyboard teleop, for bench testing without a paired gamepad.
+//!
+//! Reads keys from the terminal the runtime was started in and produces the
+//! same `ControllerOutput` as the Xbox controller:
+//!
+//! | Keys            | Action                                 | Gamepad  |
+//! |-----------------|----------------------------------------|----------|
+//! | W / S           | forward / backward                     | left stick |
+//! | A / D           | strafe left / right                    | left stick |
+//! | Q / E           | turn left / right                      | right stick |
+//! | Arrow keys      | head pitch / yaw                       | head mode |
+//! | Space           | pause / unpause                        | A        |
+//! | M               | play a sound                           | B        |
+//! | P               | toggle the projector                   | X        |
+//! | Tab             | cycle policies                         | Start    |
+//! | + / -           | gait frequency offset                  | D-pad    |
+//! | [ / ]           | previous / next emote                  | D-pad left / right |
+//! | Ctrl-C          | quit                                   |          |
+//!
+//! Keys count as full stick deflections and go through the same
+//! `CommandShaper` as the gamepad.
+//!
+//! Terminals report key presses but not releases, so a key counts as held
+//! until `KEY_HOLD` after its last press or auto-repeat. The hold bridges the
+//! initial auto-repeat delay; the robot stops that long after the key is let go.
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::controller::{Buttons, CommandShaper, CommandSource, ControllerOutput, PressedButtons};
+
+/// A key counts as held for this long after its last press or repeat.
+const KEY_HOLD: Duration = Duration::from_millis(600);
+
+/// What a key does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Forward,
+    Backward,
+    Left,
+    Right,
+    TurnLeft,
+    TurnRight,
+    HeadUp,
+    HeadDown,
+    HeadLeft,
+    HeadRight,
+    Pause,
+    Sound,
+    Projector,
+    CyclePolicy,
+    FasterGait,
+    SlowerGait,
+    PrevEmote,
+    NextEmote,
+}
+
+impl Action {
+    const COUNT: usize = 18;
+
+    fn from_key(code: KeyCode) -> Option<Self> {
+        let action = match code {
+            KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                'w' => Action::Forward,
+                's' => Action::Backward,
+                'a' => Action::Left,
+                'd' => Action::Right,
+                'q' => Action::TurnLeft,
+                'e' => Action::TurnRight,
+                ' ' => Action::Pause,
+                'm' => Action::Sound,
+                'p' => Action::Projector,
+                '+' | '=' => Action::FasterGait,
+                '-' => Action::SlowerGait,
+                '[' => Action::PrevEmote,
+                ']' => Action::NextEmote,
+                _ => return None,
+            },
+            KeyCode::Up => Action::HeadUp,
+            KeyCode::Down => Action::HeadDown,
+            KeyCode::Left => Action::HeadLeft,
+            KeyCode::Right => Action::HeadRight,
+            KeyCode::Tab => Action::CyclePolicy,
+            _ => return None,
+        };
+        Some(action)
+    }
+}
+
+/// Held keys turned into controller output.
+struct KeyState {
+    last_press: [Option<Instant>; Action::COUNT],
+    buttons: Buttons,
+    start: Instant,
+}
+
+impl KeyState {
+    fn new() -> Self {
+        Self {
+            last_press: [None; Action::COUNT],
+            buttons: Buttons::new(),
+            start: Instant::now(),
+        }
+    }
+
+    fn press(&mut self, action: Action, now: Instant) {
+        self.last_press[action as usize] = Some(now);
+    }
+
+    fn held(&self, action: Action, now: Instant) -> bool {
+        self.last_press[action as usize]
+            .is_some_and(|t| now.saturating_duration_since(t) < KEY_HOLD)
+    }
+
+    fn output(&mut self, now: Instant) -> ControllerOutput {
+        let axis = |pos: Action, neg: Action| {
+            (self.held(pos, now) as i8 - self.held(neg, now) as i8) as f64
+        };
+
+        let mut commands = [0.0f64; 7];
+        commands[..3].copy_from_slice(&CommandShaper::drive(
+            axis(Action::Forward, Action::Backward),
+            axis(Action::Left, Action::Right),
+            axis(Action::TurnLeft, Action::TurnRight),
+        ));
+        commands[4..].copy_from_slice(&CommandShaper::head(
+            axis(Action::HeadUp, Action::HeadDown),
+            axis(Action::HeadLeft, Action::HeadRight),
+            0.0,
+        ));
+
+        let t = now.saturating_duration_since(self.start).as_secs_f64();
+        let pressed = |action| self.held(action, now);
+        let pressed = PressedButtons {
+            a: pressed(Action::Pause),
+            b: pressed(Action::Sound),
+            x: pressed(Action::Projector),
+            start: pressed(Action::CyclePolicy),
+            dpad_up: pressed(Action::FasterGait),
+            dpad_down: pressed(Action::SlowerGait),
+            dpad_left: pressed(Action::PrevEmote),
+            dpad_right: pressed(Action::NextEmote),
+            ..PressedButtons::default()
+        };
+        self.buttons.update(&pressed, t);
+
+        ControllerOutput {
+            commands,
+            buttons: self.buttons.clone(),
+            ..ControllerOutput::default()
+        }
+    }
+}
+
+/// Keyboard input handler running in a background thread.
+pub struct KeyboardController {
+    receiver: Receiver<ControllerOutput>,
+    stop_tx: Sender<()>,
+    last_output: ControllerOutput,
+}
+
+impl KeyboardController {
+    /// Put the terminal in raw mode and start the background input thread.
+    pub fn new(command_freq: u32) -> Result<Self> {
+        terminal::enable_raw_mode().context("Failed to put the terminal in raw mode")?;
+        keep_output_newlines();
+
+        let (data_tx, data_rx) = bounded::<ControllerOutput>(1);
+        let (stop_tx, stop_rx) = bounded::<()>(1);
+        let drain_rx = data_rx.clone();
+
+        let period = Duration::from_secs_f64(1.0 / command_freq as f64);
+
+        thread::spawn(move || {
+            keyboard_worker(data_tx, drain_rx, stop_rx, period);
+        });
+
+        tracing::info!("Keyboard input: WASD walk, QE turn, arrows head, Space pause, Ctrl-C quit");
+
+        Ok(Self {
+            receiver: data_rx,
+            stop_tx,
+            last_output: ControllerOutput::default(),
+        })
+    }
+
+    /// Signal the background thread to stop.
+    pub fn stop(&self) {
+        let _ = self.stop_tx.try_send(());
+    }
+}
+
+impl CommandSource for KeyboardController {
+    fn get_last_command(&mut self) -> &ControllerOutput {
+        if let Ok(output) = self.receiver.try_recv() {
+            self.last_output = output;
+        }
+        &self.last_output
+    }
+}
+
+impl Drop for KeyboardController {
+    fn drop(&mut self) {
+        self.stop();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Raw mode also turns off output processing, which would print the log
+/// lines as a staircase. Turn `\n` → `\r\n` translation back on.
+#[cfg(unix)]
+fn keep_output_newlines() {
+    // SAFETY: tcgetattr/tcsetattr only read and write the termios struct
+    // passed to them.
+    unsafe {
+        let mut termios: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) == 0 {
+            termios.c_oflag |= libc::OPOST | libc::ONLCR;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn keep_output_newlines() {}
+
+/// Background worker that reads key events and publishes the held state at
+/// the command frequency.
+fn keyboard_worker(
+    data_tx: Sender<ControllerOutput>,
+    drain_rx: Receiver<ControllerOutput>,
+    stop_rx: Receiver<()>,
+    period: Duration,
+) {
+    let mut keys = KeyState::new();
+    let mut next_publish = Instant::now();
+
+    loop {
+        if stop_rx.try_recv().is_ok() {
+            break;
+        }
+
+        let wait = next_publish.saturating_duration_since(Instant::now());
+        match event::poll(wait) {
+            Ok(true) => match event::read() {
+                Ok(Event::Key(KeyEvent {
+                    code,
+                    modifiers,
+                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                    ..
+                })) => {
+                    if modifiers.contains(KeyModifiers::CONTROL) && code == KeyCode::Char('c') {
+                        // Raw mode swallows Ctrl-C; deliver it as usual
+                        let _ = signal_hook::low_level::raise(signal_hook::consts::SIGINT);
+                    } else if let Some(action) = Action::from_key(code) {
+                        keys.press(action, Instant::now());
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::error!("Keyboard read failed: {}", e);
+                    break;
+                }
+            },
+            Ok(false) => {}
+            Err(e) => {
+                tracing::error!("Keyboard poll failed: {}", e);
+                break;
+            }
+        }
+
+        let now = Instant::now();
+        if now < next_publish {
+            continue;
+        }
+        next_publish = now + period;
+
+        match data_tx.try_send(keys.output(now)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(output)) => {
+                let _ = drain_rx.try_recv();
+                let _ = data_tx.try_send(output);
+            }
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+
+    tracing::info!("Keyboard worker thread exiting");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{X_RANGE, YAW_RANGE};
+
+    #[test]
+    fn test_held_keys_drive_and_release() {
+        let mut keys = KeyState::new();
+        let t0 = keys.start + Duration::from_secs(1);
+
+        keys.press(Action::Forward, t0);
+        keys.press(Action::TurnRight, t0);
+        keys.press(Action::Pause, t0);
+        let out = keys.output(t0);
+        assert_eq!(out.commands[0], X_RANGE[1]);
+        assert_eq!(out.commands[2], YAW_RANGE[0]);
+        assert!(out.buttons.a.triggered);
+
+        // Auto-repeat keeps the key held without re-triggering the button
+        let t1 = t0 + Duration::from_millis(500);
+        keys.press(Action::Pause, t1);
+        let out = keys.output(t1);
+        assert_eq!(out.commands[0], X_RANGE[1]);
+        assert!(out.buttons.a.is_pressed && !out.buttons.a.triggered);
+
+        let out = keys.output(t1 + KEY_HOLD);
+        assert_eq!(out.commands, [0.0; 7]);
+        assert!(!out.buttons.a.is_pressed);
+    }
+
+    #[test]
+    fn test_key_bindings() {
+        assert_eq!(Action::from_key(KeyCode::Char('W')), Some(Action::Forward));
+        assert_eq!(Action::from_key(KeyCode::Left), Some(Action::HeadLeft));
+        assert_eq!(Action::from_key(KeyCode::Tab), Some(Action::CyclePolicy));
+        assert_eq!(Action::from_key(KeyCode::Char('z')), None);
+    }
+}
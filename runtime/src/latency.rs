@@ -0,0 +1,88 @@
+//! Artificial sensor latency for sim-to-real studies.
+//!
+//! Holds the IMU and joint readings back by a configured number of control
+//! ticks before the policy sees them. Sweeping the delay shows how much
+//! latency a deployed policy tolerates, and lets the latency modelled in
+//! simulation be matched to the real stack. Safety checks (falls, collisions)
+//! keep using the fresh readings.
+
+use std::collections::VecDeque;
+
+use crate::config::SensorLatencyConfig;
+use crate::imu::ImuData;
+
+/// Fixed delay of `delay` pushes.
+pub struct DelayLine<T> {
+    buffer: VecDeque<T>,
+    delay: usize,
+}
+
+impl<T: Clone> DelayLine<T> {
+    pub fn new(delay: usize) -> Self {
+        Self {
+            buffer: VecDeque::with_capacity(delay + 1),
+            delay,
+        }
+    }
+
+    /// Push this tick's value and get the one from `delay` ticks ago. Until
+    /// the line has filled, the oldest value held is returned.
+    pub fn push(&mut self, value: T) -> T {
+        self.buffer.push_back(value);
+        if self.buffer.len() > self.delay + 1 {
+            self.buffer.pop_front();
+        }
+        self.buffer[0].clone()
+    }
+}
+
+/// Delayed copies of the readings the policy observes.
+pub struct SensorDelay {
+    imu: DelayLine<ImuData>,
+    joints: DelayLine<(Vec<f64>, Vec<f64>)>,
+}
+
+impl SensorDelay {
+    /// None when no delay is configured.
+    pub fn new(config: &SensorLatencyConfig) -> Option<Self> {
+        if config.imu_ticks == 0 && config.joint_ticks == 0 {
+            return None;
+        }
+        Some(Self {
+            imu: DelayLine::new(config.imu_ticks),
+            joints: DelayLine::new(config.joint_ticks),
+        })
+    }
+
+    /// This tick's IMU data, joint positions and velocities, delayed.
+    pub fn apply(
+        &mut self,
+        imu: &ImuData,
+        dof_pos: &[f64],
+        dof_vel: &[f64],
+    ) -> (ImuData, Vec<f64>, Vec<f64>) {
+        let imu = self.imu.push(*imu);
+        let (pos, vel) = self.joints.push((dof_pos.to_vec(), dof_vel.to_vec()));
+        (imu, pos, vel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_line() {
+        let mut none = DelayLine::new(0);
+        assert_eq!(none.push(1), 1);
+        assert_eq!(none.push(2), 2);
+
+        let mut line = DelayLine::new(2);
+        // Oldest value held until the line fills
+        assert_eq!(line.push(1), 1);
+        assert_eq!(line.push(2), 1);
+        assert_eq!(line.push(3), 1);
+        assert_eq!(line.push(4), 2);
+        assert_eq!(line.push(5), 3);
+    }
+}
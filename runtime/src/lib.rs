@@ -0,0 +1,82 @@
+//! OpenDuckRust runtime library.
+//!
+//! Hardware drivers, policy inference, and control utilities for the Open Duck
+//! Mini. The `openduckrust-runtime` binary wires these into the control loop;
+//! keeping them in a library lets the loop's pieces be tested and reused by
+//! other tools without hardware.
+
+pub mod action_limits;
+pub mod adc;
+pub mod antenna;
+pub mod audit;
+pub mod battery;
+pub mod battery_sag;
+pub mod blackbox;
+pub mod bno055_uart;
+pub mod bus_errors;
+pub mod bus_scan;
+pub mod camera;
+pub mod cloud;
+pub mod collision;
+pub mod command_rate;
+pub mod config;
+pub mod config_check;
+pub mod config_reload;
+pub mod contact_filter;
+pub mod controller;
+pub mod dataset;
+pub mod duty_cycle;
+pub mod dynamixel;
+pub mod emotes;
+pub mod events;
+pub mod expression;
+pub mod eye_expression;
+pub mod fake_gamepad;
+pub mod follow;
+pub mod gait;
+pub mod gyro_bias;
+pub mod head_tracking;
+pub mod health;
+pub mod imu;
+pub mod inference;
+pub mod inference_debug;
+pub mod inference_thread;
+pub mod input;
+pub mod jog;
+pub mod joint_limits;
+pub mod keyboard;
+pub mod latency;
+pub mod log_limit;
+pub mod logging;
+pub mod loop_budget;
+pub mod loop_timing;
+pub mod microphone;
+pub mod mission;
+pub mod moments;
+pub mod motor_io;
+pub mod motors;
+pub mod normalization;
+pub mod observation;
+pub mod orientation;
+pub mod overrides;
+pub mod peripherals;
+pub mod planner;
+pub mod policy_manager;
+pub mod realtime;
+pub mod reference_motion;
+pub mod remote;
+pub mod rl_utils;
+pub mod robot_model;
+pub mod routines;
+pub mod runtime_health;
+pub mod servo_errors;
+pub mod servo_registers;
+pub mod sh2;
+pub mod sound_stream;
+pub mod sounds;
+pub mod telemetry;
+pub mod teleop;
+pub mod thermal;
+pub mod tts;
+pub mod watchdog;
+pub mod ws2812;
@@ -0,0 +1,186 @@
+//! Rate limit for repeated warnings.
+//!
+//! A warning raised every tick ("Motor write failed", budget overruns) can
+//! write thousands of lines a minute, and a slow SD card then stalls the
+//! loop. `WarnLimiter` is a log layer that lets the first warning from each
+//! call site through, holds back its repeats for `WINDOW`, then logs one
+//! summary with how many were held back. Every warning is still counted in
+//! `warning_count`, which the runtime records in its telemetry.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::callsite::Identifier;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// How long repeats of a warning are held back before being summarized.
+pub const WINDOW: Duration = Duration::from_secs(5);
+
+/// Time between checks for windows to summarize.
+const SUMMARY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Target of the summaries, which are never held back themselves.
+const SUMMARY_TARGET: &str = "repeated";
+
+/// Warnings raised since startup, including those held back.
+static WARNINGS: AtomicU64 = AtomicU64::new(0);
+
+pub fn warning_count() -> u64 {
+    WARNINGS.load(Ordering::Relaxed)
+}
+
+/// A warning held back over the rest of its window.
+#[derive(Debug)]
+struct Repeats {
+    /// The first message of the window, the one that was logged.
+    message: String,
+    since: Instant,
+    held: u64,
+}
+
+/// Windows of held-back warnings, per call site.
+#[derive(Debug)]
+struct Windows<K> {
+    windows: HashMap<K, Repeats>,
+}
+
+impl<K: Hash + Eq> Windows<K> {
+    fn new() -> Self {
+        Self {
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Whether a warning from `key` gets logged; `message` is only called
+    /// when it does.
+    fn admit(&mut self, key: K, now: Instant, message: impl FnOnce() -> String) -> bool {
+        if let Some(repeats) = self.windows.get_mut(&key) {
+            // Expired windows with repeats stay open until summarized
+            if repeats.held > 0 || now < repeats.since + WINDOW {
+                repeats.held += 1;
+                return false;
+            }
+        }
+        let repeats = Repeats {
+            message: message(),
+            since: now,
+            held: 0,
+        };
+        self.windows.insert(key, repeats);
+        true
+    }
+
+    /// Close the windows that have run out, returning the summaries of
+    /// those with repeats.
+    fn close_expired(&mut self, now: Instant) -> Vec<Repeats> {
+        let mut summaries = Vec::new();
+        self.windows.retain(|_, repeats| {
+            if now < repeats.since + WINDOW {
+                return true;
+            }
+            if repeats.held > 0 {
+                summaries.push(Repeats {
+                    message: std::mem::take(&mut repeats.message),
+                    since: repeats.since,
+                    held: repeats.held,
+                });
+            }
+            false
+        });
+        summaries
+    }
+}
+
+/// Log layer holding back repeated warnings.
+pub struct WarnLimiter {
+    windows: Arc<Mutex<Windows<Identifier>>>,
+}
+
+impl WarnLimiter {
+    /// Create the layer and start the thread that logs its summaries.
+    pub fn new() -> Self {
+        let windows = Arc::new(Mutex::new(Windows::new()));
+        let summarized = Arc::clone(&windows);
+        thread::spawn(move || loop {
+            thread::sleep(SUMMARY_INTERVAL);
+            // The lock is released before logging, which comes back here
+            let summaries = summarized.lock().unwrap().close_expired(Instant::now());
+            for repeats in summaries {
+                tracing::warn!(
+                    target: SUMMARY_TARGET,
+                    "{} (repeated {} more times in {:.0}s)",
+                    repeats.message,
+                    repeats.held,
+                    repeats.since.elapsed().as_secs_f64()
+                );
+            }
+        });
+        Self { windows }
+    }
+}
+
+impl Default for WarnLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for WarnLimiter {
+    fn event_enabled(&self, event: &Event<'_>, _ctx: Context<'_, S>) -> bool {
+        let metadata = event.metadata();
+        if *metadata.level() != Level::WARN || metadata.target() == SUMMARY_TARGET {
+            return true;
+        }
+        WARNINGS.fetch_add(1, Ordering::Relaxed);
+        let mut windows = self.windows.lock().unwrap();
+        windows.admit(metadata.callsite(), Instant::now(), || {
+            let mut visitor = MessageVisitor(String::new());
+            event.record(&mut visitor);
+            visitor.0
+        })
+    }
+}
+
+/// Picks the formatted message out of an event.
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeats_are_held_back_then_summarized() {
+        let mut windows = Windows::new();
+        let start = Instant::now();
+        let message = || "Motor write failed".to_string();
+
+        assert!(windows.admit("motors", start, message));
+        for i in 1..=10 {
+            assert!(!windows.admit("motors", start + Duration::from_millis(i), message));
+        }
+        // Other call sites have their own window
+        assert!(windows.admit("budget", start, || "Control budget exceeded".to_string()));
+
+        assert!(windows.close_expired(start + Duration::from_secs(1)).is_empty());
+        let summaries = windows.close_expired(start + WINDOW);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].message, "Motor write failed");
+        assert_eq!(summaries[0].held, 10);
+
+        assert!(windows.admit("motors", start + WINDOW, message));
+    }
+}
@@ -0,0 +1,213 @@
+//! Log outputs, shared by the runtime and the CLI.
+//!
+//! Three outputs can run side by side, each selected by a flag or the
+//! matching environment variable:
+//!
+//! - the console (stderr): `--log-format pretty|json|auto` (`ODR_LOG_FORMAT`).
+//!   `auto`, the default, is human-readable on a terminal (an SSH session)
+//!   and JSON otherwise (systemd, containers).
+//! - JSON files rotated daily: `--log-dir DIR` (`ODR_LOG_DIR`), keeping the
+//!   last `--log-keep` files (`ODR_LOG_KEEP`, default 7).
+//! - a log collector: `--log-remote HOST:PORT` (`ODR_LOG_REMOTE`) ships one
+//!   JSON object per line over TCP, e.g. to a Vector or Fluent Bit `tcp`
+//!   source. Lines are dropped while the collector is unreachable.
+//!
+//! File and remote output go through a background thread that drops lines
+//! rather than block when it falls behind, so a slow SD card or network can
+//! never stall the control loop. `RUST_LOG` filters every output, and
+//! repeats of a warning are held back and summarized (`log_limit`).
+
+use anyhow::{Context, Result};
+use std::io::{IsTerminal, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::audit::Audited;
+use crate::log_limit::WarnLimiter;
+
+/// Minimum time between connection attempts to the log collector.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Connect/write timeout towards the log collector.
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Console log format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Pretty on a terminal, JSON otherwise.
+    #[default]
+    Auto,
+    Pretty,
+    Json,
+}
+
+/// Logging flags, flattened into each binary's arguments.
+#[derive(Debug, Clone, clap::Args)]
+pub struct LogArgs {
+    /// Console log format (auto: pretty on a terminal, JSON otherwise)
+    #[arg(long, value_enum, env = "ODR_LOG_FORMAT", default_value = "auto", global = true)]
+    pub log_format: LogFormat,
+
+    /// Also write JSON logs to daily-rotated files in this directory
+    #[arg(long, env = "ODR_LOG_DIR", global = true)]
+    pub log_dir: Option<PathBuf>,
+
+    /// Rotated log files to keep
+    #[arg(long, env = "ODR_LOG_KEEP", default_value_t = 7, global = true)]
+    pub log_keep: usize,
+
+    /// Ship JSON logs to a collector at HOST:PORT (TCP, one object per line)
+    #[arg(long, env = "ODR_LOG_REMOTE", global = true)]
+    pub log_remote: Option<String>,
+}
+
+/// Keeps the background log writers running; hold it until exit so the
+/// last lines are flushed.
+#[must_use = "logs stop being written when the guard is dropped"]
+pub struct LogGuard {
+    _guards: Vec<WorkerGuard>,
+}
+
+/// Install the log outputs. `name` prefixes the log files; `default_filter`
+/// applies when `RUST_LOG` is not set.
+pub fn init(args: &LogArgs, name: &str, default_filter: &str) -> Result<LogGuard> {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_filter));
+    let pretty = match args.log_format {
+        LogFormat::Auto => std::io::stderr().is_terminal(),
+        LogFormat::Pretty => true,
+        LogFormat::Json => false,
+    };
+    let mut guards = Vec::new();
+
+    let file = match args.log_dir {
+        Some(ref dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(name)
+                .filename_suffix("log")
+                .max_log_files(args.log_keep.max(1))
+                .build(dir)
+                .with_context(|| format!("Failed to open log directory {}", dir.display()))?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            guards.push(guard);
+            let writer = move || Audited::new("log", writer.clone());
+            Some(fmt::layer().json().with_ansi(false).with_writer(writer))
+        }
+        None => None,
+    };
+
+    let remote = match args.log_remote {
+        Some(ref addr) => {
+            let (writer, guard) = tracing_appender::non_blocking(RemoteWriter::new(addr));
+            guards.push(guard);
+            let writer = move || Audited::new("log", writer.clone());
+            Some(fmt::layer().json().with_ansi(false).with_writer(writer))
+        }
+        None => None,
+    };
+
+    // Console writes block on the terminal or journal; the audit times them
+    let stderr = || Audited::new("log", std::io::stderr());
+    let console_json = (!pretty).then(|| fmt::layer().json().with_writer(stderr));
+    let console_pretty = pretty.then(|| fmt::layer().with_writer(stderr));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(WarnLimiter::new())
+        .with(console_pretty)
+        .with(console_json)
+        .with(file)
+        .with(remote)
+        .try_init()
+        .context("Failed to install the logger")?;
+
+    if let Some(ref addr) = args.log_remote {
+        tracing::info!("Shipping logs to {}", addr);
+    }
+    Ok(LogGuard { _guards: guards })
+}
+
+/// Line sink for a TCP log collector that reconnects on its own.
+struct RemoteWriter {
+    addr: String,
+    stream: Option<TcpStream>,
+    last_attempt: Option<Instant>,
+}
+
+impl RemoteWriter {
+    fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.to_string(),
+            stream: None,
+            last_attempt: None,
+        }
+    }
+
+    fn connect(&self) -> std::io::Result<TcpStream> {
+        let addr = self.addr.to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "log collector not found")
+        })?;
+        let stream = TcpStream::connect_timeout(&addr, REMOTE_TIMEOUT)?;
+        stream.set_write_timeout(Some(REMOTE_TIMEOUT))?;
+        Ok(stream)
+    }
+}
+
+impl Write for RemoteWriter {
+    /// Never fails: lines written while the collector is unreachable are
+    /// dropped.
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let retry = self.last_attempt.is_none_or(|t| t.elapsed() >= RECONNECT_INTERVAL);
+        if self.stream.is_none() && retry {
+            self.last_attempt = Some(Instant::now());
+            self.stream = self.connect().ok();
+        }
+        if let Some(ref mut stream) = self.stream {
+            if stream.write_all(buf).is_err() {
+                self.stream = None;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(ref mut stream) = self.stream {
+            let _ = stream.flush();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_remote_writer_ships_lines_and_survives_outages() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut writer = RemoteWriter::new(&listener.local_addr().unwrap().to_string());
+
+        writer.write_all(b"{\"message\":\"hello\"}\n").unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        assert_eq!(line, "{\"message\":\"hello\"}\n");
+
+        // Nothing listening: the line is dropped, not an error
+        let mut orphan = RemoteWriter::new("127.0.0.1:1");
+        assert_eq!(orphan.write(b"lost\n").unwrap(), 5);
+        assert!(orphan.stream.is_none());
+    }
+}
@@ -0,0 +1,170 @@
+//! Startup estimate of the control loop's time budget.
+//!
+//! Each tick runs the policy and makes a few round trips on the servo bus.
+//! If those alone don't fit in the control period, the loop can never keep
+//! up and the log fills with overrun warnings. Before the loop starts, the
+//! runtime benchmarks inference, times the bus, and checks the sum against
+//! the period requested with `--control-freq`.
+
+use anyhow::{bail, Result};
+use std::time::{Duration, Instant};
+
+use crate::motors::MotorInterface;
+
+/// Share of the period beyond which the estimate is reported as tight:
+/// the rest of the tick (sensors, filters, telemetry) needs some room too.
+const TIGHT_FRACTION: f64 = 0.8;
+
+/// Measured per-tick costs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoopBudget {
+    /// Slowest loaded policy.
+    pub inference: Duration,
+    /// Planner inference, paid in full on the ticks it runs.
+    pub planner: Duration,
+    /// Position and velocity reads plus a position write.
+    pub bus: Duration,
+    /// Bus traffic runs on its own thread (`serial.async_io`), in parallel
+    /// with inference.
+    pub bus_overlapped: bool,
+    /// Policy inference runs on its own thread (`--async-inference`) and
+    /// takes no time out of the tick.
+    pub inference_threaded: bool,
+}
+
+/// How the estimate compares to the control period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feasibility {
+    Ok,
+    /// Fits, but with little headroom.
+    Tight,
+    Infeasible,
+}
+
+impl LoopBudget {
+    /// Worst-case time of one tick.
+    pub fn total(&self) -> Duration {
+        let compute = if self.inference_threaded {
+            self.planner
+        } else {
+            self.inference + self.planner
+        };
+        if self.bus_overlapped {
+            compute.max(self.bus)
+        } else {
+            compute + self.bus
+        }
+    }
+
+    pub fn feasibility(&self, control_period: Duration) -> Feasibility {
+        let total = self.total();
+        if total > control_period {
+            Feasibility::Infeasible
+        } else if total.as_secs_f64() > control_period.as_secs_f64() * TIGHT_FRACTION {
+            Feasibility::Tight
+        } else {
+            Feasibility::Ok
+        }
+    }
+
+    /// Highest control frequency the estimate allows (Hz).
+    pub fn max_frequency(&self) -> f64 {
+        TIGHT_FRACTION / self.total().as_secs_f64().max(1e-6)
+    }
+
+    /// Log the estimate against `control_freq`. Fails when the loop can't
+    /// keep up, unless `allow_overrun` is set.
+    pub fn check(&self, control_freq: u32, allow_overrun: bool) -> Result<()> {
+        let period = Duration::from_secs_f64(1.0 / control_freq as f64);
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        tracing::info!(
+            "Loop budget at {} Hz: {:.2}ms of {:.2}ms (inference {:.2}ms, planner {:.2}ms, bus {:.2}ms)",
+            control_freq,
+            ms(self.total()),
+            ms(period),
+            ms(self.inference),
+            ms(self.planner),
+            ms(self.bus)
+        );
+
+        match self.feasibility(period) {
+            Feasibility::Ok => {}
+            Feasibility::Tight => tracing::warn!(
+                "Loop budget is tight at {} Hz; expect occasional overruns (about {:.0} Hz is comfortable)",
+                control_freq,
+                self.max_frequency()
+            ),
+            Feasibility::Infeasible if allow_overrun => tracing::error!(
+                "Loop budget exceeded: {:.2}ms per tick can't run at {} Hz (about {:.0} Hz fits). Continuing because of --allow-overrun",
+                ms(self.total()),
+                control_freq,
+                self.max_frequency()
+            ),
+            Feasibility::Infeasible => bail!(
+                "{:.2}ms per tick can't run at {} Hz on this hardware; lower --control-freq to about {:.0} Hz or pass --allow-overrun",
+                ms(self.total()),
+                control_freq,
+                self.max_frequency()
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// Average time of one tick's bus traffic: read positions and velocities,
+/// then write `targets`. None if no read succeeded.
+pub fn measure_bus(
+    hwi: &mut dyn MotorInterface,
+    targets: &[f64],
+    iterations: usize,
+) -> Result<Option<Duration>> {
+    let mut total = Duration::ZERO;
+    let mut ok = 0u32;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let read = hwi.get_present_state().is_some();
+        hwi.set_position_all_array(targets)?;
+        if read {
+            total += start.elapsed();
+            ok += 1;
+        }
+    }
+    Ok((ok > 0).then(|| total / ok))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feasibility_against_period() {
+        let budget = LoopBudget {
+            inference: Duration::from_millis(8),
+            planner: Duration::ZERO,
+            bus: Duration::from_millis(4),
+            bus_overlapped: false,
+            inference_threaded: false,
+        };
+        assert_eq!(budget.feasibility(Duration::from_millis(20)), Feasibility::Ok);
+        assert_eq!(budget.feasibility(Duration::from_millis(14)), Feasibility::Tight);
+        assert_eq!(budget.feasibility(Duration::from_millis(10)), Feasibility::Infeasible);
+
+        assert!(budget.check(50, false).is_ok());
+        assert!(budget.check(100, false).is_err());
+        assert!(budget.check(100, true).is_ok());
+        assert!((budget.max_frequency() - 66.7).abs() < 0.1);
+
+        let overlapped = LoopBudget {
+            bus_overlapped: true,
+            ..budget
+        };
+        assert_eq!(budget.feasibility(Duration::from_millis(11)), Feasibility::Infeasible);
+        assert_eq!(overlapped.feasibility(Duration::from_millis(11)), Feasibility::Ok);
+
+        let threaded = LoopBudget {
+            inference_threaded: true,
+            ..budget
+        };
+        assert_eq!(threaded.feasibility(Duration::from_millis(5)), Feasibility::Ok);
+    }
+}
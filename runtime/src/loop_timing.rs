@@ -0,0 +1,303 @@
+//! Loop timing statistics: how long each stage of a tick takes, and how
+//! evenly ticks start, as distributions rather than means.
+//!
+//! Every tick's sensor read, inference, motor write and total time go into a
+//! histogram per stage, and the time since the previous tick started into a
+//! `period` histogram: its spread around the control period is the loop's
+//! jitter, sleep overshoot included. Every `--timing-report-secs` the window's p50, p95,
+//! p99 and max are logged (and written to the telemetry log as `loop_stats`),
+//! and the whole run's are logged at shutdown. The tail is what matters for
+//! a control loop: a p99 near the period means occasional overruns long
+//! before the mean shows anything.
+//!
+//! The histograms are log-linear in microseconds, 16 buckets per power of
+//! two, so percentiles are within about 6% and recording never allocates.
+
+use std::time::{Duration, Instant};
+
+use crate::blackbox::StageTimings;
+
+/// Sub-buckets per power of two.
+const SUB_BUCKETS: u64 = 16;
+
+/// Longest time told apart (µs); longer ones count as this.
+const MAX_MICROS: u64 = (1 << 24) - 1;
+
+/// Buckets for 0 to `MAX_MICROS`.
+const BUCKETS: usize = 336;
+
+/// Stage names, in `StageTimings` order, then the tick-to-tick period.
+pub const STAGES: [&str; 5] = ["read", "inference", "write", "total", "period"];
+
+/// Index of the total tick time in `STAGES`.
+const TOTAL: usize = 3;
+/// Index of the tick-to-tick period in `STAGES`.
+const PERIOD: usize = 4;
+
+/// A distribution of durations.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    counts: Box<[u64; BUCKETS]>,
+    count: u64,
+    max: Duration,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: Box::new([0; BUCKETS]),
+            count: 0,
+            max: Duration::ZERO,
+        }
+    }
+}
+
+impl Histogram {
+    pub fn record(&mut self, value: Duration) {
+        let micros = (value.as_micros() as u64).min(MAX_MICROS);
+        self.counts[bucket(micros)] += 1;
+        self.count += 1;
+        self.max = self.max.max(value);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// The duration `q` (0 to 1) of the values are at or below, rounded up
+    /// to its bucket. Zero when empty.
+    pub fn percentile(&self, q: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((q * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(bucket_upper(index)).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.fill(0);
+        self.count = 0;
+        self.max = Duration::ZERO;
+    }
+}
+
+/// Bucket of `micros`: exact below 16, then 16 per power of two.
+fn bucket(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let exp = 63 - micros.leading_zeros() as u64;
+    let sub = (micros >> (exp - 4)) - SUB_BUCKETS;
+    ((exp - 3) * SUB_BUCKETS + sub) as usize
+}
+
+/// Largest value (µs) in bucket `index`.
+fn bucket_upper(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let exp = index / SUB_BUCKETS + 3;
+    let lower = (SUB_BUCKETS + index % SUB_BUCKETS) << (exp - 4);
+    lower + (1 << (exp - 4)) - 1
+}
+
+/// Percentiles of one stage.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StageStats {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl StageStats {
+    fn of(histogram: &Histogram) -> Self {
+        Self {
+            p50: histogram.percentile(0.50),
+            p95: histogram.percentile(0.95),
+            p99: histogram.percentile(0.99),
+            max: histogram.max(),
+        }
+    }
+}
+
+/// Per-stage percentiles over a number of ticks, in `STAGES` order.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimingSummary {
+    pub ticks: u64,
+    pub stages: [StageStats; STAGES.len()],
+}
+
+impl TimingSummary {
+    /// Log one line per stage, with `what` saying which ticks these are.
+    pub fn log(&self, what: &str) {
+        if self.ticks == 0 {
+            return;
+        }
+        tracing::info!("{} ({} ticks), p50/p95/p99/max ms:", what, self.ticks);
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        for (name, stats) in STAGES.iter().zip(&self.stages) {
+            tracing::info!(
+                "  {:<9} {:>7.2} {:>7.2} {:>7.2} {:>7.2}",
+                name,
+                ms(stats.p50),
+                ms(stats.p95),
+                ms(stats.p99),
+                ms(stats.max)
+            );
+        }
+    }
+
+    /// [p50, p95, p99, max] ms of each stage, for the telemetry log.
+    pub fn values(&self) -> [f64; STAGES.len() * 4] {
+        let mut values = [0.0; STAGES.len() * 4];
+        for (i, stats) in self.stages.iter().enumerate() {
+            let ms = [stats.p50, stats.p95, stats.p99, stats.max].map(|d| d.as_secs_f64() * 1000.0);
+            values[i * 4..i * 4 + 4].copy_from_slice(&ms);
+        }
+        values
+    }
+}
+
+/// Stage histograms for the current report window and the whole run.
+#[derive(Debug, Clone)]
+pub struct LoopTiming {
+    interval: Option<Duration>,
+    window_start: Instant,
+    /// Start of the last recorded tick, if the loop has run control ticks
+    /// back to back since.
+    last_start: Option<Instant>,
+    window: [Histogram; STAGES.len()],
+    run: [Histogram; STAGES.len()],
+}
+
+impl LoopTiming {
+    /// Report a window every `interval` (never when None).
+    pub fn new(interval: Option<Duration>, now: Instant) -> Self {
+        Self {
+            interval,
+            window_start: now,
+            last_start: None,
+            window: Default::default(),
+            run: Default::default(),
+        }
+    }
+
+    /// Record a control tick that started at `started`.
+    pub fn record(&mut self, timings: &StageTimings, started: Instant) {
+        let values = [timings.read, timings.inference, timings.write, timings.total];
+        for (i, &value) in values.iter().enumerate() {
+            self.window[i].record(value);
+            self.run[i].record(value);
+        }
+        if let Some(last) = self.last_start.replace(started) {
+            let period = started.saturating_duration_since(last);
+            self.window[PERIOD].record(period);
+            self.run[PERIOD].record(period);
+        }
+    }
+
+    /// The loop idled on purpose (paused, resting): the next tick's period
+    /// is not jitter.
+    pub fn skip_period(&mut self) {
+        self.last_start = None;
+    }
+
+    /// The window's summary once the interval has passed, starting the next.
+    pub fn take_window(&mut self, now: Instant) -> Option<TimingSummary> {
+        let interval = self.interval?;
+        if now.duration_since(self.window_start) < interval {
+            return None;
+        }
+        let summary = summarize(&self.window);
+        self.window.iter_mut().for_each(Histogram::clear);
+        self.window_start = now;
+        Some(summary)
+    }
+
+    /// Summary of every tick recorded.
+    pub fn run_summary(&self) -> TimingSummary {
+        summarize(&self.run)
+    }
+}
+
+fn summarize(histograms: &[Histogram; STAGES.len()]) -> TimingSummary {
+    TimingSummary {
+        ticks: histograms[TOTAL].count(),
+        stages: histograms.each_ref().map(StageStats::of),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_within_bucket_resolution() {
+        assert_eq!(bucket(15), 15);
+        assert_eq!(bucket(16), 16);
+        assert_eq!(bucket(MAX_MICROS), BUCKETS - 1);
+        for micros in [0, 15, 16, 17, 1000, 19_999, MAX_MICROS] {
+            let index = bucket(micros);
+            assert!(micros <= bucket_upper(index));
+            assert!(index == 0 || micros > bucket_upper(index - 1));
+        }
+
+        // 1 to 100 ms, one each
+        let mut histogram = Histogram::default();
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        let p50 = histogram.percentile(0.5).as_secs_f64() * 1000.0;
+        assert!((50.0..50.0 * 1.07).contains(&p50), "p50 {}", p50);
+        let p99 = histogram.percentile(0.99).as_secs_f64() * 1000.0;
+        assert!((99.0..=100.0).contains(&p99), "p99 {}", p99);
+        assert_eq!(histogram.max(), Duration::from_millis(100));
+
+        let start = Instant::now();
+        let mut timing = LoopTiming::new(Some(Duration::from_secs(1)), start);
+        let tick = StageTimings {
+            total: Duration::from_millis(4),
+            ..Default::default()
+        };
+        timing.record(&tick, start);
+        assert_eq!(timing.take_window(start), None);
+        let window = timing.take_window(start + Duration::from_secs(1)).unwrap();
+        assert_eq!(window.ticks, 1);
+        assert_eq!(window.values()[15], 4.0);
+        // The run keeps what the window let go
+        timing.record(&tick, start + Duration::from_millis(20));
+        assert_eq!(timing.run_summary().ticks, 2);
+    }
+
+    #[test]
+    fn test_period_measures_tick_spacing() {
+        let start = Instant::now();
+        let mut timing = LoopTiming::new(None, start);
+        let tick = StageTimings::default();
+        // 20 ms apart, one tick 5 ms late
+        for ms in [0, 20, 40, 65, 80] {
+            timing.record(&tick, start + Duration::from_millis(ms));
+        }
+        let period = timing.run_summary().stages[PERIOD];
+        assert_eq!(period.max, Duration::from_millis(25));
+        assert!(period.p50 >= Duration::from_millis(20) && period.p50 < Duration::from_millis(22));
+
+        // A deliberate idle is not a late tick
+        timing.skip_period();
+        timing.record(&tick, start + Duration::from_secs(5));
+        assert_eq!(timing.run_summary().stages[PERIOD].max, Duration::from_millis(25));
+    }
+}
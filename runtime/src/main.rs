@@ -7,40 +7,100 @@
 //! Usage:
 //!   openduckrust-runtime --onnx-model-path policy.onnx [OPTIONS]
 
-mod config;
-mod controller;
-mod imu;
-mod inference;
-mod motors;
-mod peripherals;
-mod reference_motion;
-mod rl_utils;
-mod sounds;
-
 use anyhow::{Context, Result};
 use clap::Parser;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use config::DuckConfig;
-use controller::XBoxController;
-use inference::PolicyInference;
-use motors::{make_action_dict, MotorController, NUM_DOFS};
-use reference_motion::PhaseTracker;
-use rl_utils::LowPassActionFilter;
-use sounds::Sounds;
+use openduckrust_runtime::action_limits::{ActionLimits, Clamps};
+use openduckrust_runtime::antenna::AntennaAnimation;
+use openduckrust_runtime::audit::{self, CountingAllocator};
+use openduckrust_runtime::battery::{BatteryLevel, BatteryMonitor};
+use openduckrust_runtime::battery_sag::SagTracker;
+use openduckrust_runtime::blackbox::{self, BlackBox, StageTimings, TickRecord};
+use openduckrust_runtime::camera::Camera;
+use openduckrust_runtime::cloud::{CloudLink, CloudStatus, ReportedHealth};
+use openduckrust_runtime::collision::CollisionDetector;
+use openduckrust_runtime::command_rate::CommandInterpolator;
+use openduckrust_runtime::config::{
+    DuckConfig, EmotesConfig, ExecutionProvider, FeetContactsConfig, Gains, ImuBusConfig, ImuType,
+    MicAction,
+};
+use openduckrust_runtime::config_reload::{self, ConfigWatcher};
+use openduckrust_runtime::contact_filter::ContactEdge;
+use openduckrust_runtime::controller::{
+    CommandSource, StickLayout, XBoxController, X_RANGE, YAW_RANGE, Y_RANGE,
+};
+use openduckrust_runtime::dataset::{self, DatasetRecorder, Label, SessionMeta};
+use openduckrust_runtime::duty_cycle::{DutyCycler, DutyEvent};
+use openduckrust_runtime::emotes::{EmoteCapture, EmoteLibrary, EmotePlayer};
+use openduckrust_runtime::events::{Event, EventBus};
+use openduckrust_runtime::expression::GaitEvents;
+use openduckrust_runtime::head_tracking::HeadTracker;
+use openduckrust_runtime::health::{HealthMonitor, HealthStatus};
+use openduckrust_runtime::fake_gamepad::{FakeGamepad, InputScript};
+use openduckrust_runtime::follow::{FollowController, FollowParams, TargetSensor, UwbBeacon};
+use openduckrust_runtime::gait::{self, GaitAnalyzer};
+use openduckrust_runtime::gyro_bias::GyroBias;
+use openduckrust_runtime::imu::{self, ImuReader, MockImu};
+use openduckrust_runtime::inference::InferenceBackend;
+use openduckrust_runtime::input::{CommandMux, Priority};
+use openduckrust_runtime::jog::Jogger;
+use openduckrust_runtime::keyboard::KeyboardController;
+use openduckrust_runtime::latency::SensorDelay;
+use openduckrust_runtime::log_limit;
+use openduckrust_runtime::logging::{self, LogArgs};
+use openduckrust_runtime::loop_budget::{self, LoopBudget};
+use openduckrust_runtime::loop_timing::LoopTiming;
+use openduckrust_runtime::microphone::Microphone;
+use openduckrust_runtime::mission::{Mission, MissionPlayer};
+use openduckrust_runtime::moments::MomentRecorder;
+use openduckrust_runtime::motor_io::AsyncMotors;
+use openduckrust_runtime::motors::{self, make_action_dict, MotorBackend, MotorInterface};
+use openduckrust_runtime::observation::ObservationInputs;
+use openduckrust_runtime::orientation::{ImuObservation, MountingBias};
+use openduckrust_runtime::overrides::Overrides;
+use openduckrust_runtime::peripherals::{FeetContactsReader, MockFeetContacts};
+use openduckrust_runtime::planner::{self, Planner};
+use openduckrust_runtime::policy_manager::{self, AutoSwitch, Policy, PolicyManager, PolicySpec};
+use openduckrust_runtime::realtime;
+use openduckrust_runtime::reference_motion::{
+    self, FrequencyMap, PhaseTracker, PolyCoefficients,
+};
+use openduckrust_runtime::remote::{self, RemoteCommand, RemoteServer, Telemetry};
+use openduckrust_runtime::rl_utils::LowPassActionFilter;
+use openduckrust_runtime::routines::{Routine, RoutineRun};
+use openduckrust_runtime::runtime_health::{LoopHealth, RuntimeHealth, SafetyState};
+use openduckrust_runtime::servo_errors::{ServoErrorHandler, ServoErrorReaction};
+use openduckrust_runtime::sounds::{self, Sounds};
+use openduckrust_runtime::telemetry::{Signal, TelemetryWriter};
+use openduckrust_runtime::thermal::{ThermalEvent, ThermalGovernor};
+use openduckrust_runtime::teleop::{self, RemoteGamepad};
+use openduckrust_runtime::watchdog::Watchdog;
 
 // Hardware types: real on Linux, mocks elsewhere
-use imu::ImuReader;
 #[cfg(target_os = "linux")]
-use imu::Imu;
-#[cfg(not(target_os = "linux"))]
-use imu::MockImu;
+use openduckrust_runtime::imu::Bno055Calibration;
+#[cfg(unix)]
+use openduckrust_runtime::runtime_health::HealthSocket;
 
 #[cfg(target_os = "linux")]
-use peripherals::{Antennas, Eyes, FeetContacts, Projector};
-#[cfg(not(target_os = "linux"))]
-use peripherals::MockFeetContacts;
+use openduckrust_runtime::expression::GaitExpression;
+#[cfg(target_os = "linux")]
+use openduckrust_runtime::eye_expression::EyeExpression;
+#[cfg(target_os = "linux")]
+use openduckrust_runtime::peripherals::{Antennas, Eyes, FeetContacts, Projector};
+
+/// Iterations of each startup loop budget measurement.
+const BUDGET_ITERATIONS: usize = 50;
+
+/// How often the runtime health snapshot is refreshed.
+const HEALTH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Trigger travel (0-1) that counts as pulled.
+const TRIGGER_PULLED: f64 = 0.5;
 
 /// OpenDuckRust: high-performance bipedal robot runtime.
 #[derive(Parser, Debug)]
@@ -55,59 +115,258 @@ struct Args {
     #[arg(long, default_value = "~/duck_config.json")]
     duck_config_path: PathBuf,
 
+    /// Persisted runtime adjustments layered over the duck config
+    /// (default: `<config>.overrides.json` next to it).
+    #[arg(long)]
+    overrides_path: Option<PathBuf>,
+
+    /// Print the effective configuration (duck config plus overrides) and exit.
+    #[arg(long)]
+    print_config: bool,
+
     /// Serial port for the Feetech servo bus.
     #[arg(long, default_value = "/dev/ttyACM0")]
     serial_port: String,
 
+    /// Motor backend. `mock` and `sim` need no hardware and also use mock
+    /// IMU and foot contact sensors.
+    #[arg(long, value_enum, default_value_t = MotorBackend::Feetech)]
+    backend: MotorBackend,
+
     /// Control loop frequency in Hz.
-    #[arg(short = 'c', long, default_value_t = 50)]
+    #[arg(short = 'c', long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..))]
     control_freq: u32,
 
+    /// Rate (Hz) motor targets are written at, a multiple of the control
+    /// frequency: the policy still runs at the control frequency and its
+    /// targets are interpolated in between. Default: the control frequency.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    command_freq: Option<u32>,
+
     /// Action scale factor applied to policy output.
     #[arg(short = 'a', long, default_value_t = 0.25)]
     action_scale: f64,
 
-    /// PID proportional gain.
-    #[arg(short = 'p', default_value_t = 30)]
-    kp: u32,
+    /// PID proportional gain for every joint, overriding `gains` in the
+    /// duck config.
+    #[arg(short = 'p')]
+    kp: Option<u32>,
+
+    /// PID integral gain for every joint, overriding the duck config.
+    #[arg(short = 'i')]
+    ki: Option<u32>,
+
+    /// PID derivative gain for every joint, overriding the duck config.
+    #[arg(short = 'd')]
+    kd: Option<u32>,
 
-    /// PID integral gain.
-    #[arg(short = 'i', default_value_t = 0)]
-    ki: u32,
+    /// IMU roll mounting bias in degrees, overriding the duck config.
+    #[arg(long, allow_hyphen_values = true)]
+    roll_bias: Option<f64>,
 
-    /// PID derivative gain.
-    #[arg(short = 'd', default_value_t = 0)]
-    kd: u32,
+    /// IMU pitch mounting bias in degrees, overriding the duck config.
+    #[arg(long, allow_hyphen_values = true)]
+    pitch_bias: Option<f64>,
 
-    /// IMU pitch bias in degrees.
-    #[arg(long, default_value_t = 0.0)]
-    pitch_bias: f64,
+    /// IMU yaw mounting bias in degrees, overriding the duck config.
+    #[arg(long, allow_hyphen_values = true)]
+    yaw_bias: Option<f64>,
 
     /// Enable gamepad (Xbox controller) commands.
     #[arg(long, default_value_t = true)]
     commands: bool,
 
+    /// Local command input: the Xbox controller, or the keyboard of the
+    /// terminal the runtime runs in (WASD walk, arrows head, Space pause).
+    #[arg(long, value_enum, default_value_t = InputSource::Gamepad)]
+    input: InputSource,
+
+    /// Gamepad stick layout: `combined` also pitches the head with the
+    /// right stick's Y axis while driving.
+    #[arg(long, value_enum, default_value_t = StickLayout::Toggle)]
+    stick_layout: StickLayout,
+
+    /// Take gamepad input forwarded by `openduckrust gamepad` on this UDP
+    /// address instead of a locally connected controller (e.g. `0.0.0.0`,
+    /// port 9870 if omitted).
+    #[arg(long)]
+    teleop_listen: Option<String>,
+
+    /// Play gamepad input from a script (JSON or YAML timeline) instead of
+    /// a controller, for testing without one.
+    #[arg(long, conflicts_with = "teleop_listen")]
+    fake_gamepad: Option<PathBuf>,
+
+    /// Serve browser teleop and live telemetry over WebSocket on this
+    /// address (e.g. `0.0.0.0`, port 9871 if omitted).
+    #[arg(long)]
+    remote_listen: Option<String>,
+
+    /// Telemetry rate (Hz) pushed to WebSocket clients.
+    #[arg(long, default_value_t = 10.0)]
+    remote_rate: f64,
+
+    /// Key WebSocket clients must send to confirm dangerous commands
+    /// (`torque_off`, `jog_start`). Without it they are refused.
+    #[arg(long, env = "ODR_REMOTE_KEY", hide_env_values = true)]
+    remote_key: Option<String>,
+
+    /// Play a scripted mission (JSON or YAML steps). Gamepad and remote
+    /// input take over while moved.
+    #[arg(long)]
+    mission: Option<PathBuf>,
+
+    /// Record a new emote under this name instead of walking: torque off,
+    /// pose the duck by hand, A captures a keyframe, B drops the last one,
+    /// Start saves and exits.
+    #[arg(long)]
+    capture_emote: Option<String>,
+
+    /// Calibrate the BNO055 instead of walking: guides you through the
+    /// motions, then saves the offsets to `imu_calibration_path` for every
+    /// later start.
+    #[arg(long)]
+    imu_calibrate: bool,
+
     /// Low-pass filter cutoff frequency (Hz). Disabled if not set.
     #[arg(long)]
     cutoff_frequency: Option<f64>,
 
+    /// Inference engine running the policy.
+    #[arg(long, value_enum, default_value_t = InferenceBackend::Ort)]
+    inference_backend: InferenceBackend,
+
+    /// ONNX Runtime execution provider, overriding `ort.execution_provider`
+    /// in the duck config.
+    #[arg(long, value_enum)]
+    execution_provider: Option<ExecutionProvider>,
+
+    /// Threads per ONNX Runtime operator (0: one per core), overriding
+    /// `ort.intra_threads` in the duck config.
+    #[arg(long)]
+    intra_threads: Option<usize>,
+
+    /// Benchmark inference for this many iterations, then exit without
+    /// touching the motors.
+    #[arg(long)]
+    benchmark: Option<usize>,
+
+    /// Unix socket serving the control loop's health as JSON to local tools.
+    #[arg(long, default_value = "/tmp/openduckrust.sock")]
+    health_socket: PathBuf,
+
+    /// Start even when the startup loop budget estimate says the control
+    /// frequency can't be met on this hardware.
+    #[arg(long)]
+    allow_overrun: bool,
+
+    /// Audit the control path: report every serial transfer, channel
+    /// operation, allocation or log write taking longer than this many µs.
+    #[arg(long)]
+    latency_audit_us: Option<u64>,
+
+    /// Run policy inference on its own thread at whatever rate it manages,
+    /// interpolating between its last two outputs at the control rate.
+    #[arg(long)]
+    async_inference: bool,
+
+    /// Dump the exact float32 input tensor and raw output of each policy
+    /// (and the planner) for this many inferences to `.npy` files.
+    #[arg(long)]
+    inference_debug: Option<usize>,
+
+    /// Directory for `--inference-debug` dumps.
+    #[arg(long, default_value = "./inference_debug")]
+    inference_debug_dir: PathBuf,
+
+    /// IMU values the policy observes after the gyro: raw accelerometer, or
+    /// the filtered gravity vector, quaternion, or Euler angles. Only used
+    /// when the model has no observation manifest.
+    #[arg(long, value_enum, default_value_t = ImuObservation::Accel)]
+    imu_obs: ImuObservation,
+
+    /// Observation manifest describing the model's input layout
+    /// (default: `<model>.obs.json` if present).
+    #[arg(long)]
+    obs_manifest: Option<PathBuf>,
+
+    /// Additional policy to switch to at runtime, as NAME=PATH (repeatable).
+    /// The main model is named `walk`; the gamepad's Start button cycles policies.
+    #[arg(long = "policy")]
+    policies: Vec<PolicySpec>,
+
+    /// High-level planner model emitting velocity commands for the walking
+    /// policy (needs `<model>.obs.json`). Manual input takes priority.
+    #[arg(long, conflicts_with = "follow_beacon")]
+    planner: Option<PathBuf>,
+
+    /// Rate (Hz) the planner runs at.
+    #[arg(long, default_value_t = planner::DEFAULT_RATE)]
+    planner_rate: f64,
+
+    /// Observation manifest for the planner (default: `<planner>.obs.json`).
+    #[arg(long)]
+    planner_obs_manifest: Option<PathBuf>,
+
+    /// Ticks over which motor targets cross-fade when switching policies.
+    #[arg(long, default_value_t = 25)]
+    policy_crossfade_ticks: usize,
+
+    /// Switch policies automatically: `getup` when fallen, `stand` when idle,
+    /// `walk` otherwise (whichever of them are loaded).
+    #[arg(long)]
+    auto_switch_policies: bool,
+
     /// Path to polynomial coefficients file for reference motion.
     #[arg(long, default_value = "./polynomial_coefficients.pkl")]
     poly_coefficients: PathBuf,
+
+    /// Record observations, actions, and operator labels to this directory
+    /// for training. While RB is held, A/B/X tag the moment good/bad/fall.
+    #[arg(long)]
+    record_dataset: Option<PathBuf>,
+
+    /// Log per-tick telemetry (timing, joints, IMU, commands) to this
+    /// directory. Sampling thins out rather than stalling the loop when the
+    /// disk falls behind.
+    #[arg(long)]
+    telemetry_log: Option<PathBuf>,
+
+    /// Log p50/p95/p99/max of each tick stage every this many seconds (0:
+    /// only at shutdown).
+    #[arg(long, default_value_t = 60)]
+    timing_report_secs: u64,
+
+    /// Serial port of a UWB beacon module; enables follow mode. Moving the
+    /// gamepad's walking sticks overrides following.
+    #[arg(long)]
+    follow_beacon: Option<String>,
+
+    /// Distance (m) to keep behind the followed target.
+    #[arg(long, default_value_t = 0.6)]
+    follow_distance: f64,
+
+    #[command(flatten)]
+    log: LogArgs,
 }
 
-fn main() -> Result<()> {
-    // Initialize structured JSON logging
-    tracing_subscriber::fmt()
-        .json()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
+/// Where local commands come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum InputSource {
+    Gamepad,
+    Keyboard,
+}
 
+// Counts control-path allocations for `--latency-audit-us`
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn main() -> Result<()> {
     let args = Args::parse();
 
+    // Console, rotated file and remote log outputs
+    let _log_guard = logging::init(&args.log, "openduckrust-runtime", "info")?;
+
     // Expand ~ in config path
     let config_path = expand_home(&args.duck_config_path);
 
@@ -115,68 +374,234 @@ fn main() -> Result<()> {
     tracing::info!("ONNX model: {}", args.onnx_model_path.display());
     tracing::info!("Config: {}", config_path.display());
     tracing::info!("Control frequency: {} Hz", args.control_freq);
+    let mut commands = CommandInterpolator::new(args.control_freq, args.command_freq)?;
+    if commands.steps() > 1 {
+        tracing::info!(
+            "Motor commands at {} Hz, {} per policy tick",
+            args.control_freq * commands.steps(),
+            commands.steps()
+        );
+    }
+    if let Some(us) = args.latency_audit_us {
+        audit::enable(Duration::from_micros(us));
+    }
+
+    // Load configuration, with runtime adjustments from earlier sessions on top
+    let overrides_path = args
+        .overrides_path
+        .as_deref()
+        .map(expand_home)
+        .unwrap_or_else(|| Overrides::path_for_config(&config_path));
+    let mut overrides = Overrides::load(&overrides_path)?;
+    let mut duck_config = DuckConfig::load_with_overrides(&config_path, &overrides)
+        .context("Failed to load duck config")?;
+
+    if args.print_config {
+        println!("{}", serde_json::to_string_pretty(&duck_config)?);
+        return Ok(());
+    }
+
+    let imu_calibration_path = expand_home(Path::new(&duck_config.imu_calibration_path));
+    if args.imu_calibrate {
+        if duck_config.imu_type != ImuType::Bno055 {
+            anyhow::bail!("--imu-calibrate is for the BNO055, not {:?}", duck_config.imu_type);
+        }
+        return calibrate_imu(
+            duck_config.imu_upside_down,
+            &duck_config.imu_bus,
+            &imu_calibration_path,
+        );
+    }
+
+    let emote_library = EmoteLibrary::new(expand_home(Path::new(&duck_config.emotes.dir)));
+    if let Some(ref name) = args.capture_emote {
+        let mut hwi = motors::open_backend(args.backend, &duck_config, &args.serial_port)
+            .context("Failed to initialize motor controller")?;
+        let (_, input) = local_input(&args)?;
+        return capture_emote(name, hwi.as_mut(), input, &emote_library, &duck_config.emotes);
+    }
+
+    let num_dofs = duck_config.robot_model.num_dofs();
+    let model_joints = duck_config.robot_model.joint_names();
+    tracing::info!("Robot model: {} ({} joints)", duck_config.robot_model.name, num_dofs);
+
+    // Load ONNX policies: the main model, then any extra ones
+    tracing::info!("Inference backend: {:?}", args.inference_backend);
+    let mut ort_config = duck_config.ort.clone();
+    if let Some(provider) = args.execution_provider {
+        ort_config.execution_provider = provider;
+    }
+    if let Some(threads) = args.intra_threads {
+        ort_config.intra_threads = threads;
+    }
+    let main_policy = PolicySpec {
+        name: policy_manager::WALK.to_string(),
+        model_path: args.onnx_model_path.clone(),
+        manifest_path: args.obs_manifest.clone(),
+    };
+    // Inference debugging records real runs only, not benchmarks
+    let inference_debug = args.inference_debug.filter(|_| args.benchmark.is_none());
+    let load_policy = |spec: &PolicySpec| -> Result<Policy> {
+        let policy = Policy::load(
+            args.inference_backend,
+            &ort_config,
+            spec,
+            args.imu_obs,
+            &model_joints,
+        )?;
+        let policy = match inference_debug {
+            Some(ticks) => policy.record_inference(&args.inference_debug_dir, ticks)?,
+            None => policy,
+        };
+        Ok(if args.async_inference { policy.threaded() } else { policy })
+    };
+    let mut policies = PolicyManager::new(load_policy(&main_policy)?, args.policy_crossfade_ticks);
+    for spec in &args.policies {
+        policies.add(load_policy(spec)?)?;
+    }
+
+    let mut auto_switch = args.auto_switch_policies.then(AutoSwitch::new);
+
+    let mut planner = match args.planner {
+        Some(ref path) => {
+            let spec = PolicySpec {
+                name: "planner".to_string(),
+                model_path: path.clone(),
+                manifest_path: args.planner_obs_manifest.clone(),
+            };
+            let planner = Planner::load(
+                args.inference_backend,
+                &ort_config,
+                &spec,
+                args.planner_rate,
+                args.control_freq,
+                &model_joints,
+            )?;
+            match inference_debug {
+                Some(ticks) => Some(planner.record_inference(&args.inference_debug_dir, ticks)?),
+                None => Some(planner),
+            }
+        }
+        None => None,
+    };
 
-    // Load configuration
-    let duck_config = DuckConfig::load(&config_path).context("Failed to load duck config")?;
+    if let Some(iterations) = args.benchmark {
+        policies.benchmark(iterations)?;
+        if let Some(ref mut planner) = planner {
+            planner.benchmark(iterations)?;
+        }
+        return Ok(());
+    }
 
-    // Load ONNX policy
-    let mut policy =
-        PolicyInference::load(&args.onnx_model_path).context("Failed to load ONNX policy")?;
+    // Loop budget: inference now, the bus once it is open
+    let mut budget = LoopBudget {
+        inference: policies.benchmark(BUDGET_ITERATIONS)?,
+        ..LoopBudget::default()
+    };
+    if let Some(ref mut planner) = planner {
+        budget.planner = planner.benchmark(BUDGET_ITERATIONS)?;
+    }
+    if args.async_inference {
+        budget.inference_threaded = true;
+        tracing::info!(
+            "Policy inference runs at up to {:.0} Hz on its own thread",
+            1.0 / budget.inference.as_secs_f64().max(1e-6)
+        );
+    }
 
     // Initialize motor controller
-    let mut hwi = MotorController::new(&duck_config, &args.serial_port)
+    let mut hwi = motors::open_backend(args.backend, &duck_config, &args.serial_port)
         .context("Failed to initialize motor controller")?;
+    tracing::info!("Motor backend: {:?}", args.backend);
 
-    // Set PID gains
-    let mut kps = vec![args.kp as f64; NUM_DOFS];
-    let kds = vec![args.kd as f64; NUM_DOFS];
-    // Lower head KPs for compliance
-    kps[5] = 8.0;
-    kps[6] = 8.0;
-    kps[7] = 8.0;
-    kps[8] = 8.0;
+    // Set PID gains: per joint from the duck config, CLI flags override all joints
+    let gains = resolve_gains(&duck_config, hwi.joint_names(), &args)?;
+    hwi.set_gains(&gains)?;
+    let mut kps = gains.kp.clone();
 
-    hwi.set_kps(&kps)?;
-    hwi.set_kds(&kds)?;
+    // Time the bus before torque comes on, so an infeasible rate stops here
+    let init_pos = hwi.init_positions_array();
+    match loop_budget::measure_bus(hwi.as_mut(), &init_pos, BUDGET_ITERATIONS)? {
+        Some(bus) => budget.bus = bus,
+        None => tracing::warn!("Could not read the servo bus, loop budget excludes it"),
+    }
+    budget.bus_overlapped = duck_config.serial.async_io;
+    budget.check(args.control_freq, args.allow_overrun)?;
+    if commands.steps() > 1 && budget.bus > commands.command_period() {
+        tracing::warn!(
+            "A servo bus round trip takes {:.2}ms, longer than the {:.2}ms between commands",
+            budget.bus.as_secs_f64() * 1000.0,
+            commands.command_period().as_secs_f64() * 1000.0
+        );
+    }
 
     // Turn on motors (gentle startup sequence)
     hwi.turn_on()?;
 
-    // Initialize IMU (real hardware on Linux, mock elsewhere)
-    #[cfg(target_os = "linux")]
-    let imu_sensor = Imu::new(args.control_freq, duck_config.imu_upside_down)
-        .context("Failed to initialize IMU")?;
-    #[cfg(not(target_os = "linux"))]
-    let imu_sensor = MockImu::new();
+    // From here on, serial transfers can overlap with inference
+    if duck_config.serial.async_io {
+        let period = Duration::from_secs_f64(1.0 / args.control_freq as f64);
+        hwi = Box::new(AsyncMotors::start(hwi, period));
+    }
+
+    // Sensors: real hardware only with the Feetech backend on Linux
+    let use_hardware_sensors = args.backend == MotorBackend::Feetech && cfg!(target_os = "linux");
+
+    // Initialize IMU
+    let imu_sensor: Box<dyn ImuReader> = if use_hardware_sensors {
+        imu::open(
+            duck_config.imu_type,
+            &duck_config.imu_bus,
+            args.control_freq,
+            duck_config.imu_upside_down,
+            &imu_calibration_path,
+        )
+        .context("Failed to initialize IMU")?
+    } else {
+        Box::new(MockImu::new())
+    };
 
     // Initialize feet contacts
-    #[cfg(target_os = "linux")]
-    let feet_contacts = FeetContacts::new().context("Failed to initialize feet contacts")?;
-    #[cfg(not(target_os = "linux"))]
-    let feet_contacts = MockFeetContacts;
+    let feet_contacts: Box<dyn FeetContactsReader> = if use_hardware_sensors {
+        open_feet_contacts(&duck_config.feet_contacts)?
+    } else {
+        Box::new(MockFeetContacts)
+    };
 
     // Initialize phase tracker
-    let nb_steps = reference_motion::load_period_from_pickle(&args.poly_coefficients)
-        .unwrap_or(25);
+    let poly = reference_motion::load_pickle(&args.poly_coefficients)
+        .context("Failed to load the gait period")?;
+    let nb_steps = poly.as_ref().map_or(25, PolyCoefficients::nb_steps_in_period);
     let mut phase_tracker =
         PhaseTracker::new(nb_steps, duck_config.phase_frequency_factor_offset);
+    // Quicker steps at higher speed: the configured table, else the file's periods
+    phase_tracker.frequency_map = if duck_config.phase_frequency.table.is_empty() {
+        poly.as_ref().map(PolyCoefficients::frequency_map).unwrap_or_default()
+    } else {
+        FrequencyMap::new(duck_config.phase_frequency.table.clone())?
+    };
+    if !phase_tracker.frequency_map.is_empty() {
+        tracing::info!("Phase frequency follows the commanded forward velocity");
+    }
+    phase_tracker.set_idle(duck_config.phase_idle, args.control_freq as f64);
 
     // Optional low-pass filter
     let mut action_filter = args
         .cutoff_frequency
         .map(|cutoff| LowPassActionFilter::new(args.control_freq as f64, cutoff));
 
-    // Optional gamepad
-    let mut xbox_controller = if args.commands {
-        Some(XBoxController::new(20))
-    } else {
-        None
-    };
+    // Command sources, merged by priority (the remote server joins below).
+    // Local gamepad: forwarded over the network or connected locally
+    let mut sources = CommandMux::new();
+    if args.teleop_listen.is_some() || args.fake_gamepad.is_some() || args.commands {
+        let (name, input) = local_input(&args)?;
+        sources.add(name, Priority::Local, input);
+    }
 
     // Optional expression features (Linux-only hardware)
     #[cfg(target_os = "linux")]
     let mut eyes = if duck_config.expression_features.eyes {
-        Eyes::new().ok()
+        Eyes::new(&duck_config.eye_leds).ok()
     } else {
         None
     };
@@ -195,200 +620,1652 @@ fn main() -> Result<()> {
         None
     };
 
-    let sound_player = if duck_config.expression_features.speaker {
-        Sounds::new(1.0, std::path::Path::new("./assets")).ok()
+    // Expressions following the gait, driven by step and speed events
+    let mut gait_events = GaitEvents::new();
+    #[cfg(target_os = "linux")]
+    let mut gait_expression =
+        GaitExpression::new(duck_config.expression_features.gait_reactions.clone());
+    #[cfg(target_os = "linux")]
+    let mut projector_flashing = false;
+    let mut triggers_pulled = [false; 2];
+    #[cfg(target_os = "linux")]
+    let mut eye_mood = EyeExpression::Idle;
+
+    // Runtime events, with one subscription per reacting subsystem
+    let bus = EventBus::new();
+    let safety_events = bus.subscribe();
+    #[cfg(target_os = "linux")]
+    let peripheral_events = bus.subscribe();
+
+    let mut sound_player = if duck_config.expression_features.speaker {
+        open_speaker(&duck_config)
     } else {
         None
     };
+    let mut sound_events = sound_player.as_ref().map(|_| bus.subscribe());
+
+    // Claps and the wake word, heard through the microphone
+    let microphone = if duck_config.expression_features.microphone {
+        let mic = &duck_config.microphone;
+        let wake_word_model = mic.wake_word.model.as_deref().map(|m| expand_home(Path::new(m)));
+        match Microphone::start(mic, wake_word_model.as_deref(), bus.clone()) {
+            Ok(microphone) => Some(microphone),
+            Err(e) => {
+                tracing::warn!("Microphone unavailable: {:#}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let mic_events = microphone.as_ref().map(|_| bus.subscribe());
+
+    // Recorded emotes, requested from the D-pad, missions or the remote
+    let emote_events = bus.subscribe();
+    let mut emote: Option<EmotePlayer> = None;
+    let mut emote_index: Option<usize> = None;
+
+    // Optional follow mode
+    let mut follow = match args.follow_beacon {
+        Some(ref port) => {
+            let beacon = UwbBeacon::new(port, 115_200).context("Failed to open UWB beacon")?;
+            let params = FollowParams {
+                follow_distance: args.follow_distance,
+                ..FollowParams::default()
+            };
+            Some((beacon, FollowController::new(params)))
+        }
+        None => None,
+    };
+
+    // Optional temperature-aware rest cycling
+    let mut duty_cycler = if duck_config.duty_cycle.enabled {
+        Some(DutyCycler::new(duck_config.duty_cycle.clone()))
+    } else {
+        None
+    };
+
+    // Gait throttling and pausing on hot servos
+    let mut thermal = duck_config
+        .thermal
+        .enabled
+        .then(|| ThermalGovernor::new(duck_config.thermal.clone()));
+
+    // Collision and jam detection from the servo loads
+    let mut collisions = duck_config
+        .collision
+        .enabled
+        .then(|| CollisionDetector::new(duck_config.collision.clone(), hwi.joint_names()));
+
+    // Safety envelope around the policy's motor targets
+    let mut action_limits = duck_config.action_limits.enabled.then(|| {
+        let control_period = 1.0 / args.control_freq as f64;
+        ActionLimits::new(&duck_config.action_limits, hwi.joint_names(), &init_pos, control_period)
+    });
+
+    // IMU mounting biases: from the duck config, CLI flags override each angle
+    let mut imu_mounting = duck_config.imu_mounting;
+    for (angle, flag) in [
+        (&mut imu_mounting.roll_bias, args.roll_bias),
+        (&mut imu_mounting.pitch_bias, args.pitch_bias),
+        (&mut imu_mounting.yaw_bias, args.yaw_bias),
+    ] {
+        if let Some(value) = flag {
+            *angle = value;
+        }
+    }
+    let mounting_bias = (!imu_mounting.is_level()).then(|| {
+        tracing::info!(
+            "IMU mounting bias: roll {:.2}°, pitch {:.2}°, yaw {:.2}°",
+            imu_mounting.roll_bias,
+            imu_mounting.pitch_bias,
+            imu_mounting.yaw_bias
+        );
+        MountingBias::new(&imu_mounting)
+    });
+
+    // Gyro bias, estimated while paused and still
+    let mut gyro_bias = duck_config
+        .gyro_bias
+        .enabled
+        .then(|| GyroBias::new(duck_config.gyro_bias.clone()));
+
+    // Artificial sensor latency seen by the policy (sim-to-real studies)
+    let mut sensor_delay = SensorDelay::new(&duck_config.sensor_latency);
+    if sensor_delay.is_some() {
+        let tick_ms = 1000.0 / args.control_freq as f64;
+        tracing::warn!(
+            "Simulating sensor latency: IMU {} ticks ({:.0} ms), joints {} ticks ({:.0} ms)",
+            duck_config.sensor_latency.imu_ticks,
+            duck_config.sensor_latency.imu_ticks as f64 * tick_ms,
+            duck_config.sensor_latency.joint_ticks,
+            duck_config.sensor_latency.joint_ticks as f64 * tick_ms
+        );
+    }
+
+    let mut health_monitor = HealthMonitor::new(duck_config.servo_health.clone());
+    let mut servo_errors =
+        ServoErrorHandler::new(duck_config.servo_errors.clone(), hwi.joint_names().len());
+    let mut health_status = HealthStatus::Ok;
+    let mut battery_monitor = BatteryMonitor::new(duck_config.battery.clone());
+    let mut battery_sag = duck_config
+        .battery_sag
+        .enabled
+        .then(|| SagTracker::new(duck_config.battery_sag.clone(), duck_config.battery.cells));
+    let mut soft_stopped = false;
+    let mut imu_stale = false;
+    let mut thermal_paused = false;
+    let mut reported_limit_events = (0u64, 0u64);
 
     // ── State vectors ──
 
-    let init_pos = hwi.init_positions_array();
     let joint_names = hwi.joint_names().to_vec();
+    // Joints the head commands (last_commands[3..7]) add onto
+    let head_joints = ["neck_pitch", "head_pitch", "head_yaw", "head_roll"]
+        .map(|name| joint_names.iter().position(|n| n == name));
 
-    let mut last_action = vec![0.0; NUM_DOFS];
-    let mut last_last_action = vec![0.0; NUM_DOFS];
-    let mut last_last_last_action = vec![0.0; NUM_DOFS];
     let mut motor_targets = init_pos.clone();
+    let mut rest_from = init_pos.clone();
     let mut last_commands = [0.0f64; 7];
     let mut paused = duck_config.start_paused;
 
+    let mut remote = match args.remote_listen {
+        Some(ref listen) => {
+            let addr = if listen.contains(':') {
+                listen.clone()
+            } else {
+                format!("{}:{}", listen, remote::DEFAULT_PORT)
+            };
+            Some(RemoteServer::bind(
+                &addr,
+                args.remote_rate,
+                joint_names.clone(),
+                &bus,
+                args.remote_key.clone(),
+            )?)
+        }
+        None => None,
+    };
+    if let Some(ref server) = remote {
+        sources.add("remote", Priority::Remote, Box::new(server.input()));
+    }
+
+    // Camera frames, streamed by the remote server
+    let camera = if duck_config.expression_features.camera {
+        match Camera::start(&duck_config.camera) {
+            Ok(camera) => Some(camera),
+            Err(e) => {
+                tracing::warn!("Camera unavailable: {:#}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    match (&remote, &camera) {
+        (Some(server), Some(camera)) => server.serve_camera(camera.feed()),
+        (None, Some(_)) => tracing::info!("Camera frames aren't streamed without --remote-listen"),
+        _ => {}
+    }
+    if let Some(ref path) = args.mission {
+        let player = MissionPlayer::new(Mission::load(path)?, &bus, paused);
+        sources.add("mission", Priority::Scripted, Box::new(player));
+    }
+
+    // Head tracking on camera frames, below missions; LB + B toggles it
+    let head_tracker = match (&camera, &duck_config.head_tracking.model) {
+        (Some(camera), Some(model)) => {
+            let model = expand_home(Path::new(model));
+            match HeadTracker::start(&duck_config.head_tracking, &model, camera.feed()) {
+                Ok(tracker) => {
+                    sources.add("head_tracking", Priority::Scripted, Box::new(tracker.input()));
+                    Some(tracker)
+                }
+                Err(e) => {
+                    tracing::warn!("Head tracking unavailable: {:#}", e);
+                    None
+                }
+            }
+        }
+        (None, Some(_)) => {
+            tracing::warn!("Head tracking needs the camera (expression_features.camera)");
+            None
+        }
+        _ => None,
+    };
+    let mut estopped = false;
+    let mut jogger: Option<Jogger> = None;
+    let mut routine: Option<RoutineRun> = None;
+    let mut gait_analyzer = duck_config
+        .gait
+        .enabled
+        .then(|| GaitAnalyzer::new(duck_config.gait.clone(), &joint_names));
+    let mut last_tick_ms = 0.0;
+    let mut fallen = false;
+
+    // Optional fleet backend link (heartbeats, queued uploads)
+    let outbox_dir = expand_home(Path::new(&duck_config.cloud.outbox_dir));
+    let mut cloud = CloudLink::from_config(&duck_config.cloud, &outbox_dir)?;
+    let mut cloud_status = CloudStatus::Disabled;
+
+    // Control-loop health for heartbeats and the local health socket
+    let mut loop_health = LoopHealth::new();
+    let mut runtime_health = RuntimeHealth::default();
+    let mut last_health_snapshot = Instant::now();
+    let report_every = (args.timing_report_secs > 0)
+        .then(|| Duration::from_secs(args.timing_report_secs));
+    let mut loop_timing = LoopTiming::new(report_every, Instant::now());
+    #[cfg(unix)]
+    let health_socket = match HealthSocket::bind(&args.health_socket) {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            tracing::warn!("{:#}, local health disabled", e);
+            None
+        }
+    };
+
+    let recorder = match args.record_dataset {
+        Some(ref dir) => Some(DatasetRecorder::new(
+            dir,
+            SessionMeta {
+                control_freq: args.control_freq,
+                action_scale: args.action_scale,
+                joint_names: joint_names.clone(),
+                init_positions: init_pos.clone(),
+                model: args.onnx_model_path.display().to_string(),
+                started_at: dataset::unix_time(),
+            },
+        )?),
+        None => None,
+    };
+
+    let mut telemetry_log = match args.telemetry_log {
+        Some(ref dir) => Some(TelemetryWriter::new(
+            dir,
+            args.control_freq,
+            dataset::unix_time(),
+        )?),
+        None => None,
+    };
+    // Warnings already counted in the telemetry
+    let mut logged_warnings = log_limit::warning_count();
+
+    // Flight recorder, dumped when the duck falls or faults
+    let mut blackbox = duck_config.blackbox.enabled.then(|| {
+        BlackBox::new(
+            duck_config.blackbox.seconds,
+            args.control_freq,
+            joint_names.clone(),
+            expand_home(Path::new(&duck_config.blackbox.dir)),
+            duck_config.blackbox.keep,
+        )
+    });
+    let blackbox_events = blackbox.as_ref().map(|_| bus.subscribe());
+
+    // Telemetry clips saved around a moment on request
+    let mut moments = duck_config.moments.enabled.then(|| {
+        MomentRecorder::new(
+            duck_config.moments.before_secs,
+            duck_config.moments.after_secs,
+            joint_names.clone(),
+            expand_home(Path::new(&duck_config.moments.dir)),
+            duck_config.moments.keep,
+        )
+    });
+    let moment_events = moments.as_ref().map(|_| bus.subscribe());
+
+    // Edits to the config file apply live where that is safe
+    let mut config_watcher = match ConfigWatcher::start(&config_path) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            tracing::warn!("Config hot reload unavailable: {:#}", e);
+            None
+        }
+    };
+
     let control_period = Duration::from_secs_f64(1.0 / args.control_freq as f64);
     let start_time = Instant::now();
+    let mut tick: u64 = 0;
+    bus.publish(Event::Started);
+
+    // Ctrl-C / SIGTERM end the loop so logs are flushed and summarized
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&shutdown))
+            .context("Failed to install signal handler")?;
+    }
+
+    // Disable torque if the control loop stalls; armed by the first tick's
+    // feed, so the setup above never counts as a stall
+    let watchdog = if duck_config.watchdog.enabled {
+        let timeout = Duration::from_secs_f64(
+            duck_config.watchdog.timeout_periods / args.control_freq as f64,
+        );
+        Some(Watchdog::start(timeout, hwi.torque_kill_switch()))
+    } else {
+        None
+    };
+
+    // Real-time scheduling last, so threads started during setup keep the
+    // normal scheduler and every core
+    realtime::apply(&duck_config.realtime).log();
 
     tracing::info!("Entering control loop at {} Hz", args.control_freq);
 
     // ── Main control loop ──
 
-    loop {
+    while !shutdown.load(Ordering::Relaxed) {
         let tick_start = Instant::now();
 
-        // ── Gamepad input ──
-        if let Some(ref mut controller) = xbox_controller {
-            let output = controller.get_last_command();
+        // ── Watchdog (bus read failures are left to the bus error escalation) ──
+        if let Some(ref wd) = watchdog {
+            wd.feed();
+            if wd.take_trip() && !soft_stopped {
+                bus.publish(Event::ServoFault {
+                    reason: "control loop stalled".to_string(),
+                });
+            }
+        }
+
+        // ── Servo bus error rate ──
+        if let Some(reason) = hwi.take_bus_fault() {
+            if !soft_stopped {
+                bus.publish(Event::ServoFault { reason });
+            }
+        }
+
+        // ── Config hot reload ──
+        if config_watcher.as_mut().is_some_and(ConfigWatcher::changed) {
+            let running = duck_config.clone();
+            let reload = DuckConfig::load_with_overrides(&config_path, &overrides)
+                .and_then(|new| config_reload::apply(&mut duck_config, &new));
+            match reload {
+                Ok(mut reload) => {
+                    // A failed write keeps the running value and the loop going
+                    if reload.applied("joints_offsets") {
+                        if let Err(e) = hwi.set_joint_offsets(&duck_config.joints_offset) {
+                            tracing::error!("Failed to apply joint offsets: {}", e);
+                            config_reload::revert(
+                                &mut duck_config,
+                                &running,
+                                &mut reload,
+                                "joints_offsets",
+                            );
+                        }
+                    }
+                    if reload.applied("gains") {
+                        match resolve_gains(&duck_config, &joint_names, &args) {
+                            Ok(gains) => match hwi.set_gains(&gains) {
+                                Ok(()) => {
+                                    kps = gains.kp;
+                                    // A resting duty cycle keeps its low KP
+                                    let resting = duty_cycler.as_ref().filter(|d| d.is_resting());
+                                    if let Some(duty) = resting {
+                                        let rest_kps = vec![duty.rest_kp(); num_dofs];
+                                        if let Err(e) = hwi.set_kps(&rest_kps) {
+                                            tracing::error!("Failed to restore rest gains: {}", e);
+                                        }
+                                    }
+                                }
+                                Err(e) => {
+                                    tracing::error!("Failed to apply gains: {}", e);
+                                    config_reload::revert(
+                                        &mut duck_config,
+                                        &running,
+                                        &mut reload,
+                                        "gains",
+                                    );
+                                }
+                            },
+                            Err(e) => {
+                                tracing::error!("Reloaded gains not applied: {:#}", e);
+                                config_reload::revert(
+                                    &mut duck_config,
+                                    &running,
+                                    &mut reload,
+                                    "gains",
+                                );
+                            }
+                        }
+                    }
+                    if reload.applied("phase_frequency_factor_offset") {
+                        phase_tracker.frequency_factor_offset =
+                            duck_config.phase_frequency_factor_offset;
+                    }
+                    // Expression hardware is opened or closed (dropped) in place
+                    let features = &duck_config.expression_features;
+                    #[cfg(target_os = "linux")]
+                    {
+                        if reload.applied("expression_features.eyes") {
+                            drop(eyes.take());
+                            eyes = features
+                                .eyes
+                                .then(|| Eyes::new(&duck_config.eye_leds).ok())
+                                .flatten();
+                        }
+                        if reload.applied("expression_features.projector") {
+                            drop(projector.take());
+                            projector = features.projector.then(|| Projector::new().ok()).flatten();
+                        }
+                        if reload.applied("expression_features.antennas") {
+                            drop(antennas.take());
+                            antennas = features.antennas.then(|| Antennas::new().ok()).flatten();
+                        }
+                        if reload.applied("expression_features.gait_reactions") {
+                            gait_expression = GaitExpression::new(features.gait_reactions.clone());
+                        }
+                    }
+                    if reload.applied("expression_features.speaker") {
+                        drop(sound_player.take());
+                        sound_player = features
+                            .speaker
+                            .then(|| unwatched(&watchdog, || open_speaker(&duck_config)))
+                            .flatten();
+                        sound_events = sound_player.as_ref().map(|_| bus.subscribe());
+                    }
+                    reload.log();
+                }
+                Err(e) => tracing::warn!("Config reload failed, nothing applied: {:#}", e),
+            }
+        }
+
+        // ── WebSocket remote (driving goes through the command mux) ──
+        let mut pause_request = None;
+        let mut torque_off_request = false;
+        let mut jog_request = None;
+        let mut jog_moves = Vec::new();
+        let mut routine_request = None;
+        let mut stop_routine = false;
+        if let Some(ref mut server) = remote {
+            for event in server.poll() {
+                match event {
+                    RemoteCommand::Pause => pause_request = Some(true),
+                    RemoteCommand::Unpause => pause_request = Some(false),
+                    RemoteCommand::PlaySound { name } => {
+                        bus.publish(Event::SoundRequested { name })
+                    }
+                    RemoteCommand::PlayUrl { url } => bus.publish(Event::SoundUrlRequested { url }),
+                    RemoteCommand::Say { text } => bus.publish(Event::SpeechRequested { text }),
+                    RemoteCommand::SetVolume { volume } => {
+                        bus.publish(Event::VolumeRequested { volume })
+                    }
+                    RemoteCommand::StopSounds => bus.publish(Event::StopSoundsRequested),
+                    RemoteCommand::PlayEmote { name } => {
+                        bus.publish(Event::EmoteRequested { name })
+                    }
+                    RemoteCommand::EyeExpression { expression } => {
+                        bus.publish(Event::EyeExpressionRequested { expression })
+                    }
+                    RemoteCommand::SaveMoment { name } => {
+                        bus.publish(Event::MomentRequested { name })
+                    }
+                    RemoteCommand::TorqueOff => torque_off_request = true,
+                    RemoteCommand::JogStart => jog_request = Some(true),
+                    RemoteCommand::JogStop => jog_request = Some(false),
+                    RemoteCommand::Jog { joint, delta } => jog_moves.push((joint, delta)),
+                    RemoteCommand::StartRoutine { routine } => routine_request = Some(routine),
+                    RemoteCommand::StopRoutine => stop_routine = true,
+                    RemoteCommand::Velocity { .. }
+                    | RemoteCommand::Head { .. }
+                    | RemoteCommand::Estop
+                    | RemoteCommand::Release
+                    | RemoteCommand::Confirm { .. } => {}
+                }
+            }
+        }
+
+        // ── Microphone (claps and the wake word) ──
+        if let Some(ref events) = mic_events {
+            let mic = &duck_config.microphone;
+            for event in events.try_iter() {
+                let action = match event {
+                    Event::ClapsHeard { claps: 1 } => &mic.on_clap,
+                    Event::ClapsHeard { .. } => &mic.on_double_clap,
+                    Event::WakeWordHeard { .. } => &mic.on_wake_word,
+                    _ => continue,
+                };
+                tracing::info!("Heard {:?}: {:?}", event, action);
+                match action {
+                    MicAction::None => {}
+                    MicAction::Sound { name } => {
+                        bus.publish(Event::SoundRequested { name: name.clone() })
+                    }
+                    MicAction::EyeExpression { expression } => {
+                        bus.publish(Event::EyeExpressionRequested {
+                            expression: *expression,
+                        })
+                    }
+                    MicAction::Unpause => pause_request = Some(false),
+                }
+            }
+        }
+
+        // ── Command input ──
+        if !sources.is_empty() {
+            let output = sources.get_last_command();
             last_commands = output.commands;
 
+            // While RB is held, face buttons tag dataset labels instead
+            let labeling = recorder.is_some() && output.buttons.rb.is_pressed;
+            if let Some(rec) = recorder.as_ref().filter(|_| labeling) {
+                let time = start_time.elapsed().as_secs_f64();
+                if output.buttons.a.triggered {
+                    rec.record_label(tick, time, Label::Good);
+                }
+                if output.buttons.b.triggered {
+                    rec.record_label(tick, time, Label::Bad);
+                }
+                if output.buttons.x.triggered {
+                    rec.record_label(tick, time, Label::Fall);
+                }
+            }
+
             // Button handling
-            if output.buttons.a.triggered {
-                paused = !paused;
-                if paused {
-                    tracing::info!("PAUSED");
-                } else {
-                    tracing::info!("UNPAUSED");
+            if output.buttons.a.triggered && !labeling {
+                pause_request = Some(!paused);
+            }
+
+            if output.buttons.start.triggered {
+                let from = policies.active_name().to_string();
+                let to = policies.cycle(&motor_targets).to_string();
+                tracing::info!("Active policy: {}", to);
+                if from != to {
+                    bus.publish(Event::PolicySwapped { from, to });
                 }
             }
 
-            if output.buttons.dpad_up.triggered {
-                phase_tracker.adjust_offset(0.05);
+            if output.buttons.dpad_up.triggered || output.buttons.dpad_down.triggered {
+                let delta = if output.buttons.dpad_up.triggered { 0.05 } else { -0.05 };
+                phase_tracker.adjust_offset(delta);
+                if let Err(e) = overrides.set(
+                    "phase_frequency_factor_offset",
+                    phase_tracker.frequency_factor_offset,
+                ) {
+                    tracing::warn!("Failed to persist phase offset: {:#}", e);
+                }
             }
 
-            if output.buttons.dpad_down.triggered {
-                phase_tracker.adjust_offset(-0.05);
+            if output.buttons.dpad_left.triggered || output.buttons.dpad_right.triggered {
+                match emote_library.names() {
+                    Ok(names) if !names.is_empty() => {
+                        let step = if output.buttons.dpad_right.triggered {
+                            1
+                        } else {
+                            names.len() - 1
+                        };
+                        let index = emote_index.map_or(0, |i| (i + step) % names.len());
+                        emote_index = Some(index);
+                        bus.publish(Event::EmoteRequested {
+                            name: names[index].clone(),
+                        });
+                    }
+                    Ok(_) => tracing::warn!("No emotes recorded yet"),
+                    Err(e) => tracing::warn!("Failed to list emotes: {:#}", e),
+                }
             }
 
-            if output.buttons.lb.is_pressed {
-                phase_tracker.set_sprint(true);
-            } else {
-                phase_tracker.set_sprint(false);
+            // LB alone sprints; LB held with a chord button doesn't
+            let lb = output.buttons.lb.is_pressed;
+            let buttons = &output.buttons;
+            let chord = [&buttons.y, &buttons.x, &buttons.b, &buttons.rb];
+            phase_tracker.set_sprint(lb && !chord.iter().any(|b| b.is_pressed));
+
+            // LB + Y or X runs a calibration routine, and stops it
+            if lb && output.buttons.y.triggered {
+                routine_request = Some(Routine::WalkInPlace);
+            }
+            if lb && output.buttons.x.triggered && !labeling {
+                routine_request = Some(Routine::StepTest);
             }
 
             #[cfg(target_os = "linux")]
-            if output.buttons.x.triggered {
+            if output.buttons.x.triggered && !labeling && !lb {
                 if let Some(ref mut proj) = projector {
                     proj.switch();
                 }
             }
 
-            if output.buttons.b.triggered {
-                if let Some(ref snd) = sound_player {
-                    let _ = snd.play_random();
+            if output.buttons.b.triggered && !labeling && !lb {
+                bus.publish(Event::SoundRequested { name: None });
+            }
+
+            // LB + B starts or stops looking at people
+            if lb && output.buttons.b.triggered && !labeling {
+                if let Some(ref tracker) = head_tracker {
+                    let on = tracker.toggle();
+                    tracing::info!("Head tracking {}", if on { "on" } else { "off" });
                 }
             }
 
+            // LB + RB saves the telemetry around now as a moment clip (Y
+            // alone switches the sticks to the head)
+            if lb && output.buttons.rb.triggered {
+                bus.publish(Event::MomentRequested { name: None });
+            }
+
+            // Pulling a trigger plays an antenna animation
+            let pulled = [output.left_trigger, output.right_trigger].map(|t| t > TRIGGER_PULLED);
+            if pulled[0] && !triggers_pulled[0] && !labeling {
+                let animation = AntennaAnimation::PerkUp;
+                bus.publish(Event::AntennaRequested { animation });
+            }
+            if pulled[1] && !triggers_pulled[1] && !labeling {
+                let animation = AntennaAnimation::Wiggle;
+                bus.publish(Event::AntennaRequested { animation });
+            }
+            triggers_pulled = pulled;
+
+            // RB cycles the eyes' mood (it tags labels while recording)
             #[cfg(target_os = "linux")]
+            if output.buttons.rb.triggered && recorder.is_none() && !lb {
+                let expression = eye_mood.next_mood();
+                bus.publish(Event::EyeExpressionRequested { expression });
+            }
+        }
+
+        // ── E-stop (from any source, whatever its priority) ──
+        match sources.estop_source() {
+            Some(source) if !estopped => {
+                estopped = true;
+                tracing::warn!("E-stop from {}, cutting torque", source);
+                if !soft_stopped {
+                    // Cut here rather than on the fault event, so nothing
+                    // else gets written this tick
+                    soft_stopped = true;
+                    if let Err(e) = hwi.disable_torque_now() {
+                        tracing::error!("Failed to disable torque: {}", e);
+                    }
+                    if !paused {
+                        paused = true;
+                        bus.publish(Event::PausedToggled { paused });
+                    }
+                    // Still a fault for the black box and the fleet
+                    bus.publish(Event::ServoFault {
+                        reason: format!("e-stop from {}", source),
+                    });
+                }
+            }
+            None if estopped => {
+                estopped = false;
+                tracing::info!("E-stop released");
+            }
+            _ => {}
+        }
+
+        if let Some(pause) = pause_request.filter(|&p| p != paused) {
+            if !pause && estopped {
+                tracing::warn!("E-stop engaged, refusing to unpause");
+            } else if !pause && battery_monitor.level() == BatteryLevel::Critical {
+                tracing::warn!("Battery critical, refusing to unpause");
+            } else if !pause && health_status == HealthStatus::Fault {
+                tracing::warn!("Servo health fault, refusing to unpause");
+            } else if !pause && thermal.as_ref().is_some_and(ThermalGovernor::is_hot) {
+                tracing::warn!("Servos too hot, refusing to unpause");
+            } else if !pause && soft_stopped {
+                // The bus may still be flaky after the fault that cut torque
+                match unwatched(&watchdog, || hwi.turn_on()) {
+                    Ok(()) => {
+                        soft_stopped = false;
+                        paused = false;
+                        bus.publish(Event::PausedToggled { paused });
+                        tracing::info!("UNPAUSED");
+                    }
+                    Err(e) => tracing::error!("Torque on failed, staying paused: {}", e),
+                }
+            } else {
+                paused = pause;
+                bus.publish(Event::PausedToggled { paused });
+                if paused {
+                    tracing::info!("PAUSED");
+                } else {
+                    tracing::info!("UNPAUSED");
+                }
+            }
+        }
+
+        // ── Follow mode (manual stick input takes priority) ──
+        if let Some((ref mut beacon, ref mut follower)) = follow {
+            let manual =
+                sources.driver().is_some() && last_commands[..3].iter().any(|c| c.abs() > 1e-3);
+            let follow_commands = follower.update(beacon.latest(), Instant::now());
+            if !manual {
+                last_commands[..3].copy_from_slice(&follow_commands);
+            }
+        }
+
+        // ── Servo health (low-rate secondary schedule) ──
+
+        let now = Instant::now();
+        if health_monitor.needs_poll(now) {
+            health_monitor.mark_polled(now);
+            if let Some(health) = hwi.get_servo_health() {
+                health_status = health_monitor.check(&health, &joint_names);
+
+                if health_status == HealthStatus::Fault && !soft_stopped {
+                    bus.publish(Event::ServoFault {
+                        reason: "servo health limit exceeded".to_string(),
+                    });
+                }
+
+                let voltages: Vec<f64> = health.iter().map(|h| h.voltage).collect();
+                if let Some(level) = battery_monitor.update(&voltages) {
+                    bus.publish(Event::LowBattery {
+                        voltage: battery_monitor.voltage().unwrap_or(0.0),
+                    });
+                    if level == BatteryLevel::Critical && !paused {
+                        tracing::warn!("Battery critical, pausing");
+                        paused = true;
+                        bus.publish(Event::PausedToggled { paused });
+                    }
+                }
+                if let Some(ref mut sag) = battery_sag {
+                    if let Some(voltage) = battery_monitor.voltage() {
+                        sag.update(voltage);
+                    }
+                }
+
+                let temperatures: Vec<f64> = health.iter().map(|h| h.temperature).collect();
+                if let Some(ref mut thermal) = thermal {
+                    match thermal.update(&temperatures) {
+                        Some(ThermalEvent::Overheated) if !paused => {
+                            paused = true;
+                            thermal_paused = true;
+                            bus.publish(Event::PausedToggled { paused });
+                        }
+                        Some(ThermalEvent::Recovered) if paused && thermal_paused => {
+                            thermal_paused = false;
+                            // Torque cut meanwhile: only an unpause switches it back on
+                            if soft_stopped {
+                                tracing::warn!("Servos cooled, torque is off: unpause to resume");
+                            } else if !estopped
+                                && health_status != HealthStatus::Fault
+                                && battery_monitor.level() != BatteryLevel::Critical
+                            {
+                                paused = false;
+                                bus.publish(Event::PausedToggled { paused });
+                            }
+                        }
+                        _ => {}
+                    }
+                    phase_tracker.frequency_scale = thermal.scale();
+                }
+
+                if let Some(ref mut duty) = duty_cycler {
+                    match duty.update(&temperatures, now) {
+                        Some(DutyEvent::StartRest) => {
+                            rest_from = motor_targets.clone();
+                            if let Err(e) = hwi.set_kps(&vec![duty.rest_kp(); num_dofs]) {
+                                tracing::error!("Failed to lower gains for rest: {}", e);
+                            }
+                        }
+                        Some(DutyEvent::Resume) => {
+                            // Blend out of the crouch, and ease the policy in from it
+                            rest_from = motor_targets.clone();
+                            if let Some(ref mut limits) = action_limits {
+                                limits.hold(&motor_targets);
+                            }
+                            if let Err(e) = hwi.set_kps(&kps) {
+                                tracing::error!("Failed to restore gains: {}", e);
+                            }
+                            phase_tracker.reset();
+                        }
+                        None => {}
+                    }
+                }
+            }
+
+            // Joint limit telemetry: report new clamp/reject events since the last poll
+            if let Some(limits) = hwi.joint_limits() {
+                let events = (limits.clamp_count(), limits.reject_count());
+                if events != reported_limit_events {
+                    let worst = limits
+                        .clamp_counts()
+                        .iter()
+                        .zip(&joint_names)
+                        .max_by_key(|(count, _)| **count)
+                        .map(|(_, name)| name.as_str())
+                        .unwrap_or("-");
+                    tracing::warn!(
+                        "Joint limits: {} clamped (+{}), {} rejected (+{}), most clamped: {}",
+                        events.0,
+                        events.0 - reported_limit_events.0,
+                        events.1,
+                        events.1 - reported_limit_events.1,
+                        worst
+                    );
+                    reported_limit_events = events;
+                }
+            }
+        }
+
+        // ── Event reactions ──
+
+        if torque_off_request && !soft_stopped {
+            tracing::warn!("Remote torque off, soft-stopping until unpaused");
+            soft_stopped = true;
+            if !paused {
+                paused = true;
+                bus.publish(Event::PausedToggled { paused });
+            }
+            if let Err(e) = hwi.disable_torque_now() {
+                tracing::error!("Failed to disable torque: {}", e);
+            }
+        }
+
+        for event in safety_events.try_iter() {
+            if let Event::ServoFault { reason } = event {
+                if soft_stopped {
+                    continue;
+                }
+                tracing::error!("Servo fault ({}), soft-stopping until unpaused", reason);
+                soft_stopped = true;
+                if !paused {
+                    paused = true;
+                    bus.publish(Event::PausedToggled { paused });
+                }
+                if let Err(e) = hwi.disable_torque_now() {
+                    tracing::error!("Failed to disable torque: {}", e);
+                }
+            }
+        }
+
+        if let (Some(ref blackbox), Some(ref events)) = (&blackbox, &blackbox_events) {
+            for event in events.try_iter() {
+                let reason = match event {
+                    Event::FallDetected { tilt } => format!("fall (tilt {:.2} rad)", tilt),
+                    Event::ServoFault { reason } => format!("servo fault ({})", reason),
+                    _ => continue,
+                };
+                blackbox.dump(&reason, dataset::unix_time());
+            }
+        }
+
+        if let (Some(ref mut moments), Some(ref events)) = (&mut moments, &moment_events) {
+            for event in events.try_iter() {
+                if let Event::MomentRequested { name } = event {
+                    let name = moments.capture(name.as_deref(), dataset::unix_time());
+                    tracing::info!("Capturing moment {}", name);
+                }
+            }
+        }
+
+        if let (Some(ref snd), Some(ref events)) = (&sound_player, &sound_events) {
+            let announce = snd.can_speak() && duck_config.speech.announce;
+            snd.update();
+            for event in events.try_iter() {
+                let priority = sounds::event_priority(&event);
+                let played = match event {
+                    Event::SoundRequested { name: Some(ref name) } => snd.play(name),
+                    Event::SoundUrlRequested { ref url } => {
+                        snd.play_url(url, priority);
+                        Ok(())
+                    }
+                    Event::SpeechRequested { ref text } => snd.say(text, priority),
+                    Event::LowBattery { .. } if announce => snd.say("battery low", priority),
+                    Event::PausedToggled { paused } if announce => {
+                        snd.say(if paused { "paused" } else { "resuming" }, priority)
+                    }
+                    Event::VolumeRequested { volume } => {
+                        snd.set_volume(volume as f32);
+                        Ok(())
+                    }
+                    Event::StopSoundsRequested => {
+                        snd.stop_all();
+                        Ok(())
+                    }
+                    ref event => match sounds::event_sound(&duck_config.sound_events, event) {
+                        Some(choice) => snd.play_choice(choice, priority),
+                        None => Ok(()),
+                    },
+                };
+                if let Err(e) = played {
+                    tracing::warn!("Sound failed: {}", e);
+                }
+            }
+        }
+
+        for event in emote_events.try_iter() {
+            let Event::EmoteRequested { name } = event else {
+                continue;
+            };
+            if paused {
+                tracing::warn!("Paused, not playing emote {}", name);
+                continue;
+            }
+            match emote_library.load(&name) {
+                Ok(recorded) => {
+                    tracing::info!("Playing emote {}", name);
+                    emote = Some(EmotePlayer::new(
+                        &recorded,
+                        &joint_names,
+                        &motor_targets,
+                        duck_config.emotes.return_duration,
+                        Instant::now(),
+                    ));
+                }
+                Err(e) => tracing::warn!("Emote {} failed: {:#}", name, e),
+            }
+        }
+
+        // Park the expression hardware when the duck falls or faults
+        #[cfg(target_os = "linux")]
+        for event in peripheral_events.try_iter() {
+            gait_expression.handle(&event, Instant::now());
+            if let Some(ref ant) = antennas {
+                if let Some(animation) = AntennaAnimation::for_event(&event) {
+                    ant.play(animation);
+                }
+            }
+            match event {
+                Event::FallDetected { .. } | Event::ServoFault { .. } => {
+                    if let Some(ref mut ant) = antennas {
+                        ant.stop();
+                    }
+                    if let Some(ref mut proj) = projector {
+                        proj.stop();
+                    }
+                }
+                Event::CloudStatusChanged { status } => {
+                    if let Some(ref e) = eyes {
+                        e.set_offline(status == CloudStatus::Offline);
+                    }
+                }
+                Event::LowBattery { .. } => {
+                    if let Some(ref e) = eyes {
+                        e.set_low_battery(true);
+                    }
+                }
+                // A blink also acknowledges a saved moment
+                Event::BlinkRequested | Event::MomentRequested { .. } => {
+                    if let Some(ref e) = eyes {
+                        e.blink();
+                    }
+                }
+                Event::EyeExpressionRequested { expression } => {
+                    if !expression.is_moment() {
+                        eye_mood = expression;
+                    }
+                    if let Some(ref e) = eyes {
+                        e.set_expression(expression);
+                    }
+                }
+                // Paused ducks look sleepy
+                Event::PausedToggled { paused } => {
+                    eye_mood = if paused {
+                        EyeExpression::Sleepy
+                    } else {
+                        EyeExpression::Idle
+                    };
+                    if let Some(ref e) = eyes {
+                        e.set_expression(eye_mood);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        if gait_expression.enabled() {
+            if let (Some(ref e), Some(brightness)) = (&eyes, gait_expression.eye_brightness()) {
+                e.set_brightness(brightness);
+            }
             if let Some(ref mut ant) = antennas {
-                ant.set_position_left(output.right_trigger);
-                ant.set_position_right(output.left_trigger);
+                let sway = gait_expression.antenna_sway().unwrap_or(0.0);
+                ant.set_rest([sway, -sway]);
+            }
+            if let Some(ref mut proj) = projector {
+                match gait_expression.projector_pulse(Instant::now()) {
+                    Some(on) => {
+                        proj.flash(on);
+                        projector_flashing = true;
+                    }
+                    None if projector_flashing => {
+                        proj.restore();
+                        projector_flashing = false;
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        // ── Runtime health ──
+
+        if last_health_snapshot.elapsed() >= HEALTH_INTERVAL {
+            last_health_snapshot = Instant::now();
+            let safety = safety_state(estopped, soft_stopped, fallen, paused);
+            let bus_error_rate = hwi.bus_stats().map_or(0.0, |s| s.error_rate);
+            runtime_health = loop_health.snapshot(bus_error_rate, imu_sensor.age(), safety);
+            #[cfg(unix)]
+            if let Some(ref socket) = health_socket {
+                socket.publish(&runtime_health);
+            }
+        }
+
+        // ── Fleet backend ──
+
+        if let Some(ref mut link) = cloud {
+            let health = if soft_stopped || fallen {
+                ReportedHealth::Faulted
+            } else if health_status == HealthStatus::Warning
+                || runtime_health.degraded_reason().is_some()
+            {
+                ReportedHealth::Degraded
+            } else {
+                ReportedHealth::Healthy
+            };
+            link.heartbeat(health, &runtime_health, Instant::now());
+
+            if link.status() != cloud_status {
+                cloud_status = link.status();
+                bus.publish(Event::CloudStatusChanged {
+                    status: cloud_status,
+                });
+            }
+        }
+
+        // ── Jog mode (paused, on a stand) ──
+
+        if (!paused || soft_stopped) && jogger.take().is_some() {
+            tracing::info!("Jog mode off");
+        }
+        if let Some(start) = jog_request.filter(|&j| j != jogger.is_some()) {
+            if !start {
+                jogger = None;
+                tracing::info!("Jog mode off");
+            } else if !paused {
+                tracing::warn!("Not paused, refusing to jog");
+            } else if estopped {
+                tracing::warn!("E-stop engaged, refusing to jog");
+            } else if health_status == HealthStatus::Fault {
+                tracing::warn!("Servo health fault, refusing to jog");
+            } else if let Err(e) =
+                if soft_stopped { unwatched(&watchdog, || hwi.turn_on()) } else { Ok(()) }
+            {
+                tracing::error!("Torque on failed, not jogging: {}", e);
+            } else {
+                soft_stopped = false;
+                match hwi.get_present_positions() {
+                    Some(positions) => {
+                        tracing::info!("Jog mode on");
+                        jogger = Some(Jogger::new(
+                            duck_config.jog.clone(),
+                            &joint_names,
+                            &duck_config.resolved_joint_limits(),
+                            &positions,
+                        ));
+                    }
+                    None => tracing::warn!("Failed to read joint positions, not jogging"),
+                }
+            }
+        }
+        for (joint, delta) in jog_moves {
+            let Some(ref mut jog) = jogger else {
+                tracing::warn!("Not in jog mode, ignoring jog of {}", joint);
+                continue;
+            };
+            match jog.jog(&joint, delta) {
+                Ok(goal) => tracing::info!("Jog {} to {:.3} rad", joint, goal),
+                Err(e) => tracing::warn!("Jog failed: {}", e),
+            }
+        }
+
+        // ── Calibration routines ──
+
+        if let Some(run) = routine.take_if(|_| stop_routine) {
+            finish_routine(&run, false, &bus);
+        }
+        if let Some(requested) = routine_request {
+            // Asking for the running routine again stops it
+            let running = routine.take();
+            let same = running.as_ref().is_some_and(|run| run.routine() == requested);
+            if let Some(run) = running {
+                finish_routine(&run, false, &bus);
+            }
+            if same {
+                tracing::info!("{} stopped", requested.name());
+            } else if paused || fallen || jogger.is_some() {
+                tracing::warn!("Unpause the duck on the ground to run {}", requested.name());
+            } else {
+                let run = RoutineRun::new(requested, &duck_config.routines, Instant::now());
+                tracing::info!("Running {} for {:.0}s", requested.name(), run.duration());
+                routine = Some(run);
             }
         }
 
         // Skip control when paused
         if paused {
-            std::thread::sleep(Duration::from_millis(100));
+            emote = None;
+            // Recurrent policies start from a clean state when unpaused
+            policies.reset_state();
+            if let Some(ref mut planner) = planner {
+                planner.reset();
+            }
+            if let Some(run) = routine.take() {
+                finish_routine(&run, false, &bus);
+            }
+            let mut jog_positions = None;
+            if let Some(ref mut jog) = jogger {
+                if let Err(e) = hwi.set_position_all_array(jog.step(Instant::now())) {
+                    tracing::warn!("Motor write failed: {}", e);
+                }
+                jog_positions = hwi.get_present_positions();
+            } else if let Some(ref mut bias) = gyro_bias {
+                bias.update(&imu_sensor.get_data(), Instant::now());
+            }
+            if remote.is_some() || moments.is_some() {
+                let snapshot = Telemetry {
+                    time: start_time.elapsed().as_secs_f64(),
+                    paused: true,
+                    active_policy: policies.active_name().to_string(),
+                    joint_positions: jog_positions.unwrap_or_default(),
+                    motor_targets: jogger
+                        .as_ref()
+                        .map_or_else(|| motor_targets.clone(), |jog| jog.targets().to_vec()),
+                    commands: last_commands,
+                    cloud: cloud_status,
+                    battery_voltage: battery_monitor.voltage().unwrap_or(0.0),
+                    battery_percent: battery_monitor.percent().unwrap_or(0.0),
+                    battery_scale: battery_sag.as_ref().map_or(1.0, SagTracker::scale),
+                    bus: hwi.bus_stats().unwrap_or_default(),
+                    jogging: jogger.is_some(),
+                    ..Telemetry::default()
+                };
+                if let Some(ref mut moments) = moments {
+                    moments.record(&snapshot);
+                }
+                if let Some(ref server) = remote {
+                    server.publish(snapshot);
+                }
+            }
+            loop_timing.skip_period();
+            // Jogged joints are stepped at the control rate
+            let idle = if jogger.is_some() { control_period } else { Duration::from_millis(100) };
+            unwatched(&watchdog, || std::thread::sleep(idle));
             continue;
         }
 
+        // ── Duty-cycle rest (temperatures come from the health poll) ──
+
+        if let Some(ref duty) = duty_cycler {
+            if duty.is_resting() {
+                let rest_pose = duty.rest_pose(&init_pos, &joint_names);
+                let blend = duty.rest_blend(Instant::now());
+                motor_targets = rest_from
+                    .iter()
+                    .zip(&rest_pose)
+                    .map(|(&from, &to)| from + (to - from) * blend)
+                    .collect();
+                if let Err(e) = hwi.set_position_all_array(&motor_targets) {
+                    tracing::warn!("Motor write failed: {}", e);
+                }
+                loop_timing.skip_period();
+                sleep_until_next_tick(tick_start, control_period);
+                continue;
+            }
+        }
+
         // ── Read sensors ──
 
-        let imu_data = imu_sensor.get_data();
+        let read_start = Instant::now();
+        let read_span = audit::span("read");
+        let mut imu_data = imu_sensor.get_data();
 
-        let dof_pos = match hwi.get_present_positions() {
-            Some(pos) if pos.len() == NUM_DOFS => pos,
-            _ => continue, // skip this tick on read failure
-        };
+        // A wedged bus leaves the IMU repeating its last sample
+        let imu_age = imu_data.age(read_start).map_or(0.0, |age| age.as_secs_f64() * 1000.0);
+        if imu_age > duck_config.imu_stale_ms {
+            if !imu_stale {
+                tracing::error!("IMU data stale for {:.0} ms, soft-stopping", imu_age);
+                imu_stale = true;
+            }
+            if !soft_stopped {
+                bus.publish(Event::ServoFault {
+                    reason: format!("IMU data stale for {:.0} ms", imu_age),
+                });
+            }
+            sleep_until_next_tick(tick_start, control_period);
+            continue;
+        } else if imu_stale {
+            tracing::info!("IMU data fresh again");
+            imu_stale = false;
+        }
 
-        let dof_vel = match hwi.get_present_velocities() {
-            Some(vel) if vel.len() == NUM_DOFS => vel,
-            _ => continue,
+        if let Some(ref bias) = gyro_bias {
+            bias.correct(&mut imu_data);
+        }
+        if let Some(ref mounting) = mounting_bias {
+            mounting.apply(&mut imu_data);
+        }
+
+        // Positions and velocities (and loads, where the bus allows) in one read
+        let present = match hwi.get_present_state() {
+            Some(state)
+                if state.positions.len() == num_dofs && state.velocities.len() == num_dofs =>
+            {
+                state
+            }
+            _ => {
+                // Skip this tick on read failure
+                sleep_until_next_tick(tick_start, control_period);
+                continue;
+            }
         };
+        let dof_pos = present.positions;
+        let dof_vel = present.velocities;
+        let read_time = read_start.elapsed();
+        drop(read_span);
+        let mut collided = false;
+
+        // The policy's view of the sensors, held back when simulating latency
+        let delayed = sensor_delay
+            .as_mut()
+            .map(|delay| delay.apply(&imu_data, &dof_pos, &dof_vel));
+        let (obs_imu, obs_pos, obs_vel) = match delayed {
+            Some((ref imu, ref pos, ref vel)) => (imu, pos.as_slice(), vel.as_slice()),
+            None => (&imu_data, dof_pos.as_slice(), dof_vel.as_slice()),
+        };
+
+        // ── Collision detection (motor_targets still holds the last tick's targets) ──
+
+        if let Some(ref mut detector) = collisions {
+            if detector.due() {
+                if let Some(loads) = present.loads.or_else(|| hwi.get_present_loads()) {
+                    for hit in detector.update(&loads, &dof_pos, &motor_targets, Instant::now()) {
+                        collided = true;
+                        let name = &joint_names[hit.joint];
+                        tracing::warn!(
+                            "Collision on {} ({:?}, load {:.0}%), backing off",
+                            name,
+                            hit.kind,
+                            hit.load * 100.0
+                        );
+                        bus.publish(Event::CollisionDetected {
+                            joint: name.clone(),
+                            load: hit.load,
+                        });
+                    }
+                }
+            }
+        }
+
+        // ── Servo status-byte errors ──
+
+        for action in servo_errors.handle(&hwi.take_servo_errors(), &joint_names) {
+            let name = &joint_names[action.joint];
+            match action.reaction {
+                ServoErrorReaction::Log => {}
+                ServoErrorReaction::ReduceGains => {
+                    kps[action.joint] = servo_errors.reduced_kp(kps[action.joint]);
+                    tracing::warn!("Reducing KP of {} to {:.0}", name, kps[action.joint]);
+                    let resting = duty_cycler.as_ref().is_some_and(|d| d.is_resting());
+                    if !resting {
+                        if let Err(e) = hwi.set_kps(&kps) {
+                            tracing::error!("Failed to reduce gains: {}", e);
+                        }
+                    }
+                }
+                ServoErrorReaction::TorqueOff => {
+                    tracing::error!("Disabling torque on {}", name);
+                    if let Err(e) = hwi.disable_joint_torque(action.joint) {
+                        tracing::error!("Failed to disable torque on {}: {}", name, e);
+                    }
+                }
+            }
+        }
 
         let feet = feet_contacts.get();
+        let contact_events = feet_contacts.events();
 
-        // ── Advance gait phase ──
+        let tilt = imu_data.tilt();
+        let now_fallen = tilt > policy_manager::FALLEN_TILT;
+        if now_fallen && !fallen {
+            bus.publish(Event::FallDetected { tilt });
+        }
+        if now_fallen != fallen {
+            // A recurrent policy's memory of walking is no use on its back,
+            // nor its memory of the fall once it is up again
+            policies.reset_state();
+        }
+        fallen = now_fallen;
 
-        let imitation_phase = phase_tracker.step();
+        // ── Gait symmetry (motor_targets still holds the last tick's targets) ──
 
-        // ── Build observation vector ──
-        // Layout: [gyro(3), accel(3), commands(7), dof_pos-init(14), dof_vel*0.05(14),
-        //          last_action(14), last_last_action(14), last_last_last_action(14),
-        //          motor_targets(14), feet_contacts(2), phase(2)]
-        // Total: 3+3+7+14+14+14+14+14+14+2+2 = 101
-        // Note: the actual dimension depends on the trained model.
+        if let Some(ref mut analyzer) = gait_analyzer {
+            let walking = !fallen && last_commands[..3].iter().any(|c| c.abs() > 1e-3);
+            let time = start_time.elapsed().as_secs_f64();
+            if let Some(report) = analyzer.update(time, walking, feet, &dof_pos, &motor_targets) {
+                gait::log_report(&report, duck_config.gait.warn_index);
+            }
+        }
 
-        let mut obs = Vec::with_capacity(128);
+        // ── Automatic policy selection ──
+
+        if let Some(name) = auto_switch
+            .as_mut()
+            .and_then(|auto| auto.update(&imu_data, &last_commands))
+            .filter(|name| policies.contains(name))
+        {
+            let from = policies.active_name().to_string();
+            if policies.switch_to(name, &motor_targets)? {
+                bus.publish(Event::PolicySwapped {
+                    from,
+                    to: name.to_string(),
+                });
+            }
+        }
 
-        // IMU data
-        obs.extend_from_slice(&imu_data.gyro);
-        obs.extend_from_slice(&imu_data.accel);
+        // ── Advance gait phase ──
+
+        let imitation_phase = phase_tracker.step(&last_commands);
 
-        // Commands
-        obs.extend_from_slice(&last_commands);
+        // ── Calibration routine (any other input driving stops it) ──
 
-        // Joint positions relative to init
-        for i in 0..NUM_DOFS {
-            obs.push(dof_pos[i] - init_pos[i]);
+        if let Some(mut run) = routine.take() {
+            let now = Instant::now();
+            run.observe(now, feet, imitation_phase, imu_data.gyro, tilt);
+            if let Some(driver) = sources.driver() {
+                tracing::info!("{} stopped, {} is driving", run.routine().name(), driver);
+                finish_routine(&run, false, &bus);
+            } else if fallen || run.is_done(now) {
+                finish_routine(&run, !fallen, &bus);
+            } else {
+                last_commands = run.commands(now);
+                routine = Some(run);
+            }
         }
 
-        // Joint velocities (scaled)
-        for i in 0..NUM_DOFS {
-            obs.push(dof_vel[i] * 0.05);
+        // ── High-level planner (manual and scripted input take priority) ──
+
+        if let Some(ref mut planner) = planner {
+            if sources.driver().is_some() || routine.is_some() {
+                planner.reset();
+            } else {
+                let planner_inputs = ObservationInputs {
+                    imu: obs_imu,
+                    commands: &last_commands,
+                    dof_pos: obs_pos,
+                    init_pos: &init_pos,
+                    dof_vel: obs_vel,
+                    motor_targets: &motor_targets,
+                    feet_contacts: &feet,
+                    phase: &imitation_phase,
+                };
+                match planner.update(&planner_inputs) {
+                    Ok(commands) => last_commands = commands,
+                    Err(e) => {
+                        tracing::error!("Planner failed: {}", e);
+                        last_commands = [0.0; 7];
+                    }
+                }
+            }
         }
 
-        // Action history
-        obs.extend_from_slice(&last_action);
-        obs.extend_from_slice(&last_last_action);
-        obs.extend_from_slice(&last_last_last_action);
+        // ── Thermal throttling of the velocity range ──
 
-        // Motor targets
-        obs.extend_from_slice(&motor_targets);
+        if let Some(ref thermal) = thermal {
+            thermal.limit_velocity(&mut last_commands[..3], &[X_RANGE, Y_RANGE, YAW_RANGE]);
+        }
 
-        // Feet contacts
-        obs.extend_from_slice(&feet);
+        // ── Battery sag: scale the velocity range down, track stability ──
 
-        // Gait phase
-        obs.extend_from_slice(&imitation_phase);
+        if let Some(ref mut sag) = battery_sag {
+            sag.limit_velocity(&mut last_commands[..3], &[X_RANGE, Y_RANGE, YAW_RANGE]);
+            sag.record(&last_commands[..3], imu_data.gyro, control_period.as_secs_f64());
+        }
+        let action_scale = args.action_scale * battery_sag.as_ref().map_or(1.0, SagTracker::scale);
 
-        // ── Policy inference ──
+        for event in gait_events.update(&last_commands, imitation_phase) {
+            bus.publish(event);
+        }
 
-        let action = match policy.infer(&obs) {
-            Ok(a) => a,
+        // ── Policy inference (builds the observation, updates action history) ──
+
+        let inputs = ObservationInputs {
+            imu: obs_imu,
+            commands: &last_commands,
+            dof_pos: obs_pos,
+            init_pos: &init_pos,
+            dof_vel: obs_vel,
+            motor_targets: &motor_targets,
+            feet_contacts: &feet,
+            phase: &imitation_phase,
+        };
+        let inference_start = Instant::now();
+        let inference_span = audit::span("inference");
+        let (obs, action) = match policies.infer(&inputs) {
+            Ok(out) => out,
             Err(e) => {
                 tracing::error!("Inference failed: {}", e);
+                sleep_until_next_tick(tick_start, control_period);
                 continue;
             }
         };
-
-        // ── Update action history ──
-
-        last_last_last_action = last_last_action.clone();
-        last_last_action = last_action.clone();
-        last_action = action.clone();
+        let inference_time = inference_start.elapsed();
+        drop(inference_span);
 
         // ── Compute motor targets ──
 
-        motor_targets = init_pos
-            .iter()
-            .zip(action.iter())
-            .map(|(&init, &act)| init + act * args.action_scale)
-            .collect();
+        let mut targets = policies.blend(
+            init_pos
+                .iter()
+                .zip(action.iter())
+                .map(|(&init, &act)| init + act * action_scale)
+                .collect(),
+        );
 
         // Optional low-pass filter
         if let Some(ref mut filter) = action_filter {
-            filter.push(&motor_targets);
+            filter.push(&targets);
             if start_time.elapsed() > Duration::from_secs(1) {
-                motor_targets = filter.get_filtered_action();
+                targets = filter.get_filtered_action();
+            }
+        }
+
+        // Safety envelope: no slams, nothing far from the init pose
+        let mut action_clamps = Clamps::default();
+        if let Some(ref mut limits) = action_limits {
+            action_clamps = limits.apply(&mut targets);
+            if action_clamps.any() {
+                tracing::warn!(
+                    "Action limits clamped {} targets by step, {} by deviation",
+                    action_clamps.step,
+                    action_clamps.deviation
+                );
             }
         }
+        motor_targets = targets;
 
         // ── Apply head commands from gamepad ──
 
-        if motor_targets.len() > 8 {
-            motor_targets[5] = last_commands[3] + motor_targets[5];
-            motor_targets[6] = last_commands[4] + motor_targets[6];
-            motor_targets[7] = last_commands[5] + motor_targets[7];
-            motor_targets[8] = last_commands[6] + motor_targets[8];
+        for (&joint, &offset) in head_joints.iter().zip(&last_commands[3..7]) {
+            if let Some(target) = joint.and_then(|j| motor_targets.get_mut(j)) {
+                *target += offset;
+            }
+        }
+
+        // ── Emote playback (overrides the policy until it is over) ──
+
+        if let Some(ref player) = emote {
+            match player.targets(Instant::now()) {
+                Some(targets) => {
+                    if let Some(ref mut limits) = action_limits {
+                        limits.hold(&targets);
+                    }
+                    motor_targets = targets;
+                }
+                None => {
+                    tracing::info!("Emote {} done", player.name());
+                    emote = None;
+                }
+            }
+        }
+
+        // ── Duty-cycle resume: out of the resting pose ──
+
+        if let Some(blend) = duty_cycler.as_ref().and_then(|d| d.resume_blend(Instant::now())) {
+            for (target, &from) in motor_targets.iter_mut().zip(&rest_from) {
+                *target = from + (*target - from) * blend;
+            }
+        }
+
+        if let Some(ref mut detector) = collisions {
+            detector.apply(&mut motor_targets, Instant::now());
+        }
+
+        // ── Record training data ──
+
+        if let Some(ref rec) = recorder {
+            let time = start_time.elapsed().as_secs_f64();
+            rec.record_frame(tick, time, &obs, &action, &motor_targets, &last_commands);
+        }
+        tick += 1;
+
+        // ── Send to motors (the first command step of this tick) ──
+
+        commands.start(&motor_targets, Instant::now());
+        let action_dict = make_action_dict(&commands.command(1), &joint_names);
+        let write_start = Instant::now();
+        let write_span = audit::span("write");
+        let written = hwi.set_position_all(&action_dict);
+        let write_time = write_start.elapsed();
+        drop(write_span);
+        if let Err(e) = written {
+            match battery_monitor.voltage() {
+                // Serial errors on a sagging bus are usually a brown-out
+                Some(v) if battery_monitor.level() > BatteryLevel::Ok => {
+                    tracing::warn!("Motor write failed: {} (battery at {:.2}V)", e, v)
+                }
+                _ => tracing::warn!("Motor write failed: {}", e),
+            }
         }
 
-        // ── Send to motors ──
+        // ── Telemetry ──
+
+        let telemetry_span = audit::span("telemetry");
+        if let Some(ref mut log) = telemetry_log {
+            let time = start_time.elapsed().as_secs_f64();
+            let imu_values: Vec<f64> = [imu_data.gyro, imu_data.euler, imu_data.projected_gravity]
+                .concat();
+            log.record(Signal::Joints, tick, time, &dof_pos);
+            log.record(Signal::Imu, tick, time, &imu_values);
+            log.record(Signal::Commands, tick, time, &last_commands);
+        }
 
-        let action_dict = make_action_dict(&motor_targets, &joint_names);
-        if let Err(e) = hwi.set_position_all(&action_dict) {
-            tracing::warn!("Motor write failed: {}", e);
+        if remote.is_some() || moments.is_some() {
+            let snapshot = Telemetry {
+                time: start_time.elapsed().as_secs_f64(),
+                paused: false,
+                active_policy: policies.active_name().to_string(),
+                joint_positions: dof_pos.clone(),
+                motor_targets: motor_targets.clone(),
+                commands: last_commands,
+                gyro: imu_data.gyro,
+                euler: imu_data.euler,
+                projected_gravity: imu_data.projected_gravity,
+                feet_contacts: feet,
+                phase: imitation_phase,
+                loop_ms: last_tick_ms,
+                cloud: cloud_status,
+                battery_voltage: battery_monitor.voltage().unwrap_or(0.0),
+                battery_percent: battery_monitor.percent().unwrap_or(0.0),
+                battery_scale: battery_sag.as_ref().map_or(1.0, SagTracker::scale),
+                bus: hwi.bus_stats().unwrap_or_default(),
+                jogging: false,
+            };
+            if let Some(ref mut moments) = moments {
+                moments.record(&snapshot);
+            }
+            if let Some(ref server) = remote {
+                server.publish(snapshot);
+            }
         }
 
+        drop(telemetry_span);
+
         // ── Timing ──
 
         let took = tick_start.elapsed();
+        last_tick_ms = took.as_secs_f64() * 1000.0;
+        loop_health.record_tick(took, control_period);
+        let mut timings = StageTimings {
+            read: read_time,
+            inference: inference_time,
+            write: write_time,
+            total: took,
+        };
+        if let Some(ref mut blackbox) = blackbox {
+            let mut flags = 0;
+            if took > control_period {
+                flags |= blackbox::FLAG_OVERRUN;
+            }
+            if collided {
+                flags |= blackbox::FLAG_COLLISION;
+            }
+            let sagging = battery_sag.as_ref().is_some_and(|s| s.scale() < 1.0);
+            if sagging || thermal.as_ref().is_some_and(|t| t.scale() < 1.0) {
+                flags |= blackbox::FLAG_THROTTLED;
+            }
+            let to_f32 = |values: &[f64]| values.iter().map(|&v| v as f32).collect();
+            blackbox.record(TickRecord {
+                tick,
+                time: start_time.elapsed().as_secs_f64(),
+                timings,
+                safety: safety_state(estopped, soft_stopped, fallen, paused),
+                flags,
+                tilt: tilt as f32,
+                gravity: imu_data.projected_gravity.map(|g| g as f32),
+                commands: last_commands.map(|c| c as f32),
+                positions: to_f32(&dof_pos),
+                targets: to_f32(&motor_targets),
+            });
+        }
+        if let Some(ref mut log) = telemetry_log {
+            let overshoot_ms = took.saturating_sub(control_period).as_secs_f64() * 1000.0;
+            let time = start_time.elapsed().as_secs_f64();
+            log.record(Signal::Timing, tick, time, &[last_tick_ms, overshoot_ms]);
+            let warnings = log_limit::warning_count();
+            if warnings > logged_warnings {
+                log.record(Signal::Warnings, tick, time, &[(warnings - logged_warnings) as f64]);
+                logged_warnings = warnings;
+            }
+            if action_clamps.any() {
+                let clamps = [action_clamps.step as f64, action_clamps.deviation as f64];
+                log.record(Signal::ActionClamps, tick, time, &clamps);
+            }
+            for event in &contact_events {
+                let touchdown = if event.edge == ContactEdge::Touchdown { 1.0 } else { 0.0 };
+                let at = event.at.saturating_duration_since(start_time).as_secs_f64();
+                log.record(Signal::ContactEvents, tick, time, &[event.foot as f64, touchdown, at]);
+            }
+        }
+        if let Some(summary) = loop_timing.take_window(Instant::now()) {
+            summary.log("Loop timing");
+            if let Some(ref mut log) = telemetry_log {
+                let time = start_time.elapsed().as_secs_f64();
+                log.record(Signal::LoopStats, tick, time, &summary.values());
+            }
+        }
+        audit::flush();
+
+        // ── Remaining command steps, evenly spaced until the next tick ──
+
+        // A late tick has no time left for the steps in between: the final
+        // targets go out at once instead of a burst of overdue steps
+        let overran = tick_start.elapsed() > control_period;
+        let first_step = if overran { commands.steps().max(2) } else { 2 };
+        for step in first_step..=commands.steps() {
+            let due = tick_start + commands.command_period() * (step - 1);
+            spin_sleep::sleep(due.saturating_duration_since(Instant::now()));
+            let step_start = Instant::now();
+            if let Err(e) = hwi.set_position_all_array(&commands.command(step)) {
+                tracing::warn!("Motor write failed: {}", e);
+            }
+            // Writes count, the waits between them don't
+            let step_write = step_start.elapsed();
+            timings.write += step_write;
+            timings.total += step_write;
+        }
+        loop_timing.record(&timings, tick_start);
+
         if took > control_period {
             let overshoot = took - control_period;
             tracing::warn!(
@@ -396,14 +2273,256 @@ fn main() -> Result<()> {
                 overshoot.as_secs_f64() * 1000.0
             );
         } else {
-            // High-precision sleep (avoids OS scheduler jitter)
-            spin_sleep::sleep(control_period - took);
+            sleep_until_next_tick(tick_start, control_period);
         }
     }
+
+    if let Some(report) = gait_analyzer.as_ref().and_then(|a| a.last_report()) {
+        tracing::info!("Gait wear report: {}", serde_json::to_string(report)?);
+    }
+
+    if let Some(stats) = hwi.bus_stats() {
+        tracing::info!(
+            "Servo bus: {} transactions, {} retries, {} failures, {} reopens",
+            stats.transactions,
+            stats.retries,
+            stats.failures,
+            stats.reopens
+        );
+    }
+
+    loop_timing.run_summary().log("Loop timing over the run");
+    audit::report(tick);
+    tracing::info!("Shutting down after {} ticks", tick);
+    Ok(())
+}
+
+/// Local driving input: a gamepad forwarded over the network, or a gamepad
+/// or keyboard on this machine.
+fn local_input(args: &Args) -> Result<(&'static str, Box<dyn CommandSource>)> {
+    if let Some(ref path) = args.fake_gamepad {
+        let script = InputScript::load(path)?;
+        return Ok(("fake_gamepad", Box::new(FakeGamepad::new(script, args.stick_layout))));
+    }
+    let input: (&'static str, Box<dyn CommandSource>) = match args.teleop_listen {
+        Some(ref addr) if addr.contains(':') => ("teleop", Box::new(RemoteGamepad::bind(addr)?)),
+        Some(ref host) => (
+            "teleop",
+            Box::new(RemoteGamepad::bind(&format!("{}:{}", host, teleop::DEFAULT_PORT))?),
+        ),
+        None => match args.input {
+            InputSource::Gamepad => {
+                ("gamepad", Box::new(XBoxController::new(20, args.stick_layout)))
+            }
+            InputSource::Keyboard => ("keyboard", Box::new(KeyboardController::new(20)?)),
+        },
+    };
+    Ok(input)
+}
+
+/// Capture mode: record an emote posed by hand, with torque off.
+fn capture_emote(
+    name: &str,
+    hwi: &mut dyn MotorInterface,
+    mut input: Box<dyn CommandSource>,
+    library: &EmoteLibrary,
+    config: &EmotesConfig,
+) -> Result<()> {
+    let joint_names = hwi.joint_names().to_vec();
+    for joint in 0..joint_names.len() {
+        hwi.disable_joint_torque(joint)?;
+    }
+    let mut capture = EmoteCapture::new(name, joint_names, config.keyframe_duration);
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&shutdown))
+            .context("Failed to install signal handler")?;
+    }
+
+    tracing::info!(
+        "Capturing emote {}: torque off, A captures a keyframe, B drops the last, Start saves",
+        name
+    );
+    loop {
+        if shutdown.load(Ordering::Relaxed) {
+            tracing::info!("Capture cancelled, {} keyframes discarded", capture.len());
+            return Ok(());
+        }
+        let buttons = &input.get_last_command().buttons;
+        let (keyframe, undo, save) =
+            (buttons.a.triggered, buttons.b.triggered, buttons.start.triggered);
+
+        if keyframe {
+            match hwi.get_present_positions() {
+                Some(positions) => {
+                    capture.capture(positions);
+                    tracing::info!("Keyframe {} captured", capture.len());
+                }
+                None => tracing::warn!("Could not read joint positions, keyframe not captured"),
+            }
+        }
+        if undo && capture.undo() {
+            tracing::info!("Dropped the last keyframe, {} left", capture.len());
+        }
+        if save {
+            if capture.is_empty() {
+                tracing::warn!("Capture at least one keyframe before saving");
+            } else {
+                break;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let path = library.save(&capture.finish()?)?;
+    tracing::info!("Emote {} saved to {}", name, path.display());
+    Ok(())
+}
+
+/// Guide the user through BNO055 calibration and save the offsets once every
+/// sensor reports fully calibrated.
+#[cfg(target_os = "linux")]
+fn calibrate_imu(upside_down: bool, bus: &ImuBusConfig, path: &Path) -> Result<()> {
+    let mut imu =
+        Bno055Calibration::open(upside_down, bus).context("Failed to open the BNO055")?;
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&shutdown))
+            .context("Failed to install signal handler")?;
+    }
+
+    tracing::info!("Calibrating the BNO055, Ctrl-C cancels");
+    let mut last = None;
+    loop {
+        if shutdown.load(Ordering::Relaxed) {
+            tracing::info!("Calibration cancelled, nothing saved");
+            return Ok(());
+        }
+        let status = imu.status()?;
+        if status.is_complete() {
+            break;
+        }
+        if last != Some(status) {
+            let hint = if status.gyro < 3 {
+                "set the duck down and keep it still for a few seconds"
+            } else if status.accel < 3 {
+                "hold the duck still in six orientations (upright, upside down, on each side, \
+                 nose down, nose up) for a few seconds each"
+            } else if status.mag < 3 {
+                "move the duck slowly through figure-eights in the air"
+            } else {
+                "keep moving the duck gently until the system level reaches 3"
+            };
+            tracing::info!(
+                "Calibration sys {} gyro {} accel {} mag {}: {}",
+                status.system,
+                status.gyro,
+                status.accel,
+                status.mag,
+                hint
+            );
+            last = Some(status);
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+
+    imu::save_calibration(path, &imu.offsets()?)?;
+    tracing::info!("BNO055 fully calibrated, offsets saved to {}", path.display());
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn calibrate_imu(_upside_down: bool, _bus: &ImuBusConfig, _path: &Path) -> Result<()> {
+    anyhow::bail!("IMU calibration needs I2C, which is only available on Linux")
+}
+
+/// Open the configured foot contact sensors: GPIO switches or FSRs on an ADC.
+#[cfg(target_os = "linux")]
+fn open_feet_contacts(config: &FeetContactsConfig) -> Result<Box<dyn FeetContactsReader>> {
+    let feet = FeetContacts::new(config).context("Failed to initialize feet contacts")?;
+    Ok(Box::new(feet))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_feet_contacts(_config: &FeetContactsConfig) -> Result<Box<dyn FeetContactsReader>> {
+    Ok(Box::new(MockFeetContacts))
+}
+
+/// What the safety logic is doing with the robot, most severe first.
+fn safety_state(estopped: bool, soft_stopped: bool, fallen: bool, paused: bool) -> SafetyState {
+    if estopped {
+        SafetyState::Estopped
+    } else if soft_stopped {
+        SafetyState::SoftStopped
+    } else if fallen {
+        SafetyState::Fallen
+    } else if paused {
+        SafetyState::Paused
+    } else {
+        SafetyState::Running
+    }
+}
+
+/// PID gains per joint from the duck config; the CLI flags override all
+/// joints.
+fn resolve_gains(config: &DuckConfig, joint_names: &[String], args: &Args) -> Result<Gains> {
+    let mut gains = config.gains.resolve(joint_names)?;
+    for (values, flag) in [
+        (&mut gains.kp, args.kp),
+        (&mut gains.ki, args.ki),
+        (&mut gains.kd, args.kd),
+    ] {
+        if let Some(value) = flag {
+            values.fill(value as f64);
+        }
+    }
+    Ok(gains)
+}
+
+/// Sounds from `./assets`, with the configured voice if it loads.
+fn open_speaker(config: &DuckConfig) -> Option<Sounds> {
+    let mut sounds =
+        Sounds::new(config.volume as f32, Path::new("./assets"), &config.sound_mixing).ok()?;
+    if let Some(voice) = config.speech.voice.as_deref().map(|v| expand_home(Path::new(v))) {
+        if let Err(e) = sounds.load_voice(&voice, &config.speech) {
+            tracing::warn!("Speech disabled: {:#}", e);
+        }
+    }
+    Some(sounds)
+}
+
+/// Run a blocking operation without tripping the watchdog.
+fn unwatched<T>(watchdog: &Option<Watchdog>, f: impl FnOnce() -> T) -> T {
+    match watchdog {
+        Some(wd) => wd.disarmed(f),
+        None => f(),
+    }
+}
+
+/// Sleep out the rest of a tick, including one that was skipped partway, so
+/// the loop never spins faster than the control rate. High-precision sleep
+/// avoids OS scheduler jitter.
+fn sleep_until_next_tick(tick_start: Instant, control_period: Duration) {
+    let next_tick = tick_start + control_period;
+    spin_sleep::sleep(next_tick.saturating_duration_since(Instant::now()));
+}
+
+/// Log a calibration routine's report and send it to remote clients.
+fn finish_routine(run: &RoutineRun, completed: bool, bus: &EventBus) {
+    let report = run.report(completed);
+    let text = report.to_string();
+    if report.warnings.is_empty() {
+        tracing::info!("{}", text.trim_end());
+    } else {
+        tracing::warn!("{}", text.trim_end());
+    }
+    bus.publish(Event::RoutineFinished { report });
 }
 
 /// Expand `~` at the start of a path to the user's home directory.
-fn expand_home(path: &PathBuf) -> PathBuf {
+fn expand_home(path: &Path) -> PathBuf {
     if let Some(s) = path.to_str() {
         if s.starts_with("~/") {
             if let Ok(home) = std::env::var("HOME") {
@@ -411,5 +2530,5 @@ fn expand_home(path: &PathBuf) -> PathBuf {
             }
         }
     }
-    path.clone()
+    path.to_path_buf()
 }
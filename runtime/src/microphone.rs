@@ -0,0 +1,404 @@
+//! Microphone listening: claps and an optional wake word.
+//!
+//! A capture thread reads the input device through `cpal` (mixed to mono)
+//! and runs two detectors on it:
+//!
+//! - `ClapDetector`: a clap is a 10 ms window louder than `clap_threshold`
+//!   and `clap_ratio` times the background, that dies away within 100 ms
+//!   (so music and speech don't count). Claps within `double_clap_secs` of
+//!   each other make one sequence, published as `claps_heard` once it ends.
+//! - `WakeWord`: an ONNX keyword spotter run every `every_secs` on the last
+//!   `window_secs` of audio, resampled to its rate. A score above
+//!   `threshold` is published as `wake_word_heard`.
+//!
+//! The control loop maps one clap, a double clap and the wake word to a
+//! `MicAction`: a sound, an eye expression, or unpausing.
+
+use anyhow::{anyhow, bail, Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use crossbeam_channel::{bounded, Sender};
+use ndarray::Array2;
+use ort::session::Session;
+use ort::value::Tensor;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::config::{MicrophoneConfig, WakeWordConfig};
+use crate::events::{Event, EventBus};
+
+/// Detection window length (s).
+const WINDOW_SECS: f64 = 0.01;
+
+/// Longest a clap may stay loud (s).
+const MAX_CLAP_SECS: f64 = 0.1;
+
+/// Shortest gap between two claps (s).
+const CLAP_REFRACTORY_SECS: f64 = 0.15;
+
+/// Background loudness smoothing per window.
+const BACKGROUND_ALPHA: f64 = 0.02;
+
+/// Audio chunks waiting for the detectors before new ones are dropped.
+const CHUNK_QUEUE: usize = 64;
+
+/// Energy-based clap detector, timed in samples.
+#[derive(Debug, Clone)]
+pub struct ClapDetector {
+    window: usize,
+    threshold: f64,
+    ratio: f64,
+    sequence_windows: u64,
+    /// Samples of the window being filled: count and sum of squares.
+    filled: usize,
+    sum_sq: f64,
+    /// Windows seen so far.
+    windows: u64,
+    background: f64,
+    /// Window the current loud stretch started in.
+    onset: Option<u64>,
+    last_clap: Option<u64>,
+    claps: u32,
+}
+
+impl ClapDetector {
+    pub fn new(sample_rate: u32, config: &MicrophoneConfig) -> Self {
+        let sample_rate = sample_rate.max(1) as f64;
+        Self {
+            window: ((sample_rate * WINDOW_SECS) as usize).max(1),
+            threshold: config.clap_threshold,
+            ratio: config.clap_ratio,
+            sequence_windows: (config.double_clap_secs / WINDOW_SECS).ceil() as u64,
+            filled: 0,
+            sum_sq: 0.0,
+            windows: 0,
+            background: 0.0,
+            onset: None,
+            last_clap: None,
+            claps: 0,
+        }
+    }
+
+    /// Feed mono samples; returns the clap counts of sequences that ended.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<u32> {
+        let mut ended = Vec::new();
+        for &sample in samples {
+            self.sum_sq += sample as f64 * sample as f64;
+            self.filled += 1;
+            if self.filled == self.window {
+                let rms = (self.sum_sq / self.window as f64).sqrt();
+                self.filled = 0;
+                self.sum_sq = 0.0;
+                ended.extend(self.window_done(rms));
+            }
+        }
+        ended
+    }
+
+    fn window_done(&mut self, rms: f64) -> Option<u32> {
+        let now = self.windows;
+        self.windows += 1;
+
+        let loud = rms >= self.threshold && rms >= self.ratio * self.background;
+        let rested = self
+            .last_clap
+            .is_none_or(|last| (now - last) as f64 * WINDOW_SECS >= CLAP_REFRACTORY_SECS);
+        match self.onset {
+            None if loud && rested => self.onset = Some(now),
+            Some(onset) if rms < self.threshold / 2.0 => {
+                // Over quickly enough to be a clap rather than a noise
+                self.onset = None;
+                if (now - onset) as f64 * WINDOW_SECS <= MAX_CLAP_SECS {
+                    self.last_clap = Some(onset);
+                    self.claps += 1;
+                }
+            }
+            _ => {}
+        }
+        self.background += BACKGROUND_ALPHA * (rms - self.background);
+
+        let quiet_since_clap = self
+            .last_clap
+            .is_some_and(|last| now - last > self.sequence_windows && self.onset.is_none());
+        if quiet_since_clap && self.claps > 0 {
+            self.last_clap = None;
+            return Some(std::mem::take(&mut self.claps));
+        }
+        None
+    }
+}
+
+/// Linear-interpolating resampler for the keyword spotter.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    step: f64,
+    pos: f64,
+    pending: Vec<f32>,
+}
+
+impl Resampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            step: from_rate.max(1) as f64 / to_rate.max(1) as f64,
+            pos: 0.0,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.pending.extend_from_slice(samples);
+        let mut out = Vec::new();
+        while self.pos + 1.0 < self.pending.len() as f64 {
+            let i = self.pos as usize;
+            let frac = (self.pos - i as f64) as f32;
+            out.push(self.pending[i] + (self.pending[i + 1] - self.pending[i]) * frac);
+            self.pos += self.step;
+        }
+        let used = (self.pos as usize).min(self.pending.len());
+        self.pending.drain(..used);
+        self.pos -= used as f64;
+        out
+    }
+}
+
+/// ONNX keyword spotter over a sliding window of audio.
+struct WakeWord {
+    session: Session,
+    input_name: String,
+    class: usize,
+    threshold: f64,
+    resampler: Resampler,
+    window: VecDeque<f32>,
+    window_len: usize,
+    every: usize,
+    since_run: usize,
+}
+
+impl WakeWord {
+    fn load(model: &Path, config: &WakeWordConfig, input_rate: u32) -> Result<Self> {
+        let session = Session::builder()
+            .context("Failed to create ONNX session builder")?
+            .commit_from_file(model)
+            .with_context(|| format!("Failed to load wake word model {}", model.display()))?;
+        let input_name = session.inputs()[0].name().to_string();
+        let rate = config.sample_rate.max(1) as f64;
+        tracing::info!("Wake word model {} (input: {})", model.display(), input_name);
+        Ok(Self {
+            session,
+            input_name,
+            class: config.class,
+            threshold: config.threshold,
+            resampler: Resampler::new(input_rate, config.sample_rate),
+            window: VecDeque::new(),
+            window_len: ((rate * config.window_secs) as usize).max(1),
+            every: ((rate * config.every_secs) as usize).max(1),
+            since_run: 0,
+        })
+    }
+
+    /// Feed samples at the device rate; returns the score of a detection.
+    fn push(&mut self, samples: &[f32]) -> Result<Option<f64>> {
+        let resampled = self.resampler.push(samples);
+        self.since_run += resampled.len();
+        self.window.extend(resampled);
+        let excess = self.window.len().saturating_sub(self.window_len);
+        self.window.drain(..excess);
+        if self.window.len() < self.window_len || self.since_run < self.every {
+            return Ok(None);
+        }
+        self.since_run = 0;
+
+        let audio: Vec<f32> = self.window.iter().copied().collect();
+        let input = Array2::from_shape_vec((1, audio.len()), audio)
+            .context("Failed to create wake word input")?;
+        let input = Tensor::from_array(input).context("Failed to create wake word tensor")?;
+        let outputs = self
+            .session
+            .run(ort::inputs![&self.input_name => input])
+            .context("Wake word inference failed")?;
+        let (_, scores) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .context("Failed to extract wake word scores")?;
+        let score = scores.get(self.class).copied().unwrap_or(0.0) as f64;
+        if score < self.threshold {
+            return Ok(None);
+        }
+        // Start over so one utterance is heard once
+        self.window.clear();
+        Ok(Some(score))
+    }
+}
+
+/// Capture thread; stops when dropped.
+pub struct Microphone {
+    stopped: Arc<AtomicBool>,
+}
+
+impl Microphone {
+    /// Start listening; `wake_word_model` enables the keyword spotter.
+    pub fn start(
+        config: &MicrophoneConfig,
+        wake_word_model: Option<&Path>,
+        bus: EventBus,
+    ) -> Result<Self> {
+        let config = config.clone();
+        let wake_word_model = wake_word_model.map(Path::to_path_buf);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let worker_stopped = Arc::clone(&stopped);
+        let (ready_tx, ready_rx) = bounded::<Result<String>>(1);
+
+        // The stream can't leave the thread that made it on every host
+        thread::spawn(move || {
+            let (chunk_tx, chunks) = bounded::<Vec<f32>>(CHUNK_QUEUE);
+            let (stream, name, rate) = match open_stream(config.device.as_deref(), chunk_tx) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let mut wake_word = match wake_word_model {
+                Some(model) => match WakeWord::load(&model, &config.wake_word, rate) {
+                    Ok(wake_word) => Some(wake_word),
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                },
+                None => None,
+            };
+            let _ = ready_tx.send(Ok(format!("{} at {} Hz", name, rate)));
+
+            let mut claps = ClapDetector::new(rate, &config);
+            while !worker_stopped.load(Ordering::Acquire) {
+                let Ok(chunk) = chunks.recv_timeout(Duration::from_millis(100)) else {
+                    continue;
+                };
+                for count in claps.push(&chunk) {
+                    bus.publish(Event::ClapsHeard { claps: count });
+                }
+                if let Some(ref mut detector) = wake_word {
+                    match detector.push(&chunk) {
+                        Ok(Some(score)) => bus.publish(Event::WakeWordHeard { score }),
+                        Ok(None) => {}
+                        Err(e) => {
+                            tracing::warn!("Wake word detection stopped: {:#}", e);
+                            wake_word = None;
+                        }
+                    }
+                }
+            }
+            drop(stream);
+        });
+
+        let device = ready_rx
+            .recv()
+            .map_err(|_| anyhow!("Microphone thread exited"))??;
+        tracing::info!("Microphone listening on {}", device);
+        Ok(Self { stopped })
+    }
+}
+
+impl Drop for Microphone {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+    }
+}
+
+/// Open the named (or default) input device, sending mono chunks to `tx`.
+fn open_stream(device: Option<&str>, tx: Sender<Vec<f32>>) -> Result<(cpal::Stream, String, u32)> {
+    let host = cpal::default_host();
+    let device = match device {
+        Some(wanted) => host
+            .input_devices()
+            .context("Failed to list input devices")?
+            .find(|d| d.name().is_ok_and(|name| name == wanted))
+            .with_context(|| format!("No input device named {:?}", wanted))?,
+        None => host.default_input_device().context("No default input device")?,
+    };
+    let name = device.name().unwrap_or_else(|_| "input".to_string());
+    let supported = device
+        .default_input_config()
+        .context("Failed to read the input format")?;
+    let config = supported.config();
+
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, tx)?,
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, tx)?,
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, tx)?,
+        SampleFormat::I32 => build_stream::<i32>(&device, &config, tx)?,
+        other => bail!("Unsupported input sample format {:?}", other),
+    };
+    stream.play().context("Failed to start audio capture")?;
+    Ok((stream, name, config.sample_rate.0))
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    tx: Sender<Vec<f32>>,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels.max(1) as usize;
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let mono = data
+                    .chunks(channels)
+                    .map(|frame| {
+                        let sum: f32 = frame.iter().map(|&s| s.to_sample::<f32>()).sum();
+                        sum / frame.len() as f32
+                    })
+                    .collect();
+                let _ = tx.try_send(mono);
+            },
+            |e| tracing::warn!("Audio capture error: {}", e),
+            None,
+        )
+        .context("Failed to open audio capture")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_double_clap_and_noise() {
+        let rate = 16_000;
+        let config = MicrophoneConfig::default();
+        let mut detector = ClapDetector::new(rate, &config);
+        let quiet = vec![0.01f32; rate as usize / 10];
+        let clap = vec![0.8f32; rate as usize / 50];
+        let noise = vec![0.5f32; rate as usize / 2];
+
+        // Two claps 0.3 s apart, then silence long enough to end them
+        let mut heard = Vec::new();
+        for chunk in [&quiet, &clap, &quiet, &quiet, &clap, &quiet] {
+            heard.extend(detector.push(chunk));
+        }
+        assert!(heard.is_empty());
+        for _ in 0..8 {
+            heard.extend(detector.push(&quiet));
+        }
+        assert_eq!(heard, [2]);
+
+        // Half a second of loud noise is not a clap
+        heard.extend(detector.push(&noise));
+        for _ in 0..10 {
+            heard.extend(detector.push(&quiet));
+        }
+        assert_eq!(heard, [2]);
+
+        let mut resampler = Resampler::new(48_000, 16_000);
+        let ramp: Vec<f32> = (0..30).map(|i| i as f32).collect();
+        assert_eq!(resampler.push(&ramp), [0.0, 3.0, 6.0, 9.0, 12.0, 15.0, 18.0, 21.0, 24.0, 27.0]);
+    }
+}
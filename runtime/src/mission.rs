@@ -0,0 +1,374 @@
+//! Scripted missions: timed command sequences for demos and repeatable tests.
+//!
+//! A mission is a JSON (or YAML) list of steps played in order:
+//!
+//! ```json
+//! {
+//!     "name": "demo",
+//!     "loop": false,
+//!     "steps": [
+//!         { "action": "walk", "vx": 0.1, "duration": 3.0 },
+//!         { "action": "turn", "degrees": 90 },
+//!         { "action": "nod", "count": 2 },
+//!         { "action": "sound", "name": "happy.wav" },
+//!         { "action": "blink" },
+//!         { "action": "emote", "name": "wave" },
+//!         { "action": "wait", "duration": 1.0 }
+//!     ]
+//! }
+//! ```
+//!
+//! `MissionPlayer` is a `CommandSource` at scripted priority, so a gamepad
+//! or remote client being moved takes over and an e-stop stops it. Sounds
+//! and blinks go out on the event bus. The mission clock stops while the
+//! duck is paused, and a fall or servo fault aborts the mission. Emotes are
+//! requested on the event bus too and take no mission time, so follow one
+//! with a `wait` as long as the emote.
+//!
+//! Turns are open loop: the yaw rate is held for `degrees / rate`, and how
+//! far the duck actually turns is up to the walking policy.
+
+use anyhow::{bail, Context, Result};
+use crossbeam_channel::Receiver;
+use serde::Deserialize;
+use std::f64::consts::TAU;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::controller::{CommandSource, ControllerOutput};
+use crate::events::{Event, EventBus};
+
+/// Seconds per nod.
+const NOD_PERIOD: f64 = 0.6;
+
+fn default_turn_rate() -> f64 {
+    0.5
+}
+
+fn default_nod_count() -> u32 {
+    2
+}
+
+fn default_nod_amplitude() -> f64 {
+    0.3
+}
+
+/// One mission step. Durations are in seconds.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Step {
+    Walk {
+        #[serde(default)]
+        vx: f64,
+        #[serde(default)]
+        vy: f64,
+        #[serde(default)]
+        yaw: f64,
+        duration: f64,
+    },
+    /// Turn in place, positive to the left.
+    Turn {
+        degrees: f64,
+        /// Yaw rate (rad/s).
+        #[serde(default = "default_turn_rate")]
+        rate: f64,
+    },
+    /// Hold a head pose (rad).
+    Head {
+        #[serde(default)]
+        neck_pitch: f64,
+        #[serde(default)]
+        head_pitch: f64,
+        #[serde(default)]
+        head_yaw: f64,
+        #[serde(default)]
+        head_roll: f64,
+        duration: f64,
+    },
+    Nod {
+        #[serde(default = "default_nod_count")]
+        count: u32,
+        /// Head pitch swing (rad).
+        #[serde(default = "default_nod_amplitude")]
+        amplitude: f64,
+    },
+    /// Play a sound by file name, or a random one without a name.
+    Sound {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    Blink,
+    /// Play a recorded emote by name.
+    Emote {
+        name: String,
+    },
+    Wait {
+        duration: f64,
+    },
+}
+
+impl Step {
+    fn duration(&self) -> f64 {
+        match *self {
+            Step::Walk { duration, .. } | Step::Head { duration, .. } | Step::Wait { duration } => {
+                duration
+            }
+            Step::Turn { degrees, rate } => degrees.to_radians().abs() / rate,
+            Step::Nod { count, .. } => count as f64 * NOD_PERIOD,
+            Step::Sound { .. } | Step::Blink | Step::Emote { .. } => 0.0,
+        }
+    }
+
+    /// Commands `t` seconds into the step.
+    fn commands(&self, t: f64) -> [f64; 7] {
+        let mut commands = [0.0; 7];
+        match *self {
+            Step::Walk { vx, vy, yaw, .. } => commands[..3].copy_from_slice(&[vx, vy, yaw]),
+            Step::Turn { degrees, rate } => commands[2] = rate.copysign(degrees),
+            Step::Head {
+                neck_pitch,
+                head_pitch,
+                head_yaw,
+                head_roll,
+                ..
+            } => commands[3..].copy_from_slice(&[neck_pitch, head_pitch, head_yaw, head_roll]),
+            Step::Nod { amplitude, .. } => commands[4] = amplitude * (TAU * t / NOD_PERIOD).sin(),
+            Step::Sound { .. } | Step::Blink | Step::Emote { .. } | Step::Wait { .. } => {}
+        }
+        commands
+    }
+
+    /// Event published when the step starts.
+    fn event(&self) -> Option<Event> {
+        match self {
+            Step::Sound { name } => Some(Event::SoundRequested { name: name.clone() }),
+            Step::Blink => Some(Event::BlinkRequested),
+            Step::Emote { name } => Some(Event::EmoteRequested { name: name.clone() }),
+            _ => None,
+        }
+    }
+}
+
+/// A named sequence of steps.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Mission {
+    #[serde(default)]
+    pub name: String,
+    /// Start over after the last step.
+    #[serde(default, rename = "loop")]
+    pub repeat: bool,
+    pub steps: Vec<Step>,
+}
+
+impl Mission {
+    /// Load a mission from JSON, or YAML for `.yaml` / `.yml` files.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read mission {}", path.display()))?;
+        let yaml = path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+        let mission: Self = if yaml {
+            serde_yaml::from_str(&text)
+                .with_context(|| format!("Failed to parse mission {}", path.display()))?
+        } else {
+            serde_json::from_str(&text)
+                .with_context(|| format!("Failed to parse mission {}", path.display()))?
+        };
+        mission.validate()?;
+        Ok(mission)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.steps.is_empty() {
+            bail!("Mission has no steps");
+        }
+        for (i, step) in self.steps.iter().enumerate() {
+            let duration = step.duration();
+            if !duration.is_finite() || duration < 0.0 {
+                bail!("Mission step {} has an invalid duration: {:?}", i + 1, step);
+            }
+        }
+        if self.repeat && self.steps.iter().all(|s| s.duration() == 0.0) {
+            bail!("A looping mission needs at least one step that takes time");
+        }
+        Ok(())
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.steps.iter().map(Step::duration).sum()
+    }
+}
+
+/// Plays a mission as a command source.
+pub struct MissionPlayer {
+    mission: Mission,
+    bus: EventBus,
+    events: Receiver<Event>,
+    paused: bool,
+    step: usize,
+    step_elapsed: f64,
+    started: bool,
+    finished: bool,
+    last_update: Option<Instant>,
+    output: ControllerOutput,
+}
+
+impl MissionPlayer {
+    /// `paused` is the duck's pause state now; later changes arrive on `bus`.
+    pub fn new(mission: Mission, bus: &EventBus, paused: bool) -> Self {
+        tracing::info!(
+            "Mission '{}': {} steps, {:.1}s{}",
+            mission.name,
+            mission.steps.len(),
+            mission.duration(),
+            if mission.repeat { ", looping" } else { "" }
+        );
+        Self {
+            mission,
+            bus: bus.clone(),
+            events: bus.subscribe(),
+            paused,
+            step: 0,
+            step_elapsed: 0.0,
+            started: false,
+            finished: false,
+            last_update: None,
+            output: ControllerOutput::default(),
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Advance the mission clock by `dt` seconds and return the commands.
+    fn advance(&mut self, dt: f64) -> [f64; 7] {
+        for event in self.events.try_iter() {
+            match event {
+                Event::PausedToggled { paused } => self.paused = paused,
+                Event::FallDetected { .. } | Event::ServoFault { .. } if !self.finished => {
+                    tracing::warn!("Mission '{}' aborted: {:?}", self.mission.name, event);
+                    self.finished = true;
+                }
+                _ => {}
+            }
+        }
+        if self.finished || self.paused {
+            return [0.0; 7];
+        }
+
+        self.step_elapsed += dt;
+        loop {
+            let step = &self.mission.steps[self.step];
+            if !self.started {
+                tracing::info!("Mission step {}: {:?}", self.step + 1, step);
+                if let Some(event) = step.event() {
+                    self.bus.publish(event);
+                }
+                self.started = true;
+            }
+            let duration = step.duration();
+            if self.step_elapsed < duration {
+                return step.commands(self.step_elapsed);
+            }
+
+            self.step_elapsed -= duration;
+            self.step += 1;
+            self.started = false;
+            if self.step == self.mission.steps.len() {
+                if !self.mission.repeat {
+                    tracing::info!("Mission '{}' complete", self.mission.name);
+                    self.finished = true;
+                    return [0.0; 7];
+                }
+                self.step = 0;
+            }
+        }
+    }
+}
+
+impl CommandSource for MissionPlayer {
+    fn get_last_command(&mut self) -> &ControllerOutput {
+        let now = Instant::now();
+        let dt = self
+            .last_update
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f64());
+        self.last_update = Some(now);
+        self.output.commands = self.advance(dt);
+        &self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mission(json: &str) -> Mission {
+        let mission: Mission = serde_json::from_str(json).unwrap();
+        mission.validate().unwrap();
+        mission
+    }
+
+    #[test]
+    fn test_steps_play_in_order() {
+        let bus = EventBus::new();
+        let events = bus.subscribe();
+        let mut player = MissionPlayer::new(
+            mission(
+                r#"{"steps": [
+                    {"action": "walk", "vx": 0.1, "duration": 1.0},
+                    {"action": "sound", "name": "quack.wav"},
+                    {"action": "turn", "degrees": -90, "rate": 0.5}
+                ]}"#,
+            ),
+            &bus,
+            false,
+        );
+
+        assert_eq!(player.advance(0.0)[0], 0.1);
+        assert_eq!(player.advance(0.9)[0], 0.1);
+
+        // Crossing into the turn fires the sound on the way
+        let commands = player.advance(0.2);
+        assert_eq!(commands[0], 0.0);
+        assert_eq!(commands[2], -0.5);
+        assert_eq!(
+            events.try_recv(),
+            Ok(Event::SoundRequested {
+                name: Some("quack.wav".into())
+            })
+        );
+
+        // 90° at 0.5 rad/s takes ~3.14 s
+        assert_eq!(player.advance(3.0)[2], -0.5);
+        assert_eq!(player.advance(0.1), [0.0; 7]);
+        assert!(player.finished());
+    }
+
+    #[test]
+    fn test_pause_freezes_and_fall_aborts() {
+        let bus = EventBus::new();
+        let mut player = MissionPlayer::new(
+            mission(r#"{"loop": true, "steps": [{"action": "walk", "vx": 0.1, "duration": 1.0}]}"#),
+            &bus,
+            true,
+        );
+        assert_eq!(player.advance(5.0), [0.0; 7]);
+
+        bus.publish(Event::PausedToggled { paused: false });
+        assert_eq!(player.advance(2.5)[0], 0.1);
+        assert!(!player.finished());
+
+        bus.publish(Event::FallDetected { tilt: 1.2 });
+        assert_eq!(player.advance(0.1), [0.0; 7]);
+        assert!(player.finished());
+    }
+
+    #[test]
+    fn test_rejects_endless_instant_loop() {
+        let mission: Mission =
+            serde_json::from_str(r#"{"loop": true, "steps": [{"action": "blink"}]}"#).unwrap();
+        assert!(mission.validate().is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Moment clips: the seconds around "it just did something weird", saved
+//! on request.
+//!
+//! The control loop's telemetry snapshots (the ones streamed to browsers)
+//! are kept in a rolling buffer of the last `before_secs`. Pressing LB + RB
+//! on the gamepad, or sending `save_moment` from a remote client, starts a clip
+//! holding that buffer, which keeps collecting snapshots for another
+//! `after_secs` and is then written to `<dir>/moment-<unix ms>-<name>.jsonl`
+//! on a background thread. Only the newest `keep` clips are kept. A clip
+//! still collecting at shutdown is written as it is, marked incomplete.
+//!
+//! ```text
+//! {"type":"meta","name":"moment-1","captured_at":1700000000.5,"trigger_time":42.1,...}
+//! {"type":"frame","time":32.1,"paused":false,"joint_positions":[...],...}
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::remote::Telemetry;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClipRecord<'a> {
+    Meta {
+        name: &'a str,
+        /// Unix time the clip was asked for.
+        captured_at: f64,
+        /// Control loop time (s) of the request; frames run from
+        /// `before` seconds earlier to `after` seconds later.
+        trigger_time: f64,
+        before: f64,
+        after: f64,
+        joint_names: &'a [String],
+        /// False if the runtime stopped before `after` seconds had passed.
+        complete: bool,
+    },
+    Frame(&'a Telemetry),
+}
+
+#[derive(Debug, Clone)]
+struct Clip {
+    name: String,
+    captured_at: f64,
+    trigger_time: f64,
+    frames: Vec<Telemetry>,
+}
+
+/// Rolling telemetry buffer and the clips being collected from it.
+pub struct MomentRecorder {
+    before: f64,
+    after: f64,
+    joint_names: Vec<String>,
+    dir: PathBuf,
+    keep: usize,
+    recent: VecDeque<Telemetry>,
+    pending: Vec<Clip>,
+    /// Clips asked for this session, for default names.
+    captured: usize,
+}
+
+impl MomentRecorder {
+    pub fn new(
+        before: f64,
+        after: f64,
+        joint_names: Vec<String>,
+        dir: PathBuf,
+        keep: usize,
+    ) -> Self {
+        Self {
+            before: before.max(0.0),
+            after: after.max(0.0),
+            joint_names,
+            dir,
+            keep: keep.max(1),
+            recent: VecDeque::new(),
+            pending: Vec::new(),
+            captured: 0,
+        }
+    }
+
+    /// Start a clip around now, named `name` or `moment-<n>`. Returns the
+    /// name.
+    pub fn capture(&mut self, name: Option<&str>, captured_at: f64) -> String {
+        self.captured += 1;
+        let name = match name.map(str::trim).filter(|n| !n.is_empty()) {
+            Some(name) => name.to_string(),
+            None => format!("moment-{}", self.captured),
+        };
+        self.pending.push(Clip {
+            name: name.clone(),
+            captured_at,
+            trigger_time: self.recent.back().map_or(0.0, |f| f.time),
+            frames: self.recent.iter().cloned().collect(),
+        });
+        name
+    }
+
+    /// Add this tick's snapshot, writing out clips that are now complete.
+    pub fn record(&mut self, frame: &Telemetry) {
+        for clip in self.finished(frame) {
+            let dir = self.dir.clone();
+            let (keep, before, after) = (self.keep, self.before, self.after);
+            let joint_names = self.joint_names.clone();
+            std::thread::spawn(move || {
+                match write_clip(&clip, &joint_names, before, after, true, &dir, keep) {
+                    Ok(path) => tracing::info!("Saved moment {} to {}", clip.name, path.display()),
+                    Err(e) => tracing::error!("Failed to save moment {}: {:#}", clip.name, e),
+                }
+            });
+        }
+    }
+
+    /// Buffer `frame` and take out the clips it completes.
+    fn finished(&mut self, frame: &Telemetry) -> Vec<Clip> {
+        while self.recent.front().is_some_and(|f| f.time < frame.time - self.before) {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(frame.clone());
+
+        for clip in &mut self.pending {
+            clip.frames.push(frame.clone());
+        }
+        let after = self.after;
+        let (done, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|clip| frame.time >= clip.trigger_time + after);
+        self.pending = pending;
+        done
+    }
+}
+
+impl Drop for MomentRecorder {
+    fn drop(&mut self) {
+        for clip in std::mem::take(&mut self.pending) {
+            let (before, after) = (self.before, self.after);
+            match write_clip(&clip, &self.joint_names, before, after, false, &self.dir, self.keep) {
+                Ok(path) => tracing::info!(
+                    "Saved incomplete moment {} to {}",
+                    clip.name,
+                    path.display()
+                ),
+                Err(e) => tracing::error!("Failed to save moment {}: {:#}", clip.name, e),
+            }
+        }
+    }
+}
+
+fn write_clip(
+    clip: &Clip,
+    joint_names: &[String],
+    before: f64,
+    after: f64,
+    complete: bool,
+    dir: &Path,
+    keep: usize,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    // Names sort by time; the clip name is kept file-name safe
+    let slug: String = clip
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let path = dir.join(format!("moment-{:013.0}-{}.jsonl", clip.captured_at * 1000.0, slug));
+    let file = std::fs::File::create(&path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+
+    let mut out = BufWriter::new(file);
+    let meta = ClipRecord::Meta {
+        name: &clip.name,
+        captured_at: clip.captured_at,
+        trigger_time: clip.trigger_time,
+        before,
+        after,
+        joint_names,
+        complete,
+    };
+    for record in std::iter::once(meta).chain(clip.frames.iter().map(ClipRecord::Frame)) {
+        serde_json::to_writer(&mut out, &record)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let mut clips: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            name.starts_with("moment-") && name.ends_with(".jsonl")
+        })
+        .collect();
+    clips.sort();
+    for old in clips.iter().take(clips.len().saturating_sub(keep)) {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(time: f64) -> Telemetry {
+        Telemetry {
+            time,
+            ..Telemetry::default()
+        }
+    }
+
+    #[test]
+    fn test_clip_spans_before_and_after() {
+        let dir = std::env::temp_dir().join(format!("odr-moments-{}", std::process::id()));
+        let mut moments = MomentRecorder::new(0.1, 0.1, vec!["neck_pitch".into()], dir.clone(), 1);
+        for tick in 0..10 {
+            assert!(moments.finished(&frame(tick as f64 * 0.02)).is_empty());
+        }
+
+        assert_eq!(moments.capture(None, 1_700_000_000.0), "moment-1");
+        assert_eq!(moments.capture(Some("fell over "), 1_700_000_001.0), "fell over");
+        for tick in 10..14 {
+            assert!(moments.finished(&frame(tick as f64 * 0.02)).is_empty());
+        }
+        let done = moments.finished(&frame(0.28));
+        assert_eq!(done.len(), 2);
+        assert!(moments.pending.is_empty());
+
+        // 0.08..0.18 s was buffered, then 0.2..0.28 s followed
+        let times: Vec<f64> = done[0].frames.iter().map(|f| (f.time * 100.0).round()).collect();
+        assert_eq!(times, [8.0, 10.0, 12.0, 14.0, 16.0, 18.0, 20.0, 22.0, 24.0, 26.0, 28.0]);
+        assert_eq!(done[0].trigger_time, 0.18);
+
+        let path = write_clip(&done[1], &moments.joint_names, 0.1, 0.1, true, &dir, 1).unwrap();
+        assert!(path.ends_with("moment-1700000001000-fell_over.jsonl"));
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let meta: serde_json::Value =
+            serde_json::from_str(contents.lines().next().unwrap()).unwrap();
+        assert_eq!(meta["type"], "meta");
+        assert_eq!(meta["name"], "fell over");
+        assert_eq!(contents.lines().count(), 12);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,457 @@
+//! Motor I/O on its own thread, overlapping serial transfers with inference.
+//!
+//! With `serial.async_io` the control loop no longer waits on the servo bus.
+//! `AsyncMotors` moves the motor backend to a worker thread and implements
+//! `MotorInterface` on top of it:
+//!
+//! - goal positions are queued and return at once; the worker writes them,
+//!   then immediately reads the joint state back,
+//! - reads return the newest of those snapshots, so tick N observes the
+//!   state read while the loop was busy with tick N-1's inference,
+//! - bus statistics, bus faults and servo error bytes come with the
+//!   snapshots; faults and errors are carried over until they are taken,
+//! - servo health and loads are asked of the worker without waiting, read
+//!   with its next snapshot and handed over with it: each call returns the
+//!   result of the one before,
+//! - everything else (gains, torque, offsets) runs on the worker between
+//!   transfers and blocks until it is done; those calls are rare and never
+//!   made every tick.
+//!
+//! Queued targets and snapshots go through single-slot channels that keep
+//! only the newest item. The worker also reads once per period when no
+//! targets arrive, and a snapshot older than a few periods counts as a
+//! failed read.
+
+use anyhow::{anyhow, Result};
+use crossbeam_channel::{bounded, select, Receiver, Sender, TrySendError};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::audit;
+use crate::bus_errors::BusStats;
+use crate::joint_limits::JointLimits;
+use crate::motors::{MotorInterface, PresentState, ServoHealth, TorqueKillSwitch};
+use crate::servo_errors::ServoErrorReport;
+
+/// Snapshots older than this many periods are not used.
+const MAX_SNAPSHOT_AGE: u32 = 4;
+
+/// Blocking calls that can be queued for the worker.
+const CALL_QUEUE: usize = 16;
+
+type Call = Box<dyn FnOnce(&mut dyn MotorInterface) + Send>;
+
+/// Goal positions waiting for the worker.
+enum Targets {
+    Array(Vec<f64>),
+    Named(HashMap<String, f64>),
+}
+
+/// Slower reads asked of the worker, made with its next snapshot.
+#[derive(Default)]
+struct PollRequests {
+    health: AtomicBool,
+    loads: AtomicBool,
+}
+
+/// One read of the bus, taken right after a write.
+#[derive(Clone)]
+struct Snapshot {
+    state: Option<PresentState>,
+    limits: Option<JointLimits>,
+    /// Why the write before this read failed, if it did.
+    write_error: Option<String>,
+    bus_stats: Option<BusStats>,
+    /// Bus fault and servo errors not taken by the control loop yet.
+    bus_fault: Option<String>,
+    servo_errors: Vec<ServoErrorReport>,
+    /// Results of the last polls.
+    health: Option<Vec<ServoHealth>>,
+    loads: Option<Vec<f64>>,
+    read_at: Instant,
+}
+
+impl Snapshot {
+    /// Keep what `older` had not handed over yet, as it is replaced.
+    fn carry_over(&mut self, older: Snapshot) {
+        if self.write_error.is_none() {
+            self.write_error = older.write_error;
+        }
+        if self.bus_fault.is_none() {
+            self.bus_fault = older.bus_fault;
+        }
+        if !older.servo_errors.is_empty() {
+            let newer = std::mem::replace(&mut self.servo_errors, older.servo_errors);
+            self.servo_errors.extend(newer);
+        }
+    }
+}
+
+/// A motor backend driven from a worker thread.
+pub struct AsyncMotors {
+    joint_names: Vec<String>,
+    init_positions: Vec<f64>,
+    targets_tx: Sender<Targets>,
+    targets_drain: Receiver<Targets>,
+    calls_tx: Sender<Call>,
+    polls: Arc<PollRequests>,
+    snapshots: Receiver<Snapshot>,
+    latest: Snapshot,
+    max_age: Duration,
+    stop_tx: Sender<()>,
+}
+
+impl AsyncMotors {
+    /// Move `motors` to a worker thread cycling every `period`.
+    pub fn start(mut motors: Box<dyn MotorInterface>, period: Duration) -> Self {
+        let joint_names = motors.joint_names().to_vec();
+        let init_positions = motors.init_positions_array();
+        let latest = read_snapshot(motors.as_mut(), None, &Polled::default());
+
+        let (targets_tx, targets_rx) = bounded::<Targets>(1);
+        let (calls_tx, calls_rx) = bounded::<Call>(CALL_QUEUE);
+        let polls = Arc::new(PollRequests::default());
+        let (snapshot_tx, snapshot_rx) = bounded::<Snapshot>(1);
+        let (stop_tx, stop_rx) = bounded::<()>(1);
+        let drain_rx = snapshot_rx.clone();
+        let targets_drain = targets_rx.clone();
+        let channels = WorkerChannels {
+            targets_rx,
+            calls_rx,
+            polls: Arc::clone(&polls),
+            snapshot_tx,
+            drain_rx,
+            stop_rx,
+        };
+        thread::spawn(move || motor_worker(motors, channels, period));
+
+        tracing::info!("Motor I/O running on its own thread");
+        Self {
+            joint_names,
+            init_positions,
+            targets_tx,
+            targets_drain,
+            calls_tx,
+            polls,
+            snapshots: snapshot_rx,
+            latest,
+            max_age: period * MAX_SNAPSHOT_AGE,
+            stop_tx,
+        }
+    }
+
+    /// Run `f` on the worker and wait for its result.
+    fn call<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut dyn MotorInterface) -> T + Send + 'static,
+    ) -> Result<T> {
+        let _span = audit::span("channel_call");
+        let (reply_tx, reply_rx) = bounded(1);
+        self.calls_tx
+            .send(Box::new(move |motors: &mut dyn MotorInterface| {
+                let _ = reply_tx.send(f(motors));
+            }))
+            .map_err(|_| anyhow!("Motor I/O thread stopped"))?;
+        reply_rx.recv().map_err(|_| anyhow!("Motor I/O thread stopped"))
+    }
+
+    /// Queue targets, replacing any the worker has not picked up yet.
+    fn queue(&mut self, targets: Targets) -> Result<()> {
+        let _span = audit::span("channel_send");
+        match self.targets_tx.try_send(targets) {
+            Ok(()) => {}
+            Err(TrySendError::Full(targets)) => {
+                let _ = self.targets_drain.try_recv();
+                let _ = self.targets_tx.try_send(targets);
+            }
+            Err(TrySendError::Disconnected(_)) => return Err(anyhow!("Motor I/O thread stopped")),
+        }
+        // A failed write is reported on the next one
+        match self.latest.write_error.take() {
+            Some(e) => Err(anyhow!(e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Take in the newest snapshot, if there is one.
+    fn refresh(&mut self) {
+        let _span = audit::span("channel_recv");
+        if let Ok(snapshot) = self.snapshots.try_recv() {
+            let older = std::mem::replace(&mut self.latest, snapshot);
+            self.latest.carry_over(older);
+        }
+    }
+
+    /// The newest snapshot, unless it is too old to use.
+    fn snapshot(&mut self) -> Option<&Snapshot> {
+        self.refresh();
+        (self.latest.read_at.elapsed() <= self.max_age).then_some(&self.latest)
+    }
+}
+
+impl Drop for AsyncMotors {
+    fn drop(&mut self) {
+        let _ = self.stop_tx.try_send(());
+    }
+}
+
+impl MotorInterface for AsyncMotors {
+    fn joint_names(&self) -> &[String] {
+        &self.joint_names
+    }
+
+    fn init_positions_array(&self) -> Vec<f64> {
+        self.init_positions.clone()
+    }
+
+    fn set_kps(&mut self, kps: &[f64]) -> Result<()> {
+        let kps = kps.to_vec();
+        self.call(move |m| m.set_kps(&kps))?
+    }
+
+    fn set_kds(&mut self, kds: &[f64]) -> Result<()> {
+        let kds = kds.to_vec();
+        self.call(move |m| m.set_kds(&kds))?
+    }
+
+    fn set_kis(&mut self, kis: &[f64]) -> Result<()> {
+        let kis = kis.to_vec();
+        self.call(move |m| m.set_kis(&kis))?
+    }
+
+    fn turn_on(&mut self) -> Result<()> {
+        self.call(|m| m.turn_on())?
+    }
+
+    fn turn_off(&mut self) -> Result<()> {
+        self.call(|m| m.turn_off())?
+    }
+
+    fn disable_torque_now(&mut self) -> Result<()> {
+        self.call(|m| m.disable_torque_now())?
+    }
+
+    fn torque_enabled(&mut self) -> Result<bool> {
+        self.call(|m| m.torque_enabled())?
+    }
+
+    fn set_position_all(&mut self, positions: &HashMap<String, f64>) -> Result<()> {
+        self.queue(Targets::Named(positions.clone()))
+    }
+
+    fn set_position_all_array(&mut self, positions: &[f64]) -> Result<()> {
+        self.queue(Targets::Array(positions.to_vec()))
+    }
+
+    fn get_present_positions(&mut self) -> Option<Vec<f64>> {
+        Some(self.snapshot()?.state.as_ref()?.positions.clone())
+    }
+
+    fn get_present_velocities(&mut self) -> Option<Vec<f64>> {
+        Some(self.snapshot()?.state.as_ref()?.velocities.clone())
+    }
+
+    fn get_present_state(&mut self) -> Option<PresentState> {
+        self.snapshot()?.state.clone()
+    }
+
+    /// Health from the previous call's poll.
+    fn get_servo_health(&mut self) -> Option<Vec<ServoHealth>> {
+        self.polls.health.store(true, Ordering::Relaxed);
+        self.snapshot()?.health.clone()
+    }
+
+    /// Loads from the previous call's poll.
+    fn get_present_loads(&mut self) -> Option<Vec<f64>> {
+        self.polls.loads.store(true, Ordering::Relaxed);
+        self.snapshot()?.loads.clone()
+    }
+
+    fn joint_limits(&self) -> Option<&JointLimits> {
+        self.latest.limits.as_ref()
+    }
+
+    fn torque_kill_switch(&self) -> Option<TorqueKillSwitch> {
+        self.call(|m| m.torque_kill_switch()).ok().flatten()
+    }
+
+    fn bus_stats(&self) -> Option<BusStats> {
+        self.latest.bus_stats
+    }
+
+    fn take_bus_fault(&mut self) -> Option<String> {
+        self.refresh();
+        self.latest.bus_fault.take()
+    }
+
+    fn take_servo_errors(&mut self) -> Vec<ServoErrorReport> {
+        self.refresh();
+        std::mem::take(&mut self.latest.servo_errors)
+    }
+
+    fn disable_joint_torque(&mut self, joint: usize) -> Result<()> {
+        self.call(move |m| m.disable_joint_torque(joint))?
+    }
+
+    fn set_joint_offsets(&mut self, offsets: &HashMap<String, f64>) -> Result<()> {
+        let offsets = offsets.clone();
+        self.call(move |m| m.set_joint_offsets(&offsets))?
+    }
+
+    fn get_temperatures(&mut self) -> Option<Vec<f64>> {
+        self.call(|m| m.get_temperatures()).ok().flatten()
+    }
+}
+
+/// The worker's latest poll results.
+#[derive(Default)]
+struct Polled {
+    health: Option<Vec<ServoHealth>>,
+    loads: Option<Vec<f64>>,
+}
+
+fn read_snapshot(
+    motors: &mut dyn MotorInterface,
+    write_error: Option<String>,
+    polled: &Polled,
+) -> Snapshot {
+    Snapshot {
+        state: motors.get_present_state(),
+        limits: motors.joint_limits().cloned(),
+        write_error,
+        bus_stats: motors.bus_stats(),
+        bus_fault: motors.take_bus_fault(),
+        servo_errors: motors.take_servo_errors(),
+        health: polled.health.clone(),
+        loads: polled.loads.clone(),
+        read_at: Instant::now(),
+    }
+}
+
+/// The worker's ends of the channels, and the poll requests.
+struct WorkerChannels {
+    targets_rx: Receiver<Targets>,
+    calls_rx: Receiver<Call>,
+    polls: Arc<PollRequests>,
+    snapshot_tx: Sender<Snapshot>,
+    drain_rx: Receiver<Snapshot>,
+    stop_rx: Receiver<()>,
+}
+
+/// Worker: write queued targets and read back, run calls in between.
+fn motor_worker(mut motors: Box<dyn MotorInterface>, channels: WorkerChannels, period: Duration) {
+    let WorkerChannels {
+        targets_rx,
+        calls_rx,
+        polls,
+        snapshot_tx,
+        drain_rx,
+        stop_rx,
+    } = channels;
+    let mut polled = Polled::default();
+    loop {
+        let write_error = select! {
+            recv(stop_rx) -> _ => break,
+            recv(calls_rx) -> call => {
+                match call {
+                    Ok(call) => call(motors.as_mut()),
+                    Err(_) => break,
+                }
+                continue;
+            }
+            recv(targets_rx) -> targets => {
+                let written = match targets {
+                    Ok(Targets::Array(targets)) => motors.set_position_all_array(&targets),
+                    Ok(Targets::Named(targets)) => motors.set_position_all(&targets),
+                    Err(_) => break,
+                };
+                written.err().map(|e| e.to_string())
+            }
+            // Nothing to write: keep the snapshots fresh anyway
+            default(period) => None,
+        };
+
+        if polls.health.swap(false, Ordering::Relaxed) {
+            polled.health = motors.get_servo_health();
+        }
+        if polls.loads.swap(false, Ordering::Relaxed) {
+            polled.loads = motors.get_present_loads();
+        }
+        let snapshot = read_snapshot(motors.as_mut(), write_error, &polled);
+        match snapshot_tx.try_send(snapshot) {
+            Ok(()) => {}
+            Err(TrySendError::Full(mut snapshot)) => {
+                // Faults and errors in the unread one must not be lost
+                if let Ok(older) = drain_rx.try_recv() {
+                    snapshot.carry_over(older);
+                }
+                let _ = snapshot_tx.try_send(snapshot);
+            }
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+
+    tracing::info!("Motor I/O thread exiting");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::motors::MockMotorController;
+
+    #[test]
+    fn test_targets_written_and_read_back() {
+        let period = Duration::from_millis(20);
+        let mut motors = AsyncMotors::start(Box::new(MockMotorController::new()), period);
+        let init = motors.init_positions_array();
+        assert_eq!(motors.get_present_positions(), Some(init.clone()));
+
+        let targets: Vec<f64> = init.iter().map(|p| p + 0.1).collect();
+        motors.set_position_all_array(&targets).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while motors.get_present_positions() != Some(targets.clone()) {
+            assert!(Instant::now() < deadline, "targets never read back");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // Blocking calls run on the worker
+        motors.set_kps(&vec![10.0; init.len()]).unwrap();
+        assert!(motors.torque_enabled().is_ok());
+
+        // Polls come back with a later snapshot
+        while motors.get_servo_health().map(|h| h.len()) != Some(init.len()) {
+            assert!(Instant::now() < deadline, "health never polled");
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(motors.bus_stats(), None);
+        assert_eq!(motors.take_bus_fault(), None);
+    }
+
+    #[test]
+    fn test_no_position_writes_after_estop() {
+        let period = Duration::from_millis(5);
+        let mut motors = AsyncMotors::start(Box::new(MockMotorController::new()), period);
+        motors.turn_on().unwrap();
+        let init = motors.init_positions_array();
+
+        motors.disable_torque_now().unwrap();
+        assert!(!motors.torque_enabled().unwrap());
+        let targets: Vec<f64> = init.iter().map(|p| p + 0.1).collect();
+        motors.set_position_all_array(&targets).unwrap();
+        // Several worker cycles, none of which may write the targets
+        thread::sleep(period * 10);
+        assert_eq!(motors.get_present_positions(), Some(init.clone()));
+
+        // Turning torque back on lifts the cut
+        motors.turn_on().unwrap();
+        motors.set_position_all_array(&targets).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while motors.get_present_positions() != Some(targets.clone()) {
+            assert!(Instant::now() < deadline, "targets never written after turn_on");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
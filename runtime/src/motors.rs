@@ -1,140 +1,388 @@
 //! Feetech STS3215 servo motor control over serial (USB).
 //!
 //! Replaces `rustypot_position_hwi.py`. Implements the Feetech serial protocol
-//! for reading positions/velocities and writing goal positions.
+//! for reading positions/velocities and writing goal positions. Dynamixel
+//! servos are driven by `dynamixel::DynamixelController` instead, selected
+//! with `servo_type` in the duck config.
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::collections::HashMap;
 use std::io::Cursor;
-use std::time::Duration;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
-use crate::config::DuckConfig;
+use crate::audit;
+use crate::bus_errors::{BusErrorTracker, BusStats, Escalation};
+use crate::config::{DuckConfig, Gains, ServoType, TorqueRampConfig};
+use crate::dynamixel::DynamixelController;
+use crate::joint_limits::{JointLimits, LimitCheck};
+use crate::robot_model::RobotModel;
+use crate::servo_errors::ServoErrorReport;
+use crate::servo_registers::{self, Access, RegisterDump, RegisterWrite, ServoRegisters};
 
 // Feetech protocol constants
-const HEADER: [u8; 2] = [0xFF, 0xFF];
+pub(crate) const HEADER: [u8; 2] = [0xFF, 0xFF];
 const INST_WRITE: u8 = 0x03;
 const INST_READ: u8 = 0x02;
 const INST_SYNC_WRITE: u8 = 0x83;
 const INST_SYNC_READ: u8 = 0x82;
+const BROADCAST_ID: u8 = 0xFE;
 
 // Register addresses for STS3215
 const ADDR_TORQUE_ENABLE: u8 = 40;
 const ADDR_GOAL_POSITION: u8 = 42;
 const ADDR_PRESENT_POSITION: u8 = 56;
 const ADDR_PRESENT_SPEED: u8 = 58;
+const ADDR_PRESENT_LOAD: u8 = 60; // load(2), voltage(1), temperature(1), async(1), status(1)
+/// Position(2), speed(2), load(2), voltage(1), temperature(1) from 56.
+const PRESENT_BLOCK_LEN: u8 = 8;
 const ADDR_P_GAIN: u8 = 21;
 const ADDR_D_GAIN: u8 = 22;
+const ADDR_I_GAIN: u8 = 23;
 
-/// Ordered joint definitions matching the Python runtime.
-pub const JOINT_NAMES: &[&str] = &[
-    "left_hip_yaw",
-    "left_hip_roll",
-    "left_hip_pitch",
-    "left_knee",
-    "left_ankle",
-    "neck_pitch",
-    "head_pitch",
-    "head_yaw",
-    "head_roll",
-    "right_hip_yaw",
-    "right_hip_roll",
-    "right_hip_pitch",
-    "right_knee",
-    "right_ankle",
-];
-
-/// Servo IDs corresponding to each joint.
-pub const JOINT_IDS: &[u8] = &[
-    20, 21, 22, 23, 24, // left leg
-    30, 31, 32, 33, // head
-    10, 11, 12, 13, 14, // right leg
-];
-
-pub const NUM_DOFS: usize = 14;
-
-/// Default initial standing pose (radians).
-pub fn default_init_positions() -> HashMap<String, f64> {
-    [
-        ("left_hip_yaw", 0.002),
-        ("left_hip_roll", 0.053),
-        ("left_hip_pitch", -0.63),
-        ("left_knee", 1.368),
-        ("left_ankle", -0.784),
-        ("neck_pitch", 0.0),
-        ("head_pitch", 0.0),
-        ("head_yaw", 0.0),
-        ("head_roll", 0.0),
-        ("right_hip_yaw", -0.003),
-        ("right_hip_roll", -0.065),
-        ("right_hip_pitch", 0.635),
-        ("right_knee", 1.379),
-        ("right_ankle", -0.796),
-    ]
-    .into_iter()
-    .map(|(k, v)| (k.to_string(), v))
-    .collect()
+/// Default serial timeout for blocking operations.
+pub(crate) const SERIAL_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Duration of the soft-start/soft-stop position trajectories.
+pub(crate) const RAMP_DURATION: Duration = Duration::from_millis(1500);
+
+/// Duration of the gain fade-out before torque is disabled.
+pub(crate) const GAIN_FADE_DURATION: Duration = Duration::from_millis(500);
+
+/// Interval between setpoints while ramping.
+pub(crate) const RAMP_PERIOD: Duration = Duration::from_millis(20);
+
+/// Servo bus baud rate.
+pub(crate) const BAUD_RATE: u32 = 1_000_000;
+
+/// Which motor backend drives the joints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MotorBackend {
+    /// Real servos on the serial bus: Feetech STS3215, or Dynamixel with
+    /// `"servo_type": "dynamixel"` in the duck config.
+    Feetech,
+    /// Ideal servos that reach every target instantly (no hardware).
+    Mock,
+    /// Kinematic servo model with first-order tracking and speed limits.
+    Sim,
+}
+
+/// Hardware abstraction over the servo bus.
+///
+/// The control loop only talks to this trait, so the same loop can drive real
+/// servos, a mock for tests, or the kinematic simulator.
+pub trait MotorInterface: Send {
+    /// Get joint names in order.
+    fn joint_names(&self) -> &[String];
+
+    /// Get the initial standing positions as an ordered array.
+    fn init_positions_array(&self) -> Vec<f64>;
+
+    /// Set PID proportional gains for all joints.
+    fn set_kps(&mut self, kps: &[f64]) -> Result<()>;
+
+    /// Set PID derivative gains for all joints.
+    fn set_kds(&mut self, kds: &[f64]) -> Result<()>;
+
+    /// Set PID integral gains for all joints. Backends without an integral
+    /// term ignore them.
+    fn set_kis(&mut self, _kis: &[f64]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Set all three PID gains for all joints.
+    fn set_gains(&mut self, gains: &Gains) -> Result<()> {
+        self.set_kps(&gains.kp)?;
+        self.set_kds(&gains.kd)?;
+        self.set_kis(&gains.ki)
+    }
+
+    /// Enable torque and move gently to the init pose.
+    fn turn_on(&mut self) -> Result<()>;
+
+    /// Ease the joints to rest and disable torque on all joints. For a
+    /// normal shutdown.
+    fn turn_off(&mut self) -> Result<()>;
+
+    /// Disable torque on all joints at once, wherever they are. For faults
+    /// and e-stops, where driving the joints anywhere could do harm. Goal
+    /// position writes are dropped from then on, until the next `turn_on`.
+    fn disable_torque_now(&mut self) -> Result<()>;
+
+    /// Whether torque is currently enabled on every joint.
+    fn torque_enabled(&mut self) -> Result<bool>;
+
+    /// Write goal positions by joint name (radians).
+    fn set_position_all(&mut self, positions: &HashMap<String, f64>) -> Result<()>;
+
+    /// Write goal positions from an ordered array (radians).
+    fn set_position_all_array(&mut self, positions: &[f64]) -> Result<()>;
+
+    /// Read present positions of all joints (radians).
+    /// Returns None if communication fails.
+    fn get_present_positions(&mut self) -> Option<Vec<f64>>;
+
+    /// Read present velocities of all joints (rad/s).
+    /// Returns None if communication fails.
+    fn get_present_velocities(&mut self) -> Option<Vec<f64>>;
+
+    /// Read positions and velocities, plus load, voltage and temperature
+    /// where the bus can return them in the same transaction.
+    /// Returns None if communication fails.
+    fn get_present_state(&mut self) -> Option<PresentState> {
+        let positions = self.get_present_positions()?;
+        let velocities = self.get_present_velocities()?;
+        Some(PresentState {
+            positions,
+            velocities,
+            ..PresentState::default()
+        })
+    }
+
+    /// Read temperature, voltage, load, and error status of all joints.
+    /// Returns None if communication fails.
+    fn get_servo_health(&mut self) -> Option<Vec<ServoHealth>>;
+
+    /// Read the present load of all joints (signed fraction of max torque).
+    /// None if the servos cannot report it.
+    fn get_present_loads(&mut self) -> Option<Vec<f64>> {
+        None
+    }
+
+    /// Joint limits and their clamp counters, if this backend enforces them.
+    fn joint_limits(&self) -> Option<&JointLimits> {
+        None
+    }
+
+    /// Independent handle that can disable torque on the whole bus from
+    /// another thread, for backends driving real servos.
+    fn torque_kill_switch(&self) -> Option<TorqueKillSwitch> {
+        None
+    }
+
+    /// Serial error counters, for backends with a real bus.
+    fn bus_stats(&self) -> Option<BusStats> {
+        None
+    }
+
+    /// Reason to soft-stop, once, when the bus error rate stays too high.
+    fn take_bus_fault(&mut self) -> Option<String> {
+        None
+    }
+
+    /// Non-zero status bytes received since the last call.
+    fn take_servo_errors(&mut self) -> Vec<ServoErrorReport> {
+        Vec::new()
+    }
+
+    /// Disable torque on one joint, leaving the others driven.
+    fn disable_joint_torque(&mut self, _joint: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Replace the per-joint offsets (radians) applied to goals and
+    /// readings. Backends without offsets ignore them.
+    fn set_joint_offsets(&mut self, _offsets: &HashMap<String, f64>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Read servo temperatures of all joints (°C).
+    /// Returns None if communication fails.
+    fn get_temperatures(&mut self) -> Option<Vec<f64>> {
+        self.get_servo_health()
+            .map(|health| health.iter().map(|h| h.temperature).collect())
+    }
+}
+
+/// Per-tick joint feedback, in joint order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PresentState {
+    /// Radians, minus offsets.
+    pub positions: Vec<f64>,
+    /// rad/s.
+    pub velocities: Vec<f64>,
+    /// Signed fraction of max torque, when read along.
+    pub loads: Option<Vec<f64>>,
+    /// Input voltage (V), when read along.
+    pub voltages: Option<Vec<f64>>,
+    /// °C, when read along.
+    pub temperatures: Option<Vec<f64>>,
+}
+
+/// Slow-changing servo state, polled at a low rate for health monitoring.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServoHealth {
+    pub id: u8,
+    /// Internal temperature (°C).
+    pub temperature: f64,
+    /// Input voltage (V).
+    pub voltage: f64,
+    /// Load as a signed fraction of maximum torque (-1.0..1.0).
+    pub load: f64,
+    /// Hardware error status bits (0 = healthy).
+    pub error_flags: u8,
+}
+
+/// Open the motor backend selected on the command line.
+pub fn open_backend(
+    backend: MotorBackend,
+    config: &DuckConfig,
+    serial_port: &str,
+) -> Result<Box<dyn MotorInterface>> {
+    Ok(match backend {
+        MotorBackend::Feetech => {
+            let names: Vec<String> = if config.rig_joints.is_empty() {
+                config.robot_model.joint_names()
+            } else {
+                config.rig_joints.clone()
+            };
+            let hardware: Box<dyn MotorInterface> = match config.servo_type {
+                ServoType::Feetech => Box::new(MotorController::with_joints(config, serial_port, &names)?),
+                ServoType::Dynamixel => {
+                    Box::new(DynamixelController::with_joints(config, serial_port, &names)?)
+                }
+            };
+            if config.rig_joints.is_empty() {
+                hardware
+            } else {
+                Box::new(PartialRig::new(hardware, config))
+            }
+        }
+        MotorBackend::Mock => Box::new(MockMotorController::for_model(&config.robot_model)),
+        MotorBackend::Sim => Box::new(SimMotorController::for_model(&config.robot_model)),
+    })
 }
 
 /// Hardware interface for the Feetech STS3215 bus servos.
 pub struct MotorController {
     port: Box<dyn serialport::SerialPort>,
+    port_path: String,
+    model: RobotModel,
     joint_ids: Vec<u8>,
     joint_names: Vec<String>,
     offsets: HashMap<String, f64>,
+    limits: JointLimits,
     init_pos: HashMap<String, f64>,
     kps: Vec<f64>,
     kds: Vec<f64>,
+    kis: Vec<f64>,
+    torque_ramp: TorqueRampConfig,
+    retries: u32,
+    /// How long one read waits for all status packets.
+    status_timeout: Duration,
+    errors: BusErrorTracker,
+    bus_fault: Option<String>,
+    /// Port handed to the torque kill switch, refreshed on reopen.
+    kill_port: KillPort,
+    /// Error bytes seen since the last `take_servo_errors`.
+    servo_errors: Vec<ServoErrorReport>,
+    /// Torque was cut: goal writes are dropped until `turn_on`.
+    torque_cut: bool,
 }
 
 impl MotorController {
     /// Open the serial port and initialize the motor controller.
     pub fn new(config: &DuckConfig, serial_port: &str) -> Result<Self> {
-        let port = serialport::new(serial_port, 1_000_000)
-            .timeout(Duration::from_millis(10))
-            .open()
-            .with_context(|| format!("Failed to open serial port {}", serial_port))?;
+        Self::with_joints(config, serial_port, &config.robot_model.joint_names())
+    }
 
-        let joint_names: Vec<String> = JOINT_NAMES.iter().map(|s| s.to_string()).collect();
-        let joint_ids = JOINT_IDS.to_vec();
+    /// Drive only the named joints (in robot joint order), e.g. on a test rig.
+    pub fn with_joints(config: &DuckConfig, serial_port: &str, names: &[String]) -> Result<Self> {
+        let (joint_names, joint_ids) = config.robot_model.select(names)?;
+
+        let port = open_port(serial_port)?;
 
         Ok(Self {
             port,
+            port_path: serial_port.to_string(),
+            model: config.robot_model.clone(),
+            limits: JointLimits::new(&joint_names, &config.resolved_joint_limits()),
+            kps: vec![32.0; joint_ids.len()],
+            kds: vec![0.0; joint_ids.len()],
+            kis: vec![0.0; joint_ids.len()],
             joint_ids,
-            joint_names: joint_names.clone(),
+            joint_names,
             offsets: config.joints_offset.clone(),
-            init_pos: default_init_positions(),
-            kps: vec![32.0; NUM_DOFS],
-            kds: vec![0.0; NUM_DOFS],
+            init_pos: config.robot_model.init_positions(),
+            torque_ramp: config.torque_ramp.clone(),
+            retries: config.serial.retries,
+            status_timeout: Duration::from_secs_f64(config.serial.status_timeout_ms / 1000.0),
+            errors: BusErrorTracker::new(config.serial.clone()),
+            bus_fault: None,
+            kill_port: KillPort::default(),
+            servo_errors: Vec::new(),
+            torque_cut: false,
         })
     }
+}
 
-    /// Get the initial standing positions as an ordered array.
-    pub fn init_positions_array(&self) -> Vec<f64> {
+pub(crate) fn open_port(serial_port: &str) -> Result<Box<dyn serialport::SerialPort>> {
+    serialport::new(serial_port, BAUD_RATE)
+        .timeout(SERIAL_TIMEOUT)
+        .open()
+        .with_context(|| format!("Failed to open serial port {}", serial_port))
+}
+
+impl MotorController {
+    /// Write KP registers without changing the configured gains.
+    fn write_kps(&mut self, kps: &[f64]) -> Result<()> {
+        let ids = self.joint_ids.clone();
+        for (i, id) in ids.iter().enumerate() {
+            let kp_val = kps[i] as u8;
+            self.write_register(*id, ADDR_P_GAIN, &[kp_val])?;
+        }
+        Ok(())
+    }
+
+    /// Switch torque on at every joint.
+    fn write_torque_on(&mut self) -> Result<()> {
+        let ids = self.joint_ids.clone();
+        for &id in &ids {
+            self.write_register(id, ADDR_TORQUE_ENABLE, &[1])?;
+        }
+        Ok(())
+    }
+
+    /// Enable torque at a low KP and ramp KP up to the configured gains.
+    /// Every torque-off to torque-on transition goes through here, so the
+    /// servos never jump to their goal at full stiffness.
+    fn enable_torque(&mut self) -> Result<()> {
+        let (kps, ramp) = (self.kps.clone(), self.torque_ramp.clone());
+        ramp_torque_on(self, &kps, &ramp, Self::write_kps, Self::write_torque_on)?;
+        tracing::info!("Motors: torque enabled, KP ramped up");
+        Ok(())
+    }
+
+    /// Startup without position feedback: aim at the init pose, then let
+    /// the KP ramp pull the joints there gently.
+    fn turn_on_low_kp(&mut self) -> Result<()> {
+        self.set_position_all_array(&self.init_positions_array())?;
+        self.enable_torque()?;
+        tracing::info!("Motors: init position set");
+
+        Ok(())
+    }
+}
+
+impl MotorInterface for MotorController {
+    fn init_positions_array(&self) -> Vec<f64> {
         self.joint_names
             .iter()
             .map(|name| self.init_pos.get(name).copied().unwrap_or(0.0))
             .collect()
     }
 
-    /// Get joint names in order.
-    pub fn joint_names(&self) -> &[String] {
+    fn joint_names(&self) -> &[String] {
         &self.joint_names
     }
 
-    /// Set PID proportional gains for all joints.
-    pub fn set_kps(&mut self, kps: &[f64]) -> Result<()> {
+    fn set_kps(&mut self, kps: &[f64]) -> Result<()> {
         self.kps = kps.to_vec();
-        let ids = self.joint_ids.clone();
-        for (i, id) in ids.iter().enumerate() {
-            let kp_val = kps[i] as u8;
-            self.write_register(*id, ADDR_P_GAIN, &[kp_val])?;
-        }
-        Ok(())
+        self.write_kps(kps)
     }
 
-    /// Set PID derivative gains for all joints.
-    pub fn set_kds(&mut self, kds: &[f64]) -> Result<()> {
+    fn set_kds(&mut self, kds: &[f64]) -> Result<()> {
         self.kds = kds.to_vec();
         let ids = self.joint_ids.clone();
         for (i, id) in ids.iter().enumerate() {
@@ -144,37 +392,57 @@ impl MotorController {
         Ok(())
     }
 
-    /// Enable torque on all servos (with low KP first, then init position).
-    pub fn turn_on(&mut self) -> Result<()> {
-        // Enable torque
+    fn set_kis(&mut self, kis: &[f64]) -> Result<()> {
+        self.kis = kis.to_vec();
         let ids = self.joint_ids.clone();
-        for &id in &ids {
-            self.write_register(id, ADDR_TORQUE_ENABLE, &[1])?;
+        for (i, id) in ids.iter().enumerate() {
+            let ki_val = kis[i] as u8;
+            self.write_register(*id, ADDR_I_GAIN, &[ki_val])?;
         }
+        Ok(())
+    }
 
-        // Set low KP for gentle startup
-        let low_kps = vec![2.0; NUM_DOFS];
-        self.set_kps(&low_kps)?;
-        tracing::info!("Motors: low KPs set");
-
-        std::thread::sleep(Duration::from_secs(1));
-
-        // Move to init position
-        self.set_position_all_array(&self.init_positions_array())?;
-        tracing::info!("Motors: init position set");
+    /// Enable torque and follow a smooth trajectory from wherever the joints
+    /// are to the init pose, so a robot left crouched does not snap upright.
+    fn turn_on(&mut self) -> Result<()> {
+        self.torque_cut = false;
+        let Some(present) = self.get_present_positions() else {
+            tracing::warn!("Motors: could not read positions, falling back to low-KP startup");
+            return self.turn_on_low_kp();
+        };
 
-        std::thread::sleep(Duration::from_secs(1));
+        // Hold the present pose first: enabling torque moves to the stale goal register
+        self.set_position_all_array(&present)?;
+        self.enable_torque()?;
+        tracing::info!("Motors: ramping to init position");
 
-        // Restore full KPs
-        let full_kps = self.kps.clone();
-        self.set_kps(&full_kps)?;
-        tracing::info!("Motors: full KPs set");
+        let init = self.init_positions_array();
+        ramp_positions(&present, &init, RAMP_DURATION, |t| self.set_position_all_array(t))?;
+        tracing::info!("Motors: init position reached");
 
         Ok(())
     }
 
-    /// Disable torque on all servos.
-    pub fn turn_off(&mut self) -> Result<()> {
+    /// Ramp to the init pose, fade the gains out, then disable torque, so the
+    /// robot settles instead of collapsing from wherever it was.
+    fn turn_off(&mut self) -> Result<()> {
+        if let Some(present) = self.get_present_positions() {
+            let init = self.init_positions_array();
+            ramp_positions(&present, &init, RAMP_DURATION, |t| self.set_position_all_array(t))?;
+
+            // The configured gains are kept for the next turn_on
+            let full_kps = self.kps.clone();
+            let steps = ramp_steps(GAIN_FADE_DURATION);
+            for k in 1..=steps {
+                let scale = 1.0 - cubic_ease(k as f64 / steps as f64);
+                let kps: Vec<f64> = full_kps.iter().map(|kp| kp * scale).collect();
+                self.write_kps(&kps)?;
+                std::thread::sleep(RAMP_PERIOD);
+            }
+        } else {
+            tracing::warn!("Motors: could not read positions, disabling torque without ramp");
+        }
+
         let ids = self.joint_ids.clone();
         for &id in &ids {
             self.write_register(id, ADDR_TORQUE_ENABLE, &[0])?;
@@ -183,41 +451,72 @@ impl MotorController {
         Ok(())
     }
 
-    /// Write goal positions for all joints (radians). Applies per-joint offsets.
-    pub fn set_position_all(&mut self, positions: &HashMap<String, f64>) -> Result<()> {
-        let mut ids = Vec::with_capacity(NUM_DOFS);
-        let mut raw_positions = Vec::with_capacity(NUM_DOFS);
+    /// One broadcast write; servos do not reply to it.
+    fn disable_torque_now(&mut self) -> Result<()> {
+        let packet = write_packet(BROADCAST_ID, ADDR_TORQUE_ENABLE, &[0]);
+        let written = self
+            .port
+            .write_all(&packet)
+            .context("Serial write failed")
+            .and_then(|()| self.port.flush().context("Serial flush failed"));
+        self.record_transaction(written.is_ok());
+        written?;
+        self.torque_cut = true;
+        tracing::warn!("Motors: torque cut");
+        Ok(())
+    }
+
+    fn torque_enabled(&mut self) -> Result<bool> {
+        let ids = self.joint_ids.clone();
+        for &id in &ids {
+            let value = self.read_register(id, ADDR_TORQUE_ENABLE, 1)?;
+            if value[0] == 0 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Write goal positions for all joints (radians). Enforces joint limits,
+    /// then applies per-joint offsets.
+    fn set_position_all(&mut self, positions: &HashMap<String, f64>) -> Result<()> {
+        let mut ids = Vec::with_capacity(self.joint_ids.len());
+        let mut raw_positions = Vec::with_capacity(self.joint_ids.len());
 
         for (i, name) in self.joint_names.iter().enumerate() {
             if let Some(&pos) = positions.get(name) {
-                let offset = self.offsets.get(name).copied().unwrap_or(0.0);
-                ids.push(self.joint_ids[i]);
-                raw_positions.push(rad_to_raw(pos + offset));
+                if let LimitCheck::Write(pos) = self.limits.check(i, pos) {
+                    let offset = self.offsets.get(name).copied().unwrap_or(0.0);
+                    ids.push(self.joint_ids[i]);
+                    raw_positions.push(rad_to_raw(pos + offset));
+                }
             }
         }
 
         self.sync_write_positions(&ids, &raw_positions)
     }
 
-    /// Write goal positions from an ordered array (radians). Applies per-joint offsets.
-    pub fn set_position_all_array(&mut self, positions: &[f64]) -> Result<()> {
-        let mut ids = Vec::with_capacity(NUM_DOFS);
-        let mut raw_positions = Vec::with_capacity(NUM_DOFS);
+    /// Write goal positions from an ordered array (radians). Enforces joint
+    /// limits, then applies per-joint offsets.
+    fn set_position_all_array(&mut self, positions: &[f64]) -> Result<()> {
+        let mut ids = Vec::with_capacity(self.joint_ids.len());
+        let mut raw_positions = Vec::with_capacity(self.joint_ids.len());
 
         for (i, &pos) in positions.iter().enumerate() {
-            let name = &self.joint_names[i];
-            let offset = self.offsets.get(name).copied().unwrap_or(0.0);
-            ids.push(self.joint_ids[i]);
-            raw_positions.push(rad_to_raw(pos + offset));
+            if let LimitCheck::Write(pos) = self.limits.check(i, pos) {
+                let name = &self.joint_names[i];
+                let offset = self.offsets.get(name).copied().unwrap_or(0.0);
+                ids.push(self.joint_ids[i]);
+                raw_positions.push(rad_to_raw(pos + offset));
+            }
         }
 
         self.sync_write_positions(&ids, &raw_positions)
     }
 
     /// Read present positions of all joints (radians), minus offsets.
-    /// Returns None if communication fails.
-    pub fn get_present_positions(&mut self) -> Option<Vec<f64>> {
-        match self.sync_read(&self.joint_ids.clone(), ADDR_PRESENT_POSITION, 2) {
+    fn get_present_positions(&mut self) -> Option<Vec<f64>> {
+        match self.sync_read_i16(ADDR_PRESENT_POSITION) {
             Ok(raw_values) => {
                 let positions: Vec<f64> = raw_values
                     .iter()
@@ -237,10 +536,8 @@ impl MotorController {
         }
     }
 
-    /// Read present velocities of all joints (rad/s).
-    /// Returns None if communication fails.
-    pub fn get_present_velocities(&mut self) -> Option<Vec<f64>> {
-        match self.sync_read(&self.joint_ids.clone(), ADDR_PRESENT_SPEED, 2) {
+    fn get_present_velocities(&mut self) -> Option<Vec<f64>> {
+        match self.sync_read_i16(ADDR_PRESENT_SPEED) {
             Ok(raw_values) => {
                 let velocities: Vec<f64> =
                     raw_values.iter().map(|&raw| raw_to_rad_per_sec(raw)).collect();
@@ -253,20 +550,165 @@ impl MotorController {
         }
     }
 
+    /// One sync read of the contiguous position..temperature block instead
+    /// of one per register.
+    fn get_present_state(&mut self) -> Option<PresentState> {
+        let ids = self.joint_ids.clone();
+        let results = match self.sync_read(&ids, ADDR_PRESENT_POSITION, PRESENT_BLOCK_LEN) {
+            Ok(results) => results,
+            Err(e) => {
+                tracing::warn!("Failed to read joint state: {}", e);
+                return None;
+            }
+        };
+
+        let mut blocks = Vec::with_capacity(ids.len());
+        let mut failures = Vec::new();
+        for (&id, result) in ids.iter().zip(results) {
+            match result {
+                Ok(data) => blocks.push(data),
+                Err(e) => failures.push(format!("{}: {}", id, e)),
+            }
+        }
+        if !failures.is_empty() {
+            tracing::warn!("Failed to read joint state ({})", failures.join(", "));
+            return None;
+        }
+
+        let offsets: Vec<f64> = self
+            .joint_names
+            .iter()
+            .map(|name| self.offsets.get(name).copied().unwrap_or(0.0))
+            .collect();
+        Some(decode_present_state(&blocks, &offsets))
+    }
+
+    fn get_present_loads(&mut self) -> Option<Vec<f64>> {
+        match self.sync_read_i16(ADDR_PRESENT_LOAD) {
+            Ok(raw_values) => Some(raw_values.iter().map(|&raw| decode_load(raw as u16)).collect()),
+            Err(e) => {
+                tracing::warn!("Failed to read loads: {}", e);
+                None
+            }
+        }
+    }
+
+    fn get_servo_health(&mut self) -> Option<Vec<ServoHealth>> {
+        let ids = self.joint_ids.clone();
+        let results = match self.sync_read(&ids, ADDR_PRESENT_LOAD, 6) {
+            Ok(results) => results,
+            Err(e) => {
+                tracing::warn!("Failed to read servo health: {}", e);
+                return None;
+            }
+        };
+
+        results
+            .into_iter()
+            .zip(&ids)
+            .map(|(result, &id)| match result {
+                Ok(data) => Some(decode_servo_health(id, &data)),
+                Err(e) => {
+                    tracing::warn!("Failed to read health of servo {}: {}", id, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn joint_limits(&self) -> Option<&JointLimits> {
+        Some(&self.limits)
+    }
+
+    fn torque_kill_switch(&self) -> Option<TorqueKillSwitch> {
+        self.kill_port.refresh(&*self.port).then(|| {
+            TorqueKillSwitch::new(
+                self.kill_port.clone(),
+                write_packet(BROADCAST_ID, ADDR_TORQUE_ENABLE, &[0]),
+            )
+        })
+    }
+
+    fn bus_stats(&self) -> Option<BusStats> {
+        Some(self.errors.stats())
+    }
+
+    fn take_bus_fault(&mut self) -> Option<String> {
+        self.bus_fault.take()
+    }
+
+    fn take_servo_errors(&mut self) -> Vec<ServoErrorReport> {
+        std::mem::take(&mut self.servo_errors)
+    }
+
+    fn disable_joint_torque(&mut self, joint: usize) -> Result<()> {
+        let id = self.joint_ids[joint];
+        self.write_register(id, ADDR_TORQUE_ENABLE, &[0])
+    }
+
+    fn set_joint_offsets(&mut self, offsets: &HashMap<String, f64>) -> Result<()> {
+        self.offsets = offsets.clone();
+        Ok(())
+    }
+}
+
+/// Clone of the bus's serial port shared with the torque kill switch. The
+/// controller swaps in a clone of the new port whenever it reopens the bus,
+/// so a kill switch taken at startup still reaches the servos.
+#[derive(Clone, Default)]
+pub(crate) struct KillPort(Arc<Mutex<Option<Box<dyn serialport::SerialPort>>>>);
+
+impl KillPort {
+    /// Point the kill switch at a clone of `port`. Returns whether it has one.
+    pub(crate) fn refresh(&self, port: &dyn serialport::SerialPort) -> bool {
+        let clone = match port.try_clone() {
+            Ok(clone) => Some(clone),
+            Err(e) => {
+                tracing::warn!("Failed to clone serial port for the torque kill switch: {}", e);
+                None
+            }
+        };
+        let cloned = clone.is_some();
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = clone;
+        cloned
+    }
+}
+
+/// Second handle on the servo bus that disables torque on every servo with
+/// one broadcast write. Used by the watchdog when the control loop stalls.
+pub struct TorqueKillSwitch {
+    port: KillPort,
+    /// Broadcast torque-disable packet in the bus's protocol.
+    packet: Vec<u8>,
+}
+
+impl TorqueKillSwitch {
+    pub(crate) fn new(port: KillPort, packet: Vec<u8>) -> Self {
+        Self { port, packet }
+    }
+
+    /// Broadcast torque-disable. Servos do not reply to broadcasts, so the
+    /// packet is repeated in case it collides with a half-written packet from
+    /// the stalled thread.
+    pub fn disable_all(&mut self) -> Result<()> {
+        let mut port = self.port.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(port) = port.as_mut() else {
+            bail!("No serial port: reopening the bus failed to clone it");
+        };
+        for _ in 0..3 {
+            port.write_all(&self.packet).context("Serial write failed")?;
+            port.flush().context("Serial flush failed")?;
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        Ok(())
+    }
+}
+
+impl MotorController {
     // ── Low-level protocol ──
 
     fn write_register(&mut self, id: u8, addr: u8, data: &[u8]) -> Result<()> {
-        let length = (data.len() + 3) as u8;
-        let mut packet = Vec::with_capacity(6 + data.len());
-        packet.extend_from_slice(&HEADER);
-        packet.push(id);
-        packet.push(length);
-        packet.push(INST_WRITE);
-        packet.push(addr);
-        packet.extend_from_slice(data);
-
-        let checksum = compute_checksum(&packet[2..]);
-        packet.push(checksum);
+        let packet = write_packet(id, addr, data);
 
         self.port
             .write_all(&packet)
@@ -278,7 +720,27 @@ impl MotorController {
         Ok(())
     }
 
+    fn read_register(&mut self, id: u8, addr: u8, len: u8) -> Result<Vec<u8>> {
+        let packet = read_packet(id, addr, len);
+
+        self.port
+            .write_all(&packet)
+            .context("Serial write failed")?;
+        self.port.flush().context("Serial flush failed")?;
+
+        let result = self
+            .read_status(&[id], len)
+            .pop()
+            .expect("one result per requested id");
+        self.record_transaction(result.is_ok());
+        result.with_context(|| format!("Failed to read register {} of servo {}", addr, id))
+    }
+
     fn sync_write_positions(&mut self, ids: &[u8], values: &[i16]) -> Result<()> {
+        if ids.is_empty() || self.torque_cut {
+            return Ok(());
+        }
+
         let data_len: u8 = 2; // 2 bytes per position
         let param_len = ids.len() * (1 + data_len as usize);
         let length = (param_len + 4) as u8;
@@ -302,15 +764,58 @@ impl MotorController {
         let checksum = compute_checksum(&packet[2..]);
         packet.push(checksum);
 
-        self.port
+        let _span = audit::span("serial_write");
+        let written = self
+            .port
             .write_all(&packet)
-            .context("Serial write failed")?;
-        self.port.flush().context("Serial flush failed")?;
+            .context("Serial write failed")
+            .and_then(|()| self.port.flush().context("Serial flush failed"));
+        self.record_transaction(written.is_ok());
+        written
+    }
 
-        Ok(())
+    /// Read `data_len` bytes at `addr` from every servo in `ids`, retrying
+    /// the servos that didn't answer up to `retries` times. Returns one
+    /// result per requested ID, in request order.
+    fn sync_read(
+        &mut self,
+        ids: &[u8],
+        addr: u8,
+        data_len: u8,
+    ) -> Result<Vec<Result<Vec<u8>, ServoReadError>>> {
+        let mut results = match self.sync_read_once(ids, addr, data_len) {
+            Ok(results) => results,
+            Err(e) => {
+                self.record_transaction(false);
+                return Err(e);
+            }
+        };
+        for _ in 0..self.retries {
+            let failed: Vec<usize> = (0..ids.len()).filter(|&i| results[i].is_err()).collect();
+            if failed.is_empty() {
+                break;
+            }
+            self.errors.retried();
+            let failed_ids: Vec<u8> = failed.iter().map(|&i| ids[i]).collect();
+            let Ok(retried) = self.sync_read_once(&failed_ids, addr, data_len) else {
+                break;
+            };
+            for (i, result) in failed.into_iter().zip(retried) {
+                results[i] = result;
+            }
+        }
+        self.record_transaction(results.iter().all(Result::is_ok));
+        Ok(results)
     }
 
-    fn sync_read(&mut self, ids: &[u8], addr: u8, data_len: u8) -> Result<Vec<i16>> {
+    /// Read `data_len` bytes at `addr` from every servo in `ids` in one
+    /// transaction.
+    fn sync_read_once(
+        &mut self,
+        ids: &[u8],
+        addr: u8,
+        data_len: u8,
+    ) -> Result<Vec<Result<Vec<u8>, ServoReadError>>> {
         // Build sync read packet
         let length = (ids.len() + 4) as u8;
         let mut packet = Vec::with_capacity(8 + ids.len());
@@ -325,44 +830,88 @@ impl MotorController {
         let checksum = compute_checksum(&packet[2..]);
         packet.push(checksum);
 
+        // Discard stale bytes so they can't be mistaken for this reply
+        let _ = self.port.clear(serialport::ClearBuffer::Input);
+
+        let span = audit::span("serial_write");
         self.port
             .write_all(&packet)
             .context("Serial write failed")?;
         self.port.flush()?;
+        drop(span);
 
-        // Read responses: each servo replies with [0xFF, 0xFF, id, len, err, data..., checksum]
-        let mut values = Vec::with_capacity(ids.len());
-        let response_size = (6 + data_len as usize) * ids.len();
-        let mut buf = vec![0u8; response_size];
-
-        // Allow partial reads
-        std::thread::sleep(Duration::from_micros(500));
-        let bytes_read = self.port.read(&mut buf).unwrap_or(0);
+        Ok(self.read_status(ids, data_len))
+    }
 
-        if bytes_read == 0 {
-            anyhow::bail!("No response from servos");
-        }
+    /// Sync-read a 2-byte register from all joints and decode it as i16.
+    ///
+    /// Fails if any servo did not answer correctly: a missing value must never
+    /// be replaced by a made-up one.
+    fn sync_read_i16(&mut self, addr: u8) -> Result<Vec<i16>> {
+        let ids = self.joint_ids.clone();
+        let results = self.sync_read(&ids, addr, 2)?;
 
-        // Parse individual servo responses
-        let mut cursor = 0;
-        for _ in 0..ids.len() {
-            if cursor + 6 + data_len as usize > bytes_read {
-                // Pad with zero if we got a short read
-                values.push(0);
-                continue;
+        let mut values = Vec::with_capacity(ids.len());
+        let mut failures = Vec::new();
+        for (&id, result) in ids.iter().zip(results) {
+            match result {
+                Ok(data) => {
+                    let mut rdr = Cursor::new(&data);
+                    values.push(rdr.read_i16::<LittleEndian>()?);
+                }
+                Err(e) => failures.push(format!("{}: {}", id, e)),
             }
+        }
 
-            // Skip header (0xFF 0xFF), id, length, error
-            cursor += 5;
+        if !failures.is_empty() {
+            anyhow::bail!("Servo read failed ({})", failures.join(", "));
+        }
+        Ok(values)
+    }
 
-            let mut rdr = Cursor::new(&buf[cursor..cursor + data_len as usize]);
-            let val = rdr.read_i16::<LittleEndian>().unwrap_or(0);
-            values.push(val);
+    /// Collect status packets from `ids`, reporting the error bytes they carry.
+    fn read_status(&mut self, ids: &[u8], data_len: u8) -> Vec<Result<Vec<u8>, ServoReadError>> {
+        let (joint_ids, servo_errors) = (&self.joint_ids, &mut self.servo_errors);
+        let timeout = self.status_timeout;
+        collect_status(
+            &mut *self.port,
+            ids,
+            data_len.into(),
+            timeout,
+            parse_status_packet,
+            |id, error| {
+                if let Some(joint) = joint_ids.iter().position(|&i| i == id) {
+                    servo_errors.push(ServoErrorReport { joint, id, flags: error });
+                }
+            },
+        )
+    }
 
-            cursor += data_len as usize + 1; // data + checksum
+    /// Feed the error rate and act on its escalation.
+    fn record_transaction(&mut self, ok: bool) {
+        match self.errors.record(ok) {
+            None => {}
+            Some(Escalation::Reopen) => {
+                tracing::warn!(
+                    "Servo bus error rate at {:.0}%, reopening {}",
+                    self.errors.stats().error_rate * 100.0,
+                    self.port_path
+                );
+                match open_port(&self.port_path) {
+                    Ok(port) => {
+                        self.port = port;
+                        self.kill_port.refresh(&*self.port);
+                    }
+                    Err(e) => tracing::error!("{:#}", e),
+                }
+            }
+            Some(Escalation::SoftStop) => {
+                self.bus_fault = Some(format!(
+                    "servo bus error rate at {:.0}% after reopening the port",
+                    self.errors.stats().error_rate * 100.0
+                ));
+            }
         }
-
-        Ok(values)
     }
 
     fn drain_response(&mut self) {
@@ -372,32 +921,427 @@ impl MotorController {
     }
 }
 
-/// Compute Feetech checksum: ~(sum of bytes) & 0xFF.
-fn compute_checksum(data: &[u8]) -> u8 {
-    let sum: u16 = data.iter().map(|&b| b as u16).sum();
-    (!sum as u8) & 0xFF
-}
+// ── Register dump and restore ──
 
-/// Convert radians to raw servo position (STS3215: 0-4095, center at 2048).
-fn rad_to_raw(rad: f64) -> i16 {
-    // STS3215: 0-4095 maps to 0-360 degrees, center at 2048
-    let degrees = rad.to_degrees();
-    let raw = (degrees / 360.0 * 4096.0 + 2048.0) as i16;
-    raw.clamp(0, 4095)
-}
+/// Time for a servo to commit one EEPROM write.
+const EEPROM_WRITE_DELAY: Duration = Duration::from_millis(10);
 
-/// Convert raw servo position to radians.
-fn raw_to_rad(raw: i16) -> f64 {
-    ((raw as f64 - 2048.0) / 4096.0 * 360.0).to_radians()
-}
+impl MotorController {
+    /// Read the whole register table of servo `id`.
+    pub fn dump_registers(&mut self, id: u8) -> Result<ServoRegisters> {
+        let table = self.read_register(id, 0, servo_registers::TABLE_LEN)?;
+        let joint = self.model.joint_for_id(id).map(str::to_string);
+        ServoRegisters::decode(id, joint, &table)
+    }
 
-/// Convert raw velocity to rad/s.
-fn raw_to_rad_per_sec(raw: i16) -> f64 {
+    /// Dump every servo of the joint map.
+    pub fn dump_all_registers(&mut self) -> Result<RegisterDump> {
+        let ids = self.joint_ids.clone();
+        let servos = ids
+            .into_iter()
+            .map(|id| self.dump_registers(id))
+            .collect::<Result<_>>()?;
+        Ok(RegisterDump { servos })
+    }
+
+    /// Write the saved EEPROM settings onto servo `id`, e.g. a replacement
+    /// still at its factory ID. Returns the writes made.
+    pub fn restore_registers(
+        &mut self,
+        id: u8,
+        saved: &ServoRegisters,
+    ) -> Result<Vec<RegisterWrite>> {
+        let current = self.dump_registers(id)?;
+        let plan = servo_registers::restore_plan(&current, saved);
+        self.write_eeprom(id, &plan)?;
+        Ok(plan)
+    }
+
+    /// Write one register by name; EEPROM registers are unlocked around
+    /// the write.
+    pub fn set_register(&mut self, id: u8, name: &str, value: u16) -> Result<()> {
+        let register = servo_registers::register(name)?;
+        match register.access {
+            Access::ReadOnly => bail!("Register {} is read-only", name),
+            Access::Ram => {
+                let data = servo_registers::register_bytes(register, value)?;
+                self.write_register(id, register.addr, &data)
+            }
+            Access::Eeprom => self.write_eeprom(
+                id,
+                &[RegisterWrite {
+                    register,
+                    from: None,
+                    to: value,
+                }],
+            ),
+        }
+    }
+
+    /// Unlock the EEPROM, make `writes` in order and lock it again at the
+    /// servo's new ID and baud rate.
+    fn write_eeprom(&mut self, id: u8, writes: &[RegisterWrite]) -> Result<()> {
+        if writes.is_empty() {
+            return Ok(());
+        }
+        let encoded = writes
+            .iter()
+            .map(|w| servo_registers::register_bytes(w.register, w.to))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.write_register(id, servo_registers::ADDR_LOCK, &[0])?;
+        let mut address = id;
+        let mut baud = None;
+        for (write, data) in writes.iter().zip(encoded) {
+            self.write_register(address, write.register.addr, &data)?;
+            std::thread::sleep(EEPROM_WRITE_DELAY);
+            match write.register.name {
+                "id" => address = write.to as u8,
+                "baud_rate" => baud = servo_registers::baud_for_code(write.to),
+                _ => {}
+            }
+        }
+
+        if let Some(baud) = baud {
+            self.port.set_baud_rate(baud)?;
+        }
+        let locked = self.write_register(address, servo_registers::ADDR_LOCK, &[1]);
+        if baud.is_some() {
+            self.port.set_baud_rate(BAUD_RATE)?;
+        }
+        locked
+    }
+}
+
+// ── Status packet parsing ──
+
+/// Why a servo's reply to a read could not be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServoReadError {
+    /// No valid status packet arrived before the deadline.
+    NoResponse,
+    /// A packet arrived with an unexpected payload length.
+    Malformed,
+}
+
+impl std::fmt::Display for ServoReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoResponse => write!(f, "no response"),
+            Self::Malformed => write!(f, "malformed reply"),
+        }
+    }
+}
+
+impl std::error::Error for ServoReadError {}
+
+/// Result of scanning a byte buffer for one status packet.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ParsedStatus {
+    /// Not enough bytes yet for a complete packet.
+    Incomplete,
+    /// Drop this many leading bytes (noise before a header, a bogus length,
+    /// or a packet whose checksum fails).
+    Skip(usize),
+    /// A complete packet, checksum verified, spanning `len` bytes from the
+    /// start of the buffer.
+    Packet {
+        id: u8,
+        /// Status byte (0 = no error).
+        error: u8,
+        params: Vec<u8>,
+        len: usize,
+    },
+}
+
+/// What to drop before `buf` starts with `header`, or `None` once it does.
+/// A trailing partial header is kept, the next read may complete it.
+pub(crate) fn seek_header(buf: &[u8], header: &[u8]) -> Option<ParsedStatus> {
+    let Some(start) = buf.windows(header.len()).position(|w| w == header) else {
+        let keep = (1..header.len()).rev().find(|&n| buf.ends_with(&header[..n])).unwrap_or(0);
+        return Some(match buf.len() - keep {
+            0 => ParsedStatus::Incomplete,
+            n => ParsedStatus::Skip(n),
+        });
+    };
+    (start > 0).then_some(ParsedStatus::Skip(start))
+}
+
+/// Collect status packets from `ids` on `port` until all have answered or
+/// `timeout` passes, accumulating partial reads. `parse` finds one packet at
+/// the start of a buffer in the bus's protocol, and `on_error` gets the ID
+/// and status byte of every reply that reports an error. Returns one result
+/// per requested ID, in request order.
+pub(crate) fn collect_status(
+    port: &mut dyn serialport::SerialPort,
+    ids: &[u8],
+    data_len: usize,
+    timeout: Duration,
+    parse: fn(&[u8]) -> ParsedStatus,
+    mut on_error: impl FnMut(u8, u8),
+) -> Vec<Result<Vec<u8>, ServoReadError>> {
+    let mut results: Vec<Option<Result<Vec<u8>, ServoReadError>>> = vec![None; ids.len()];
+    let mut pending = ids.len();
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 256];
+    let deadline = Instant::now() + timeout;
+
+    while pending > 0 {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        let _ = port.set_timeout(deadline - now);
+
+        let _span = audit::span("serial_read");
+        match port.read(&mut chunk) {
+            Ok(0) => continue,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => break,
+            Err(e) => {
+                tracing::trace!("Serial read error: {}", e);
+                break;
+            }
+        }
+
+        let mut consumed = 0;
+        loop {
+            match parse(&buf[consumed..]) {
+                ParsedStatus::Incomplete => break,
+                ParsedStatus::Skip(n) => consumed += n,
+                ParsedStatus::Packet { id, error, params, len } => {
+                    consumed += len;
+                    let Some(slot) = ids.iter().position(|&i| i == id) else {
+                        continue;
+                    };
+                    if results[slot].is_some() {
+                        continue;
+                    }
+                    if error != 0 {
+                        on_error(id, error);
+                    }
+                    results[slot] = Some(if params.len() != data_len {
+                        Err(ServoReadError::Malformed)
+                    } else {
+                        Ok(params)
+                    });
+                    pending -= 1;
+                }
+            }
+        }
+        buf.drain(..consumed);
+    }
+
+    let _ = port.set_timeout(SERIAL_TIMEOUT);
+
+    results
+        .into_iter()
+        .map(|r| r.unwrap_or(Err(ServoReadError::NoResponse)))
+        .collect()
+}
+
+/// Parse a status packet `[0xFF, 0xFF, id, len, err, params..., checksum]`
+/// from the start of `buf`, resynchronizing on the header if needed.
+pub(crate) fn parse_status_packet(buf: &[u8]) -> ParsedStatus {
+    if let Some(skip) = seek_header(buf, &HEADER) {
+        return skip;
+    }
+
+    // A third 0xFF means the header started one byte later (0xFF is never a valid ID here)
+    if buf.len() < 4 {
+        return ParsedStatus::Incomplete;
+    }
+    if buf[2] == 0xFF {
+        return ParsedStatus::Skip(1);
+    }
+
+    let id = buf[2];
+    let length = buf[3] as usize; // error + params + checksum
+    if length < 2 {
+        return ParsedStatus::Skip(2);
+    }
+
+    let total = 4 + length;
+    if buf.len() < total {
+        return ParsedStatus::Incomplete;
+    }
+
+    // Neither the ID nor the length can be trusted: resync on the next header,
+    // which may be another servo's reply inside the bogus length
+    if compute_checksum(&buf[2..total - 1]) != buf[total - 1] {
+        return ParsedStatus::Skip(1);
+    }
+    ParsedStatus::Packet {
+        id,
+        error: buf[4],
+        params: buf[5..total - 1].to_vec(),
+        len: total,
+    }
+}
+
+/// Build a READ instruction packet for `len` bytes at `addr`.
+pub(crate) fn read_packet(id: u8, addr: u8, len: u8) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(8);
+    packet.extend_from_slice(&HEADER);
+    packet.push(id);
+    packet.push(4); // instruction + address + length + checksum
+    packet.push(INST_READ);
+    packet.push(addr);
+    packet.push(len);
+    packet.push(compute_checksum(&packet[2..]));
+    packet
+}
+
+/// Build a WRITE instruction packet.
+fn write_packet(id: u8, addr: u8, data: &[u8]) -> Vec<u8> {
+    let length = (data.len() + 3) as u8;
+    let mut packet = Vec::with_capacity(7 + data.len());
+    packet.extend_from_slice(&HEADER);
+    packet.push(id);
+    packet.push(length);
+    packet.push(INST_WRITE);
+    packet.push(addr);
+    packet.extend_from_slice(data);
+    packet.push(compute_checksum(&packet[2..]));
+    packet
+}
+
+/// Compute Feetech checksum: ~(sum of bytes) & 0xFF.
+pub(crate) fn compute_checksum(data: &[u8]) -> u8 {
+    let sum: u16 = data.iter().map(|&b| b as u16).sum();
+    !(sum as u8)
+}
+
+/// Convert radians to raw servo position (STS3215: 0-4095, center at 2048).
+pub(crate) fn rad_to_raw(rad: f64) -> i16 {
+    // STS3215: 0-4095 maps to 0-360 degrees, center at 2048
+    let degrees = rad.to_degrees();
+    let raw = (degrees / 360.0 * 4096.0 + 2048.0) as i16;
+    raw.clamp(0, 4095)
+}
+
+/// Convert raw servo position to radians.
+pub(crate) fn raw_to_rad(raw: i16) -> f64 {
+    ((raw as f64 - 2048.0) / 4096.0 * 360.0).to_radians()
+}
+
+/// Convert raw velocity to rad/s.
+fn raw_to_rad_per_sec(raw: i16) -> f64 {
     // STS3215 velocity unit: ~0.0116 RPM per step
     let rpm = raw as f64 * 0.0116;
     rpm * std::f64::consts::PI / 30.0
 }
 
+/// Decode the present load register: bits 0-9 magnitude in 0.1% of max
+/// torque, bit 10 direction.
+fn decode_load(raw: u16) -> f64 {
+    let magnitude = (raw & 0x3FF) as f64 / 1000.0;
+    if raw & 0x400 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Decode present position..temperature blocks (addresses 56-63), one per
+/// joint, subtracting each joint's offset from its position.
+fn decode_present_state(blocks: &[Vec<u8>], offsets: &[f64]) -> PresentState {
+    let word = |data: &[u8], at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let n = blocks.len();
+    let (mut positions, mut velocities) = (Vec::with_capacity(n), Vec::with_capacity(n));
+    let (mut loads, mut voltages, mut temperatures) =
+        (Vec::with_capacity(n), Vec::with_capacity(n), Vec::with_capacity(n));
+    for (data, offset) in blocks.iter().zip(offsets) {
+        positions.push(raw_to_rad(word(data, 0) as i16) - offset);
+        velocities.push(raw_to_rad_per_sec(word(data, 2) as i16));
+        loads.push(decode_load(word(data, 4)));
+        voltages.push(data[6] as f64 / 10.0);
+        temperatures.push(data[7] as f64);
+    }
+    PresentState {
+        positions,
+        velocities,
+        loads: Some(loads),
+        voltages: Some(voltages),
+        temperatures: Some(temperatures),
+    }
+}
+
+/// Decode the present load..status register block (addresses 60-65).
+fn decode_servo_health(id: u8, data: &[u8]) -> ServoHealth {
+    ServoHealth {
+        id,
+        temperature: data[3] as f64,
+        voltage: data[2] as f64 / 10.0,
+        load: decode_load(u16::from_le_bytes([data[0], data[1]])),
+        error_flags: data[5],
+    }
+}
+
+/// Cubic ease-in/ease-out (zero velocity at both ends) for `s` in 0..1.
+pub(crate) fn cubic_ease(s: f64) -> f64 {
+    let s = s.clamp(0.0, 1.0);
+    s * s * (3.0 - 2.0 * s)
+}
+
+/// Point at fraction `s` along a cubic trajectory from `from` to `to`.
+pub fn cubic_interpolate(from: &[f64], to: &[f64], s: f64) -> Vec<f64> {
+    let e = cubic_ease(s);
+    from.iter().zip(to).map(|(&a, &b)| a + (b - a) * e).collect()
+}
+
+/// KPs at fraction `s` of the torque ramp from `start_kp` up to `full`.
+/// Joints configured below `start_kp` stay at their own gain.
+pub(crate) fn kp_ramp(full: &[f64], start_kp: f64, s: f64) -> Vec<f64> {
+    let e = cubic_ease(s);
+    full.iter()
+        .map(|&kp| {
+            let low = start_kp.min(kp);
+            low + (kp - low) * e
+        })
+        .collect()
+}
+
+pub(crate) fn ramp_steps(duration: Duration) -> usize {
+    ((duration.as_secs_f64() / RAMP_PERIOD.as_secs_f64()).ceil() as usize).max(1)
+}
+
+/// Stream a cubic trajectory from `from` to `to` over `duration`, one
+/// `write` of goal positions per ramp period.
+pub(crate) fn ramp_positions(
+    from: &[f64],
+    to: &[f64],
+    duration: Duration,
+    mut write: impl FnMut(&[f64]) -> Result<()>,
+) -> Result<()> {
+    let steps = ramp_steps(duration);
+    for k in 1..=steps {
+        write(&cubic_interpolate(from, to, k as f64 / steps as f64))?;
+        std::thread::sleep(RAMP_PERIOD);
+    }
+    Ok(())
+}
+
+/// Switch torque on with `torque_on` at the ramp's start KP, then ramp KP up
+/// to `full_kps` over the ramp's duration with `write_kps`.
+pub(crate) fn ramp_torque_on<B>(
+    bus: &mut B,
+    full_kps: &[f64],
+    ramp: &TorqueRampConfig,
+    write_kps: fn(&mut B, &[f64]) -> Result<()>,
+    torque_on: fn(&mut B) -> Result<()>,
+) -> Result<()> {
+    write_kps(bus, &kp_ramp(full_kps, ramp.start_kp, 0.0))?;
+    torque_on(bus)?;
+
+    let steps = ramp_steps(Duration::from_secs_f64(ramp.duration.max(0.0)));
+    for k in 1..=steps {
+        write_kps(bus, &kp_ramp(full_kps, ramp.start_kp, k as f64 / steps as f64))?;
+        std::thread::sleep(RAMP_PERIOD);
+    }
+    Ok(())
+}
+
 /// Build a name->position HashMap from an ordered action array and joint name list.
 pub fn make_action_dict(action: &[f64], joint_names: &[String]) -> HashMap<String, f64> {
     let mut dict = HashMap::new();
@@ -410,3 +1354,789 @@ pub fn make_action_dict(action: &[f64], joint_names: &[String]) -> HashMap<Strin
     }
     dict
 }
+
+/// Look up the index of each named target in `joint_names` and apply `f` to it.
+fn for_each_named(
+    joint_names: &[String],
+    positions: &HashMap<String, f64>,
+    mut f: impl FnMut(usize, f64),
+) {
+    for (i, name) in joint_names.iter().enumerate() {
+        if let Some(&pos) = positions.get(name) {
+            f(i, pos);
+        }
+    }
+}
+
+// ── Mock implementation (always available) ──
+
+/// Mock motor controller with ideal servos: every goal position is reached
+/// instantly. Useful for unit tests and dry runs of the control loop.
+pub struct MockMotorController {
+    joint_ids: Vec<u8>,
+    joint_names: Vec<String>,
+    init_pos: Vec<f64>,
+    positions: Vec<f64>,
+    kps: Vec<f64>,
+    kds: Vec<f64>,
+    torque_enabled: bool,
+    /// Torque was cut: goal writes are dropped until `turn_on`.
+    torque_cut: bool,
+}
+
+impl MockMotorController {
+    pub fn new() -> Self {
+        Self::for_model(&RobotModel::default())
+    }
+
+    /// Mock every joint of `model`.
+    pub fn for_model(model: &RobotModel) -> Self {
+        Self::with_joints(model, &model.joint_names()).expect("model joint names are valid")
+    }
+
+    /// Mock only the named joints (in robot joint order), like a test rig.
+    pub fn with_joints(model: &RobotModel, names: &[String]) -> Result<Self> {
+        let (joint_names, joint_ids) = model.select(names)?;
+        let init_pos_map = model.init_positions();
+        let init_pos: Vec<f64> = joint_names
+            .iter()
+            .map(|name| init_pos_map.get(name).copied().unwrap_or(0.0))
+            .collect();
+
+        Ok(Self {
+            positions: init_pos.clone(),
+            init_pos,
+            kps: vec![32.0; joint_ids.len()],
+            kds: vec![0.0; joint_ids.len()],
+            joint_ids,
+            joint_names,
+            torque_enabled: false,
+            torque_cut: false,
+        })
+    }
+
+    /// Last goal positions written (radians).
+    pub fn positions(&self) -> &[f64] {
+        &self.positions
+    }
+
+    /// Last proportional gains written.
+    pub fn kps(&self) -> &[f64] {
+        &self.kps
+    }
+
+    /// Last derivative gains written.
+    pub fn kds(&self) -> &[f64] {
+        &self.kds
+    }
+}
+
+impl Default for MockMotorController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MotorInterface for MockMotorController {
+    fn joint_names(&self) -> &[String] {
+        &self.joint_names
+    }
+
+    fn init_positions_array(&self) -> Vec<f64> {
+        self.init_pos.clone()
+    }
+
+    fn set_kps(&mut self, kps: &[f64]) -> Result<()> {
+        self.kps = kps.to_vec();
+        Ok(())
+    }
+
+    fn set_kds(&mut self, kds: &[f64]) -> Result<()> {
+        self.kds = kds.to_vec();
+        Ok(())
+    }
+
+    fn turn_on(&mut self) -> Result<()> {
+        self.torque_enabled = true;
+        self.torque_cut = false;
+        self.positions = self.init_pos.clone();
+        tracing::info!("Mock motors: torque enabled");
+        Ok(())
+    }
+
+    fn turn_off(&mut self) -> Result<()> {
+        self.torque_enabled = false;
+        tracing::info!("Mock motors: torque disabled");
+        Ok(())
+    }
+
+    fn disable_torque_now(&mut self) -> Result<()> {
+        self.torque_enabled = false;
+        self.torque_cut = true;
+        tracing::warn!("Mock motors: torque cut");
+        Ok(())
+    }
+
+    fn torque_enabled(&mut self) -> Result<bool> {
+        Ok(self.torque_enabled)
+    }
+
+    fn set_position_all(&mut self, positions: &HashMap<String, f64>) -> Result<()> {
+        if self.torque_cut {
+            return Ok(());
+        }
+        let current = &mut self.positions;
+        for_each_named(&self.joint_names, positions, |i, pos| current[i] = pos);
+        Ok(())
+    }
+
+    fn set_position_all_array(&mut self, positions: &[f64]) -> Result<()> {
+        if self.torque_cut {
+            return Ok(());
+        }
+        for (current, &pos) in self.positions.iter_mut().zip(positions) {
+            *current = pos;
+        }
+        Ok(())
+    }
+
+    fn get_present_positions(&mut self) -> Option<Vec<f64>> {
+        Some(self.positions.clone())
+    }
+
+    fn get_present_velocities(&mut self) -> Option<Vec<f64>> {
+        Some(vec![0.0; self.joint_names.len()])
+    }
+
+    fn get_servo_health(&mut self) -> Option<Vec<ServoHealth>> {
+        Some(ideal_servo_health(&self.joint_ids))
+    }
+
+    fn get_present_loads(&mut self) -> Option<Vec<f64>> {
+        Some(vec![0.0; self.joint_names.len()])
+    }
+}
+
+// ── Kinematic simulation (always available) ──
+
+/// Time constant (s) of the simulated position loop at the reference KP.
+const SIM_TIME_CONSTANT: f64 = 0.04;
+
+/// KP at which the simulated servo responds with `SIM_TIME_CONSTANT`.
+const SIM_REFERENCE_KP: f64 = 32.0;
+
+/// Maximum simulated joint speed (rad/s), roughly an STS3215 at 7.4V.
+const SIM_MAX_SPEED: f64 = 4.7;
+
+/// Temperature reported by the mock and simulated servos (°C).
+const SIM_AMBIENT_TEMPERATURE: f64 = 25.0;
+
+/// Supply voltage reported by the mock and simulated servos (V).
+const SIM_SUPPLY_VOLTAGE: f64 = 7.4;
+
+/// Health readings of cool, unloaded servos on a full battery.
+fn ideal_servo_health(ids: &[u8]) -> Vec<ServoHealth> {
+    ids.iter()
+        .map(|&id| ServoHealth {
+            id,
+            temperature: SIM_AMBIENT_TEMPERATURE,
+            voltage: SIM_SUPPLY_VOLTAGE,
+            load: 0.0,
+            error_flags: 0,
+        })
+        .collect()
+}
+
+/// Simulated servos: each joint tracks its goal with a first-order lag whose
+/// time constant scales inversely with KP, capped at a realistic speed.
+///
+/// There is no gravity or contact model — this exercises the control loop and
+/// lets a policy be watched without hardware, not validated.
+pub struct SimMotorController {
+    joint_names: Vec<String>,
+    joint_ids: Vec<u8>,
+    init_pos: Vec<f64>,
+    positions: Vec<f64>,
+    velocities: Vec<f64>,
+    targets: Vec<f64>,
+    kps: Vec<f64>,
+    kds: Vec<f64>,
+    torque_enabled: bool,
+    /// Torque was cut: goal writes are dropped until `turn_on`.
+    torque_cut: bool,
+    last_update: Instant,
+}
+
+impl SimMotorController {
+    pub fn new() -> Self {
+        Self::for_model(&RobotModel::default())
+    }
+
+    /// Simulate every joint of `model`.
+    pub fn for_model(model: &RobotModel) -> Self {
+        let n = model.num_dofs();
+        Self {
+            joint_names: model.joint_names(),
+            joint_ids: model.joint_ids(),
+            positions: vec![0.0; n],
+            velocities: vec![0.0; n],
+            targets: vec![0.0; n],
+            init_pos: model.joints.iter().map(|j| j.init).collect(),
+            kps: vec![32.0; n],
+            kds: vec![0.0; n],
+            torque_enabled: false,
+            torque_cut: false,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Advance the simulation by `dt` seconds.
+    pub fn step(&mut self, dt: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+
+        for i in 0..self.positions.len() {
+            if !self.torque_enabled || self.kps[i] <= 0.0 {
+                // Limp joint: no gravity model, so it simply stays put
+                self.velocities[i] = 0.0;
+                continue;
+            }
+
+            let tau = SIM_TIME_CONSTANT * SIM_REFERENCE_KP / self.kps[i];
+            let alpha = 1.0 - (-dt / tau).exp();
+            let max_step = SIM_MAX_SPEED * dt;
+            let delta = ((self.targets[i] - self.positions[i]) * alpha).clamp(-max_step, max_step);
+
+            self.positions[i] += delta;
+            self.velocities[i] = delta / dt;
+        }
+    }
+
+    /// Advance the simulation to the current wall-clock time.
+    fn update(&mut self) {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_update).as_secs_f64();
+        self.last_update = now;
+        self.step(dt);
+    }
+}
+
+impl Default for SimMotorController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MotorInterface for SimMotorController {
+    fn joint_names(&self) -> &[String] {
+        &self.joint_names
+    }
+
+    fn init_positions_array(&self) -> Vec<f64> {
+        self.init_pos.clone()
+    }
+
+    fn set_kps(&mut self, kps: &[f64]) -> Result<()> {
+        self.update();
+        self.kps = kps.to_vec();
+        Ok(())
+    }
+
+    fn set_kds(&mut self, kds: &[f64]) -> Result<()> {
+        self.kds = kds.to_vec();
+        Ok(())
+    }
+
+    fn turn_on(&mut self) -> Result<()> {
+        self.update();
+        self.torque_enabled = true;
+        self.torque_cut = false;
+        self.targets = self.init_pos.clone();
+        tracing::info!("Sim motors: torque enabled, moving to init position");
+        Ok(())
+    }
+
+    fn turn_off(&mut self) -> Result<()> {
+        self.update();
+        self.torque_enabled = false;
+        tracing::info!("Sim motors: torque disabled");
+        Ok(())
+    }
+
+    fn disable_torque_now(&mut self) -> Result<()> {
+        self.update();
+        self.torque_enabled = false;
+        self.torque_cut = true;
+        tracing::warn!("Sim motors: torque cut");
+        Ok(())
+    }
+
+    fn torque_enabled(&mut self) -> Result<bool> {
+        Ok(self.torque_enabled)
+    }
+
+    fn set_position_all(&mut self, positions: &HashMap<String, f64>) -> Result<()> {
+        self.update();
+        if self.torque_cut {
+            return Ok(());
+        }
+        let targets = &mut self.targets;
+        for_each_named(&self.joint_names, positions, |i, pos| targets[i] = pos);
+        Ok(())
+    }
+
+    fn set_position_all_array(&mut self, positions: &[f64]) -> Result<()> {
+        self.update();
+        if self.torque_cut {
+            return Ok(());
+        }
+        for (target, &pos) in self.targets.iter_mut().zip(positions) {
+            *target = pos;
+        }
+        Ok(())
+    }
+
+    fn get_present_positions(&mut self) -> Option<Vec<f64>> {
+        self.update();
+        Some(self.positions.clone())
+    }
+
+    fn get_present_velocities(&mut self) -> Option<Vec<f64>> {
+        self.update();
+        Some(self.velocities.clone())
+    }
+
+    fn get_servo_health(&mut self) -> Option<Vec<ServoHealth>> {
+        Some(ideal_servo_health(&self.joint_ids))
+    }
+
+    fn get_present_loads(&mut self) -> Option<Vec<f64>> {
+        Some(vec![0.0; self.joint_names.len()])
+    }
+}
+
+// ── Partial test rig ──
+
+/// Real servos for the joints present on a test rig (e.g. one leg on a bench
+/// stand), simulated servos for the rest.
+///
+/// The control loop still sees all 14 joints, so observations keep the
+/// policy's layout; missing joints track their targets in the kinematic sim.
+/// Joint limits are enforced here for every joint.
+pub struct PartialRig {
+    hardware: Box<dyn MotorInterface>,
+    sim: SimMotorController,
+    joint_names: Vec<String>,
+    /// Index into the hardware's joints for each robot joint, if present.
+    present: Vec<Option<usize>>,
+    limits: JointLimits,
+}
+
+impl PartialRig {
+    pub fn new(hardware: Box<dyn MotorInterface>, config: &DuckConfig) -> Self {
+        let joint_names = config.robot_model.joint_names();
+        let present: Vec<Option<usize>> = joint_names
+            .iter()
+            .map(|name| hardware.joint_names().iter().position(|n| n == name))
+            .collect();
+
+        let simulated: Vec<&str> = joint_names
+            .iter()
+            .zip(&present)
+            .filter(|(_, p)| p.is_none())
+            .map(|(n, _)| n.as_str())
+            .collect();
+        tracing::info!(
+            "Test rig: {} of {} joints present, simulating {}",
+            hardware.joint_names().len(),
+            joint_names.len(),
+            simulated.join(", ")
+        );
+
+        Self {
+            hardware,
+            sim: SimMotorController::for_model(&config.robot_model),
+            limits: JointLimits::new(&joint_names, &config.resolved_joint_limits()),
+            joint_names,
+            present,
+        }
+    }
+
+    /// Values of the present joints, in hardware order.
+    fn present_values(&self, values: &[f64]) -> Vec<f64> {
+        let mut out = vec![0.0; self.hardware.joint_names().len()];
+        for (&value, p) in values.iter().zip(&self.present) {
+            if let Some(j) = *p {
+                out[j] = value;
+            }
+        }
+        out
+    }
+
+    /// Hardware readings for present joints, sim readings for the rest.
+    fn merge<T: Copy>(&self, hardware: &[T], sim: &[T]) -> Vec<T> {
+        self.present
+            .iter()
+            .zip(sim)
+            .map(|(p, &s)| p.and_then(|j| hardware.get(j).copied()).unwrap_or(s))
+            .collect()
+    }
+}
+
+impl MotorInterface for PartialRig {
+    fn joint_names(&self) -> &[String] {
+        &self.joint_names
+    }
+
+    fn init_positions_array(&self) -> Vec<f64> {
+        self.sim.init_positions_array()
+    }
+
+    fn set_kps(&mut self, kps: &[f64]) -> Result<()> {
+        self.hardware.set_kps(&self.present_values(kps))?;
+        self.sim.set_kps(kps)
+    }
+
+    fn set_kds(&mut self, kds: &[f64]) -> Result<()> {
+        self.hardware.set_kds(&self.present_values(kds))?;
+        self.sim.set_kds(kds)
+    }
+
+    fn set_kis(&mut self, kis: &[f64]) -> Result<()> {
+        self.hardware.set_kis(&self.present_values(kis))
+    }
+
+    fn turn_on(&mut self) -> Result<()> {
+        self.sim.turn_on()?;
+        self.hardware.turn_on()
+    }
+
+    fn turn_off(&mut self) -> Result<()> {
+        self.sim.turn_off()?;
+        self.hardware.turn_off()
+    }
+
+    fn disable_torque_now(&mut self) -> Result<()> {
+        self.sim.disable_torque_now()?;
+        self.hardware.disable_torque_now()
+    }
+
+    fn torque_enabled(&mut self) -> Result<bool> {
+        self.hardware.torque_enabled()
+    }
+
+    fn set_position_all(&mut self, positions: &HashMap<String, f64>) -> Result<()> {
+        let mut checked = HashMap::with_capacity(positions.len());
+        let limits = &mut self.limits;
+        let names = &self.joint_names;
+        for_each_named(names, positions, |i, pos| {
+            if let LimitCheck::Write(pos) = limits.check(i, pos) {
+                checked.insert(names[i].clone(), pos);
+            }
+        });
+
+        self.sim.set_position_all(&checked)?;
+        self.hardware.set_position_all(&checked)
+    }
+
+    fn set_position_all_array(&mut self, positions: &[f64]) -> Result<()> {
+        self.set_position_all(&make_action_dict(positions, &self.joint_names))
+    }
+
+    fn get_present_positions(&mut self) -> Option<Vec<f64>> {
+        let hardware = self.hardware.get_present_positions()?;
+        let sim = self.sim.get_present_positions()?;
+        Some(self.merge(&hardware, &sim))
+    }
+
+    fn get_present_velocities(&mut self) -> Option<Vec<f64>> {
+        let hardware = self.hardware.get_present_velocities()?;
+        let sim = self.sim.get_present_velocities()?;
+        Some(self.merge(&hardware, &sim))
+    }
+
+    /// One hardware read for the rig; loads are merged too, since the
+    /// collision detector uses them.
+    fn get_present_state(&mut self) -> Option<PresentState> {
+        let hardware = self.hardware.get_present_state()?;
+        let sim = self.sim.get_present_state()?;
+        let loads = match (hardware.loads, self.sim.get_present_loads()) {
+            (Some(hardware), Some(sim)) => Some(self.merge(&hardware, &sim)),
+            _ => None,
+        };
+        Some(PresentState {
+            positions: self.merge(&hardware.positions, &sim.positions),
+            velocities: self.merge(&hardware.velocities, &sim.velocities),
+            loads,
+            ..PresentState::default()
+        })
+    }
+
+    fn get_servo_health(&mut self) -> Option<Vec<ServoHealth>> {
+        let hardware = self.hardware.get_servo_health()?;
+        let sim = self.sim.get_servo_health()?;
+        Some(self.merge(&hardware, &sim))
+    }
+
+    fn get_present_loads(&mut self) -> Option<Vec<f64>> {
+        let hardware = self.hardware.get_present_loads()?;
+        let sim = self.sim.get_present_loads()?;
+        Some(self.merge(&hardware, &sim))
+    }
+
+    fn joint_limits(&self) -> Option<&JointLimits> {
+        Some(&self.limits)
+    }
+
+    fn torque_kill_switch(&self) -> Option<TorqueKillSwitch> {
+        self.hardware.torque_kill_switch()
+    }
+
+    fn bus_stats(&self) -> Option<BusStats> {
+        self.hardware.bus_stats()
+    }
+
+    fn take_bus_fault(&mut self) -> Option<String> {
+        self.hardware.take_bus_fault()
+    }
+
+    /// Reports from the hardware, with joints renumbered in robot order.
+    fn take_servo_errors(&mut self) -> Vec<ServoErrorReport> {
+        self.hardware
+            .take_servo_errors()
+            .into_iter()
+            .filter_map(|report| {
+                let joint = self.present.iter().position(|&p| p == Some(report.joint))?;
+                Some(ServoErrorReport { joint, ..report })
+            })
+            .collect()
+    }
+
+    fn disable_joint_torque(&mut self, joint: usize) -> Result<()> {
+        match self.present.get(joint).copied().flatten() {
+            Some(j) => self.hardware.disable_joint_torque(j),
+            None => Ok(()),
+        }
+    }
+
+    fn set_joint_offsets(&mut self, offsets: &HashMap<String, f64>) -> Result<()> {
+        self.hardware.set_joint_offsets(offsets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_applies_named_targets() {
+        let mut motors = MockMotorController::new();
+        motors.turn_on().unwrap();
+
+        let mut targets = HashMap::new();
+        targets.insert("left_knee".to_string(), 0.5);
+        motors.set_position_all(&targets).unwrap();
+
+        let pos = motors.get_present_positions().unwrap();
+        let knee = RobotModel::default().index_of("left_knee").unwrap();
+        assert_eq!(pos[knee], 0.5);
+        assert_eq!(pos[0], motors.init_positions_array()[0]);
+    }
+
+    #[test]
+    fn test_partial_rig_merges_hardware_and_sim() {
+        let model = RobotModel::default();
+        let bench_leg = ["left_knee".to_string(), "left_ankle".to_string()];
+        let hardware = MockMotorController::with_joints(&model, &bench_leg).unwrap();
+        let mut rig = PartialRig::new(Box::new(hardware), &DuckConfig::default());
+        assert_eq!(rig.joint_names().len(), model.num_dofs());
+        assert!(MockMotorController::with_joints(&model, &["tail".to_string()]).is_err());
+
+        rig.turn_on().unwrap();
+        let mut targets = rig.init_positions_array();
+        let knee = model.index_of("left_knee").unwrap();
+        targets[knee] = 1.0;
+        rig.set_position_all_array(&targets).unwrap();
+
+        // The mock knee reaches its target instantly; simulated joints lag behind
+        let pos = rig.get_present_positions().unwrap();
+        assert_eq!(pos.len(), model.num_dofs());
+        assert_eq!(pos[knee], 1.0);
+        assert!((pos[0] - targets[0]).abs() > 1e-6);
+
+        let health = rig.get_servo_health().unwrap();
+        assert_eq!(health[knee].id, 23);
+        assert_eq!(health[0].id, 20);
+    }
+
+    #[test]
+    fn test_sim_converges_to_target() {
+        let mut motors = SimMotorController::new();
+        motors.turn_on().unwrap();
+
+        for _ in 0..200 {
+            motors.step(0.02);
+        }
+
+        let init = motors.init_positions_array();
+        for (p, t) in motors.positions.iter().zip(init.iter()) {
+            assert!((p - t).abs() < 1e-3, "Sim did not converge: {} vs {}", p, t);
+        }
+    }
+
+    #[test]
+    fn test_sim_respects_speed_limit_and_torque() {
+        let mut motors = SimMotorController::new();
+        let n = motors.joint_names.len();
+        motors.set_position_all_array(&vec![1.0; n]).unwrap();
+
+        // Torque off: joints stay limp
+        motors.step(0.1);
+        assert!(motors.positions.iter().all(|&p| p == 0.0));
+
+        motors.torque_enabled = true;
+        motors.targets = vec![1.0; n];
+        motors.step(0.02);
+        for &v in &motors.velocities {
+            assert!(v <= SIM_MAX_SPEED + 1e-9);
+        }
+    }
+
+    /// Build a status packet as a servo would send it.
+    fn status_packet(id: u8, params: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0xFF, 0xFF, id, params.len() as u8 + 2, 0];
+        packet.extend_from_slice(params);
+        packet.push(compute_checksum(&packet[2..]));
+        packet
+    }
+
+    #[test]
+    fn test_parse_status_packet() {
+        let packet = status_packet(21, &[0x00, 0x08]);
+        assert_eq!(
+            parse_status_packet(&packet),
+            ParsedStatus::Packet {
+                id: 21,
+                error: 0,
+                params: vec![0x00, 0x08],
+                len: packet.len(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_status_packet_resyncs_and_waits() {
+        let packet = status_packet(10, &[0x34, 0x12]);
+
+        // Noise before the header is skipped
+        let mut noisy = vec![0x00, 0x42];
+        noisy.extend_from_slice(&packet);
+        assert_eq!(parse_status_packet(&noisy), ParsedStatus::Skip(2));
+
+        // Partial packets wait for more bytes
+        assert_eq!(parse_status_packet(&packet[..5]), ParsedStatus::Incomplete);
+        assert_eq!(parse_status_packet(&[0x00, 0xFF]), ParsedStatus::Skip(1));
+        assert_eq!(parse_status_packet(&[0xFF]), ParsedStatus::Incomplete);
+
+        // Triple 0xFF: header starts one byte later
+        let mut triple = vec![0xFF];
+        triple.extend_from_slice(&packet);
+        assert_eq!(parse_status_packet(&triple), ParsedStatus::Skip(1));
+    }
+
+    #[test]
+    fn test_seek_header_keeps_partial_header() {
+        let header = [0xFF, 0xFF, 0xFD, 0x00];
+        let seek = |buf: &[u8]| seek_header(buf, &header);
+        assert_eq!(seek(&[0xFF, 0xFF, 0xFD, 0x00, 0x01]), None);
+        assert_eq!(seek(&[0x42, 0xFF, 0xFF, 0xFD, 0x00]), Some(ParsedStatus::Skip(1)));
+        // A header cut off by the read is kept for the next one
+        assert_eq!(seek(&[0x42, 0x00, 0xFF, 0xFF, 0xFD]), Some(ParsedStatus::Skip(2)));
+        assert_eq!(seek(&[0xFF, 0xFF]), Some(ParsedStatus::Incomplete));
+        assert_eq!(seek(&[]), Some(ParsedStatus::Incomplete));
+    }
+
+    #[test]
+    fn test_parse_status_packet_bad_checksum() {
+        let mut packet = status_packet(12, &[0x00, 0x08]);
+        let last = packet.len() - 1;
+        packet[last] ^= 0x01;
+        assert_eq!(parse_status_packet(&packet), ParsedStatus::Skip(1));
+    }
+
+    #[test]
+    fn test_parse_status_packet_bad_length_keeps_next_reply() {
+        // The first reply's length byte is corrupted to reach into the second
+        let mut buf = status_packet(10, &[0x34, 0x12]);
+        buf[3] += 3;
+        buf.extend_from_slice(&status_packet(11, &[0x00, 0x08]));
+
+        let mut replies = Vec::new();
+        let mut rest = &buf[..];
+        loop {
+            match parse_status_packet(rest) {
+                ParsedStatus::Incomplete => break,
+                ParsedStatus::Skip(n) => rest = &rest[n..],
+                ParsedStatus::Packet { id, params, len, .. } => {
+                    replies.push((id, params));
+                    rest = &rest[len..];
+                }
+            }
+        }
+        assert_eq!(replies, [(11, vec![0x00, 0x08])]);
+    }
+
+    #[test]
+    fn test_decode_servo_health() {
+        // load 250 (25%) reversed, 7.4V, 41°C, async flag, status 0x04
+        let health = decode_servo_health(20, &[0xFA, 0x04, 74, 41, 0, 0x04]);
+        assert_eq!(health.id, 20);
+        assert!((health.load + 0.25).abs() < 1e-12);
+        assert!((health.voltage - 7.4).abs() < 1e-12);
+        assert_eq!(health.temperature, 41.0);
+        assert_eq!(health.error_flags, 0x04);
+    }
+
+    #[test]
+    fn test_decode_present_state_block() {
+        // Position 3072 (+90°), speed -100, load 250 reversed, 7.4V, 41°C
+        let speed = (-100i16).to_le_bytes();
+        let block = vec![0x00, 0x0C, speed[0], speed[1], 0xFA, 0x04, 74, 41];
+        let state = decode_present_state(&[block.clone(), block], &[0.0, 0.5]);
+        assert!((state.positions[0] - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+        assert!((state.positions[1] - (std::f64::consts::FRAC_PI_2 - 0.5)).abs() < 1e-9);
+        assert_eq!(state.velocities[0], raw_to_rad_per_sec(-100));
+        assert_eq!(state.loads, Some(vec![-0.25, -0.25]));
+        assert_eq!(state.voltages, Some(vec![7.4, 7.4]));
+        assert_eq!(state.temperatures, Some(vec![41.0, 41.0]));
+    }
+
+    #[test]
+    fn test_cubic_interpolate_eases_between_poses() {
+        let from = [0.0, 1.0];
+        let to = [1.0, -1.0];
+        assert_eq!(cubic_interpolate(&from, &to, 0.0), from);
+        assert_eq!(cubic_interpolate(&from, &to, 1.0), to);
+        assert_eq!(cubic_interpolate(&from, &to, 0.5), [0.5, 0.0]);
+        assert_eq!(cubic_interpolate(&from, &to, 2.0), to);
+
+        // Zero slope at the ends: the first step moves far less than linear
+        let first = cubic_interpolate(&from, &to, 0.01)[0];
+        assert!(first < 0.001);
+    }
+
+    #[test]
+    fn test_kp_ramp_starts_low_and_ends_at_gains() {
+        let full = [30.0, 8.0, 1.0];
+        assert_eq!(kp_ramp(&full, 2.0, 0.0), [2.0, 2.0, 1.0]);
+        assert_eq!(kp_ramp(&full, 2.0, 0.5), [16.0, 5.0, 1.0]);
+        assert_eq!(kp_ramp(&full, 2.0, 1.0), full);
+    }
+
+    #[test]
+    fn test_checksum() {
+        // Ping packet for ID 1: FF FF 01 02 01 FB
+        assert_eq!(compute_checksum(&[0x01, 0x02, 0x01]), 0xFB);
+    }
+}
@@ -0,0 +1,365 @@
+//! Observation and action normalization statistics from training.
+//!
+//! Policies trained with running observation normalization expect the
+//! observation shifted and scaled by the statistics gathered in training,
+//! and some output normalized actions too. The statistics ship next to the
+//! model, as `policy.norm.json` or `policy.norm.npz` (from `numpy.savez`),
+//! with these arrays:
+//!
+//! ```json
+//! {
+//!   "obs_mean": [...],
+//!   "obs_std": [...],
+//!   "action_mean": [...],
+//!   "action_std": [...],
+//!   "clip_obs": 5.0,
+//!   "epsilon": 1e-8
+//! }
+//! ```
+//!
+//! The observation becomes `(obs - obs_mean) / (obs_std + epsilon)`, or
+//! `(obs - obs_mean) / sqrt(obs_var + epsilon)` when the file has `obs_var`
+//! instead (as saved by SB3's `VecNormalize`), clipped to ±`clip_obs` if
+//! given. With `action_mean` and `action_std`, the model's output is mapped
+//! back with `action * action_std + action_mean`. `NormalizedPolicy` does
+//! both around the model, so the rest of the runtime only sees raw values.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::inference::PolicyInference;
+
+/// Default `epsilon`, the usual one of the normalizers.
+const EPSILON: f64 = 1e-8;
+
+/// The statistics as saved, before checking.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StatsFile {
+    obs_mean: Vec<f64>,
+    #[serde(default)]
+    obs_std: Option<Vec<f64>>,
+    #[serde(default)]
+    obs_var: Option<Vec<f64>>,
+    #[serde(default)]
+    action_mean: Option<Vec<f64>>,
+    #[serde(default)]
+    action_std: Option<Vec<f64>>,
+    #[serde(default)]
+    clip_obs: Option<f64>,
+    #[serde(default)]
+    epsilon: Option<f64>,
+}
+
+/// Loaded normalization statistics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalization {
+    obs_mean: Vec<f64>,
+    /// What each centered observation value is divided by.
+    obs_scale: Vec<f64>,
+    clip_obs: Option<f64>,
+    /// Mean and std of the actions.
+    action: Option<(Vec<f64>, Vec<f64>)>,
+}
+
+impl Normalization {
+    /// Statistics file stored next to a model, if there is one.
+    pub fn path_for_model(model_path: &Path) -> Option<PathBuf> {
+        ["norm.json", "norm.npz"]
+            .into_iter()
+            .map(|ext| model_path.with_extension(ext))
+            .find(|path| path.exists())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let context = || format!("Failed to load normalization statistics {}", path.display());
+        let stats = if path.extension().is_some_and(|ext| ext == "npz") {
+            let file = std::fs::File::open(path).with_context(context)?;
+            stats_from_npz(file).with_context(context)?
+        } else {
+            let text = std::fs::read_to_string(path).with_context(context)?;
+            serde_json::from_str(&text).with_context(context)?
+        };
+        Self::from_stats(stats).with_context(context)
+    }
+
+    fn from_stats(stats: StatsFile) -> Result<Self> {
+        let epsilon = stats.epsilon.unwrap_or(EPSILON);
+        let obs_scale: Vec<f64> = match (stats.obs_std, stats.obs_var) {
+            (Some(std), None) => std.iter().map(|s| s + epsilon).collect(),
+            (None, Some(var)) => var.iter().map(|v| (v + epsilon).sqrt()).collect(),
+            _ => bail!("Expected one of obs_std and obs_var"),
+        };
+        if obs_scale.len() != stats.obs_mean.len() {
+            bail!(
+                "obs_mean has {} values but the observation scale has {}",
+                stats.obs_mean.len(),
+                obs_scale.len()
+            );
+        }
+        if !obs_scale.iter().all(|s| s.is_finite() && *s > 0.0) {
+            bail!("Observation std must be positive");
+        }
+        if stats.clip_obs.is_some_and(|clip| !(clip.is_finite() && clip > 0.0)) {
+            bail!("clip_obs must be positive");
+        }
+        let action = match (stats.action_mean, stats.action_std) {
+            (Some(mean), Some(std)) if mean.len() == std.len() => Some((mean, std)),
+            (Some(mean), Some(std)) => bail!(
+                "action_mean has {} values but action_std has {}",
+                mean.len(),
+                std.len()
+            ),
+            (None, None) => None,
+            _ => bail!("action_mean and action_std go together"),
+        };
+        Ok(Self {
+            obs_mean: stats.obs_mean,
+            obs_scale,
+            clip_obs: stats.clip_obs,
+            action,
+        })
+    }
+
+    /// Observation length the statistics are for.
+    pub fn obs_dim(&self) -> usize {
+        self.obs_mean.len()
+    }
+
+    pub fn normalize_observation(&self, observation: &[f64]) -> Result<Vec<f64>> {
+        if observation.len() != self.obs_dim() {
+            bail!(
+                "Observation has {} values, the normalization statistics {}",
+                observation.len(),
+                self.obs_dim()
+            );
+        }
+        let clip = self.clip_obs.unwrap_or(f64::INFINITY);
+        Ok(observation
+            .iter()
+            .zip(&self.obs_mean)
+            .zip(&self.obs_scale)
+            .map(|((x, mean), scale)| ((x - mean) / scale).clamp(-clip, clip))
+            .collect())
+    }
+
+    pub fn denormalize_action(&self, action: Vec<f64>) -> Result<Vec<f64>> {
+        let Some((ref mean, ref std)) = self.action else {
+            return Ok(action);
+        };
+        if action.len() != mean.len() {
+            bail!(
+                "Action has {} values, the normalization statistics {}",
+                action.len(),
+                mean.len()
+            );
+        }
+        Ok(action.iter().zip(mean).zip(std).map(|((a, mean), std)| a * std + mean).collect())
+    }
+}
+
+/// Read the statistics' arrays from a `.npz` archive.
+fn stats_from_npz<R: Read + std::io::Seek>(reader: R) -> Result<StatsFile> {
+    let mut archive = zip::ZipArchive::new(reader).context("Not a .npz archive")?;
+    let mut arrays: HashMap<String, Vec<f64>> = HashMap::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(name) = entry.name().strip_suffix(".npy").map(str::to_string) else {
+            continue;
+        };
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        let values = read_npy(&bytes).with_context(|| format!("Bad array {}", name))?;
+        arrays.insert(name, values);
+    }
+
+    let scalar = |arrays: &mut HashMap<String, Vec<f64>>, name: &str| -> Result<Option<f64>> {
+        match arrays.remove(name).as_deref() {
+            None => Ok(None),
+            Some([value]) => Ok(Some(*value)),
+            Some(_) => bail!("{} must be a single value", name),
+        }
+    };
+    let stats = StatsFile {
+        obs_mean: arrays.remove("obs_mean").context("No obs_mean array")?,
+        obs_std: arrays.remove("obs_std"),
+        obs_var: arrays.remove("obs_var"),
+        action_mean: arrays.remove("action_mean"),
+        action_std: arrays.remove("action_std"),
+        clip_obs: scalar(&mut arrays, "clip_obs")?,
+        epsilon: scalar(&mut arrays, "epsilon")?,
+    };
+    if let Some(name) = arrays.keys().next() {
+        bail!("Unknown array {}", name);
+    }
+    Ok(stats)
+}
+
+/// Values of a little-endian float array in NumPy `.npy` format, flattened.
+fn read_npy(bytes: &[u8]) -> Result<Vec<f64>> {
+    let Some(rest) = bytes.strip_prefix(b"\x93NUMPY") else {
+        bail!("Not a .npy array");
+    };
+    // Version 1 has a 2-byte header length, versions 2 and 3 a 4-byte one
+    let (header_len, rest) = match rest {
+        [1, _, a, b, rest @ ..] => (u16::from_le_bytes([*a, *b]) as usize, rest),
+        [2 | 3, _, a, b, c, d, rest @ ..] => (u32::from_le_bytes([*a, *b, *c, *d]) as usize, rest),
+        _ => bail!("Unsupported .npy version"),
+    };
+    if rest.len() < header_len {
+        bail!("Truncated .npy header");
+    }
+    let (header, data) = rest.split_at(header_len);
+    let header = String::from_utf8_lossy(header);
+    if header.contains("'fortran_order': True") {
+        bail!("Fortran-ordered arrays are not supported");
+    }
+    let values: Vec<f64> = if header.contains("'descr': '<f8'") {
+        let mut chunks = data.chunks_exact(8);
+        let values = chunks.by_ref().map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect();
+        if !chunks.remainder().is_empty() {
+            bail!("Truncated .npy data");
+        }
+        values
+    } else if header.contains("'descr': '<f4'") {
+        let mut chunks = data.chunks_exact(4);
+        let values = chunks
+            .by_ref()
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64)
+            .collect();
+        if !chunks.remainder().is_empty() {
+            bail!("Truncated .npy data");
+        }
+        values
+    } else {
+        bail!("Expected a float32 or float64 array, got {}", header.trim());
+    };
+    Ok(values)
+}
+
+/// Policy wrapper that normalizes observations and denormalizes actions.
+pub struct NormalizedPolicy {
+    inner: Box<dyn PolicyInference>,
+    stats: Normalization,
+}
+
+impl NormalizedPolicy {
+    pub fn new(inner: Box<dyn PolicyInference>, stats: Normalization) -> Result<Self> {
+        match inner.input_dim() {
+            Some(dim) if dim != stats.obs_dim() => bail!(
+                "Normalization statistics are for {} observation values but the model takes {}",
+                stats.obs_dim(),
+                dim
+            ),
+            _ => Ok(Self { inner, stats }),
+        }
+    }
+
+    /// Wrap `inner` if statistics are stored next to `model_path`.
+    pub fn for_model(
+        inner: Box<dyn PolicyInference>,
+        model_path: &Path,
+    ) -> Result<Box<dyn PolicyInference>> {
+        let Some(path) = Normalization::path_for_model(model_path) else {
+            return Ok(inner);
+        };
+        let stats = Normalization::load(&path)?;
+        tracing::info!(
+            "Normalization statistics loaded from {}{}",
+            path.display(),
+            if stats.action.is_some() { " (with actions)" } else { "" }
+        );
+        Ok(Box::new(Self::new(inner, stats)?))
+    }
+}
+
+impl PolicyInference for NormalizedPolicy {
+    fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>> {
+        let observation = self.stats.normalize_observation(observation)?;
+        let action = self.inner.infer(&observation)?;
+        self.stats.denormalize_action(action)
+    }
+
+    fn input_dim(&self) -> Option<usize> {
+        Some(self.stats.obs_dim())
+    }
+
+    fn reset_state(&mut self) {
+        self.inner.reset_state();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    /// Echoes the observation.
+    struct Echo;
+
+    impl PolicyInference for Echo {
+        fn infer(&mut self, observation: &[f64]) -> Result<Vec<f64>> {
+            Ok(observation.to_vec())
+        }
+
+        fn input_dim(&self) -> Option<usize> {
+            Some(2)
+        }
+    }
+
+    #[test]
+    fn test_normalizes_observation_and_denormalizes_action() {
+        let stats: StatsFile = serde_json::from_str(
+            r#"{"obs_mean": [1.0, -2.0], "obs_std": [2.0, 0.5], "clip_obs": 3.0,
+                "action_mean": [0.5, 0.0], "action_std": [0.1, 1.0], "epsilon": 0}"#,
+        )
+        .unwrap();
+        let stats = Normalization::from_stats(stats).unwrap();
+        let mut policy = NormalizedPolicy::new(Box::new(Echo), stats).unwrap();
+        // (5 - 1) / 2 = 2 and (0 + 2) / 0.5 = 4, clipped to 3
+        assert_eq!(policy.infer(&[5.0, 0.0]).unwrap(), [0.7, 3.0]);
+        assert!(policy.infer(&[5.0]).is_err());
+
+        let zero_std = r#"{"obs_mean": [0.0], "obs_std": [0.0], "epsilon": 0}"#;
+        assert!(Normalization::from_stats(serde_json::from_str(zero_std).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_reads_npz_arrays() {
+        // As numpy.savez writes them: one .npy per array, float64 or float32
+        let npy = |descr: &str, shape: &str, data: Vec<u8>| {
+            let header =
+                format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}\n", descr, shape);
+            let mut out = b"\x93NUMPY\x01\x00".to_vec();
+            out.extend((header.len() as u16).to_le_bytes());
+            out.extend(header.as_bytes());
+            out.extend(data);
+            out
+        };
+        let f8 = |values: &[f64]| values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let f4 = |values: &[f32]| values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        for (name, array) in [
+            ("obs_mean.npy", npy("<f8", "(2,)", f8(&[1.0, 2.0]))),
+            ("obs_var.npy", npy("<f4", "(2,)", f4(&[4.0, 9.0]))),
+            ("clip_obs.npy", npy("<f8", "()", f8(&[10.0]))),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(&array).unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let stats = stats_from_npz(Cursor::new(bytes)).unwrap();
+        assert_eq!(stats.obs_mean, [1.0, 2.0]);
+        assert_eq!(stats.obs_var, Some(vec![4.0, 9.0]));
+        assert_eq!(stats.clip_obs, Some(10.0));
+        let stats = Normalization::from_stats(stats).unwrap();
+        let obs = stats.normalize_observation(&[3.0, 2.0]).unwrap();
+        assert!((obs[0] - 1.0).abs() < 1e-6 && obs[1] == 0.0);
+    }
+}
@@ -0,0 +1,353 @@
+//! Observation vector layout driven by a policy manifest.
+//!
+//! A retrained policy with a different observation spec ships a JSON manifest
+//! next to its ONNX file (`policy.onnx` → `policy.obs.json`) naming the
+//! components in order, with optional scaling:
+//!
+//! ```json
+//! {
+//!   "components": [
+//!     { "name": "gyro" },
+//!     { "name": "projected_gravity" },
+//!     { "name": "commands" },
+//!     { "name": "dof_pos" },
+//!     { "name": "dof_vel", "scale": 0.05 },
+//!     { "name": "action_history", "depth": 3 },
+//!     { "name": "phase" }
+//!   ],
+//!   "joints": ["left_hip_yaw", "left_hip_roll", "..."]
+//! }
+//! ```
+//!
+//! `joints` is the order of the model's joint values, in its observations
+//! and actions; without it, `MUJOCO_JOINTS_ORDER` is assumed. Joint values
+//! are remapped to and from the hardware order by name.
+//!
+//! Without a manifest, the original Open Duck Mini layout is used. The total
+//! dimension is checked against the model's input at load time.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use crate::imu::ImuData;
+use crate::orientation::ImuObservation;
+
+/// One named block of the observation vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentKind {
+    /// Gyroscope [x, y, z] (rad/s).
+    Gyro,
+    /// Raw accelerometer [x, y, z] (m/s²).
+    Accel,
+    /// Unit gravity vector in the body frame.
+    ProjectedGravity,
+    /// Orientation quaternion [w, x, y, z].
+    Orientation,
+    /// Roll, pitch, yaw (rad).
+    Euler,
+    /// The 7 gamepad commands.
+    Commands,
+    /// Joint positions relative to the init pose.
+    DofPos,
+    /// Joint velocities.
+    DofVel,
+    /// Previous actions, most recent first (`depth` of them).
+    ActionHistory,
+    /// Last motor targets.
+    MotorTargets,
+    /// Left/right foot contact.
+    FeetContacts,
+    /// Gait phase [cos, sin].
+    Phase,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_depth() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Component {
+    pub name: ComponentKind,
+
+    /// Multiplier applied to every value of the component.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+
+    /// Number of past actions (`action_history` only).
+    #[serde(default = "default_depth")]
+    pub depth: usize,
+}
+
+impl Component {
+    fn new(name: ComponentKind) -> Self {
+        Self {
+            name,
+            scale: default_scale(),
+            depth: default_depth(),
+        }
+    }
+
+    fn scaled(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Number of values this component contributes.
+    pub fn len(&self, num_dofs: usize) -> usize {
+        match self.name {
+            ComponentKind::Gyro
+            | ComponentKind::Accel
+            | ComponentKind::ProjectedGravity
+            | ComponentKind::Euler => 3,
+            ComponentKind::Orientation => 4,
+            ComponentKind::Commands => 7,
+            ComponentKind::DofPos | ComponentKind::DofVel | ComponentKind::MotorTargets => num_dofs,
+            ComponentKind::ActionHistory => num_dofs * self.depth,
+            ComponentKind::FeetContacts | ComponentKind::Phase => 2,
+        }
+    }
+}
+
+/// Declarative observation layout.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ObservationManifest {
+    pub components: Vec<Component>,
+
+    /// Joint order of the model (default: `JointMap::default_order`).
+    #[serde(default)]
+    pub joints: Option<Vec<String>>,
+}
+
+impl ObservationManifest {
+    /// The original Open Duck Mini layout, with the IMU block after the gyro
+    /// chosen by `imu`.
+    pub fn default_layout(imu: ImuObservation) -> Self {
+        use ComponentKind::*;
+        let imu = match imu {
+            ImuObservation::Accel => Accel,
+            ImuObservation::Gravity => ProjectedGravity,
+            ImuObservation::Quaternion => Orientation,
+            ImuObservation::Euler => Euler,
+        };
+        Self {
+            components: vec![
+                Component::new(Gyro),
+                Component::new(imu),
+                Component::new(Commands),
+                Component::new(DofPos),
+                Component::new(DofVel).scaled(0.05),
+                Component::new(ActionHistory).with_depth(3),
+                Component::new(MotorTargets),
+                Component::new(FeetContacts),
+                Component::new(Phase),
+            ],
+            joints: None,
+        }
+    }
+
+    /// Manifest path conventionally stored next to a model.
+    pub fn path_for_model(model_path: &Path) -> PathBuf {
+        model_path.with_extension("obs.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read observation manifest {}", path.display()))?;
+        let manifest: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse observation manifest {}", path.display()))?;
+        if manifest.components.is_empty() {
+            bail!("Observation manifest {} has no components", path.display());
+        }
+        Ok(manifest)
+    }
+
+    /// Total observation length.
+    pub fn dim(&self, num_dofs: usize) -> usize {
+        self.components.iter().map(|c| c.len(num_dofs)).sum()
+    }
+
+    /// Check the layout against the model's input dimension, if known.
+    pub fn validate(&self, num_dofs: usize, model_input_dim: Option<usize>) -> Result<()> {
+        if let Some(c) = self
+            .components
+            .iter()
+            .find(|c| c.name == ComponentKind::ActionHistory && c.depth == 0)
+        {
+            bail!("Observation component {:?} needs depth >= 1", c.name);
+        }
+
+        let dim = self.dim(num_dofs);
+        match model_input_dim {
+            Some(expected) if expected != dim => bail!(
+                "Observation layout has {} values but the model expects {}",
+                dim,
+                expected
+            ),
+            Some(_) => {}
+            None => tracing::warn!("Model input dimension is dynamic, cannot validate observation layout"),
+        }
+        Ok(())
+    }
+
+    fn history_depth(&self) -> usize {
+        self.components
+            .iter()
+            .filter(|c| c.name == ComponentKind::ActionHistory)
+            .map(|c| c.depth)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Per-tick sensor and state inputs to the observation.
+pub struct ObservationInputs<'a> {
+    pub imu: &'a ImuData,
+    pub commands: &'a [f64; 7],
+    pub dof_pos: &'a [f64],
+    pub init_pos: &'a [f64],
+    pub dof_vel: &'a [f64],
+    pub motor_targets: &'a [f64],
+    pub feet_contacts: &'a [f64; 2],
+    pub phase: &'a [f64; 2],
+}
+
+/// Builds observation vectors from a manifest and keeps the action history.
+pub struct ObservationBuilder {
+    manifest: ObservationManifest,
+    num_dofs: usize,
+    dim: usize,
+    history: VecDeque<Vec<f64>>,
+}
+
+impl ObservationBuilder {
+    pub fn new(manifest: ObservationManifest, num_dofs: usize) -> Self {
+        let depth = manifest.history_depth();
+        let dim = manifest.dim(num_dofs);
+        Self {
+            manifest,
+            num_dofs,
+            dim,
+            history: (0..depth).map(|_| vec![0.0; num_dofs]).collect(),
+        }
+    }
+
+    /// Observation length.
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Record the action just produced by the policy.
+    pub fn push_action(&mut self, action: &[f64]) {
+        if self.history.pop_back().is_some() {
+            self.history.push_front(action.to_vec());
+        }
+    }
+
+    /// Forget the action history, e.g. when a policy takes over control.
+    pub fn reset(&mut self) {
+        self.history.iter_mut().for_each(|a| a.fill(0.0));
+    }
+
+    /// Assemble the observation for this tick.
+    pub fn build(&self, inputs: &ObservationInputs) -> Vec<f64> {
+        let mut obs = Vec::with_capacity(self.dim);
+
+        for c in &self.manifest.components {
+            let start = obs.len();
+            match c.name {
+                ComponentKind::Gyro => obs.extend_from_slice(&inputs.imu.gyro),
+                ComponentKind::Accel => obs.extend_from_slice(&inputs.imu.accel),
+                ComponentKind::ProjectedGravity => obs.extend_from_slice(&inputs.imu.projected_gravity),
+                ComponentKind::Orientation => obs.extend_from_slice(&inputs.imu.orientation),
+                ComponentKind::Euler => obs.extend_from_slice(&inputs.imu.euler),
+                ComponentKind::Commands => obs.extend_from_slice(inputs.commands),
+                ComponentKind::DofPos => obs.extend(
+                    inputs.dof_pos.iter().zip(inputs.init_pos).map(|(p, init)| p - init),
+                ),
+                ComponentKind::DofVel => obs.extend_from_slice(inputs.dof_vel),
+                ComponentKind::ActionHistory => {
+                    for action in self.history.iter().take(c.depth) {
+                        obs.extend_from_slice(action);
+                    }
+                }
+                ComponentKind::MotorTargets => obs.extend_from_slice(inputs.motor_targets),
+                ComponentKind::FeetContacts => obs.extend_from_slice(inputs.feet_contacts),
+                ComponentKind::Phase => obs.extend_from_slice(inputs.phase),
+            }
+
+            if c.scale != 1.0 {
+                obs[start..].iter_mut().for_each(|v| *v *= c.scale);
+            }
+            debug_assert_eq!(obs.len() - start, c.len(self.num_dofs));
+        }
+
+        obs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs_with<'a>(imu: &'a ImuData, joints: &'a [f64]) -> ObservationInputs<'a> {
+        ObservationInputs {
+            imu,
+            commands: &[0.0; 7],
+            dof_pos: joints,
+            init_pos: joints,
+            dof_vel: joints,
+            motor_targets: joints,
+            feet_contacts: &[1.0, 0.0],
+            phase: &[1.0, 0.0],
+        }
+    }
+
+    #[test]
+    fn test_default_layout_matches_original_dimension() {
+        let manifest = ObservationManifest::default_layout(ImuObservation::Accel);
+        assert_eq!(manifest.dim(14), 101);
+        assert!(manifest.validate(14, Some(101)).is_ok());
+        assert!(manifest.validate(14, Some(100)).is_err());
+    }
+
+    #[test]
+    fn test_manifest_order_and_scaling() {
+        let manifest: ObservationManifest = serde_json::from_str(
+            r#"{"components": [
+                {"name": "feet_contacts"},
+                {"name": "dof_vel", "scale": 0.5},
+                {"name": "action_history", "depth": 2}
+            ]}"#,
+        )
+        .unwrap();
+
+        let mut builder = ObservationBuilder::new(manifest, 2);
+        assert_eq!(builder.dim(), 2 + 2 + 4);
+
+        builder.push_action(&[1.0, 1.0]);
+        builder.push_action(&[2.0, 2.0]);
+
+        let imu = ImuData::default();
+        let obs = builder.build(&inputs_with(&imu, &[4.0, 6.0]));
+        assert_eq!(obs, vec![1.0, 0.0, 2.0, 3.0, 2.0, 2.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_rejects_unknown_component() {
+        let result: Result<ObservationManifest, _> =
+            serde_json::from_str(r#"{"components": [{"name": "lidar"}]}"#);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,311 @@
+//! Orientation estimation from the raw IMU stream (Madgwick filter).
+//!
+//! Many trained policies expect a gravity vector or an orientation quaternion
+//! rather than raw accelerometer readings. The filter runs in the IMU worker
+//! thread on every sample, fusing gyro integration with the accelerometer's
+//! gravity reference, and its output is published alongside the raw data in
+//! `ImuData`. `MountingBias` then rotates the whole sample into the body frame
+//! when the IMU board sits at a slant.
+
+use crate::config::ImuMountingConfig;
+use crate::imu::ImuData;
+
+/// Default filter gain: how strongly the accelerometer corrects gyro drift.
+/// Higher converges faster but lets walking impacts disturb the estimate.
+pub const DEFAULT_BETA: f64 = 0.1;
+
+/// Quaternion as [w, x, y, z].
+pub type Quaternion = [f64; 4];
+
+pub const IDENTITY: Quaternion = [1.0, 0.0, 0.0, 0.0];
+
+/// Madgwick gradient-descent orientation filter (6-axis: gyro + accel).
+#[derive(Debug, Clone)]
+pub struct Madgwick {
+    beta: f64,
+    q: Quaternion,
+    initialized: bool,
+}
+
+impl Madgwick {
+    pub fn new(beta: f64) -> Self {
+        Self {
+            beta,
+            q: IDENTITY,
+            initialized: false,
+        }
+    }
+
+    /// Current orientation estimate (body to world).
+    pub fn quaternion(&self) -> Quaternion {
+        self.q
+    }
+
+    /// Fuse one sample: gyro in rad/s, accel in any unit, `dt` in seconds.
+    pub fn update(&mut self, gyro: [f64; 3], accel: [f64; 3], dt: f64) {
+        // Start from the accelerometer's roll/pitch instead of converging from level
+        if !self.initialized {
+            if let Some(q) = from_accel(accel) {
+                self.q = q;
+                self.initialized = true;
+            }
+            return;
+        }
+
+        let [q0, q1, q2, q3] = self.q;
+        let [gx, gy, gz] = gyro;
+
+        // Rate of change from the gyro: 0.5 * q ⊗ (0, ω)
+        let mut dq = [
+            0.5 * (-q1 * gx - q2 * gy - q3 * gz),
+            0.5 * (q0 * gx + q2 * gz - q3 * gy),
+            0.5 * (q0 * gy - q1 * gz + q3 * gx),
+            0.5 * (q0 * gz + q1 * gy - q2 * gx),
+        ];
+
+        // Gradient-descent correction toward the measured gravity direction
+        if let Some([ax, ay, az]) = normalize3(accel) {
+            let f1 = 2.0 * (q1 * q3 - q0 * q2) - ax;
+            let f2 = 2.0 * (q0 * q1 + q2 * q3) - ay;
+            let f3 = 2.0 * (0.5 - q1 * q1 - q2 * q2) - az;
+
+            let s = [
+                -2.0 * q2 * f1 + 2.0 * q1 * f2,
+                2.0 * q3 * f1 + 2.0 * q0 * f2 - 4.0 * q1 * f3,
+                -2.0 * q0 * f1 + 2.0 * q3 * f2 - 4.0 * q2 * f3,
+                2.0 * q1 * f1 + 2.0 * q2 * f2,
+            ];
+            if let Some(s) = normalize4(s) {
+                for (d, s) in dq.iter_mut().zip(s) {
+                    *d -= self.beta * s;
+                }
+            }
+        }
+
+        let mut q = self.q;
+        for (q, d) in q.iter_mut().zip(dq) {
+            *q += d * dt;
+        }
+        self.q = normalize4(q).unwrap_or(IDENTITY);
+    }
+
+    /// Fill the orientation fields of `data` from the current estimate.
+    pub fn annotate(&self, data: &mut ImuData) {
+        data.orientation = self.q;
+        data.euler = euler(self.q);
+        data.projected_gravity = projected_gravity(self.q);
+    }
+}
+
+impl Default for Madgwick {
+    fn default() -> Self {
+        Self::new(DEFAULT_BETA)
+    }
+}
+
+/// Rotation from the IMU frame to the body frame, for a board that isn't
+/// mounted square. Applied after the gyro bias is subtracted, since that is
+/// estimated in the IMU frame.
+#[derive(Debug, Clone, Copy)]
+pub struct MountingBias {
+    /// IMU orientation relative to the body.
+    q: Quaternion,
+}
+
+impl MountingBias {
+    pub fn new(config: &ImuMountingConfig) -> Self {
+        Self {
+            q: from_euler([
+                config.roll_bias.to_radians(),
+                config.pitch_bias.to_radians(),
+                config.yaw_bias.to_radians(),
+            ]),
+        }
+    }
+
+    /// Rotate the raw readings and the orientation of `data` into the body
+    /// frame.
+    pub fn apply(&self, data: &mut ImuData) {
+        data.gyro = rotate(self.q, data.gyro);
+        data.accel = rotate(self.q, data.accel);
+        data.orientation = normalize4(multiply(data.orientation, conjugate(self.q)))
+            .unwrap_or(IDENTITY);
+        data.euler = euler(data.orientation);
+        data.projected_gravity = projected_gravity(data.orientation);
+    }
+}
+
+/// Orientation with zero yaw whose gravity direction matches `accel`.
+fn from_accel(accel: [f64; 3]) -> Option<Quaternion> {
+    let [ax, ay, az] = normalize3(accel)?;
+    let roll = ay.atan2(az);
+    let pitch = (-ax).atan2((ay * ay + az * az).sqrt());
+
+    let (sr, cr) = (roll / 2.0).sin_cos();
+    let (sp, cp) = (pitch / 2.0).sin_cos();
+    Some([cr * cp, sr * cp, cr * sp, -sr * sp])
+}
+
+/// Euler angles [roll, pitch, yaw] (radians, ZYX convention).
+pub fn euler(q: Quaternion) -> [f64; 3] {
+    let [w, x, y, z] = q;
+    let roll = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+    let pitch = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin();
+    let yaw = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+    [roll, pitch, yaw]
+}
+
+/// Quaternion from Euler angles [roll, pitch, yaw] (radians, ZYX convention).
+pub fn from_euler([roll, pitch, yaw]: [f64; 3]) -> Quaternion {
+    let (sr, cr) = (roll / 2.0).sin_cos();
+    let (sp, cp) = (pitch / 2.0).sin_cos();
+    let (sy, cy) = (yaw / 2.0).sin_cos();
+    [
+        cr * cp * cy + sr * sp * sy,
+        sr * cp * cy - cr * sp * sy,
+        cr * sp * cy + sr * cp * sy,
+        cr * cp * sy - sr * sp * cy,
+    ]
+}
+
+/// World gravity direction (0, 0, -1) expressed in the body frame.
+pub fn projected_gravity(q: Quaternion) -> [f64; 3] {
+    let [w, x, y, z] = q;
+    [
+        -2.0 * (x * z - w * y),
+        -2.0 * (w * x + y * z),
+        -(w * w - x * x - y * y + z * z),
+    ]
+}
+
+/// Hamilton product `a * b`.
+pub fn multiply(a: Quaternion, b: Quaternion) -> Quaternion {
+    let [aw, ax, ay, az] = a;
+    let [bw, bx, by, bz] = b;
+    [
+        aw * bw - ax * bx - ay * by - az * bz,
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+    ]
+}
+
+pub fn conjugate([w, x, y, z]: Quaternion) -> Quaternion {
+    [w, -x, -y, -z]
+}
+
+/// Vector `v` rotated by `q`.
+pub fn rotate(q: Quaternion, [x, y, z]: [f64; 3]) -> [f64; 3] {
+    let [_, rx, ry, rz] = multiply(multiply(q, [0.0, x, y, z]), conjugate(q));
+    [rx, ry, rz]
+}
+
+fn normalize3(v: [f64; 3]) -> Option<[f64; 3]> {
+    let norm = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    (norm > 1e-9).then(|| [v[0] / norm, v[1] / norm, v[2] / norm])
+}
+
+fn normalize4(v: [f64; 4]) -> Option<[f64; 4]> {
+    let norm = v.iter().map(|c| c * c).sum::<f64>().sqrt();
+    (norm > 1e-9).then(|| v.map(|c| c / norm))
+}
+
+/// Which IMU-derived values the default observation layout carries after the
+/// gyro. Must match what the policy was trained with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ImuObservation {
+    /// Raw accelerometer (3), as in the original Open Duck Mini policies.
+    #[default]
+    Accel,
+    /// Unit gravity vector in the body frame (3).
+    Gravity,
+    /// Orientation quaternion [w, x, y, z] (4).
+    Quaternion,
+    /// Roll, pitch, yaw (3).
+    Euler,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &[f64], b: &[f64], tol: f64) {
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() < tol, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_level_and_still_stays_identity() {
+        let mut filter = Madgwick::default();
+        for _ in 0..100 {
+            filter.update([0.0; 3], [0.0, 0.0, 9.81], 0.02);
+        }
+        assert_close(&filter.quaternion(), &IDENTITY, 1e-9);
+        assert_close(&projected_gravity(filter.quaternion()), &[0.0, 0.0, -1.0], 1e-9);
+    }
+
+    #[test]
+    fn test_initializes_from_tilted_accel() {
+        let mut filter = Madgwick::default();
+        // Rolled 30° about x: gravity reference tilts into +y
+        let roll = 30f64.to_radians();
+        filter.update([0.0; 3], [0.0, roll.sin(), roll.cos()], 0.02);
+
+        let [r, p, _] = euler(filter.quaternion());
+        assert!((r - roll).abs() < 1e-9);
+        assert!(p.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pitch_sign_matches_gravity() {
+        let mut filter = Madgwick::default();
+        let pitch = 20f64.to_radians();
+        let accel = [-pitch.sin(), 0.0, pitch.cos()];
+        filter.update([0.0; 3], accel, 0.02);
+
+        assert!((euler(filter.quaternion())[1] - pitch).abs() < 1e-9);
+        // Projected gravity is the opposite of the accelerometer's up direction
+        assert_close(&projected_gravity(filter.quaternion()), &accel.map(|a| -a), 1e-9);
+    }
+
+    #[test]
+    fn test_mounting_bias_levels_a_slanted_board() {
+        let config = ImuMountingConfig {
+            roll_bias: 4.0,
+            pitch_bias: -7.0,
+            yaw_bias: 0.0,
+        };
+        let mount = from_euler([4f64.to_radians(), -7f64.to_radians(), 0.0]);
+        assert_close(&euler(mount), &[4f64.to_radians(), -7f64.to_radians(), 0.0], 1e-9);
+
+        // Level body turning in place, seen by the slanted IMU
+        let body_accel = [0.0, 0.0, 9.81];
+        let body_gyro = [0.0, 0.0, 0.5];
+        let mut data = ImuData {
+            gyro: rotate(conjugate(mount), body_gyro),
+            accel: rotate(conjugate(mount), body_accel),
+            ..ImuData::default()
+        };
+        let mut filter = Madgwick::default();
+        filter.update([0.0; 3], data.accel, 0.02);
+        filter.annotate(&mut data);
+        assert!(data.tilt() > 0.1);
+
+        MountingBias::new(&config).apply(&mut data);
+        assert_close(&data.accel, &body_accel, 1e-9);
+        assert_close(&data.gyro, &body_gyro, 1e-9);
+        assert_close(&data.projected_gravity, &[0.0, 0.0, -1.0], 1e-9);
+        assert!(data.tilt() < 1e-6);
+    }
+
+    #[test]
+    fn test_integrates_yaw_rate() {
+        let mut filter = Madgwick::new(0.0);
+        filter.update([0.0; 3], [0.0, 0.0, 1.0], 0.01);
+        for _ in 0..100 {
+            filter.update([0.0, 0.0, 0.5], [0.0, 0.0, 1.0], 0.01);
+        }
+        assert!((euler(filter.quaternion())[2] - 0.5).abs() < 1e-3);
+    }
+}
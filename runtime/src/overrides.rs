@@ -0,0 +1,162 @@
+//! Persisted overrides for values adjusted while the robot runs.
+//!
+//! Tweaks made at runtime (the gait offset on the D-pad, volume, tuned gains)
+//! would otherwise be lost on restart. They are kept in a small JSON file next
+//! to the duck config (`duck_config.json` → `duck_config.overrides.json`)
+//! whose keys mirror the config's, and which is layered on top of it at load
+//! time:
+//!
+//! ```json
+//! { "phase_frequency_factor_offset": 0.05, "duty_cycle": { "rest_kp": 6.0 } }
+//! ```
+//!
+//! Every change rewrites the file atomically (temp file + rename), so a power
+//! cut mid-write leaves either the old or the new overrides, never a torn file.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Overrides layered above the duck config.
+#[derive(Debug, Clone)]
+pub struct Overrides {
+    path: PathBuf,
+    values: Map<String, Value>,
+}
+
+impl Overrides {
+    /// Overrides path conventionally stored next to a config file.
+    pub fn path_for_config(config_path: &Path) -> PathBuf {
+        config_path.with_extension("overrides.json")
+    }
+
+    /// Load overrides from `path`; a missing file means no overrides.
+    pub fn load(path: &Path) -> Result<Self> {
+        let values = if path.exists() {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read overrides {}", path.display()))?;
+            match serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse overrides {}", path.display()))?
+            {
+                Value::Object(map) => map,
+                _ => bail!("Overrides {} must be a JSON object", path.display()),
+            }
+        } else {
+            Map::new()
+        };
+
+        if !values.is_empty() {
+            tracing::info!("Loaded {} override(s) from {}", values.len(), path.display());
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            values,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Merge the overrides into a config document, nested objects key by key.
+    pub fn apply(&self, config: &mut Value) {
+        if !config.is_object() {
+            *config = Value::Object(Map::new());
+        }
+        if let Value::Object(target) = config {
+            merge(target, &self.values);
+        }
+    }
+
+    /// Set a value by dotted key (e.g. `duty_cycle.rest_kp`) and persist.
+    pub fn set(&mut self, key: &str, value: impl Serialize) -> Result<()> {
+        let value = serde_json::to_value(value)?;
+
+        let mut parts = key.split('.').peekable();
+        let mut map = &mut self.values;
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                map.insert(part.to_string(), value);
+                break;
+            }
+            let entry = map
+                .entry(part.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            map = entry.as_object_mut().expect("entry is an object");
+        }
+
+        self.save()
+    }
+
+    /// Write the file atomically.
+    fn save(&self) -> Result<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        let contents = serde_json::to_vec_pretty(&self.values)?;
+
+        let mut file = fs::File::create(&tmp)
+            .with_context(|| format!("Failed to create {}", tmp.display()))?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to replace {}", self.path.display()))?;
+
+        tracing::debug!("Overrides saved to {}", self.path.display());
+        Ok(())
+    }
+}
+
+fn merge(target: &mut Map<String, Value>, overrides: &Map<String, Value>) {
+    for (key, value) in overrides {
+        match (target.get_mut(key), value) {
+            (Some(Value::Object(t)), Value::Object(o)) => merge(t, o),
+            _ => {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_set_persists_and_layers_over_config() {
+        let dir = std::env::temp_dir().join(format!("odr-overrides-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = Overrides::path_for_config(&dir.join("duck_config.json"));
+        assert_eq!(path.file_name().unwrap(), "duck_config.overrides.json");
+
+        let mut overrides = Overrides::load(&path).unwrap();
+        assert!(overrides.is_empty());
+        overrides.set("phase_frequency_factor_offset", 0.05).unwrap();
+        overrides.set("duty_cycle.rest_kp", 6.0).unwrap();
+
+        let reloaded = Overrides::load(&path).unwrap();
+        let mut config = json!({
+            "phase_frequency_factor_offset": 0.0,
+            "duty_cycle": { "enabled": true, "rest_kp": 8.0 }
+        });
+        reloaded.apply(&mut config);
+        assert_eq!(
+            config,
+            json!({
+                "phase_frequency_factor_offset": 0.05,
+                "duty_cycle": { "enabled": true, "rest_kp": 6.0 }
+            })
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,18 +1,48 @@
 //! GPIO peripherals — feet contacts, LED eyes, projector, antennas.
 //!
 //! Replaces `feet_contacts.py`, `eyes.py`, `projector.py`, `antennas.py`.
-//! All GPIO access goes through the `rppal` crate (Linux-only).
+//! All GPIO access goes through the `rppal` crate (Linux-only). Feet can
+//! also be force-sensitive resistors on an ADC; either way they are sampled
+//! and filtered on their own thread (`contact_filter`). The antenna servos
+//! run on the hardware PWM channels, animated from a background thread. The
+//! eyes are single LEDs or WS2812 rings driven over SPI (`ws2812`).
+
+use crate::contact_filter::ContactEvent;
+
+/// Trait for foot contact sensors (supports dependency injection for testing).
+pub trait FeetContactsReader: Send {
+    /// Returns [left_contact, right_contact] as f64: 0.0 or 1.0 from
+    /// switches, anywhere in 0..1 from FSRs.
+    fn get(&self) -> [f64; 2];
+
+    /// Touchdowns and liftoffs since the last call.
+    fn events(&self) -> Vec<ContactEvent> {
+        Vec::new()
+    }
+}
 
 // ── Hardware implementations (Linux only — requires rppal / GPIO) ──
 
 #[cfg(target_os = "linux")]
 mod hw {
-    use anyhow::{Context, Result};
+    use super::FeetContactsReader;
+    use crate::adc;
+    use crate::antenna::{AntennaAnimation, Animator};
+    use crate::contact_filter::{ContactEvent, FeetSampler};
+    use crate::eye_expression::{EyeEngine, EyeExpression};
+    use crate::ws2812;
+    use crate::config::{EyeLedsConfig, EyeLedsKind};
+    use crate::config::{FeetContactsConfig, FeetContactsSource, FsrConfig};
+    use anyhow::{bail, Context, Result};
+    use crossbeam_channel::{unbounded, Sender};
     use rppal::gpio::{Gpio, InputPin, OutputPin};
-    use std::sync::atomic::{AtomicBool, Ordering};
+    use rppal::i2c::I2c;
+    use rppal::pwm::{Channel, Polarity, Pwm};
+    use rppal::spi::{Bus, Mode, SlaveSelect, Spi};
+    use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
     use std::sync::Arc;
     use std::thread;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     // GPIO pin assignments (BCM numbering, matching the Python runtime)
     const LEFT_FOOT_PIN: u8 = 22;
@@ -20,70 +50,277 @@ mod hw {
     const LEFT_EYE_PIN: u8 = 24;
     const RIGHT_EYE_PIN: u8 = 23;
     const PROJECTOR_PIN: u8 = 25;
-    const LEFT_ANTENNA_PIN: u8 = 13;
-    const RIGHT_ANTENNA_PIN: u8 = 12;
+    // Antennas on GPIO 13 (PWM1) and GPIO 12 (PWM0)
+    const LEFT_ANTENNA_PWM: Channel = Channel::Pwm1;
+    const RIGHT_ANTENNA_PWM: Channel = Channel::Pwm0;
+
+    /// Time between eye brightness updates.
+    const EYES_PERIOD: Duration = Duration::from_millis(20);
+
+    /// Hobby servo frame; the antennas are also updated once per frame.
+    const SERVO_PERIOD: Duration = Duration::from_millis(20);
+
+    /// MCP3008 clock, within its 1.35 MHz limit at 3.3 V.
+    const MCP3008_CLOCK_HZ: u32 = 1_000_000;
+
+    /// Open `/dev/spidev<bus>.<chip_select>`.
+    pub(crate) fn open_spi(bus: u8, chip_select: u8, clock_hz: u32, mode: Mode) -> Result<Spi> {
+        let spi_bus = match bus {
+            0 => Bus::Spi0,
+            1 => Bus::Spi1,
+            2 => Bus::Spi2,
+            3 => Bus::Spi3,
+            4 => Bus::Spi4,
+            5 => Bus::Spi5,
+            6 => Bus::Spi6,
+            n => bail!("No SPI bus {n}"),
+        };
+        let slave_select = match chip_select {
+            0 => SlaveSelect::Ss0,
+            1 => SlaveSelect::Ss1,
+            2 => SlaveSelect::Ss2,
+            n => bail!("No SPI chip select {n}"),
+        };
+        Spi::new(spi_bus, slave_select, clock_hz, mode)
+            .with_context(|| format!("Failed to open /dev/spidev{bus}.{chip_select}"))
+    }
 
     // ── Feet Contact Sensors ──
 
-    /// Binary foot contact sensors (pull-up GPIOs).
+    /// Foot contact sensors, sampled and filtered on a background thread:
+    /// switches on pull-up GPIOs, or FSRs on an ADC.
     pub struct FeetContacts {
-        left: InputPin,
-        right: InputPin,
+        sampler: FeetSampler,
     }
 
     impl FeetContacts {
-        pub fn new() -> Result<Self> {
-            let gpio = Gpio::new().context("Failed to initialize GPIO")?;
-            let left = gpio
-                .get(LEFT_FOOT_PIN)
-                .context("Failed to get left foot pin")?
-                .into_input_pullup();
-            let right = gpio
-                .get(RIGHT_FOOT_PIN)
-                .context("Failed to get right foot pin")?
-                .into_input_pullup();
+        pub fn new(config: &FeetContactsConfig) -> Result<Self> {
+            let sampler = match config.source {
+                FeetContactsSource::Gpio => {
+                    let (left, right) = open_switches()?;
+                    // Active low: pin LOW = foot in contact
+                    let read = move || {
+                        Ok([&left, &right].map(|pin| if pin.is_low() { 1.0 } else { 0.0 }))
+                    };
+                    FeetSampler::start(config, true, read)
+                }
+                _ => {
+                    let mut fsrs = AdcFeet::open(config)?;
+                    FeetSampler::start(config, false, move || fsrs.read())
+                }
+            };
+            tracing::info!("Feet contact sensors initialized ({:?})", config.source);
+            Ok(Self { sampler })
+        }
+    }
 
-            tracing::info!("Feet contact sensors initialized");
-            Ok(Self { left, right })
+    impl FeetContactsReader for FeetContacts {
+        fn get(&self) -> [f64; 2] {
+            self.sampler.get()
         }
 
-        /// Returns [left_contact, right_contact] as f64 (0.0 or 1.0).
-        pub fn get(&self) -> [f64; 2] {
-            // Active low: pin LOW = foot in contact
-            let left = if self.left.is_low() { 1.0 } else { 0.0 };
-            let right = if self.right.is_low() { 1.0 } else { 0.0 };
-            [left, right]
+        fn events(&self) -> Vec<ContactEvent> {
+            self.sampler.events()
+        }
+    }
+
+    fn open_switches() -> Result<(InputPin, InputPin)> {
+        let gpio = Gpio::new().context("Failed to initialize GPIO")?;
+        let left = gpio
+            .get(LEFT_FOOT_PIN)
+            .context("Failed to get left foot pin")?
+            .into_input_pullup();
+        let right = gpio
+            .get(RIGHT_FOOT_PIN)
+            .context("Failed to get right foot pin")?
+            .into_input_pullup();
+        Ok((left, right))
+    }
+
+    /// An ADC read as a fraction of its full scale.
+    trait Adc: Send {
+        fn read(&mut self, channel: u8) -> Result<f64>;
+    }
+
+    struct Mcp3008 {
+        spi: Spi,
+    }
+
+    impl Adc for Mcp3008 {
+        fn read(&mut self, channel: u8) -> Result<f64> {
+            let mut frame = [0u8; 3];
+            self.spi
+                .transfer(&mut frame, &adc::mcp3008_command(channel))
+                .context("MCP3008 read failed")?;
+            Ok(adc::mcp3008_value(&frame))
+        }
+    }
+
+    struct Ads1115 {
+        i2c: I2c,
+    }
+
+    impl Adc for Ads1115 {
+        fn read(&mut self, channel: u8) -> Result<f64> {
+            let config = adc::ads1115_single_shot(channel).to_be_bytes();
+            self.i2c
+                .block_write(adc::ADS1115_CONFIG, &config)
+                .context("ADS1115 write failed")?;
+            thread::sleep(adc::ADS1115_CONVERSION_TIME);
+            let mut value = [0u8; 2];
+            self.i2c
+                .block_read(adc::ADS1115_CONVERSION, &mut value)
+                .context("ADS1115 read failed")?;
+            Ok(adc::ads1115_value(value))
+        }
+    }
+
+    /// Force-sensitive resistors under the feet, read on an ADC and mapped
+    /// to 0..1 with each foot's calibration.
+    struct AdcFeet {
+        chip: Box<dyn Adc>,
+        feet: [FsrConfig; 2],
+    }
+
+    impl AdcFeet {
+        fn open(config: &FeetContactsConfig) -> Result<Self> {
+            let (mut chip, channels): (Box<dyn Adc>, u8) = match config.source {
+                FeetContactsSource::Mcp3008 => {
+                    let spi = open_spi(
+                        config.spi_bus,
+                        config.spi_chip_select,
+                        MCP3008_CLOCK_HZ,
+                        Mode::Mode0,
+                    )?;
+                    (Box::new(Mcp3008 { spi }), adc::MCP3008_CHANNELS)
+                }
+                FeetContactsSource::Ads1115 => {
+                    let mut i2c = I2c::new().context("Failed to open I2C bus")?;
+                    i2c.set_slave_address(config.i2c_address)
+                        .context("Failed to set I2C slave address")?;
+                    (Box::new(Ads1115 { i2c }), adc::ADS1115_CHANNELS)
+                }
+                FeetContactsSource::Gpio => bail!("GPIO feet have no ADC"),
+            };
+            let feet = [config.left.clone(), config.right.clone()];
+            for (side, fsr) in ["left", "right"].iter().zip(&feet) {
+                if fsr.channel >= channels {
+                    bail!("{:?} has no channel {} ({side} foot)", config.source, fsr.channel);
+                }
+                if fsr.saturation <= fsr.zero {
+                    bail!("The {side} foot's FSR saturation must be above its zero");
+                }
+            }
+
+            // Logged so `zero` can be set from a duck held in the air
+            let left = chip.read(feet[0].channel)?;
+            let right = chip.read(feet[1].channel)?;
+            tracing::info!(
+                "FSR feet on {:?} (readings now: left {:.3}, right {:.3})",
+                config.source,
+                left,
+                right
+            );
+            Ok(Self { chip, feet })
+        }
+
+        fn read(&mut self) -> Result<[f64; 2]> {
+            let left = self.chip.read(self.feet[0].channel)?;
+            let right = self.chip.read(self.feet[1].channel)?;
+            Ok([
+                adc::fsr_contact(left, &self.feet[0]),
+                adc::fsr_contact(right, &self.feet[1]),
+            ])
         }
     }
 
     // ── LED Eyes ──
 
-    /// Blinking LED eyes running in a background thread.
+    /// LED eyes showing expressions from a background thread: single LEDs
+    /// dimmed with software PWM, or WS2812 rings. Expressions are queued
+    /// (`eye_expression`).
     pub struct Eyes {
         stop_flag: Arc<AtomicBool>,
+        offline: Arc<AtomicBool>,
+        low_battery: Arc<AtomicBool>,
+        brightness: Arc<AtomicU8>,
+        expressions: Sender<EyeExpression>,
         _thread: thread::JoinHandle<()>,
     }
 
     impl Eyes {
-        pub fn new() -> Result<Self> {
-            let gpio = Gpio::new()?;
-            let left_eye = gpio.get(LEFT_EYE_PIN)?.into_output();
-            let right_eye = gpio.get(RIGHT_EYE_PIN)?.into_output();
+        pub fn new(config: &EyeLedsConfig) -> Result<Self> {
+            let mut output: Box<dyn EyeOutput> = match config.kind {
+                EyeLedsKind::Gpio => Box::new(GpioEyes::new()?),
+                EyeLedsKind::Neopixel => Box::new(NeopixelEyes::new(config)?),
+            };
 
             let stop_flag = Arc::new(AtomicBool::new(false));
             let flag = stop_flag.clone();
+            let offline = Arc::new(AtomicBool::new(false));
+            let worker_offline = offline.clone();
+            let low_battery = Arc::new(AtomicBool::new(false));
+            let worker_low_battery = low_battery.clone();
+            let brightness = Arc::new(AtomicU8::new(100));
+            let worker_brightness = brightness.clone();
+            let (expressions, expressions_rx) = unbounded();
 
             let handle = thread::spawn(move || {
-                eyes_worker(left_eye, right_eye, flag);
+                let mut engine = EyeEngine::new(Instant::now());
+                while !flag.load(Ordering::Relaxed) {
+                    for expression in expressions_rx.try_iter() {
+                        if !engine.request(expression) {
+                            tracing::debug!("Eye expression queue full, dropped {:?}", expression);
+                        }
+                    }
+                    engine.set_offline(worker_offline.load(Ordering::Relaxed));
+                    engine.set_low_battery(worker_low_battery.load(Ordering::Relaxed));
+                    engine.set_brightness(worker_brightness.load(Ordering::Relaxed) as f64 / 100.0);
+
+                    let level = engine.level(Instant::now());
+                    output.show(&engine, level);
+                    thread::sleep(EYES_PERIOD);
+                }
+                output.off();
             });
 
-            tracing::info!("LED eyes initialized");
+            tracing::info!("LED eyes initialized ({:?})", config.kind);
             Ok(Self {
                 stop_flag,
+                offline,
+                low_battery,
+                brightness,
+                expressions,
                 _thread: handle,
             })
         }
 
+        /// Show whether the backend is unreachable.
+        pub fn set_offline(&self, offline: bool) {
+            self.offline.store(offline, Ordering::Relaxed);
+        }
+
+        /// Show that the battery is low.
+        pub fn set_low_battery(&self, low: bool) {
+            self.low_battery.store(low, Ordering::Relaxed);
+        }
+
+        /// Set the brightness while the eyes are open (0-1).
+        pub fn set_brightness(&self, brightness: f64) {
+            let percent = (brightness.clamp(0.0, 1.0) * 100.0).round() as u8;
+            self.brightness.store(percent, Ordering::Relaxed);
+        }
+
+        /// Queue an expression, after those already waiting.
+        pub fn set_expression(&self, expression: EyeExpression) {
+            let _ = self.expressions.send(expression);
+        }
+
+        /// Blink now instead of waiting for the next random blink.
+        pub fn blink(&self) {
+            self.set_expression(EyeExpression::Blink);
+        }
+
         pub fn stop(&self) {
             self.stop_flag.store(true, Ordering::Relaxed);
         }
@@ -95,27 +332,140 @@ mod hw {
         }
     }
 
-    fn eyes_worker(mut left: OutputPin, mut right: OutputPin, stop: Arc<AtomicBool>) {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+    /// Where the eye worker draws: single LEDs or a WS2812 chain.
+    trait EyeOutput: Send {
+        /// Show `level` ([left, right], 0-1) of what `engine` is showing.
+        fn show(&mut self, engine: &EyeEngine, level: [f64; 2]);
+        fn off(&mut self);
+    }
 
-        while !stop.load(Ordering::Relaxed) {
-            // Blink: eyes off briefly
-            left.set_low();
-            right.set_low();
-            thread::sleep(Duration::from_millis(100));
+    struct GpioEyes {
+        pins: [OutputPin; 2],
+        /// Brightness (%) each pin was last set to.
+        lit: [Option<u8>; 2],
+    }
 
-            // Eyes on
-            left.set_high();
-            right.set_high();
+    impl GpioEyes {
+        fn new() -> Result<Self> {
+            let gpio = Gpio::new()?;
+            let left_eye = gpio.get(LEFT_EYE_PIN)?.into_output();
+            let right_eye = gpio.get(RIGHT_EYE_PIN)?.into_output();
+            Ok(Self {
+                pins: [left_eye, right_eye],
+                lit: [None; 2],
+            })
+        }
+    }
 
-            // Random interval before next blink
-            let interval_ms = rng.gen_range(1000..4000);
-            thread::sleep(Duration::from_millis(interval_ms));
+    impl EyeOutput for GpioEyes {
+        fn show(&mut self, _engine: &EyeEngine, level: [f64; 2]) {
+            for ((pin, lit), level) in self.pins.iter_mut().zip(&mut self.lit).zip(level) {
+                let percent = (level * 100.0).round() as u8;
+                if *lit != Some(percent) {
+                    *lit = Some(percent);
+                    if percent == 0 {
+                        eye_off(pin);
+                    } else {
+                        eye_on(pin, percent);
+                    }
+                }
+            }
         }
 
-        left.set_low();
-        right.set_low();
+        fn off(&mut self) {
+            for pin in &mut self.pins {
+                eye_off(pin);
+            }
+        }
+    }
+
+    /// Two WS2812 rings and optional status pixels on one SPI chain.
+    struct NeopixelEyes {
+        spi: Spi,
+        pixels_per_eye: usize,
+        status_pixels: usize,
+        color: [u8; 3],
+        max_brightness: f64,
+        /// Last frame sent, so unchanged frames aren't resent.
+        last: Vec<u8>,
+        failing: bool,
+    }
+
+    impl NeopixelEyes {
+        fn new(config: &EyeLedsConfig) -> Result<Self> {
+            let spi = open_spi(
+                config.spi_bus,
+                config.spi_chip_select,
+                ws2812::SPI_CLOCK_HZ,
+                Mode::Mode0,
+            )?;
+            Ok(Self {
+                spi,
+                pixels_per_eye: config.pixels_per_eye,
+                status_pixels: config.status_pixels,
+                color: config.color,
+                max_brightness: config.max_brightness.clamp(0.0, 1.0),
+                last: Vec::new(),
+                failing: false,
+            })
+        }
+
+        fn send(&mut self, pixels: &[[u8; 3]]) {
+            let frame = ws2812::encode(pixels);
+            if frame == self.last {
+                return;
+            }
+            match self.spi.write(&frame) {
+                Ok(_) => self.failing = false,
+                Err(e) => {
+                    if !self.failing {
+                        tracing::warn!("NeoPixel write failed: {}", e);
+                    }
+                    self.failing = true;
+                }
+            }
+            self.last = frame;
+        }
+    }
+
+    impl EyeOutput for NeopixelEyes {
+        fn show(&mut self, engine: &EyeEngine, level: [f64; 2]) {
+            let expression = engine.showing();
+            let mut pixels = Vec::with_capacity(2 * self.pixels_per_eye + self.status_pixels);
+            for level in level {
+                pixels.extend(ws2812::ring(
+                    expression,
+                    level * self.max_brightness,
+                    self.color,
+                    self.pixels_per_eye,
+                ));
+            }
+            let status = ws2812::status_color(engine.offline(), engine.low_battery());
+            let status = ws2812::scale(status, self.max_brightness);
+            pixels.resize(pixels.len() + self.status_pixels, status);
+            self.send(&pixels);
+        }
+
+        fn off(&mut self) {
+            let pixels = vec![[0, 0, 0]; 2 * self.pixels_per_eye + self.status_pixels];
+            self.send(&pixels);
+        }
+    }
+
+    fn eye_off(pin: &mut OutputPin) {
+        let _ = pin.clear_pwm();
+        pin.set_low();
+    }
+
+    /// Light an eye at `percent` brightness: fully on, or software PWM.
+    fn eye_on(pin: &mut OutputPin, percent: u8) {
+        if percent >= 100 {
+            let _ = pin.clear_pwm();
+            pin.set_high();
+        } else if let Err(e) = pin.set_pwm_frequency(200.0, percent as f64 / 100.0) {
+            tracing::debug!("Eye PWM failed: {}", e);
+            pin.set_high();
+        }
     }
 
     // ── Projector ──
@@ -136,14 +486,25 @@ mod hw {
 
         pub fn switch(&mut self) {
             self.is_on = !self.is_on;
-            if self.is_on {
+            self.restore();
+        }
+
+        /// Drive the lamp without changing its switched state.
+        pub fn flash(&mut self, on: bool) {
+            if on {
                 self.pin.set_high();
             } else {
                 self.pin.set_low();
             }
         }
 
+        /// Back to the switched state after flashing.
+        pub fn restore(&mut self) {
+            self.flash(self.is_on);
+        }
+
         pub fn stop(&mut self) {
+            self.is_on = false;
             self.pin.set_low();
         }
     }
@@ -156,71 +517,130 @@ mod hw {
 
     // ── Antennas (PWM Servos) ──
 
-    /// PWM-controlled antenna servos.
+    enum AntennaCommand {
+        Rest([f64; 2]),
+        Play(AntennaAnimation),
+        Stop,
+    }
+
+    /// Antenna servos on the hardware PWM channels (`dtoverlay=pwm-2chan`
+    /// with GPIO 12 and 13), animated by a background thread so the control
+    /// loop only queues commands.
     pub struct Antennas {
-        left: OutputPin,
-        right: OutputPin,
+        commands: Sender<AntennaCommand>,
+        rest: [f64; 2],
+        stop_flag: Arc<AtomicBool>,
+        _thread: thread::JoinHandle<()>,
     }
 
     impl Antennas {
         pub fn new() -> Result<Self> {
-            let gpio = Gpio::new()?;
-            let left = gpio.get(LEFT_ANTENNA_PIN)?.into_output();
-            let right = gpio.get(RIGHT_ANTENNA_PIN)?.into_output();
+            let left = open_servo(LEFT_ANTENNA_PWM).context("Failed to open left antenna PWM")?;
+            let right =
+                open_servo(RIGHT_ANTENNA_PWM).context("Failed to open right antenna PWM")?;
+
+            let (commands, commands_rx) = unbounded();
+            let stop_flag = Arc::new(AtomicBool::new(false));
+            let flag = stop_flag.clone();
+            let handle = thread::spawn(move || {
+                let mut animator = Animator::new();
+                let mut written = None;
+                let mut failing = false;
+                while !flag.load(Ordering::Relaxed) {
+                    let now = Instant::now();
+                    for command in commands_rx.try_iter() {
+                        match command {
+                            AntennaCommand::Rest(rest) => animator.set_rest(rest),
+                            AntennaCommand::Play(animation) => animator.play(animation, now),
+                            AntennaCommand::Stop => animator.stop(),
+                        }
+                    }
+                    let pose = animator.pose(now);
+                    if written == Some(pose) {
+                        thread::sleep(SERVO_PERIOD);
+                        continue;
+                    }
+                    let [l, r] = pose;
+                    let result = set_antenna_position(&left, l, 1.0)
+                        .and_then(|()| set_antenna_position(&right, r, -1.0));
+                    match result {
+                        Ok(()) => {
+                            written = Some(pose);
+                            failing = false;
+                        }
+                        Err(e) => {
+                            if !failing {
+                                tracing::warn!("Antenna PWM write failed: {}", e);
+                            }
+                            failing = true;
+                        }
+                    }
+                    thread::sleep(SERVO_PERIOD);
+                }
+                let _ = set_antenna_position(&left, 0.0, 1.0);
+                let _ = set_antenna_position(&right, 0.0, -1.0);
+            });
 
             tracing::info!("Antenna servos initialized");
-            Ok(Self { left, right })
+            Ok(Self {
+                commands,
+                rest: [0.0, 0.0],
+                stop_flag,
+                _thread: handle,
+            })
         }
 
-        /// Set left antenna position (-1.0 to 1.0).
-        pub fn set_position_left(&mut self, position: f64) {
-            set_antenna_position(&mut self.left, position, 1.0);
+        /// Resting [left, right] position (-1.0 to 1.0), between animations.
+        pub fn set_rest(&mut self, rest: [f64; 2]) {
+            if rest != self.rest {
+                self.rest = rest;
+                let _ = self.commands.send(AntennaCommand::Rest(rest));
+            }
         }
 
-        /// Set right antenna position (-1.0 to 1.0).
-        pub fn set_position_right(&mut self, position: f64) {
-            set_antenna_position(&mut self.right, position, -1.0);
+        pub fn play(&self, animation: AntennaAnimation) {
+            let _ = self.commands.send(AntennaCommand::Play(animation));
         }
 
+        /// Center the antennas, cutting any animation short.
         pub fn stop(&mut self) {
-            set_antenna_position(&mut self.left, 0.0, 1.0);
-            set_antenna_position(&mut self.right, 0.0, -1.0);
+            self.rest = [0.0, 0.0];
+            let _ = self.commands.send(AntennaCommand::Stop);
         }
     }
 
     impl Drop for Antennas {
         fn drop(&mut self) {
-            self.stop();
+            self.stop_flag.store(true, Ordering::Relaxed);
         }
     }
 
-    /// Convert a -1.0..1.0 value to a PWM duty cycle for a hobby servo.
-    ///
-    /// Uses software PWM bit-banging via rppal output pins.
-    /// For production use, consider rppal's hardware PWM channels.
-    fn set_antenna_position(pin: &mut OutputPin, value: f64, sign: f64) {
+    fn open_servo(channel: Channel) -> Result<Pwm> {
+        let center = Duration::from_micros(1500);
+        Ok(Pwm::with_period(channel, SERVO_PERIOD, center, Polarity::Normal, true)?)
+    }
+
+    /// Set a hobby servo's pulse from a -1.0..1.0 value.
+    fn set_antenna_position(pwm: &Pwm, value: f64, sign: f64) -> rppal::pwm::Result<()> {
         let v = (value * sign).clamp(-1.0, 1.0);
         // Pulse width: 1.0ms (-1) to 2.0ms (+1), center 1.5ms
-        let pulse_width_us = ((1.5 + v * 0.5) * 1000.0) as u64;
-
-        // Software PWM: set high for pulse width, then low for remainder of 20ms period
-        pin.set_high();
-        thread::sleep(Duration::from_micros(pulse_width_us));
-        pin.set_low();
-        thread::sleep(Duration::from_micros(20_000 - pulse_width_us));
+        pwm.set_pulse_width(Duration::from_micros(((1.5 + v * 0.5) * 1000.0) as u64))
     }
 }
 
 #[cfg(target_os = "linux")]
 pub use hw::{Antennas, Eyes, FeetContacts, Projector};
 
+#[cfg(target_os = "linux")]
+pub(crate) use hw::open_spi;
+
 // ── Mock implementations (always available) ──
 
 /// Mock feet contacts that always report no contact.
 pub struct MockFeetContacts;
 
-impl MockFeetContacts {
-    pub fn get(&self) -> [f64; 2] {
+impl FeetContactsReader for MockFeetContacts {
+    fn get(&self) -> [f64; 2] {
         [0.0, 0.0]
     }
 }
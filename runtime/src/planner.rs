@@ -0,0 +1,189 @@
+//! Hierarchical control: a slow high-level policy steering the walking policy.
+//!
+//! A planner is an ONNX model run at a lower rate than the control loop
+//! (10 Hz by default) that outputs velocity commands, which the walking
+//! policy then tracks at the full control rate — learned navigation without
+//! any external infrastructure. The planner has its own observation
+//! manifest (`<planner>.obs.json`, required) built from the same components
+//! as the walking policy's; its `commands` component is the commands it is
+//! currently emitting.
+//!
+//! The output is either `[lin_vel_x, lin_vel_y, ang_vel]` or all 7 commands
+//! (head included). Velocities are clamped to the gamepad ranges. Between
+//! planner runs the last output is held. Manual or scripted input takes
+//! priority; the planner picks up again as soon as nobody else is driving.
+
+use anyhow::{bail, Result};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::OrtConfig;
+use crate::controller::{X_RANGE, Y_RANGE, YAW_RANGE};
+use crate::inference::{self, InferenceBackend};
+use crate::observation::{ComponentKind, ObservationInputs, ObservationManifest};
+use crate::policy_manager::{Policy, PolicySpec};
+
+/// Default planner rate (Hz).
+pub const DEFAULT_RATE: f64 = 10.0;
+
+/// High-level policy emitting commands every few control ticks.
+pub struct Planner {
+    policy: Policy,
+    every: u32,
+    countdown: u32,
+    commands: [f64; 7],
+}
+
+impl Planner {
+    /// Run `policy` at `rate` Hz inside a loop ticking at `control_freq`.
+    pub fn new(policy: Policy, rate: f64, control_freq: u32) -> Self {
+        let every = (control_freq as f64 / rate.max(0.1)).round().max(1.0) as u32;
+        tracing::info!(
+            "Planner {:?} every {} ticks ({:.1} Hz)",
+            policy.name(),
+            every,
+            control_freq as f64 / every as f64
+        );
+        Self {
+            policy,
+            every,
+            countdown: 0,
+            commands: [0.0; 7],
+        }
+    }
+
+    /// Load the planner model and its manifest.
+    pub fn load(
+        backend: InferenceBackend,
+        ort: &OrtConfig,
+        spec: &PolicySpec,
+        rate: f64,
+        control_freq: u32,
+        joint_names: &[String],
+    ) -> Result<Self> {
+        let manifest_path = spec
+            .manifest_path
+            .clone()
+            .unwrap_or_else(|| ObservationManifest::path_for_model(&spec.model_path));
+        if !manifest_path.exists() {
+            bail!(
+                "Planner needs an observation manifest, {} not found",
+                manifest_path.display()
+            );
+        }
+        let manifest = ObservationManifest::load(&manifest_path)?;
+        if manifest
+            .components
+            .iter()
+            .any(|c| c.name == ComponentKind::ActionHistory)
+        {
+            bail!("Planner observations can't include action_history, use commands");
+        }
+
+        let inference = inference::load_policy(backend, ort, &spec.model_path)?;
+        tracing::info!("Planner loaded from {}", spec.model_path.display());
+        let policy = Policy::new(&spec.name, inference, manifest, joint_names)?;
+        Ok(Self::new(policy, rate, control_freq))
+    }
+
+    /// Dump the planner's first `ticks` runs to `.npy` files in `dir`.
+    pub fn record_inference(self, dir: &Path, ticks: usize) -> Result<Self> {
+        Ok(Self {
+            policy: self.policy.record_inference(dir, ticks)?,
+            ..self
+        })
+    }
+
+    /// Run the planner if it is due and return the commands it emits.
+    pub fn update(&mut self, inputs: &ObservationInputs) -> Result<[f64; 7]> {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return Ok(self.commands);
+        }
+        self.countdown = self.every - 1;
+
+        let (_, output) = self.policy.infer(inputs)?;
+        let mut commands = match output.len() {
+            3 => {
+                let mut commands = [0.0; 7];
+                commands[..3].copy_from_slice(&output);
+                commands
+            }
+            7 => {
+                let mut commands = [0.0; 7];
+                commands.copy_from_slice(&output);
+                commands
+            }
+            n => bail!("Planner output has {} values, expected 3 or 7", n),
+        };
+        for (c, range) in commands.iter_mut().zip([X_RANGE, Y_RANGE, YAW_RANGE]) {
+            *c = c.clamp(range[0], range[1]);
+        }
+        self.commands = commands;
+        Ok(commands)
+    }
+
+    /// Drop the held output so the planner runs on its next update, e.g.
+    /// after manual input let go.
+    pub fn reset(&mut self) {
+        self.countdown = 0;
+        self.commands = [0.0; 7];
+        self.policy.reset_state();
+    }
+
+    pub fn benchmark(&mut self, iterations: usize) -> Result<Duration> {
+        self.policy.benchmark(iterations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imu::ImuData;
+    use crate::inference::PolicyInference;
+    use anyhow::Result;
+
+    /// Counts its runs and outputs a fast forward command.
+    struct Counting(usize);
+
+    impl PolicyInference for Counting {
+        fn infer(&mut self, _observation: &[f64]) -> Result<Vec<f64>> {
+            self.0 += 1;
+            Ok(vec![0.5, 0.0, self.0 as f64 * 0.1])
+        }
+
+        fn input_dim(&self) -> Option<usize> {
+            Some(3)
+        }
+    }
+
+    #[test]
+    fn test_runs_at_its_own_rate_and_clamps() {
+        let manifest: ObservationManifest =
+            serde_json::from_str(r#"{"components": [{"name": "gyro"}]}"#).unwrap();
+        let names = ["left_knee".to_string(), "right_knee".to_string()];
+        let policy = Policy::new("planner", Box::new(Counting(0)), manifest, &names).unwrap();
+        let mut planner = Planner::new(policy, 10.0, 50);
+
+        let imu = ImuData::default();
+        let joints = [0.0; 2];
+        let inputs = ObservationInputs {
+            imu: &imu,
+            commands: &[0.0; 7],
+            dof_pos: &joints,
+            init_pos: &joints,
+            dof_vel: &joints,
+            motor_targets: &joints,
+            feet_contacts: &[0.0; 2],
+            phase: &[1.0, 0.0],
+        };
+
+        let commands: Vec<_> = (0..6).map(|_| planner.update(&inputs).unwrap()).collect();
+        assert_eq!(commands[0][0], X_RANGE[1]);
+        assert!((commands[4][2] - 0.1).abs() < 1e-9);
+        assert!((commands[5][2] - 0.2).abs() < 1e-9);
+
+        planner.reset();
+        assert!((planner.update(&inputs).unwrap()[2] - 0.3).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,452 @@
+//! Several policies on one duck, switched at runtime.
+//!
+//! A duck can carry separate policies for walking, standing balance, and
+//! getting up, each with its own observation manifest and action history.
+//! `PolicyManager` runs whichever is active and cross-fades the motor targets
+//! over a number of ticks when switching, so the joints never jump from one
+//! policy's pose to another's. Switches come from the gamepad (Start cycles) or
+//! from `AutoSwitch`, which picks a policy from the duck's state.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::imu::ImuData;
+use crate::inference::{self, InferenceBackend, PolicyInference};
+use crate::config::OrtConfig;
+use crate::inference_debug::InferenceRecorder;
+use crate::inference_thread::ThreadedPolicy;
+use crate::motors::cubic_interpolate;
+use crate::observation::{ObservationBuilder, ObservationInputs, ObservationManifest};
+use crate::orientation::ImuObservation;
+use crate::rl_utils::JointMap;
+
+/// Conventional policy names used by `AutoSwitch`.
+pub const WALK: &str = "walk";
+pub const STAND: &str = "stand";
+pub const GET_UP: &str = "getup";
+
+/// Tilt from vertical (rad) beyond which the duck is considered fallen.
+pub const FALLEN_TILT: f64 = 1.05;
+
+/// Ticks without a walking command before switching to the stand policy.
+const IDLE_TICKS: u32 = 50;
+
+/// Walking commands smaller than this count as idle.
+const IDLE_COMMAND: f64 = 1e-3;
+
+/// A policy to load: `name=path/to/model.onnx` on the command line.
+#[derive(Debug, Clone)]
+pub struct PolicySpec {
+    pub name: String,
+    pub model_path: PathBuf,
+    /// Observation manifest (default: `<model>.obs.json` if present).
+    pub manifest_path: Option<PathBuf>,
+}
+
+impl FromStr for PolicySpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((name, path)) if !name.is_empty() && !path.is_empty() => Ok(Self {
+                name: name.to_string(),
+                model_path: PathBuf::from(path),
+                manifest_path: None,
+            }),
+            _ => Err(format!("expected NAME=PATH, got {:?}", s)),
+        }
+    }
+}
+
+/// One loaded policy with its own observation layout, joint order and
+/// action history.
+pub struct Policy {
+    name: String,
+    inference: Box<dyn PolicyInference>,
+    obs_builder: ObservationBuilder,
+    joints: JointMap,
+}
+
+impl Policy {
+    /// Wrap an inference backend, checking the manifest against its input.
+    /// `joint_names` is the hardware joint order.
+    pub fn new(
+        name: &str,
+        inference: Box<dyn PolicyInference>,
+        manifest: ObservationManifest,
+        joint_names: &[String],
+    ) -> Result<Self> {
+        let order = match manifest.joints {
+            Some(ref joints) => joints.clone(),
+            None => JointMap::default_order(joint_names),
+        };
+        let joints = JointMap::new(&order, joint_names)
+            .with_context(|| format!("Joint order of policy {:?} does not fit the robot", name))?;
+        if !joints.is_identity() {
+            tracing::info!("Policy {:?} joint order: {}", name, order.join(", "));
+        }
+        let num_dofs = joints.policy_len();
+        manifest
+            .validate(num_dofs, inference.input_dim())
+            .with_context(|| format!("Observation layout does not match policy {:?}", name))?;
+        Ok(Self {
+            name: name.to_string(),
+            inference,
+            obs_builder: ObservationBuilder::new(manifest, num_dofs),
+            joints,
+        })
+    }
+
+    /// Load a model and its manifest; without one, `default_imu` selects the
+    /// original layout.
+    pub fn load(
+        backend: InferenceBackend,
+        ort: &OrtConfig,
+        spec: &PolicySpec,
+        default_imu: ImuObservation,
+        joint_names: &[String],
+    ) -> Result<Self> {
+        let inference = inference::load_policy(backend, ort, &spec.model_path)
+            .with_context(|| format!("Failed to load policy {:?}", spec.name))?;
+
+        let manifest_path = spec
+            .manifest_path
+            .clone()
+            .unwrap_or_else(|| ObservationManifest::path_for_model(&spec.model_path));
+        let manifest = if spec.manifest_path.is_some() || manifest_path.exists() {
+            tracing::info!(
+                "Policy {:?}: observation manifest {}",
+                spec.name,
+                manifest_path.display()
+            );
+            ObservationManifest::load(&manifest_path)?
+        } else {
+            ObservationManifest::default_layout(default_imu)
+        };
+
+        tracing::info!("Policy {:?} loaded from {}", spec.name, spec.model_path.display());
+        Self::new(&spec.name, inference, manifest, joint_names)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Dump this policy's first `ticks` inputs and outputs to `.npy` files
+    /// in `dir` (see `inference_debug`).
+    pub fn record_inference(self, dir: &Path, ticks: usize) -> Result<Self> {
+        let inference = InferenceRecorder::new(self.inference, &self.name, dir, ticks)?;
+        Ok(Self {
+            inference: Box::new(inference),
+            ..self
+        })
+    }
+
+    /// Run this policy's inference on its own thread (see `inference_thread`).
+    pub fn threaded(self) -> Self {
+        Self {
+            inference: Box::new(ThreadedPolicy::start(self.inference)),
+            ..self
+        }
+    }
+
+    /// Build this policy's observation and run it. `inputs` are in
+    /// hardware joint order; the observation and the returned output are in
+    /// the policy's (see `joint_action`).
+    pub fn infer(&mut self, inputs: &ObservationInputs) -> Result<(Vec<f64>, Vec<f64>)> {
+        let obs = if self.joints.is_identity() {
+            self.obs_builder.build(inputs)
+        } else {
+            let dof_pos = self.joints.to_policy(inputs.dof_pos);
+            let init_pos = self.joints.to_policy(inputs.init_pos);
+            let dof_vel = self.joints.to_policy(inputs.dof_vel);
+            let motor_targets = self.joints.to_policy(inputs.motor_targets);
+            self.obs_builder.build(&ObservationInputs {
+                dof_pos: &dof_pos,
+                init_pos: &init_pos,
+                dof_vel: &dof_vel,
+                motor_targets: &motor_targets,
+                ..*inputs
+            })
+        };
+        let action = self.inference.infer(&obs)?;
+        self.obs_builder.push_action(&action);
+        Ok((obs, action))
+    }
+
+    /// A joint action output by this policy, in hardware joint order.
+    pub fn joint_action(&self, action: &[f64]) -> Result<Vec<f64>> {
+        if action.len() != self.joints.policy_len() {
+            bail!(
+                "Policy {:?} output {} values for {} joints",
+                self.name,
+                action.len(),
+                self.joints.policy_len()
+            );
+        }
+        if self.joints.is_identity() {
+            return Ok(action.to_vec());
+        }
+        Ok(self.joints.to_hardware(action))
+    }
+
+    /// Zero the hidden state of a recurrent model.
+    pub fn reset_state(&mut self) {
+        self.inference.reset_state();
+    }
+
+    /// Average inference time over `iterations` runs.
+    pub fn benchmark(&mut self, iterations: usize) -> Result<Duration> {
+        tracing::info!("Benchmarking policy {:?}", self.name);
+        self.inference.benchmark(self.obs_builder.dim(), iterations)
+    }
+}
+
+#[derive(Debug)]
+struct Crossfade {
+    from: Vec<f64>,
+    tick: usize,
+}
+
+/// Runs the active policy and blends motor targets across switches.
+pub struct PolicyManager {
+    policies: Vec<Policy>,
+    active: usize,
+    crossfade_ticks: usize,
+    crossfade: Option<Crossfade>,
+}
+
+impl PolicyManager {
+    /// Start with `initial` active.
+    pub fn new(initial: Policy, crossfade_ticks: usize) -> Self {
+        Self {
+            policies: vec![initial],
+            active: 0,
+            crossfade_ticks,
+            crossfade: None,
+        }
+    }
+
+    pub fn add(&mut self, policy: Policy) -> Result<()> {
+        if self.contains(&policy.name) {
+            bail!("Policy {:?} is loaded twice", policy.name);
+        }
+        self.policies.push(policy);
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.policies.iter().any(|p| p.name == name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.policies.iter().map(|p| p.name())
+    }
+
+    pub fn active_name(&self) -> &str {
+        self.policies[self.active].name()
+    }
+
+    /// Make `name` the active policy, cross-fading from `current_targets`.
+    /// Returns whether the active policy changed.
+    pub fn switch_to(&mut self, name: &str, current_targets: &[f64]) -> Result<bool> {
+        let Some(index) = self.policies.iter().position(|p| p.name == name) else {
+            bail!("No policy named {:?}", name);
+        };
+        if index == self.active {
+            return Ok(false);
+        }
+        self.activate(index, current_targets);
+        Ok(true)
+    }
+
+    /// Switch to the next policy in load order.
+    pub fn cycle(&mut self, current_targets: &[f64]) -> &str {
+        if self.policies.len() > 1 {
+            self.activate((self.active + 1) % self.policies.len(), current_targets);
+        }
+        self.active_name()
+    }
+
+    fn activate(&mut self, index: usize, current_targets: &[f64]) {
+        tracing::info!(
+            "Switching policy {:?} -> {:?}",
+            self.active_name(),
+            self.policies[index].name()
+        );
+        self.active = index;
+        // The new policy starts with no memory of actions it did not take
+        self.policies[index].obs_builder.reset();
+        self.policies[index].reset_state();
+        self.crossfade = (self.crossfade_ticks > 0).then(|| Crossfade {
+            from: current_targets.to_vec(),
+            tick: 0,
+        });
+    }
+
+    /// Zero the active policy's recurrent state, e.g. on unpause or after
+    /// a fall.
+    pub fn reset_state(&mut self) {
+        self.policies[self.active].reset_state();
+    }
+
+    /// Build the active policy's observation and run it. Returns the
+    /// observation and the action, in hardware joint order.
+    pub fn infer(&mut self, inputs: &ObservationInputs) -> Result<(Vec<f64>, Vec<f64>)> {
+        let policy = &mut self.policies[self.active];
+        let (obs, action) = policy.infer(inputs)?;
+        Ok((obs, policy.joint_action(&action)?))
+    }
+
+    /// Blend the active policy's motor targets with the pose held at the
+    /// last switch, until the cross-fade completes.
+    pub fn blend(&mut self, targets: Vec<f64>) -> Vec<f64> {
+        let Some(fade) = self.crossfade.as_mut() else {
+            return targets;
+        };
+        fade.tick += 1;
+        let s = fade.tick as f64 / self.crossfade_ticks as f64;
+        if s >= 1.0 {
+            self.crossfade = None;
+            return targets;
+        }
+        cubic_interpolate(&fade.from, &targets, s)
+    }
+
+    pub fn is_crossfading(&self) -> bool {
+        self.crossfade.is_some()
+    }
+
+    /// Benchmark every loaded policy. Returns the slowest average.
+    pub fn benchmark(&mut self, iterations: usize) -> Result<Duration> {
+        let mut slowest = Duration::ZERO;
+        for policy in &mut self.policies {
+            slowest = slowest.max(policy.benchmark(iterations)?);
+        }
+        Ok(slowest)
+    }
+}
+
+/// Picks a policy from the duck's state: get up when fallen, stand when no
+/// walking command has been given for a while, walk otherwise.
+///
+/// Only changes of the desired policy are reported, so a policy chosen by hand
+/// stays active until the situation changes.
+pub struct AutoSwitch {
+    idle_ticks: u32,
+    desired: Option<&'static str>,
+}
+
+impl AutoSwitch {
+    pub fn new() -> Self {
+        Self {
+            idle_ticks: 0,
+            desired: None,
+        }
+    }
+
+    /// Feed one tick of state; returns the policy to switch to, if it changed.
+    pub fn update(&mut self, imu: &ImuData, commands: &[f64; 7]) -> Option<&'static str> {
+        let fallen = imu.tilt() > FALLEN_TILT;
+
+        if commands[..3].iter().all(|c| c.abs() < IDLE_COMMAND) {
+            self.idle_ticks = self.idle_ticks.saturating_add(1);
+        } else {
+            self.idle_ticks = 0;
+        }
+
+        let desired = if fallen {
+            GET_UP
+        } else if self.idle_ticks >= IDLE_TICKS {
+            STAND
+        } else {
+            WALK
+        };
+
+        if self.desired == Some(desired) {
+            return None;
+        }
+        self.desired = Some(desired);
+        Some(desired)
+    }
+}
+
+impl Default for AutoSwitch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Policy that always outputs the same action.
+    struct Constant(Vec<f64>);
+
+    impl PolicyInference for Constant {
+        fn infer(&mut self, _observation: &[f64]) -> Result<Vec<f64>> {
+            Ok(self.0.clone())
+        }
+
+        fn input_dim(&self) -> Option<usize> {
+            None
+        }
+    }
+
+    fn policy(name: &str, action: f64) -> Policy {
+        let manifest = ObservationManifest::default_layout(ImuObservation::Accel);
+        let joints = ["left_knee".to_string(), "right_knee".to_string()];
+        Policy::new(name, Box::new(Constant(vec![action; 2])), manifest, &joints).unwrap()
+    }
+
+    #[test]
+    fn test_switch_crossfades_targets() {
+        let mut manager = PolicyManager::new(policy(WALK, 0.0), 4);
+        manager.add(policy(STAND, 1.0)).unwrap();
+        assert!(manager.add(policy(STAND, 2.0)).is_err());
+
+        assert!(manager.switch_to(STAND, &[0.0, 0.0]).unwrap());
+        assert!(!manager.switch_to(STAND, &[0.0, 0.0]).unwrap());
+        assert_eq!(manager.active_name(), STAND);
+
+        let mid = [manager.blend(vec![1.0, 1.0]), manager.blend(vec![1.0, 1.0])];
+        assert!(mid[0][0] > 0.0 && mid[0][0] < mid[1][0] && mid[1][0] < 1.0);
+        manager.blend(vec![1.0, 1.0]);
+        assert_eq!(manager.blend(vec![1.0, 1.0]), vec![1.0, 1.0]);
+        assert!(!manager.is_crossfading());
+
+        assert_eq!(manager.cycle(&[1.0, 1.0]), WALK);
+        assert!(manager.switch_to("dance", &[0.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn test_auto_switch_reports_changes_only() {
+        let mut auto = AutoSwitch::new();
+        let upright = ImuData::default();
+        let walking = [0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        let idle = [0.0; 7];
+
+        assert_eq!(auto.update(&upright, &walking), Some(WALK));
+        assert_eq!(auto.update(&upright, &walking), None);
+
+        let switched: Vec<_> = (0..IDLE_TICKS).filter_map(|_| auto.update(&upright, &idle)).collect();
+        assert_eq!(switched, vec![STAND]);
+
+        let on_side = ImuData {
+            projected_gravity: [1.0, 0.0, 0.0],
+            ..ImuData::default()
+        };
+        assert_eq!(auto.update(&on_side, &idle), Some(GET_UP));
+    }
+
+    #[test]
+    fn test_parse_policy_spec() {
+        let spec: PolicySpec = "getup=models/getup.onnx".parse().unwrap();
+        assert_eq!(spec.name, GET_UP);
+        assert_eq!(spec.model_path, PathBuf::from("models/getup.onnx"));
+        assert!("models/getup.onnx".parse::<PolicySpec>().is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Real-time scheduling for the control thread.
+//!
+//! On a busy Pi the control loop gets preempted by whatever else runs, and
+//! each preemption is an overrun. The `realtime` config section asks for
+//! three things, each applied independently just before the loop starts:
+//!
+//! - a SCHED_FIFO priority, so the loop preempts everything with a normal
+//!   priority. Threads started by the control thread afterwards get the
+//!   normal scheduler back (SCHED_RESET_ON_FORK);
+//! - a CPU core the thread is pinned to, best one isolated with the
+//!   `isolcpus` kernel parameter. Threads started afterwards share the pin;
+//! - mlockall, so no page of the runtime is ever swapped out or faulted in
+//!   during a tick.
+//!
+//! What the system refuses (no CAP_SYS_NICE, a low memlock limit, a core
+//! that doesn't exist) is reported at startup and the loop runs without it.
+
+use crate::config::RealtimeConfig;
+
+/// What was asked for and what was obtained: each field is None when not
+/// requested, else the outcome.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RealtimeReport {
+    pub fifo_priority: Option<Result<i32, String>>,
+    pub cpu: Option<Result<usize, String>>,
+    pub lock_memory: Option<Result<(), String>>,
+}
+
+impl RealtimeReport {
+    /// Log what the control thread got, with a warning per refusal.
+    pub fn log(&self) {
+        let mut obtained = Vec::new();
+        if let Some(ref outcome) = self.fifo_priority {
+            match outcome {
+                Ok(priority) => obtained.push(format!("SCHED_FIFO priority {}", priority)),
+                Err(e) => tracing::warn!("Real-time priority not obtained: {}", e),
+            }
+        }
+        if let Some(ref outcome) = self.cpu {
+            match outcome {
+                Ok(cpu) => obtained.push(format!("pinned to CPU {}", cpu)),
+                Err(e) => tracing::warn!("CPU pinning not obtained: {}", e),
+            }
+        }
+        if let Some(ref outcome) = self.lock_memory {
+            match outcome {
+                Ok(()) => obtained.push("memory locked".to_string()),
+                Err(e) => tracing::warn!("Memory locking not obtained: {}", e),
+            }
+        }
+        if obtained.is_empty() {
+            tracing::info!("Control thread runs with normal scheduling");
+        } else {
+            tracing::info!("Control thread real-time settings: {}", obtained.join(", "));
+        }
+    }
+}
+
+/// Apply `config` to the calling thread (and, for memory, the process).
+pub fn apply(config: &RealtimeConfig) -> RealtimeReport {
+    RealtimeReport {
+        fifo_priority: config.fifo_priority.map(set_fifo_priority),
+        cpu: config.cpu.map(pin_to_cpu),
+        lock_memory: config.lock_memory.then(lock_memory),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_fifo_priority(priority: i32) -> Result<i32, String> {
+    // SAFETY: plain queries of the priority range
+    let (min, max) = unsafe {
+        (
+            libc::sched_get_priority_min(libc::SCHED_FIFO),
+            libc::sched_get_priority_max(libc::SCHED_FIFO),
+        )
+    };
+    if !(min..=max).contains(&priority) {
+        return Err(format!("priority {} is outside {}-{}", priority, min, max));
+    }
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+    // SAFETY: `param` outlives the call; pid 0 is the calling thread
+    let policy = libc::SCHED_FIFO | libc::SCHED_RESET_ON_FORK;
+    if unsafe { libc::sched_setscheduler(0, policy, &param) } != 0 {
+        let e = std::io::Error::last_os_error();
+        return Err(match e.raw_os_error() {
+            Some(libc::EPERM) => format!(
+                "{} (run as root, grant CAP_SYS_NICE, or raise the rtprio limit)",
+                e
+            ),
+            _ => e.to_string(),
+        });
+    }
+    Ok(priority)
+}
+
+#[cfg(target_os = "linux")]
+fn pin_to_cpu(cpu: usize) -> Result<usize, String> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(format!("CPU {} does not exist", cpu));
+    }
+    // SAFETY: the set is a plain bitmask, `cpu` is within it, and pid 0 is
+    // the calling thread
+    let pinned = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if pinned != 0 {
+        let e = std::io::Error::last_os_error();
+        return Err(match e.raw_os_error() {
+            Some(libc::EINVAL) => format!("CPU {} does not exist or is offline", cpu),
+            _ => e.to_string(),
+        });
+    }
+    Ok(cpu)
+}
+
+#[cfg(target_os = "linux")]
+fn lock_memory() -> Result<(), String> {
+    // SAFETY: mlockall takes only flags
+    if unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } != 0 {
+        let e = std::io::Error::last_os_error();
+        return Err(match e.raw_os_error() {
+            Some(libc::EPERM | libc::ENOMEM) => {
+                format!("{} (run as root or raise the memlock limit)", e)
+            }
+            _ => e.to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_fifo_priority(_priority: i32) -> Result<i32, String> {
+    Err("only supported on Linux".to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_cpu(_cpu: usize) -> Result<usize, String> {
+    Err("only supported on Linux".to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lock_memory() -> Result<(), String> {
+    Err("only supported on Linux".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_requested_settings_are_applied() {
+        assert_eq!(apply(&RealtimeConfig::default()), RealtimeReport::default());
+
+        // Refused before any system call
+        let config = RealtimeConfig {
+            fifo_priority: Some(150),
+            cpu: Some(100_000),
+            lock_memory: false,
+        };
+        let report = apply(&config);
+        assert!(matches!(report.fifo_priority, Some(Err(_))));
+        assert!(matches!(report.cpu, Some(Err(_))));
+        assert_eq!(report.lock_memory, None);
+    }
+}
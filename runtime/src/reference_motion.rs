@@ -4,13 +4,21 @@
 //! needed by the RL policy — specifically the `nb_steps_in_period` value
 //! and the sinusoidal phase signal.
 //!
-//! The polynomial coefficients are loaded from a pickle file at startup,
-//! but for the Rust runtime we only need the timing metadata. The actual
-//! reference motion generation is handled by the RL policy in the ONNX model.
+//! The polynomial coefficients are loaded from the pickle file the
+//! reference motion generator writes: a dict keyed by command triple, either
+//! a `"dx_dy_dtheta"` string or a `(dx, dy, dtheta)` tuple, whose entries
+//! hold the gait `period` (s), the `fps` it was fitted at and the polynomial
+//! `coefficients` of each dimension. The runtime needs the timing to drive
+//! the phase signal; the reference motion itself is handled by the RL policy
+//! in the ONNX model.
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
+use serde_pickle::{DeOptions, HashableValue, Value};
+use std::collections::BTreeMap;
 use std::path::Path;
 
+use crate::config::{PhaseIdleConfig, PhaseIdleMode};
+
 /// Reference motion phase tracker.
 ///
 /// The RL policy uses a sinusoidal phase signal [cos(phase), sin(phase)]
@@ -28,6 +36,21 @@ pub struct PhaseTracker {
 
     /// Additive offset to the frequency factor (per-robot tuning).
     pub frequency_factor_offset: f64,
+
+    /// Multiplier on the whole frequency (thermal throttling).
+    pub frequency_scale: f64,
+
+    /// Frequency factor by commanded forward velocity.
+    pub frequency_map: FrequencyMap,
+
+    /// What the phase does while the commands are zero.
+    idle: PhaseIdleConfig,
+
+    /// Decrease of `idle_scale` per step when decaying.
+    idle_decay: f64,
+
+    /// Fraction of the phase advance still applied: 1 moving, 0 stopped.
+    idle_scale: f64,
 }
 
 impl PhaseTracker {
@@ -42,17 +65,53 @@ impl PhaseTracker {
             step_index: 0.0,
             frequency_factor: 1.0,
             frequency_factor_offset,
+            frequency_scale: 1.0,
+            frequency_map: FrequencyMap::default(),
+            idle: PhaseIdleConfig::default(),
+            idle_decay: 1.0,
+            idle_scale: 1.0,
         }
     }
 
+    /// Freeze or wind down the phase while the commands are zero, at
+    /// `control_freq` steps per second.
+    pub fn set_idle(&mut self, idle: PhaseIdleConfig, control_freq: f64) {
+        self.idle_decay = 1.0 / (idle.decay_time * control_freq).max(1.0);
+        self.idle = idle;
+    }
+
+    /// Whether the phase is stopped because the commands are zero.
+    pub fn is_stopped(&self) -> bool {
+        self.idle_scale == 0.0
+    }
+
     /// Create with default period settings (50Hz, 0.5s period = 25 steps).
     pub fn default_50hz() -> Self {
         Self::new(25, 0.0)
     }
 
-    /// Advance the phase by one step and return [cos(phase), sin(phase)].
-    pub fn step(&mut self) -> [f64; 2] {
-        self.step_index += self.frequency_factor + self.frequency_factor_offset;
+    /// Advance the phase by one step at the frequency for `commands` (the
+    /// policy's command vector, forward velocity first) and return
+    /// [cos(phase), sin(phase)].
+    pub fn step(&mut self, commands: &[f64]) -> [f64; 2] {
+        let vx = commands.first().copied().unwrap_or(0.0);
+        let idle = commands.iter().take(3).all(|c| c.abs() < self.idle.deadband);
+        self.idle_scale = match self.idle.mode {
+            PhaseIdleMode::March => 1.0,
+            _ if !idle => {
+                // Start again from the touchdown the standing pose matches
+                if self.is_stopped() {
+                    self.step_index = self.idle.resume_phase * self.nb_steps_in_period as f64;
+                }
+                1.0
+            }
+            PhaseIdleMode::Freeze => 0.0,
+            PhaseIdleMode::Decay => (self.idle_scale - self.idle_decay).max(0.0),
+        };
+        self.step_index += (self.frequency_factor + self.frequency_factor_offset)
+            * self.frequency_map.factor(vx)
+            * self.frequency_scale
+            * self.idle_scale;
         self.step_index %= self.nb_steps_in_period as f64;
 
         let phase =
@@ -88,28 +147,312 @@ impl PhaseTracker {
     }
 }
 
-/// Attempt to load nb_steps_in_period from a polynomial coefficients pickle file.
-///
-/// This is a best-effort parser for the Python pickle format. If it fails,
-/// returns a sensible default.
-pub fn load_period_from_pickle(path: &Path) -> Result<usize> {
+/// Polynomial coefficients of each dimension, by dimension name.
+pub type Coefficients = BTreeMap<String, Vec<f64>>;
+
+/// The fit of one command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolyCommand {
+    /// `[dx, dy, dtheta]`.
+    pub command: [f64; 3],
+    /// Gait period (s) at this command.
+    pub period: f64,
+    pub coefficients: Coefficients,
+}
+
+/// Polynomial fits of the reference gait, one per command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolyCoefficients {
+    /// Gait period (s) standing still: that of the `[0, 0, 0]` command, or
+    /// of the first one.
+    pub period: f64,
+    /// Frame rate the motions were fitted at, shared by all commands.
+    pub fps: f64,
+    pub commands: Vec<PolyCommand>,
+}
+
+impl PolyCoefficients {
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_pickle(&bytes)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn from_pickle(bytes: &[u8]) -> Result<Self> {
+        // numpy values can't be decoded; they come out as None
+        let options = DeOptions::new().replace_unresolved_globals();
+        let Value::Dict(entries) = serde_pickle::value_from_slice(bytes, options)? else {
+            bail!("Expected a dict keyed by command");
+        };
+
+        let mut fps = None;
+        let mut commands = Vec::with_capacity(entries.len());
+        for (key, entry) in &entries {
+            let command = command_key(key).ok_or_else(|| anyhow!("Bad command key {}", key))?;
+            let Value::Dict(entry) = entry else {
+                bail!("Entry {} is not a dict", key);
+            };
+            let field = |name: &str| entry.get(&HashableValue::String(name.to_string()));
+            let number = |name: &str| {
+                field(name)
+                    .and_then(as_f64)
+                    .ok_or_else(|| anyhow!("Entry {} has no numeric {}", key, name))
+            };
+            let period = number("period")?;
+            if !(period.is_finite() && period > 0.0) {
+                bail!("Entry {} has period {} s", key, period);
+            }
+            let entry_fps = number("fps")?;
+            match fps {
+                None => fps = Some(entry_fps),
+                Some(first) if first != entry_fps => {
+                    bail!("Entry {} is at {} fps, others at {}", key, entry_fps, first)
+                }
+                Some(_) => {}
+            }
+
+            let mut coefficients = Coefficients::new();
+            if let Some(Value::Dict(dimensions)) = field("coefficients") {
+                for (dimension, values) in dimensions {
+                    let values = match values {
+                        Value::List(values) | Value::Tuple(values) => {
+                            values.iter().map(as_f64).collect::<Option<Vec<f64>>>()
+                        }
+                        _ => None,
+                    };
+                    let name = match dimension {
+                        HashableValue::String(name) => name.clone(),
+                        other => other.to_string(),
+                    };
+                    let values = values.ok_or_else(|| {
+                        anyhow!("Coefficients {} of {} are not a list of numbers", name, key)
+                    })?;
+                    coefficients.insert(name, values);
+                }
+            }
+            commands.push(PolyCommand {
+                command,
+                period,
+                coefficients,
+            });
+        }
+
+        let fps = fps.ok_or_else(|| anyhow!("No commands in the file"))?;
+        if !(fps.is_finite() && fps > 0.0) {
+            bail!("Invalid frame rate {} fps", fps);
+        }
+        let standing = commands.iter().find(|c| c.command == [0.0; 3]).unwrap_or(&commands[0]);
+        Ok(Self {
+            period: standing.period,
+            fps,
+            commands,
+        })
+    }
+
+    /// Control steps in one gait period, as the Python runtime computes it
+    /// (the period rounded to 4 decimals, truncated).
+    pub fn nb_steps_in_period(&self) -> usize {
+        let period = (self.period * 1e4).round() / 1e4;
+        ((period * self.fps) as usize).max(1)
+    }
+
+    /// Coefficients fitted for `command` (`[dx, dy, dtheta]`), if any.
+    pub fn coefficients(&self, command: [f64; 3]) -> Option<&Coefficients> {
+        self.commands
+            .iter()
+            .find(|c| c.command == command)
+            .map(|c| &c.coefficients)
+    }
+
+    /// Phase frequency by forward speed, from the periods of the straight
+    /// walking commands (`dy` and `dtheta` zero) relative to standing.
+    pub fn frequency_map(&self) -> FrequencyMap {
+        let points = self
+            .commands
+            .iter()
+            .filter(|c| c.command[1] == 0.0 && c.command[2] == 0.0)
+            .map(|c| [c.command[0], self.period / c.period])
+            .collect();
+        FrequencyMap::new(points).unwrap_or_default()
+    }
+}
+
+/// Phase frequency factor by commanded forward velocity: the gait the
+/// policy was trained on takes quicker steps as it walks faster.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrequencyMap {
+    /// `[vx, factor]`, sorted by vx.
+    points: Vec<[f64; 2]>,
+}
+
+impl FrequencyMap {
+    /// Map through `[vx, factor]` points; no points means a factor of 1.
+    pub fn new(mut points: Vec<[f64; 2]>) -> Result<Self> {
+        let valid = |&[vx, factor]: &[f64; 2]| vx.is_finite() && factor.is_finite() && factor > 0.0;
+        if let Some(&[vx, factor]) = points.iter().find(|p| !valid(p)) {
+            bail!("Invalid phase frequency point [{}, {}]", vx, factor);
+        }
+        points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        points.dedup_by(|a, b| a[0] == b[0]);
+        Ok(Self { points })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Factor at `vx`, interpolated linearly and held past the ends.
+    pub fn factor(&self, vx: f64) -> f64 {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return 1.0;
+        };
+        if vx <= first[0] {
+            return first[1];
+        }
+        if vx >= last[0] {
+            return last[1];
+        }
+        let i = self.points.partition_point(|p| p[0] <= vx);
+        let ([x0, y0], [x1, y1]) = (self.points[i - 1], self.points[i]);
+        y0 + (y1 - y0) * (vx - x0) / (x1 - x0)
+    }
+}
+
+/// `[dx, dy, dtheta]` from a `"dx_dy_dtheta"` string or a 3-tuple key.
+fn command_key(key: &HashableValue) -> Option<[f64; 3]> {
+    let parts: Vec<f64> = match key {
+        HashableValue::String(s) => s.split('_').map(|p| p.parse().ok()).collect::<Option<_>>()?,
+        HashableValue::Tuple(values) => values.iter().map(hashable_f64).collect::<Option<_>>()?,
+        _ => return None,
+    };
+    parts.try_into().ok()
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match *value {
+        Value::F64(v) => Some(v),
+        Value::I64(v) => Some(v as f64),
+        _ => None,
+    }
+}
+
+fn hashable_f64(value: &HashableValue) -> Option<f64> {
+    match *value {
+        HashableValue::F64(v) => Some(v),
+        HashableValue::I64(v) => Some(v as f64),
+        _ => None,
+    }
+}
+
+/// Load a polynomial coefficients pickle file; None if it is missing.
+pub fn load_pickle(path: &Path) -> Result<Option<PolyCoefficients>> {
     if !path.exists() {
         tracing::warn!(
             "Polynomial coefficients file not found at {}, using default period",
             path.display()
         );
-        return Ok(25); // default: 0.5s period at 50Hz
+        return Ok(None);
     }
 
-    // The pickle file contains a dict with entries like:
-    // "0.0_0.0_0.0" -> { "period": 0.5, "fps": 50, ... }
-    // For now, we use the default. Full pickle parsing would require
-    // a pickle decoder crate.
+    let poly = PolyCoefficients::load(path)?;
     tracing::info!(
-        "Polynomial coefficients file found at {}, using default period extraction",
-        path.display()
+        "Gait period {:.3}s at {} fps: {} steps ({} commands)",
+        poly.period,
+        poly.fps,
+        poly.nb_steps_in_period(),
+        poly.commands.len()
     );
+    Ok(Some(poly))
+}
 
-    // Default: period=0.5s, fps=50 -> 25 steps
-    Ok(25)
+/// Load nb_steps_in_period from a polynomial coefficients pickle file.
+/// A missing file falls back to the default 25 steps (0.5 s at 50 Hz).
+pub fn load_period_from_pickle(path: &Path) -> Result<usize> {
+    Ok(load_pickle(path)?.map_or(25, |poly| poly.nb_steps_in_period()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pickle_from_the_generator() {
+        // Written by Python's pickle (protocol 4) in the generator's layout
+        let poly =
+            PolyCoefficients::from_pickle(include_bytes!("../testdata/polynomial_coefficients.pkl"))
+                .unwrap();
+        assert_eq!((poly.period, poly.fps), (0.54, 50.0));
+        assert_eq!(poly.nb_steps_in_period(), 27);
+        assert_eq!(poly.commands.len(), 4);
+        let standing = poly.coefficients([0.0, 0.0, 0.0]).unwrap();
+        assert_eq!(standing["dim_1"], [0.02, 0.04, 0.06, 0.08]);
+        assert!(poly.coefficients([0.05, 0.0, -0.1]).is_some());
+
+        // Tuple keys work too
+        let key = HashableValue::Tuple(vec![
+            HashableValue::F64(0.1),
+            HashableValue::I64(0),
+            HashableValue::F64(0.0),
+        ]);
+        let entry = BTreeMap::from([
+            (HashableValue::String("period".into()), Value::F64(0.5)),
+            (HashableValue::String("fps".into()), Value::I64(50)),
+        ]);
+        let bytes = serde_pickle::value_to_vec(
+            &Value::Dict(BTreeMap::from([(key, Value::Dict(entry))])),
+            Default::default(),
+        )
+        .unwrap();
+        let poly = PolyCoefficients::from_pickle(&bytes).unwrap();
+        assert_eq!(poly.nb_steps_in_period(), 25);
+        assert!(poly.coefficients([0.1, 0.0, 0.0]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_phase_stops_when_idle_and_resumes_at_touchdown() {
+        let idle = PhaseIdleConfig {
+            mode: PhaseIdleMode::Decay,
+            deadband: 0.01,
+            decay_time: 0.1,
+            resume_phase: 0.5,
+        };
+        let mut tracker = PhaseTracker::new(20, 0.0);
+        tracker.set_idle(idle, 50.0);
+        let walk = [0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        tracker.step(&walk);
+        // Winds down over 5 steps: 0.8 + 0.6 + 0.4 + 0.2 + 0
+        let before = tracker.step_index;
+        for _ in 0..8 {
+            tracker.step(&[0.005; 7]);
+        }
+        assert!(tracker.is_stopped());
+        assert!((tracker.step_index - before - 2.0).abs() < 1e-9);
+
+        // Resumes half a period in, then advances normally
+        tracker.step(&walk);
+        assert!(!tracker.is_stopped());
+        assert!((tracker.step_index - 11.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_phase_advances_faster_with_forward_speed() {
+        let map = FrequencyMap::new(vec![[0.15, 1.5], [0.0, 1.0]]).unwrap();
+        assert_eq!(map.factor(-0.1), 1.0);
+        assert!((map.factor(0.075) - 1.25).abs() < 1e-12);
+        assert_eq!(map.factor(0.3), 1.5);
+        assert!(FrequencyMap::new(vec![[0.1, 0.0]]).is_err());
+
+        let mut standing = PhaseTracker::new(20, 0.0);
+        let mut walking = PhaseTracker::new(20, 0.0);
+        walking.frequency_map = map;
+        for _ in 0..5 {
+            standing.step(&[0.0; 7]);
+            walking.step(&[0.15, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        }
+        // 5 steps of 20 is a quarter period; 1.5x that is 3/8
+        assert!((standing.current_phase()[0] - 0.0).abs() < 1e-9);
+        let phase = 0.75 * std::f64::consts::PI;
+        assert!((walking.current_phase()[0] - phase.cos()).abs() < 1e-9);
+    }
 }
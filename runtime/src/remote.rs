@@ -0,0 +1,775 @@
+//! WebSocket teleop and telemetry server.
+//!
+//! Lets a browser drive the duck when no gamepad is paired. Each connected
+//! client first gets a `hello` with the joint names, then a `telemetry`
+//! message at the configured rate with the latest state published by the
+//! control loop. Clients send JSON commands:
+//!
+//! ```json
+//! { "type": "velocity", "vx": 0.1, "vy": 0.0, "yaw": 0.0 }
+//! { "type": "head", "head_yaw": 0.3 }
+//! { "type": "pause" }
+//! { "type": "unpause" }
+//! { "type": "play_sound", "name": "happy.wav" }
+//! { "type": "play_url", "url": "https://example.com/quack.mp3" }
+//! { "type": "say", "text": "hello" }
+//! { "type": "set_volume", "volume": 0.5 }
+//! { "type": "stop_sounds" }
+//! { "type": "play_emote", "name": "wave" }
+//! { "type": "eye_expression", "expression": "heart_eyes" }
+//! { "type": "save_moment", "name": "tripped on rug" }
+//! { "type": "start_routine", "routine": "walk_in_place" }
+//! { "type": "stop_routine" }
+//! { "type": "estop" }
+//! { "type": "release" }
+//! { "type": "torque_off" }
+//! { "type": "jog_start" }
+//! { "type": "jog", "joint": "left_knee", "delta": 0.05 }
+//! { "type": "jog_stop" }
+//! { "type": "confirm", "token": "...", "key": "..." }
+//! ```
+//!
+//! Runtime events (pauses, falls, faults, policy swaps) are forwarded to
+//! clients as they happen.
+//!
+//! With a camera attached (`serve_camera`), plain HTTP requests for
+//! `/camera.mjpg` and `/camera.jpg` on the same port get the camera's MJPEG
+//! stream and latest frame (see the `camera` module).
+//!
+//! Velocity and head commands are held until replaced; if no client has sent
+//! one for `COMMAND_TIMEOUT`, they fall back to zero so a closed browser tab
+//! stops the robot. They are in physical units and clamped to the gamepad's
+//! ranges by `CommandShaper::limit`. They reach the control loop through
+//! `RemoteInput`, a `CommandSource` for the command multiplexer.
+//!
+//! `estop` holds the robot stopped over every other input, including the
+//! local gamepad, until a client sends `release`. It stays engaged if the
+//! client disconnects.
+//!
+//! Jog commands move single joints of a paused robot on its stand (see the
+//! `jog` module); telemetry then carries the present joint positions.
+//!
+//! Dangerous commands (`torque_off`, which drops a walking robot, and
+//! `jog_start`, which switches torque on) take two steps: the server holds
+//! the command and answers `confirm_required` with a token, and only runs it
+//! if the same client sends `confirm` with that token and the server's key
+//! (`--remote-key`) within `CONFIRMATION_WINDOW`. The key is the permission:
+//! without one configured, dangerous commands are refused outright. Anything
+//! else is answered `confirm_rejected`. This only covers commands sent here
+//! over the WebSocket; the runtime does not fetch commands queued on the
+//! backend, which checks its own Cedar permissions.
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::{Message, WebSocket};
+
+use crate::bus_errors::BusStats;
+use crate::camera::{self, CameraRequest, FrameFeed};
+use crate::cloud::CloudStatus;
+use crate::controller::{CommandShaper, CommandSource, ControllerOutput};
+use crate::events::{Event, EventBus};
+use crate::eye_expression::EyeExpression;
+use crate::routines::Routine;
+
+/// TCP port the server listens on by default.
+pub const DEFAULT_PORT: u16 = 9871;
+
+/// Without velocity/head commands for this long, they are zeroed.
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Longest HTTP request head read while telling camera requests from
+/// WebSocket handshakes.
+const MAX_REQUEST_HEAD: usize = 4096;
+
+/// How often idle threads check for shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Commands queued between control ticks before new ones are dropped.
+const COMMAND_QUEUE: usize = 32;
+
+/// Time a client has to confirm a dangerous command.
+const CONFIRMATION_WINDOW: Duration = Duration::from_secs(10);
+
+/// Snapshot of the robot state published by the control loop.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Telemetry {
+    /// Seconds since the control loop started.
+    pub time: f64,
+    pub paused: bool,
+    pub active_policy: String,
+    pub joint_positions: Vec<f64>,
+    pub motor_targets: Vec<f64>,
+    /// [lin_vel_x, lin_vel_y, ang_vel, neck_pitch, head_pitch, head_yaw, head_roll]
+    pub commands: [f64; 7],
+    pub gyro: [f64; 3],
+    /// Roll, pitch, yaw in radians.
+    pub euler: [f64; 3],
+    pub projected_gravity: [f64; 3],
+    /// [left, right], 1.0 when in contact.
+    pub feet_contacts: [f64; 2],
+    /// Gait phase as [cos, sin].
+    pub phase: [f64; 2],
+    /// Duration of the last control tick in milliseconds.
+    pub loop_ms: f64,
+    pub cloud: CloudStatus,
+    /// Smoothed battery voltage and estimated charge (%), 0 until read.
+    pub battery_voltage: f64,
+    pub battery_percent: f64,
+    /// Battery-sag scale on the action scale and velocity range (0 until
+    /// the control loop reports it, 1 at full strength).
+    pub battery_scale: f64,
+    /// Servo bus error counters, default without a real bus.
+    pub bus: BusStats,
+    /// Whether jog mode is on.
+    pub jogging: bool,
+}
+
+/// Messages sent to clients.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    Hello { joint_names: &'a [String], rate: f64 },
+    Telemetry(&'a Telemetry),
+    Event { event: &'a Event },
+    /// A dangerous command is held until the client confirms it with `token`.
+    ConfirmRequired {
+        command: &'a RemoteCommand,
+        token: &'a str,
+        expires_in: f64,
+    },
+    ConfirmRejected { reason: &'a str },
+}
+
+/// Commands accepted from clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RemoteCommand {
+    Velocity {
+        #[serde(default)]
+        vx: f64,
+        #[serde(default)]
+        vy: f64,
+        #[serde(default)]
+        yaw: f64,
+    },
+    Head {
+        #[serde(default)]
+        neck_pitch: f64,
+        #[serde(default)]
+        head_pitch: f64,
+        #[serde(default)]
+        head_yaw: f64,
+        #[serde(default)]
+        head_roll: f64,
+    },
+    Pause,
+    Unpause,
+    /// Play a sound by file name, or a random one without a name.
+    PlaySound {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Stream a sound from an HTTP URL.
+    PlayUrl {
+        url: String,
+    },
+    /// Speak text (needs a voice, see the `tts` module).
+    Say {
+        text: String,
+    },
+    /// Set the speaker's master volume (0-1).
+    SetVolume {
+        volume: f64,
+    },
+    /// Stop every sound playing.
+    StopSounds,
+    /// Play a recorded emote by name.
+    PlayEmote {
+        name: String,
+    },
+    /// Queue an eye expression.
+    EyeExpression {
+        expression: EyeExpression,
+    },
+    /// Save a telemetry clip around now (see the `moments` module).
+    SaveMoment {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Run a calibration routine (see the `routines` module).
+    StartRoutine {
+        routine: Routine,
+    },
+    StopRoutine,
+    Estop,
+    Release,
+    /// Switch every servo's torque off until unpaused. Needs confirmation.
+    TorqueOff,
+    /// Enter jog mode while paused, switching torque on. Needs confirmation.
+    JogStart,
+    /// Move one joint's jog goal by `delta` (rad).
+    Jog {
+        joint: String,
+        delta: f64,
+    },
+    JogStop,
+    /// Confirm the dangerous command the server asked about, with the
+    /// server's key.
+    Confirm {
+        token: String,
+        #[serde(default)]
+        key: Option<String>,
+    },
+}
+
+impl RemoteCommand {
+    /// Whether the command must be confirmed before it runs.
+    pub fn is_dangerous(&self) -> bool {
+        matches!(self, RemoteCommand::TorqueOff | RemoteCommand::JogStart)
+    }
+}
+
+/// What to do with a command from a client.
+#[derive(Debug, PartialEq)]
+enum Gate {
+    /// Pass it on to the control loop.
+    Forward(RemoteCommand),
+    /// Hold it and ask the client to confirm with the token.
+    Confirm(RemoteCommand, String),
+    Reject(&'static str),
+}
+
+/// Per-client two-phase check for dangerous commands.
+struct ConfirmGate<'a> {
+    /// Key a confirmation must carry; None refuses dangerous commands.
+    key: Option<&'a str>,
+    /// Held command, its token and its deadline.
+    pending: Option<(RemoteCommand, String, Instant)>,
+}
+
+impl<'a> ConfirmGate<'a> {
+    fn new(key: Option<&'a str>) -> Self {
+        Self { key, pending: None }
+    }
+
+    fn check(&mut self, command: RemoteCommand, now: Instant) -> Gate {
+        match command {
+            RemoteCommand::Confirm { token, key } => match self.pending.take() {
+                Some((command, expected, deadline)) if token == expected => {
+                    if now > deadline {
+                        Gate::Reject("confirmation expired")
+                    } else if key.is_none() || key.as_deref() != self.key {
+                        Gate::Reject("wrong or missing key")
+                    } else {
+                        Gate::Forward(command)
+                    }
+                }
+                Some(pending) => {
+                    self.pending = Some(pending);
+                    Gate::Reject("wrong confirmation token")
+                }
+                None => Gate::Reject("nothing to confirm"),
+            },
+            command if command.is_dangerous() && self.key.is_none() => {
+                Gate::Reject("dangerous commands need the runtime started with --remote-key")
+            }
+            command if command.is_dangerous() => {
+                let token = format!("{:016x}", rand::random::<u64>());
+                self.pending = Some((command.clone(), token.clone(), now + CONFIRMATION_WINDOW));
+                Gate::Confirm(command, token)
+            }
+            command => Gate::Forward(command),
+        }
+    }
+}
+
+/// State shared between the control loop and the client threads.
+struct Shared {
+    latest: Mutex<Option<Telemetry>>,
+    joint_names: Vec<String>,
+    rate: f64,
+    bus: EventBus,
+    camera: Mutex<Option<FrameFeed>>,
+    stopped: AtomicBool,
+    /// Key that authorizes dangerous commands.
+    key: Option<String>,
+}
+
+/// Driving state folded from client commands, read by `RemoteInput`.
+#[derive(Debug, Default)]
+struct Driving {
+    commands: [f64; 7],
+    last_command: Option<Instant>,
+    estop: bool,
+}
+
+/// WebSocket server for browser teleop and telemetry.
+pub struct RemoteServer {
+    local_addr: SocketAddr,
+    shared: Arc<Shared>,
+    command_rx: Receiver<RemoteCommand>,
+    driving: Arc<Mutex<Driving>>,
+}
+
+impl RemoteServer {
+    /// Bind the listener and start accepting clients in the background.
+    /// Telemetry is pushed to each client `rate` times per second. Clients
+    /// confirm dangerous commands with `key`; without one they are refused.
+    pub fn bind(
+        addr: &str,
+        rate: f64,
+        joint_names: Vec<String>,
+        bus: &EventBus,
+        key: Option<String>,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .with_context(|| format!("Failed to bind remote server {}", addr))?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+
+        let shared = Arc::new(Shared {
+            latest: Mutex::new(None),
+            joint_names,
+            rate: rate.max(0.1),
+            bus: bus.clone(),
+            camera: Mutex::new(None),
+            stopped: AtomicBool::new(false),
+            key,
+        });
+        let (command_tx, command_rx) = bounded::<RemoteCommand>(COMMAND_QUEUE);
+
+        let worker_shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            accept_worker(listener, worker_shared, command_tx);
+        });
+
+        tracing::info!("Remote control server on ws://{} at {} Hz", local_addr, rate);
+
+        Ok(Self {
+            local_addr,
+            shared,
+            command_rx,
+            driving: Arc::new(Mutex::new(Driving::default())),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Make `telemetry` the state sent to clients on their next update.
+    pub fn publish(&self, telemetry: Telemetry) {
+        if let Ok(mut latest) = self.shared.latest.lock() {
+            *latest = Some(telemetry);
+        }
+    }
+
+    /// Serve `feed` over HTTP at `/camera.mjpg` and `/camera.jpg`.
+    pub fn serve_camera(&self, feed: FrameFeed) {
+        if let Ok(mut camera) = self.shared.camera.lock() {
+            *camera = Some(feed);
+        }
+        tracing::info!("Camera stream on http://{}/camera.mjpg", self.local_addr);
+    }
+
+    /// Drain commands received since the last call. Velocity, head and
+    /// e-stop commands are folded into the `input()` state; the rest are
+    /// returned.
+    pub fn poll(&mut self) -> Vec<RemoteCommand> {
+        let received: Vec<_> = self.command_rx.try_iter().collect();
+        self.apply(received, Instant::now())
+    }
+
+    fn apply(&mut self, received: Vec<RemoteCommand>, now: Instant) -> Vec<RemoteCommand> {
+        let mut events = Vec::new();
+        let Ok(mut driving) = self.driving.lock() else {
+            return events;
+        };
+        for command in received {
+            match command {
+                RemoteCommand::Velocity { vx, vy, yaw } => {
+                    driving.commands[..3].copy_from_slice(&[vx, vy, yaw]);
+                    CommandShaper::limit(&mut driving.commands);
+                    driving.last_command = Some(now);
+                }
+                RemoteCommand::Head {
+                    neck_pitch,
+                    head_pitch,
+                    head_yaw,
+                    head_roll,
+                } => {
+                    driving.commands[3..].copy_from_slice(&[neck_pitch, head_pitch, head_yaw, head_roll]);
+                    CommandShaper::limit(&mut driving.commands);
+                    driving.last_command = Some(now);
+                }
+                RemoteCommand::Estop => {
+                    if !driving.estop {
+                        tracing::warn!("Remote e-stop engaged");
+                    }
+                    driving.estop = true;
+                }
+                RemoteCommand::Release => {
+                    if driving.estop {
+                        tracing::info!("Remote e-stop released");
+                    }
+                    driving.estop = false;
+                }
+                event => events.push(event),
+            }
+        }
+
+        let stale = driving
+            .last_command
+            .is_some_and(|t| now.saturating_duration_since(t) > COMMAND_TIMEOUT);
+        if stale {
+            tracing::warn!("Remote commands silent for {:?}, stopping", COMMAND_TIMEOUT);
+            driving.commands = [0.0; 7];
+            driving.last_command = None;
+        }
+        events
+    }
+
+    /// The latest velocity and head commands, while a client is sending them.
+    pub fn commands(&self) -> Option<[f64; 7]> {
+        let driving = self.driving.lock().ok()?;
+        driving.last_command.map(|_| driving.commands)
+    }
+
+    /// Command source fed by this server's clients. Its state is updated
+    /// by `poll()`.
+    pub fn input(&self) -> RemoteInput {
+        RemoteInput {
+            driving: Arc::clone(&self.driving),
+            output: ControllerOutput::default(),
+        }
+    }
+
+    /// Signal the background threads to stop.
+    pub fn stop(&self) {
+        self.shared.stopped.store(true, Ordering::Release);
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Remote clients as a `CommandSource`: their velocity and head commands
+/// while fresh, and the e-stop.
+pub struct RemoteInput {
+    driving: Arc<Mutex<Driving>>,
+    output: ControllerOutput,
+}
+
+impl CommandSource for RemoteInput {
+    fn get_last_command(&mut self) -> &ControllerOutput {
+        if let Ok(driving) = self.driving.lock() {
+            self.output.commands = match driving.last_command {
+                Some(_) => driving.commands,
+                None => [0.0; 7],
+            };
+            self.output.estop = driving.estop;
+        }
+        &self.output
+    }
+}
+
+/// Background worker that accepts connections and spawns a thread per client.
+fn accept_worker(listener: TcpListener, shared: Arc<Shared>, command_tx: Sender<RemoteCommand>) {
+    while !shared.stopped.load(Ordering::Acquire) {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                let shared = Arc::clone(&shared);
+                let command_tx = command_tx.clone();
+                thread::spawn(move || {
+                    match client_worker(stream, peer, &shared, &command_tx) {
+                        Ok(()) => tracing::info!("Remote client {} disconnected", peer),
+                        Err(e) => tracing::info!("Remote client {} dropped: {:#}", peer, e),
+                    }
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                tracing::warn!("Remote server accept failed: {}", e);
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+
+    tracing::info!("Remote server thread exiting");
+}
+
+/// Serve one client: push telemetry at the configured rate, forward commands.
+fn client_worker(
+    stream: TcpStream,
+    peer: SocketAddr,
+    shared: &Shared,
+    command_tx: &Sender<RemoteCommand>,
+) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    let camera = shared.camera.lock().ok().and_then(|c| c.clone());
+    if let Some(feed) = camera {
+        if let Some(request) = camera_request(&stream)? {
+            tracing::info!("Camera client {} connected ({:?})", peer, request);
+            return camera::serve(&stream, request, &feed, || {
+                shared.stopped.load(Ordering::Acquire)
+            });
+        }
+    }
+    let mut ws = tungstenite::accept(stream).context("WebSocket handshake failed")?;
+    tracing::info!("Remote client {} connected", peer);
+    let events = shared.bus.subscribe();
+
+    let period = Duration::from_secs_f64(1.0 / shared.rate);
+    ws.get_ref().set_read_timeout(Some(period.min(POLL_INTERVAL)))?;
+
+    send(
+        &mut ws,
+        &ServerMessage::Hello {
+            joint_names: &shared.joint_names,
+            rate: shared.rate,
+        },
+    )?;
+
+    let mut gate = ConfirmGate::new(shared.key.as_deref());
+    let mut next_send = Instant::now();
+    while !shared.stopped.load(Ordering::Acquire) {
+        match ws.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str::<RemoteCommand>(&text) {
+                Ok(command) => match gate.check(command, Instant::now()) {
+                    Gate::Forward(command) => {
+                        if command.is_dangerous() {
+                            tracing::warn!("Remote client {} confirmed {:?}", peer, command);
+                        }
+                        if command_tx.try_send(command).is_err() {
+                            tracing::debug!("Remote command queue full, dropping command");
+                        }
+                    }
+                    Gate::Confirm(command, token) => {
+                        tracing::info!("Remote client {}: {:?} awaits confirmation", peer, command);
+                        let message = ServerMessage::ConfirmRequired {
+                            command: &command,
+                            token: &token,
+                            expires_in: CONFIRMATION_WINDOW.as_secs_f64(),
+                        };
+                        send(&mut ws, &message)?;
+                    }
+                    Gate::Reject(reason) => {
+                        tracing::warn!("Remote client {}: confirmation refused: {}", peer, reason);
+                        send(&mut ws, &ServerMessage::ConfirmRejected { reason })?;
+                    }
+                },
+                Err(e) => tracing::debug!("Remote client {}: bad command: {}", peer, e),
+            },
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+
+        for event in events.try_iter() {
+            send(&mut ws, &ServerMessage::Event { event: &event })?;
+        }
+
+        let now = Instant::now();
+        if now >= next_send {
+            next_send = now + period;
+            let latest = shared.latest.lock().ok().and_then(|t| t.clone());
+            if let Some(telemetry) = latest {
+                send(&mut ws, &ServerMessage::Telemetry(&telemetry))?;
+            }
+        }
+    }
+
+    let _ = ws.close(None);
+    Ok(())
+}
+
+/// Peek at the request head: a camera request is consumed and returned,
+/// anything else is left for the WebSocket handshake.
+fn camera_request(mut stream: &TcpStream) -> Result<Option<CameraRequest>> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut head = vec![0u8; MAX_REQUEST_HEAD];
+    let len = loop {
+        let n = stream.peek(&mut head)?;
+        if let Some(end) = head[..n].windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if n == 0 || n == head.len() {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(5));
+    };
+    let request = CameraRequest::parse(&String::from_utf8_lossy(&head[..len]));
+    if request.is_some() {
+        stream.read_exact(&mut head[..len])?;
+    }
+    stream.set_read_timeout(None)?;
+    Ok(request)
+}
+
+fn send(ws: &mut WebSocket<TcpStream>, message: &ServerMessage) -> Result<()> {
+    let text = serde_json::to_string(message)?;
+    ws.send(Message::text(text))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        let cmd: RemoteCommand =
+            serde_json::from_str(r#"{"type": "velocity", "vx": 0.1, "yaw": -0.2}"#).unwrap();
+        assert_eq!(
+            cmd,
+            RemoteCommand::Velocity {
+                vx: 0.1,
+                vy: 0.0,
+                yaw: -0.2
+            }
+        );
+        let cmd: RemoteCommand = serde_json::from_str(r#"{"type": "play_sound"}"#).unwrap();
+        assert_eq!(cmd, RemoteCommand::PlaySound { name: None });
+        let cmd: RemoteCommand =
+            serde_json::from_str(r#"{"type": "jog", "joint": "left_knee", "delta": -0.05}"#).unwrap();
+        assert!(!cmd.is_dangerous());
+        assert!(RemoteCommand::JogStart.is_dangerous());
+        assert!(serde_json::from_str::<RemoteCommand>(r#"{"type": "dance"}"#).is_err());
+    }
+
+    #[test]
+    fn test_client_gets_telemetry_and_drives() {
+        let bus = EventBus::new();
+        let mut server =
+            RemoteServer::bind("127.0.0.1:0", 50.0, vec!["left_knee".into()], &bus, None).unwrap();
+        server.publish(Telemetry {
+            loop_ms: 3.5,
+            ..Telemetry::default()
+        });
+
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        let url = format!("ws://{}/", server.local_addr());
+        let (mut client, _) = tungstenite::client(url, stream).unwrap();
+
+        let hello: serde_json::Value =
+            serde_json::from_str(client.read().unwrap().to_text().unwrap()).unwrap();
+        assert_eq!(hello["type"], "hello");
+        assert_eq!(hello["joint_names"][0], "left_knee");
+        let telemetry: serde_json::Value =
+            serde_json::from_str(client.read().unwrap().to_text().unwrap()).unwrap();
+        assert_eq!(telemetry["type"], "telemetry");
+        assert_eq!(telemetry["loop_ms"], 3.5);
+
+        client
+            .send(Message::text(r#"{"type": "velocity", "vx": 0.15}"#))
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while server.commands().is_none() && Instant::now() < deadline {
+            server.poll();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(server.commands().unwrap()[0], 0.15);
+    }
+
+    #[test]
+    fn test_commands_held_then_released() {
+        let mut server =
+            RemoteServer::bind("127.0.0.1:0", 10.0, Vec::new(), &EventBus::new(), None).unwrap();
+        let t0 = Instant::now();
+        assert_eq!(server.commands(), None);
+
+        let events = server.apply(
+            vec![
+                RemoteCommand::Velocity {
+                    vx: 0.1,
+                    vy: 0.0,
+                    yaw: 0.0,
+                },
+                RemoteCommand::Pause,
+            ],
+            t0,
+        );
+        assert_eq!(events, vec![RemoteCommand::Pause]);
+        assert_eq!(server.commands().unwrap()[0], 0.1);
+
+        server.apply(Vec::new(), t0 + COMMAND_TIMEOUT / 2);
+        assert_eq!(server.commands().unwrap()[0], 0.1);
+
+        server.apply(Vec::new(), t0 + COMMAND_TIMEOUT * 2);
+        assert_eq!(server.commands(), None);
+    }
+
+    #[test]
+    fn test_dangerous_commands_need_confirmation() {
+        let mut gate = ConfirmGate::new(Some("secret"));
+        let key = Some("secret".to_string());
+        let t0 = Instant::now();
+        assert_eq!(gate.check(RemoteCommand::Pause, t0), Gate::Forward(RemoteCommand::Pause));
+
+        let Gate::Confirm(_, token) = gate.check(RemoteCommand::TorqueOff, t0) else {
+            panic!("torque_off went through unconfirmed");
+        };
+        let wrong = RemoteCommand::Confirm { token: "nope".into(), key: key.clone() };
+        assert!(matches!(gate.check(wrong, t0), Gate::Reject(_)));
+        let confirm = RemoteCommand::Confirm { token: token.clone(), key: key.clone() };
+        assert_eq!(gate.check(confirm.clone(), t0), Gate::Forward(RemoteCommand::TorqueOff));
+        // Tokens are single use
+        assert!(matches!(gate.check(confirm, t0), Gate::Reject(_)));
+
+        let Gate::Confirm(_, token) = gate.check(RemoteCommand::TorqueOff, t0) else {
+            panic!("torque_off went through unconfirmed");
+        };
+        let late = t0 + CONFIRMATION_WINDOW * 2;
+        let confirm = RemoteCommand::Confirm { token, key };
+        assert!(matches!(gate.check(confirm, late), Gate::Reject(_)));
+    }
+
+    #[test]
+    fn test_dangerous_commands_need_the_key() {
+        // Without a key configured, nobody may
+        let mut gate = ConfirmGate::new(None);
+        let t0 = Instant::now();
+        assert!(matches!(gate.check(RemoteCommand::JogStart, t0), Gate::Reject(_)));
+
+        // A client without the key can't confirm its own command
+        let mut gate = ConfirmGate::new(Some("secret"));
+        for key in [None, Some("guess".to_string())] {
+            let Gate::Confirm(_, token) = gate.check(RemoteCommand::TorqueOff, t0) else {
+                panic!("torque_off went through unconfirmed");
+            };
+            let confirm = RemoteCommand::Confirm { token, key };
+            assert!(matches!(gate.check(confirm, t0), Gate::Reject(_)));
+        }
+    }
+
+    #[test]
+    fn test_estop_latches_until_release() {
+        let mut server =
+            RemoteServer::bind("127.0.0.1:0", 10.0, Vec::new(), &EventBus::new(), None).unwrap();
+        let mut input = server.input();
+        let t0 = Instant::now();
+
+        server.apply(vec![RemoteCommand::Estop], t0);
+        assert!(input.get_last_command().estop);
+
+        // Still engaged after the client goes quiet
+        server.apply(Vec::new(), t0 + COMMAND_TIMEOUT * 2);
+        assert!(input.get_last_command().estop);
+
+        server.apply(vec![RemoteCommand::Release], t0 + COMMAND_TIMEOUT * 3);
+        assert!(!input.get_last_command().estop);
+    }
+}
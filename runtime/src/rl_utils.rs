@@ -2,6 +2,8 @@
 //!
 //! Replaces `rl_utils.py`.
 
+use anyhow::{bail, Result};
+
 /// Mujoco joint ordering (matches the ONNX model output).
 pub const MUJOCO_JOINTS_ORDER: &[&str] = &[
     "left_hip_yaw",
@@ -22,6 +24,104 @@ pub const MUJOCO_JOINTS_ORDER: &[&str] = &[
     "right_ankle",
 ];
 
+/// Maps joint-indexed values between a policy's joint order and the
+/// hardware's, by joint name.
+///
+/// Joints the policy has but the robot lacks read as 0 in observations;
+/// joints the robot has but the policy doesn't drive get a 0 action, which
+/// holds them at their standing pose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JointMap {
+    /// Hardware index of each policy joint.
+    hardware_index: Vec<Option<usize>>,
+    hardware_len: usize,
+}
+
+impl JointMap {
+    pub fn new<P: AsRef<str>, H: AsRef<str>>(policy: &[P], hardware: &[H]) -> Result<Self> {
+        let policy: Vec<&str> = policy.iter().map(AsRef::as_ref).collect();
+        let hardware: Vec<&str> = hardware.iter().map(AsRef::as_ref).collect();
+        for (i, name) in policy.iter().enumerate() {
+            if policy[..i].contains(name) {
+                bail!("Joint {:?} appears twice in the policy's joint order", name);
+            }
+        }
+        let hardware_index: Vec<Option<usize>> = policy
+            .iter()
+            .map(|name| hardware.iter().position(|h| h == name))
+            .collect();
+        if !policy.is_empty() && hardware_index.iter().all(Option::is_none) {
+            bail!("None of the policy's joints ({}) are on the robot", policy.join(", "));
+        }
+
+        let missing: Vec<&str> = policy
+            .iter()
+            .zip(&hardware_index)
+            .filter_map(|(name, index)| index.is_none().then_some(*name))
+            .collect();
+        if !missing.is_empty() {
+            tracing::warn!("Policy joints not on the robot, observed as 0: {}", missing.join(", "));
+        }
+        let undriven: Vec<&str> = hardware
+            .iter()
+            .filter(|name| !policy.contains(name))
+            .copied()
+            .collect();
+        if !undriven.is_empty() {
+            tracing::warn!("Joints the policy doesn't drive, held still: {}", undriven.join(", "));
+        }
+        Ok(Self {
+            hardware_index,
+            hardware_len: hardware.len(),
+        })
+    }
+
+    /// The joint order assumed for a policy that doesn't state its own:
+    /// `MUJOCO_JOINTS_ORDER` without the joints the robot lacks, or the
+    /// hardware order for robots with joints it doesn't name.
+    pub fn default_order<H: AsRef<str>>(hardware: &[H]) -> Vec<String> {
+        if hardware.iter().all(|h| MUJOCO_JOINTS_ORDER.contains(&h.as_ref())) {
+            MUJOCO_JOINTS_ORDER
+                .iter()
+                .filter(|name| hardware.iter().any(|h| h.as_ref() == **name))
+                .map(|name| name.to_string())
+                .collect()
+        } else {
+            hardware.iter().map(|h| h.as_ref().to_string()).collect()
+        }
+    }
+
+    /// Number of joints in the policy's order.
+    pub fn policy_len(&self) -> usize {
+        self.hardware_index.len()
+    }
+
+    /// Whether both orders are the same, so values need no remapping.
+    pub fn is_identity(&self) -> bool {
+        self.hardware_len == self.hardware_index.len()
+            && self.hardware_index.iter().enumerate().all(|(i, index)| *index == Some(i))
+    }
+
+    /// Hardware-ordered values (positions, velocities) in policy order.
+    pub fn to_policy(&self, hardware: &[f64]) -> Vec<f64> {
+        self.hardware_index
+            .iter()
+            .map(|index| index.and_then(|i| hardware.get(i).copied()).unwrap_or(0.0))
+            .collect()
+    }
+
+    /// A policy-ordered action in hardware order.
+    pub fn to_hardware(&self, policy: &[f64]) -> Vec<f64> {
+        let mut hardware = vec![0.0; self.hardware_len];
+        for (value, index) in policy.iter().zip(&self.hardware_index) {
+            if let Some(i) = index {
+                hardware[*i] = *value;
+            }
+        }
+        hardware
+    }
+}
+
 /// Convert action-scale offsets to absolute PD targets.
 #[inline]
 pub fn action_to_pd_targets(action: &[f64], offset: &[f64], scale: f64) -> Vec<f64> {
@@ -142,6 +242,23 @@ impl LowPassActionFilter {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_joint_map_round_trips_between_orders() {
+        let hardware = ["left_knee", "right_knee", "head_yaw"];
+        assert_eq!(JointMap::default_order(&hardware), ["left_knee", "head_yaw", "right_knee"]);
+        assert!(JointMap::new(&hardware, &hardware).unwrap().is_identity());
+
+        // The policy swaps the knees, adds an antenna and leaves the head out
+        let map = JointMap::new(&["right_knee", "left_antenna", "left_knee"], &hardware).unwrap();
+        assert!(!map.is_identity());
+        assert_eq!(map.policy_len(), 3);
+        assert_eq!(map.to_policy(&[1.0, 2.0, 3.0]), [2.0, 0.0, 1.0]);
+        assert_eq!(map.to_hardware(&[2.0, 9.0, 1.0]), [1.0, 2.0, 0.0]);
+
+        assert!(JointMap::new(&["left_knee", "left_knee"], &hardware).is_err());
+        assert!(JointMap::new(&["left_antenna"], &hardware).is_err());
+    }
+
     #[test]
     fn test_low_pass_filter_converges() {
         let mut filter = LowPassActionFilter::new(50.0, 30.0);
@@ -0,0 +1,298 @@
+//! Robot model: the joints of the hardware variant being driven.
+//!
+//! Lists the joints in policy order, each with its servo ID on the bus, its
+//! standing pose and its mechanical limits, so variants with extra joints
+//! (actuated antennas, ankles with roll) run from the same binary.
+//! `robot_model` in `duck_config.json` names a built-in model or defines
+//! one:
+//!
+//! ```json
+//! "robot_model": "mini_v2_16dof"
+//!
+//! "robot_model": {
+//!   "name": "my_duck",
+//!   "joints": [
+//!     { "name": "left_hip_yaw", "id": 20, "init": 0.002, "limits": [-0.524, 0.524] },
+//!     ...
+//!   ]
+//! }
+//! ```
+//!
+//! `joint_limits` in the duck config still overrides the model's limits.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::joint_limits::default_joint_limits;
+
+/// The 14-joint Open Duck Mini v2 (the default).
+pub const MINI_V2: &str = "mini_v2";
+
+/// The Open Duck Mini v2 with bus-servo antennas (16 joints).
+pub const MINI_V2_16DOF: &str = "mini_v2_16dof";
+
+/// Open Duck Mini v2 joints in policy order: name, servo ID, standing pose.
+const MINI_V2_JOINTS: [(&str, u8, f64); 14] = [
+    ("left_hip_yaw", 20, 0.002),
+    ("left_hip_roll", 21, 0.053),
+    ("left_hip_pitch", 22, -0.63),
+    ("left_knee", 23, 1.368),
+    ("left_ankle", 24, -0.784),
+    ("neck_pitch", 30, 0.0),
+    ("head_pitch", 31, 0.0),
+    ("head_yaw", 32, 0.0),
+    ("head_roll", 33, 0.0),
+    ("right_hip_yaw", 10, -0.003),
+    ("right_hip_roll", 11, -0.065),
+    ("right_hip_pitch", 12, 0.635),
+    ("right_knee", 13, 1.379),
+    ("right_ankle", 14, -0.796),
+];
+
+/// Antenna joints of the 16-joint variant, on the head's ID block.
+const ANTENNA_JOINTS: [(&str, u8, f64); 2] =
+    [("left_antenna", 34, 0.0), ("right_antenna", 35, 0.0)];
+
+/// Antenna travel (rad).
+const ANTENNA_LIMITS: [f64; 2] = [-1.571, 1.571];
+
+/// One joint of the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JointSpec {
+    pub name: String,
+    /// Servo ID on the bus.
+    pub id: u8,
+    /// Standing pose (rad).
+    #[serde(default)]
+    pub init: f64,
+    /// Mechanical limits [min, max] (rad).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<[f64; 2]>,
+}
+
+/// Joints of a hardware variant, in policy order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ModelSpec")]
+pub struct RobotModel {
+    pub name: String,
+    pub joints: Vec<JointSpec>,
+}
+
+/// What `robot_model` may hold in the config: a built-in name or a model.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ModelSpec {
+    Preset(String),
+    Custom {
+        #[serde(default = "default_custom_name")]
+        name: String,
+        joints: Vec<JointSpec>,
+    },
+}
+
+fn default_custom_name() -> String {
+    "custom".to_string()
+}
+
+impl TryFrom<ModelSpec> for RobotModel {
+    type Error = anyhow::Error;
+
+    fn try_from(spec: ModelSpec) -> Result<Self> {
+        let model = match spec {
+            ModelSpec::Preset(name) => match Self::preset(&name) {
+                Some(model) => model,
+                None => bail!(
+                    "Unknown robot model {:?} (built in: {}, {})",
+                    name,
+                    MINI_V2,
+                    MINI_V2_16DOF
+                ),
+            },
+            ModelSpec::Custom { name, joints } => Self { name, joints },
+        };
+        model.validate()?;
+        Ok(model)
+    }
+}
+
+impl Default for RobotModel {
+    fn default() -> Self {
+        Self::mini_v2()
+    }
+}
+
+impl RobotModel {
+    /// A built-in model by name.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            MINI_V2 => Some(Self::mini_v2()),
+            MINI_V2_16DOF => {
+                let mut model = Self::mini_v2();
+                model.name = MINI_V2_16DOF.to_string();
+                let head_end = model
+                    .index_of("head_roll")
+                    .map_or(model.joints.len(), |i| i + 1);
+                let antennas = ANTENNA_JOINTS.iter().map(|&(name, id, init)| JointSpec {
+                    name: name.to_string(),
+                    id,
+                    init,
+                    limits: Some(ANTENNA_LIMITS),
+                });
+                model.joints.splice(head_end..head_end, antennas);
+                Some(model)
+            }
+            _ => None,
+        }
+    }
+
+    /// The 14-joint Open Duck Mini v2.
+    pub fn mini_v2() -> Self {
+        let limits = default_joint_limits();
+        Self {
+            name: MINI_V2.to_string(),
+            joints: MINI_V2_JOINTS
+                .iter()
+                .map(|&(name, id, init)| JointSpec {
+                    name: name.to_string(),
+                    id,
+                    init,
+                    limits: limits.get(name).copied(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Reject models the bus can't drive: no joints, or a name or servo ID
+    /// used twice.
+    pub fn validate(&self) -> Result<()> {
+        if self.joints.is_empty() {
+            bail!("Robot model {:?} has no joints", self.name);
+        }
+        let mut names = HashSet::new();
+        let mut ids = HashSet::new();
+        for joint in &self.joints {
+            if !names.insert(joint.name.as_str()) {
+                bail!(
+                    "Robot model {:?} lists joint {:?} twice",
+                    self.name,
+                    joint.name
+                );
+            }
+            if !ids.insert(joint.id) {
+                bail!(
+                    "Robot model {:?} gives servo ID {} to two joints",
+                    self.name,
+                    joint.id
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn num_dofs(&self) -> usize {
+        self.joints.len()
+    }
+
+    pub fn joint_names(&self) -> Vec<String> {
+        self.joints.iter().map(|j| j.name.clone()).collect()
+    }
+
+    pub fn joint_ids(&self) -> Vec<u8> {
+        self.joints.iter().map(|j| j.id).collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.index_of(name).is_some()
+    }
+
+    /// Position of the named joint in policy order.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|j| j.name == name)
+    }
+
+    /// Joint driven by servo `id`.
+    pub fn joint_for_id(&self, id: u8) -> Option<&str> {
+        self.joints
+            .iter()
+            .find(|j| j.id == id)
+            .map(|j| j.name.as_str())
+    }
+
+    /// Standing pose by joint name.
+    pub fn init_positions(&self) -> HashMap<String, f64> {
+        self.joints
+            .iter()
+            .map(|j| (j.name.clone(), j.init))
+            .collect()
+    }
+
+    /// Limits by joint name, for the joints that have them.
+    pub fn joint_limits(&self) -> HashMap<String, [f64; 2]> {
+        self.joints
+            .iter()
+            .filter_map(|j| j.limits.map(|l| (j.name.clone(), l)))
+            .collect()
+    }
+
+    /// The named joints in policy order, with their servo IDs.
+    pub fn select(&self, names: &[String]) -> Result<(Vec<String>, Vec<u8>)> {
+        if let Some(unknown) = names.iter().find(|n| !self.contains(n)) {
+            bail!(
+                "Unknown joint {:?} for robot model {:?}",
+                unknown,
+                self.name
+            );
+        }
+        Ok(self
+            .joints
+            .iter()
+            .filter(|j| names.contains(&j.name))
+            .map(|j| (j.name.clone(), j.id))
+            .unzip())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_and_custom_models() {
+        let mini: RobotModel = serde_json::from_str(r#""mini_v2""#).unwrap();
+        assert_eq!(mini, RobotModel::default());
+        assert_eq!(mini.num_dofs(), 14);
+        assert_eq!(mini.joint_for_id(23), Some("left_knee"));
+
+        let antennas: RobotModel = serde_json::from_str(r#""mini_v2_16dof""#).unwrap();
+        assert_eq!(antennas.num_dofs(), 16);
+        assert_eq!(antennas.index_of("left_antenna"), Some(9));
+        assert_eq!(antennas.index_of("right_hip_yaw"), Some(11));
+
+        let custom = r#"{"joints": [{"name": "knee", "id": 3, "init": 1.2},
+                                    {"name": "ankle", "id": 4}]}"#;
+        let custom: RobotModel = serde_json::from_str(custom).unwrap();
+        assert_eq!(custom.name, "custom");
+        assert_eq!(custom.init_positions()["knee"], 1.2);
+        assert!(custom.joint_limits().is_empty());
+
+        // A saved model reads back as written
+        let json = serde_json::to_string(&antennas).unwrap();
+        assert_eq!(serde_json::from_str::<RobotModel>(&json).unwrap(), antennas);
+
+        assert!(serde_json::from_str::<RobotModel>(r#""mini_v3""#).is_err());
+        let twice = r#"{ "joints": [{ "name": "a", "id": 1 }, { "name": "b", "id": 1 }] }"#;
+        assert!(serde_json::from_str::<RobotModel>(twice).is_err());
+    }
+
+    #[test]
+    fn test_select_keeps_policy_order() {
+        let model = RobotModel::default();
+        let (names, ids) = model
+            .select(&["right_knee".to_string(), "left_knee".to_string()])
+            .unwrap();
+        assert_eq!(names, ["left_knee", "right_knee"]);
+        assert_eq!(ids, [23, 13]);
+        assert!(model.select(&["tail".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! Calibration routines: walk in place and the step test.
+//!
+//! Both run the walking policy with the gait phase going and check the legs
+//! against each other, for verifying foot contact sensors and measuring
+//! per-leg stability after mechanical changes:
+//!
+//! - `walk_in_place` holds zero velocity for `walk_in_place_secs`. Each gait
+//!   cycle should bring one touchdown per foot.
+//! - `step_test` shifts the weight from leg to leg `step_test_shifts` times
+//!   with short sideways commands, `shift_secs` each.
+//!
+//! While running, touchdowns and stance time are counted per foot, and body
+//! sway (angular rate RMS) and peak tilt are measured while each leg alone
+//! carries the duck. The report flags feet that never touch down or never
+//! lift, touchdown counts far from the gait cycles, and a leg much less
+//! stable than the other. Routines are started from the gamepad (LB + Y,
+//! LB + X) or the remote (`start_routine`); pausing, a fall, or any other
+//! input driving stops them early.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::{PI, TAU};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::config::RoutinesConfig;
+
+/// Contact value above which a foot counts as down.
+const CONTACT_THRESHOLD: f64 = 0.5;
+
+/// Touchdowns per gait cycle may be off by this share before it's flagged.
+const STEP_TOLERANCE: f64 = 0.25;
+
+/// A leg swaying this many times more than the other is flagged.
+const SWAY_RATIO_WARN: f64 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Routine {
+    WalkInPlace,
+    StepTest,
+}
+
+impl Routine {
+    pub const ALL: [Routine; 2] = [Routine::WalkInPlace, Routine::StepTest];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Routine::WalkInPlace => "walk_in_place",
+            Routine::StepTest => "step_test",
+        }
+    }
+}
+
+impl FromStr for Routine {
+    type Err = anyhow::Error;
+
+    /// Accepts `walk_in_place` and `walk-in-place`.
+    fn from_str(name: &str) -> Result<Self> {
+        let name = name.replace('-', "_");
+        Self::ALL.into_iter().find(|r| r.name() == name).ok_or_else(|| {
+            let names: Vec<_> = Self::ALL.iter().map(|r| r.name()).collect();
+            anyhow!("Unknown routine {:?} (expected one of {})", name, names.join(", "))
+        })
+    }
+}
+
+/// What one leg did during a routine.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LegStats {
+    pub touchdowns: u32,
+    /// Share of the routine with the foot down (0-1).
+    pub stance: f64,
+    /// Angular rate RMS (rad/s) while this leg alone was down.
+    pub sway_rms: f64,
+    /// Largest tilt (rad) while this leg alone was down.
+    pub max_tilt: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutineReport {
+    pub routine: Routine,
+    /// Seconds run.
+    pub duration: f64,
+    /// False if it was stopped early.
+    pub completed: bool,
+    /// Full gait cycles run: the touchdowns each foot should have.
+    pub gait_cycles: u32,
+    /// [left, right]
+    pub legs: [LegStats; 2],
+    pub warnings: Vec<String>,
+}
+
+impl fmt::Display for RoutineReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = if self.completed { "complete" } else { "stopped early" };
+        writeln!(
+            f,
+            "{} {} after {:.1}s, {} gait cycles",
+            self.routine.name(),
+            status,
+            self.duration,
+            self.gait_cycles
+        )?;
+        for (side, leg) in ["left", "right"].iter().zip(&self.legs) {
+            writeln!(
+                f,
+                "  {:<5}  {:>3} touchdowns  stance {:>3.0}%  sway {:.3} rad/s  \
+                 max tilt {:.3} rad",
+                side,
+                leg.touchdowns,
+                leg.stance * 100.0,
+                leg.sway_rms,
+                leg.max_tilt
+            )?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "  ! {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct LegTally {
+    touchdowns: u32,
+    down: u32,
+    alone: u32,
+    sway_sq: f64,
+    max_tilt: f64,
+}
+
+/// A routine being run: its commands and what the legs did.
+#[derive(Debug, Clone)]
+pub struct RoutineRun {
+    routine: Routine,
+    config: RoutinesConfig,
+    started: Instant,
+    last: Instant,
+    samples: u32,
+    legs: [LegTally; 2],
+    was_down: Option<[bool; 2]>,
+    last_phase: Option<f64>,
+    gait_cycles: u32,
+}
+
+impl RoutineRun {
+    pub fn new(routine: Routine, config: &RoutinesConfig, now: Instant) -> Self {
+        Self {
+            routine,
+            config: config.clone(),
+            started: now,
+            last: now,
+            samples: 0,
+            legs: Default::default(),
+            was_down: None,
+            last_phase: None,
+            gait_cycles: 0,
+        }
+    }
+
+    pub fn routine(&self) -> Routine {
+        self.routine
+    }
+
+    /// Total length (s).
+    pub fn duration(&self) -> f64 {
+        match self.routine {
+            Routine::WalkInPlace => self.config.walk_in_place_secs,
+            Routine::StepTest => self.config.step_test_shifts as f64 * self.config.shift_secs,
+        }
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        self.elapsed(now) >= self.duration()
+    }
+
+    /// Commands at `now`: zero, plus the sideways shifts of the step test
+    /// (left first).
+    pub fn commands(&self, now: Instant) -> [f64; 7] {
+        let mut commands = [0.0; 7];
+        if self.routine == Routine::StepTest && !self.is_done(now) {
+            let shift_secs = self.config.shift_secs.max(0.1);
+            let t = self.elapsed(now);
+            let side = if ((t / shift_secs) as u64).is_multiple_of(2) { 1.0 } else { -1.0 };
+            let envelope = (PI * (t % shift_secs) / shift_secs).sin();
+            commands[1] = side * self.config.shift_speed * envelope;
+        }
+        commands
+    }
+
+    /// Take this tick's foot contacts, gait phase ([cos, sin]), angular rate
+    /// and tilt.
+    pub fn observe(
+        &mut self,
+        now: Instant,
+        feet: [f64; 2],
+        phase: [f64; 2],
+        gyro: [f64; 3],
+        tilt: f64,
+    ) {
+        self.last = now;
+        self.samples += 1;
+        let down = feet.map(|f| f > CONTACT_THRESHOLD);
+        let sway_sq: f64 = gyro.iter().map(|g| g * g).sum();
+        for (i, leg) in self.legs.iter_mut().enumerate() {
+            if down[i] {
+                leg.down += 1;
+                if self.was_down.is_some_and(|was| !was[i]) {
+                    leg.touchdowns += 1;
+                }
+                if !down[1 - i] {
+                    leg.alone += 1;
+                    leg.sway_sq += sway_sq;
+                    leg.max_tilt = leg.max_tilt.max(tilt);
+                }
+            }
+        }
+        self.was_down = Some(down);
+
+        // A cycle ends each time the phase angle wraps around
+        let angle = phase[1].atan2(phase[0]).rem_euclid(TAU);
+        if self.last_phase.is_some_and(|last| angle < last - PI) {
+            self.gait_cycles += 1;
+        }
+        self.last_phase = Some(angle);
+    }
+
+    pub fn report(&self, completed: bool) -> RoutineReport {
+        let samples = self.samples.max(1) as f64;
+        let legs = self.legs.clone().map(|leg| LegStats {
+            touchdowns: leg.touchdowns,
+            stance: leg.down as f64 / samples,
+            sway_rms: if leg.alone > 0 {
+                (leg.sway_sq / leg.alone as f64).sqrt()
+            } else {
+                0.0
+            },
+            max_tilt: leg.max_tilt,
+        });
+
+        let mut warnings = Vec::new();
+        for (side, (leg, tally)) in ["left", "right"].iter().zip(legs.iter().zip(&self.legs)) {
+            if self.samples > 0 && tally.down == 0 {
+                warnings.push(format!("{} foot never touched down: check its sensor", side));
+            } else if self.samples > 0 && tally.down == self.samples {
+                warnings.push(format!("{} foot never lifted: check its sensor", side));
+            } else if self.routine == Routine::WalkInPlace && self.gait_cycles > 0 {
+                let expected = self.gait_cycles as f64;
+                if (leg.touchdowns as f64 - expected).abs() > expected * STEP_TOLERANCE {
+                    warnings.push(format!(
+                        "{} foot touched down {} times in {} gait cycles",
+                        side, leg.touchdowns, self.gait_cycles
+                    ));
+                }
+            }
+        }
+        let [left, right] = [legs[0].sway_rms, legs[1].sway_rms];
+        if left > 0.0 && right > 0.0 {
+            let (worse, ratio) = if left > right {
+                ("left", left / right)
+            } else {
+                ("right", right / left)
+            };
+            if ratio > SWAY_RATIO_WARN {
+                warnings.push(format!("{} leg sways {:.1}x more than the other", worse, ratio));
+            }
+        }
+
+        RoutineReport {
+            routine: self.routine,
+            duration: self.elapsed(self.last),
+            completed,
+            gait_cycles: self.gait_cycles,
+            legs,
+            warnings,
+        }
+    }
+
+    fn elapsed(&self, now: Instant) -> f64 {
+        now.saturating_duration_since(self.started).as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_walk_in_place_counts_steps_per_foot() {
+        let config = RoutinesConfig::default();
+        let start = Instant::now();
+        let mut run = RoutineRun::new("walk-in-place".parse().unwrap(), &config, start);
+
+        // Four gait cycles of 50 ticks; the left foot steps every cycle and
+        // the right foot's sensor is stuck down
+        for tick in 0..200u64 {
+            let now = start + Duration::from_millis(tick * 20);
+            let angle = (tick % 50) as f64 / 50.0 * 2.0 * PI;
+            let left = if tick % 50 < 25 { 1.0 } else { 0.0 };
+            let gyro = [0.1, 0.0, 0.0];
+            run.observe(now, [left, 1.0], [angle.cos(), angle.sin()], gyro, 0.05);
+            assert_eq!(run.commands(now), [0.0; 7]);
+        }
+
+        let report = run.report(false);
+        assert_eq!(report.gait_cycles, 3);
+        assert_eq!(report.legs[0].touchdowns, 3);
+        assert_eq!(report.legs[0].stance, 0.5);
+        assert_eq!(report.legs[1].stance, 1.0);
+        assert!((report.legs[1].sway_rms - 0.1).abs() < 1e-9);
+        assert_eq!(report.warnings, ["right foot never lifted: check its sensor"]);
+
+        let step_test = RoutineRun::new(Routine::StepTest, &config, start);
+        let half_shift = Duration::from_secs_f64(config.shift_secs / 2.0);
+        assert!((step_test.commands(start + half_shift)[1] - config.shift_speed).abs() < 1e-9);
+        let next_shift = start + half_shift * 3;
+        assert!((step_test.commands(next_shift)[1] + config.shift_speed).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,262 @@
+//! Control-loop health: how well the runtime itself is doing.
+//!
+//! Network reachability says nothing about whether the duck can still walk.
+//! `RuntimeHealth` summarizes the control loop's condition — timing margin,
+//! servo bus error rate, IMU staleness and the safety state — and is sent
+//! with every heartbeat so the fleet backend sees it. The same payload is
+//! served locally as one JSON line to anyone connecting to the health Unix
+//! socket (`--health-socket`), e.g. `socat - UNIX-CONNECT:/tmp/openduckrust.sock`.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Smoothing of the loop margin, in ticks.
+const MARGIN_WINDOW: f64 = 50.0;
+
+/// Bus error rate above which the loop counts as degraded.
+const DEGRADED_BUS_ERROR_RATE: f64 = 0.05;
+
+/// IMU samples older than this count as stale (ms).
+const STALE_IMU_MS: f64 = 100.0;
+
+/// What the safety logic is currently doing with the robot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyState {
+    #[default]
+    Running,
+    Paused,
+    /// Torque off after a fault, until unpaused.
+    SoftStopped,
+    Estopped,
+    Fallen,
+}
+
+/// Snapshot of the control loop's condition.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeHealth {
+    /// Smoothed share of the control period left after each tick (negative
+    /// when the loop overruns).
+    pub loop_margin: f64,
+    /// Slowest tick since the previous snapshot (ms).
+    pub worst_loop_ms: f64,
+    /// Ticks that overran the period since startup.
+    pub overruns: u64,
+    /// Smoothed share of failed servo bus transactions.
+    pub bus_error_rate: f64,
+    /// Age of the newest IMU sample (ms), if the IMU reports it.
+    pub imu_age_ms: Option<f64>,
+    pub safety: SafetyState,
+}
+
+impl RuntimeHealth {
+    /// Why the loop is degraded, if it is.
+    pub fn degraded_reason(&self) -> Option<&'static str> {
+        if self.loop_margin < 0.0 {
+            Some("control loop overrunning")
+        } else if self.bus_error_rate > DEGRADED_BUS_ERROR_RATE {
+            Some("servo bus errors")
+        } else if self.imu_age_ms.is_some_and(|age| age > STALE_IMU_MS) {
+            Some("IMU data stale")
+        } else {
+            None
+        }
+    }
+}
+
+/// Accumulates tick timings between snapshots.
+pub struct LoopHealth {
+    margin: Option<f64>,
+    worst: Duration,
+    overruns: u64,
+}
+
+impl LoopHealth {
+    pub fn new() -> Self {
+        Self {
+            margin: None,
+            worst: Duration::ZERO,
+            overruns: 0,
+        }
+    }
+
+    /// Record one tick that took `took` of `period`.
+    pub fn record_tick(&mut self, took: Duration, period: Duration) {
+        let margin = 1.0 - took.as_secs_f64() / period.as_secs_f64();
+        self.margin = Some(match self.margin {
+            Some(m) => m + (margin - m) / MARGIN_WINDOW,
+            None => margin,
+        });
+        self.worst = self.worst.max(took);
+        if took > period {
+            self.overruns += 1;
+        }
+    }
+
+    /// Current health; starts a new worst-tick window.
+    pub fn snapshot(
+        &mut self,
+        bus_error_rate: f64,
+        imu_age: Option<Duration>,
+        safety: SafetyState,
+    ) -> RuntimeHealth {
+        let worst = std::mem::take(&mut self.worst);
+        RuntimeHealth {
+            loop_margin: self.margin.unwrap_or(1.0),
+            worst_loop_ms: worst.as_secs_f64() * 1000.0,
+            overruns: self.overruns,
+            bus_error_rate,
+            imu_age_ms: imu_age.map(|age| age.as_secs_f64() * 1000.0),
+            safety,
+        }
+    }
+}
+
+impl Default for LoopHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(unix)]
+pub use socket::HealthSocket;
+
+#[cfg(unix)]
+mod socket {
+    use super::RuntimeHealth;
+    use anyhow::{Context, Result};
+    use crossbeam_channel::{bounded, Receiver, Sender};
+    use std::io::Write;
+    use std::os::unix::net::UnixListener;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    /// How often the server checks for connections and shutdown.
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Unix socket answering each connection with the latest health as one
+    /// JSON line.
+    pub struct HealthSocket {
+        path: PathBuf,
+        latest: Arc<Mutex<RuntimeHealth>>,
+        stop_tx: Sender<()>,
+    }
+
+    impl HealthSocket {
+        pub fn bind(path: &Path) -> Result<Self> {
+            // A socket file left by a previous run would make bind fail
+            let _ = std::fs::remove_file(path);
+            let listener = UnixListener::bind(path)
+                .with_context(|| format!("Failed to bind health socket {}", path.display()))?;
+            listener.set_nonblocking(true)?;
+
+            let latest = Arc::new(Mutex::new(RuntimeHealth::default()));
+            let (stop_tx, stop_rx) = bounded::<()>(1);
+            let worker_latest = Arc::clone(&latest);
+            thread::spawn(move || socket_worker(listener, worker_latest, stop_rx));
+
+            tracing::info!("Health socket at {}", path.display());
+            Ok(Self {
+                path: path.to_path_buf(),
+                latest,
+                stop_tx,
+            })
+        }
+
+        pub fn publish(&self, health: &RuntimeHealth) {
+            if let Ok(mut latest) = self.latest.lock() {
+                latest.clone_from(health);
+            }
+        }
+    }
+
+    impl Drop for HealthSocket {
+        fn drop(&mut self) {
+            let _ = self.stop_tx.try_send(());
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn socket_worker(listener: UnixListener, latest: Arc<Mutex<RuntimeHealth>>, stop_rx: Receiver<()>) {
+        loop {
+            if stop_rx.try_recv().is_ok() {
+                return;
+            }
+            match listener.accept() {
+                Ok((mut stream, _)) => {
+                    let body = latest
+                        .lock()
+                        .ok()
+                        .and_then(|health| serde_json::to_string(&*health).ok())
+                        .unwrap_or_default();
+                    let _ = stream.set_nonblocking(false);
+                    let _ = writeln!(stream, "{}", body);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => {
+                    tracing::warn!("Health socket accept failed: {}", e);
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrunning_loop_is_degraded() {
+        let period = Duration::from_millis(20);
+        let mut health = LoopHealth::new();
+        for _ in 0..10 {
+            health.record_tick(Duration::from_millis(10), period);
+        }
+        let snapshot = health.snapshot(0.0, Some(Duration::from_millis(5)), SafetyState::Running);
+        assert!((snapshot.loop_margin - 0.5).abs() < 1e-9);
+        assert_eq!(snapshot.worst_loop_ms, 10.0);
+        assert_eq!(snapshot.degraded_reason(), None);
+
+        for _ in 0..200 {
+            health.record_tick(Duration::from_millis(25), period);
+        }
+        let snapshot = health.snapshot(0.0, None, SafetyState::Running);
+        assert_eq!(snapshot.overruns, 200);
+        assert_eq!(snapshot.degraded_reason(), Some("control loop overrunning"));
+        // The worst tick restarts with each snapshot
+        assert_eq!(health.snapshot(0.0, None, SafetyState::Running).worst_loop_ms, 0.0);
+
+        let stale = RuntimeHealth {
+            imu_age_ms: Some(250.0),
+            ..RuntimeHealth::default()
+        };
+        assert_eq!(stale.degraded_reason(), Some("IMU data stale"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_serves_latest_health() {
+        use std::io::Read;
+        use std::os::unix::net::UnixStream;
+
+        let path = std::env::temp_dir().join(format!("odr-health-{}.sock", std::process::id()));
+        let socket = HealthSocket::bind(&path).unwrap();
+        socket.publish(&RuntimeHealth {
+            overruns: 3,
+            safety: SafetyState::Paused,
+            ..RuntimeHealth::default()
+        });
+
+        let mut reply = String::new();
+        UnixStream::connect(&path).unwrap().read_to_string(&mut reply).unwrap();
+        let health: RuntimeHealth = serde_json::from_str(reply.trim()).unwrap();
+        assert_eq!(health.overruns, 3);
+        assert_eq!(health.safety, SafetyState::Paused);
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
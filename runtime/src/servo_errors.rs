@@ -0,0 +1,191 @@
+//! Servo status-byte errors and the control loop's reactions to them.
+//!
+//! Every Feetech status packet carries an error byte next to its data. The
+//! motor controller collects the non-zero ones as `ServoErrorReport`s; the
+//! control loop hands them to `ServoErrorHandler`, which decodes the bits
+//! into `ServoError`s and picks a reaction per error from the
+//! `servo_errors` config: log it, reduce that joint's gains, or turn its
+//! torque off. Reactions fire when an error appears on a joint, not on every
+//! packet while it persists.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ServoErrorsConfig;
+
+/// One bit of the status packet's error byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServoError {
+    /// Supply voltage out of the configured range.
+    InputVoltage,
+    /// Goal position outside the angle limits.
+    AngleLimit,
+    Overheat,
+    /// Current above the protection limit.
+    Overcurrent,
+    /// The servo received a packet with a bad checksum.
+    Checksum,
+    /// Load above the protection torque.
+    Overload,
+    /// Unknown or malformed instruction.
+    Instruction,
+}
+
+impl ServoError {
+    const ALL: [ServoError; 7] = [
+        ServoError::InputVoltage,
+        ServoError::AngleLimit,
+        ServoError::Overheat,
+        ServoError::Overcurrent,
+        ServoError::Checksum,
+        ServoError::Overload,
+        ServoError::Instruction,
+    ];
+
+    /// This error's bit in a Feetech status byte, the layout reports use.
+    pub fn bit(self) -> u8 {
+        1 << Self::ALL.iter().position(|&e| e == self).unwrap_or(0)
+    }
+
+    /// Errors set in a status byte.
+    pub fn decode(flags: u8) -> Vec<ServoError> {
+        Self::ALL.into_iter().filter(|e| flags & e.bit() != 0).collect()
+    }
+}
+
+impl std::fmt::Display for ServoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::InputVoltage => "input voltage",
+            Self::AngleLimit => "angle limit",
+            Self::Overheat => "overheat",
+            Self::Overcurrent => "overcurrent",
+            Self::Checksum => "checksum",
+            Self::Overload => "overload",
+            Self::Instruction => "instruction",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Non-zero error byte seen in a reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServoErrorReport {
+    /// Index into the interface's joint names.
+    pub joint: usize,
+    pub id: u8,
+    pub flags: u8,
+}
+
+/// What to do when a servo reports an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServoErrorReaction {
+    Log,
+    /// Scale the joint's KP by `gain_reduction`.
+    ReduceGains,
+    /// Disable torque on the joint until torque is next turned on.
+    TorqueOff,
+}
+
+/// Reaction to apply to one joint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServoAction {
+    pub joint: usize,
+    pub reaction: ServoErrorReaction,
+}
+
+/// Tracks the error bits of each joint and picks reactions to new ones.
+pub struct ServoErrorHandler {
+    config: ServoErrorsConfig,
+    flags: Vec<u8>,
+}
+
+impl ServoErrorHandler {
+    pub fn new(config: ServoErrorsConfig, num_joints: usize) -> Self {
+        Self {
+            config,
+            flags: vec![0; num_joints],
+        }
+    }
+
+    fn reaction(&self, error: ServoError) -> ServoErrorReaction {
+        let c = &self.config;
+        match error {
+            ServoError::Overload => c.overload,
+            ServoError::Overheat => c.overheat,
+            ServoError::InputVoltage => c.input_voltage,
+            ServoError::Checksum => c.checksum,
+            ServoError::AngleLimit | ServoError::Overcurrent | ServoError::Instruction => c.other,
+        }
+    }
+
+    /// Feed this tick's reports (joints without one are clear); returns the
+    /// strongest reaction for each joint that raised a new error.
+    pub fn handle(&mut self, reports: &[ServoErrorReport], joint_names: &[String]) -> Vec<ServoAction> {
+        let mut current = vec![0u8; self.flags.len()];
+        for report in reports {
+            if let Some(flags) = current.get_mut(report.joint) {
+                *flags |= report.flags;
+            }
+        }
+
+        let mut actions = Vec::new();
+        for (joint, (&now, &before)) in current.iter().zip(&self.flags).enumerate() {
+            let raised = ServoError::decode(now & !before);
+            if raised.is_empty() {
+                continue;
+            }
+            let name = joint_names.get(joint).map_or("?", String::as_str);
+            let names: Vec<String> = raised.iter().map(ToString::to_string).collect();
+            tracing::warn!("Servo error on {}: {}", name, names.join(", "));
+            let reaction = raised
+                .iter()
+                .map(|&e| self.reaction(e))
+                .max()
+                .unwrap_or(ServoErrorReaction::Log);
+            if reaction != ServoErrorReaction::Log {
+                actions.push(ServoAction { joint, reaction });
+            }
+        }
+        self.flags = current;
+        actions
+    }
+
+    /// KP for a joint after a `ReduceGains` reaction.
+    pub fn reduced_kp(&self, kp: f64) -> f64 {
+        kp * self.config.gain_reduction.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reacts_to_new_errors_only() {
+        assert_eq!(ServoError::decode(0x24), vec![ServoError::Overheat, ServoError::Overload]);
+
+        let names: Vec<String> = vec!["left_knee".into(), "right_knee".into()];
+        let mut handler = ServoErrorHandler::new(ServoErrorsConfig::default(), 2);
+        let overload = ServoErrorReport { joint: 0, id: 23, flags: 0x20 };
+        assert_eq!(
+            handler.handle(&[overload], &names),
+            vec![ServoAction { joint: 0, reaction: ServoErrorReaction::ReduceGains }]
+        );
+        // Still overloaded: no new reaction
+        assert!(handler.handle(&[overload], &names).is_empty());
+
+        // Overheat on top escalates to torque off; voltage alone only logs
+        let both = ServoErrorReport { flags: 0x24, ..overload };
+        let voltage = ServoErrorReport { joint: 1, id: 13, flags: 0x01 };
+        assert_eq!(
+            handler.handle(&[both, voltage], &names),
+            vec![ServoAction { joint: 0, reaction: ServoErrorReaction::TorqueOff }]
+        );
+
+        // Cleared then raised again reacts again
+        assert!(handler.handle(&[], &names).is_empty());
+        assert_eq!(handler.handle(&[overload], &names).len(), 1);
+    }
+}
@@ -0,0 +1,280 @@
+//! STS3215 register map: dump every servo's settings to JSON and restore
+//! them onto a replacement.
+//!
+//! A dump holds the raw value of every named register, EEPROM and RAM, of
+//! each servo:
+//!
+//! ```json
+//! { "servos": [ { "id": 20, "joint": "left_hip_yaw",
+//!                 "registers": { "baud_rate": 0, "return_delay": 0, ... } } ] }
+//! ```
+//!
+//! Restoring writes back the writable EEPROM registers that differ — return
+//! delay, angle limits, offsets, operating mode (multi-turn), protections
+//! and gains — with the EEPROM unlocked around the writes. RAM registers
+//! are volatile and only dumped for inspection. The ID and the baud rate are
+//! written last, since the servo stops answering at its old address after
+//! either changes.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::bus_scan::{COMMON_BAUD_RATES, MAX_ID};
+
+/// Where a register lives and whether it can be written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    ReadOnly,
+    /// Persistent settings; written with the EEPROM unlocked.
+    Eeprom,
+    /// Volatile control and status registers.
+    Ram,
+}
+
+/// One named register of the STS3215 memory table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Register {
+    pub name: &'static str,
+    pub addr: u8,
+    /// 1 or 2 bytes, little-endian.
+    pub size: u8,
+    pub access: Access,
+}
+
+const fn reg(name: &'static str, addr: u8, size: u8, access: Access) -> Register {
+    Register {
+        name,
+        addr,
+        size,
+        access,
+    }
+}
+
+/// The STS3215 memory table, by address.
+pub const REGISTERS: &[Register] = &[
+    reg("firmware_major", 0, 1, Access::ReadOnly),
+    reg("firmware_minor", 1, 1, Access::ReadOnly),
+    reg("model_number", 3, 2, Access::ReadOnly),
+    reg("id", 5, 1, Access::Eeprom),
+    reg("baud_rate", 6, 1, Access::Eeprom),
+    reg("return_delay", 7, 1, Access::Eeprom),
+    reg("status_return_level", 8, 1, Access::Eeprom),
+    reg("min_angle_limit", 9, 2, Access::Eeprom),
+    reg("max_angle_limit", 11, 2, Access::Eeprom),
+    reg("max_temperature", 13, 1, Access::Eeprom),
+    reg("max_voltage", 14, 1, Access::Eeprom),
+    reg("min_voltage", 15, 1, Access::Eeprom),
+    reg("max_torque", 16, 2, Access::Eeprom),
+    reg("phase", 18, 1, Access::Eeprom),
+    reg("unloading_condition", 19, 1, Access::Eeprom),
+    reg("led_alarm_condition", 20, 1, Access::Eeprom),
+    reg("p_gain", 21, 1, Access::Eeprom),
+    reg("d_gain", 22, 1, Access::Eeprom),
+    reg("i_gain", 23, 1, Access::Eeprom),
+    reg("min_startup_force", 24, 2, Access::Eeprom),
+    reg("cw_dead_zone", 26, 1, Access::Eeprom),
+    reg("ccw_dead_zone", 27, 1, Access::Eeprom),
+    reg("protection_current", 28, 2, Access::Eeprom),
+    reg("angular_resolution", 30, 1, Access::Eeprom),
+    reg("position_offset", 31, 2, Access::Eeprom),
+    reg("operating_mode", 33, 1, Access::Eeprom),
+    reg("protective_torque", 34, 1, Access::Eeprom),
+    reg("protection_time", 35, 1, Access::Eeprom),
+    reg("overload_torque", 36, 1, Access::Eeprom),
+    reg("speed_p_gain", 37, 1, Access::Eeprom),
+    reg("overcurrent_protection_time", 38, 1, Access::Eeprom),
+    reg("speed_i_gain", 39, 1, Access::Eeprom),
+    reg("torque_enable", 40, 1, Access::Ram),
+    reg("acceleration", 41, 1, Access::Ram),
+    reg("goal_position", 42, 2, Access::Ram),
+    reg("goal_time", 44, 2, Access::Ram),
+    reg("goal_speed", 46, 2, Access::Ram),
+    reg("torque_limit", 48, 2, Access::Ram),
+    reg("lock", 55, 1, Access::Ram),
+    reg("present_position", 56, 2, Access::ReadOnly),
+    reg("present_speed", 58, 2, Access::ReadOnly),
+    reg("present_load", 60, 2, Access::ReadOnly),
+    reg("present_voltage", 62, 1, Access::ReadOnly),
+    reg("present_temperature", 63, 1, Access::ReadOnly),
+    reg("status", 65, 1, Access::ReadOnly),
+    reg("moving", 66, 1, Access::ReadOnly),
+    reg("present_current", 69, 2, Access::ReadOnly),
+];
+
+/// Address of the EEPROM write lock (0 = writes persist, 1 = locked).
+pub const ADDR_LOCK: u8 = 55;
+
+/// Bytes read to cover the whole table, from address 0.
+pub const TABLE_LEN: u8 = 71;
+
+/// Look a register up by name.
+pub fn register(name: &str) -> Result<&'static Register> {
+    REGISTERS
+        .iter()
+        .find(|r| r.name == name)
+        .with_context(|| format!("Unknown register {:?}", name))
+}
+
+/// Baud rate selected by a `baud_rate` register value.
+pub fn baud_for_code(code: u16) -> Option<u32> {
+    COMMON_BAUD_RATES.get(code as usize).copied()
+}
+
+/// Encode a value for `register`, rejecting ones it cannot hold.
+pub fn register_bytes(register: &Register, value: u16) -> Result<Vec<u8>> {
+    match register.name {
+        "id" if value > MAX_ID as u16 => bail!("Servo ID {} is above {}", value, MAX_ID),
+        "baud_rate" if baud_for_code(value).is_none() => bail!("Unknown baud rate code {}", value),
+        _ => {}
+    }
+    match register.size {
+        1 => match u8::try_from(value) {
+            Ok(byte) => Ok(vec![byte]),
+            Err(_) => bail!("Register {} holds one byte, {} does not fit", register.name, value),
+        },
+        _ => Ok(value.to_le_bytes().to_vec()),
+    }
+}
+
+/// Raw register values of one servo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServoRegisters {
+    pub id: u8,
+    /// Joint the servo drives, when its ID is in the joint map.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub joint: Option<String>,
+    pub registers: BTreeMap<String, u16>,
+}
+
+impl ServoRegisters {
+    /// Decode a read of the whole table (`TABLE_LEN` bytes from address 0).
+    pub fn decode(id: u8, joint: Option<String>, table: &[u8]) -> Result<Self> {
+        if table.len() < TABLE_LEN as usize {
+            bail!(
+                "Register table of servo {} is {} bytes, expected {}",
+                id,
+                table.len(),
+                TABLE_LEN
+            );
+        }
+        let registers = REGISTERS
+            .iter()
+            .map(|r| {
+                let at = r.addr as usize;
+                let value = match r.size {
+                    1 => table[at] as u16,
+                    _ => u16::from_le_bytes([table[at], table[at + 1]]),
+                };
+                (r.name.to_string(), value)
+            })
+            .collect();
+        Ok(Self { id, joint, registers })
+    }
+}
+
+/// A register write needed to restore a servo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterWrite {
+    pub register: &'static Register,
+    pub from: Option<u16>,
+    pub to: u16,
+}
+
+/// EEPROM writes turning `current` into `saved`: every writable EEPROM
+/// register that differs, by address, with the ID and then the baud rate
+/// last.
+pub fn restore_plan(current: &ServoRegisters, saved: &ServoRegisters) -> Vec<RegisterWrite> {
+    let last = |r: &Register| match r.name {
+        "id" => 1,
+        "baud_rate" => 2,
+        _ => 0,
+    };
+    let mut writes: Vec<RegisterWrite> = REGISTERS
+        .iter()
+        .filter(|r| r.access == Access::Eeprom)
+        .filter_map(|r| {
+            let to = *saved.registers.get(r.name)?;
+            let from = current.registers.get(r.name).copied();
+            (from != Some(to)).then_some(RegisterWrite { register: r, from, to })
+        })
+        .collect();
+    writes.sort_by_key(|w| last(w.register));
+    writes
+}
+
+/// A dump file: every servo on the bus.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegisterDump {
+    pub servos: Vec<ServoRegisters>,
+}
+
+impl RegisterDump {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("Invalid register dump {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The saved servo with this ID or joint name.
+    pub fn find(&self, servo: &str) -> Option<&ServoRegisters> {
+        self.servos.iter().find(|s| {
+            s.joint.as_deref() == Some(servo) || servo.parse::<u8>().is_ok_and(|id| id == s.id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_and_restore_plan() {
+        let mut table = vec![0u8; TABLE_LEN as usize];
+        table[3..5].copy_from_slice(&777u16.to_le_bytes());
+        table[5] = 1; // factory ID
+        table[7] = 250; // return delay
+        table[11..13].copy_from_slice(&4095u16.to_le_bytes());
+        table[56..58].copy_from_slice(&2048u16.to_le_bytes());
+        let fresh = ServoRegisters::decode(1, None, &table).unwrap();
+        assert_eq!(fresh.registers["model_number"], 777);
+        assert_eq!(fresh.registers["max_angle_limit"], 4095);
+        assert_eq!(fresh.registers["present_position"], 2048);
+
+        let mut saved = fresh.clone();
+        saved.id = 20;
+        let changes = [("id", 20), ("baud_rate", 1), ("return_delay", 0), ("max_angle_limit", 0)];
+        for (name, value) in changes {
+            saved.registers.insert(name.to_string(), value);
+        }
+        // Read-only and RAM registers are never written
+        saved.registers.insert("present_position".to_string(), 100);
+        saved.registers.insert("goal_position".to_string(), 100);
+
+        let plan: Vec<(&str, u16)> =
+            restore_plan(&fresh, &saved).iter().map(|w| (w.register.name, w.to)).collect();
+        assert_eq!(
+            plan,
+            vec![("return_delay", 0), ("max_angle_limit", 0), ("id", 20), ("baud_rate", 1)]
+        );
+        assert_eq!(baud_for_code(1), Some(500_000));
+        assert!(register_bytes(register("baud_rate").unwrap(), 9).is_err());
+        assert!(register_bytes(register("return_delay").unwrap(), 256).is_err());
+        let limit = register_bytes(register("max_angle_limit").unwrap(), 4095).unwrap();
+        assert_eq!(limit, vec![0xFF, 0x0F]);
+
+        let dump = RegisterDump {
+            servos: vec![ServoRegisters { joint: Some("left_hip_yaw".into()), ..saved }],
+        };
+        assert_eq!(dump.find("left_hip_yaw").map(|s| s.id), Some(20));
+        assert_eq!(dump.find("20").map(|s| s.id), Some(20));
+        assert!(dump.find("21").is_none());
+    }
+}
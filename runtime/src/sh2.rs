@@ -0,0 +1,152 @@
+//! SH-2 sensor hub protocol for the CEVA BNO08x IMUs.
+//!
+//! The chip talks SHTP: each packet is a 4-byte header (length with a
+//! continuation bit, channel, per-channel sequence number) followed by a
+//! payload. Sensors are enabled with a Set Feature command on the control
+//! channel and report on the input channel, each batch preceded by a base
+//! timestamp. Only the framing and the reports the runtime uses are
+//! implemented here; the I2C transport lives in the `imu` module.
+
+use crate::orientation::Quaternion;
+
+/// Default I2C address (SA0 low).
+pub const I2C_ADDR: u16 = 0x4A;
+
+/// SHTP header length.
+pub const HEADER_LEN: usize = 4;
+
+/// Executable channel (reset).
+pub const CHANNEL_EXECUTABLE: u8 = 1;
+/// Sensor hub control channel (Set Feature).
+pub const CHANNEL_CONTROL: u8 = 2;
+/// Normal input reports.
+pub const CHANNEL_REPORTS: u8 = 3;
+
+/// Executable channel command: reset the hub.
+pub const EXECUTABLE_RESET: u8 = 1;
+
+pub const REPORT_ACCELEROMETER: u8 = 0x01;
+pub const REPORT_GYROSCOPE: u8 = 0x02;
+pub const REPORT_GAME_ROTATION_VECTOR: u8 = 0x08;
+
+const SET_FEATURE_COMMAND: u8 = 0xFD;
+const TIMESTAMP_REBASE: u8 = 0xFA;
+const BASE_TIMESTAMP: u8 = 0xFB;
+
+// Fixed-point scales of the report fields.
+const Q_ACCEL: i32 = 8;
+const Q_GYRO: i32 = 9;
+const Q_ROTATION: i32 = 14;
+
+/// Latest values decoded from input reports. Fields stay `None` until the
+/// matching report arrives.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sh2Reading {
+    /// Calibrated acceleration [x, y, z] in m/s^2.
+    pub accel: Option<[f64; 3]>,
+    /// Calibrated angular rate [x, y, z] in rad/s.
+    pub gyro: Option<[f64; 3]>,
+    /// Game rotation vector as [w, x, y, z].
+    pub orientation: Option<Quaternion>,
+}
+
+/// Payload length and channel from an SHTP header. The length includes the
+/// header itself; the continuation bit is masked off.
+pub fn parse_header(header: &[u8; HEADER_LEN]) -> (usize, u8) {
+    let len = u16::from_le_bytes([header[0], header[1]]) & 0x7FFF;
+    (len as usize, header[2])
+}
+
+/// Frame a payload for `channel` with the channel's next sequence number.
+pub fn packet(channel: u8, sequence: u8, payload: &[u8]) -> Vec<u8> {
+    let len = (payload.len() + HEADER_LEN) as u16;
+    let mut packet = Vec::with_capacity(len as usize);
+    packet.extend_from_slice(&len.to_le_bytes());
+    packet.push(channel);
+    packet.push(sequence);
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// Set Feature command enabling `report_id` every `interval_us`.
+pub fn set_feature(report_id: u8, interval_us: u32) -> [u8; 17] {
+    let mut cmd = [0u8; 17];
+    cmd[0] = SET_FEATURE_COMMAND;
+    cmd[1] = report_id;
+    cmd[5..9].copy_from_slice(&interval_us.to_le_bytes());
+    cmd
+}
+
+/// Decode the sensor reports in an input channel payload into `reading`.
+/// Stops at the first unknown report, whose length can't be known.
+pub fn parse_reports(payload: &[u8], reading: &mut Sh2Reading) {
+    let mut at = 0;
+    while let Some(&id) = payload.get(at) {
+        let len = match id {
+            BASE_TIMESTAMP | TIMESTAMP_REBASE => 5,
+            REPORT_ACCELEROMETER | REPORT_GYROSCOPE => 10,
+            REPORT_GAME_ROTATION_VECTOR => 12,
+            _ => {
+                tracing::trace!("SH-2: unhandled report 0x{:02X}", id);
+                return;
+            }
+        };
+        let Some(report) = payload.get(at..at + len) else {
+            return;
+        };
+        // Sensor reports: id, sequence, status, delay, then i16 fields
+        let field = |i: usize, q: i32| {
+            let raw = i16::from_le_bytes([report[4 + 2 * i], report[5 + 2 * i]]);
+            raw as f64 / 2f64.powi(q)
+        };
+        match id {
+            REPORT_ACCELEROMETER => {
+                reading.accel = Some([field(0, Q_ACCEL), field(1, Q_ACCEL), field(2, Q_ACCEL)]);
+            }
+            REPORT_GYROSCOPE => {
+                reading.gyro = Some([field(0, Q_GYRO), field(1, Q_GYRO), field(2, Q_GYRO)]);
+            }
+            REPORT_GAME_ROTATION_VECTOR => {
+                // Sent as i, j, k, real
+                reading.orientation = Some([
+                    field(3, Q_ROTATION),
+                    field(0, Q_ROTATION),
+                    field(1, Q_ROTATION),
+                    field(2, Q_ROTATION),
+                ]);
+            }
+            _ => {}
+        }
+        at += len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_batched_reports() {
+        let mut payload = vec![BASE_TIMESTAMP, 0, 0, 0, 0];
+        // Accelerometer: z = 9.8125 m/s^2 (2512 / 2^8)
+        payload.extend_from_slice(&[REPORT_ACCELEROMETER, 1, 3, 0, 0, 0, 0, 0]);
+        payload.extend_from_slice(&2512i16.to_le_bytes());
+        // Gyroscope: x = 0.5 rad/s (256 / 2^9)
+        payload.extend_from_slice(&[REPORT_GYROSCOPE, 1, 3, 0]);
+        payload.extend_from_slice(&256i16.to_le_bytes());
+        payload.extend_from_slice(&[0, 0, 0, 0]);
+        // Game rotation vector: identity (real = 1 << 14)
+        payload.extend_from_slice(&[REPORT_GAME_ROTATION_VECTOR, 1, 3, 0, 0, 0, 0, 0, 0, 0]);
+        payload.extend_from_slice(&(1i16 << 14).to_le_bytes());
+
+        let mut reading = Sh2Reading::default();
+        parse_reports(&payload, &mut reading);
+        assert_eq!(reading.accel, Some([0.0, 0.0, 9.8125]));
+        assert_eq!(reading.gyro, Some([0.5, 0.0, 0.0]));
+        assert_eq!(reading.orientation, Some([1.0, 0.0, 0.0, 0.0]));
+
+        let framed = packet(CHANNEL_REPORTS, 7, &payload);
+        let header: [u8; HEADER_LEN] = framed[..HEADER_LEN].try_into().unwrap();
+        assert_eq!(parse_header(&header), (framed.len(), CHANNEL_REPORTS));
+    }
+}
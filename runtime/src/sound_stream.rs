@@ -0,0 +1,194 @@
+//! Sounds streamed from an HTTP URL, e.g. one the backend asks the duck to
+//! play.
+//!
+//! The audio thread must never wait on the network, so the download and
+//! decoding run on their own thread and hand decoded samples over a short
+//! channel. `StreamSource` plays whatever has arrived and fills gaps with
+//! silence, so a slow connection stutters that one sound but stalls nothing
+//! else. Any format rodio decodes works (wav, mp3, ogg, flac); the response
+//! is read once, front to back.
+
+use anyhow::{anyhow, Context, Result};
+use crossbeam_channel::{bounded, Receiver, TryRecvError};
+use rodio::{Decoder, Source};
+use std::io::{Read, Seek, SeekFrom};
+use std::thread;
+use std::time::Duration;
+
+/// Connect and response header timeout.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Decoded samples per chunk handed to the audio thread.
+const CHUNK: usize = 4096;
+
+/// Chunks decoded ahead of playback.
+const AHEAD: usize = 16;
+
+/// Forward-only reader for an unseekable body. Seeks that don't go back
+/// are served by skipping bytes; the decoder is told not to seek otherwise.
+pub struct ForwardReader<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> ForwardReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, position: 0 }
+    }
+}
+
+impl<R: Read> Read for ForwardReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read> Seek for ForwardReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => self.position.checked_add_signed(n),
+            SeekFrom::End(_) => None,
+        };
+        match target {
+            Some(target) if target >= self.position => {
+                let skip = target - self.position;
+                let skipped = std::io::copy(&mut self.by_ref().take(skip), &mut std::io::sink())?;
+                if skipped < skip {
+                    return Err(std::io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(self.position)
+            }
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "stream can only be read forward",
+            )),
+        }
+    }
+}
+
+/// Samples decoded on the download thread, played as they arrive.
+pub struct StreamSource {
+    chunks: Receiver<Vec<f32>>,
+    chunk: Vec<f32>,
+    index: usize,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl StreamSource {
+    /// Start downloading and decoding `url`. Blocks until the stream's
+    /// format is known, so call it off the control loop.
+    pub fn open(url: &str) -> Result<Self> {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_connect(Some(TIMEOUT))
+            .timeout_recv_response(Some(TIMEOUT))
+            .build()
+            .into();
+        let body = agent
+            .get(url)
+            .call()
+            .with_context(|| format!("Failed to fetch {}", url))?
+            .into_body()
+            .into_reader();
+        let mut builder = Decoder::builder()
+            .with_data(ForwardReader::new(body))
+            .with_seekable(false);
+        if let Some(ext) = extension(url) {
+            builder = builder.with_hint(ext);
+        }
+        let decoder = builder.build().with_context(|| format!("Failed to decode {}", url))?;
+        let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+        if channels == 0 {
+            return Err(anyhow!("{} has no audio channels", url));
+        }
+
+        let (tx, chunks) = bounded::<Vec<f32>>(AHEAD);
+        thread::spawn(move || {
+            let mut decoder = decoder.peekable();
+            while decoder.peek().is_some() {
+                let chunk: Vec<f32> = decoder.by_ref().take(CHUNK).collect();
+                if tx.send(chunk).is_err() {
+                    break; // stopped
+                }
+            }
+        });
+        Ok(Self {
+            chunks,
+            chunk: Vec::new(),
+            index: 0,
+            channels,
+            sample_rate,
+        })
+    }
+}
+
+impl Iterator for StreamSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.index >= self.chunk.len() {
+            match self.chunks.try_recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.index = 0;
+                }
+                // Still downloading: silence until the data arrives
+                Err(TryRecvError::Empty) => return Some(0.0),
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+        let sample = self.chunk.get(self.index).copied();
+        self.index += 1;
+        sample.or(Some(0.0))
+    }
+}
+
+impl Source for StreamSource {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// File extension of a URL's path, as a format hint.
+fn extension(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    let name = path.rsplit('/').next()?;
+    let (_, ext) = name.rsplit_once('.')?;
+    (!ext.is_empty() && ext.len() <= 4).then_some(ext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward_reader_skips_but_never_rewinds() {
+        let mut reader = ForwardReader::new(&b"0123456789"[..]);
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.seek(SeekFrom::Current(3)).unwrap(), 5);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"56");
+        assert!(reader.seek(SeekFrom::Start(0)).is_err());
+        assert!(reader.seek(SeekFrom::End(0)).is_err());
+        assert!(reader.seek(SeekFrom::Start(20)).is_err());
+
+        assert_eq!(extension("https://cdn.example.com/quack.mp3?sig=abc"), Some("mp3"));
+        assert_eq!(extension("https://cdn.example.com/sounds/"), None);
+    }
+}
@@ -1,42 +1,71 @@
 //! Sound playback for the duck's speaker.
 //!
 //! Replaces `sounds.py`. Uses the `rodio` crate for cross-platform audio.
+//! Sounds are wav, mp3, ogg or flac files anywhere under the sound
+//! directory, named by their path in it (`quacks/quack1.mp3`). Each
+//! subfolder is a category: playing `quacks` picks one of its sounds at
+//! random. `play_url` streams a sound from the web (see `sound_stream`).
+//! With a piper voice loaded, `say` speaks text as well (see `tts`).
+//! `event_sound` picks what plays when something happens, from the
+//! `sound_events` config.
+//!
+//! Every sound plays with a `SoundPriority`. While one plays, the sounds of
+//! lower priority are ducked to `duck_volume`, and a safety sound stops the
+//! ambient ones outright, so "battery low" is never drowned out by quacks.
+//! At most `max_concurrent` sounds play at once: past that, the oldest of
+//! the lowest priority gives way, or the new sound is dropped if everything
+//! playing matters more. `play_with` returns a `Playback` handle to stop the
+//! sound, change its volume, or check whether it is still playing.
 
 use anyhow::{Context, Result};
-use rodio::{Decoder, OutputStream, Sink};
+use rodio::mixer::Mixer;
+use rodio::{Decoder, OutputStream, Sink, Source};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+use crate::config::{SoundChoice, SoundEventsConfig, SoundMixingConfig, SpeechConfig};
+use crate::events::Event;
+use crate::sound_stream::StreamSource;
+use crate::tts::{Speaker, Voice};
+
+/// How much a sound matters when sounds compete for the speaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SoundPriority {
+    /// Quacks and other sounds for fun; stopped by safety sounds.
+    Ambient,
+    Normal,
+    /// Warnings the people around must hear.
+    Safety,
+}
 
-/// Audio playback manager that loads .wav files from a directory.
+/// Extensions of the sound files loaded.
+const SOUND_EXTENSIONS: [&str; 4] = ["wav", "mp3", "ogg", "flac"];
+
+/// Audio playback manager that loads sound files from a directory.
 pub struct Sounds {
     _stream: OutputStream,
     sound_files: HashMap<String, PathBuf>,
-    volume: f32,
+    /// Sound names per subfolder.
+    categories: HashMap<String, Vec<String>>,
+    player: Player,
+    speaker: Option<Speaker>,
 }
 
 impl Sounds {
-    /// Initialize audio output and scan a directory for .wav files.
-    pub fn new(volume: f32, sound_directory: &Path) -> Result<Self> {
+    /// Initialize audio output and scan a directory tree for sound files.
+    pub fn new(volume: f32, sound_directory: &Path, mixing: &SoundMixingConfig) -> Result<Self> {
         let stream = rodio::OutputStreamBuilder::open_default_stream()
             .context("Failed to initialize audio output")?;
 
         let mut sound_files = HashMap::new();
 
         if sound_directory.exists() {
-            for entry in std::fs::read_dir(sound_directory)
-                .context("Failed to read sound directory")?
-            {
-                let entry = entry?;
-                let path = entry.path();
-                if path.extension().and_then(|e| e.to_str()) == Some("wav") {
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        tracing::info!("Loaded sound: {}", name);
-                        sound_files.insert(name.to_string(), path);
-                    }
-                }
-            }
+            scan_sounds(sound_directory, "", &mut sound_files)
+                .context("Failed to read sound directory")?;
         } else {
             tracing::warn!(
                 "Sound directory not found: {}",
@@ -45,25 +74,68 @@ impl Sounds {
         }
 
         if sound_files.is_empty() {
-            tracing::warn!("No .wav sound files found");
+            tracing::warn!("No sound files found");
         }
 
+        let categories = categories(sound_files.keys());
+        for (category, names) in &categories {
+            tracing::info!("Sound category {}: {} sounds", category, names.len());
+        }
+
+        let player = Player::new(stream.mixer().clone(), volume, mixing);
         Ok(Self {
             _stream: stream,
             sound_files,
-            volume,
+            categories,
+            player,
+            speaker: None,
         })
     }
 
+    /// Load a piper voice for `say`.
+    pub fn load_voice(&mut self, model: &Path, config: &SpeechConfig) -> Result<()> {
+        let voice = Voice::load(model, config)?;
+        self.speaker = Some(Speaker::start(voice, self.player.clone()));
+        Ok(())
+    }
+
+    pub fn can_speak(&self) -> bool {
+        self.speaker.is_some()
+    }
+
+    /// Speak `text` in the background.
+    pub fn say(&self, text: &str, priority: SoundPriority) -> Result<()> {
+        match self.speaker {
+            Some(ref speaker) => speaker.say(text, priority),
+            None => tracing::warn!("No voice loaded, not saying \"{}\"", text),
+        }
+        Ok(())
+    }
+
     /// Play a specific sound by filename.
     pub fn play(&self, name: &str) -> Result<()> {
-        if let Some(path) = self.sound_files.get(name) {
-            self.play_file(path)?;
-            tracing::info!("Playing: {}", name);
-        } else {
+        self.play_with(name, SoundPriority::Normal).map(|_| ())
+    }
+
+    /// Play a sound by name, or a random one of a category, at `priority`.
+    /// No handle if the sound isn't there or everything playing matters
+    /// more.
+    pub fn play_with(&self, name: &str, priority: SoundPriority) -> Result<Option<Playback>> {
+        let name = match self.categories.get(name) {
+            Some(names) if !self.sound_files.contains_key(name) => {
+                &names[rand::random::<usize>() % names.len()]
+            }
+            _ => name,
+        };
+        let Some(path) = self.sound_files.get(name) else {
             tracing::warn!("Sound '{}' not found", name);
+            return Ok(None);
+        };
+        let playback = self.play_file(path, priority)?;
+        if playback.is_some() {
+            tracing::info!("Playing: {}", name);
         }
-        Ok(())
+        Ok(playback)
     }
 
     /// Play a random sound from the loaded set.
@@ -77,21 +149,357 @@ impl Sounds {
         let idx = rand::random::<usize>() % keys.len();
         let name = keys[idx].clone();
 
-        self.play(&name)
+        self.play_with(&name, SoundPriority::Ambient).map(|_| ())
+    }
+
+    /// Play a configured sound: the file, or a random one of the pool.
+    pub fn play_choice(&self, choice: &SoundChoice, priority: SoundPriority) -> Result<()> {
+        let name = match choice {
+            SoundChoice::File(name) => name,
+            SoundChoice::Pool(names) if names.is_empty() => return self.play_random(),
+            SoundChoice::Pool(names) => &names[rand::random::<usize>() % names.len()],
+        };
+        self.play_with(name, priority).map(|_| ())
     }
 
-    fn play_file(&self, path: &Path) -> Result<()> {
+    /// Stream a sound from `url` in the background.
+    pub fn play_url(&self, url: &str, priority: SoundPriority) {
+        let player = self.player.clone();
+        let url = url.to_string();
+        thread::spawn(move || match StreamSource::open(&url) {
+            Ok(source) => {
+                if player.play(source, priority).is_some() {
+                    tracing::info!("Streaming: {}", url);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to stream sound: {:#}", e),
+        });
+    }
+
+    /// Master volume (0-1) of every sound, including those playing.
+    pub fn set_volume(&self, volume: f32) {
+        self.player.set_volume(volume);
+        tracing::info!("Volume set to {:.2}", volume);
+    }
+
+    pub fn stop_all(&self) {
+        self.player.stop_all();
+    }
+
+    /// Drop finished sounds and bring ducked ones back up once nothing of
+    /// higher priority plays. Call it regularly.
+    pub fn update(&self) {
+        self.player.refresh();
+    }
+
+    fn play_file(&self, path: &Path, priority: SoundPriority) -> Result<Option<Playback>> {
         let file = BufReader::new(
             File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
         );
         let source =
             Decoder::new(file).with_context(|| format!("Failed to decode {}", path.display()))?;
+        Ok(self.player.play(source, priority))
+    }
+}
+
+/// Add the sound files under `dir` to `files`, named by their path below
+/// the sound directory (`prefix` is `dir`'s).
+fn scan_sounds(dir: &Path, prefix: &str, files: &mut HashMap<String, PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let name = format!("{}{}", prefix, file_name);
+        if path.is_dir() {
+            scan_sounds(&path, &format!("{}/", name), files)?;
+        } else if is_sound_file(&path) {
+            tracing::info!("Loaded sound: {}", name);
+            files.insert(name, path);
+        }
+    }
+    Ok(())
+}
+
+fn is_sound_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| SOUND_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Sound names grouped by the folder they are in, at every level
+/// (`animals/ducks/quack.wav` is in `animals/ducks` and `animals`).
+fn categories<'a>(names: impl Iterator<Item = &'a String>) -> HashMap<String, Vec<String>> {
+    let mut categories: HashMap<String, Vec<String>> = HashMap::new();
+    for name in names {
+        let mut folder = name.as_str();
+        while let Some((parent, _)) = folder.rsplit_once('/') {
+            categories.entry(parent.to_string()).or_default().push(name.clone());
+            folder = parent;
+        }
+    }
+    for names in categories.values_mut() {
+        names.sort();
+    }
+    categories
+}
+
+/// Mixes sounds onto the output: concurrency limit, ducking and volume.
+/// Clones share the same sounds.
+#[derive(Clone)]
+pub struct Player {
+    mixer: Mixer,
+    state: Arc<Mutex<PlayerState>>,
+}
+
+struct PlayerState {
+    volume: f32,
+    duck_volume: f32,
+    max_concurrent: usize,
+    /// Playing sounds, oldest first.
+    active: Vec<Active>,
+}
+
+struct Active {
+    sink: Arc<Sink>,
+    priority: SoundPriority,
+    /// The sound's own volume, before master volume and ducking.
+    volume: f32,
+}
+
+impl PlayerState {
+    fn prune(&mut self) {
+        self.active.retain(|a| !a.sink.empty());
+    }
 
-        let sink = Sink::connect_new(&self._stream.mixer());
-        sink.set_volume(self.volume);
+    fn apply_volumes(&self) {
+        let levels: Vec<(SoundPriority, f32)> =
+            self.active.iter().map(|a| (a.priority, a.volume)).collect();
+        let volumes = mixed_volumes(&levels, self.volume, self.duck_volume);
+        for (active, volume) in self.active.iter().zip(volumes) {
+            active.sink.set_volume(volume);
+        }
+    }
+}
+
+impl Player {
+    fn new(mixer: Mixer, volume: f32, config: &SoundMixingConfig) -> Self {
+        Self {
+            mixer,
+            state: Arc::new(Mutex::new(PlayerState {
+                volume,
+                duck_volume: config.duck_volume.clamp(0.0, 1.0) as f32,
+                max_concurrent: config.max_concurrent.max(1),
+                active: Vec::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, PlayerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Start `source` at `priority`, unless everything playing matters more.
+    pub fn play(
+        &self,
+        source: impl Source + Send + 'static,
+        priority: SoundPriority,
+    ) -> Option<Playback> {
+        let mut state = self.state();
+        state.prune();
+        if priority == SoundPriority::Safety {
+            for active in state.active.iter().filter(|a| a.priority == SoundPriority::Ambient) {
+                active.sink.stop();
+            }
+            state.active.retain(|a| a.priority != SoundPriority::Ambient);
+        }
+        let playing: Vec<SoundPriority> = state.active.iter().map(|a| a.priority).collect();
+        match make_room(&playing, priority, state.max_concurrent) {
+            Room::Free => {}
+            Room::Evict(i) => state.active.remove(i).sink.stop(),
+            Room::Full => {
+                tracing::debug!("{} sounds of higher priority playing", playing.len());
+                return None;
+            }
+        }
+
+        let sink = Arc::new(Sink::connect_new(&self.mixer));
+        state.active.push(Active {
+            sink: sink.clone(),
+            priority,
+            volume: 1.0,
+        });
+        state.apply_volumes();
         sink.append(source);
-        sink.detach(); // Play in background without blocking
+        Some(Playback {
+            sink,
+            player: self.clone(),
+            stopped: false,
+        })
+    }
 
-        Ok(())
+    fn set_volume(&self, volume: f32) {
+        let mut state = self.state();
+        state.volume = volume.clamp(0.0, 1.0);
+        state.apply_volumes();
+    }
+
+    fn stop_all(&self) {
+        for active in self.state().active.drain(..) {
+            active.sink.stop();
+        }
+    }
+
+    fn refresh(&self) {
+        let mut state = self.state();
+        let playing = state.active.len();
+        state.prune();
+        if state.active.len() < playing {
+            state.apply_volumes();
+        }
+    }
+}
+
+/// Control over one playing sound.
+pub struct Playback {
+    sink: Arc<Sink>,
+    player: Player,
+    stopped: bool,
+}
+
+impl Playback {
+    pub fn stop(&mut self) {
+        self.sink.stop();
+        self.stopped = true;
+        let mut state = self.player.state();
+        state.active.retain(|a| !Arc::ptr_eq(&a.sink, &self.sink));
+        state.apply_volumes();
+    }
+
+    /// The sound's own volume (0-1), under the master volume.
+    pub fn set_volume(&self, volume: f32) {
+        let mut state = self.player.state();
+        if let Some(active) = state.active.iter_mut().find(|a| Arc::ptr_eq(&a.sink, &self.sink)) {
+            active.volume = volume.clamp(0.0, 1.0);
+        }
+        state.apply_volumes();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.stopped && !self.sink.empty()
+    }
+
+    /// Block until the sound ends.
+    pub fn wait(&self) {
+        self.sink.sleep_until_end();
+    }
+}
+
+/// Where a new sound goes when the concurrency limit is reached.
+#[derive(Debug, PartialEq, Eq)]
+enum Room {
+    Free,
+    /// Stop the playing sound at this index first.
+    Evict(usize),
+    /// Everything playing matters more.
+    Full,
+}
+
+/// Room for a sound at `priority` among `playing` (oldest first): the
+/// oldest sound of the lowest priority not above the new one gives way.
+fn make_room(playing: &[SoundPriority], priority: SoundPriority, max: usize) -> Room {
+    if playing.len() < max {
+        return Room::Free;
+    }
+    let lowest = playing.iter().enumerate().filter(|(_, &p)| p <= priority).min_by_key(|(_, &p)| p);
+    match lowest {
+        Some((i, _)) => Room::Evict(i),
+        None => Room::Full,
+    }
+}
+
+/// Volume of each playing sound: its own times the master volume, ducked
+/// while a sound of higher priority plays.
+fn mixed_volumes(playing: &[(SoundPriority, f32)], master: f32, duck: f32) -> Vec<f32> {
+    let top = playing.iter().map(|&(p, _)| p).max();
+    playing
+        .iter()
+        .map(|&(p, volume)| {
+            let ducked = if Some(p) < top { duck } else { 1.0 };
+            volume * master * ducked
+        })
+        .collect()
+}
+
+/// Priority of the sound or speech played for `event`.
+pub fn event_priority(event: &Event) -> SoundPriority {
+    match event {
+        Event::LowBattery { .. } | Event::FallDetected { .. } => SoundPriority::Safety,
+        Event::SoundRequested { name: None } => SoundPriority::Ambient,
+        _ => SoundPriority::Normal,
+    }
+}
+
+/// The sound configured for `event`, if it has one.
+pub fn event_sound<'a>(config: &'a SoundEventsConfig, event: &Event) -> Option<&'a SoundChoice> {
+    match event {
+        Event::Started => config.startup.as_ref(),
+        Event::PausedToggled { paused: true } => config.pause.as_ref(),
+        Event::PausedToggled { paused: false } => config.unpause.as_ref(),
+        Event::FallDetected { .. } => config.fall_detected.as_ref(),
+        Event::LowBattery { .. } => config.low_battery.as_ref(),
+        Event::SoundRequested { name: None } => config.button_b.as_ref(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_sounds_from_config() {
+        let config: SoundEventsConfig = serde_json::from_str(
+            r#"{"startup": "hello.wav", "fall_detected": ["oof.wav", "ouch.wav"],
+                "button_b": null}"#,
+        )
+        .unwrap();
+        let file = |name: &str| Some(SoundChoice::File(name.to_string()));
+        assert_eq!(event_sound(&config, &Event::Started).cloned(), file("hello.wav"));
+        assert_eq!(
+            event_sound(&config, &Event::FallDetected { tilt: 1.0 }),
+            Some(&SoundChoice::Pool(vec!["oof.wav".to_string(), "ouch.wav".to_string()]))
+        );
+        assert_eq!(event_sound(&config, &Event::PausedToggled { paused: true }), None);
+        assert_eq!(event_sound(&config, &Event::SoundRequested { name: None }), None);
+        // Unset keys keep their defaults
+        let low = event_sound(&config, &Event::LowBattery { voltage: 6.4 }).cloned();
+        assert_eq!(low, file(crate::battery::LOW_BATTERY_SOUND));
+    }
+
+    #[test]
+    fn test_priorities_duck_and_make_room() {
+        use SoundPriority::*;
+        let volumes = mixed_volumes(&[(Ambient, 1.0), (Normal, 0.5), (Safety, 1.0)], 0.8, 0.25);
+        assert_eq!(volumes, vec![0.2, 0.1, 0.8]);
+        assert_eq!(mixed_volumes(&[(Normal, 1.0), (Normal, 1.0)], 1.0, 0.25), vec![1.0, 1.0]);
+
+        assert_eq!(make_room(&[Normal], Ambient, 2), Room::Free);
+        assert_eq!(make_room(&[Normal, Ambient, Ambient], Normal, 3), Room::Evict(1));
+        assert_eq!(make_room(&[Safety, Normal], Safety, 2), Room::Evict(1));
+        assert_eq!(make_room(&[Safety, Normal], Ambient, 2), Room::Full);
+    }
+
+    #[test]
+    fn test_categories_from_folders() {
+        let names = ["happy.wav", "quacks/a.mp3", "quacks/b.ogg", "quacks/loud/c.flac"]
+            .map(String::from);
+        let categories = categories(names.iter());
+        assert_eq!(categories.len(), 2);
+        let quacks = vec!["quacks/a.mp3", "quacks/b.ogg", "quacks/loud/c.flac"];
+        assert_eq!(categories["quacks"], quacks);
+        assert_eq!(categories["quacks/loud"], vec!["quacks/loud/c.flac"]);
+        assert!(is_sound_file(Path::new("x/QUACK.MP3")));
+        assert!(!is_sound_file(Path::new("notes.txt")));
     }
 }
@@ -0,0 +1,449 @@
+//! Telemetry log — per-tick signals written to disk without ever blocking
+//! the control loop.
+//!
+//! Samples go through a bounded queue to a writer thread. When the writer
+//! falls behind (an SD card stall), the oldest queued sample is dropped to
+//! make room for the newest, and every signal's sampling interval backs off
+//! (up to its own limit) until the queue drains again. Drops are counted per
+//! signal and written to the session summary, the last record of the file:
+//!
+//! ```text
+//! {"type":"meta","control_freq":50,"signals":["timing","joints","imu",...],...}
+//! {"type":"sample","signal":"imu","tick":0,"time":0.0,"values":[...]}
+//! {"type":"summary","signals":{"imu":{"written":9120,"dropped":14,"every":2},...}}
+//! ```
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Samples buffered between the control loop and the writer thread.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Flush the file every this many records, bounding data lost on power cut.
+const FLUSH_INTERVAL: usize = 200;
+
+/// Ticks between sampling-rate adjustments.
+const ADAPT_TICKS: u64 = 25;
+
+/// Queue fill above which sampling backs off, and below which it recovers.
+const HIGH_WATER: f64 = 0.5;
+const LOW_WATER: f64 = 0.1;
+
+/// A logged signal, each with its own sampling limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// [tick duration ms, budget overshoot ms]
+    Timing,
+    /// Present joint positions.
+    Joints,
+    /// [gyro x3, euler x3, projected gravity x3]
+    Imu,
+    /// [lin_vel_x, lin_vel_y, ang_vel, neck_pitch, head_pitch, head_yaw, head_roll]
+    Commands,
+    /// [warnings raised since the previous sample], only on ticks with some.
+    Warnings,
+    /// [foot (0 left, 1 right), 1 touchdown / 0 liftoff, event time s], one
+    /// sample per event.
+    ContactEvents,
+    /// [targets held back by the per-tick step limit, by the deviation
+    /// limit], only on ticks with some.
+    ActionClamps,
+    /// [p50, p95, p99, max ms of read, inference, write, total], one sample
+    /// per loop timing report.
+    LoopStats,
+}
+
+impl Signal {
+    pub const ALL: [Signal; 8] = [
+        Signal::Timing,
+        Signal::Joints,
+        Signal::Imu,
+        Signal::Commands,
+        Signal::Warnings,
+        Signal::ContactEvents,
+        Signal::ActionClamps,
+        Signal::LoopStats,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Timing => "timing",
+            Signal::Joints => "joints",
+            Signal::Imu => "imu",
+            Signal::Commands => "commands",
+            Signal::Warnings => "warnings",
+            Signal::ContactEvents => "contact_events",
+            Signal::ActionClamps => "action_clamps",
+            Signal::LoopStats => "loop_stats",
+        }
+    }
+
+    /// Sampling interval (ticks) when the writer keeps up.
+    fn base_every(self) -> u32 {
+        match self {
+            Signal::Timing
+            | Signal::Joints
+            | Signal::Imu
+            | Signal::Warnings
+            | Signal::ContactEvents
+            | Signal::ActionClamps
+            | Signal::LoopStats => 1,
+            Signal::Commands => 5,
+        }
+    }
+
+    /// Longest sampling interval under backpressure. Timing is tiny and is
+    /// what explains a stall, so it is never thinned, nor are the sparse
+    /// warning counts, contact events, action clamps and loop stats.
+    fn max_every(self) -> u32 {
+        match self {
+            Signal::Timing
+            | Signal::Warnings
+            | Signal::ContactEvents
+            | Signal::ActionClamps
+            | Signal::LoopStats => 1,
+            Signal::Joints | Signal::Imu => 8,
+            Signal::Commands => 50,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    Meta {
+        control_freq: u32,
+        signals: Vec<&'static str>,
+        started_at: f64,
+    },
+    Sample {
+        signal: &'static str,
+        tick: u64,
+        time: f64,
+        values: &'a [f64],
+    },
+    Summary {
+        signals: BTreeMap<&'static str, SignalSummary>,
+    },
+}
+
+#[derive(Debug)]
+struct Sample {
+    signal: Signal,
+    tick: u64,
+    time: f64,
+    values: Vec<f64>,
+}
+
+/// Per-signal outcome of a session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SignalSummary {
+    pub written: u64,
+    pub dropped: u64,
+    /// Sampling interval in effect when the session ended.
+    pub every: u32,
+}
+
+/// Counters shared with the writer thread.
+#[derive(Default)]
+struct Stats {
+    dropped: [AtomicU64; Signal::ALL.len()],
+    every: [AtomicU32; Signal::ALL.len()],
+}
+
+/// Per-signal sampling intervals that back off under queue pressure.
+#[derive(Debug)]
+struct Sampler {
+    every: [u32; Signal::ALL.len()],
+}
+
+impl Sampler {
+    fn new() -> Self {
+        Self {
+            every: Signal::ALL.map(Signal::base_every),
+        }
+    }
+
+    fn wants(&self, signal: Signal, tick: u64) -> bool {
+        tick.is_multiple_of(self.every[signal.index()] as u64)
+    }
+
+    /// Double every interval while the queue is filling, halve them once it
+    /// has drained. Returns whether anything changed.
+    fn adapt(&mut self, fill: f64) -> bool {
+        let before = self.every;
+        for signal in Signal::ALL {
+            let every = &mut self.every[signal.index()];
+            if fill > HIGH_WATER {
+                *every = (*every * 2).min(signal.max_every());
+            } else if fill < LOW_WATER {
+                *every = (*every / 2).max(signal.base_every());
+            }
+        }
+        self.every != before
+    }
+}
+
+/// Telemetry log with a background writer thread.
+pub struct TelemetryWriter {
+    sender: Option<Sender<Sample>>,
+    drain_rx: Receiver<Sample>,
+    sampler: Sampler,
+    last_adapt: Option<u64>,
+    stats: Arc<Stats>,
+    path: PathBuf,
+    writer: Option<thread::JoinHandle<BTreeMap<&'static str, SignalSummary>>>,
+}
+
+impl TelemetryWriter {
+    /// Create a new session file in `directory` and start the writer thread.
+    pub fn new(directory: &Path, control_freq: u32, started_at: f64) -> Result<Self> {
+        std::fs::create_dir_all(directory).with_context(|| {
+            format!("Failed to create telemetry directory {}", directory.display())
+        })?;
+
+        let path = directory.join(format!("telemetry-{}.jsonl", started_at as u64));
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create telemetry file {}", path.display()))?;
+
+        let mut out = BufWriter::new(file);
+        let meta = Record::Meta {
+            control_freq,
+            signals: Signal::ALL.map(Signal::name).to_vec(),
+            started_at,
+        };
+        serde_json::to_writer(&mut out, &meta)?;
+        out.write_all(b"\n")?;
+
+        let sampler = Sampler::new();
+        let stats = Arc::new(Stats::default());
+        for signal in Signal::ALL {
+            stats.every[signal.index()].store(sampler.every[signal.index()], Ordering::Relaxed);
+        }
+
+        let (tx, rx) = bounded::<Sample>(QUEUE_CAPACITY);
+        let drain_rx = rx.clone();
+        let worker_stats = Arc::clone(&stats);
+        let writer = thread::spawn(move || telemetry_worker(out, rx, worker_stats));
+
+        tracing::info!("Writing telemetry to {}", path.display());
+
+        Ok(Self {
+            sender: Some(tx),
+            drain_rx,
+            sampler,
+            last_adapt: None,
+            stats,
+            path,
+            writer: Some(writer),
+        })
+    }
+
+    /// Queue one sample of `signal` if it is due this tick. Never blocks: if
+    /// the writer is behind, the oldest queued sample makes room.
+    pub fn record(&mut self, signal: Signal, tick: u64, time: f64, values: &[f64]) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        let due_adapt = self.last_adapt.is_none_or(|last| tick >= last + ADAPT_TICKS);
+        if due_adapt {
+            self.last_adapt = Some(tick);
+            let fill = sender.len() as f64 / QUEUE_CAPACITY as f64;
+            if self.sampler.adapt(fill) {
+                tracing::debug!(
+                    "Telemetry queue {:.0}% full, sampling every {:?} ticks",
+                    fill * 100.0,
+                    self.sampler.every
+                );
+                for signal in Signal::ALL {
+                    self.stats.every[signal.index()]
+                        .store(self.sampler.every[signal.index()], Ordering::Relaxed);
+                }
+            }
+        }
+
+        if !self.sampler.wants(signal, tick) {
+            return;
+        }
+
+        let sample = Sample {
+            signal,
+            tick,
+            time,
+            values: values.to_vec(),
+        };
+        push_drop_oldest(sender, &self.drain_rx, sample, &self.stats);
+    }
+
+    /// Samples dropped so far, over all signals.
+    pub fn dropped(&self) -> u64 {
+        self.stats.dropped.iter().map(|d| d.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Path of the session file being written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TelemetryWriter {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer drain the queue, write the
+        // summary and exit
+        self.sender.take();
+        let Some(summary) = self.writer.take().and_then(|w| w.join().ok()) else {
+            return;
+        };
+        tracing::info!("Telemetry session closed: {}", self.path.display());
+        for (name, s) in &summary {
+            tracing::info!(
+                "Telemetry {}: {} written, {} dropped, sampling every {} ticks",
+                name,
+                s.written,
+                s.dropped,
+                s.every
+            );
+        }
+    }
+}
+
+/// Queue `sample`, evicting the oldest queued one if the queue is full.
+fn push_drop_oldest(
+    sender: &Sender<Sample>,
+    drain_rx: &Receiver<Sample>,
+    sample: Sample,
+    stats: &Stats,
+) {
+    match sender.try_send(sample) {
+        Ok(()) => {}
+        Err(TrySendError::Full(sample)) => {
+            if let Ok(oldest) = drain_rx.try_recv() {
+                stats.dropped[oldest.signal.index()].fetch_add(1, Ordering::Relaxed);
+            }
+            let signal = sample.signal;
+            if sender.try_send(sample).is_err() {
+                stats.dropped[signal.index()].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Err(TrySendError::Disconnected(_)) => {}
+    }
+}
+
+/// Background worker that serializes samples, then the session summary.
+fn telemetry_worker(
+    mut out: BufWriter<File>,
+    rx: Receiver<Sample>,
+    stats: Arc<Stats>,
+) -> BTreeMap<&'static str, SignalSummary> {
+    let mut written = [0u64; Signal::ALL.len()];
+    let mut since_flush = 0;
+    let mut failed = false;
+
+    for sample in rx {
+        if failed {
+            continue;
+        }
+        let record = Record::Sample {
+            signal: sample.signal.name(),
+            tick: sample.tick,
+            time: sample.time,
+            values: &sample.values,
+        };
+        let result = serde_json::to_writer(&mut out, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|_| out.write_all(b"\n"));
+        if let Err(e) = result {
+            tracing::error!("Telemetry write failed, stopping log: {}", e);
+            failed = true;
+            continue;
+        }
+        written[sample.signal.index()] += 1;
+
+        since_flush += 1;
+        if since_flush >= FLUSH_INTERVAL {
+            let _ = out.flush();
+            since_flush = 0;
+        }
+    }
+
+    let summary: BTreeMap<_, _> = Signal::ALL
+        .into_iter()
+        .map(|signal| {
+            let i = signal.index();
+            let s = SignalSummary {
+                written: written[i],
+                dropped: stats.dropped[i].load(Ordering::Relaxed),
+                every: stats.every[i].load(Ordering::Relaxed),
+            };
+            (signal.name(), s)
+        })
+        .collect();
+
+    if !failed {
+        let record = Record::Summary {
+            signals: summary.clone(),
+        };
+        let _ = serde_json::to_writer(&mut out, &record);
+        let _ = out.write_all(b"\n");
+    }
+    let _ = out.flush();
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_backs_off_and_recovers() {
+        let mut sampler = Sampler::new();
+        assert!(sampler.wants(Signal::Imu, 3));
+        assert!(!sampler.wants(Signal::Commands, 3));
+
+        for _ in 0..10 {
+            sampler.adapt(0.9);
+        }
+        assert_eq!(sampler.every[Signal::Timing.index()], 1);
+        assert_eq!(sampler.every[Signal::Imu.index()], 8);
+        assert_eq!(sampler.every[Signal::Commands.index()], 50);
+        assert!(!sampler.wants(Signal::Imu, 3));
+
+        assert!(!sampler.adapt(0.3));
+        for _ in 0..10 {
+            sampler.adapt(0.0);
+        }
+        assert_eq!(sampler.every, Sampler::new().every);
+    }
+
+    #[test]
+    fn test_full_queue_drops_oldest() {
+        let (tx, rx) = bounded::<Sample>(2);
+        let stats = Stats::default();
+        for tick in 0..3 {
+            let sample = Sample {
+                signal: if tick == 0 { Signal::Imu } else { Signal::Joints },
+                tick,
+                time: 0.0,
+                values: Vec::new(),
+            };
+            push_drop_oldest(&tx, &rx, sample, &stats);
+        }
+
+        assert_eq!(stats.dropped[Signal::Imu.index()].load(Ordering::Relaxed), 1);
+        let ticks: Vec<_> = rx.try_iter().map(|s| s.tick).collect();
+        assert_eq!(ticks, vec![1, 2]);
+    }
+}
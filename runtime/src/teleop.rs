@@ -0,0 +1,276 @@
+//! Gamepad forwarding over UDP.
+//!
+//! Operators often have the controller paired to their laptop rather than the
+//! Pi. `openduckrust gamepad` reads it there with the same `controller` input
+//! stack and sends one JSON `TeleopPacket` per poll to the robot, where
+//! `RemoteGamepad` turns them back into a `ControllerOutput` for the control
+//! loop. Button edges are detected on the robot side, so a lost packet never
+//! swallows a press, and a silent link releases everything after a timeout.
+//! Received commands are clamped by `CommandShaper::limit`, so a sender with
+//! different ranges can't drive the robot faster than a local gamepad.
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::controller::{CommandShaper, CommandSource, ControllerOutput};
+
+pub use crate::controller::PressedButtons;
+
+/// UDP port the runtime listens on by default.
+pub const DEFAULT_PORT: u16 = 9870;
+
+/// Without packets for this long, commands are zeroed and buttons released.
+const LINK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Largest packet accepted (a packet is ~300 bytes of JSON).
+const MAX_PACKET_SIZE: usize = 2048;
+
+/// One gamepad sample sent from the operator to the robot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeleopPacket {
+    /// Increases with every packet; older packets arriving late are dropped.
+    pub seq: u64,
+    /// [lin_vel_x, lin_vel_y, ang_vel, neck_pitch, head_pitch, head_yaw, head_roll]
+    pub commands: [f64; 7],
+    pub buttons: PressedButtons,
+    pub left_trigger: f64,
+    pub right_trigger: f64,
+}
+
+impl TeleopPacket {
+    pub fn from_output(seq: u64, output: &ControllerOutput) -> Self {
+        Self {
+            seq,
+            commands: output.commands,
+            buttons: output.buttons.pressed(),
+            left_trigger: output.left_trigger,
+            right_trigger: output.right_trigger,
+        }
+    }
+}
+
+/// Gamepad state received from the network.
+pub struct RemoteGamepad {
+    receiver: Receiver<TeleopPacket>,
+    stop_tx: Sender<()>,
+    state: RemoteState,
+}
+
+impl RemoteGamepad {
+    /// Bind the UDP socket and start the background receive thread.
+    pub fn bind(addr: &str) -> Result<Self> {
+        let socket =
+            UdpSocket::bind(addr).with_context(|| format!("Failed to bind teleop socket {}", addr))?;
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+
+        let (data_tx, data_rx) = bounded::<TeleopPacket>(1);
+        let (stop_tx, stop_rx) = bounded::<()>(1);
+        let drain_rx = data_rx.clone();
+
+        thread::spawn(move || {
+            teleop_worker(socket, data_tx, drain_rx, stop_rx);
+        });
+
+        tracing::info!("Listening for forwarded gamepad on udp://{}", addr);
+
+        Ok(Self {
+            receiver: data_rx,
+            stop_tx,
+            state: RemoteState::new(),
+        })
+    }
+
+    /// Signal the background thread to stop.
+    pub fn stop(&self) {
+        let _ = self.stop_tx.try_send(());
+    }
+}
+
+impl CommandSource for RemoteGamepad {
+    fn get_last_command(&mut self) -> &ControllerOutput {
+        let packet = self.receiver.try_recv().ok();
+        self.state.apply(packet, Instant::now());
+        &self.state.output
+    }
+}
+
+impl Drop for RemoteGamepad {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Reassembles `ControllerOutput` from packets, with edge detection and the
+/// link timeout.
+struct RemoteState {
+    output: ControllerOutput,
+    pressed: PressedButtons,
+    last_seq: Option<u64>,
+    last_packet: Option<Instant>,
+    connected: bool,
+    start: Instant,
+}
+
+impl RemoteState {
+    fn new() -> Self {
+        Self {
+            output: ControllerOutput::default(),
+            pressed: PressedButtons::default(),
+            last_seq: None,
+            last_packet: None,
+            connected: false,
+            start: Instant::now(),
+        }
+    }
+
+    fn apply(&mut self, packet: Option<TeleopPacket>, now: Instant) {
+        if let Some(packet) = packet.filter(|p| self.last_seq.is_none_or(|seq| p.seq > seq)) {
+            if !self.connected {
+                tracing::info!("Forwarded gamepad connected");
+                self.connected = true;
+            }
+            self.last_seq = Some(packet.seq);
+            self.last_packet = Some(now);
+            self.pressed = packet.buttons;
+            self.output.commands = packet.commands;
+            CommandShaper::limit(&mut self.output.commands);
+            self.output.left_trigger = packet.left_trigger;
+            self.output.right_trigger = packet.right_trigger;
+        }
+
+        let stale = self
+            .last_packet
+            .is_none_or(|t| now.saturating_duration_since(t) > LINK_TIMEOUT);
+        if stale {
+            if self.connected {
+                tracing::warn!("Forwarded gamepad silent for {:?}, stopping", LINK_TIMEOUT);
+                self.connected = false;
+                // The sender may have restarted with a fresh sequence
+                self.last_seq = None;
+            }
+            self.pressed = PressedButtons::default();
+            self.output.commands = [0.0; 7];
+            self.output.left_trigger = 0.0;
+            self.output.right_trigger = 0.0;
+        }
+
+        let t = now.saturating_duration_since(self.start).as_secs_f64();
+        self.output.buttons.update(&self.pressed, t);
+    }
+}
+
+/// Background worker that receives and parses teleop packets.
+fn teleop_worker(
+    socket: UdpSocket,
+    data_tx: Sender<TeleopPacket>,
+    drain_rx: Receiver<TeleopPacket>,
+    stop_rx: Receiver<()>,
+) {
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+
+    loop {
+        if stop_rx.try_recv().is_ok() {
+            break;
+        }
+
+        let (len, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(e) => {
+                tracing::trace!("Teleop receive error: {}", e);
+                continue;
+            }
+        };
+
+        let packet: TeleopPacket = match serde_json::from_slice(&buf[..len]) {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::trace!("Teleop: ignoring malformed packet from {}: {}", from, e);
+                continue;
+            }
+        };
+
+        match data_tx.try_send(packet) {
+            Ok(()) => {}
+            Err(TrySendError::Full(packet)) => {
+                let _ = drain_rx.try_recv();
+                let _ = data_tx.try_send(packet);
+            }
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+
+    tracing::info!("Teleop worker thread exiting");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(seq: u64, vx: f64, a: bool) -> Option<TeleopPacket> {
+        Some(TeleopPacket {
+            seq,
+            commands: [vx, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            buttons: PressedButtons {
+                a,
+                ..PressedButtons::default()
+            },
+            left_trigger: 0.0,
+            right_trigger: 0.0,
+        })
+    }
+
+    #[test]
+    fn test_packets_in_order_and_button_edges() {
+        let mut state = RemoteState::new();
+        let t0 = state.start + Duration::from_secs(1);
+
+        state.apply(packet(2, 0.1, true), t0);
+        assert_eq!(state.output.commands[0], 0.1);
+        assert!(state.output.buttons.a.triggered);
+
+        // Late packet is ignored; the held button does not re-trigger
+        state.apply(packet(1, 0.05, true), t0 + Duration::from_millis(20));
+        assert_eq!(state.output.commands[0], 0.1);
+        assert!(!state.output.buttons.a.triggered);
+        assert!(state.output.buttons.a.is_pressed);
+    }
+
+    #[test]
+    fn test_silent_link_stops_and_releases() {
+        let mut state = RemoteState::new();
+        let t0 = state.start + Duration::from_secs(1);
+        state.apply(packet(5, 0.1, true), t0);
+
+        state.apply(None, t0 + LINK_TIMEOUT + Duration::from_millis(1));
+        assert_eq!(state.output.commands, [0.0; 7]);
+        assert!(!state.output.buttons.a.is_pressed);
+
+        // A restarted sender counts from zero again
+        state.apply(packet(0, 0.08, false), t0 + Duration::from_secs(2));
+        assert_eq!(state.output.commands[0], 0.08);
+    }
+
+    #[test]
+    fn test_packet_roundtrip() {
+        let mut output = ControllerOutput::default();
+        output.commands[2] = -0.5;
+        output.buttons.start.is_pressed = true;
+        let json = serde_json::to_vec(&TeleopPacket::from_output(7, &output)).unwrap();
+        let parsed: TeleopPacket = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed.seq, 7);
+        assert_eq!(parsed.commands[2], -0.5);
+        assert!(parsed.buttons.start);
+    }
+}
@@ -0,0 +1,121 @@
+//! Thermal throttling of the gait from servo temperatures.
+//!
+//! Sustained walking heats the hip servos faster than they can shed it.
+//! Once the hottest servo passes the warm threshold, the gait is slowed:
+//! the commanded velocity range and the phase frequency shrink linearly
+//! from full at the warm threshold to `min_scale` at the hot threshold.
+//! At the hot threshold the duck pauses, and it resumes on its own once
+//! every servo has cooled below the resume temperature.
+//!
+//! Unlike duty cycling, which rests on a schedule set by the average
+//! temperature, this reacts to the single hottest servo and keeps the duck
+//! walking for as long as it safely can.
+
+use crate::config::ThermalConfig;
+
+/// Pause state change requested by the governor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermalEvent {
+    /// A servo reached the hot threshold: pause.
+    Overheated,
+    /// Every servo cooled below the resume temperature: resume.
+    Recovered,
+}
+
+/// Throttle and pause state driven by the hottest servo.
+pub struct ThermalGovernor {
+    config: ThermalConfig,
+    scale: f64,
+    hot: bool,
+}
+
+impl ThermalGovernor {
+    pub fn new(config: ThermalConfig) -> Self {
+        tracing::info!(
+            "Thermal throttling above {:.0}°C, pause at {:.0}°C, resume below {:.0}°C",
+            config.warm_temperature,
+            config.hot_temperature,
+            config.resume_temperature
+        );
+        Self {
+            config,
+            scale: 1.0,
+            hot: false,
+        }
+    }
+
+    /// Feed fresh temperature readings; returns a pause change if one is due.
+    pub fn update(&mut self, temperatures: &[f64]) -> Option<ThermalEvent> {
+        let hottest = temperatures.iter().copied().reduce(f64::max)?;
+        let c = &self.config;
+
+        let heat = (hottest - c.warm_temperature) / (c.hot_temperature - c.warm_temperature);
+        let scale = 1.0 - heat.clamp(0.0, 1.0) * (1.0 - c.min_scale.clamp(0.0, 1.0));
+        if (scale < 1.0) != (self.scale < 1.0) {
+            if scale < 1.0 {
+                tracing::warn!("Hottest servo at {:.0}°C, throttling the gait", hottest);
+            } else {
+                tracing::info!("Hottest servo at {:.0}°C, gait back to full speed", hottest);
+            }
+        }
+        self.scale = scale;
+
+        if !self.hot && hottest >= c.hot_temperature {
+            tracing::warn!("Hottest servo at {:.0}°C, pausing to cool down", hottest);
+            self.hot = true;
+            return Some(ThermalEvent::Overheated);
+        }
+        if self.hot && hottest < c.resume_temperature {
+            tracing::info!("Hottest servo cooled to {:.0}°C, resuming", hottest);
+            self.hot = false;
+            return Some(ThermalEvent::Recovered);
+        }
+        None
+    }
+
+    /// Factor (`min_scale`..1) applied to the velocity range and phase
+    /// frequency.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Whether a servo is too hot to walk.
+    pub fn is_hot(&self) -> bool {
+        self.hot
+    }
+
+    /// Clamp velocity commands to the throttled share of `ranges`.
+    pub fn limit_velocity(&self, commands: &mut [f64], ranges: &[[f64; 2]]) {
+        for (c, range) in commands.iter_mut().zip(ranges) {
+            *c = c.clamp(range[0] * self.scale, range[1] * self.scale);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttles_pauses_and_recovers() {
+        let mut thermal = ThermalGovernor::new(ThermalConfig::default());
+
+        assert_eq!(thermal.update(&[40.0, 50.0]), None);
+        assert_eq!(thermal.scale(), 1.0);
+
+        // Halfway between warm (55) and hot (65)
+        assert_eq!(thermal.update(&[40.0, 60.0]), None);
+        assert!((thermal.scale() - 0.75).abs() < 1e-9);
+        let mut commands = [0.15, -0.05, 1.0];
+        thermal.limit_velocity(&mut commands, &[[-0.15, 0.15], [-0.2, 0.2], [-1.0, 1.0]]);
+        assert!((commands[0] - 0.1125).abs() < 1e-9);
+        assert_eq!(commands[1], -0.05);
+        assert!((commands[2] - 0.75).abs() < 1e-9);
+
+        assert_eq!(thermal.update(&[66.0]), Some(ThermalEvent::Overheated));
+        assert!(thermal.is_hot());
+        assert_eq!(thermal.update(&[58.0]), None);
+        assert_eq!(thermal.update(&[54.0]), Some(ThermalEvent::Recovered));
+        assert!(!thermal.is_hot());
+    }
+}
@@ -0,0 +1,254 @@
+//! Text-to-speech with an offline piper voice, for announcing state
+//! ("battery low", "paused") without recorded sounds.
+//!
+//! A piper voice is a VITS model exported to ONNX plus a `.onnx.json`
+//! describing it: the sample rate, the espeak-ng voice, the default noise
+//! and length scales, and the id of every phoneme. Text is turned into IPA
+//! phonemes by the `espeak-ng` executable (voices with `"phoneme_type":
+//! "text"` skip it and read the characters directly), the phonemes into ids
+//! with a pad after each, and the model returns the waveform. Synthesis
+//! takes a good fraction of a second on a Pi, so `Speaker` does it on its
+//! own thread and plays each utterance in turn.
+
+use anyhow::{bail, Context, Result};
+use crossbeam_channel::{bounded, Sender, TrySendError};
+use ndarray::{Array1, Array2};
+use ort::session::Session;
+use ort::value::Tensor;
+use rodio::buffer::SamplesBuffer;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+
+use crate::config::SpeechConfig;
+use crate::sounds::{Player, SoundPriority};
+
+/// Utterances waiting to be spoken before new ones are dropped.
+const QUEUE: usize = 4;
+
+/// Marks the piper phoneme id map uses.
+const BOS: &str = "^";
+const EOS: &str = "$";
+const PAD: &str = "_";
+
+/// The parts of a piper `.onnx.json` synthesis needs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VoiceConfig {
+    pub audio: AudioConfig,
+    #[serde(default)]
+    pub espeak: Option<EspeakConfig>,
+    #[serde(default)]
+    pub inference: InferenceConfig,
+    #[serde(default)]
+    pub phoneme_type: Option<String>,
+    pub phoneme_id_map: HashMap<String, Vec<i64>>,
+    #[serde(default)]
+    pub num_speakers: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AudioConfig {
+    pub sample_rate: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EspeakConfig {
+    pub voice: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InferenceConfig {
+    pub noise_scale: f32,
+    pub length_scale: f32,
+    pub noise_w: f32,
+}
+
+impl Default for InferenceConfig {
+    fn default() -> Self {
+        Self {
+            noise_scale: 0.667,
+            length_scale: 1.0,
+            noise_w: 0.8,
+        }
+    }
+}
+
+impl VoiceConfig {
+    /// Model input ids for a phoneme string: each known phoneme followed by
+    /// a pad, between the sentence start and end marks.
+    pub fn phoneme_ids(&self, phonemes: &str) -> Vec<i64> {
+        let ids = |mark: &str| self.phoneme_id_map.get(mark).map_or(&[][..], Vec::as_slice);
+        let mut out = ids(BOS).to_vec();
+        out.extend_from_slice(ids(PAD));
+        let mut buf = [0u8; 4];
+        for phoneme in phonemes.chars() {
+            if let Some(id) = self.phoneme_id_map.get(phoneme.encode_utf8(&mut buf) as &str) {
+                out.extend_from_slice(id);
+                out.extend_from_slice(ids(PAD));
+            }
+        }
+        out.extend_from_slice(ids(EOS));
+        out
+    }
+
+    fn reads_text(&self) -> bool {
+        self.phoneme_type.as_deref() == Some("text")
+    }
+}
+
+/// A loaded piper voice.
+pub struct Voice {
+    session: Session,
+    config: VoiceConfig,
+    speaker: i64,
+    length_scale: f32,
+    espeak: String,
+}
+
+impl Voice {
+    pub fn load(model: &Path, config: &SpeechConfig) -> Result<Self> {
+        let config_path = format!("{}.json", model.display());
+        let text = std::fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read voice config {}", config_path))?;
+        let voice: VoiceConfig = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse voice config {}", config_path))?;
+        if voice.num_speakers > 1 && !(0..voice.num_speakers as i64).contains(&config.speaker) {
+            bail!("Voice has {} speakers, not speaker {}", voice.num_speakers, config.speaker);
+        }
+        if !(config.speed.is_finite() && config.speed > 0.0) {
+            bail!("Speech speed must be positive, got {}", config.speed);
+        }
+
+        let session = Session::builder()
+            .context("Failed to create ONNX session builder")?
+            .commit_from_file(model)
+            .with_context(|| format!("Failed to load voice {}", model.display()))?;
+        tracing::info!(
+            "Loaded voice {} ({} Hz)",
+            model.display(),
+            voice.audio.sample_rate
+        );
+        Ok(Self {
+            session,
+            length_scale: voice.inference.length_scale / config.speed as f32,
+            config: voice,
+            speaker: config.speaker,
+            espeak: config.espeak.clone(),
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.config.audio.sample_rate
+    }
+
+    /// Phonemes of `text`, from espeak-ng unless the voice reads text.
+    fn phonemize(&self, text: &str) -> Result<String> {
+        if self.config.reads_text() {
+            return Ok(text.to_lowercase());
+        }
+        let language = self.config.espeak.as_ref().map_or("en-us", |e| e.voice.as_str());
+        let output = Command::new(&self.espeak)
+            .args(["-q", "--ipa", "-v", language, "--", text])
+            .output()
+            .with_context(|| format!("Failed to run {}", self.espeak))?;
+        if !output.status.success() {
+            bail!("{} failed: {}", self.espeak, String::from_utf8_lossy(&output.stderr).trim());
+        }
+        // One line per clause
+        let ipa = String::from_utf8_lossy(&output.stdout);
+        Ok(ipa.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+
+    /// Mono audio (-1..1) of `text` at `sample_rate`.
+    pub fn synthesize(&mut self, text: &str) -> Result<Vec<f32>> {
+        let ids = self.config.phoneme_ids(&self.phonemize(text)?);
+        let inference = &self.config.inference;
+        let scales = [inference.noise_scale, self.length_scale, inference.noise_w];
+        let length = ids.len() as i64;
+
+        let input = Tensor::from_array(Array2::from_shape_vec((1, ids.len()), ids)?)?;
+        let lengths = Tensor::from_array(Array1::from_vec(vec![length]))?;
+        let scales = Tensor::from_array(Array1::from_vec(scales.to_vec()))?;
+        let outputs = if self.config.num_speakers > 1 {
+            let sid = Tensor::from_array(Array1::from_vec(vec![self.speaker]))?;
+            self.session.run(ort::inputs![
+                "input" => input,
+                "input_lengths" => lengths,
+                "scales" => scales,
+                "sid" => sid,
+            ])
+        } else {
+            self.session.run(ort::inputs![
+                "input" => input,
+                "input_lengths" => lengths,
+                "scales" => scales,
+            ])
+        }
+        .context("Speech synthesis failed")?;
+
+        let (_, audio) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .context("Failed to extract synthesized audio")?;
+        Ok(audio.iter().map(|s| s.clamp(-1.0, 1.0)).collect())
+    }
+}
+
+/// Speaks queued text on its own thread, one utterance at a time.
+pub struct Speaker {
+    texts: Sender<(String, SoundPriority)>,
+}
+
+impl Speaker {
+    pub fn start(mut voice: Voice, player: Player) -> Self {
+        let (texts, rx) = bounded::<(String, SoundPriority)>(QUEUE);
+        thread::spawn(move || {
+            for (text, priority) in rx {
+                let audio = match voice.synthesize(&text) {
+                    Ok(audio) => audio,
+                    Err(e) => {
+                        tracing::warn!("Failed to say \"{}\": {:#}", text, e);
+                        continue;
+                    }
+                };
+                let audio = SamplesBuffer::new(1, voice.sample_rate(), audio);
+                if let Some(playback) = player.play(audio, priority) {
+                    playback.wait();
+                }
+            }
+        });
+        Self { texts }
+    }
+
+    /// Queue `text`; dropped if the speaker is too far behind.
+    pub fn say(&self, text: &str, priority: SoundPriority) {
+        match self.texts.try_send((text.to_string(), priority)) {
+            Ok(()) => tracing::info!("Saying \"{}\"", text),
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!("Speech queue full, not saying \"{}\"", text)
+            }
+            Err(TrySendError::Disconnected(_)) => tracing::warn!("Speech thread stopped"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phoneme_ids_padded_between_marks() {
+        let config: VoiceConfig = serde_json::from_str(
+            r#"{
+                "audio": {"sample_rate": 22050},
+                "phoneme_id_map": {"_": [0], "^": [1], "$": [2], "h": [20], "ə": [59], " ": [3]}
+            }"#,
+        )
+        .unwrap();
+        // Unknown phonemes ("ˈ") are skipped
+        assert_eq!(config.phoneme_ids("hˈə h"), vec![1, 0, 20, 0, 59, 0, 3, 0, 20, 0, 2]);
+        assert_eq!(config.inference.noise_w, 0.8);
+        assert!(!config.reads_text());
+    }
+}
@@ -0,0 +1,189 @@
+//! Control-loop watchdog.
+//!
+//! Servos hold their last goal position forever, so a control loop that
+//! deadlocks leaves the robot stiffly frozen mid-step. The loop feeds the
+//! watchdog every tick, and the first feed arms it; once armed, if it goes
+//! unfed for longer than the timeout, the watchdog thread logs the stall and
+//! broadcasts a torque-disable on its own handle to the servo bus. The loop
+//! notices the trip on its next tick and stays paused until the operator
+//! unpauses.
+//!
+//! Only the loop itself stalling counts. Ticks skipped because the servo bus
+//! or the IMU stopped answering still feed the watchdog; those failures go
+//! through the bus error escalation and the IMU staleness check, which
+//! reopen the port or soft-stop instead of cutting torque under a walking
+//! robot.
+//!
+//! A thread cannot act while the whole process is stopped (SIGSTOP); the
+//! watchdog then fires as soon as the process is continued.
+
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::motors::TorqueKillSwitch;
+
+struct Shared {
+    epoch: Instant,
+    /// Time of the last feed, in microseconds since `epoch`.
+    last_feed: AtomicU64,
+    armed: AtomicBool,
+    tripped: AtomicBool,
+}
+
+impl Shared {
+    fn now_us(&self) -> u64 {
+        self.epoch.elapsed().as_micros() as u64
+    }
+
+    fn feed(&self) {
+        self.last_feed.store(self.now_us(), Ordering::Release);
+    }
+
+    /// Time since the last feed, or `None` while disarmed or already tripped.
+    fn stalled_for(&self) -> Option<Duration> {
+        if !self.armed.load(Ordering::Acquire) || self.tripped.load(Ordering::Acquire) {
+            return None;
+        }
+        let since = self.now_us().saturating_sub(self.last_feed.load(Ordering::Acquire));
+        Some(Duration::from_micros(since))
+    }
+}
+
+/// Watchdog thread that disables torque when the control loop stalls.
+pub struct Watchdog {
+    shared: Arc<Shared>,
+    stop_tx: Sender<()>,
+}
+
+impl Watchdog {
+    /// Start watching. Stays disarmed until the first `feed`, so setup
+    /// between here and the first control tick is never a stall. Without a
+    /// kill switch (simulated servos), a stall is only logged.
+    pub fn start(timeout: Duration, kill_switch: Option<TorqueKillSwitch>) -> Self {
+        let shared = Arc::new(Shared {
+            epoch: Instant::now(),
+            last_feed: AtomicU64::new(0),
+            armed: AtomicBool::new(false),
+            tripped: AtomicBool::new(false),
+        });
+        let (stop_tx, stop_rx) = bounded::<()>(1);
+
+        let worker_shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            watchdog_worker(worker_shared, timeout, kill_switch, stop_rx);
+        });
+
+        tracing::info!("Watchdog started with a {:.0}ms timeout", timeout.as_secs_f64() * 1000.0);
+
+        Self { shared, stop_tx }
+    }
+
+    /// Signal that the control loop is alive, arming the watchdog on the
+    /// first call.
+    pub fn feed(&self) {
+        self.shared.feed();
+        self.shared.armed.store(true, Ordering::Release);
+    }
+
+    /// Run a long blocking operation (e.g. the torque-on ramp) without
+    /// tripping the watchdog.
+    pub fn disarmed<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.shared.armed.store(false, Ordering::Release);
+        let result = f();
+        self.shared.feed();
+        self.shared.armed.store(true, Ordering::Release);
+        result
+    }
+
+    /// Whether the watchdog has fired since the last call. Clears the trip,
+    /// re-arming it for the next stall.
+    pub fn take_trip(&self) -> bool {
+        let tripped = self.shared.tripped.swap(false, Ordering::AcqRel);
+        if tripped {
+            self.shared.feed();
+        }
+        tripped
+    }
+
+    /// Signal the background thread to stop.
+    pub fn stop(&self) {
+        let _ = self.stop_tx.try_send(());
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Background worker that checks the feed time several times per timeout.
+fn watchdog_worker(
+    shared: Arc<Shared>,
+    timeout: Duration,
+    mut kill_switch: Option<TorqueKillSwitch>,
+    stop_rx: Receiver<()>,
+) {
+    let check_interval = (timeout / 4).max(Duration::from_millis(1));
+
+    while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(check_interval) {
+        let Some(stalled) = shared.stalled_for().filter(|&s| s > timeout) else {
+            continue;
+        };
+        shared.tripped.store(true, Ordering::Release);
+
+        tracing::error!(
+            "Watchdog: control loop stalled for {:.0}ms, disabling torque",
+            stalled.as_secs_f64() * 1000.0
+        );
+        if let Some(ref mut kill) = kill_switch {
+            if let Err(e) = kill.disable_all() {
+                tracing::error!("Watchdog: torque disable failed: {}", e);
+            }
+        }
+    }
+
+    tracing::info!("Watchdog thread exiting");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trips_when_not_fed() {
+        let watchdog = Watchdog::start(Duration::from_millis(100), None);
+        for _ in 0..10 {
+            watchdog.feed();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(!watchdog.take_trip());
+
+        thread::sleep(Duration::from_millis(300));
+        assert!(watchdog.take_trip());
+        // Cleared once taken
+        assert!(!watchdog.take_trip());
+    }
+
+    #[test]
+    fn test_setup_before_first_feed_does_not_trip() {
+        let watchdog = Watchdog::start(Duration::from_millis(10), None);
+        // Slow setup between start and the first control tick
+        thread::sleep(Duration::from_millis(60));
+        assert!(!watchdog.take_trip());
+
+        watchdog.feed();
+        thread::sleep(Duration::from_millis(60));
+        assert!(watchdog.take_trip());
+    }
+
+    #[test]
+    fn test_disarmed_section_does_not_trip() {
+        let watchdog = Watchdog::start(Duration::from_millis(10), None);
+        watchdog.disarmed(|| thread::sleep(Duration::from_millis(50)));
+        assert!(!watchdog.take_trip());
+    }
+}
@@ -0,0 +1,102 @@
+//! WS2812 (NeoPixel) framing over SPI, and eye rings drawn from expressions.
+//!
+//! The Pi has no WS2812 peripheral, but its SPI MOSI line can produce the
+//! timing: clocked at 2.4 MHz, each WS2812 bit becomes three SPI bits, `110`
+//! for a one and `100` for a zero, and a run of zero bytes latches the
+//! frame. Pixels are sent green, red, blue. Eye rings show the same
+//! expressions as single LEDs (`eye_expression`), with shapes and colors
+//! single LEDs can't do. Only the framing lives here; the SPI bus is driven
+//! by the `peripherals` module.
+
+use crate::eye_expression::EyeExpression;
+
+pub const SPI_CLOCK_HZ: u32 = 2_400_000;
+
+/// Low time that latches a frame: 32 bytes at 2.4 MHz is about 107 µs.
+const RESET_BYTES: usize = 32;
+
+const HEART_COLOR: [u8; 3] = [255, 20, 100];
+const ONLINE_COLOR: [u8; 3] = [0, 255, 0];
+const OFFLINE_COLOR: [u8; 3] = [255, 120, 0];
+const LOW_BATTERY_COLOR: [u8; 3] = [255, 0, 0];
+
+/// SPI bytes for a chain of RGB pixels, reset included.
+pub fn encode(pixels: &[[u8; 3]]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(pixels.len() * 9 + RESET_BYTES);
+    for &[r, g, b] in pixels {
+        for byte in [g, r, b] {
+            let mut bits = 0u32;
+            for i in (0..8).rev() {
+                bits = (bits << 3) | if (byte >> i) & 1 == 1 { 0b110 } else { 0b100 };
+            }
+            frame.extend_from_slice(&bits.to_be_bytes()[1..]);
+        }
+    }
+    frame.resize(frame.len() + RESET_BYTES, 0);
+    frame
+}
+
+pub fn scale(color: [u8; 3], level: f64) -> [u8; 3] {
+    color.map(|c| (f64::from(c) * level.clamp(0.0, 1.0)).round() as u8)
+}
+
+/// One eye ring of `pixels` LEDs, pixel 0 at the top going clockwise.
+pub fn ring(
+    expression: EyeExpression,
+    level: f64,
+    color: [u8; 3],
+    pixels: usize,
+) -> Vec<[u8; 3]> {
+    let color = match expression {
+        EyeExpression::HeartEyes => HEART_COLOR,
+        _ => color,
+    };
+    let lit = scale(color, level);
+    (0..pixels)
+        .map(|i| {
+            // Sleepy eyes are half closed: only the lower half is lit
+            let covered = expression == EyeExpression::Sleepy
+                && (i < pixels / 4 || i >= pixels - pixels / 4);
+            if covered {
+                [0, 0, 0]
+            } else {
+                lit
+            }
+        })
+        .collect()
+}
+
+/// Status pixel color: red on low battery, amber while the backend is
+/// unreachable, green otherwise.
+pub fn status_color(offline: bool, low_battery: bool) -> [u8; 3] {
+    if low_battery {
+        LOW_BATTERY_COLOR
+    } else if offline {
+        OFFLINE_COLOR
+    } else {
+        ONLINE_COLOR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodes_grb_bits_and_sleepy_ring() {
+        let frame = encode(&[[0xFF, 0x00, 0x80]]);
+        assert_eq!(frame.len(), 9 + RESET_BYTES);
+        // Green 0x00: eight `100`s
+        assert_eq!(frame[..3], [0x92, 0x49, 0x24]);
+        // Red 0xFF: eight `110`s
+        assert_eq!(frame[3..6], [0xDB, 0x6D, 0xB6]);
+        // Blue 0x80: `110` then seven `100`s
+        assert_eq!(frame[6..9], [0xD2, 0x49, 0x24]);
+        assert!(frame[9..].iter().all(|&b| b == 0));
+
+        let sleepy = ring(EyeExpression::Sleepy, 0.5, [200, 100, 0], 8);
+        let lit: Vec<bool> = sleepy.iter().map(|p| p != &[0, 0, 0]).collect();
+        assert_eq!(lit, [false, false, true, true, true, true, false, false]);
+        assert_eq!(sleepy[4], [100, 50, 0]);
+    }
+}